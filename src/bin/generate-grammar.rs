@@ -0,0 +1,13 @@
+//! Regenerates the committed parser, run it with `cargo regenerate-grammar`.
+
+use std::path::Path;
+
+use anyhow::Result;
+use sweet::generate::{generated_parser, GENERATED_PATH};
+
+fn main() -> Result<()> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(GENERATED_PATH);
+    std::fs::write(&path, generated_parser())?;
+    println!("wrote {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,13 @@
+//! Regenerates the committed key table, run it with `cargo regenerate-keys`.
+
+use std::path::Path;
+
+use anyhow::Result;
+use sweet::generate::{generated_key_table, GENERATED_KEYS_PATH};
+
+fn main() -> Result<()> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(GENERATED_KEYS_PATH);
+    std::fs::write(&path, generated_key_table())?;
+    println!("wrote {}", path.display());
+    Ok(())
+}
@@ -1,42 +1,707 @@
-use std::fmt::Display;
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    hash::{Hash, Hasher},
+    ops::Range,
+    sync::Arc,
+};
 
-use crate::{Definition, ModeInstruction};
+use itertools::Itertools;
 
-#[derive(Debug, PartialEq, Eq)]
+use crate::{expand::Product, Definition, ModeInstruction, Schedule, Settings, SourceSpan};
+
+/// Bindings compare equal if they do the same thing, regardless of how
+/// they were written. Hashing and ordering agree: bindings are ordered by
+/// their chords first, then by their command.
+#[derive(Debug, Clone, Eq)]
 pub struct Binding {
-    pub definition: Definition,
-    pub command: String,
-    pub mode_instructions: Vec<ModeInstruction>,
+    /// The chords pressed one after another, see [`Binding::sequence`].
+    /// Never empty.
+    sequence: Vec<Definition>,
+    command: String,
+    mode_instructions: Vec<ModeInstruction>,
+    /// How many of `mode_instructions` take effect before the command.
+    before_command: usize,
+    schedule: Option<Schedule>,
+    device: Option<String>,
+    settings: Settings,
+    /// Recorded when the config is resolved, see [`Binding::mode`].
+    mode: Option<String>,
+    /// Shared by every binding expanded from a declaration, like
+    /// `source_text`.
+    description: Option<Arc<str>>,
+    source_text: Arc<str>,
+    span: Option<SourceSpan>,
+}
+
+/// See [`Binding::identity`].
+type Identity<'a> = (
+    &'a [Definition],
+    &'a str,
+    &'a [ModeInstruction],
+    usize,
+    Option<&'a Schedule>,
+    Option<&'a str>,
+    &'a Settings,
+);
+
+impl PartialEq for Binding {
+    fn eq(&self, other: &Self) -> bool {
+        self.identity() == other.identity()
+    }
+}
+
+impl Hash for Binding {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.identity().hash(state);
+    }
+}
+
+impl PartialOrd for Binding {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Binding {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.identity().cmp(&other.identity())
+    }
 }
 
 impl Binding {
+    /// Starts building a binding, mostly useful to compare against parsed
+    /// bindings.
+    ///
+    /// ```
+    /// use sweet::{Binding, Definition, Modifier, ParserInput, SwhkdParser};
+    ///
+    /// let expected = Binding::running("firefox")
+    ///     .on(Definition::new(evdev::Key::KEY_W).with_modifiers(&[Modifier::Super]));
+    /// let parsed = SwhkdParser::from(ParserInput::Raw("super + w\n    firefox"))?;
+    /// assert_eq!(parsed.bindings, [expected]);
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
     pub fn running<S: AsRef<str>>(command: S) -> BindingBuilder {
         BindingBuilder {
             command: command.as_ref().to_string(),
+            mode_instructions: vec![],
+        }
+    }
+
+    /// Appends a chord pressed after the ones of the binding, see
+    /// [`Binding::sequence`].
+    ///
+    /// ```
+    /// use sweet::{Binding, Definition, Modifier, ParserInput, SwhkdParser};
+    ///
+    /// let expected = Binding::running("firefox")
+    ///     .on(Definition::new(evdev::Key::KEY_A).with_modifiers(&[Modifier::Super]))
+    ///     .then(Definition::new(evdev::Key::KEY_W));
+    /// let parsed = SwhkdParser::from(ParserInput::Raw("super + a ; w\n    firefox"))?;
+    /// assert_eq!(parsed.bindings, [expected]);
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn then(mut self, definition: Definition) -> Self {
+        self.sequence.push(definition);
+        self
+    }
+
+    /// Sets the modes entered or left after running the command.
+    pub fn with_mode_instructions(mut self, mode_instructions: Vec<ModeInstruction>) -> Self {
+        self.mode_instructions.truncate(self.before_command);
+        self.mode_instructions.extend(mode_instructions);
+        self
+    }
+
+    /// Sets the modes entered or left before running the command, like
+    /// `@enter resize && notify-send resizing` in a config.
+    pub fn with_mode_instructions_before_command(
+        mut self,
+        mode_instructions: Vec<ModeInstruction>,
+    ) -> Self {
+        let after = self.mode_instructions.split_off(self.before_command);
+        self.before_command = mode_instructions.len();
+        self.mode_instructions = mode_instructions;
+        self.mode_instructions.extend(after);
+        self
+    }
+
+    /// Limits when the binding is active, see [`Binding::schedule`].
+    pub fn with_schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = Some(schedule);
+        self
+    }
+
+    /// When the binding is active, as given by a `[when=...]` tag. `None`
+    /// for bindings that are always active.
+    pub fn schedule(&self) -> Option<&Schedule> {
+        self.schedule.as_ref()
+    }
+
+    /// Limits the binding to one input device, see [`Binding::device`].
+    pub fn with_device<S: Into<String>>(mut self, device: S) -> Self {
+        self.device = Some(device.into());
+        self
+    }
+
+    /// The input device the binding applies to, as given by a
+    /// `[device=...]` tag. `None` for bindings applying to every device.
+    ///
+    /// Bindings on the same chord for different devices are distinct, a
+    /// later one does not override an earlier one.
+    ///
+    /// ```
+    /// use sweet::{ParserInput, SwhkdParser};
+    ///
+    /// let config = "
+    /// [device=Keychron K6] super + a
+    ///     keyboard
+    /// [device=macro-pad] super + a
+    ///     macro
+    /// ";
+    /// let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    /// assert_eq!(parsed.bindings.len(), 2);
+    /// assert_eq!(parsed.bindings[0].device(), Some("Keychron K6"));
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn device(&self) -> Option<&str> {
+        self.device.as_deref()
+    }
+
+    /// Sets the binding's own settings, see [`Binding::settings`].
+    pub fn with_settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// The settings of this binding alone, as given by `[set NAME=VALUE]`
+    /// tags. They take precedence over every other scope, see
+    /// [`SwhkdParser::effective_settings`](crate::SwhkdParser::effective_settings).
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// The name of the mode the binding belongs to, `None` for top-level
+    /// bindings. It is recorded on the bindings of every mode once the
+    /// config is resolved, and kept by clones and serde.
+    ///
+    /// ```
+    /// use sweet::{ParserInput, SwhkdParser};
+    ///
+    /// let config = "mode resize\nh\n    shrink\nendmode";
+    /// let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    /// assert_eq!(parsed.modes[0].bindings[0].mode(), Some("resize"));
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn mode(&self) -> Option<&str> {
+        self.mode.as_deref()
+    }
+
+    /// Records the mode the binding belongs to, see [`Binding::mode`].
+    pub(crate) fn record_mode(&mut self, mode: Option<&str>) {
+        self.mode = mode.map(str::to_string);
+    }
+
+    /// Whether `other` fires on the same chord of the same devices, so that
+    /// one of them overrides the other.
+    pub(crate) fn same_trigger(&self, other: &Binding) -> bool {
+        self.sequence == other.sequence && self.device == other.device
+    }
+
+    /// Whether `other` is a sequence whose chords before the last one are
+    /// the same as in this binding, so that their last chords fire in the
+    /// same situation.
+    pub(crate) fn same_prefix(&self, other: &Binding) -> bool {
+        let (prefix, _) = self.sequence.split_at(self.sequence.len() - 1);
+        let (other_prefix, _) = other.sequence.split_at(other.sequence.len() - 1);
+        prefix == other_prefix
+    }
+
+    /// Records where a parsed binding was written.
+    pub(crate) fn written_at(mut self, source_text: Arc<str>, span: Option<SourceSpan>) -> Self {
+        self.source_text = source_text;
+        self.span = span;
+        self
+    }
+
+    /// Describes the binding, see [`Binding::description`].
+    pub fn with_description<S: Into<Arc<str>>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// What the binding does, as told by the comment lines right above it
+    /// in the config without their `#`, e.g. for a cheat sheet. Lines of a
+    /// longer comment are joined by newlines. A blank line between the
+    /// comment and the binding leaves the binding without a description.
+    ///
+    /// ```
+    /// use sweet::{ParserInput, SwhkdParser};
+    ///
+    /// let config = "
+    /// ## Open a terminal
+    /// super + return
+    ///     alacritty
+    /// ";
+    /// let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    /// assert_eq!(parsed.bindings[0].description(), Some("Open a terminal"));
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// The chord of the binding, the first one for sequences.
+    pub fn definition(&self) -> &Definition {
+        &self.sequence[0]
+    }
+
+    /// The chords to press one after another, e.g. `super + a` then `b` for
+    /// `super + a ; b`. A single chord for most bindings, the one of
+    /// [`Binding::definition`]. The last chord fires the binding.
+    pub fn sequence(&self) -> &[Definition] {
+        &self.sequence
+    }
+
+    /// The chord that fires the binding, the last one for sequences.
+    pub fn last_definition(&self) -> &Definition {
+        // Safety: a sequence is never empty.
+        self.sequence.last().unwrap()
+    }
+
+    /// What equality, hashing and ordering look at: everything but where
+    /// and how the binding was written.
+    fn identity(&self) -> Identity<'_> {
+        (
+            &self.sequence,
+            &self.command,
+            &self.mode_instructions,
+            self.before_command,
+            self.schedule.as_ref(),
+            self.device.as_deref(),
+            &self.settings,
+        )
+    }
+
+    /// The chords of the binding in config syntax, separated by ` ; `.
+    pub(crate) fn chords_string(&self) -> String {
+        self.sequence.iter().join(" ; ")
+    }
+
+    /// Renders the binding the way it is written in a config: its
+    /// description as a comment, the chord, then every command line
+    /// indented by four spaces, with the mode instructions before or after
+    /// the command as they were written.
+    ///
+    /// Parsing the result of a parsed binding gives back an equal binding,
+    /// except for commands holding braces, which a config can only write
+    /// within shorthands.
+    ///
+    /// ```
+    /// use sweet::{ParserInput, SwhkdParser};
+    ///
+    /// let binding = "super + shift + return\n    alacritty && @enter terminal";
+    /// let config = format!("{binding}\nmode terminal\n    escape\n        @escape\nendmode");
+    /// let parsed = SwhkdParser::from(ParserInput::Raw(&config))?;
+    /// assert_eq!(parsed.bindings[0].to_config_string(), binding);
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn to_config_string(&self) -> String {
+        let instruction = |instruction: &ModeInstruction| match instruction {
+            ModeInstruction::Enter(mode) => format!("@enter {mode}"),
+            ModeInstruction::Escape => "@escape".to_string(),
+        };
+        let mut command = self.command.clone();
+        if !self.instructions_before_command().is_empty() {
+            let space = if command.starts_with(char::is_whitespace) {
+                ""
+            } else {
+                " "
+            };
+            let before = self.instructions_before_command().iter().map(instruction);
+            command = format!(
+                "{} &&{space}{command}",
+                before.collect::<Vec<_>>().join(" && ")
+            );
+        }
+        if !self.instructions_after_command().is_empty() {
+            let after = self.instructions_after_command().iter().map(instruction);
+            let after = after.collect::<Vec<_>>().join(" && ");
+            command = if command.is_empty() {
+                after
+            } else if command.ends_with(char::is_whitespace) {
+                format!("{command}&& {after}")
+            } else {
+                format!("{command} && {after}")
+            };
+        }
+        let mut out = String::new();
+        for line in self
+            .description
+            .iter()
+            .flat_map(|description| description.lines())
+        {
+            out.push_str(&format!("# {line}\n"));
+        }
+        if let Some(device) = &self.device {
+            out.push_str(&format!("[device={device}] "));
+        }
+        if let Some(schedule) = &self.schedule {
+            out.push_str(&format!("[when={schedule}] "));
+        }
+        for tag in self.settings.to_tags() {
+            out.push_str(&format!("{tag} "));
+        }
+        out.push_str(&self.chords_string());
+        for line in command.split('\n') {
+            out.push_str("\n    ");
+            out.push_str(line);
         }
+        out
+    }
+
+    /// The command to run. A binding declared with several command lines
+    /// runs them in order, they are separated by newlines here.
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// The commands run by this binding, one per command line in the config.
+    pub fn commands(&self) -> impl Iterator<Item = &str> {
+        self.command.lines()
+    }
+
+    /// Every mode instruction in the order they take effect, see
+    /// [`Binding::instructions_before_command`] and
+    /// [`Binding::instructions_after_command`] for when.
+    pub fn mode_instructions(&self) -> &[ModeInstruction] {
+        &self.mode_instructions
+    }
+
+    /// Instructions written before any command text, which take effect
+    /// before the command runs.
+    pub fn instructions_before_command(&self) -> &[ModeInstruction] {
+        &self.mode_instructions[..self.before_command]
+    }
+
+    /// Instructions written after command text, which take effect once the
+    /// command has been started. Bindings without a command have all their
+    /// instructions here.
+    pub fn instructions_after_command(&self) -> &[ModeInstruction] {
+        &self.mode_instructions[self.before_command..]
+    }
+
+    /// The key definition exactly as written in the config, e.g.
+    /// `super+{h,j,k,l}`, shared by every binding expanded from it. Empty
+    /// for bindings built in code.
+    pub fn source_text(&self) -> &str {
+        &self.source_text
+    }
+
+    /// Where the key definition is in the config, `None` for bindings built
+    /// in code.
+    pub fn span(&self) -> Option<&SourceSpan> {
+        self.span.as_ref()
     }
 }
 
 pub struct BindingBuilder {
-    pub command: String,
+    command: String,
+    mode_instructions: Vec<ModeInstruction>,
 }
 
 impl BindingBuilder {
+    /// Sets the modes entered or left after running the command, see
+    /// [`Binding::with_mode_instructions`].
+    ///
+    /// ```
+    /// use sweet::{Binding, Definition, ModeInstruction, ParserInput, SwhkdParser};
+    ///
+    /// let expected = Binding::running("")
+    ///     .with_mode_instructions(vec![ModeInstruction::Enter("resize".to_string())])
+    ///     .on(Definition::new(evdev::Key::KEY_R));
+    /// let config = "r\n    @enter resize\nmode resize\nh\n    shrink\nendmode";
+    /// let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    /// assert_eq!(parsed.bindings, [expected]);
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn with_mode_instructions(mut self, mode_instructions: Vec<ModeInstruction>) -> Self {
+        self.mode_instructions = mode_instructions;
+        self
+    }
+
     pub fn on(self, definition: Definition) -> Binding {
         Binding {
-            definition,
+            sequence: vec![definition],
             command: self.command,
-            mode_instructions: vec![],
+            mode_instructions: self.mode_instructions,
+            before_command: 0,
+            schedule: None,
+            device: None,
+            settings: Settings::default(),
+            mode: None,
+            description: None,
+            source_text: "".into(),
+            span: None,
+        }
+    }
+}
+
+/// The command lines and mode instructions of a binding as they are read,
+/// before shorthands are expanded. Configs and [`ConfigItem`]s both go
+/// through it, so that they expand alike.
+///
+/// [`ConfigItem`]: crate::ConfigItem
+#[derive(Default)]
+pub(crate) struct CommandUncompiled {
+    /// Chunks of every finished command line, each with its variants.
+    lines: Vec<Product<String>>,
+    /// Chunks of the current line, each with its variants.
+    chunks: Vec<Vec<String>>,
+    /// Byte range from the first to the last shorthand of every finished
+    /// command line, `None` for lines without shorthands or not written in
+    /// a config.
+    shorthands: Vec<Option<Range<usize>>>,
+    /// The same for the current line.
+    line_shorthands: Option<Range<usize>>,
+    /// Mode instructions in source order, the first `before_command` of
+    /// them precede any command text.
+    instructions: Vec<ModeInstruction>,
+    before_command: usize,
+    seen_command_text: bool,
+}
+
+impl CommandUncompiled {
+    pub(crate) fn text(&mut self, text: String) {
+        self.seen_command_text |= !text.trim().is_empty();
+        self.chunks.push(vec![text]);
+    }
+
+    /// A shorthand, one variant per definition of the binding, along with
+    /// the byte range it is written at if it is written in a config.
+    pub(crate) fn variants(&mut self, variants: Vec<String>, written: Option<Range<usize>>) {
+        self.seen_command_text = true;
+        self.chunks.push(variants);
+        if let Some(written) = written {
+            let line = self.line_shorthands.get_or_insert(written.clone());
+            line.end = written.end;
+        }
+    }
+
+    /// Joins a mode instruction to the command, as in
+    /// `@enter resize && notify-send resizing`. Only kept in the command
+    /// between two pieces of command text.
+    pub(crate) fn and(&mut self) {
+        if self
+            .chunks
+            .last()
+            .is_none_or(|last| last.len() == 1 && last[0] == "&&")
+        {
+            return;
+        }
+        self.chunks.push(vec!["&&".to_string()]);
+    }
+
+    pub(crate) fn enter(&mut self, mode: String) {
+        self.instructions.push(ModeInstruction::Enter(mode));
+        if !self.seen_command_text {
+            self.before_command += 1;
+        }
+    }
+
+    /// An escape cancels the last mode entered by the binding.
+    pub(crate) fn escape(&mut self) {
+        let entered = self
+            .instructions
+            .iter()
+            .rposition(|i| matches!(i, ModeInstruction::Enter(_)));
+        if let Some(index) = entered {
+            self.instructions.remove(index);
+            if index < self.before_command {
+                self.before_command -= 1;
+            }
+        } else {
+            self.instructions.push(ModeInstruction::Escape);
+            if !self.seen_command_text {
+                self.before_command += 1;
+            }
         }
     }
+
+    pub(crate) fn end_line(&mut self) {
+        let mut chunks = std::mem::take(&mut self.chunks);
+        if chunks
+            .last()
+            .is_some_and(|last| last.len() == 1 && last[0] == "&&")
+        {
+            chunks.pop();
+        }
+        // A line holding nothing but mode instructions runs no command.
+        self.lines.push(Product::new(chunks));
+        self.shorthands.push(self.line_shorthands.take());
+    }
+
+    /// Pairs every sequence of definitions with its variant of the command,
+    /// failing with a message if a command line has a different number of
+    /// variants, along with where the shorthands of that line are written
+    /// if known. The numbers are computed from the sizes of the shorthands,
+    /// the bindings are only built when iterating.
+    pub(crate) fn compile(
+        self,
+        sequences: Product<Definition>,
+        schedule: Option<Schedule>,
+        device: Option<String>,
+        source_text: Arc<str>,
+        span: Option<SourceSpan>,
+    ) -> Result<BindingVariants, (String, Option<Range<usize>>)> {
+        // Without a command there is nothing to come before.
+        let before_command = if self.seen_command_text {
+            self.before_command
+        } else {
+            0
+        };
+        // Each command line is checked on its own, so that a mismatch is not
+        // hidden by another line with a matching shorthand.
+        let bind_len = sequences.len();
+        if let Some((command_len, written)) = self
+            .lines
+            .iter()
+            .map(Product::len)
+            .zip(self.shorthands)
+            .find(|(command_len, _)| *command_len != bind_len)
+        {
+            let message = format!(
+                "the number of possible binding variants {0} does not equal the number of possible command variants {1}.",
+                bind_len, command_len
+            );
+            return Err((message, written));
+        }
+        let mut before = self.instructions;
+        let after = before.split_off(before_command);
+        Ok(BindingVariants {
+            sequences,
+            lines: self.lines,
+            before,
+            after,
+            schedule,
+            device,
+            settings: Settings::default(),
+            description: None,
+            source_text,
+            span,
+            next: 0,
+            len: bind_len,
+        })
+    }
+}
+
+/// The bindings a single declaration expands to, built one at a time, see
+/// [`CommandUncompiled::compile`].
+#[derive(Debug)]
+pub(crate) struct BindingVariants {
+    sequences: Product<Definition>,
+    lines: Vec<Product<String>>,
+    before: Vec<ModeInstruction>,
+    after: Vec<ModeInstruction>,
+    schedule: Option<Schedule>,
+    device: Option<String>,
+    settings: Settings,
+    description: Option<Arc<str>>,
+    source_text: Arc<str>,
+    span: Option<SourceSpan>,
+    next: usize,
+    len: usize,
 }
 
+impl BindingVariants {
+    /// Where the chord of every variant is written.
+    pub(crate) fn span(&self) -> Option<&SourceSpan> {
+        self.span.as_ref()
+    }
+
+    /// Describes every variant, see [`Binding::description`].
+    pub(crate) fn described(mut self, description: Option<String>) -> Self {
+        self.description = description.map(Into::into);
+        self
+    }
+
+    /// Sets the settings of every variant, see [`Binding::settings`].
+    pub(crate) fn with_settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// The mode instructions shared by every variant.
+    pub(crate) fn mode_instructions(&self) -> impl Iterator<Item = &ModeInstruction> {
+        self.before.iter().chain(&self.after)
+    }
+
+    fn variant(&self, variant: usize) -> Binding {
+        let command = self
+            .lines
+            .iter()
+            .map(|line| line.get(variant).into_iter().join(""))
+            .join("\n");
+        let mut steps = self.sequences.get(variant).into_iter().cloned();
+        // Safety: the grammar guarantees a chord in every sequence.
+        let binding = Binding::running(command).on(steps.next().unwrap());
+        let binding = steps
+            .fold(binding, Binding::then)
+            .with_mode_instructions_before_command(self.before.clone())
+            .with_mode_instructions(self.after.clone())
+            .written_at(self.source_text.clone(), self.span.clone());
+        let binding = match &self.schedule {
+            Some(schedule) => binding.with_schedule(schedule.clone()),
+            None => binding,
+        };
+        let binding = match &self.device {
+            Some(device) => binding.with_device(device.clone()),
+            None => binding,
+        }
+        .with_settings(self.settings.clone());
+        match &self.description {
+            Some(description) => binding.with_description(description.clone()),
+            None => binding,
+        }
+    }
+}
+
+impl Iterator for BindingVariants {
+    type Item = Binding;
+
+    fn next(&mut self) -> Option<Binding> {
+        if self.next == self.len {
+            return None;
+        }
+        self.next += 1;
+        Some(self.variant(self.next - 1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for BindingVariants {}
+
 impl Display for Binding {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Binding {}", self.chords_string())?;
+        if let Some(device) = &self.device {
+            write!(f, " [device={device}]")?;
+        }
+        if let Some(schedule) = &self.schedule {
+            write!(f, " [when={schedule}]")?;
+        }
+        if !self.source_text.is_empty() {
+            write!(f, " (written `{}`)", self.source_text)?;
+        }
         write!(
             f,
-            "Binding {} \u{2192} {} (mode instructions: {:?})",
-            self.definition, self.command, self.mode_instructions
+            " \u{2192} {} (mode instructions: {:?})",
+            self.command, self.mode_instructions
         )
     }
 }
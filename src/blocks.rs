@@ -0,0 +1,160 @@
+//! Commands reused by name, defined with `block name` ... `endblock` and
+//! referenced as `&name` in commands.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Range,
+};
+
+use itertools::Itertools;
+use pest::{iterators::Pair, Span};
+
+use crate::{
+    expand::escape_variant,
+    parse::{run_grammar, spanned_error},
+    variables, ParseError, Rule,
+};
+
+/// The command lines of blocks by name, joined with newlines.
+pub(crate) type Blocks = BTreeMap<String, String>;
+
+/// The name and command lines of a `block` statement.
+pub(crate) fn block_parser(pair: Pair<'_, Rule>) -> (String, String) {
+    let mut name = String::new();
+    let mut lines = vec![];
+    for component in pair.into_inner() {
+        match component.as_rule() {
+            Rule::block_name => name = component.as_str().to_string(),
+            Rule::block_line => lines.push(component.as_str().trim()),
+            _ => {}
+        }
+    }
+    (
+        name,
+        lines.into_iter().filter(|line| !line.is_empty()).join("\n"),
+    )
+}
+
+/// Runs `f` on a command after replacing every `&name` in it by the lines
+/// of the block `name`, indented like the command, and adding the names to
+/// `used`. The expanded command is not in the config, so errors about it
+/// are reported on the command as written.
+///
+/// A `&name` naming no block is left to the shell, e.g. `sleep 1 &disown`.
+///
+/// Shorthands in a block expand like the ones written in the command. In a
+/// variant of a shorthand, a block is a single line taken literally.
+/// Blocks are expanded a single level: a block referencing another block
+/// is an error naming both.
+pub(crate) fn with_command<R>(
+    command: Pair<'_, Rule>,
+    blocks: &Blocks,
+    used: &mut BTreeSet<String>,
+    f: impl for<'i> FnOnce(Pair<'i, Rule>) -> Result<R, ParseError>,
+) -> Result<R, ParseError> {
+    let references = references_in(&command, blocks);
+    if references.is_empty() {
+        return f(command);
+    }
+    let span = command.as_span();
+    let input = span.get_input();
+    let (lines, indent, mut expanded) = if command.as_rule() == Rule::command_inline {
+        // A single-line command is expanded like an indented one, so that
+        // blocks may run several lines.
+        let lines = span.as_str()[1..].trim_start_matches([' ', '\t']);
+        (lines, "    ", "\n    ".to_string())
+    } else {
+        // The command starts with the newline ending the chord, then the
+        // indent of its lines.
+        let body = span.as_str().trim_start_matches(['\r', '\n']);
+        let lines = body.trim_start_matches([' ', '\t']);
+        (lines, &body[..body.len() - lines.len()], String::new())
+    };
+
+    // Where the block of each reference ended up in `expanded`.
+    let mut expansions: Vec<(Range<usize>, &Pair<'_, Rule>)> = vec![];
+    let mut copied = if expanded.is_empty() {
+        span.start()
+    } else {
+        span.end() - lines.len()
+    };
+    for (reference, in_shorthand) in &references {
+        let name = &reference.as_str()[1..];
+        // Safety: `references_in` only keeps references to blocks.
+        let block = &blocks[name];
+        used.insert(name.to_string());
+        let text = if !in_shorthand {
+            block.replace('\n', &format!("\n{indent}"))
+        } else if block.contains('\n') {
+            return Err(spanned_error(
+                format!(
+                    "block `{}` has several lines, it cannot be used in a shorthand",
+                    reference.as_str()
+                ),
+                reference.as_span(),
+            ));
+        } else {
+            escape_variant(block)
+        };
+        let at = reference.as_span();
+        expanded.push_str(&input[copied..at.start()]);
+        let start = expanded.len();
+        expanded.push_str(&text);
+        expansions.push((start..expanded.len(), reference));
+        copied = at.end();
+    }
+    expanded.push_str(&input[copied..span.end()]);
+
+    // Safety: the lines end the command.
+    let lines = Span::new(input, span.end() - lines.len(), span.end()).unwrap();
+    let names = references
+        .iter()
+        .map(|(reference, _)| reference.as_str())
+        .unique()
+        .join("`, `");
+    let in_command =
+        |message: String| spanned_error(format!("with `{names}` expanded, {message}"), lines);
+    let reparsed = run_grammar(Rule::command, &expanded)
+        .ok()
+        .and_then(|mut pairs| pairs.next())
+        .filter(|reparsed| reparsed.as_str().len() == expanded.len())
+        .ok_or_else(|| in_command("the command is not valid".to_string()))?;
+    if let Some((nested, _)) = references_in(&reparsed, blocks).first() {
+        let at = nested.as_span().start();
+        // Safety: nested references come from the text of a block.
+        let (_, outer) = expansions
+            .iter()
+            .find(|(range, _)| range.contains(&at))
+            .unwrap();
+        return Err(spanned_error(
+            format!(
+                "recursive block reference `{}` \u{2192} `{}`, blocks cannot reference other blocks",
+                outer.as_str(),
+                nested.as_str()
+            ),
+            outer.as_span(),
+        ));
+    }
+    f(reparsed).map_err(|err| in_command(variables::message(err)))
+}
+
+/// Every reference to one of `blocks` in a command in order, along with
+/// whether it is in a shorthand.
+fn references_in<'i>(command: &Pair<'i, Rule>, blocks: &Blocks) -> Vec<(Pair<'i, Rule>, bool)> {
+    let mut references = vec![];
+    for chunk in command.clone().into_inner() {
+        match chunk.as_rule() {
+            Rule::block_reference => references.push((chunk, false)),
+            Rule::command_shorthand => references.extend(
+                chunk
+                    .into_inner()
+                    .flatten()
+                    .filter(|pair| pair.as_rule() == Rule::block_reference)
+                    .map(|reference| (reference, true)),
+            ),
+            _ => {}
+        }
+    }
+    references.retain(|(reference, _)| blocks.contains_key(&reference.as_str()[1..]));
+    references
+}
@@ -0,0 +1,222 @@
+//! Building configs in code from chords that are already expanded, see
+//! [`ConfigBuilder`].
+
+use crate::{
+    definition::{check_key, check_modifiers_allowed},
+    items::{check_mode_name, invalid},
+    resolve::ImportContext,
+    settings::SettingScope,
+    Binding, Definition, Mode, ParseError, Settings, SwhkdParser, Trigger,
+};
+
+/// Builds a config from bindings and modes rather than text, for tools
+/// that edit configs. Unlike [`SwhkdParser::from_items`], chords are given
+/// as [`Definition`]s, without shorthands.
+///
+/// [`ConfigBuilder::build`] checks the config and merges it like
+/// [`SwhkdParser::from`]: later bindings override earlier ones on the same
+/// chord, `ignore`s apply wherever they are, and every mode entered has to
+/// be declared.
+///
+/// ```
+/// use sweet::{
+///     Binding, ConfigBuilder, Definition, ModeInstruction, Modifier, ParserInput, SwhkdParser,
+/// };
+///
+/// let super_r = Definition::new(evdev::Key::KEY_R).with_modifiers(&[Modifier::Super]);
+/// let built = ConfigBuilder::new()
+///     .with_binding(
+///         Binding::running("")
+///             .with_mode_instructions(vec![ModeInstruction::Enter("resize".to_string())])
+///             .on(super_r),
+///     )
+///     .mode("resize", |mode| {
+///         mode.oneoff()
+///             .binding(Definition::new(evdev::Key::KEY_H), "bspc node -z left -20 0")
+///     })
+///     .build()?;
+/// let config = "
+/// super + r
+///     @enter resize
+/// mode resize oneoff
+///     h
+///         bspc node -z left -20 0
+/// endmode";
+/// let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+/// assert_eq!(built.bindings, parsed.bindings);
+/// assert_eq!(built.modes[0].bindings, parsed.modes[0].bindings);
+/// assert_eq!(built.to_config_string(), parsed.to_config_string());
+/// # Ok::<(), sweet::ParseError>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct ConfigBuilder {
+    root: SwhkdParser,
+}
+
+impl ConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a binding running `command` on `definition`.
+    pub fn binding<S: AsRef<str>>(self, definition: Definition, command: S) -> Self {
+        self.with_binding(Binding::running(command).on(definition))
+    }
+
+    /// Adds a binding, e.g. one entering a mode or on a sequence of chords.
+    pub fn with_binding(mut self, binding: Binding) -> Self {
+        self.root.bindings.push(binding);
+        self
+    }
+
+    /// Adds an `ignore` of `definition`.
+    pub fn unbind(mut self, definition: Definition) -> Self {
+        self.root.unbinds.push(definition);
+        self
+    }
+
+    /// Adds the mode `name`, declared by `build`. A mode added twice is
+    /// merged like a mode declared twice in a config.
+    pub fn mode<S: Into<String>>(
+        mut self,
+        name: S,
+        build: impl FnOnce(ModeBuilder) -> ModeBuilder,
+    ) -> Self {
+        let mode = build(ModeBuilder(Mode {
+            name: name.into(),
+            ..Default::default()
+        }));
+        self.root.modes.push(mode.0);
+        self
+    }
+
+    /// Sets the top-level settings, like `set` statements.
+    pub fn with_settings(mut self, settings: Settings) -> Self {
+        self.root.settings = settings;
+        self
+    }
+
+    /// Sets the settings of the bindings of `device`, like
+    /// `[device=name] set` statements.
+    pub fn with_device_settings<S: Into<String>>(mut self, device: S, settings: Settings) -> Self {
+        self.root.device_settings.insert(device.into(), settings);
+        self
+    }
+
+    /// Checks the config and merges it into the bindings and modes
+    /// [`SwhkdParser::from`] would give for the same config. Chords and
+    /// settings a config could not hold are rejected with
+    /// [`ParseError::InvalidItem`], and entering a mode that is not
+    /// declared with [`ParseError::UnknownMode`].
+    pub fn build(self) -> Result<SwhkdParser, ParseError> {
+        let root = self.root;
+        check_bindings(&root.bindings)?;
+        check_unbinds(&root.unbinds)?;
+        root.settings
+            .check(SettingScope::TopLevel)
+            .map_err(|message| invalid("settings", message))?;
+        for (device, settings) in &root.device_settings {
+            settings
+                .check(SettingScope::Device)
+                .map_err(|message| invalid(&format!("settings of device `{device}`"), message))?;
+        }
+        for mode in &root.modes {
+            let context = format!("mode `{}`", mode.name);
+            check_mode_name(&mode.name).map_err(|message| invalid(&context, message))?;
+            mode.settings
+                .check(SettingScope::Mode)
+                .map_err(|message| invalid(&context, message))?;
+            check_bindings(&mode.bindings)?;
+            check_unbinds(&mode.unbinds)?;
+            for definition in &mode.replay {
+                check_definition(definition, false)?;
+            }
+        }
+        SwhkdParser::merge(root, &mut ImportContext::default())
+    }
+}
+
+/// Declares a mode of a [`ConfigBuilder`].
+#[derive(Debug)]
+pub struct ModeBuilder(Mode);
+
+impl ModeBuilder {
+    /// Leaves the mode after any of its bindings fires, like `oneoff`.
+    pub fn oneoff(mut self) -> Self {
+        self.0.oneoff = true;
+        self
+    }
+
+    /// Keeps the keys of the keyboard from the focused client while the
+    /// mode is active, like `swallow`.
+    pub fn swallow(mut self) -> Self {
+        self.0.swallow = true;
+        self
+    }
+
+    /// Adds a binding running `command` on `definition`.
+    pub fn binding<S: AsRef<str>>(self, definition: Definition, command: S) -> Self {
+        self.with_binding(Binding::running(command).on(definition))
+    }
+
+    pub fn with_binding(mut self, binding: Binding) -> Self {
+        self.0.bindings.push(binding);
+        self
+    }
+
+    /// Adds an `ignore` of `definition`.
+    pub fn unbind(mut self, definition: Definition) -> Self {
+        self.0.unbinds.push(definition);
+        self
+    }
+
+    /// Adds a `replay` of `definition`, see [`Mode::replay`].
+    pub fn replay(mut self, definition: Definition) -> Self {
+        self.0.replay.push(definition);
+        self
+    }
+
+    /// Sets the settings of the mode, like `set` statements in its block.
+    pub fn with_settings(mut self, settings: Settings) -> Self {
+        self.0.settings = settings;
+        self
+    }
+}
+
+fn check_bindings(bindings: &[Binding]) -> Result<(), ParseError> {
+    for binding in bindings {
+        binding
+            .settings()
+            .check(SettingScope::Binding)
+            .map_err(|message| {
+                invalid(
+                    &format!("settings of `{}`", binding.chords_string()),
+                    message,
+                )
+            })?;
+    }
+    bindings
+        .iter()
+        .flat_map(Binding::sequence)
+        .try_for_each(|definition| check_definition(definition, false))
+}
+
+fn check_unbinds(unbinds: &[Definition]) -> Result<(), ParseError> {
+    unbinds
+        .iter()
+        .try_for_each(|definition| check_definition(definition, true))
+}
+
+/// Checks that a config could hold `definition`, in the same way as the
+/// parser checks the chords it reads. Only unbinds hold `any_key`.
+fn check_definition(definition: &Definition, unbind: bool) -> Result<(), ParseError> {
+    let key = definition.key();
+    let checked = if key.trigger == Trigger::AnyKey && !unbind {
+        Err("`any_key` only applies to `ignore` statements".to_string())
+    } else if definition.modifiers().is_empty() {
+        check_key(key)
+    } else {
+        check_modifiers_allowed(&key.trigger).and_then(|_| check_key(key))
+    };
+    checked.map_err(|message| invalid(&format!("`{definition}`"), message))
+}
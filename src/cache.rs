@@ -0,0 +1,221 @@
+//! Caching parsed configs on disk, enabled by the `cache` feature, so that
+//! the daemon can skip expanding a config that did not change.
+//!
+//! A cache file starts with `sweet\0` and the version of its format, then
+//! holds a CBOR header naming the sweet version and every file that was
+//! read, and the parsed config as CBOR.
+
+use std::{io::Cursor, path::Path};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{resolve::ImportContext, sha256::Sha256, ParseOptions, ParserInput, SwhkdParser};
+
+const MAGIC: &[u8; 6] = b"sweet\0";
+/// Bumped whenever the layout of cache files changes.
+const FORMAT_VERSION: u32 = 1;
+
+/// Why a cache could not be used. [`SwhkdParser::from_with_cache_file`]
+/// parses the config again on any of them.
+#[derive(Debug, Error)]
+pub enum CacheError {
+    #[error("not a sweet cache file")]
+    NotACache,
+    #[error("cache format {0} is not supported, expected {FORMAT_VERSION}")]
+    UnsupportedFormat(u32),
+    #[error("cache written by sweet {0}, this is sweet {}", env!("CARGO_PKG_VERSION"))]
+    OtherVersion(String),
+    /// The cache could not be decoded, e.g. because it was cut short.
+    #[error("corrupted cache: {0}")]
+    Corrupted(String),
+    /// A config file changed since the cache was written, or could not be
+    /// read again.
+    #[error("config file `{0}` changed since the cache was written")]
+    Stale(String),
+}
+
+/// A file read while parsing a config, with enough to read it again the
+/// same way.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct SourceFile {
+    /// The path as written on the command line or in the `include`
+    /// statement, `<anonymous>` for raw input.
+    requested: String,
+    /// The path the request was resolved from, `None` for the root config
+    /// and the includes of raw input.
+    base: Option<String>,
+    /// The path the request resolved to.
+    path: String,
+    sha256: String,
+}
+
+impl SourceFile {
+    pub(crate) fn new(requested: &str, base: Option<&Path>, path: &str, contents: &str) -> Self {
+        Self {
+            requested: requested.to_string(),
+            base: base.map(|base| base.to_string_lossy().into_owned()),
+            path: path.to_string(),
+            sha256: digest(contents),
+        }
+    }
+}
+
+fn digest(contents: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(contents.as_bytes());
+    hasher.finish_hex()
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    sweet_version: String,
+    files: Vec<SourceFile>,
+}
+
+/// A parsed config along with a fingerprint of the files it was parsed
+/// from, see [`SwhkdParser::from_with_cache_file`].
+#[derive(Debug)]
+pub struct CachedConfig {
+    pub parsed: SwhkdParser,
+    files: Vec<SourceFile>,
+}
+
+impl CachedConfig {
+    /// Encodes the config for [`CachedConfig::deserialize`].
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.extend(FORMAT_VERSION.to_le_bytes());
+        let header = Header {
+            sweet_version: env!("CARGO_PKG_VERSION").to_string(),
+            files: self.files.clone(),
+        };
+        // Safety: writing to a vector cannot fail, and every part of a
+        // parsed config serializes.
+        ciborium::into_writer(&header, &mut out).unwrap();
+        ciborium::into_writer(&self.parsed, &mut out).unwrap();
+        out
+    }
+
+    /// Decodes a cache written by [`CachedConfig::serialize`], failing on
+    /// caches of another format or sweet version and on corrupted ones.
+    /// Whether the config changed since is checked by
+    /// [`CachedConfig::check_fresh`].
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, CacheError> {
+        let rest = bytes.strip_prefix(MAGIC).ok_or(CacheError::NotACache)?;
+        let (version, rest) = rest
+            .split_first_chunk::<4>()
+            .ok_or_else(|| CacheError::Corrupted("missing format version".to_string()))?;
+        let version = u32::from_le_bytes(*version);
+        if version != FORMAT_VERSION {
+            return Err(CacheError::UnsupportedFormat(version));
+        }
+        let corrupted =
+            |err: ciborium::de::Error<std::io::Error>| CacheError::Corrupted(err.to_string());
+        let mut reader = Cursor::new(rest);
+        let header: Header = ciborium::from_reader(&mut reader).map_err(corrupted)?;
+        if header.sweet_version != env!("CARGO_PKG_VERSION") {
+            return Err(CacheError::OtherVersion(header.sweet_version));
+        }
+        let parsed = ciborium::from_reader(&mut reader).map_err(corrupted)?;
+        if reader.position() != rest.len() as u64 {
+            return Err(CacheError::Corrupted("trailing bytes".to_string()));
+        }
+        Ok(Self {
+            parsed,
+            files: header.files,
+        })
+    }
+
+    /// Reads every file of the config again, without parsing them, and
+    /// fails on the first one that changed. A file resolving elsewhere,
+    /// e.g. after an environment variable in an `include` path changed,
+    /// counts as changed.
+    pub fn check_fresh(&self, input: &ParserInput) -> Result<(), CacheError> {
+        let options = ParseOptions::default();
+        let resolver = options.resolver();
+        let stale = |file: &SourceFile| CacheError::Stale(file.path.clone());
+        let read = |file: &SourceFile| {
+            let resolved = resolver
+                .resolve(&file.requested, file.base.as_deref().map(Path::new))
+                .map_err(|_| stale(file))?;
+            if resolved.path.to_string_lossy() != file.path {
+                return Err(stale(file));
+            }
+            Ok(resolved.contents)
+        };
+        // The root config is recorded first.
+        let (root, includes) = self
+            .files
+            .split_first()
+            .ok_or_else(|| CacheError::Corrupted("no config file".to_string()))?;
+        let contents = match input {
+            ParserInput::Raw(raw) if root.requested == "<anonymous>" => raw.to_string(),
+            ParserInput::Path(path) if path.to_str() == Some(root.requested.as_str()) => {
+                read(root)?
+            }
+            _ => return Err(stale(root)),
+        };
+        if digest(&contents) != root.sha256 {
+            return Err(stale(root));
+        }
+        for file in includes {
+            if digest(&read(file)?) != file.sha256 {
+                return Err(stale(file));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SwhkdParser {
+    /// Parses `input` like [`SwhkdParser::from`], recording the files it
+    /// reads so that the result can be cached.
+    pub fn cacheable(input: ParserInput) -> Result<CachedConfig, crate::ParseError> {
+        let mut ctx = ImportContext {
+            sources: Some(vec![]),
+            ..Default::default()
+        };
+        let parsed = Self::from_context(input, &mut ctx)?;
+        Ok(CachedConfig {
+            parsed,
+            files: ctx.sources.unwrap_or_default(),
+        })
+    }
+
+    /// Like [`SwhkdParser::from`], reusing the config cached at
+    /// `cache_path` if none of its files changed. Otherwise the config is
+    /// parsed and the cache written for the next time.
+    ///
+    /// Checking the cache reads every file of the config but parses none,
+    /// which skips expanding and merging its bindings. An unreadable,
+    /// corrupted or stale cache is never an error, and neither is failing
+    /// to write it: the config is parsed as usual.
+    ///
+    /// ```
+    /// use sweet::{ParserInput, SwhkdParser};
+    ///
+    /// let dir = tempfile::tempdir()?;
+    /// let cache = dir.path().join("swhkdrc.cache");
+    /// let config = "super + {1-9}\n    bspc desktop -f ^{1-9}";
+    /// let parsed = SwhkdParser::from_with_cache_file(ParserInput::Raw(config), &cache)?;
+    /// let cached = SwhkdParser::from_with_cache_file(ParserInput::Raw(config), &cache)?;
+    /// assert_eq!(cached.bindings, parsed.bindings);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_with_cache_file(
+        input: ParserInput,
+        cache_path: &Path,
+    ) -> Result<Self, crate::ParseError> {
+        let cached = std::fs::read(cache_path)
+            .ok()
+            .and_then(|bytes| CachedConfig::deserialize(&bytes).ok())
+            .filter(|cached| cached.check_fresh(&input).is_ok());
+        if let Some(cached) = cached {
+            return Ok(cached.parsed);
+        }
+        let cached = Self::cacheable(input)?;
+        let _ = std::fs::write(cache_path, cached.serialize());
+        Ok(cached.parsed)
+    }
+}
@@ -0,0 +1,256 @@
+//! Reading sxhkd configs, see [`from_sxhkd`].
+//!
+//! sxhkd and sweet share most of their syntax, shorthands, `@` and `~`
+//! included, and chains of chords written with `;` parse as sequences.
+//! What differs is how chords name keys: sxhkd uses X11 keysyms and
+//! `buttonN` for the mouse. Chords are translated word by word before
+//! parsing, commands are left as they are.
+
+use std::ops::Range;
+
+use pest::Span;
+use phf::phf_map;
+
+use crate::{
+    parse::{in_file, spanned_error},
+    resolve::{path_str, ImportContext},
+    ParseError, ParserInput, SwhkdParser,
+};
+
+/// X11 keysym names sxhkd configs use, lowercase, and the key names sweet
+/// knows them by. Keysyms sweet accepts as they are, e.g.
+/// `XF86AudioRaiseVolume` or `bracketleft`, are not listed.
+static KEYSYMS: phf::Map<&'static str, &'static str> = phf_map! {
+    // sweet reads `prior` and `next` the other way around.
+    "prior" => "pageup",
+    "next" => "pagedown",
+    "page_up" => "pageup",
+    "page_down" => "pagedown",
+    "caps_lock" => "capslock",
+    "num_lock" => "numlock",
+    "sys_req" => "print",
+    "menu" => "key_compose",
+    "quoteleft" => "grave",
+    "quoteright" => "apostrophe",
+    "super_l" => "key_leftmeta",
+    "super_r" => "key_rightmeta",
+    "control_l" => "key_leftctrl",
+    "control_r" => "key_rightctrl",
+    "alt_l" => "key_leftalt",
+    "alt_r" => "key_rightalt",
+    "shift_l" => "key_leftshift",
+    "shift_r" => "key_rightshift",
+    "kp_0" => "kp0",
+    "kp_1" => "kp1",
+    "kp_2" => "kp2",
+    "kp_3" => "kp3",
+    "kp_4" => "kp4",
+    "kp_5" => "kp5",
+    "kp_6" => "kp6",
+    "kp_7" => "kp7",
+    "kp_8" => "kp8",
+    "kp_9" => "kp9",
+    "kp_add" => "kpplus",
+    "kp_subtract" => "kpminus",
+    "kp_multiply" => "kpasterisk",
+    "kp_divide" => "kpslash",
+    "kp_decimal" => "kpdot",
+    "kp_equal" => "kpequal",
+    "xf86audiopause" => "key_pausecd",
+    "xf86audioforward" => "key_fastforward",
+    "xf86audiorewind" => "key_rewind",
+    "xf86audiorecord" => "key_record",
+    "xf86kbdbrightnessup" => "key_kbdillumup",
+    "xf86kbdbrightnessdown" => "key_kbdillumdown",
+    "xf86kbdlightonoff" => "key_kbdillumtoggle",
+    "xf86calculator" => "key_calc",
+    "xf86mail" => "key_mail",
+    "xf86search" => "key_search",
+    "xf86homepage" => "key_homepage",
+    "xf86www" => "key_www",
+    "xf86favorites" => "key_bookmarks",
+    "xf86back" => "key_back",
+    "xf86forward" => "key_forward",
+    "xf86refresh" => "key_refresh",
+    "xf86copy" => "key_copy",
+    "xf86cut" => "key_cut",
+    "xf86paste" => "key_paste",
+    "xf86eject" => "key_ejectcd",
+    "xf86sleep" => "key_sleep",
+    "xf86wakeup" => "key_wakeup",
+    "xf86poweroff" => "key_power",
+    "xf86screensaver" => "key_screenlock",
+    "xf86display" => "key_switchvideomode",
+    "xf86touchpadtoggle" => "key_touchpad_toggle",
+    "xf86wlan" => "key_wlan",
+    "xf86bluetooth" => "key_bluetooth",
+    // Mouse buttons as X11 numbers them.
+    "button1" => "btn_left",
+    "button2" => "btn_middle",
+    "button3" => "btn_right",
+    "button8" => "btn_side",
+    "button9" => "btn_extra",
+};
+
+/// Words of sxhkd chords sweet has no counterpart for, lowercase, and why.
+const UNSUPPORTED: &[(&str, &str)] = &[
+    ("hyper", "the `hyper` modifier is not supported"),
+    ("mode_switch", "the `mode_switch` modifier is not supported"),
+    ("lock", "the `lock` modifier is not supported"),
+    ("mod2", "the `mod2` modifier is not supported"),
+    ("mod3", "the `mod3` modifier is not supported"),
+    ("button4", "scrolling cannot be bound"),
+    ("button5", "scrolling cannot be bound"),
+    ("button6", "scrolling cannot be bound"),
+    ("button7", "scrolling cannot be bound"),
+];
+
+/// Parses an sxhkd config, translating the X11 keysyms and mouse buttons
+/// of its chords to sweet's key names first.
+///
+/// Constructs sweet has no counterpart for, e.g. the `hyper` modifier,
+/// scroll buttons or chains locked with `:`, fail with an error pointing at
+/// them. Translating keeps lines as they are, errors raised after it point
+/// at the line at fault.
+///
+/// ```
+/// use sweet::{from_sxhkd, ParseError, ParserInput};
+///
+/// let config = "
+/// super + {Prior, Next}
+///     bspc desktop -f {prev, next}
+/// XF86Audio{Play,Pause}
+///     playerctl {play,pause}
+/// super + button1
+///     bspc node -f pointed";
+/// let parsed = from_sxhkd(ParserInput::Raw(config))?;
+/// assert_eq!(parsed.bindings.len(), 5);
+///
+/// let Err(ParseError::Grammar(err)) = from_sxhkd(ParserInput::Raw("hyper + a\n    firefox")) else {
+///     panic!("sweet has no `hyper` modifier");
+/// };
+/// assert!(err.to_string().contains("the `hyper` modifier is not supported"));
+/// # Ok::<(), sweet::ParseError>(())
+/// ```
+pub fn from_sxhkd(input: ParserInput) -> Result<SwhkdParser, ParseError> {
+    let mut ctx = ImportContext::default();
+    let (contents, path, requested) = match input {
+        ParserInput::Raw(s) => (s.to_string(), None, "<anonymous>"),
+        ParserInput::Path(p) => {
+            let resolved = ctx.options.resolver().resolve(path_str(p)?, None)?;
+            let requested = path_str(p)?;
+            (resolved.contents, Some(resolved.path), requested)
+        }
+    };
+    let translated = translate(&contents).map_err(|err| match &path {
+        Some(path) => in_file(err, &path.to_string_lossy()),
+        None => err,
+    })?;
+    let root = SwhkdParser::as_root_contents(&translated, path.as_deref(), requested, &mut ctx)?;
+    SwhkdParser::merge(root, &mut ctx)
+}
+
+/// The sxhkd config `contents` with the words of its chords translated.
+/// Lines starting with a blank are commands and `#` starts a comment, as
+/// in sxhkd.
+fn translate(contents: &str) -> Result<String, ParseError> {
+    let mut translated = String::with_capacity(contents.len());
+    let mut offset = 0;
+    for line in contents.split_inclusive('\n') {
+        if line.starts_with([' ', '\t', '#', '\r', '\n']) {
+            translated.push_str(line);
+        } else {
+            check_chord(contents, offset..offset + line.len())?;
+            for (_, token) in tokens(&distribute(line)) {
+                let lowercase = token.to_ascii_lowercase();
+                translated.push_str(KEYSYMS.get(lowercase.as_str()).copied().unwrap_or(token));
+            }
+        }
+        offset += line.len();
+    }
+    Ok(translated)
+}
+
+/// Fails on the first construct of the chord line at `range` of `contents`
+/// that sweet has no counterpart for.
+fn check_chord(contents: &str, range: Range<usize>) -> Result<(), ParseError> {
+    for (start, token) in tokens(&contents[range.clone()]) {
+        let message = if token == ":" {
+            "chains locked with `:` are not supported, chain chords with `;`"
+        } else if let Some((_, message)) = UNSUPPORTED
+            .iter()
+            .find(|(name, _)| token.eq_ignore_ascii_case(name))
+        {
+            message
+        } else {
+            continue;
+        };
+        let start = range.start + start;
+        // Safety: tokens lie within the line.
+        let span = Span::new(contents, start, start + token.len()).unwrap();
+        return Err(spanned_error(message.to_string(), span));
+    }
+    Ok(())
+}
+
+/// Moves the parts of words around a shorthand into it, sweet only takes
+/// shorthands of whole keys: `XF86Audio{Play,Stop}` becomes
+/// `{XF86AudioPlay,XF86AudioStop}`. Shorthands of ranges, omissions or
+/// modifiers are left as they are.
+fn distribute(line: &str) -> String {
+    let mut distributed = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some((open, close)) = rest
+        .find('{')
+        .and_then(|open| Some((open, open + rest[open..].find('}')?)))
+    {
+        let before = &rest[..open];
+        let prefix = &before[before.trim_end_matches(is_word).len()..];
+        let after = &rest[close + 1..];
+        let suffix = &after[..after.find(|c| !is_word(c)).unwrap_or(after.len())];
+        let elements: Vec<_> = rest[open + 1..close].split(',').map(str::trim).collect();
+        if prefix.is_empty() && suffix.is_empty()
+            || elements
+                .iter()
+                .any(|element| *element == "_" || element.contains(['-', '+']))
+        {
+            distributed.push_str(&rest[..=close]);
+            rest = after;
+            continue;
+        }
+        distributed.push_str(&before[..before.len() - prefix.len()]);
+        distributed.push('{');
+        let elements: Vec<_> = elements
+            .iter()
+            .map(|element| format!("{prefix}{element}{suffix}"))
+            .collect();
+        distributed.push_str(&elements.join(","));
+        distributed.push('}');
+        rest = &after[suffix.len()..];
+    }
+    distributed.push_str(rest);
+    distributed
+}
+
+/// Whether `c` is part of a word of a chord, e.g. a keysym or a modifier.
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// The words of `line` and the characters between them, one by one, along
+/// with where they start.
+fn tokens(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut rest = line;
+    std::iter::from_fn(move || {
+        let c = rest.chars().next()?;
+        let len = if is_word(c) {
+            rest.find(|c| !is_word(c)).unwrap_or(rest.len())
+        } else {
+            c.len_utf8()
+        };
+        let start = line.len() - rest.len();
+        let (token, tail) = rest.split_at(len);
+        rest = tail;
+        Some((start, token))
+    })
+}
@@ -0,0 +1,227 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{Binding, Definition, Modifier, SwhkdParser, Warning};
+
+/// How two bindings of a [`BindingConflict`] overlap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// Both bindings have the same definition in the same scope, so the
+    /// `first` one is overridden by the `second` one, declared later.
+    Overridden,
+    /// The `first` binding uses the `any` modifier on the same key as the
+    /// `second` one. It also fires on the chord of the `second` one, except
+    /// that the `second` one takes precedence while its modifiers are held.
+    AnyOverlap,
+    /// The `first` binding is at the top level and the `second` one has the
+    /// same definition in a mode, replacing it while the mode is active.
+    ModeOverridesTopLevel,
+}
+
+/// Two bindings that fire on the same chord, see [`SwhkdParser::conflicts`].
+/// Their spans tell where each of them was declared.
+#[derive(Debug, PartialEq, Eq)]
+pub struct BindingConflict<'a> {
+    pub kind: ConflictKind,
+    /// Name of the mode the `second` binding is in, or `None` for the top
+    /// level.
+    pub mode: Option<&'a str>,
+    pub first: &'a Binding,
+    pub second: &'a Binding,
+}
+
+/// Where `binding` was declared, as `file:line:column`.
+pub(crate) fn location(binding: &Binding) -> String {
+    binding
+        .span()
+        .map_or("<built in code>".to_string(), |span| {
+            format!("{}:{}:{}", span.file, span.line, span.column)
+        })
+}
+
+impl Display for BindingConflict<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.mode {
+            Some(mode) => write!(f, "in mode `{}`, ", mode)?,
+            None => write!(f, "at the top level, ")?,
+        }
+        let (first, second) = (self.first.chords_string(), self.second.chords_string());
+        let (at_first, at_second) = (location(self.first), location(self.second));
+        match self.kind {
+            ConflictKind::Overridden => write!(
+                f,
+                "`{second}` at {at_second} overrides the same chord at {at_first}"
+            ),
+            ConflictKind::AnyOverlap => write!(
+                f,
+                "`{first}` at {at_first} also fires on `{second}` at {at_second}, which takes precedence while its modifiers are held"
+            ),
+            ConflictKind::ModeOverridesTopLevel => write!(
+                f,
+                "`{second}` at {at_second} replaces the top-level binding at {at_first} while the mode is active"
+            ),
+        }
+    }
+}
+
+/// Bindings of one scope, top level or mode, that overlap each other.
+fn conflicts_in_scope<'a>(
+    mode: Option<&'a str>,
+    bindings: &'a [Binding],
+) -> Vec<BindingConflict<'a>> {
+    let mut conflicts = vec![];
+    for (index, first) in bindings.iter().enumerate() {
+        for second in &bindings[index + 1..] {
+            // The chords before the last one of sequences have to match.
+            let (a, b) = (first.last_definition(), second.last_definition());
+            if !first.same_prefix(second)
+                || a.key() != b.key()
+                || a.held_keys() != b.held_keys()
+                || first.device() != second.device()
+            {
+                continue;
+            }
+            let kind = if a == b {
+                ConflictKind::Overridden
+            } else if a.modifiers().contains(&Modifier::Any) {
+                ConflictKind::AnyOverlap
+            } else if b.modifiers().contains(&Modifier::Any) {
+                conflicts.push(BindingConflict {
+                    kind: ConflictKind::AnyOverlap,
+                    mode,
+                    first: second,
+                    second: first,
+                });
+                continue;
+            } else {
+                continue;
+            };
+            conflicts.push(BindingConflict {
+                kind,
+                mode,
+                first,
+                second,
+            });
+        }
+    }
+    conflicts
+}
+
+impl SwhkdParser {
+    /// Finds bindings that fire on the same chord: bindings overridden by a
+    /// later one, bindings using the `any` modifier next to a binding with
+    /// specific modifiers on the same key, and mode bindings replacing a
+    /// top-level one.
+    ///
+    /// ```
+    /// use sweet::{ConflictKind, ParserInput, SwhkdParser};
+    ///
+    /// let config = "
+    /// any + a
+    ///     one
+    /// super + a
+    ///     two
+    /// super + a
+    ///     three";
+    /// let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    /// let kinds: Vec<_> = parsed.conflicts().iter().map(|c| c.kind).collect();
+    /// assert_eq!(kinds, [ConflictKind::Overridden, ConflictKind::AnyOverlap]);
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn conflicts(&self) -> Vec<BindingConflict<'_>> {
+        let mut conflicts: Vec<_> = self
+            .overridden
+            .iter()
+            .filter_map(|first| {
+                let second = self
+                    .bindings
+                    .iter()
+                    .find(|binding| binding.same_trigger(first))?;
+                Some(BindingConflict {
+                    kind: ConflictKind::Overridden,
+                    mode: None,
+                    first,
+                    second,
+                })
+            })
+            .collect();
+        conflicts.extend(conflicts_in_scope(None, &self.bindings));
+        for mode in &self.modes {
+            conflicts.extend(conflicts_in_scope(Some(&mode.name), &mode.bindings));
+            for second in &mode.bindings {
+                conflicts.extend(
+                    self.bindings
+                        .iter()
+                        .filter(|first| first.same_trigger(second))
+                        .map(|first| BindingConflict {
+                            kind: ConflictKind::ModeOverridesTopLevel,
+                            mode: Some(&mode.name),
+                            first,
+                            second,
+                        }),
+                );
+            }
+        }
+        conflicts
+    }
+
+    /// Warns about `any` chords written differently that override one
+    /// another, see [`Warning::AnyCollision`]. Variants of one shorthand
+    /// colliding are written differently too, e.g. `any + {ctrl +, alt +} c`.
+    pub(crate) fn warn_about_any_collisions(&mut self) {
+        let mut pairs = vec![];
+        let top_level = any_bindings(&self.bindings);
+        for first in &self.overridden {
+            if let Some(&second) = top_level
+                .get(&(first.sequence(), first.device()))
+                .and_then(|indices| indices.first())
+            {
+                pairs.push((first, &self.bindings[second]));
+            }
+        }
+        let scopes =
+            std::iter::once(&self.bindings).chain(self.modes.iter().map(|mode| &mode.bindings));
+        for bindings in scopes {
+            let by_trigger = any_bindings(bindings);
+            for (index, first) in bindings.iter().enumerate() {
+                let Some(indices) = by_trigger.get(&(first.sequence(), first.device())) else {
+                    continue;
+                };
+                for &second in indices.iter().filter(|&&second| second > index) {
+                    pairs.push((first, &bindings[second]));
+                }
+            }
+        }
+        let warnings: Vec<_> = pairs
+            .into_iter()
+            .filter(|(first, second)| {
+                first.source_text() != second.source_text() || first.span() == second.span()
+            })
+            .filter_map(|(first, second)| {
+                Some(Warning::AnyCollision {
+                    first: first.span()?.clone(),
+                    second: second.span()?.clone(),
+                })
+            })
+            .collect();
+        self.warnings.extend(warnings);
+    }
+}
+
+/// Indices of the bindings whose last chord uses the `any` modifier, by
+/// sequence and device, so that the bindings of one entry override each other.
+fn any_bindings(bindings: &[Binding]) -> HashMap<(&[Definition], Option<&str>), Vec<usize>> {
+    let mut by_trigger: HashMap<_, Vec<usize>> = HashMap::new();
+    for (index, binding) in bindings.iter().enumerate() {
+        if binding
+            .last_definition()
+            .modifiers()
+            .contains(&Modifier::Any)
+        {
+            by_trigger
+                .entry((binding.sequence(), binding.device()))
+                .or_default()
+                .push(index);
+        }
+    }
+    by_trigger
+}
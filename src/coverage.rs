@@ -0,0 +1,92 @@
+//! Recording which rules of the grammar parsing visits, to find rules that
+//! no config exercises, enabled by the `grammar-coverage` feature.
+//!
+//! Every run of the grammar goes through [`crate::parse::run_grammar`],
+//! which hands the pairs it produced to [`record`]. Silent rules and rules
+//! only used in lookaheads produce no pairs, so they are never counted.
+
+use std::{cell::RefCell, collections::BTreeMap};
+
+use pest::iterators::Pairs;
+
+use crate::Rule;
+
+thread_local! {
+    /// Hits per rule while [`record_rules`] runs on this thread.
+    static HITS: RefCell<Option<BTreeMap<Rule, usize>>> = const { RefCell::new(None) };
+}
+
+/// How many times each rule of the grammar produced a pair, see
+/// [`record_rules`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleCoverage {
+    hits: BTreeMap<Rule, usize>,
+}
+
+impl RuleCoverage {
+    /// How many times `rule` produced a pair.
+    pub fn hits(&self, rule: Rule) -> usize {
+        self.hits.get(&rule).copied().unwrap_or_default()
+    }
+
+    /// Every rule of the grammar along with its hits, in grammar order.
+    pub fn rules(&self) -> impl Iterator<Item = (Rule, usize)> + '_ {
+        Rule::all_rules()
+            .iter()
+            .map(|rule| (*rule, self.hits(*rule)))
+    }
+
+    /// The rules that never produced a pair, in grammar order.
+    pub fn unreached(&self) -> impl Iterator<Item = Rule> + '_ {
+        self.rules()
+            .filter(|(_, hits)| *hits == 0)
+            .map(|(rule, _)| rule)
+    }
+
+    /// Adds the hits of `other`, e.g. to sum up the coverage of a corpus.
+    pub fn merge(&mut self, other: &RuleCoverage) {
+        for (rule, hits) in &other.hits {
+            *self.hits.entry(*rule).or_default() += hits;
+        }
+    }
+}
+
+/// Runs `f`, recording every rule the grammar visits on this thread in the
+/// meantime, whether parsing succeeds or fails later on. Recordings nest:
+/// an outer recording sees the rules of an inner one as well.
+///
+/// ```
+/// use sweet::{record_rules, ParserInput, Rule, SwhkdParser};
+///
+/// let (parsed, coverage) =
+///     record_rules(|| SwhkdParser::from(ParserInput::Raw("super + {a, b}\n    {firefox, kitty}")));
+/// assert!(parsed.is_ok());
+/// assert_eq!(coverage.hits(Rule::binding), 1);
+/// assert_eq!(coverage.hits(Rule::command_shorthand), 1);
+/// assert_eq!(coverage.hits(Rule::mode), 0);
+/// ```
+pub fn record_rules<R>(f: impl FnOnce() -> R) -> (R, RuleCoverage) {
+    let outer = HITS.with(|hits| hits.replace(Some(BTreeMap::new())));
+    let result = f();
+    let hits = HITS.with(|hits| hits.replace(outer)).unwrap_or_default();
+    let coverage = RuleCoverage { hits };
+    HITS.with(|hits| {
+        if let Some(outer) = hits.borrow_mut().as_mut() {
+            for (rule, count) in &coverage.hits {
+                *outer.entry(*rule).or_default() += count;
+            }
+        }
+    });
+    (result, coverage)
+}
+
+/// Counts the rules of every pair in `pairs` and below, if recording.
+pub(crate) fn record(pairs: Pairs<'_, Rule>) {
+    HITS.with(|hits| {
+        if let Some(hits) = hits.borrow_mut().as_mut() {
+            for pair in pairs.flatten() {
+                *hits.entry(pair.as_rule()).or_default() += 1;
+            }
+        }
+    });
+}
@@ -0,0 +1,69 @@
+use std::path::{Path, PathBuf};
+
+use crate::{ConfigReadError, ParseError, ParserInput, SwhkdParser};
+
+/// The built-in default config, used for swhkd's first-run experience
+/// when no user config exists.
+pub const DEFAULT_CONFIG: &str = include_str!("default.swhkd");
+
+/// What to do when none of the searched config paths exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FallbackPolicy {
+    /// Return a [`ConfigReadError::NotFound`] error.
+    #[default]
+    Error,
+    /// Parse [`DEFAULT_CONFIG`] instead.
+    BuiltinDefault,
+}
+
+/// Paths searched by [`SwhkdParser::from_default`], in order of preference.
+///
+/// These are `$XDG_CONFIG_HOME/swhkd/swhkdrc` (falling back to
+/// `$HOME/.config/swhkd/swhkdrc`) followed by `/etc/swhkd/swhkdrc`.
+pub fn default_config_paths() -> Vec<PathBuf> {
+    let mut paths = vec![];
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+    if let Some(config_home) = config_home {
+        paths.push(config_home.join("swhkd/swhkdrc"));
+    }
+    paths.push(PathBuf::from("/etc/swhkd/swhkdrc"));
+    paths
+}
+
+impl SwhkdParser {
+    /// Parses [`DEFAULT_CONFIG`].
+    ///
+    /// The default config is checked by the test suite, so this never fails.
+    pub fn default_bindings() -> SwhkdParser {
+        Self::from(ParserInput::Raw(DEFAULT_CONFIG))
+            .expect("the built-in default config must always parse")
+    }
+
+    /// Parses the first config found in [`default_config_paths`].
+    pub fn from_default(fallback: FallbackPolicy) -> Result<Self, ParseError> {
+        Self::from_first_existing(&default_config_paths(), fallback)
+    }
+
+    /// Parses the first of `paths` that exists, applying `fallback` if none do.
+    pub fn from_first_existing<P: AsRef<Path>>(
+        paths: &[P],
+        fallback: FallbackPolicy,
+    ) -> Result<Self, ParseError> {
+        if let Some(path) = paths.iter().map(AsRef::as_ref).find(|path| path.exists()) {
+            return Self::from(ParserInput::Path(path));
+        }
+        match fallback {
+            FallbackPolicy::BuiltinDefault => Ok(Self::default_bindings()),
+            FallbackPolicy::Error => Err(ConfigReadError::NotFound(
+                paths
+                    .iter()
+                    .map(|path| path.as_ref().to_path_buf())
+                    .collect(),
+            )
+            .into()),
+        }
+    }
+}
@@ -2,112 +2,709 @@ use itertools::Itertools;
 use pest::iterators::Pair;
 
 use crate::{
-    pair_to_string, parse_key,
-    range::Bounds,
-    token::{Key, KeyAttribute, Modifier},
-    KeyRepr, ModifierRepr, ParseError, Rule,
+    evdev_mappings,
+    expand::{self, Bounds},
+    parse::{pair_to_string, parse_key, run_grammar, spanned_error},
+    span::Spanned,
+    token::{Key, KeyAttribute, Modifier, ScrollDirection, SwitchType, Trigger},
+    variables, ModifierRepr, ParseError, Rule,
+};
+use pest::Span;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    ops::Range,
+    str::FromStr,
 };
-use std::{collections::BTreeSet, fmt::Display};
 
-#[derive(Debug, PartialEq, Eq)]
+/// The chord a binding fires on.
+///
+/// ```
+/// use sweet::{Definition, KeyAttribute, Modifier, Trigger};
+///
+/// let definition: Definition = "super + shift + @q".parse()?;
+/// assert!(definition.modifiers().contains(&Modifier::Super));
+/// assert!(definition.modifiers().contains(&Modifier::Shift));
+/// assert_eq!(definition.key().trigger, Trigger::Key(evdev::Key::KEY_Q));
+/// assert_eq!(definition.key().attribute, KeyAttribute::OnRelease);
+/// # Ok::<(), sweet::ParseError>(())
+/// ```
+///
+/// Every definition holds real modifiers only: the `_` placeholder of
+/// omission shorthands is dropped, and `any` stands alone since it already
+/// matches every set of modifiers.
+///
+/// A chord may also hold other keys, e.g. `ctrl + x & e` fires when `e` is
+/// pressed while Control and `x` are held, see
+/// [`ParseOptions::with_multi_key_chords`](crate::ParseOptions::with_multi_key_chords).
+/// [`Definition::key`] is always the key that fires, and the held keys are
+/// a set: `x & y & e` and `y & x & e` are the same definition, but
+/// `x & e` is not `e & x`.
+///
+/// Modifiers may carry attributes of their own, e.g. `@super + tab` for a
+/// binding that also waits on Super being released, see
+/// [`Definition::modifier_attributes`]. Most chords have none.
+///
+/// Modifiers and held keys being sets, definitions hash and compare the
+/// same however they were written, so they can key a `HashMap` or a
+/// `BTreeMap`. Definitions are ordered by their modifiers first, then by
+/// their key.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "crate::serialize::DefinitionParts")
+)]
 pub struct Definition {
-    pub modifiers: BTreeSet<Modifier>,
-    pub key: Key,
+    modifiers: BTreeSet<Modifier>,
+    key: Key,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            with = "crate::serialize::held_keys",
+            skip_serializing_if = "BTreeSet::is_empty"
+        )
+    )]
+    held: BTreeSet<evdev::Key>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            with = "crate::serialize::modifier_attributes",
+            skip_serializing_if = "BTreeMap::is_empty"
+        )
+    )]
+    modifier_attributes: BTreeMap<Modifier, KeyAttribute>,
 }
 
 impl Definition {
-    pub fn new(key: evdev::Key) -> Self {
+    /// The only way to build a definition, upholding the invariants above.
+    pub(crate) fn from_parts(modifiers: impl IntoIterator<Item = Modifier>, key: Key) -> Self {
+        let mut modifiers: BTreeSet<_> = modifiers
+            .into_iter()
+            .filter(|modifier| *modifier != Modifier::Omission)
+            .collect();
+        if modifiers.contains(&Modifier::Any) {
+            modifiers = BTreeSet::from([Modifier::Any]);
+        }
         Self {
-            modifiers: BTreeSet::default(),
-            key: Key::new(key, KeyAttribute::None),
+            modifiers,
+            key,
+            held: BTreeSet::new(),
+            modifier_attributes: BTreeMap::new(),
         }
     }
 
-    pub fn with_modifiers(mut self, modifiers: &[Modifier]) -> Self {
-        self.modifiers = modifiers.iter().cloned().collect();
+    pub fn new(key: evdev::Key) -> Self {
+        Self::from_parts([], Key::new(key, KeyAttribute::None))
+    }
+
+    pub fn modifiers(&self) -> &BTreeSet<Modifier> {
+        &self.modifiers
+    }
+
+    /// The key that fires the chord.
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    /// Keys that have to be held along with the modifiers, e.g. `x` in
+    /// `ctrl + x & e`. Empty for most chords.
+    pub fn held_keys(&self) -> &BTreeSet<evdev::Key> {
+        &self.held
+    }
+
+    /// Sets the keys held along with the modifiers. The key that fires is
+    /// never one of them.
+    pub fn with_held_keys(mut self, keys: impl IntoIterator<Item = evdev::Key>) -> Self {
+        self.held = keys
+            .into_iter()
+            .filter(|key| self.key.trigger != Trigger::Key(*key))
+            .collect();
         self
     }
+
+    /// The attributes of the modifiers that have any, e.g.
+    /// [`KeyAttribute::OnRelease`] for Super in `@super + tab`. Empty for
+    /// most chords.
+    ///
+    /// ```
+    /// use sweet::{Definition, KeyAttribute, Modifier};
+    ///
+    /// let definition: Definition = "@super + ~shift + tab".parse()?;
+    /// assert_eq!(definition.modifier_attribute(Modifier::Super), KeyAttribute::OnRelease);
+    /// assert_eq!(definition.modifier_attribute(Modifier::Shift), KeyAttribute::Send);
+    /// assert_eq!(definition.key().attribute, KeyAttribute::None);
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn modifier_attributes(&self) -> &BTreeMap<Modifier, KeyAttribute> {
+        &self.modifier_attributes
+    }
+
+    /// The attributes of `modifier`, [`KeyAttribute::None`] if it has none
+    /// or is not part of the chord.
+    pub fn modifier_attribute(&self, modifier: Modifier) -> KeyAttribute {
+        self.modifier_attributes
+            .get(&modifier)
+            .copied()
+            .unwrap_or(KeyAttribute::None)
+    }
+
+    /// Sets the attributes of one of the modifiers, e.g.
+    /// [`KeyAttribute::OnRelease`] for `@super`. Only `~` and `@` apply to
+    /// modifiers, and modifiers not in the chord, `any` and the modifiers
+    /// of modifier-only chords, which take the attributes of the chord, are
+    /// left alone.
+    pub fn with_modifier_attribute(mut self, modifier: Modifier, attribute: KeyAttribute) -> Self {
+        let attribute = attribute & KeyAttribute::Both;
+        if !self.modifiers.contains(&modifier)
+            || modifier == Modifier::Any
+            || self.is_modifiers_only()
+        {
+            return self;
+        }
+        if attribute == KeyAttribute::None {
+            self.modifier_attributes.remove(&modifier);
+        } else {
+            self.modifier_attributes.insert(modifier, attribute);
+        }
+        self
+    }
+
+    /// Takes the definition apart, e.g. to match on its trigger. Held keys
+    /// and the attributes of modifiers are left out, see
+    /// [`Definition::held_keys`] and [`Definition::modifier_attributes`].
+    ///
+    /// ```
+    /// use sweet::{Definition, Trigger};
+    ///
+    /// let definition: Definition = "super + a".parse()?;
+    /// let (modifiers, key) = definition.into_parts();
+    /// assert_eq!(modifiers.len(), 1);
+    /// assert!(matches!(key.trigger, Trigger::Key(evdev::Key::KEY_A)));
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn into_parts(self) -> (BTreeSet<Modifier>, Key) {
+        (self.modifiers, self.key)
+    }
+
+    /// A definition triggered by the consumer-defined virtual key `name`.
+    pub fn virtual_key<S: AsRef<str>>(name: S) -> Self {
+        Self::from_parts([], Key::virtual_key(name))
+    }
+
+    /// A definition triggered by `modifiers` alone, without any other key.
+    pub fn modifiers_only(modifiers: &[Modifier], attribute: KeyAttribute) -> Self {
+        Self::from_parts(modifiers.iter().cloned(), Key::modifiers_only(attribute))
+    }
+
+    pub fn is_modifiers_only(&self) -> bool {
+        self.key.trigger == Trigger::ModifiersOnly
+    }
+
+    /// A definition triggered by the scroll wheel turning in `direction`.
+    pub fn scroll(direction: ScrollDirection) -> Self {
+        Self::from_parts([], Key::scroll(direction))
+    }
+
+    pub fn is_scroll(&self) -> bool {
+        matches!(self.key.trigger, Trigger::Scroll(_))
+    }
+
+    pub fn is_virtual(&self) -> bool {
+        matches!(self.key.trigger, Trigger::Virtual(_))
+    }
+
+    /// A definition triggered by `switch` changing to `state`.
+    pub fn switch(switch: SwitchType, state: bool) -> Self {
+        Self::from_parts([], Key::switch(switch, state))
+    }
+
+    pub fn is_switch(&self) -> bool {
+        matches!(self.key.trigger, Trigger::Switch { .. })
+    }
+
+    /// Sets when the key fires and whether it is passed on, e.g.
+    /// [`KeyAttribute::OnRelease`] for `@a`.
+    pub fn with_attribute(mut self, attribute: KeyAttribute) -> Self {
+        self.key.attribute = attribute;
+        self
+    }
+
+    /// Whether an `ignore` of this chord removes a binding on `other`.
+    /// Beyond an exact match, the `any` modifier stands for every set of
+    /// modifiers, e.g. `ignore any + a` removes `a` and `super + a`, and
+    /// [`Trigger::AnyKey`] for every key along with its attributes and held
+    /// keys, e.g. `ignore super + any_key` removes `super + a` and
+    /// `super + @b`. Otherwise the key attributes have to match: `ignore
+    /// super + a` leaves `super + @a`, and so do the attributes of the
+    /// modifiers: it also leaves `@super + a`.
+    ///
+    /// ```
+    /// use sweet::Definition;
+    ///
+    /// let unbind: Definition = "any + a".parse()?;
+    /// assert!(unbind.unbinds(&"super + shift + a".parse()?));
+    /// assert!(!unbind.unbinds(&"super + b".parse()?));
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn unbinds(&self, other: &Definition) -> bool {
+        let modifiers = self.modifiers.contains(&Modifier::Any)
+            || (self.modifiers == other.modifiers
+                && self.modifier_attributes == other.modifier_attributes);
+        let key = self.key.trigger == Trigger::AnyKey
+            || (self.key == other.key && self.held == other.held);
+        modifiers && key
+    }
+
+    /// Whether [`Definition::unbinds`] matches more than one chord.
+    pub(crate) fn is_wildcard(&self) -> bool {
+        self.modifiers.contains(&Modifier::Any) || self.key.trigger == Trigger::AnyKey
+    }
+
+    /// Replaces the modifiers, keeping the invariants described on
+    /// [`Definition`]. Modifiers that stay keep their attributes.
+    pub fn with_modifiers(self, modifiers: &[Modifier]) -> Self {
+        let replaced =
+            Self::from_parts(modifiers.iter().cloned(), self.key).with_held_keys(self.held);
+        self.modifier_attributes
+            .into_iter()
+            .fold(replaced, |definition, (modifier, attribute)| {
+                definition.with_modifier_attribute(modifier, attribute)
+            })
+    }
+}
+
+/// Whether an `ignore` of the chords `unbind` removes a binding on
+/// `sequence`: each of its chords unbinds the matching chord of the
+/// sequence, see [`Definition::unbinds`], so a sequence is removed along
+/// with every longer one starting with it.
+pub(crate) fn unbinds_sequence(unbind: &[Definition], sequence: &[Definition]) -> bool {
+    unbind.len() <= sequence.len()
+        && unbind
+            .iter()
+            .zip(sequence)
+            .all(|(unbind, definition)| unbind.unbinds(definition))
 }
 
+/// Parses a single chord written in config syntax, e.g. `super + shift + a`.
+///
+/// Shorthands are accepted as long as they expand to exactly one definition.
+impl FromStr for Definition {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chord = run_grammar(Rule::chord, s)
+            .map_err(ParseError::Grammar)?
+            .next()
+            .ok_or(ParseError::MainSection)?;
+        let span = chord.as_span();
+        let mut uncompiled = DefinitionUncompiled::default();
+        for component in chord.into_inner() {
+            uncompiled.ingest(component)?;
+        }
+        let mut definitions = uncompiled.compile();
+        if definitions.len() != 1 {
+            return Err(spanned_error(
+                format!(
+                    "chord expands to {} definitions, expected exactly one",
+                    definitions.len()
+                ),
+                span,
+            ));
+        }
+        Ok(definitions.remove(0))
+    }
+}
+
+/// Config syntax, see [`Definition::to_config_string`].
 impl Display for Definition {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "[")?;
-        for modifier in self.modifiers.iter() {
-            write!(f, "{:?}, ", modifier)?;
+        f.write_str(&self.to_config_string())
+    }
+}
+
+/// Converts a key in a chord, rejecting attributes on scroll events. Also
+/// tells whether the key is a shifted symbol such as `exclam`, which
+/// implies Shift.
+fn key(component: Pair<'_, Rule>) -> Result<(Key, bool), ParseError> {
+    let span = component.as_span();
+    let repr = parse_key(component)?.value;
+    let attribute = repr.attribute;
+    let key: Key = match repr.try_into() {
+        Ok(key) => key,
+        Err(ParseError::InvalidKey(name)) => {
+            return match evdev_mappings::convert_shifted(&name) {
+                Some(key) => Ok((Key::new(key, attribute), true)),
+                None => Err(spanned_error(
+                    ParseError::InvalidKey(name).to_string(),
+                    span,
+                )),
+            };
+        }
+        Err(err) => return Err(err),
+    };
+    check_key(&key).map_err(|message| spanned_error(message, span))?;
+    Ok((key, false))
+}
+
+/// Converts a modifier in a chord along with its attributes, rejecting
+/// attributes on `any`.
+pub(crate) fn modifier(component: Pair<'_, Rule>) -> Result<(Modifier, KeyAttribute), ParseError> {
+    let span = component.as_span();
+    let mut attribute = KeyAttribute::None;
+    for inner in component.clone().into_inner() {
+        match inner.as_rule() {
+            Rule::send => attribute |= KeyAttribute::Send,
+            Rule::on_release => attribute |= KeyAttribute::OnRelease,
+            _ => {}
+        }
+    }
+    let name = pair_to_string(component);
+    let modifier = Modifier::try_from(ModifierRepr(
+        name.trim_start_matches(['~', '@']).to_lowercase(),
+    ))
+    .map_err(|err| spanned_error(err.to_string(), span))?;
+    if modifier == Modifier::Any && attribute != KeyAttribute::None {
+        return Err(spanned_error(
+            "`any` matches every set of modifiers, `~` and `@` cannot be used on it".to_string(),
+            span,
+        ));
+    }
+    Ok((modifier, attribute))
+}
+
+/// Checks a key of a chord on its own, failing with a message for keys a
+/// config cannot hold, e.g. a scroll event marked with `@`.
+pub(crate) fn check_key(key: &Key) -> Result<(), String> {
+    let attribute = key.attribute;
+    match &key.trigger {
+        Trigger::Key(_) | Trigger::AnyKey => {}
+        _ if attribute.contains(KeyAttribute::Passthrough) => {
+            return Err(
+                "`passthrough` can only be used on keys, e.g. `super + a passthrough`".to_string(),
+            )
+        }
+        _ => {}
+    }
+    match &key.trigger {
+        Trigger::Scroll(_) if attribute != KeyAttribute::None => Err(
+            "scroll events have no press or release, `@` and `~` cannot be used on them"
+                .to_string(),
+        ),
+        Trigger::Virtual(_) | Trigger::Switch { .. } if attribute != KeyAttribute::None => Err(
+            "`~`, `@` and `!` cannot be used on virtual keys and switches".to_string(),
+        ),
+        Trigger::Virtual(name)
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_')) =>
+        {
+            Err(format!(
+                "virtual key name `{name}` may only contain lowercase letters, digits and underscores"
+            ))
+        }
+        Trigger::ModifiersOnly
+            if attribute.contains(KeyAttribute::Solo)
+                && !attribute.contains(KeyAttribute::OnRelease) =>
+        {
+            Err("`!` can only be used on release, e.g. `@super!`".to_string())
+        }
+        Trigger::Key(_) if attribute.contains(KeyAttribute::Solo) => {
+            Err("`!` can only be used on modifier-only chords, e.g. `@super!`".to_string())
         }
-        write!(f, "{:?}", self.key)?;
-        write!(f, "]")
+        _ => Ok(()),
+    }
+}
+
+/// Checks that a chord on `trigger` may hold modifiers.
+pub(crate) fn check_modifiers_allowed(trigger: &Trigger) -> Result<(), String> {
+    match trigger {
+        Trigger::Virtual(_) => Err("modifiers cannot be combined with virtual keys".to_string()),
+        Trigger::Switch { .. } => Err("modifiers cannot be combined with switches".to_string()),
+        _ => Ok(()),
     }
 }
 
 #[derive(Default)]
 pub struct DefinitionUncompiled {
-    pub modifiers: Vec<Vec<Modifier>>,
+    /// The variants of each modifier slot of the chord, a variant holding
+    /// the modifiers it adds, e.g. `[[], [Ctrl], [Ctrl, Shift]]` for
+    /// `{_, ctrl, ctrl + shift}`.
+    pub modifiers: Vec<Vec<Vec<Modifier>>>,
+    /// The attributes of the modifiers of each variant of `modifiers`, e.g.
+    /// [`KeyAttribute::OnRelease`] for Shift in `{_, @shift +}`. Slots and
+    /// variants past its end have none.
+    modifier_attributes: Vec<Vec<BTreeMap<Modifier, KeyAttribute>>>,
     pub keys: Vec<Key>,
+    /// Indices of `keys` that are shifted symbols, see [`key`].
+    shifted: BTreeSet<usize>,
+    /// The keys before each `&`, along with whether they are shifted
+    /// symbols.
+    held: Vec<Vec<(evdev::Key, bool)>>,
+    /// Byte range each of `keys` is written at, the bounds for the keys of
+    /// a range, to report on a key once it turns out to be held.
+    written: Vec<Range<usize>>,
 }
 
 impl DefinitionUncompiled {
+    /// Adds a key along with whether it is a shifted symbol, see [`key`].
+    fn push_key(&mut self, key: Spanned<(Key, bool)>) {
+        let Spanned {
+            value: (key, shifted),
+            range,
+        } = key;
+        if shifted {
+            self.shifted.insert(self.keys.len());
+        }
+        self.keys.push(key);
+        self.written.push(range);
+    }
+
+    /// Adds a modifier slot, each variant holding modifiers along with
+    /// their attributes.
+    fn push_modifiers(&mut self, variants: Vec<Vec<(Modifier, KeyAttribute)>>) {
+        let attributes = variants
+            .iter()
+            .map(|variant| {
+                let mut attributes = BTreeMap::new();
+                for &(modifier, attribute) in variant {
+                    if attribute != KeyAttribute::None {
+                        *attributes.entry(modifier).or_insert(KeyAttribute::None) |= attribute;
+                    }
+                }
+                attributes
+            })
+            .collect();
+        self.modifier_attributes.push(attributes);
+        self.modifiers.push(
+            variants
+                .into_iter()
+                .map(|variant| variant.into_iter().map(|(modifier, _)| modifier).collect())
+                .collect(),
+        );
+    }
+
+    fn check_modifiers_allowed(&self, key: &Key, span: Span<'_>) -> Result<(), ParseError> {
+        if self.modifiers.is_empty() {
+            return Ok(());
+        }
+        check_modifiers_allowed(&key.trigger).map_err(|message| spanned_error(message, span))
+    }
+
     pub fn ingest(&mut self, component: Pair<'_, Rule>) -> Result<(), ParseError> {
         match component.as_rule() {
-            Rule::modifier => {
-                self.modifiers.push(vec![
-                    ModifierRepr(pair_to_string(component).to_lowercase()).into()
-                ])
-            }
-            Rule::modifier_shorthand | Rule::modifier_omit_shorthand => self.modifiers.push(
-                component
+            Rule::modifier => self.push_modifiers(vec![vec![modifier(component)?]]),
+            Rule::modifier_shorthand | Rule::modifier_omit_shorthand => {
+                let variants = component
                     .into_inner()
-                    .map(|component| ModifierRepr(pair_to_string(component)).into())
-                    .collect(),
-            ),
+                    .map(|variant| match variant.as_rule() {
+                        Rule::modifier_group => variant.into_inner().map(modifier).collect(),
+                        _ => Ok(vec![modifier(variant)?]),
+                    })
+                    .collect::<Result<_, _>>()?;
+                self.push_modifiers(variants);
+            }
             Rule::shorthand => {
+                let input = component.get_input();
+                let mut elements = vec![];
+                // Attributes before the braces, e.g. `~{1-3}`, apply to
+                // every key and come first.
+                let mut shared = KeyAttribute::None;
                 for shorthand_component in component.into_inner() {
+                    let span = shorthand_component.as_span();
                     match shorthand_component.as_rule() {
+                        Rule::send => shared |= KeyAttribute::Send,
+                        Rule::on_release => shared |= KeyAttribute::OnRelease,
                         Rule::key_in_shorthand => {
-                            self.keys.push(parse_key(shorthand_component).try_into()?)
+                            elements.push(Spanned::new(key(shorthand_component)?, span))
                         }
-                        Rule::key_range => {
-                            let (lower_bound, upper_bound) =
-                                Bounds::new(shorthand_component).expand_keys()?;
-                            let keys = (lower_bound..=upper_bound)
-                                .map(|key| {
-                                    KeyRepr {
-                                        key: key.to_string(),
-                                        attribute: KeyAttribute::None,
-                                    }
-                                    .try_into()
-                                })
-                                .collect::<Result<Vec<Key>, ParseError>>()?;
-                            self.keys.extend(keys);
+                        Rule::key_range => elements.extend(
+                            Bounds::new(shorthand_component)?
+                                .expand_keys()?
+                                .into_iter()
+                                .map(|key| Spanned::new((key, false), span)),
+                        ),
+                        _ => {}
+                    }
+                }
+                for mut element in elements {
+                    element.value.0.attribute |= shared;
+                    check_key(&element.value.0).map_err(|message| element.error(message, input))?;
+                    self.push_key(element);
+                }
+            }
+            Rule::key_normal => {
+                let span = component.as_span();
+                self.push_key(Spanned::new(key(component)?, span));
+            }
+            Rule::key_and => self.hold_keys(component)?,
+            // Marks every key the chord fires on, e.g. all of `{1-3}`.
+            Rule::passthrough => {
+                let span = component.as_span();
+                for key in &mut self.keys {
+                    key.attribute |= KeyAttribute::Passthrough;
+                    check_key(key).map_err(|message| spanned_error(message, span))?;
+                }
+            }
+            Rule::any_key => {
+                let span = component.as_span();
+                let key = Key {
+                    trigger: Trigger::AnyKey,
+                    attribute: KeyAttribute::None,
+                };
+                self.push_key(Spanned::new((key, false), span));
+            }
+            // Variables are substituted before, see `variables::with_chord`.
+            Rule::variable => return Err(variables::undefined(&component)),
+            Rule::modifier_only => {
+                let span = component.as_span();
+                let attributed = self
+                    .modifier_attributes
+                    .iter()
+                    .flatten()
+                    .any(|attributes| !attributes.is_empty());
+                if attributed {
+                    return Err(spanned_error(
+                        "modifier-only chords take `~` and `@` on their last modifier alone, e.g. `shift + @super`"
+                            .to_string(),
+                        span,
+                    ));
+                }
+                let mut attribute = KeyAttribute::None;
+                for inner in component.into_inner() {
+                    match inner.as_rule() {
+                        Rule::send => attribute |= KeyAttribute::Send,
+                        Rule::on_release => attribute |= KeyAttribute::OnRelease,
+                        Rule::solo => attribute |= KeyAttribute::Solo,
+                        Rule::modifier => {
+                            let (modifier, _) = modifier(inner)?;
+                            self.modifiers.push(vec![vec![modifier]]);
                         }
                         _ => {}
                     }
                 }
+                let key = Key::modifiers_only(attribute);
+                check_key(&key).map_err(|message| spanned_error(message, span))?;
+                self.push_key(Spanned::new((key, false), span));
+            }
+            Rule::virtual_key => {
+                let span = component.as_span();
+                for name in component
+                    .into_inner()
+                    .flat_map(|inner| match inner.as_rule() {
+                        Rule::virtual_shorthand => inner.into_inner().collect_vec(),
+                        _ => vec![inner],
+                    })
+                {
+                    let key = Key::virtual_key(name.as_str());
+                    self.check_modifiers_allowed(&key, span)?;
+                    check_key(&key).map_err(|message| spanned_error(message, name.as_span()))?;
+                    self.push_key(Spanned::new((key, false), name.as_span()));
+                }
+            }
+            Rule::switch_event => {
+                let span = component.as_span();
+                for name in component
+                    .into_inner()
+                    .flat_map(|inner| match inner.as_rule() {
+                        Rule::switch_shorthand => inner.into_inner().collect_vec(),
+                        _ => vec![inner],
+                    })
+                {
+                    let Some((switch, state)) =
+                        SwitchType::from_event_name(&name.as_str().to_lowercase())
+                    else {
+                        return Err(spanned_error(
+                            format!(
+                                "unknown switch event `{}`, expected one of {}",
+                                name.as_str(),
+                                SwitchType::event_names().join(", ")
+                            ),
+                            name.as_span(),
+                        ));
+                    };
+                    let key = Key::switch(switch, state);
+                    self.check_modifiers_allowed(&key, span)?;
+                    self.push_key(Spanned::new((key, false), name.as_span()));
+                }
             }
-            Rule::key_normal => self.keys.push(parse_key(component).try_into()?),
             _ => {}
         };
         Ok(())
     }
 
-    pub fn compile(self) -> Vec<Definition> {
-        if self.modifiers.is_empty() {
-            return self
-                .keys
-                .into_iter()
-                .map(|key| Definition {
-                    modifiers: BTreeSet::default(),
-                    key,
-                })
-                .collect();
+    /// Turns the keys seen so far into held keys at an `&`, reporting on
+    /// the key at fault rather than on all of them.
+    fn hold_keys(&mut self, and: Pair<'_, Rule>) -> Result<(), ParseError> {
+        let input = and.get_input();
+        let shifted = std::mem::take(&mut self.shifted);
+        let written = std::mem::take(&mut self.written);
+        let mut held = vec![];
+        for ((index, key), range) in std::mem::take(&mut self.keys)
+            .into_iter()
+            .enumerate()
+            .zip(written)
+        {
+            let key = Spanned { value: key, range };
+            let Trigger::Key(code) = key.value.trigger else {
+                return Err(key.error(
+                    "scroll events cannot be held, only keys can come before `&`".to_string(),
+                    input,
+                ));
+            };
+            if key.value.attribute != KeyAttribute::None {
+                return Err(key.error(
+                    "held keys cannot have attributes, `~` and `@` apply to the key after the last `&`"
+                        .to_string(),
+                    input,
+                ));
+            }
+            held.push((code, shifted.contains(&index)));
         }
-        self.modifiers
+        self.held.push(held);
+        Ok(())
+    }
+
+    /// Expands the chord into every combination of its modifiers and keys,
+    /// the modifiers varying slowest and the key that fires fastest.
+    pub fn compile(self) -> Vec<Definition> {
+        let shifted = self.shifted;
+        let implied = |index: usize| shifted.contains(&index).then_some(Modifier::Shift);
+        let keys = self.keys.into_iter().enumerate();
+        let mut attributes = self.modifier_attributes.into_iter();
+        let slots = self
+            .modifiers
+            .into_iter()
+            .map(|variants| {
+                let mut attributes = attributes.next().unwrap_or_default().into_iter();
+                variants
+                    .into_iter()
+                    .map(|variant| (variant, attributes.next().unwrap_or_default()))
+                    .collect()
+            })
+            .collect();
+        expand::combinations(slots)
             .into_iter()
-            .multi_cartesian_product()
-            .cartesian_product(self.keys)
-            .map(|(modifiers, key)| Definition {
-                modifiers: modifiers.into_iter().collect(),
-                key,
+            .cartesian_product(expand::combinations(self.held))
+            .cartesian_product(keys)
+            .map(|((variants, held), (index, key))| {
+                let held_shifted = held.iter().any(|(_, shifted)| *shifted);
+                let (modifiers, attributes): (Vec<_>, Vec<_>) = variants.into_iter().unzip();
+                let modifiers = modifiers
+                    .into_iter()
+                    .flatten()
+                    .chain(implied(index))
+                    .chain(held_shifted.then_some(Modifier::Shift));
+                let definition = Definition::from_parts(modifiers, key)
+                    .with_held_keys(held.into_iter().map(|(key, _)| key));
+                attributes.into_iter().flatten().fold(
+                    definition,
+                    |definition, (modifier, attribute)| {
+                        let attribute = definition.modifier_attribute(modifier) | attribute;
+                        definition.with_modifier_attribute(modifier, attribute)
+                    },
+                )
             })
             .collect()
     }
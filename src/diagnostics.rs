@@ -0,0 +1,236 @@
+use pest::{
+    error::{Error, ErrorVariant, InputLocation},
+    Span,
+};
+
+use crate::{
+    homoglyphs::{is_declaration_line, Homoglyph},
+    Rule,
+};
+
+/// Replaces the generic "expected ..." error pest reports when a config
+/// ends in the middle of a declaration with one naming what was left
+/// unterminated.
+pub(crate) fn explain_eof(err: Error<Rule>, raw: &str) -> Error<Rule> {
+    let (InputLocation::Pos(pos), ErrorVariant::ParsingError { positives, .. }) =
+        (&err.location, &err.variant)
+    else {
+        return err;
+    };
+    if !raw[*pos..].trim().is_empty() {
+        return err;
+    }
+
+    let custom = |message: String, start: usize, end: usize| {
+        // Safety: both ends are taken from line boundaries of `raw`.
+        let span = Span::new(raw, start, end).unwrap();
+        Error::new_from_span(ErrorVariant::CustomError { message }, span)
+    };
+    let line_start = |pos: usize| raw[..pos].rfind('\n').map_or(0, |i| i + 1);
+
+    if positives.contains(&Rule::command) {
+        let start = line_start(*pos);
+        let line = &raw[start..*pos];
+        let chord = line.trim();
+        // A chord continued from earlier lines, the last one left empty.
+        if chord.is_empty() {
+            return err;
+        }
+        return custom(
+            format!("key definition `{chord}` at end of file has no command"),
+            start + line.len() - line.trim_start().len(),
+            start + line.trim_end().len(),
+        );
+    }
+    // Inside a mode block only bindings, unbinds and settings are expected,
+    // and a last command without a newline is cut short by the missing
+    // `endmode`.
+    let in_mode = positives.contains(&Rule::unbind) && !positives.contains(&Rule::import);
+    let after_command = !is_declaration_line(&raw[line_start(*pos)..]);
+    if in_mode || after_command {
+        if let Some((start, header)) = unterminated_mode(&raw[..*pos]) {
+            return custom(
+                format!("unterminated mode block, `{header}` is never closed with `endmode`"),
+                start,
+                start + header.len(),
+            );
+        }
+    }
+    err
+}
+
+/// The offset and header of the last mode block of `raw` not closed with
+/// `endmode`, e.g. `mode resize oneoff`.
+fn unterminated_mode(raw: &str) -> Option<(usize, &str)> {
+    let mut open = None;
+    let mut offset = 0;
+    for line in raw.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        if !is_declaration_line(line) {
+            continue;
+        }
+        let declaration = match line.strip_prefix('[') {
+            Some(rest) => rest.split_once(']').map_or(rest, |(_, rest)| rest),
+            None => line,
+        }
+        .trim_start();
+        let word = declaration.split_whitespace().next();
+        match word {
+            Some("mode") => {
+                let header = declaration.lines().next().unwrap_or_default().trim_end();
+                open = Some((start + line.len() - declaration.len(), header));
+            }
+            Some("endmode") => open = None,
+            _ => {}
+        }
+    }
+    open
+}
+
+/// Points grammar errors at a Unicode lookalike of an ASCII character on
+/// the offending line, such as a non-breaking space or an en dash pasted
+/// from a web page. Commands are left alone, any text is valid there.
+pub(crate) fn explain_homoglyphs(err: Error<Rule>, raw: &str) -> Error<Rule> {
+    let pos = match (&err.location, &err.variant) {
+        (_, ErrorVariant::CustomError { .. }) => return err,
+        (InputLocation::Pos(pos), _) | (InputLocation::Span((pos, _)), _) => *pos,
+    };
+    let line_start = raw[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = raw[pos..].find('\n').map_or(raw.len(), |i| pos + i);
+    let line = &raw[line_start..line_end];
+    if !is_declaration_line(line) {
+        return err;
+    }
+    // The lookalike closest to where the parser gave up.
+    let Some((offset, glyph)) = line
+        .char_indices()
+        .filter_map(|(i, c)| Some((line_start + i, Homoglyph::lookup(c)?)))
+        .min_by_key(|(offset, _)| offset.abs_diff(pos))
+    else {
+        return err;
+    };
+    // Safety: `offset` is the start of a character of `raw`.
+    let span = Span::new(raw, offset, offset + glyph.character.len_utf8()).unwrap();
+    Error::new_from_span(
+        ErrorVariant::CustomError {
+            message: glyph.hint(),
+        },
+        span,
+    )
+}
+
+/// Points grammar errors at misplaced braces on the offending line: a
+/// shorthand group opened inside another one, more than one level deep in
+/// commands, or a group never closed. pest gives up somewhere after the
+/// brace with a generic message.
+pub(crate) fn explain_braces(err: Error<Rule>, raw: &str) -> Error<Rule> {
+    let pos = match (&err.location, &err.variant) {
+        (_, ErrorVariant::CustomError { .. }) => return err,
+        (InputLocation::Pos(pos), _) | (InputLocation::Span((pos, _)), _) => *pos,
+    };
+    // The whole line, along with the lines it is continued on with a
+    // trailing `\`, so that a brace closed on a continued line is closed.
+    let mut line_start = raw[..pos].rfind('\n').map_or(0, |i| i + 1);
+    while line_start > 0 && raw[..line_start - 1].ends_with('\\') {
+        line_start = raw[..line_start - 1].rfind('\n').map_or(0, |i| i + 1);
+    }
+    let mut line_end = raw[pos..].find('\n').map_or(raw.len(), |i| pos + i);
+    while line_end < raw.len() && raw[..line_end].ends_with('\\') {
+        line_end = raw[line_end + 1..]
+            .find('\n')
+            .map_or(raw.len(), |i| line_end + 1 + i);
+    }
+    let line = &raw[line_start..line_end];
+    // Only declarations have comments, commands may contain `#`.
+    let has_comments = is_declaration_line(line);
+
+    let custom = |message: String, offset: usize| {
+        // Safety: `offset` is the position of a brace on the line.
+        let span = Span::new(raw, line_start + offset, line_start + offset + 1).unwrap();
+        Error::new_from_span(ErrorVariant::CustomError { message }, span)
+    };
+    let mut open = vec![];
+    let mut chars = line.char_indices();
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '#' if has_comments && open.is_empty() => break,
+            // Indented lines are commands, or the chords of a mode which
+            // pest gives up on at the nested brace.
+            '{' if open.len() == 1 && (has_comments || line_start + offset == pos) => {
+                return custom(
+                    "nested shorthand groups are not supported; write the variants in a single group"
+                        .to_string(),
+                    offset,
+                )
+            }
+            '{' if open.len() > 1 => return custom(
+                "shorthand groups in commands nest one level deep at most; write the variants in fewer groups"
+                    .to_string(),
+                offset,
+            ),
+            '{' => open.push(offset),
+            // A stray closing brace, pest already points at it.
+            '}' if open.is_empty() => return err,
+            '}' => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+    match open.last().copied() {
+        Some(offset) => {
+            let before = &line[..offset];
+            let column = before[before.rfind('\n').map_or(0, |i| i + 1)..]
+                .chars()
+                .count()
+                + 1;
+            custom(format!("unclosed '{{' opened at column {column}"), offset)
+        }
+        None => err,
+    }
+}
+
+/// Points grammar errors at an `any_key` wildcard written where it does not
+/// apply: outside of an `ignore` statement, with attributes, in a shorthand
+/// or in a sequence. pest gives up within the wildcard, taking `any` for a
+/// modifier, or after it.
+pub(crate) fn explain_any_key(err: Error<Rule>, raw: &str) -> Error<Rule> {
+    const ANY_KEY: &str = "any_key";
+    let pos = match (&err.location, &err.variant) {
+        (_, ErrorVariant::CustomError { .. }) => return err,
+        (InputLocation::Pos(pos), _) | (InputLocation::Span((pos, _)), _) => *pos,
+    };
+    let line_start = raw[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = raw[pos..].find('\n').map_or(raw.len(), |i| pos + i);
+    let line = raw[line_start..line_end].to_ascii_lowercase();
+    let unbind = line.trim_start().starts_with("ignore ");
+    let word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let Some(offset) = line.match_indices(ANY_KEY).map(|(i, _)| i).find(|&i| {
+        // Commands may mention `any_key`, the chords of modes are only
+        // looked at when pest gave up on the wildcard itself.
+        let within = (line_start + i..=line_start + i + ANY_KEY.len()).contains(&pos);
+        (is_declaration_line(&line) || unbind || within)
+            && !line[..i].ends_with(word)
+            && !line[i + ANY_KEY.len()..].starts_with(word)
+    }) else {
+        return err;
+    };
+    let message = if unbind {
+        "`any_key` stands for every key, it cannot have attributes or be part of a shorthand or a sequence"
+    } else {
+        "`any_key` only applies to `ignore` statements, e.g. `ignore super + any_key`"
+    };
+    let start = line_start + offset;
+    // Safety: the wildcard is ASCII, its ends are character boundaries.
+    let span = Span::new(raw, start, start + ANY_KEY.len()).unwrap();
+    Error::new_from_span(
+        ErrorVariant::CustomError {
+            message: message.to_string(),
+        },
+        span,
+    )
+}
@@ -0,0 +1,470 @@
+//! Printing parsed configs for other tools and for people, see
+//! [`SwhkdParser::dump`], and back as configs, see
+//! [`SwhkdParser::to_config_string`].
+
+use std::fmt::Write;
+
+use crate::{
+    evdev_mappings,
+    report::{json_optional, json_string},
+    style::key_name,
+    Binding, Definition, Mode, ModeInstruction, ModifierInfo, Settings, SourceSpan, SwhkdParser,
+    Trigger,
+};
+
+/// How [`SwhkdParser::dump`] prints a config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DumpFormat {
+    /// One line per item, see [`SwhkdParser::dump`].
+    #[default]
+    Text,
+    /// A single JSON object, see [`SwhkdParser::dump`].
+    Json,
+}
+
+/// What [`SwhkdParser::dump_with_options`] adds to a dump.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DumpOptions {
+    /// Print the settings each binding runs with, see
+    /// [`SwhkdParser::effective_settings`]. Off by default, since they
+    /// repeat the settings of the config for every binding.
+    pub effective_settings: bool,
+}
+
+impl SwhkdParser {
+    /// Prints the parsed config in `format`, e.g. to check what a config
+    /// declares from scripts. Both formats are stable: later versions only
+    /// add to them.
+    ///
+    /// The text format has one line per item, except for bindings running
+    /// several command lines which span as many lines, in this order:
+    ///
+    /// - `Binding <chords> (written `<source>`) → <command> (mode
+    ///   instructions: [...])` for every top-level binding, see the
+    ///   [`Display`](std::fmt::Display) implementation of [`Binding`];
+    /// - `unbind: <chords>` for every top-level unbind, sequences of
+    ///   several chords separated by ` ; `;
+    /// - `import: "<id>"` for every included file;
+    /// - `set <name> <value>` for every top-level setting, then
+    ///   `[device=<name>] set <name> <value>` for every device setting;
+    /// - `mode: <name>`, followed by ` oneoff` and ` swallow` when they
+    ///   apply, for every mode, then its bindings, unbinds, `replay:
+    ///   <chord>` keys and settings as above, prefixed with `mode <name>: `.
+    ///
+    /// The JSON format is an object with the `bindings`, `unbinds`,
+    /// `sequence_unbinds`, `modes`, `settings`, `imports` and `warnings`
+    /// of the config. Chords are objects with the canonical names of their
+    /// `modifiers`, their `key`, `null` for modifier-only chords, the
+    /// names of their key `attributes`, their `held` keys and the
+    /// `modifier_attributes` of modifiers that have any, e.g.
+    /// `{"super":["OnRelease"]}` for `@super + tab`.
+    ///
+    /// ```
+    /// use sweet::{DumpFormat, ParserInput, SwhkdParser};
+    ///
+    /// let parsed = SwhkdParser::from(ParserInput::Raw("super + @Return\n    foot"))?;
+    /// assert_eq!(
+    ///     parsed.dump(DumpFormat::Json),
+    ///     concat!(
+    ///         r#"{"bindings":[{"chords":[{"modifiers":["super"],"key":"return","attributes":["OnRelease"],"held":[],"#,
+    ///         r#""modifier_attributes":{}}],"#,
+    ///         r#""command":"foot","instructions_before_command":[],"instructions_after_command":[],"#,
+    ///         r#""device":null,"schedule":null,"span":{"file":"<anonymous>","line":1,"column":1}}],"#,
+    ///         r#""unbinds":[],"sequence_unbinds":[],"modes":[],"#,
+    ///         r#""settings":{"shell":null,"throttle_default_ms":null,"start_mode":null},"#,
+    ///         r#""imports":[],"warnings":[]}"#,
+    ///     )
+    /// );
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn dump(&self, format: DumpFormat) -> String {
+        self.dump_with_options(format, DumpOptions::default())
+    }
+
+    /// Like [`SwhkdParser::dump`], with the additions of `options`.
+    ///
+    /// With [`DumpOptions::effective_settings`], the text format has an
+    /// `effective: set <name> <value>` line for every setting a binding
+    /// runs with, after the binding, and every binding of the JSON format
+    /// has `effective_settings` in the form of `settings`.
+    ///
+    /// ```
+    /// use sweet::{DumpFormat, DumpOptions, ParserInput, SwhkdParser};
+    ///
+    /// let config = "
+    /// set throttle_default 50ms
+    /// [set shell=/bin/bash] super + a
+    ///     foot";
+    /// let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    /// let options = DumpOptions {
+    ///     effective_settings: true,
+    /// };
+    /// let dump = parsed.dump_with_options(DumpFormat::Text, options);
+    /// assert!(dump.contains("effective: set shell /bin/bash\neffective: set throttle_default 50ms\n"));
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn dump_with_options(&self, format: DumpFormat, options: DumpOptions) -> String {
+        let effective = |binding: &Binding| {
+            options
+                .effective_settings
+                .then(|| self.effective_settings(binding))
+        };
+        match format {
+            DumpFormat::Text => self.dump_text(&effective),
+            DumpFormat::Json => self.dump_json(&effective),
+        }
+    }
+
+    fn dump_text(&self, effective: &dyn Fn(&Binding) -> Option<Settings>) -> String {
+        let mut out = String::new();
+        write_scope(
+            &mut out,
+            "",
+            &self.bindings,
+            &self.unbinds,
+            &self.sequence_unbinds,
+            effective,
+        );
+        for import in &self.imports {
+            let _ = writeln!(out, "import: {import:?}");
+        }
+        for statement in self.settings.to_statements() {
+            let _ = writeln!(out, "{statement}");
+        }
+        for statement in self.device_setting_statements() {
+            let _ = writeln!(out, "{statement}");
+        }
+        for mode in &self.modes {
+            let _ = write!(out, "mode: {}", mode.name);
+            if mode.oneoff {
+                out.push_str(" oneoff");
+            }
+            if mode.swallow {
+                out.push_str(" swallow");
+            }
+            out.push('\n');
+            let prefix = format!("mode {}: ", mode.name);
+            write_scope(
+                &mut out,
+                &prefix,
+                &mode.bindings,
+                &mode.unbinds,
+                &mode.sequence_unbinds,
+                effective,
+            );
+            for replay in &mode.replay {
+                let _ = writeln!(out, "{prefix}replay: {replay}");
+            }
+            for statement in mode.settings.to_statements() {
+                let _ = writeln!(out, "{prefix}{statement}");
+            }
+        }
+        out
+    }
+
+    fn dump_json(&self, effective: &dyn Fn(&Binding) -> Option<Settings>) -> String {
+        let binding_json = |binding| binding_json(binding, effective(binding));
+        let modes = self.modes.iter().map(|mode| {
+            format!(
+                r#"{{"name":{},"oneoff":{},"swallow":{},"bindings":{},"unbinds":{},"sequence_unbinds":{},"replay":{},"settings":{}}}"#,
+                json_string(&mode.name),
+                mode.oneoff,
+                mode.swallow,
+                json_array(mode.bindings.iter().map(binding_json)),
+                json_array(mode.unbinds.iter().map(chord_json)),
+                json_array(mode.sequence_unbinds.iter().map(|sequence| sequence_json(sequence))),
+                json_array(mode.replay.iter().map(chord_json)),
+                settings_json(&mode.settings),
+            )
+        });
+        format!(
+            r#"{{"bindings":{},"unbinds":{},"sequence_unbinds":{},"modes":{},"settings":{},"imports":{},"warnings":{}}}"#,
+            json_array(self.bindings.iter().map(binding_json)),
+            json_array(self.unbinds.iter().map(chord_json)),
+            json_array(
+                self.sequence_unbinds
+                    .iter()
+                    .map(|sequence| sequence_json(sequence))
+            ),
+            json_array(modes),
+            settings_json(&self.settings),
+            json_array(self.imports.iter().map(|import| json_string(import))),
+            json_array(
+                self.warnings
+                    .iter()
+                    .map(|warning| json_string(&warning.to_string()))
+            ),
+        )
+    }
+}
+
+impl SwhkdParser {
+    /// Renders the parsed config as a config in a canonical layout: the
+    /// included files as comments, since their declarations are already
+    /// part of the config, then the settings, the bindings, the `ignore`
+    /// statements and the modes, each chord in the form of
+    /// [`Definition::to_config_string`] and each command indented by four
+    /// spaces. Pass the result to [`reindent`](crate::reindent) for another
+    /// indentation.
+    ///
+    /// Variables and blocks are not kept, their values are already
+    /// substituted, and every variant of a shorthand is a binding of its
+    /// own. Comments are only kept as the descriptions of bindings and
+    /// modes, see [`Binding::description`]. Parsing the result gives back the same bindings,
+    /// unbinds, modes and settings, as long as no command holds braces,
+    /// see [`Binding::to_config_string`].
+    ///
+    /// ```
+    /// use sweet::{ParserInput, SwhkdParser};
+    ///
+    /// let config = "
+    /// ## launchers
+    /// Super + {a, b}
+    ///   {firefox, kitty}
+    /// mode resize oneoff
+    ///   ignore Super+B
+    ///   Escape
+    ///     @escape
+    /// endmode";
+    /// let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    /// let emitted = parsed.to_config_string();
+    /// assert_eq!(
+    ///     emitted,
+    ///     "# launchers\nsuper + a\n    firefox\n\n# launchers\nsuper + b\n    kitty\n\n\
+    ///      mode resize oneoff\n    escape\n        @escape\n    ignore super + b\nendmode\n"
+    /// );
+    /// let reparsed = SwhkdParser::from(ParserInput::Raw(&emitted))?;
+    /// assert_eq!(reparsed.bindings, parsed.bindings);
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn to_config_string(&self) -> String {
+        let mut sections = vec![];
+        if !self.imports.is_empty() {
+            let imports = self
+                .imports
+                .iter()
+                .map(|import| format!("# included: {import}\n"));
+            sections.push(imports.collect::<String>());
+        }
+        let mut settings = self.settings.to_statements();
+        settings.extend(self.device_setting_statements());
+        if !settings.is_empty() {
+            sections.push(settings.iter().map(|line| format!("{line}\n")).collect());
+        }
+        for binding in &self.bindings {
+            sections.push(format!("{}\n", binding.to_config_string()));
+        }
+        let unbinds = unbind_statements(&self.unbinds, &self.sequence_unbinds);
+        if !unbinds.is_empty() {
+            sections.push(unbinds.iter().map(|line| format!("{line}\n")).collect());
+        }
+        for mode in &self.modes {
+            sections.push(mode.to_config_string());
+        }
+        // A blank line ends the command of a binding, and separates the
+        // other sections for readability.
+        sections.join("\n")
+    }
+
+    /// The `[device=name] set` statements of every device setting.
+    fn device_setting_statements(&self) -> Vec<String> {
+        self.device_settings
+            .iter()
+            .flat_map(|(device, settings)| {
+                let statements = settings.to_statements();
+                statements
+                    .into_iter()
+                    .map(move |statement| format!("[device={device}] {statement}"))
+            })
+            .collect()
+    }
+}
+
+impl Mode {
+    /// Renders the mode as a `mode` block in the layout of
+    /// [`SwhkdParser::to_config_string`], after its description as a
+    /// comment: its bindings, then its `ignore`, `replay` and `set`
+    /// statements.
+    pub fn to_config_string(&self) -> String {
+        let mut out = String::new();
+        for line in self
+            .description
+            .iter()
+            .flat_map(|description| description.lines())
+        {
+            let _ = writeln!(out, "# {line}");
+        }
+        let _ = write!(out, "mode {}", self.name);
+        if self.oneoff {
+            out.push_str(" oneoff");
+        }
+        if self.swallow {
+            out.push_str(" swallow");
+        }
+        out.push('\n');
+        let mut lines: Vec<String> = vec![];
+        for binding in &self.bindings {
+            lines.extend(binding.to_config_string().lines().map(str::to_string));
+        }
+        lines.extend(unbind_statements(&self.unbinds, &self.sequence_unbinds));
+        lines.extend(self.replay.iter().map(|replay| format!("replay {replay}")));
+        lines.extend(self.settings.to_statements());
+        // The grammar requires a statement, a comment will do.
+        if lines.is_empty() {
+            lines.push("# no bindings".to_string());
+        }
+        for line in lines {
+            let _ = writeln!(out, "    {line}");
+        }
+        out.push_str("endmode\n");
+        out
+    }
+}
+
+/// The `ignore` statements of a scope.
+fn unbind_statements(unbinds: &[Definition], sequence_unbinds: &[Vec<Definition>]) -> Vec<String> {
+    let sequences = sequence_unbinds.iter().map(|sequence| {
+        let chords: Vec<_> = sequence.iter().map(ToString::to_string).collect();
+        chords.join(" ; ")
+    });
+    unbinds
+        .iter()
+        .map(ToString::to_string)
+        .chain(sequences)
+        .map(|chords| format!("ignore {chords}"))
+        .collect()
+}
+
+/// Writes the text lines of the bindings and unbinds of a scope, each
+/// line starting with `prefix`, and the `effective` settings of each
+/// binding when there are any.
+fn write_scope(
+    out: &mut String,
+    prefix: &str,
+    bindings: &[Binding],
+    unbinds: &[Definition],
+    sequence_unbinds: &[Vec<Definition>],
+    effective: &dyn Fn(&Binding) -> Option<Settings>,
+) {
+    for binding in bindings {
+        let _ = writeln!(out, "{prefix}{binding}");
+        for statement in effective(binding).iter().flat_map(Settings::to_statements) {
+            let _ = writeln!(out, "{prefix}effective: {statement}");
+        }
+    }
+    for unbind in unbinds {
+        let _ = writeln!(out, "{prefix}unbind: {unbind}");
+    }
+    for sequence in sequence_unbinds {
+        let chords: Vec<_> = sequence.iter().map(ToString::to_string).collect();
+        let _ = writeln!(out, "{prefix}unbind: {}", chords.join(" ; "));
+    }
+}
+
+fn json_array(items: impl Iterator<Item = String>) -> String {
+    format!("[{}]", items.collect::<Vec<_>>().join(","))
+}
+
+fn chord_json(definition: &Definition) -> String {
+    let key = definition.key();
+    let modifiers = definition
+        .modifiers()
+        .iter()
+        .map(|modifier| json_string(ModifierInfo::of(*modifier).canonical));
+    let name = match key.trigger {
+        Trigger::ModifiersOnly => "null".to_string(),
+        _ => json_string(&key_name(key)),
+    };
+    let attributes = key
+        .attribute
+        .iter_names()
+        .map(|(name, _)| json_string(name));
+    let held = definition.held_keys().iter().map(|key| {
+        json_string(
+            &evdev_mappings::key_name(*key)
+                .map_or_else(|| evdev_mappings::key_code_name(*key), str::to_string),
+        )
+    });
+    let modifier_attributes = definition
+        .modifier_attributes()
+        .iter()
+        .map(|(modifier, attribute)| {
+            let names = attribute.iter_names().map(|(name, _)| json_string(name));
+            format!(
+                "{}:{}",
+                json_string(ModifierInfo::of(*modifier).canonical),
+                json_array(names)
+            )
+        })
+        .collect::<Vec<_>>();
+    format!(
+        r#"{{"modifiers":{},"key":{},"attributes":{},"held":{},"modifier_attributes":{{{}}}}}"#,
+        json_array(modifiers),
+        name,
+        json_array(attributes),
+        json_array(held),
+        modifier_attributes.join(","),
+    )
+}
+
+fn sequence_json(sequence: &[Definition]) -> String {
+    json_array(sequence.iter().map(chord_json))
+}
+
+fn instruction_json(instruction: &ModeInstruction) -> String {
+    match instruction {
+        ModeInstruction::Enter(mode) => format!(r#"{{"enter":{}}}"#, json_string(mode)),
+        ModeInstruction::Escape => r#"{"escape":null}"#.to_string(),
+    }
+}
+
+fn span_json(span: &SourceSpan) -> String {
+    format!(
+        r#"{{"file":{},"line":{},"column":{}}}"#,
+        json_string(&span.file),
+        span.line,
+        span.column
+    )
+}
+
+/// The JSON object of `binding`, with its `effective` settings if given.
+fn binding_json(binding: &Binding, effective: Option<Settings>) -> String {
+    let effective = effective.map_or(String::new(), |settings| {
+        format!(r#","effective_settings":{}"#, settings_json(&settings))
+    });
+    format!(
+        r#"{{"chords":{},"command":{},"instructions_before_command":{},"instructions_after_command":{},"device":{},"schedule":{},"span":{}{}}}"#,
+        sequence_json(binding.sequence()),
+        json_string(binding.command()),
+        json_array(
+            binding
+                .instructions_before_command()
+                .iter()
+                .map(instruction_json)
+        ),
+        json_array(
+            binding
+                .instructions_after_command()
+                .iter()
+                .map(instruction_json)
+        ),
+        json_optional(binding.device()),
+        json_optional(binding.schedule().map(ToString::to_string).as_deref()),
+        binding.span().map_or("null".to_string(), span_json),
+        effective,
+    )
+}
+
+fn settings_json(settings: &Settings) -> String {
+    let throttle = settings
+        .throttle_default
+        .map_or("null".to_string(), |duration| {
+            duration.as_millis().to_string()
+        });
+    format!(
+        r#"{{"shell":{},"throttle_default_ms":{},"start_mode":{}}}"#,
+        json_optional(settings.shell.as_deref()),
+        throttle,
+        json_optional(settings.start_mode.as_deref()),
+    )
+}
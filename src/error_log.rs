@@ -0,0 +1,166 @@
+//! Bounding the errors collected by [`SwhkdParser::from_all_errors`], so
+//! that a generated config broken on every line neither fills memory with
+//! errors nor renders megabytes of diagnostics.
+//!
+//! [`SwhkdParser::from_all_errors`]: crate::SwhkdParser::from_all_errors
+
+use std::sync::Arc;
+
+use crate::{Diagnostic, ParseError, ParseOutcome, RenderStyle};
+
+/// The errors of the declarations skipped so far: errors with the same
+/// message on consecutive lines are collapsed into one
+/// [`ParseError::Repeated`], and past
+/// [`ParseOptions::max_errors`](crate::ParseOptions::max_errors) errors are
+/// only counted.
+#[derive(Debug, Default)]
+pub(crate) struct ErrorLog {
+    errors: Vec<ParseError>,
+    omitted: usize,
+    /// The most errors kept, 0 for no limit.
+    max: usize,
+}
+
+impl ErrorLog {
+    pub(crate) fn new(max: usize) -> Self {
+        Self {
+            max,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn push(&mut self, err: ParseError) {
+        if let Some(last) = self.errors.last_mut() {
+            if collapse(last, &err) {
+                return;
+            }
+        }
+        if self.max != 0 && self.errors.len() >= self.max {
+            self.omitted += 1;
+            return;
+        }
+        self.errors.push(err);
+    }
+
+    /// The errors kept, followed by `fatal`, the error that ended parsing
+    /// if any, and by a [`ParseError::MoreErrors`] if errors were left out.
+    pub(crate) fn finish(mut self, fatal: Option<ParseError>) -> Vec<ParseError> {
+        self.errors.extend(fatal);
+        if self.omitted != 0 {
+            self.errors.push(ParseError::MoreErrors(self.omitted));
+        }
+        self.errors
+    }
+}
+
+/// What makes errors the same: the file they are in and what they say,
+/// along with the line of `err`. `None` for errors pointing nowhere, which
+/// are never collapsed.
+fn identity(err: &ParseError) -> Option<(Arc<str>, String, usize)> {
+    let diagnostic = Diagnostic::from(err);
+    let span = &diagnostic.labels.first()?.span;
+    Some((span.file.clone(), summary(&diagnostic), span.line))
+}
+
+/// The message of `diagnostic`, or the note of its first label when it has
+/// none, e.g. `expected command` for a grammar error.
+fn summary(diagnostic: &Diagnostic) -> String {
+    let note = diagnostic.labels.first().map(|label| label.note.as_str());
+    match (diagnostic.message.as_str(), note) {
+        ("unable to parse config file" | "", Some(note)) => note.to_string(),
+        (message, _) => message.to_string(),
+    }
+}
+
+/// Folds `err` into `last` if both say the same in the same file and `err`
+/// is on the line after the last of `last`, telling whether it did.
+fn collapse(last: &mut ParseError, err: &ParseError) -> bool {
+    let Some((file, message, line)) = identity(err) else {
+        return false;
+    };
+    let first = match &*last {
+        ParseError::Repeated { error, .. } => &**error,
+        first => first,
+    };
+    let Some((first_file, first_message, first_line)) = identity(first) else {
+        return false;
+    };
+    let previous_line = match &*last {
+        ParseError::Repeated { last_line, .. } => *last_line,
+        _ => first_line,
+    };
+    if (first_file, first_message) != (file, message) || line != previous_line + 1 {
+        return false;
+    }
+    match last {
+        ParseError::Repeated {
+            count, last_line, ..
+        } => {
+            *count += 1;
+            *last_line = line;
+        }
+        _ => {
+            let first = std::mem::replace(last, ParseError::MoreErrors(0));
+            *last = ParseError::Repeated {
+                error: Box::new(first),
+                count: 2,
+                first_line,
+                last_line: line,
+            };
+        }
+    }
+    true
+}
+
+/// The message of a [`ParseError::Repeated`], e.g. `lines 100-250:
+/// expected command (×151)`.
+pub(crate) fn repeated_note(
+    error: &ParseError,
+    count: usize,
+    first_line: usize,
+    last_line: usize,
+) -> String {
+    format!(
+        "lines {first_line}-{last_line}: {} (×{count})",
+        summary(&Diagnostic::from(error))
+    )
+}
+
+impl ParseOutcome {
+    /// Renders every error as a [`Diagnostic`], one per line, as long
+    /// as the report stays within
+    /// [`ParseOptions::max_report_bytes`](crate::ParseOptions::max_report_bytes).
+    /// The first error is always rendered, and a last line tells how many
+    /// errors were left out past the limit.
+    ///
+    /// ```
+    /// use sweet::{ParseOptions, ParserInput, RenderStyle, SwhkdParser};
+    ///
+    /// let config: String = (0..100).map(|i| format!("super + key_nope{i}\n    true\n")).collect();
+    /// let options = ParseOptions::default().with_max_report_bytes(1024);
+    /// let outcome = SwhkdParser::from_all_errors_with_options(ParserInput::Raw(&config), &options);
+    /// let report = outcome.render(RenderStyle::Plain);
+    /// assert!(report.ends_with("more errors not shown, the report is limited to 1024 bytes"));
+    /// ```
+    pub fn render(&self, style: RenderStyle) -> String {
+        let mut report = String::new();
+        for (index, err) in self.errors.iter().enumerate() {
+            let rendered = Diagnostic::from(err).render(style);
+            if index != 0 {
+                let within = self.max_report_bytes == 0
+                    || report.len() + 1 + rendered.len() <= self.max_report_bytes;
+                if !within {
+                    report.push_str(&format!(
+                        "\n... {} more errors not shown, the report is limited to {} bytes",
+                        self.errors.len() - index,
+                        self.max_report_bytes
+                    ));
+                    break;
+                }
+                report.push('\n');
+            }
+            report.push_str(&rendered);
+        }
+        report
+    }
+}
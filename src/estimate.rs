@@ -0,0 +1,192 @@
+//! Counting the bindings a config expands to without expanding it, e.g. to
+//! warn in an editor before a change creates thousands of bindings.
+
+use pest::{iterators::Pair, Span};
+
+use crate::{
+    expand::Bounds,
+    parse::parse_main,
+    span::LineIndex,
+    variables::{self, Variables},
+    ParseError, Rule, SourceSpan,
+};
+
+/// How many bindings the declarations of a config expand to, see
+/// [`estimate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Estimate {
+    /// Every binding declaration in source order, including the ones in
+    /// modes.
+    pub declarations: Vec<DeclarationEstimate>,
+    /// The sum of the variants of every declaration.
+    pub total: usize,
+}
+
+/// A binding declaration along with the number of bindings it expands to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeclarationEstimate {
+    /// The chord of the declaration, the same as the
+    /// [`Binding::span`](crate::Binding::span) of its bindings.
+    pub span: SourceSpan,
+    /// The mode the declaration is in, `None` at the top level.
+    pub mode: Option<String>,
+    /// The number of bindings the declaration expands to, saturating at
+    /// `usize::MAX`.
+    pub variants: usize,
+}
+
+impl Estimate {
+    /// The declarations expanding to more than `limit` bindings each.
+    pub fn exceeding(&self, limit: usize) -> impl Iterator<Item = &DeclarationEstimate> {
+        self.declarations
+            .iter()
+            .filter(move |declaration| declaration.variants > limit)
+    }
+}
+
+/// Counts the bindings every declaration of `input` expands to from the
+/// sizes of its shorthands and ranges, without building any binding.
+///
+/// Only the grammar and the bounds of ranges are checked, so a config
+/// passing the estimate may still fail to parse, e.g. on an unknown key.
+/// For configs that parse, the counts are exact. Includes are not read, so
+/// only variables defined in `input` are known, and declarations count
+/// regardless of their profiles.
+///
+/// ```
+/// let estimate = sweet::estimate("super + {_, shift +} {1-9}\n    bspc {desktop,node} '^{1-9}'")?;
+/// assert_eq!(estimate.total, 18);
+/// assert_eq!(estimate.declarations[0].span.line, 1);
+/// assert_eq!(estimate.exceeding(16).count(), 1);
+/// # Ok::<(), sweet::ParseError>(())
+/// ```
+pub fn estimate(input: &str) -> Result<Estimate, ParseError> {
+    let source = "<anonymous>";
+    let contents = parse_main(input, source)?;
+    let lines = LineIndex::new(input);
+    let variables: Variables = contents
+        .clone()
+        .into_inner()
+        .filter(|decl| decl.as_rule() == Rule::define)
+        .map(variables::define_parser)
+        .collect();
+    let mut declarations = vec![];
+    for decl in contents.into_inner() {
+        match decl.as_rule() {
+            Rule::binding => {
+                declarations.push(declaration(decl, source, &lines, None, &variables)?)
+            }
+            Rule::mode => {
+                let mut name = None;
+                for component in decl.into_inner() {
+                    match component.as_rule() {
+                        Rule::modename => name = Some(component.as_str().to_string()),
+                        Rule::binding => declarations.push(declaration(
+                            component,
+                            source,
+                            &lines,
+                            name.clone(),
+                            &variables,
+                        )?),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let total = declarations.iter().fold(0usize, |total, declaration| {
+        total.saturating_add(declaration.variants)
+    });
+    Ok(Estimate {
+        declarations,
+        total,
+    })
+}
+
+fn declaration(
+    binding: Pair<'_, Rule>,
+    source: &str,
+    lines: &LineIndex,
+    mode: Option<String>,
+    variables: &Variables,
+) -> Result<DeclarationEstimate, ParseError> {
+    let input = binding.get_input();
+    // Spanned like in `binding_parser`, from the first to the last
+    // component of the chord.
+    let mut trigger_start = None;
+    let mut trigger_end = 0;
+    let mut trigger = vec![];
+    for component in binding.into_inner() {
+        match component.as_rule() {
+            Rule::command
+            | Rule::command_inline
+            | Rule::schedule_tag
+            | Rule::device_tag
+            | Rule::setting_tag
+            | Rule::profile_tag
+            | Rule::maybe_some_lines_of_comments => {}
+            _ => {
+                let span = component.as_span();
+                trigger_start.get_or_insert(span.start());
+                trigger_end = span.end();
+                trigger.push(component);
+            }
+        }
+    }
+    // Safety: the grammar guarantees a chord in every binding, and both
+    // ends are boundaries of its components.
+    let span = Span::new(input, trigger_start.unwrap(), trigger_end).unwrap();
+    Ok(DeclarationEstimate {
+        span: lines.span(source, span),
+        mode,
+        variants: variables::with_chord(trigger, variables, &mut Default::default(), variants)?,
+    })
+}
+
+/// The number of definitions the components of a sequence expand to, the
+/// product of the numbers of its chords.
+fn variants(components: Vec<Pair<'_, Rule>>) -> Result<usize, ParseError> {
+    components
+        .split(|component| component.as_rule() == Rule::sequence_then)
+        .try_fold(1usize, |total, chord| {
+            Ok(total.saturating_mul(chord_variants(chord)?))
+        })
+}
+
+/// The number of definitions the components of a chord expand to.
+fn chord_variants(components: &[Pair<'_, Rule>]) -> Result<usize, ParseError> {
+    let mut modifiers = 1usize;
+    // The variants of the keys before each `&`, and of the keys since.
+    let mut held = 1usize;
+    let mut keys = 0usize;
+    for component in components {
+        match component.as_rule() {
+            Rule::modifier_shorthand | Rule::modifier_omit_shorthand => {
+                modifiers = modifiers.saturating_mul(component.clone().into_inner().count())
+            }
+            Rule::shorthand => {
+                for key in component.clone().into_inner() {
+                    keys = keys.saturating_add(match key.as_rule() {
+                        Rule::key_range => Bounds::new(key)?.count_keys()?,
+                        Rule::key_in_shorthand => 1,
+                        // Attributes of the whole shorthand.
+                        _ => 0,
+                    });
+                }
+            }
+            // A shorthand of names, or a single name.
+            Rule::virtual_key | Rule::switch_event => {
+                keys += component
+                    .clone()
+                    .into_inner()
+                    .map(|inner| inner.into_inner().count().max(1))
+                    .sum::<usize>()
+            }
+            Rule::key_normal | Rule::modifier_only => keys += 1,
+            Rule::key_and => held = held.saturating_mul(std::mem::take(&mut keys)),
+            _ => {}
+        }
+    }
+    Ok(modifiers.saturating_mul(held).saturating_mul(keys))
+}
@@ -1,6 +1,14 @@
-use phf::phf_map;
+//! Key names as written in configs and the evdev keys they stand for.
+//!
+//! Besides the curated names below, every key can be written with its
+//! kernel name, e.g. `key_macro1`, looked up in a table generated from
+//! `input-event-codes.h`.
+
+use std::{cmp::Reverse, collections::HashMap, sync::OnceLock};
+
+use crate::{keys_generated::KERNEL_KEYS, ParseError};
 use evdev::Key;
-use crate::ParseError;
+use phf::phf_map;
 
 // Perfect Hash Function static map (loads at compile time)
 static KEY_MAP: phf::Map<&'static str, Key> = phf_map! {
@@ -68,6 +76,15 @@ static KEY_MAP: phf::Map<&'static str, Key> = phf_map! {
     "kpminus" => Key::KEY_KPMINUS,
     "kpplusminus" => Key::KEY_KPPLUSMINUS,
     "kprightparen" => Key::KEY_KPRIGHTPAREN,
+    "kpplus" => Key::KEY_KPPLUS,
+    "kpslash" => Key::KEY_KPSLASH,
+    "kp_enter" => Key::KEY_KPENTER,
+    "kp_plus" => Key::KEY_KPPLUS,
+    "kp_minus" => Key::KEY_KPMINUS,
+    "kp_asterisk" => Key::KEY_KPASTERISK,
+    "kp_slash" => Key::KEY_KPSLASH,
+    "kp_dot" => Key::KEY_KPDOT,
+    "numlock" => Key::KEY_NUMLOCK,
     "minus" => Key::KEY_MINUS,
     "-" => Key::KEY_MINUS,
     "equal" => Key::KEY_EQUAL,
@@ -92,13 +109,22 @@ static KEY_MAP: phf::Map<&'static str, Key> = phf_map! {
     "xf86audioprev" => Key::KEY_PREVIOUSSONG,
     "xf86audiostop" => Key::KEY_STOP,
     "xf86monbrightnessdown" => Key::KEY_BRIGHTNESSDOWN,
+    "playpause" => Key::KEY_PLAYPAUSE,
+    "nextsong" => Key::KEY_NEXTSONG,
+    "previoussong" => Key::KEY_PREVIOUSSONG,
+    "stopcd" => Key::KEY_STOPCD,
+    "xf86search" => Key::KEY_SEARCH,
+    "xf86explorer" => Key::KEY_FILE,
+    "xf86calculator" => Key::KEY_CALC,
+    "sleep" => Key::KEY_SLEEP,
+    "wakeup" => Key::KEY_WAKEUP,
+    "power" => Key::KEY_POWER,
     "," => Key::KEY_COMMA,
     "comma" => Key::KEY_COMMA,
     "." => Key::KEY_DOT,
     "dot" => Key::KEY_DOT,
     "period" => Key::KEY_DOT,
     "/" => Key::KEY_SLASH,
-    "question" => Key::KEY_QUESTION,
     "slash" => Key::KEY_SLASH,
     "backslash" => Key::KEY_BACKSLASH,
     "\\" => Key::KEY_BACKSLASH,
@@ -150,11 +176,191 @@ static KEY_MAP: phf::Map<&'static str, Key> = phf_map! {
     "f22" => Key::KEY_F22,
     "f23" => Key::KEY_F23,
     "f24" => Key::KEY_F24,
+    // Mouse buttons, `btn_side` and `btn_extra` being the usual thumb buttons.
+    "btn_left" => Key::BTN_LEFT,
+    "btn_right" => Key::BTN_RIGHT,
+    "btn_middle" => Key::BTN_MIDDLE,
+    "btn_side" => Key::BTN_SIDE,
+    "btn_extra" => Key::BTN_EXTRA,
+    "btn_forward" => Key::BTN_FORWARD,
+    "btn_back" => Key::BTN_BACK,
 };
 
+/// X11 keysym names of the symbols typed with Shift on a US layout, mapped
+/// to the key typed along with Shift. A chord on one of them, e.g.
+/// `super + exclam`, holds Shift as well.
+static SHIFTED_KEY_MAP: phf::Map<&'static str, Key> = phf_map! {
+    "exclam" => Key::KEY_1,
+    "at" => Key::KEY_2,
+    "numbersign" => Key::KEY_3,
+    "dollar" => Key::KEY_4,
+    "percent" => Key::KEY_5,
+    "asciicircum" => Key::KEY_6,
+    "ampersand" => Key::KEY_7,
+    "asterisk" => Key::KEY_8,
+    "parenleft" => Key::KEY_9,
+    "parenright" => Key::KEY_0,
+    "underscore" => Key::KEY_MINUS,
+    "question" => Key::KEY_SLASH,
+    "colon" => Key::KEY_SEMICOLON,
+    "quotedbl" => Key::KEY_APOSTROPHE,
+    "braceleft" => Key::KEY_LEFTBRACE,
+    "braceright" => Key::KEY_RIGHTBRACE,
+    "bar" => Key::KEY_BACKSLASH,
+    "asciitilde" => Key::KEY_GRAVE,
+    "less" => Key::KEY_COMMA,
+    "greater" => Key::KEY_DOT,
+};
+
+/// The evdev key named `s` in a config, either by its curated name or its
+/// kernel name. Names are lowercase.
+///
+/// ```
+/// use sweet::evdev_mappings::{convert, name_of};
+///
+/// let key = convert("enter")?;
+/// assert_eq!(key, evdev::Key::KEY_ENTER);
+/// assert_eq!(name_of(key), Some("return"));
+/// # Ok::<(), sweet::ParseError>(())
+/// ```
 pub fn convert(s: &str) -> Result<Key, ParseError> {
     KEY_MAP
         .get(s)
         .copied()
+        .or_else(|| KERNEL_KEYS.get(s).map(|&code| Key::new(code)))
+        .or_else(|| key_code(s).and_then(Result::ok))
         .ok_or_else(|| ParseError::InvalidKey(s.to_string()))
 }
+
+/// The highest key code, `KEY_MAX` in `input-event-codes.h`.
+pub const KEY_MAX: u16 = 0x2ff;
+
+/// The key written as `keycode(N)`, with `N` in decimal or in hexadecimal
+/// after `0x`, or `None` if `s` is not written like that. Fails with a
+/// message for codes above [`KEY_MAX`].
+pub(crate) fn key_code(s: &str) -> Option<Result<Key, String>> {
+    let code = s.strip_prefix("keycode(")?.strip_suffix(')')?;
+    let parsed = match code.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => code.parse(),
+    };
+    Some(match parsed {
+        Ok(parsed) if parsed <= KEY_MAX => Ok(Key::new(parsed)),
+        _ => Err(format!(
+            "`{code}` is not a key code, they range from 0 to {KEY_MAX}"
+        )),
+    })
+}
+
+/// `keycode(N)`, the config name of a key without a name of its own.
+pub(crate) fn key_code_name(key: Key) -> String {
+    format!("keycode({})", key.code())
+}
+
+/// The key typed along with Shift for the shifted symbol named `s`, e.g.
+/// `KEY_1` for `exclam`. Consulted when [`convert`] does not know `s`.
+///
+/// ```
+/// use sweet::evdev_mappings::{convert_shifted, shifted_name_of};
+///
+/// assert_eq!(convert_shifted("question"), Some(evdev::Key::KEY_SLASH));
+/// assert_eq!(shifted_name_of(evdev::Key::KEY_SLASH), Some("question"));
+/// ```
+pub fn convert_shifted(s: &str) -> Option<Key> {
+    SHIFTED_KEY_MAP.get(s).copied()
+}
+
+/// The name of the symbol typed with Shift and `key`, the reverse of
+/// [`convert_shifted`].
+pub fn shifted_name_of(key: Key) -> Option<&'static str> {
+    SHIFTED_KEY_MAP
+        .entries()
+        .find(|(_, value)| **value == key)
+        .map(|(name, _)| *name)
+}
+
+/// Every shifted symbol name along with the key typed with Shift for it.
+pub fn shifted_key_names() -> impl Iterator<Item = (&'static str, Key)> {
+    SHIFTED_KEY_MAP.entries().map(|(name, key)| (*name, *key))
+}
+
+/// The evdev key named `name` in a config, ignoring case. Unlike
+/// [`convert`], this is meant for names that did not come from a config,
+/// e.g. to validate a key name in another tool.
+///
+/// ```
+/// assert_eq!(sweet::lookup_key("Return"), Some(evdev::Key::KEY_ENTER));
+/// assert_eq!(sweet::lookup_key("hyper"), None);
+/// ```
+pub fn lookup_key(name: &str) -> Option<Key> {
+    convert(&name.to_lowercase()).ok()
+}
+
+/// Every key name accepted in a config, curated names first and then the
+/// kernel names, in no particular order within each. Shifted symbol names
+/// are listed by [`shifted_key_names`].
+pub fn supported_key_names() -> impl Iterator<Item = &'static str> {
+    KEY_MAP.keys().copied().chain(
+        KERNEL_KEYS
+            .keys()
+            .copied()
+            .filter(|name| !KEY_MAP.contains_key(name)),
+    )
+}
+
+/// How well `name` reads when rendering a key, lower is better: spelled
+/// out names over symbols, plain names over `xf86` ones, and the longest
+/// of the rest, e.g. `period` over `dot`.
+fn rank(name: &str) -> (bool, bool, Reverse<usize>, &str) {
+    (
+        !name.chars().all(|c| c.is_ascii_alphanumeric()),
+        name.starts_with("xf86"),
+        Reverse(name.len()),
+        name,
+    )
+}
+
+/// The best name of every key, built on first use.
+fn canonical_names() -> &'static HashMap<Key, &'static str> {
+    static NAMES: OnceLock<HashMap<Key, &'static str>> = OnceLock::new();
+    NAMES.get_or_init(|| {
+        let mut names = HashMap::new();
+        for (name, code) in KERNEL_KEYS.entries() {
+            names
+                .entry(Key::new(*code))
+                .and_modify(|best: &mut &str| *best = (*best).min(*name))
+                .or_insert(*name);
+        }
+        let mut curated: HashMap<Key, &'static str> = HashMap::new();
+        for (name, key) in KEY_MAP.entries() {
+            curated
+                .entry(*key)
+                .and_modify(|best| {
+                    if rank(name) < rank(best) {
+                        *best = name;
+                    }
+                })
+                .or_insert(*name);
+        }
+        names.extend(curated);
+        names
+    })
+}
+
+/// The canonical config name of `key`, the reverse of [`convert`]. Keys
+/// with several names get the longest spelled out one, e.g. `return`
+/// rather than `enter` and `comma` rather than `,`, and keys without a
+/// curated name get their kernel name.
+///
+/// ```
+/// assert_eq!(sweet::key_name(evdev::Key::KEY_DOT), Some("period"));
+/// assert_eq!(sweet::key_name(evdev::Key::new(0x290)), Some("key_macro1"));
+/// ```
+pub fn key_name(key: Key) -> Option<&'static str> {
+    canonical_names().get(&key).copied()
+}
+
+/// The config name of `key`, same as [`key_name`].
+pub fn name_of(key: Key) -> Option<&'static str> {
+    key_name(key)
+}
@@ -0,0 +1,342 @@
+//! Expansion of shorthands and ranges, shared by the chords of bindings,
+//! `ignore` and `replay` statements and by command shorthands, so that
+//! escapes and ranges mean the same wherever they are written.
+
+use itertools::Itertools;
+use pest::{iterators::Pair, Span};
+
+use crate::{
+    parse::{parse_key, spanned_error},
+    span::Spanned,
+    Key, KeyAttribute, KeyRepr, ParseError, Rule,
+};
+
+/// Characters that may be escaped in key position.
+/// The grammar rejects a backslash before any other character.
+pub const KEY_ESCAPES: &[char] = &['\\', ',', '{', '}', '-', '~', '@', '+', '='];
+
+/// Characters that may be escaped in command shorthands.
+pub const COMMAND_ESCAPES: &[char] = &['\\', '{', '}', '|', '-', '+', '~', '@', ','];
+
+/// Unescapes a string that has been escaped using backslashes
+/// but only for the given charset of characters that were allowed to
+/// be escaped in the grammar in the first place. Any other escape
+/// sequence is kept verbatim so that commands reach the shell unchanged.
+///
+/// ```
+/// use sweet::{unescape, COMMAND_ESCAPES};
+///
+/// assert_eq!(
+///     unescape(r"hello\\\{\}\|\-\+\~\@\,\n", COMMAND_ESCAPES),
+///     r"hello\{}|-+~@,\n"
+/// );
+/// ```
+pub fn unescape(s: &str, escapable: &[char]) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut was_a_slash = false;
+    for char in s.chars() {
+        if std::mem::take(&mut was_a_slash) {
+            if !escapable.contains(&char) {
+                unescaped.push('\\');
+            }
+            unescaped.push(char);
+        } else if char == '\\' {
+            was_a_slash = true;
+        } else {
+            unescaped.push(char);
+        }
+    }
+    if was_a_slash {
+        unescaped.push('\\');
+    }
+    unescaped
+}
+
+/// Escapes text to be read back as a single variant of a command
+/// shorthand, the inverse of unescaping it with [`COMMAND_ESCAPES`]. A
+/// lone `_` is escaped so that it is not read as an omission.
+pub(crate) fn escape_variant(variant: &str) -> String {
+    if variant.trim() == "_" {
+        return variant.replace('_', "\\_");
+    }
+    let mut escaped = String::with_capacity(variant.len());
+    for c in variant.chars() {
+        if matches!(c, '\\' | '{' | '}' | ',' | '-') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Every combination of one variant of each group, in declaration order,
+/// e.g. the modifiers of `{super, alt} + {shift, ctrl} + a` or the pieces of
+/// a command line. Without any group there is a single, empty combination.
+pub(crate) fn combinations<T: Clone>(groups: Vec<Vec<T>>) -> Vec<Vec<T>> {
+    if groups.is_empty() {
+        return vec![vec![]];
+    }
+    groups.into_iter().multi_cartesian_product().collect()
+}
+
+/// The combinations of one variant of each group in the order of
+/// [`combinations`], built one at a time so that large products are never
+/// held in memory.
+#[derive(Debug, Clone)]
+pub(crate) struct Product<T> {
+    groups: Vec<Vec<T>>,
+}
+
+impl<T> Product<T> {
+    pub(crate) fn new(groups: Vec<Vec<T>>) -> Self {
+        Self { groups }
+    }
+
+    /// The number of combinations, computed from the sizes of the groups
+    /// and saturating at `usize::MAX`.
+    pub(crate) fn len(&self) -> usize {
+        self.groups
+            .iter()
+            .fold(1usize, |len, group| len.saturating_mul(group.len()))
+    }
+
+    /// The combination at `index`, less than [`Product::len`]. The first
+    /// group varies slowest.
+    pub(crate) fn get(&self, mut index: usize) -> Vec<&T> {
+        let mut combination = Vec::with_capacity(self.groups.len());
+        for group in self.groups.iter().rev() {
+            combination.push(&group[index % group.len()]);
+            index /= group.len();
+        }
+        combination.reverse();
+        combination
+    }
+}
+
+/// The variants of a command shorthand, e.g. `{1-3,ten}`, each along with
+/// where it is written: the element for a plain variant, the bounds for the
+/// variants of a range. A lone `_` omits the variant, running nothing in its
+/// place as `_` omits modifiers in a chord, and `\_` is an underscore.
+pub(crate) fn command_variants(pair: Pair<'_, Rule>) -> Result<Vec<Spanned<String>>, ParseError> {
+    let mut variants = vec![];
+    for component in pair.into_inner() {
+        match component.as_rule() {
+            Rule::command_component | Rule::nested_component => {
+                variants.extend(nested_variants(component)?)
+            }
+            Rule::range => variants.extend(Bounds::new(component)?.expand_commands()?),
+            _ => {}
+        }
+    }
+    Ok(variants)
+}
+
+/// The variants a variant of a command shorthand stands for, several for
+/// a variant nesting shorthands, e.g. `bar1` to `bar3` for `bar{1-3}`. All
+/// are written at the variant.
+fn nested_variants(component: Pair<'_, Rule>) -> Result<Vec<Spanned<String>>, ParseError> {
+    let span = component.as_span();
+    let text = component.as_str();
+    let nested = component
+        .into_inner()
+        .filter(|pair| pair.as_rule() == Rule::nested_shorthand)
+        .collect_vec();
+    if nested.is_empty() {
+        let variant = match text.trim() {
+            "_" => String::new(),
+            "\\_" => text.replace("\\_", "_"),
+            _ => unescape(text, COMMAND_ESCAPES),
+        };
+        return Ok(vec![Spanned::new(variant, span)]);
+    }
+    let input = span.get_input();
+    let mut pieces = vec![];
+    let mut at = span.start();
+    for shorthand in nested {
+        let inner = shorthand.as_span();
+        pieces.push(vec![unescape(&input[at..inner.start()], COMMAND_ESCAPES)]);
+        pieces.push(
+            command_variants(shorthand)?
+                .into_iter()
+                .map(|variant| variant.value)
+                .collect(),
+        );
+        at = inner.end();
+    }
+    pieces.push(vec![unescape(&input[at..span.end()], COMMAND_ESCAPES)]);
+    Ok(combinations(pieces)
+        .into_iter()
+        .map(|pieces| Spanned::new(pieces.concat(), span))
+        .collect())
+}
+
+/// A range in a shorthand, e.g. `a-z` in a chord or `1-9` in a command.
+/// Bounds are unescaped like any other key or command variant, and must be
+/// single ASCII characters of the same class. A range goes down from a
+/// greater lower bound, e.g. `9-1`, and keeps every nth value with a step,
+/// e.g. `1-9:2`. A bound at fault is reported on by itself, and a range at
+/// fault as a whole on both bounds.
+pub(crate) struct Bounds<'a> {
+    lower: Pair<'a, Rule>,
+    upper: Pair<'a, Rule>,
+    step: Option<Pair<'a, Rule>>,
+    span: Span<'a>,
+}
+
+impl<'a> Bounds<'a> {
+    pub fn new(pair: Pair<'a, Rule>) -> Result<Self, ParseError> {
+        let span = pair.as_span();
+        let mut iter = pair.into_inner();
+        let (Some(lower), Some(upper)) = (iter.next(), iter.next()) else {
+            return Err(spanned_error(
+                "a range needs a lower and an upper bound, e.g. `1-9`".to_string(),
+                span,
+            ));
+        };
+        Ok(Self {
+            lower,
+            upper,
+            step: iter.next(),
+            span,
+        })
+    }
+
+    /// The keys of a range in a chord. Both bounds must have the same
+    /// attributes, which every key of the range keeps, e.g. `{@1-@3}` fires
+    /// on the release of each key.
+    pub fn expand_keys(&self) -> Result<Vec<Key>, ParseError> {
+        let (keys, attribute) = self.key_chars()?;
+        keys.into_iter()
+            .map(|key| {
+                KeyRepr {
+                    key: key.to_string(),
+                    attribute,
+                }
+                .try_into()
+                .map_err(|err| match err {
+                    ParseError::InvalidKey(name) => spanned_error(
+                        format!(
+                            "the range `{}` includes `{name}`, which is not a valid evdev key",
+                            self.span.as_str()
+                        ),
+                        self.span,
+                    ),
+                    err => err,
+                })
+            })
+            .collect()
+    }
+
+    /// The number of keys in a range in a chord, checking it like
+    /// [`Bounds::expand_keys`] but for the keys themselves.
+    pub fn count_keys(&self) -> Result<usize, ParseError> {
+        Ok(self.key_chars()?.0.len())
+    }
+
+    fn key_chars(&self) -> Result<(Vec<char>, KeyAttribute), ParseError> {
+        let lower = parse_key(self.lower.clone())?;
+        let upper = parse_key(self.upper.clone())?;
+        if lower.value.attribute != upper.value.attribute {
+            return Err(spanned_error(
+                "range bounds must have the same timing attributes".to_string(),
+                self.span,
+            ));
+        }
+        let attribute = lower.value.attribute;
+        let chars = self.chars(lower.map(|repr| repr.key), upper.map(|repr| repr.key))?;
+        Ok((chars, attribute))
+    }
+
+    /// The variants of a range in a command shorthand, e.g. `1`, `2` and
+    /// `3` for `1-3`, each written at the range.
+    pub fn expand_commands(&self) -> Result<Vec<Spanned<String>>, ParseError> {
+        let unescaped = |bound: &Pair<'_, Rule>| {
+            Spanned::new(unescape(bound.as_str(), COMMAND_ESCAPES), bound.as_span())
+        };
+        Ok(self
+            .chars(unescaped(&self.lower), unescaped(&self.upper))?
+            .into_iter()
+            .map(|variant| Spanned::new(variant.to_string(), self.span))
+            .collect())
+    }
+
+    /// The characters from the unescaped bounds `lower` to `upper`, in
+    /// that order and every step.
+    fn chars(
+        &self,
+        lower: Spanned<String>,
+        upper: Spanned<String>,
+    ) -> Result<Vec<char>, ParseError> {
+        let lower = self.bound(lower, "lower")?;
+        let upper = self.bound(upper, "upper")?;
+        if class(lower) != class(upper) {
+            return Err(spanned_error(
+                format!(
+                    "shorthand range `{}` goes from {} to {}",
+                    self.span.as_str(),
+                    class(lower),
+                    class(upper)
+                ),
+                self.span,
+            ));
+        }
+        let step = match &self.step {
+            Some(written) => {
+                // Steps too large to count keep the lower bound alone.
+                let step = written.as_str()[1..].parse().unwrap_or(usize::MAX);
+                if step == 0 {
+                    return Err(spanned_error(
+                        "the step of a range must be at least 1".to_string(),
+                        written.as_span(),
+                    ));
+                }
+                step
+            }
+            None => 1,
+        };
+        Ok(if lower <= upper {
+            (lower..=upper).step_by(step).collect()
+        } else {
+            (upper..=lower).rev().step_by(step).collect()
+        })
+    }
+
+    fn bound(&self, bound: Spanned<String>, which: &str) -> Result<char, ParseError> {
+        let input = self.span.get_input();
+        let mut chars = bound.value.chars();
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            // Keys with longer names, e.g. `comma`, have no order to range
+            // over, listing them is the way.
+            return Err(bound.error(
+                format!(
+                    "shorthand {which} bound `{}` is not a single character, list the keys instead, e.g. `{{{}, {}}}`",
+                    bound.value,
+                    self.lower.as_str(),
+                    self.upper.as_str()
+                ),
+                input,
+            ));
+        };
+        if !c.is_ascii() {
+            return Err(bound.error(
+                format!("shorthand {which} bound `{c}` is not an ASCII character"),
+                input,
+            ));
+        }
+        Ok(c)
+    }
+}
+
+/// The class of a range bound, bounds of different classes would range
+/// over the symbols between them.
+fn class(c: char) -> &'static str {
+    if c.is_ascii_digit() {
+        "a digit"
+    } else if c.is_ascii_lowercase() {
+        "a lowercase letter"
+    } else if c.is_ascii_uppercase() {
+        "an uppercase letter"
+    } else {
+        "a symbol"
+    }
+}
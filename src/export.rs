@@ -0,0 +1,495 @@
+//! Writing the bindings of a config for compositors, see [`to_sway`] and
+//! [`to_hyprland`].
+//!
+//! Keys are written as the XKB keysyms compositors expect, e.g. `Return`
+//! or `XF86AudioRaiseVolume`, and keys without one by their keycode.
+//! Commands run with `exec`, and modes become sway modes or Hyprland
+//! submaps. Compositors keep no stack of modes: `@escape` goes back to the
+//! default mode, and top-level bindings do not fire in modes.
+
+use std::{collections::BTreeMap, fmt::Display};
+
+use evdev::Key;
+
+use crate::{
+    evdev_mappings::name_of, Binding, KeyAttribute, Mode, ModeInstruction, Modifier,
+    ScrollDirection, SwhkdParser, Trigger,
+};
+
+/// How [`to_sway_with_options`] and [`to_hyprland_with_options`] write
+/// bindings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ExportOptions {
+    /// Written instead of the compositor's name for a modifier, e.g. `$mod`
+    /// for the Super modifier of a sway config that does `set $mod Mod4`.
+    pub modifier_aliases: BTreeMap<Modifier, String>,
+}
+
+impl ExportOptions {
+    pub fn with_modifier_alias<S: Into<String>>(mut self, modifier: Modifier, alias: S) -> Self {
+        self.modifier_aliases.insert(modifier, alias.into());
+        self
+    }
+}
+
+/// The bindings of a config written for a compositor, along with those it
+/// cannot express.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exported<'a> {
+    /// Lines of the compositor's config, one binding per line.
+    pub config: String,
+    /// Bindings left out of `config`, in the order of the config.
+    pub skipped: Vec<SkippedBinding<'a>>,
+}
+
+/// A binding the compositor has no counterpart for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedBinding<'a> {
+    /// Name of the mode the binding is in, or `None` for the top level.
+    pub mode: Option<&'a str>,
+    pub binding: &'a Binding,
+    /// What the compositor cannot express, e.g. `sequences of chords`.
+    pub reason: &'static str,
+}
+
+impl Display for SkippedBinding<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}`", self.binding.chords_string())?;
+        if let Some(mode) = self.mode {
+            write!(f, " in mode `{mode}`")?;
+        }
+        write!(f, " skipped: {}", self.reason)
+    }
+}
+
+/// The bindings of `parser` as sway `bindsym` lines, see
+/// [`to_sway_with_options`]. Bindings sway cannot express are left out.
+///
+/// ```
+/// use sweet::{to_sway, ParserInput, SwhkdParser};
+///
+/// let config = "super + shift + Return\n    alacritty\n@XF86AudioMute\n    pamixer -t";
+/// let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+/// assert_eq!(
+///     to_sway(&parsed),
+///     "bindsym Mod4+Shift+Return exec alacritty\nbindsym --release XF86AudioMute exec pamixer -t\n"
+/// );
+/// # Ok::<(), sweet::ParseError>(())
+/// ```
+pub fn to_sway(parser: &SwhkdParser) -> String {
+    to_sway_with_options(parser, &ExportOptions::default()).config
+}
+
+/// Writes the bindings of `parser` as sway `bindsym` lines and its modes
+/// as sway modes. Keys released with `@` bind with `--release`, mouse
+/// buttons and scrolling with `--whole-window`. Sway has no counterpart
+/// for sequences, chords of several keys, the `any` modifier, attributes
+/// on modifiers, keys passed through with `~` or `passthrough`, modifier-only chords, virtual
+/// keys, switches, device tags and schedules.
+pub fn to_sway_with_options<'a>(parser: &'a SwhkdParser, options: &ExportOptions) -> Exported<'a> {
+    export(parser, options, Compositor::Sway)
+}
+
+/// The bindings of `parser` as Hyprland `bind` lines, see
+/// [`to_hyprland_with_options`]. Bindings Hyprland cannot express are left
+/// out.
+///
+/// ```
+/// use sweet::{to_hyprland, ParserInput, SwhkdParser};
+///
+/// let config = "super + shift + Return\n    alacritty\n~XF86AudioPlay\n    playerctl play-pause";
+/// let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+/// assert_eq!(
+///     to_hyprland(&parsed),
+///     "bind = SUPER SHIFT, Return, exec, alacritty\nbindn = , XF86AudioPlay, exec, playerctl play-pause\n"
+/// );
+/// # Ok::<(), sweet::ParseError>(())
+/// ```
+pub fn to_hyprland(parser: &SwhkdParser) -> String {
+    to_hyprland_with_options(parser, &ExportOptions::default()).config
+}
+
+/// Writes the bindings of `parser` as Hyprland `bind` lines and its modes
+/// as submaps. Keys released with `@` bind with `bindr`, keys passed
+/// through with `~` or `passthrough` with `bindn`. Hyprland has no counterpart for
+/// sequences, chords of several keys, the `any` modifier, attributes on
+/// modifiers, modifier-only chords, virtual keys, switches, device tags and schedules.
+pub fn to_hyprland_with_options<'a>(
+    parser: &'a SwhkdParser,
+    options: &ExportOptions,
+) -> Exported<'a> {
+    export(parser, options, Compositor::Hyprland)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compositor {
+    Sway,
+    Hyprland,
+}
+
+/// A chord as a compositor binds it.
+struct Chord {
+    modifiers: Vec<String>,
+    key: String,
+    /// Whether `key` is a keycode rather than a keysym.
+    code: bool,
+    pointer: bool,
+    release: bool,
+    send: bool,
+}
+
+fn export<'a>(
+    parser: &'a SwhkdParser,
+    options: &ExportOptions,
+    compositor: Compositor,
+) -> Exported<'a> {
+    let mut exported = Exported {
+        config: String::new(),
+        skipped: vec![],
+    };
+    for binding in &parser.bindings {
+        write_binding(&mut exported, binding, None, options, compositor);
+    }
+    for mode in &parser.modes {
+        match compositor {
+            Compositor::Sway => exported
+                .config
+                .push_str(&format!("mode \"{}\" {{\n", mode.name)),
+            Compositor::Hyprland => exported
+                .config
+                .push_str(&format!("submap = {}\n", mode.name)),
+        }
+        for binding in &mode.bindings {
+            write_binding(&mut exported, binding, Some(mode), options, compositor);
+        }
+        match compositor {
+            Compositor::Sway => exported.config.push_str("}\n"),
+            Compositor::Hyprland => exported.config.push_str("submap = reset\n"),
+        }
+    }
+    exported
+}
+
+/// Appends the lines binding `binding` of `mode`, or records why it is
+/// skipped.
+fn write_binding<'a>(
+    exported: &mut Exported<'a>,
+    binding: &'a Binding,
+    mode: Option<&'a Mode>,
+    options: &ExportOptions,
+    compositor: Compositor,
+) {
+    let chord = match chord(binding, options, compositor) {
+        Ok(chord) => chord,
+        Err(reason) => {
+            exported.skipped.push(SkippedBinding {
+                mode: mode.map(|mode| mode.name.as_str()),
+                binding,
+                reason,
+            });
+            return;
+        }
+    };
+    let instruction = |instruction: &ModeInstruction| match (compositor, instruction) {
+        (Compositor::Sway, ModeInstruction::Enter(name)) => format!("mode \"{name}\""),
+        (Compositor::Sway, ModeInstruction::Escape) => "mode \"default\"".to_string(),
+        (Compositor::Hyprland, ModeInstruction::Enter(name)) => format!("submap, {name}"),
+        (Compositor::Hyprland, ModeInstruction::Escape) => "submap, reset".to_string(),
+    };
+    let mut actions: Vec<_> = binding
+        .instructions_before_command()
+        .iter()
+        .map(instruction)
+        .collect();
+    // Command lines run one after another.
+    let command = binding.commands().map(str::trim).collect::<Vec<_>>();
+    let command = command.join("; ");
+    if !command.is_empty() {
+        actions.push(match compositor {
+            Compositor::Sway => format!("exec {}", sway_argument(&command)),
+            Compositor::Hyprland => format!("exec, {}", command.replace('#', "##")),
+        });
+    }
+    actions.extend(binding.instructions_after_command().iter().map(instruction));
+    // A oneoff mode is left after a binding not changing modes itself.
+    if mode.is_some_and(|mode| mode.oneoff) && binding.mode_instructions().is_empty() {
+        actions.push(instruction(&ModeInstruction::Escape));
+    }
+
+    let indent = if mode.is_some() && compositor == Compositor::Sway {
+        "    "
+    } else {
+        ""
+    };
+    match compositor {
+        Compositor::Sway => {
+            let mut flags = String::new();
+            if chord.release {
+                flags.push_str("--release ");
+            }
+            if chord.pointer {
+                flags.push_str("--whole-window ");
+            }
+            let bind = if chord.code { "bindcode" } else { "bindsym" };
+            let combo: Vec<_> = chord
+                .modifiers
+                .iter()
+                .chain([&chord.key])
+                .cloned()
+                .collect();
+            exported.config.push_str(&format!(
+                "{indent}{bind} {flags}{} {}\n",
+                combo.join("+"),
+                actions.join("; ")
+            ));
+        }
+        // Hyprland runs a single dispatcher per line, every line bound to
+        // the chord runs.
+        Compositor::Hyprland => {
+            let flags = match (chord.release, chord.send) {
+                (false, false) => "",
+                (true, false) => "r",
+                (false, true) => "n",
+                (true, true) => "rn",
+            };
+            for action in actions {
+                exported.config.push_str(&format!(
+                    "bind{flags} = {}, {}, {action}\n",
+                    chord.modifiers.join(" "),
+                    chord.key
+                ));
+            }
+        }
+    }
+}
+
+/// The chord of `binding` as `compositor` binds it, or why it cannot.
+fn chord(
+    binding: &Binding,
+    options: &ExportOptions,
+    compositor: Compositor,
+) -> Result<Chord, &'static str> {
+    if binding.sequence().len() > 1 {
+        return Err("sequences of chords");
+    }
+    if binding.device().is_some() {
+        return Err("device tags");
+    }
+    if binding.schedule().is_some() {
+        return Err("schedules");
+    }
+    let definition = binding.definition();
+    if !definition.held_keys().is_empty() {
+        return Err("chords of several keys");
+    }
+    if definition.modifiers().contains(&Modifier::Any) {
+        return Err("the `any` modifier");
+    }
+    if definition
+        .modifiers()
+        .iter()
+        .any(|modifier| modifier.is_sided())
+    {
+        return Err("modifiers of one side");
+    }
+    if !definition.modifier_attributes().is_empty() {
+        return Err("`~` and `@` on modifiers");
+    }
+    let attribute = definition.key().attribute;
+    if attribute.contains(KeyAttribute::Send) && compositor == Compositor::Sway {
+        return Err("keys passed through with `~`");
+    }
+    if attribute.contains(KeyAttribute::Passthrough) && compositor == Compositor::Sway {
+        return Err("keys left to the focused window with `passthrough`");
+    }
+    let (key, code, pointer) = match (&definition.key().trigger, compositor) {
+        (Trigger::Key(key), _) if is_mouse_button(*key) => {
+            let name = match compositor {
+                Compositor::Sway => format!("{key:?}"),
+                Compositor::Hyprland => format!("mouse:{}", key.code()),
+            };
+            (name, false, true)
+        }
+        (Trigger::Key(key), _) => match keysym(*key) {
+            Some(keysym) => (keysym, false, false),
+            // XKB keycodes are evdev codes offset by 8, up to 255.
+            None if key.code() > 247 => return Err("keys without an XKB keycode"),
+            None => match compositor {
+                Compositor::Sway => ((key.code() + 8).to_string(), true, false),
+                Compositor::Hyprland => (format!("code:{}", key.code() + 8), false, false),
+            },
+        },
+        (Trigger::Scroll(direction), Compositor::Sway) => {
+            let button = match direction {
+                ScrollDirection::Up => "button4",
+                ScrollDirection::Down => "button5",
+                ScrollDirection::Left => "button6",
+                ScrollDirection::Right => "button7",
+            };
+            (button.to_string(), false, true)
+        }
+        (Trigger::Scroll(direction), Compositor::Hyprland) => {
+            let wheel = match direction {
+                ScrollDirection::Up => "mouse_up",
+                ScrollDirection::Down => "mouse_down",
+                ScrollDirection::Left => "mouse_left",
+                ScrollDirection::Right => "mouse_right",
+            };
+            (wheel.to_string(), false, true)
+        }
+        (Trigger::ModifiersOnly, _) => return Err("modifier-only chords"),
+        (Trigger::Virtual(_), _) => return Err("virtual keys"),
+        (Trigger::Switch { .. }, _) => return Err("switches"),
+        (Trigger::AnyKey, _) => return Err("wildcards"),
+    };
+    let modifiers = definition
+        .modifiers()
+        .iter()
+        .map(|modifier| match options.modifier_aliases.get(modifier) {
+            Some(alias) => alias.clone(),
+            None => modifier_name(*modifier, compositor).to_string(),
+        })
+        .collect();
+    Ok(Chord {
+        modifiers,
+        key,
+        code,
+        pointer,
+        release: attribute.contains(KeyAttribute::OnRelease),
+        send: attribute.intersects(KeyAttribute::Send | KeyAttribute::Passthrough),
+    })
+}
+
+fn modifier_name(modifier: Modifier, compositor: Compositor) -> &'static str {
+    match (compositor, modifier) {
+        (Compositor::Sway, Modifier::Super) => "Mod4",
+        (Compositor::Sway, Modifier::Alt) => "Mod1",
+        (Compositor::Sway, Modifier::Altgr) => "Mod5",
+        (Compositor::Sway, Modifier::Control) => "Ctrl",
+        (Compositor::Sway, Modifier::Shift) => "Shift",
+        (Compositor::Hyprland, Modifier::Super) => "SUPER",
+        (Compositor::Hyprland, Modifier::Alt) => "ALT",
+        (Compositor::Hyprland, Modifier::Altgr) => "MOD5",
+        (Compositor::Hyprland, Modifier::Control) => "CTRL",
+        (Compositor::Hyprland, Modifier::Shift) => "SHIFT",
+        // Definitions never hold omissions, and chords using `any` or the
+        // modifiers of one side are skipped.
+        (_, modifier) => unreachable!("{modifier:?} is not exported"),
+    }
+}
+
+/// Whether `key` is a button of a mouse, `BTN_LEFT` to `BTN_TASK`.
+fn is_mouse_button(key: Key) -> bool {
+    (Key::BTN_LEFT.code()..=Key::BTN_TASK.code()).contains(&key.code())
+}
+
+/// XKB keysyms of the keys whose config name is not their keysym. Letters,
+/// digits and function keys are written as in configs.
+const KEYSYMS: &[(Key, &str)] = &[
+    (Key::KEY_ESC, "Escape"),
+    (Key::KEY_BACKSPACE, "BackSpace"),
+    (Key::KEY_TAB, "Tab"),
+    (Key::KEY_ENTER, "Return"),
+    (Key::KEY_SPACE, "space"),
+    (Key::KEY_CAPSLOCK, "Caps_Lock"),
+    (Key::KEY_MINUS, "minus"),
+    (Key::KEY_EQUAL, "equal"),
+    (Key::KEY_GRAVE, "grave"),
+    (Key::KEY_COMMA, "comma"),
+    (Key::KEY_DOT, "period"),
+    (Key::KEY_SLASH, "slash"),
+    (Key::KEY_BACKSLASH, "backslash"),
+    (Key::KEY_LEFTBRACE, "bracketleft"),
+    (Key::KEY_RIGHTBRACE, "bracketright"),
+    (Key::KEY_SEMICOLON, "semicolon"),
+    (Key::KEY_APOSTROPHE, "apostrophe"),
+    (Key::KEY_SYSRQ, "Print"),
+    (Key::KEY_PAUSE, "Pause"),
+    (Key::KEY_SCROLLLOCK, "Scroll_Lock"),
+    (Key::KEY_NUMLOCK, "Num_Lock"),
+    (Key::KEY_INSERT, "Insert"),
+    (Key::KEY_DELETE, "Delete"),
+    (Key::KEY_HOME, "Home"),
+    (Key::KEY_END, "End"),
+    (Key::KEY_PAGEUP, "Prior"),
+    (Key::KEY_PAGEDOWN, "Next"),
+    (Key::KEY_LEFT, "Left"),
+    (Key::KEY_RIGHT, "Right"),
+    (Key::KEY_UP, "Up"),
+    (Key::KEY_DOWN, "Down"),
+    (Key::KEY_COMPOSE, "Menu"),
+    (Key::KEY_LEFTMETA, "Super_L"),
+    (Key::KEY_RIGHTMETA, "Super_R"),
+    (Key::KEY_LEFTCTRL, "Control_L"),
+    (Key::KEY_RIGHTCTRL, "Control_R"),
+    (Key::KEY_LEFTALT, "Alt_L"),
+    (Key::KEY_RIGHTALT, "Alt_R"),
+    (Key::KEY_LEFTSHIFT, "Shift_L"),
+    (Key::KEY_RIGHTSHIFT, "Shift_R"),
+    (Key::KEY_KP0, "KP_0"),
+    (Key::KEY_KP1, "KP_1"),
+    (Key::KEY_KP2, "KP_2"),
+    (Key::KEY_KP3, "KP_3"),
+    (Key::KEY_KP4, "KP_4"),
+    (Key::KEY_KP5, "KP_5"),
+    (Key::KEY_KP6, "KP_6"),
+    (Key::KEY_KP7, "KP_7"),
+    (Key::KEY_KP8, "KP_8"),
+    (Key::KEY_KP9, "KP_9"),
+    (Key::KEY_KPPLUS, "KP_Add"),
+    (Key::KEY_KPMINUS, "KP_Subtract"),
+    (Key::KEY_KPASTERISK, "KP_Multiply"),
+    (Key::KEY_KPSLASH, "KP_Divide"),
+    (Key::KEY_KPDOT, "KP_Decimal"),
+    (Key::KEY_KPENTER, "KP_Enter"),
+    (Key::KEY_KPEQUAL, "KP_Equal"),
+    (Key::KEY_VOLUMEUP, "XF86AudioRaiseVolume"),
+    (Key::KEY_VOLUMEDOWN, "XF86AudioLowerVolume"),
+    (Key::KEY_MUTE, "XF86AudioMute"),
+    (Key::KEY_MICMUTE, "XF86AudioMicMute"),
+    (Key::KEY_PLAYPAUSE, "XF86AudioPlay"),
+    (Key::KEY_PAUSECD, "XF86AudioPause"),
+    (Key::KEY_STOPCD, "XF86AudioStop"),
+    (Key::KEY_NEXTSONG, "XF86AudioNext"),
+    (Key::KEY_PREVIOUSSONG, "XF86AudioPrev"),
+    (Key::KEY_FASTFORWARD, "XF86AudioForward"),
+    (Key::KEY_REWIND, "XF86AudioRewind"),
+    (Key::KEY_RECORD, "XF86AudioRecord"),
+    (Key::KEY_MEDIA, "XF86AudioMedia"),
+    (Key::KEY_BRIGHTNESSUP, "XF86MonBrightnessUp"),
+    (Key::KEY_BRIGHTNESSDOWN, "XF86MonBrightnessDown"),
+    (Key::KEY_KBDILLUMUP, "XF86KbdBrightnessUp"),
+    (Key::KEY_KBDILLUMDOWN, "XF86KbdBrightnessDown"),
+    (Key::KEY_CALC, "XF86Calculator"),
+    (Key::KEY_MAIL, "XF86Mail"),
+    (Key::KEY_SEARCH, "XF86Search"),
+    (Key::KEY_WWW, "XF86WWW"),
+    (Key::KEY_SLEEP, "XF86Sleep"),
+    (Key::KEY_POWER, "XF86PowerOff"),
+];
+
+/// The XKB keysym of `key`, `None` for keys best bound by keycode.
+fn keysym(key: Key) -> Option<String> {
+    if let Some((_, keysym)) = KEYSYMS.iter().find(|(known, _)| *known == key) {
+        return Some(keysym.to_string());
+    }
+    let name = name_of(key)?;
+    match name.as_bytes() {
+        [c] if c.is_ascii_alphanumeric() => Some(name.to_string()),
+        [b'f', digits @ ..] if !digits.is_empty() && digits.iter().all(u8::is_ascii_digit) => {
+            Some(name.to_uppercase())
+        }
+        _ => None,
+    }
+}
+
+/// `command` as a single argument of a sway command, quoted if it holds
+/// characters sway would split commands on.
+fn sway_argument(command: &str) -> String {
+    if command.contains([';', ',', '"', '\'', '\\']) {
+        format!("\"{}\"", command.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        command.to_string()
+    }
+}
@@ -0,0 +1,132 @@
+//! Rewriting the layout of configs without changing what they mean, built
+//! on the [syntax tree](crate::syntax_tree) so that comments and spacing
+//! outside of what is rewritten are kept.
+
+use std::ops::Range;
+
+use crate::{
+    indent::indent_sites, parse::parse_main, syntax_tree, IndentStyle, ParseError, SyntaxKind,
+    SyntaxNode,
+};
+
+/// How [`normalize_commands`] lays out the command of a binding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandForm {
+    /// On the lines after the chord, indented, e.g. `super + a\n    firefox`.
+    Indented,
+    /// On the line of the chord after ` = `, e.g. `super + a = firefox`.
+    Inline,
+}
+
+/// Rewrites every binding of `input` to put its command in `form`,
+/// leaving the rest of the config untouched.
+///
+/// Bindings that cannot be written inline are left indented: the ones
+/// running several command lines, and the ones with a comment between the
+/// chord and the command. Indented commands are indented by four spaces
+/// more than their chord.
+///
+/// ```
+/// use sweet::{normalize_commands, CommandForm};
+///
+/// let config = "super + a\n    firefox\nsuper + b = alacritty";
+/// assert_eq!(
+///     normalize_commands(config, CommandForm::Inline)?,
+///     "super + a = firefox\nsuper + b = alacritty"
+/// );
+/// assert_eq!(
+///     normalize_commands(config, CommandForm::Indented)?,
+///     "super + a\n    firefox\nsuper + b\n    alacritty"
+/// );
+/// # Ok::<(), sweet::ParseError>(())
+/// ```
+pub fn normalize_commands(input: &str, form: CommandForm) -> Result<String, ParseError> {
+    let root = syntax_tree(input)?;
+    let edits: Vec<(Range<usize>, String)> = root
+        .preorder()
+        .filter(|node| node.kind() == SyntaxKind::Binding)
+        .filter_map(|binding| rewrite(input, &binding, form))
+        .collect();
+    let mut output = input.to_string();
+    // Back to front, so that the ranges of the remaining edits still hold.
+    for (range, replacement) in edits.into_iter().rev() {
+        output.replace_range(range, &replacement);
+    }
+    Ok(output)
+}
+
+/// The edit putting the command of `binding` in `form`, `None` if it
+/// already is or cannot be.
+fn rewrite(input: &str, binding: &SyntaxNode, form: CommandForm) -> Option<(Range<usize>, String)> {
+    let children: Vec<_> = binding.children().collect();
+    let at = children
+        .iter()
+        .position(|child| child.kind() == SyntaxKind::Command)?;
+    let command = &children[at];
+    // Comments and newlines between the chord and an indented command
+    // belong to the binding too.
+    let chord_end = children[..at]
+        .iter()
+        .rev()
+        .find(|child| !child.kind().is_trivia())?
+        .range()
+        .end;
+    let text = command.text();
+    match (form, text.strip_prefix('=')) {
+        (CommandForm::Indented, Some(body)) => {
+            let line_start = input[..binding.range().start]
+                .rfind('\n')
+                .map_or(0, |i| i + 1);
+            let indent = &input[line_start..binding.range().start];
+            let body = body.trim_start_matches([' ', '\t']);
+            Some((
+                chord_end..command.range().end,
+                format!("\n{indent}    {body}"),
+            ))
+        }
+        (CommandForm::Inline, None) => {
+            let several_lines = command
+                .children()
+                .any(|child| child.kind() == SyntaxKind::CommandSeparator);
+            if several_lines || input[chord_end..command.range().start].contains('#') {
+                return None;
+            }
+            let body = text.trim_start_matches(['\r', '\n', ' ', '\t']);
+            if body.trim().is_empty() {
+                return None;
+            }
+            Some((chord_end..command.range().end, format!(" = {body}")))
+        }
+        _ => None,
+    }
+}
+
+/// Rewrites the indentation of every command and mode body of `input` in
+/// `style`, leaving the rest of the config untouched. [`IndentStyle::Any`]
+/// leaves the config as is.
+///
+/// ```
+/// use sweet::{reindent, IndentStyle};
+///
+/// let config = "super + a\n    firefox \\\n    --private\nmode media\n  p\n      playerctl play\nendmode";
+/// assert_eq!(
+///     reindent(config, IndentStyle::Tabs)?,
+///     "super + a\n\tfirefox \\\n\t\t--private\nmode media\n\tp\n\t\tplayerctl play\nendmode"
+/// );
+/// # Ok::<(), sweet::ParseError>(())
+/// ```
+pub fn reindent(input: &str, style: IndentStyle) -> Result<String, ParseError> {
+    let contents = parse_main(input, "<anonymous>")?;
+    let mut output = String::with_capacity(input.len());
+    let mut copied = 0;
+    for site in indent_sites(contents) {
+        let Some(indent) = style.indent(site.depth) else {
+            break;
+        };
+        output.push_str(&input[copied..site.range.start]);
+        output.push_str(&indent);
+        copied = site.range.end;
+    }
+    output.push_str(&input[copied..]);
+    Ok(output)
+}
@@ -0,0 +1,80 @@
+//! Generates the committed sources that would otherwise be derived at
+//! build time or maintained by hand:
+//!
+//! - `src/grammar_generated.rs` from `template.pest`, so that building
+//!   sweet does not need `pest_derive`. Run `cargo regenerate-grammar`
+//!   after changing the grammar.
+//! - `src/keys_generated.rs` from the kernel's `input-event-codes.h`,
+//!   vendored in `vendor/`. Run `cargo regenerate-keys` after updating it.
+
+use std::{collections::BTreeMap, fmt::Write, str::FromStr};
+
+use proc_macro2::TokenStream;
+
+/// The grammar the committed parser is generated from.
+pub const GRAMMAR: &str = include_str!("../template.pest");
+
+/// Where the generated parser lives, relative to the crate root.
+pub const GENERATED_PATH: &str = "src/grammar_generated.rs";
+
+/// Source of the parser module generated from [`GRAMMAR`].
+pub fn generated_parser() -> String {
+    let input = format!("#[grammar_inline = {GRAMMAR:?}] pub struct SwhkdGrammar;");
+    // Safety: the input is a plain struct with a string literal attribute.
+    let input = TokenStream::from_str(&input).unwrap();
+    let parser = pest_generator::derive_parser(input, false);
+    let module = format!("use crate::SwhkdGrammar;\n{parser}");
+    // Safety: pest_generator emits valid items.
+    let module = syn::parse_file(&module).unwrap();
+    format!(
+        "// @generated by `cargo regenerate-grammar` from template.pest, do not edit.\n\n{}",
+        prettyplease::unparse(&module)
+    )
+}
+
+/// The kernel header the key table is generated from.
+pub const KEY_CODES_HEADER: &str = include_str!("../vendor/input-event-codes.h");
+
+/// Where the generated key table lives, relative to the crate root.
+pub const GENERATED_KEYS_PATH: &str = "src/keys_generated.rs";
+
+/// Bounds and placeholders of the key space rather than keys.
+const NOT_KEYS: &[&str] = &["KEY_RESERVED", "KEY_MIN_INTERESTING", "KEY_MAX", "KEY_CNT"];
+
+/// Every `KEY_*` code defined in [`KEY_CODES_HEADER`], including the ones
+/// defined as another key, e.g. `KEY_HANGUEL`.
+pub fn kernel_key_codes() -> BTreeMap<String, u16> {
+    let mut codes = BTreeMap::new();
+    for line in KEY_CODES_HEADER.lines() {
+        let mut words = line.split_whitespace();
+        let (Some("#define"), Some(name), Some(value)) = (words.next(), words.next(), words.next())
+        else {
+            continue;
+        };
+        if !name.starts_with("KEY_") || NOT_KEYS.contains(&name) {
+            continue;
+        }
+        let code = match value.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16).ok(),
+            None => value.parse().ok().or_else(|| codes.get(value).copied()),
+        };
+        if let Some(code) = code {
+            codes.insert(name.to_string(), code);
+        }
+    }
+    codes
+}
+
+/// Source of the key table module generated from [`KEY_CODES_HEADER`].
+pub fn generated_key_table() -> String {
+    let mut out = String::from(
+        "// @generated by `cargo regenerate-keys` from vendor/input-event-codes.h, do not edit.\n\n",
+    );
+    out.push_str("/// Every `KEY_*` code of the kernel, by lowercase name.\n");
+    out.push_str("pub(crate) static KERNEL_KEYS: phf::Map<&'static str, u16> = phf::phf_map! {\n");
+    for (name, code) in kernel_key_codes() {
+        let _ = writeln!(out, "    \"{}\" => {},", name.to_lowercase(), code);
+    }
+    out.push_str("};\n");
+    out
+}
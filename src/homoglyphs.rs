@@ -0,0 +1,85 @@
+/// A Unicode character commonly pasted into configs in place of an ASCII
+/// one, see [`HOMOGLYPHS`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Homoglyph {
+    pub character: char,
+    /// The Unicode name of the character.
+    pub name: &'static str,
+    /// What the character most likely stands for, empty for invisible
+    /// characters that should be dropped.
+    pub replacement: &'static str,
+}
+
+const fn homoglyph(character: char, name: &'static str, replacement: &'static str) -> Homoglyph {
+    Homoglyph {
+        character,
+        name,
+        replacement,
+    }
+}
+
+/// Characters that sneak into configs copied from web pages and chats.
+pub const HOMOGLYPHS: &[Homoglyph] = &[
+    homoglyph('\u{00a0}', "NO-BREAK SPACE", " "),
+    homoglyph('\u{202f}', "NARROW NO-BREAK SPACE", " "),
+    homoglyph('\u{2010}', "HYPHEN", "-"),
+    homoglyph('\u{2011}', "NON-BREAKING HYPHEN", "-"),
+    homoglyph('\u{2012}', "FIGURE DASH", "-"),
+    homoglyph('\u{2013}', "EN DASH", "-"),
+    homoglyph('\u{2014}', "EM DASH", "-"),
+    homoglyph('\u{2212}', "MINUS SIGN", "-"),
+    homoglyph('\u{2018}', "LEFT SINGLE QUOTATION MARK", "'"),
+    homoglyph('\u{2019}', "RIGHT SINGLE QUOTATION MARK", "'"),
+    homoglyph('\u{201c}', "LEFT DOUBLE QUOTATION MARK", "\""),
+    homoglyph('\u{201d}', "RIGHT DOUBLE QUOTATION MARK", "\""),
+    homoglyph('\u{200b}', "ZERO WIDTH SPACE", ""),
+    homoglyph('\u{200c}', "ZERO WIDTH NON-JOINER", ""),
+    homoglyph('\u{200d}', "ZERO WIDTH JOINER", ""),
+    homoglyph('\u{2060}', "WORD JOINER", ""),
+    homoglyph('\u{feff}', "ZERO WIDTH NO-BREAK SPACE", ""),
+];
+
+impl Homoglyph {
+    pub fn lookup(character: char) -> Option<&'static Homoglyph> {
+        HOMOGLYPHS.iter().find(|glyph| glyph.character == character)
+    }
+
+    /// A hint naming the character and what to write instead.
+    pub fn hint(&self) -> String {
+        let found = format!(
+            "found Unicode character U+{:04X} '{}'",
+            self.character as u32, self.name
+        );
+        if self.replacement.is_empty() {
+            format!("{found}, which is invisible; did you mean to remove it?")
+        } else {
+            format!("{found}; did you mean '{}'?", self.replacement)
+        }
+    }
+}
+
+/// Whether `line` holds key definitions and statements, as opposed to
+/// commands, which are indented and may legitimately contain any text,
+/// or comments.
+pub(crate) fn is_declaration_line(line: &str) -> bool {
+    !line.starts_with([' ', '\t']) && !line.trim_start().starts_with('#')
+}
+
+/// Replaces every [`Homoglyph`] outside of commands and comments with its
+/// ASCII equivalent.
+pub fn fix_homoglyphs(config: &str) -> String {
+    config
+        .split_inclusive('\n')
+        .map(|line| {
+            if !is_declaration_line(line) {
+                return line.to_string();
+            }
+            line.chars()
+                .map(|c| match Homoglyph::lookup(c) {
+                    Some(glyph) => glyph.replacement.to_string(),
+                    None => c.to_string(),
+                })
+                .collect()
+        })
+        .collect()
+}
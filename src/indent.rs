@@ -0,0 +1,156 @@
+//! The indentation of commands and mode bodies, checked against an
+//! [`IndentStyle`] while parsing and rewritten by
+//! [`reindent`](crate::reindent). The grammar accepts any blanks, so
+//! neither changes what a config means.
+
+use std::ops::Range;
+
+use pest::{iterators::Pair, Span};
+
+use crate::{resolve::ImportContext, Rule, Warning};
+
+/// How commands and the statements of mode bodies are indented, see
+/// [`ParseOptions::indent_style`](crate::ParseOptions::indent_style).
+///
+/// Indentation is counted in levels: the command of a top-level binding and
+/// the statements of a mode are one level deep, the command of a binding in
+/// a mode two levels deep, and a line continuing a command line with `\`
+/// one level deeper than the line it continues.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IndentStyle {
+    /// Any spaces and tabs, as the grammar accepts.
+    #[default]
+    Any,
+    /// The given number of spaces per level.
+    Spaces(usize),
+    /// A tab per level.
+    Tabs,
+}
+
+impl IndentStyle {
+    /// The indentation of `depth` levels, `None` for [`IndentStyle::Any`].
+    ///
+    /// ```
+    /// use sweet::IndentStyle;
+    ///
+    /// assert_eq!(IndentStyle::Spaces(2).indent(2).as_deref(), Some("    "));
+    /// assert_eq!(IndentStyle::Tabs.indent(1).as_deref(), Some("\t"));
+    /// assert_eq!(IndentStyle::Any.indent(1), None);
+    /// ```
+    pub fn indent(&self, depth: usize) -> Option<String> {
+        match self {
+            Self::Any => None,
+            Self::Spaces(width) => Some(" ".repeat(width * depth)),
+            Self::Tabs => Some("\t".repeat(depth)),
+        }
+    }
+}
+
+/// The blanks indenting a line of a config, `depth` levels deep.
+pub(crate) struct IndentSite {
+    pub(crate) range: Range<usize>,
+    pub(crate) depth: usize,
+}
+
+/// Where the commands and mode bodies of one file are indented, in source
+/// order.
+pub(crate) fn indent_sites(contents: Pair<'_, Rule>) -> Vec<IndentSite> {
+    let mut sites = vec![];
+    for decl in contents.into_inner() {
+        match decl.as_rule() {
+            Rule::binding => binding_sites(decl, 0, &mut sites),
+            Rule::mode => {
+                let input = decl.get_input();
+                for statement in decl.into_inner() {
+                    if !matches!(
+                        statement.as_rule(),
+                        Rule::binding | Rule::unbind | Rule::replay | Rule::setting | Rule::import
+                    ) {
+                        continue;
+                    }
+                    let start = statement.as_span().start();
+                    let line_start = input[..start].rfind('\n').map_or(0, |i| i + 1);
+                    if input[line_start..start]
+                        .chars()
+                        .all(|c| matches!(c, ' ' | '\t'))
+                    {
+                        sites.push(IndentSite {
+                            range: line_start..start,
+                            depth: 1,
+                        });
+                    }
+                    if statement.as_rule() == Rule::binding {
+                        binding_sites(statement, 1, &mut sites);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    sites
+}
+
+/// The command lines of a binding `depth` levels deep, and the lines
+/// continuing them.
+fn binding_sites(binding: Pair<'_, Rule>, depth: usize, sites: &mut Vec<IndentSite>) {
+    let Some(command) = binding
+        .into_inner()
+        .find(|component| component.as_rule() == Rule::command)
+    else {
+        return;
+    };
+    let span = command.as_span();
+    let mut offset = span.start();
+    let mut continued = false;
+    // The command starts with the newline ending the chord.
+    for line in span.as_str().split('\n') {
+        let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+        if offset != span.start() {
+            sites.push(IndentSite {
+                range: offset..offset + indent,
+                depth: depth + 1 + usize::from(continued),
+            });
+        }
+        continued = line.ends_with('\\');
+        offset += line.len() + 1;
+    }
+}
+
+/// Warns about every line of the file `source` not indented in the style
+/// of the parse options.
+pub(crate) fn warn_about_indentation(
+    contents: Pair<'_, Rule>,
+    source: &str,
+    ctx: &mut ImportContext,
+) {
+    let style = ctx.options.indent_style;
+    let input = contents.get_input();
+    for site in indent_sites(contents) {
+        let Some(expected) = style.indent(site.depth) else {
+            return;
+        };
+        let found = &input[site.range.clone()];
+        if found != expected {
+            // Safety: the range lies within the input.
+            let span = Span::new(input, site.range.start, site.range.end).unwrap();
+            ctx.warnings.push(Warning::Indentation {
+                span: ctx.span(source, span),
+                found: describe(found),
+                expected: describe(&expected),
+            });
+        }
+    }
+}
+
+/// E.g. `4 spaces`, `1 tab` or `1 tab and 2 spaces`.
+fn describe(indent: &str) -> String {
+    let count = |c: char, name: &str| {
+        let n = indent.chars().filter(|&x| x == c).count();
+        (n > 0).then(|| format!("{n} {name}{}", if n == 1 { "" } else { "s" }))
+    };
+    match (count('\t', "tab"), count(' ', "space")) {
+        (Some(tabs), Some(spaces)) => format!("{tabs} and {spaces}"),
+        (Some(only), None) | (None, Some(only)) => only,
+        (None, None) => "no indentation".to_string(),
+    }
+}
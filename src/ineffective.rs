@@ -0,0 +1,125 @@
+//! Finding statements of a config that parse but have no effect, e.g. an
+//! `ignore` that matches no binding.
+
+use crate::{
+    definition::unbinds_sequence, Binding, Definition, Mode, SourceSpan, SwhkdParser, Warning,
+};
+
+/// An `ignore` statement, see [`Warning::UnmatchedIgnore`].
+#[derive(Debug, Clone)]
+pub(crate) struct IgnoreSite {
+    pub(crate) span: SourceSpan,
+    /// The mode whose block holds the statement, directly or through an
+    /// include, `None` at the top level.
+    pub(crate) mode: Option<String>,
+    /// The single chords it unbinds, along with every sequence starting
+    /// with them.
+    pub(crate) chords: Vec<Definition>,
+    /// The sequences of several chords it unbinds.
+    pub(crate) sequences: Vec<Vec<Definition>>,
+}
+
+impl IgnoreSite {
+    /// Whether the statement unbinds `binding`, the same way
+    /// [`SwhkdParser::from`] does.
+    fn matches(&self, binding: &Binding) -> bool {
+        self.chords
+            .iter()
+            .any(|chord| chord.unbinds(binding.definition()))
+            || self
+                .sequences
+                .iter()
+                .any(|sequence| unbinds_sequence(sequence, binding.sequence()))
+    }
+}
+
+/// A `mode` declaration, see [`Warning::EmptyMode`].
+#[derive(Debug, Clone)]
+pub(crate) struct ModeSite {
+    pub(crate) name: String,
+    /// Where the mode is named.
+    pub(crate) span: SourceSpan,
+    /// Whether the declaration holds bindings, including ones of inactive
+    /// profiles.
+    pub(crate) declares_bindings: bool,
+}
+
+/// Warns about a top-level binding `second` overriding an earlier one
+/// `first` on the same chord, when both are declared in the same file.
+/// Variants of one declaration overriding one another are not warned
+/// about, they share its span.
+pub(crate) fn overridden_binding(first: &Binding, second: &Binding) -> Option<Warning> {
+    let (first, second) = (first.span()?, second.span()?);
+    (first.file == second.file && first != second).then(|| Warning::OverriddenBinding {
+        first: first.clone(),
+        second: second.clone(),
+    })
+}
+
+/// Warns about the `ignore` statements of `sites` matching no binding of
+/// their scope. Top-level ones are matched against the top-level
+/// `bindings`, and ones of a mode against the bindings of every
+/// declaration of the mode among `modes` as well, since top-level bindings
+/// also fire while the mode is active. `inactive` bindings count
+/// everywhere: they are only left out for now.
+pub(crate) fn unmatched_ignores(
+    sites: &[IgnoreSite],
+    bindings: &[Binding],
+    modes: &[Mode],
+    inactive: &[Binding],
+) -> Vec<Warning> {
+    sites
+        .iter()
+        .filter(|site| {
+            let in_mode = modes
+                .iter()
+                .filter(|mode| site.mode.as_ref() == Some(&mode.name))
+                .flat_map(|mode| &mode.bindings);
+            !bindings
+                .iter()
+                .chain(inactive)
+                .chain(in_mode)
+                .any(|binding| site.matches(binding))
+        })
+        .map(|site| Warning::UnmatchedIgnore(site.span.clone()))
+        .collect()
+}
+
+/// Warns about the `ignore mode` statements of `sites` naming none of
+/// `modes`.
+pub(crate) fn unmatched_mode_ignores(
+    sites: &[(String, SourceSpan)],
+    modes: &[Mode],
+) -> Vec<Warning> {
+    sites
+        .iter()
+        .filter(|(name, _)| !modes.iter().any(|mode| mode.name == *name))
+        .map(|(name, span)| Warning::UnmatchedModeIgnore {
+            name: name.clone(),
+            span: span.clone(),
+        })
+        .collect()
+}
+
+impl SwhkdParser {
+    /// Warns about the modes none of whose declarations holds a binding.
+    /// Modes whose bindings are all tagged with inactive profiles are only
+    /// empty for now, and are not warned about.
+    pub(crate) fn warn_about_empty_modes(&mut self, declared: &[ModeSite]) {
+        for mode in &self.modes {
+            let mut sites = declared.iter().filter(|site| site.name == mode.name);
+            // A mode declared several times is reported at its first
+            // declaration.
+            let Some(first) = sites.clone().next() else {
+                continue;
+            };
+            if !mode.bindings.is_empty() || sites.any(|site| site.declares_bindings) {
+                continue;
+            }
+            self.warnings.push(Warning::EmptyMode {
+                name: mode.name.clone(),
+                span: first.span.clone(),
+            });
+        }
+    }
+}
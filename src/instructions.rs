@@ -0,0 +1,83 @@
+use std::collections::BTreeSet;
+
+use crate::{Binding, KeyAttribute, ModeInstruction, SwhkdParser, Warning};
+
+impl SwhkdParser {
+    /// Warns about mode instructions that likely do not do what was meant.
+    pub(crate) fn warn_about_mode_instructions(&mut self) {
+        let scopes =
+            std::iter::once(&self.bindings).chain(self.modes.iter().map(|mode| &mode.bindings));
+        let mut warnings = vec![];
+        for binding in scopes.clone().flatten() {
+            let Some(span) = binding.span() else {
+                continue;
+            };
+            let enters = binding
+                .mode_instructions()
+                .iter()
+                .any(|instruction| matches!(instruction, ModeInstruction::Enter(_)));
+            let on_release = binding
+                .last_definition()
+                .key()
+                .attribute
+                .contains(KeyAttribute::OnRelease);
+            if enters && on_release && !binding.command().trim().is_empty() {
+                warnings.push(Warning::CommandRunsOnRelease(span.clone()));
+            }
+        }
+        // The modes some binding enters, along with the one the config
+        // starts in.
+        let entered: BTreeSet<&str> = scopes
+            .flatten()
+            .flat_map(|binding| binding.mode_instructions())
+            .filter_map(|instruction| match instruction {
+                ModeInstruction::Enter(name) => Some(name.as_str()),
+                ModeInstruction::Escape => None,
+            })
+            .chain(self.settings.start_mode.as_deref())
+            .collect();
+        let escapes = |bindings: &'_ [Binding]| {
+            bindings
+                .iter()
+                .filter(|binding| {
+                    binding
+                        .mode_instructions()
+                        .contains(&ModeInstruction::Escape)
+                })
+                .filter_map(|binding| binding.span().cloned())
+                .collect::<Vec<_>>()
+        };
+        // A top-level `@escape` leaves whichever mode is active, one in a
+        // mode only runs while that mode is.
+        if self.modes.is_empty() {
+            warnings.extend(
+                escapes(&self.bindings)
+                    .into_iter()
+                    .map(Warning::EscapeWithoutModes),
+            );
+        } else if entered.is_empty() {
+            warnings.extend(
+                escapes(&self.bindings)
+                    .into_iter()
+                    .map(|span| Warning::EscapeWithoutEnter { span, mode: None }),
+            );
+        }
+        for mode in &self.modes {
+            if entered.contains(mode.name.as_str()) {
+                continue;
+            }
+            warnings.extend(escapes(&mode.bindings).into_iter().map(|span| {
+                Warning::EscapeWithoutEnter {
+                    span,
+                    mode: Some(mode.name.clone()),
+                }
+            }));
+        }
+        // Bindings expanded from one declaration share its span.
+        for warning in warnings {
+            if !self.warnings.contains(&warning) {
+                self.warnings.push(warning);
+            }
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::{collections::BTreeSet, fmt::Display};
+
+use thiserror::Error;
+
+use crate::{evdev_mappings::lookup_key, Binding, Modifier, SwhkdParser, Trigger};
+
+/// A keybinding registered outside of swhkd, typically by the compositor.
+///
+/// Callers build these from their compositor's IPC, or with
+/// [`ExternalBinding::parse_lines`] and [`ExternalBinding::parse_sway_json`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalBinding {
+    pub modifiers: BTreeSet<Modifier>,
+    pub key: evdev::Key,
+    /// Who registered the binding, e.g. `sway: workspace 3`.
+    pub owner: String,
+}
+
+/// One of our bindings that fires on the same chord as an external binding.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Conflict<'a> {
+    /// Name of the mode the binding is in, or `None` for the top level.
+    pub mode: Option<&'a str>,
+    /// Position of the binding within its scope.
+    pub index: usize,
+    pub binding: &'a Binding,
+    pub external: &'a ExternalBinding,
+}
+
+impl Display for Conflict<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.mode {
+            Some(mode) => write!(f, "in mode `{}`, ", mode)?,
+            None => write!(f, "at the top level, ")?,
+        }
+        write!(
+            f,
+            "binding #{} {} conflicts with `{}`",
+            self.index,
+            self.binding.chords_string(),
+            self.external.owner
+        )
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ExternalBindingError {
+    #[error("line {line}: `{name}` is not a recognized modifier")]
+    InvalidModifier { line: usize, name: String },
+    #[error("line {line}: `{name}` is not recongnized as a valid evdev key")]
+    InvalidKey { line: usize, name: String },
+    #[error("line {line}: expected a chord such as `super+shift+a`")]
+    MissingKey { line: usize },
+    #[cfg(feature = "sway-json")]
+    #[error("unable to read sway bindings: {0}")]
+    Json(String),
+    #[cfg(feature = "sway-json")]
+    #[error("sway binding #{index} is missing its `{field}` field")]
+    MissingField { index: usize, field: &'static str },
+    #[cfg(feature = "sway-json")]
+    #[error("sway binding #{index}: `{name}` is not usable as a key or modifier")]
+    InvalidSwayBinding { index: usize, name: String },
+}
+
+fn modifier_from_name(name: &str) -> Option<Modifier> {
+    match name.to_lowercase().as_str() {
+        "super" | "mod4" | "logo" => Some(Modifier::Super),
+        "alt" | "mod1" => Some(Modifier::Alt),
+        "altgr" | "mod5" => Some(Modifier::Altgr),
+        "ctrl" | "control" => Some(Modifier::Control),
+        "shift" => Some(Modifier::Shift),
+        _ => None,
+    }
+}
+
+impl ExternalBinding {
+    /// Parses one binding per line in the form `mod+mod+key [description]`,
+    /// e.g. `super+shift+3 workspace 3`.
+    ///
+    /// Modifier and key names are case insensitive and accept the usual
+    /// aliases (`Mod4`, `Ctrl`, ...). The owner is `owner` followed by the
+    /// description, if any. Blank lines and lines starting with `#` are skipped.
+    pub fn parse_lines(input: &str, owner: &str) -> Result<Vec<Self>, ExternalBindingError> {
+        let mut bindings = vec![];
+        for (index, text) in input.lines().enumerate() {
+            let line = index + 1;
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            let (chord, description) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+            let mut names = chord.split('+').map(str::trim).collect::<Vec<_>>();
+            let key_name = names.pop().filter(|name| !name.is_empty());
+            let Some(key_name) = key_name else {
+                return Err(ExternalBindingError::MissingKey { line });
+            };
+            let key = lookup_key(key_name).ok_or_else(|| ExternalBindingError::InvalidKey {
+                line,
+                name: key_name.to_string(),
+            })?;
+            let modifiers = names
+                .into_iter()
+                .map(|name| {
+                    modifier_from_name(name).ok_or_else(|| ExternalBindingError::InvalidModifier {
+                        line,
+                        name: name.to_string(),
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+            let description = description.trim();
+            let owner = if description.is_empty() {
+                owner.to_string()
+            } else {
+                format!("{owner}: {description}")
+            };
+            bindings.push(Self {
+                modifiers,
+                key,
+                owner,
+            });
+        }
+        Ok(bindings)
+    }
+
+    /// Parses a JSON array of sway binding objects, as found in the
+    /// `binding` field of sway's binding IPC events:
+    ///
+    /// ```json
+    /// [{"command": "workspace 3", "event_state_mask": ["Mod4"], "symbol": "3", "input_type": "keyboard"}]
+    /// ```
+    ///
+    /// Whole events (`{"change": "run", "binding": {...}}`) are accepted too.
+    /// Bindings on anything other than the keyboard are skipped. The owner
+    /// is `sway: ` followed by the bound command.
+    #[cfg(feature = "sway-json")]
+    pub fn parse_sway_json(input: &str) -> Result<Vec<Self>, ExternalBindingError> {
+        use serde_json::Value;
+
+        let entries: Vec<Value> = serde_json::from_str(input)
+            .map_err(|err| ExternalBindingError::Json(err.to_string()))?;
+        let mut bindings = vec![];
+        for (index, entry) in entries.iter().enumerate() {
+            let entry = entry.get("binding").unwrap_or(entry);
+            let field = |field: &'static str| {
+                entry
+                    .get(field)
+                    .ok_or(ExternalBindingError::MissingField { index, field })
+            };
+            if field("input_type")?.as_str() != Some("keyboard") {
+                continue;
+            }
+            let invalid = |name: &str| ExternalBindingError::InvalidSwayBinding {
+                index,
+                name: name.to_string(),
+            };
+            let symbol = field("symbol")?.as_str().unwrap_or_default();
+            let key = lookup_key(symbol).ok_or_else(|| invalid(symbol))?;
+            let modifiers = field("event_state_mask")?
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|name| {
+                    let name = name.as_str().unwrap_or_default();
+                    modifier_from_name(name).ok_or_else(|| invalid(name))
+                })
+                .collect::<Result<_, _>>()?;
+            let command = field("command")?.as_str().unwrap_or_default();
+            bindings.push(Self {
+                modifiers,
+                key,
+                owner: format!("sway: {command}"),
+            });
+        }
+        Ok(bindings)
+    }
+
+    /// Whether a definition with `modifiers` and `key` fires on this chord.
+    /// A definition using the `any` modifier overlaps every modifier set.
+    fn overlaps(&self, modifiers: &BTreeSet<Modifier>, key: evdev::Key) -> bool {
+        key == self.key && (modifiers.contains(&Modifier::Any) || *modifiers == self.modifiers)
+    }
+}
+
+impl SwhkdParser {
+    /// Finds our bindings that fire on the same chord as one of `external`.
+    ///
+    /// Every scope is checked, the top level as well as each mode, since a
+    /// compositor binding grabs the chord regardless of the active mode.
+    /// Virtual keys, switches and chords holding several keys never
+    /// conflict. Sequences conflict on their first chord, which is the one
+    /// a compositor would grab.
+    pub fn conflicts_with<'a>(&'a self, external: &'a [ExternalBinding]) -> Vec<Conflict<'a>> {
+        let scopes = std::iter::once((None, &self.bindings)).chain(
+            self.modes
+                .iter()
+                .map(|mode| (Some(mode.name.as_str()), &mode.bindings)),
+        );
+        let mut conflicts = vec![];
+        for (mode, bindings) in scopes {
+            for (index, binding) in bindings.iter().enumerate() {
+                let Trigger::Key(key) = binding.definition().key().trigger else {
+                    continue;
+                };
+                if !binding.definition().held_keys().is_empty() {
+                    continue;
+                }
+                conflicts.extend(
+                    external
+                        .iter()
+                        .filter(|ext| ext.overlaps(binding.definition().modifiers(), key))
+                        .map(|external| Conflict {
+                            mode,
+                            index,
+                            binding,
+                            external,
+                        }),
+                );
+            }
+        }
+        conflicts
+    }
+}
@@ -0,0 +1,735 @@
+use itertools::Itertools;
+
+use crate::{
+    bindings::CommandUncompiled,
+    definition::{check_key, check_modifiers_allowed},
+    evdev_mappings,
+    expand::{escape_variant, Product},
+    resolve::ImportContext,
+    settings::SettingScope,
+    Binding, ChordStyle, DefaultStyle, Definition, DefinitionUncompiled, Key, Mode, Modifier,
+    ModifierInfo, ParseError, Schedule, Settings, SwhkdParser, Trigger,
+};
+
+/// A declaration of a config built in code rather than parsed, see
+/// [`SwhkdParser::from_items`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigItem {
+    Binding(RawBinding),
+    /// Like `ignore super + a`.
+    Unbind(RawChord),
+    Mode(RawMode),
+    /// Like `set` statements, fields set by a later item take precedence.
+    Settings(Settings),
+}
+
+/// A chord before its shorthands are expanded, like `{super, alt} + {a, b}`.
+///
+/// ```
+/// use sweet::{Modifier, RawChord};
+///
+/// let chord = RawChord::shorthand([evdev::Key::KEY_A, evdev::Key::KEY_B])
+///     .with_modifier_shorthand([Modifier::Super, Modifier::Alt]);
+/// assert_eq!(chord.to_config_string(), "{super, alt} + {a, b}");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawChord {
+    /// One group per modifier of the chord. A group of several modifiers
+    /// is a shorthand such as `{super, alt}`, in which
+    /// [`Modifier::Omission`] stands for `_`.
+    pub modifiers: Vec<Vec<Modifier>>,
+    /// The key, or the keys of a shorthand such as `{a, b}`. A
+    /// modifier-only chord such as `@super!` has a single
+    /// [`Trigger::ModifiersOnly`] key and holds its modifier in the last
+    /// group.
+    pub keys: Vec<Key>,
+}
+
+impl RawChord {
+    pub fn new(key: impl Into<Key>) -> Self {
+        Self::shorthand([key])
+    }
+
+    /// A shorthand over `keys`, like `{a, b}`.
+    pub fn shorthand(keys: impl IntoIterator<Item = impl Into<Key>>) -> Self {
+        Self {
+            modifiers: vec![],
+            keys: keys.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn with_modifier(mut self, modifier: Modifier) -> Self {
+        self.modifiers.push(vec![modifier]);
+        self
+    }
+
+    /// Adds a modifier shorthand, like `{super, alt}` or, with
+    /// [`Modifier::Omission`], `{_, shift +}`.
+    pub fn with_modifier_shorthand(
+        mut self,
+        modifiers: impl IntoIterator<Item = Modifier>,
+    ) -> Self {
+        self.modifiers.push(modifiers.into_iter().collect());
+        self
+    }
+
+    /// Renders the chord the way it is written in a config.
+    pub fn to_config_string(&self) -> String {
+        let name = |modifier: &Modifier| ModifierInfo::of(*modifier).canonical;
+        let (groups, key) = match (self.keys.as_slice(), self.modifiers.split_last()) {
+            // The last modifier of a modifier-only chord carries its attributes.
+            ([key], Some((last, groups))) if key.trigger == Trigger::ModifiersOnly => (
+                groups,
+                DefaultStyle.attributed(key.attribute, name(&last[0])),
+            ),
+            _ => (self.modifiers.as_slice(), self.keys_to_config_string()),
+        };
+        let mut out = String::new();
+        for group in groups {
+            match group.as_slice() {
+                [Modifier::Omission] => {}
+                [modifier] => out.push_str(&format!("{} + ", name(modifier))),
+                group if group.contains(&Modifier::Omission) => {
+                    let variants = group.iter().map(|modifier| match modifier {
+                        Modifier::Omission => "_".to_string(),
+                        modifier => format!("{} +", name(modifier)),
+                    });
+                    out.push_str(&format!("{{{}}} ", variants.format(", ")));
+                }
+                group => {
+                    let modifiers = group.iter().map(name).format(", ");
+                    out.push_str(&format!("{{{modifiers}}} + "));
+                }
+            }
+        }
+        out + &key
+    }
+
+    /// The key, or the shorthand of keys, in config syntax.
+    fn keys_to_config_string(&self) -> String {
+        let [first, _, ..] = self.keys.as_slice() else {
+            return self.keys.iter().map(|key| DefaultStyle.key(key)).join("");
+        };
+        let (prefix, names): (&str, Vec<_>) = match first.trigger {
+            Trigger::Virtual(_) => (
+                "virtual:",
+                self.keys
+                    .iter()
+                    .map(|key| match &key.trigger {
+                        Trigger::Virtual(name) => name.clone(),
+                        _ => DefaultStyle.key(key).into_owned(),
+                    })
+                    .collect(),
+            ),
+            Trigger::Switch { .. } => (
+                "switch:",
+                self.keys
+                    .iter()
+                    .map(|key| match key.trigger {
+                        Trigger::Switch { switch, state } => switch.event_name(state).to_string(),
+                        _ => DefaultStyle.key(key).into_owned(),
+                    })
+                    .collect(),
+            ),
+            _ => (
+                "",
+                self.keys
+                    .iter()
+                    .map(|key| DefaultStyle.key(key).into_owned())
+                    .collect(),
+            ),
+        };
+        format!("{prefix}{{{}}}", names.join(", "))
+    }
+
+    /// Checks that a config could hold the chord, in the same way as the
+    /// parser checks the chords it reads.
+    fn check(&self) -> Result<(), String> {
+        if self.keys.is_empty() {
+            return Err("the chord has no key".to_string());
+        }
+        if self.modifiers.iter().any(Vec::is_empty) {
+            return Err("a modifier group of the chord is empty".to_string());
+        }
+        let kind = |key: &Key| match key.trigger {
+            Trigger::Key(_) | Trigger::Scroll(_) => 0,
+            Trigger::Virtual(_) => 1,
+            Trigger::Switch { .. } => 2,
+            Trigger::ModifiersOnly => 3,
+            Trigger::AnyKey => 4,
+        };
+        if self.keys.iter().map(kind).dedup().count() > 1 {
+            return Err(
+                "a shorthand cannot mix keys with virtual keys, switches or modifier-only chords"
+                    .to_string(),
+            );
+        }
+        let last_modifier = match self.modifiers.last().map(Vec::as_slice) {
+            Some([modifier]) => Some(*modifier),
+            _ => None,
+        };
+        if self.keys[0].trigger == Trigger::AnyKey && self.keys.len() > 1 {
+            return Err("`any_key` stands for every key, a shorthand cannot hold it".to_string());
+        }
+        if self.keys[0].trigger == Trigger::ModifiersOnly
+            && (self.keys.len() > 1 || last_modifier.is_none_or(|m| m == Modifier::Omission))
+        {
+            return Err(
+                "a modifier-only chord ends with a single modifier, e.g. `@super!`".to_string(),
+            );
+        }
+        for key in &self.keys {
+            if !self.modifiers.is_empty() {
+                check_modifiers_allowed(&key.trigger)?;
+            }
+            check_key(key)?;
+            if let Trigger::Key(code) = key.trigger {
+                if evdev_mappings::key_name(code).is_none() {
+                    return Err(format!("{code:?} has no name a config could use"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn compile(&self) -> Result<Vec<Definition>, ParseError> {
+        if self.keys.iter().any(|key| key.trigger == Trigger::AnyKey) {
+            return Err(invalid(
+                &format!("`{}`", self.to_config_string()),
+                "`any_key` only applies to `ignore` statements".to_string(),
+            ));
+        }
+        self.compile_unbind()
+    }
+
+    /// Like [`RawChord::compile`], also accepting [`Trigger::AnyKey`].
+    fn compile_unbind(&self) -> Result<Vec<Definition>, ParseError> {
+        self.check()
+            .map_err(|message| invalid(&format!("`{}`", self.to_config_string()), message))?;
+        let mut uncompiled = DefinitionUncompiled::default();
+        uncompiled.modifiers = self
+            .modifiers
+            .iter()
+            .map(|variants| variants.iter().map(|modifier| vec![*modifier]).collect())
+            .collect();
+        uncompiled.keys = self.keys.clone();
+        Ok(uncompiled.compile())
+    }
+}
+
+/// A piece of command text, see [`CommandPart::Run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandSegment {
+    /// Text run as is.
+    Text(String),
+    /// A shorthand like `{firefox, kitty}`, one variant per definition the
+    /// chord expands to.
+    Variants(Vec<String>),
+}
+
+impl CommandSegment {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text(text.into())
+    }
+
+    pub fn variants(variants: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self::Variants(variants.into_iter().map(Into::into).collect())
+    }
+}
+
+/// A part of a command line. The parts of a line are joined by `&&` as in
+/// a config, e.g. `notify-send resizing && @enter resize`. Mode
+/// instructions are taken out of the command, along with any `&&` that
+/// does not join two pieces of command text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandPart {
+    Run(Vec<CommandSegment>),
+    /// Like `@enter resize`.
+    Enter(String),
+    /// Like `@escape`.
+    Escape,
+}
+
+impl CommandPart {
+    /// Command text without shorthands.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Run(vec![CommandSegment::text(text)])
+    }
+
+    pub fn run(segments: impl IntoIterator<Item = CommandSegment>) -> Self {
+        Self::Run(segments.into_iter().collect())
+    }
+
+    pub fn enter(mode: impl Into<String>) -> Self {
+        Self::Enter(mode.into())
+    }
+}
+
+/// A binding before its shorthands are expanded.
+///
+/// ```
+/// use sweet::{CommandPart, CommandSegment, Modifier, RawBinding, RawChord};
+///
+/// let chord = RawChord::shorthand([evdev::Key::KEY_H, evdev::Key::KEY_L])
+///     .with_modifier(Modifier::Super);
+/// let binding = RawBinding::new(chord).with_line([CommandPart::run([
+///     CommandSegment::text("bspc node -f "),
+///     CommandSegment::variants(["west", "east"]),
+/// ])]);
+/// assert_eq!(
+///     binding.to_config_string(),
+///     "super + {h, l}\n    bspc node -f {west,east}"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawBinding {
+    pub chord: RawChord,
+    /// Every command line, run one after the other.
+    pub lines: Vec<Vec<CommandPart>>,
+    /// When the binding is active, see [`Binding::schedule`].
+    pub schedule: Option<Schedule>,
+    /// The input device the binding is scoped to, see [`Binding::device`].
+    pub device: Option<String>,
+}
+
+impl RawBinding {
+    /// A binding on `chord` without any command line yet.
+    pub fn new(chord: RawChord) -> Self {
+        Self {
+            chord,
+            lines: vec![],
+            schedule: None,
+            device: None,
+        }
+    }
+
+    pub fn with_schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = Some(schedule);
+        self
+    }
+
+    pub fn with_device(mut self, device: impl Into<String>) -> Self {
+        self.device = Some(device.into());
+        self
+    }
+
+    /// Adds a command line of plain text.
+    pub fn with_command(self, command: impl Into<String>) -> Self {
+        self.with_line([CommandPart::text(command)])
+    }
+
+    pub fn with_line(mut self, parts: impl IntoIterator<Item = CommandPart>) -> Self {
+        self.lines.push(parts.into_iter().collect());
+        self
+    }
+
+    /// Renders the binding the way it is written in a config.
+    pub fn to_config_string(&self) -> String {
+        self.render("")
+    }
+
+    fn render(&self, indent: &str) -> String {
+        let mut out = indent.to_string();
+        if let Some(device) = &self.device {
+            out.push_str(&format!("[device={device}] "));
+        }
+        if let Some(schedule) = &self.schedule {
+            out.push_str(&format!("[when={schedule}] "));
+        }
+        out.push_str(&self.chord.to_config_string());
+        for line in &self.lines {
+            out.push_str(&format!("\n{indent}    "));
+            for (index, part) in line.iter().enumerate() {
+                if index > 0 {
+                    out.push_str("&&");
+                }
+                match part {
+                    CommandPart::Run(segments) => {
+                        for segment in segments {
+                            match segment {
+                                CommandSegment::Text(text) => out.push_str(text),
+                                CommandSegment::Variants(variants) => out.push_str(&format!(
+                                    "{{{}}}",
+                                    variants
+                                        .iter()
+                                        .map(|variant| match variant.as_str() {
+                                            "" => "_".to_string(),
+                                            variant => escape_variant(variant),
+                                        })
+                                        .format(",")
+                                )),
+                            }
+                        }
+                    }
+                    CommandPart::Enter(_) | CommandPart::Escape => {
+                        // The grammar takes one blank on either side of an
+                        // instruction as part of it.
+                        if index > 0 {
+                            out.push(' ');
+                        }
+                        match part {
+                            CommandPart::Enter(mode) => out.push_str(&format!("@enter {mode}")),
+                            _ => out.push_str("@escape"),
+                        }
+                        if index + 1 < line.len() {
+                            out.push(' ');
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Checks that a config could hold the command lines, in the same way
+    /// as the parser checks the commands it reads.
+    fn check(&self) -> Result<(), String> {
+        if self.lines.is_empty() {
+            return Err("the binding has no command".to_string());
+        }
+        // Times are public fields, so they may be out of range.
+        if let Some(schedule) = &self.schedule {
+            Schedule::parse_expression(&schedule.to_string()).map_err(|(message, _)| message)?;
+        }
+        if let Some(device) = &self.device {
+            if device.trim().is_empty() || device.contains([']', '\n', '\r']) {
+                return Err(format!(
+                    "device name `{device}` must be non-empty and hold neither `]` nor a line break"
+                ));
+            }
+        }
+        for line in &self.lines {
+            let Some(last) = line.last() else {
+                return Err("a command line is empty".to_string());
+            };
+            if let Some(CommandPart::Run(segments)) = line.first() {
+                if let Some(CommandSegment::Text(text)) = segments.first() {
+                    if text.starts_with([' ', '\t']) {
+                        return Err(format!(
+                            "command line `{text}` cannot start with whitespace"
+                        ));
+                    }
+                }
+            }
+            if let CommandPart::Run(segments) = last {
+                if let Some(CommandSegment::Text(text)) = segments.last() {
+                    if text.ends_with('\\') {
+                        return Err(format!(
+                            "command line `{text}` cannot end with a backslash, which continues the line"
+                        ));
+                    }
+                }
+            }
+            for part in line {
+                match part {
+                    CommandPart::Run(segments) => {
+                        for (index, segment) in segments.iter().enumerate() {
+                            check_segment(segment, index == 0)?;
+                        }
+                    }
+                    CommandPart::Enter(mode) => check_mode_name(mode)?,
+                    CommandPart::Escape => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn compile(&self) -> Result<Vec<Binding>, ParseError> {
+        let definitions = self.chord.compile()?;
+        let context = format!("binding `{}`", self.chord.to_config_string());
+        self.check().map_err(|message| invalid(&context, message))?;
+        let mut command = CommandUncompiled::default();
+        for line in &self.lines {
+            for (index, part) in line.iter().enumerate() {
+                if index > 0 {
+                    command.and();
+                }
+                match part {
+                    CommandPart::Run(segments) => {
+                        for segment in segments {
+                            match segment {
+                                // A config splits command text at `&&`.
+                                CommandSegment::Text(text) => {
+                                    for (index, piece) in text.split("&&").enumerate() {
+                                        if index > 0 {
+                                            command.and();
+                                        }
+                                        if !piece.is_empty() {
+                                            command.text(piece.to_string());
+                                        }
+                                    }
+                                }
+                                CommandSegment::Variants(variants) => {
+                                    command.variants(variants.clone(), None)
+                                }
+                            }
+                        }
+                    }
+                    CommandPart::Enter(mode) => command.enter(mode.clone()),
+                    CommandPart::Escape => command.escape(),
+                }
+            }
+            command.end_line();
+        }
+        command
+            .compile(
+                Product::new(vec![definitions]),
+                self.schedule.clone(),
+                self.device.clone(),
+                "".into(),
+                None,
+            )
+            .map(Iterator::collect)
+            .map_err(|(message, _)| invalid(&context, message))
+    }
+}
+
+fn check_control_characters(text: &str) -> Result<(), String> {
+    match text.chars().find(|c| c.is_control() && *c != '\t') {
+        Some(c) => Err(format!(
+            "command contains the control character U+{:04X}, write it as an escape sequence such as `\\x{:02x}` instead",
+            c as u32, c as u32
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Checks a segment of command text, `first` in its part of the line.
+fn check_segment(segment: &CommandSegment, first: bool) -> Result<(), String> {
+    match segment {
+        CommandSegment::Text(text) => {
+            check_control_characters(text)?;
+            if text.contains(['{', '}']) {
+                return Err(format!(
+                    "command text `{text}` cannot hold braces outside of a shorthand"
+                ));
+            }
+            // Text right after a `&&` that starts like a mode instruction
+            // is read as one.
+            let instruction = text.split("&&").enumerate().any(|(index, piece)| {
+                let piece = piece.strip_prefix([' ', '\t']).unwrap_or(piece);
+                (first || index > 0)
+                    && (piece.starts_with("@enter") || piece.starts_with("@escape"))
+            });
+            if instruction {
+                return Err(format!(
+                    "command text `{text}` holds a mode instruction, use `CommandPart::Enter` or `CommandPart::Escape` instead"
+                ));
+            }
+        }
+        CommandSegment::Variants(variants) => {
+            if variants.len() < 2 {
+                return Err("a shorthand needs at least two variants".to_string());
+            }
+            for (index, variant) in variants.iter().enumerate() {
+                check_control_characters(variant)?;
+                if variant.ends_with('\\') || (index > 0 && variant.starts_with([' ', '\t'])) {
+                    return Err(format!(
+                        "shorthand variant {variant:?} cannot be written in a config"
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Mirrors `modename` in the grammar.
+pub(crate) fn check_mode_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || name.chars().any(|c| c == ' ' || c.is_control())
+        || ["&&", "oneoff", "swallow"]
+            .iter()
+            .any(|word| name.contains(word))
+    {
+        return Err(format!(
+            "`{name}` is not a valid mode name, names cannot be empty or hold blanks, `&&`, `oneoff` or `swallow`"
+        ));
+    }
+    Ok(())
+}
+
+/// A mode before its bindings are expanded.
+///
+/// ```
+/// use sweet::{Modifier, RawBinding, RawChord, RawMode};
+///
+/// let mode = RawMode::new("resize")
+///     .with_binding(RawBinding::new(RawChord::new(evdev::Key::KEY_H)).with_command("shrink"));
+/// assert_eq!(mode.to_config_string(), "mode resize\n    h\n        shrink\nendmode");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawMode {
+    pub name: String,
+    pub oneoff: bool,
+    pub swallow: bool,
+    pub bindings: Vec<RawBinding>,
+    pub unbinds: Vec<RawChord>,
+    /// Like `replay` statements, see [`Mode::replay`].
+    pub replay: Vec<RawChord>,
+    pub settings: Settings,
+}
+
+impl RawMode {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_binding(mut self, binding: RawBinding) -> Self {
+        self.bindings.push(binding);
+        self
+    }
+
+    /// Renders the mode the way it is written in a config.
+    pub fn to_config_string(&self) -> String {
+        let mut out = format!("mode {}", self.name);
+        if self.oneoff {
+            out.push_str(" oneoff");
+        }
+        if self.swallow {
+            out.push_str(" swallow");
+        }
+        let statements = self.settings.to_statements().into_iter().chain(
+            self.unbinds
+                .iter()
+                .map(|chord| format!("ignore {}", chord.to_config_string()))
+                .chain(
+                    self.replay
+                        .iter()
+                        .map(|chord| format!("replay {}", chord.to_config_string())),
+                ),
+        );
+        for statement in statements {
+            out.push_str(&format!("\n    {statement}"));
+        }
+        for binding in &self.bindings {
+            out.push('\n');
+            out.push_str(&binding.render("    "));
+        }
+        out + "\nendmode"
+    }
+
+    fn compile(&self) -> Result<Mode, ParseError> {
+        let context = format!("mode `{}`", self.name);
+        check_mode_name(&self.name).map_err(|message| invalid(&context, message))?;
+        self.settings
+            .check(SettingScope::Mode)
+            .map_err(|message| invalid(&context, message))?;
+        if self.bindings.is_empty()
+            && self.unbinds.is_empty()
+            && self.replay.is_empty()
+            && self.settings == Settings::default()
+        {
+            return Err(invalid(&context, "the mode declares nothing".to_string()));
+        }
+        let mut mode = Mode {
+            name: self.name.clone(),
+            oneoff: self.oneoff,
+            swallow: self.swallow,
+            settings: self.settings.clone(),
+            ..Default::default()
+        };
+        for binding in &self.bindings {
+            mode.bindings.extend(binding.compile()?);
+        }
+        for chord in &self.unbinds {
+            mode.unbinds.extend(chord.compile_unbind()?);
+        }
+        for chord in &self.replay {
+            mode.replay.extend(chord.compile()?);
+        }
+        Ok(mode)
+    }
+}
+
+impl ConfigItem {
+    /// Renders the item the way it is written in a config.
+    pub fn to_config_string(&self) -> String {
+        match self {
+            Self::Binding(binding) => binding.to_config_string(),
+            Self::Unbind(chord) => format!("ignore {}", chord.to_config_string()),
+            Self::Mode(mode) => mode.to_config_string(),
+            Self::Settings(settings) => settings.to_statements().join("\n"),
+        }
+    }
+}
+
+impl From<RawBinding> for ConfigItem {
+    fn from(binding: RawBinding) -> Self {
+        Self::Binding(binding)
+    }
+}
+
+impl From<RawMode> for ConfigItem {
+    fn from(mode: RawMode) -> Self {
+        Self::Mode(mode)
+    }
+}
+
+impl From<Settings> for ConfigItem {
+    fn from(settings: Settings) -> Self {
+        Self::Settings(settings)
+    }
+}
+
+/// Writes `items` as a config, which [`SwhkdParser::from`] parses to the
+/// same result as [`SwhkdParser::from_items`] gives for them.
+pub fn render_items(items: &[ConfigItem]) -> String {
+    let mut config = items
+        .iter()
+        .map(ConfigItem::to_config_string)
+        .filter(|item| !item.is_empty())
+        .join("\n\n");
+    config.push('\n');
+    config
+}
+
+pub(crate) fn invalid(context: &str, message: String) -> ParseError {
+    ParseError::InvalidItem(format!("{context}: {message}"))
+}
+
+impl SwhkdParser {
+    /// Builds a config from items rather than text, for programs that
+    /// generate configs. Items are expanded, checked and merged like the
+    /// declarations of a parsed config, see [`SwhkdParser::from`], so that
+    /// parsing [`render_items`] gives the same bindings. Items a config
+    /// could not hold are rejected with [`ParseError::InvalidItem`].
+    ///
+    /// Bindings built from items have no span, so the warnings pointing
+    /// into a config are not reported.
+    ///
+    /// ```
+    /// use sweet::{ConfigItem, Modifier, ParserInput, RawBinding, RawChord, SwhkdParser};
+    ///
+    /// let items = vec![ConfigItem::from(
+    ///     RawBinding::new(RawChord::new(evdev::Key::KEY_ENTER).with_modifier(Modifier::Super))
+    ///         .with_command("alacritty"),
+    /// )];
+    /// let config = sweet::render_items(&items);
+    /// assert_eq!(config, "super + return\n    alacritty\n");
+    /// let built = SwhkdParser::from_items(items)?;
+    /// assert_eq!(built.bindings, SwhkdParser::from(ParserInput::Raw(&config))?.bindings);
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn from_items(items: Vec<ConfigItem>) -> Result<Self, ParseError> {
+        let mut root = Self::default();
+        for item in items {
+            match item {
+                ConfigItem::Binding(binding) => root.bindings.extend(binding.compile()?),
+                ConfigItem::Unbind(chord) => root.unbinds.extend(chord.compile_unbind()?),
+                ConfigItem::Mode(mode) => root.modes.push(mode.compile()?),
+                ConfigItem::Settings(settings) => {
+                    settings
+                        .check(SettingScope::TopLevel)
+                        .map_err(|message| invalid("settings", message))?;
+                    root.settings = settings.or(&root.settings);
+                }
+            }
+        }
+        Self::merge(root, &mut ImportContext::default())
+    }
+}
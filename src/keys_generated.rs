@@ -0,0 +1,517 @@
+// @generated by `cargo regenerate-keys` from vendor/input-event-codes.h, do not edit.
+
+/// Every `KEY_*` code of the kernel, by lowercase name.
+pub(crate) static KERNEL_KEYS: phf::Map<&'static str, u16> = phf::phf_map! {
+    "key_0" => 11,
+    "key_1" => 2,
+    "key_102nd" => 86,
+    "key_10channelsdown" => 441,
+    "key_10channelsup" => 440,
+    "key_2" => 3,
+    "key_3" => 4,
+    "key_3d_mode" => 623,
+    "key_4" => 5,
+    "key_5" => 6,
+    "key_6" => 7,
+    "key_7" => 8,
+    "key_8" => 9,
+    "key_9" => 10,
+    "key_a" => 30,
+    "key_ab" => 406,
+    "key_addressbook" => 429,
+    "key_again" => 129,
+    "key_all_applications" => 204,
+    "key_als_toggle" => 560,
+    "key_alterase" => 222,
+    "key_angle" => 371,
+    "key_apostrophe" => 40,
+    "key_appselect" => 580,
+    "key_archive" => 361,
+    "key_aspect_ratio" => 375,
+    "key_assistant" => 583,
+    "key_attendant_off" => 540,
+    "key_attendant_on" => 539,
+    "key_attendant_toggle" => 541,
+    "key_audio" => 392,
+    "key_audio_desc" => 622,
+    "key_autopilot_engage_toggle" => 637,
+    "key_aux" => 390,
+    "key_b" => 48,
+    "key_back" => 158,
+    "key_backslash" => 43,
+    "key_backspace" => 14,
+    "key_bassboost" => 209,
+    "key_battery" => 236,
+    "key_blue" => 401,
+    "key_bluetooth" => 237,
+    "key_bookmarks" => 156,
+    "key_break" => 411,
+    "key_brightnessdown" => 224,
+    "key_brightnessup" => 225,
+    "key_brightness_auto" => 244,
+    "key_brightness_cycle" => 243,
+    "key_brightness_max" => 593,
+    "key_brightness_menu" => 649,
+    "key_brightness_min" => 592,
+    "key_brightness_toggle" => 431,
+    "key_brightness_zero" => 244,
+    "key_brl_dot1" => 497,
+    "key_brl_dot10" => 506,
+    "key_brl_dot2" => 498,
+    "key_brl_dot3" => 499,
+    "key_brl_dot4" => 500,
+    "key_brl_dot5" => 501,
+    "key_brl_dot6" => 502,
+    "key_brl_dot7" => 503,
+    "key_brl_dot8" => 504,
+    "key_brl_dot9" => 505,
+    "key_buttonconfig" => 576,
+    "key_c" => 46,
+    "key_calc" => 140,
+    "key_calendar" => 397,
+    "key_camera" => 212,
+    "key_camera_down" => 536,
+    "key_camera_focus" => 528,
+    "key_camera_left" => 537,
+    "key_camera_right" => 538,
+    "key_camera_up" => 535,
+    "key_camera_zoomin" => 533,
+    "key_camera_zoomout" => 534,
+    "key_cancel" => 223,
+    "key_capslock" => 58,
+    "key_cd" => 383,
+    "key_channel" => 363,
+    "key_channeldown" => 403,
+    "key_channelup" => 402,
+    "key_chat" => 216,
+    "key_clear" => 355,
+    "key_clearvu_sonar" => 646,
+    "key_close" => 206,
+    "key_closecd" => 160,
+    "key_coffee" => 152,
+    "key_comma" => 51,
+    "key_compose" => 127,
+    "key_computer" => 157,
+    "key_config" => 171,
+    "key_connect" => 218,
+    "key_context_menu" => 438,
+    "key_controlpanel" => 579,
+    "key_copy" => 133,
+    "key_cut" => 137,
+    "key_cyclewindows" => 154,
+    "key_d" => 32,
+    "key_dashboard" => 204,
+    "key_data" => 631,
+    "key_database" => 426,
+    "key_delete" => 111,
+    "key_deletefile" => 146,
+    "key_del_eol" => 448,
+    "key_del_eos" => 449,
+    "key_del_line" => 451,
+    "key_dictate" => 586,
+    "key_digits" => 413,
+    "key_direction" => 153,
+    "key_directory" => 394,
+    "key_displaytoggle" => 431,
+    "key_display_off" => 245,
+    "key_documents" => 235,
+    "key_dollar" => 434,
+    "key_dot" => 52,
+    "key_down" => 108,
+    "key_dual_range_radar" => 643,
+    "key_dvd" => 389,
+    "key_e" => 18,
+    "key_edit" => 176,
+    "key_editor" => 422,
+    "key_ejectcd" => 161,
+    "key_ejectclosecd" => 162,
+    "key_email" => 215,
+    "key_emoji_picker" => 585,
+    "key_end" => 107,
+    "key_enter" => 28,
+    "key_epg" => 365,
+    "key_equal" => 13,
+    "key_esc" => 1,
+    "key_euro" => 435,
+    "key_exit" => 174,
+    "key_f" => 33,
+    "key_f1" => 59,
+    "key_f10" => 68,
+    "key_f11" => 87,
+    "key_f12" => 88,
+    "key_f13" => 183,
+    "key_f14" => 184,
+    "key_f15" => 185,
+    "key_f16" => 186,
+    "key_f17" => 187,
+    "key_f18" => 188,
+    "key_f19" => 189,
+    "key_f2" => 60,
+    "key_f20" => 190,
+    "key_f21" => 191,
+    "key_f22" => 192,
+    "key_f23" => 193,
+    "key_f24" => 194,
+    "key_f3" => 61,
+    "key_f4" => 62,
+    "key_f5" => 63,
+    "key_f6" => 64,
+    "key_f7" => 65,
+    "key_f8" => 66,
+    "key_f9" => 67,
+    "key_fastforward" => 208,
+    "key_fastreverse" => 629,
+    "key_favorites" => 364,
+    "key_file" => 144,
+    "key_finance" => 219,
+    "key_find" => 136,
+    "key_first" => 404,
+    "key_fishing_chart" => 641,
+    "key_fn" => 464,
+    "key_fn_1" => 478,
+    "key_fn_2" => 479,
+    "key_fn_b" => 484,
+    "key_fn_d" => 480,
+    "key_fn_e" => 481,
+    "key_fn_esc" => 465,
+    "key_fn_f" => 482,
+    "key_fn_f1" => 466,
+    "key_fn_f10" => 475,
+    "key_fn_f11" => 476,
+    "key_fn_f12" => 477,
+    "key_fn_f2" => 467,
+    "key_fn_f3" => 468,
+    "key_fn_f4" => 469,
+    "key_fn_f5" => 470,
+    "key_fn_f6" => 471,
+    "key_fn_f7" => 472,
+    "key_fn_f8" => 473,
+    "key_fn_f9" => 474,
+    "key_fn_right_shift" => 485,
+    "key_fn_s" => 483,
+    "key_forward" => 159,
+    "key_forwardmail" => 233,
+    "key_frameback" => 436,
+    "key_frameforward" => 437,
+    "key_front" => 132,
+    "key_full_screen" => 372,
+    "key_g" => 34,
+    "key_games" => 417,
+    "key_goto" => 354,
+    "key_graphicseditor" => 424,
+    "key_grave" => 41,
+    "key_green" => 399,
+    "key_h" => 35,
+    "key_hangeul" => 122,
+    "key_hanguel" => 122,
+    "key_hangup_phone" => 446,
+    "key_hanja" => 123,
+    "key_help" => 138,
+    "key_henkan" => 92,
+    "key_hiragana" => 91,
+    "key_home" => 102,
+    "key_homepage" => 172,
+    "key_hp" => 211,
+    "key_i" => 23,
+    "key_images" => 442,
+    "key_info" => 358,
+    "key_insert" => 110,
+    "key_ins_line" => 450,
+    "key_iso" => 170,
+    "key_j" => 36,
+    "key_journal" => 578,
+    "key_k" => 37,
+    "key_katakana" => 90,
+    "key_katakanahiragana" => 93,
+    "key_kbdillumdown" => 229,
+    "key_kbdillumtoggle" => 228,
+    "key_kbdillumup" => 230,
+    "key_kbdinputassist_accept" => 612,
+    "key_kbdinputassist_cancel" => 613,
+    "key_kbdinputassist_next" => 609,
+    "key_kbdinputassist_nextgroup" => 611,
+    "key_kbdinputassist_prev" => 608,
+    "key_kbdinputassist_prevgroup" => 610,
+    "key_kbd_layout_next" => 584,
+    "key_kbd_lcd_menu1" => 696,
+    "key_kbd_lcd_menu2" => 697,
+    "key_kbd_lcd_menu3" => 698,
+    "key_kbd_lcd_menu4" => 699,
+    "key_kbd_lcd_menu5" => 700,
+    "key_keyboard" => 374,
+    "key_kp0" => 82,
+    "key_kp1" => 79,
+    "key_kp2" => 80,
+    "key_kp3" => 81,
+    "key_kp4" => 75,
+    "key_kp5" => 76,
+    "key_kp6" => 77,
+    "key_kp7" => 71,
+    "key_kp8" => 72,
+    "key_kp9" => 73,
+    "key_kpasterisk" => 55,
+    "key_kpcomma" => 121,
+    "key_kpdot" => 83,
+    "key_kpenter" => 96,
+    "key_kpequal" => 117,
+    "key_kpjpcomma" => 95,
+    "key_kpleftparen" => 179,
+    "key_kpminus" => 74,
+    "key_kpplus" => 78,
+    "key_kpplusminus" => 118,
+    "key_kprightparen" => 180,
+    "key_kpslash" => 98,
+    "key_l" => 38,
+    "key_language" => 368,
+    "key_last" => 405,
+    "key_left" => 105,
+    "key_leftalt" => 56,
+    "key_leftbrace" => 26,
+    "key_leftctrl" => 29,
+    "key_leftmeta" => 125,
+    "key_leftshift" => 42,
+    "key_left_down" => 617,
+    "key_left_up" => 616,
+    "key_lights_toggle" => 542,
+    "key_linefeed" => 101,
+    "key_link_phone" => 447,
+    "key_list" => 395,
+    "key_logoff" => 433,
+    "key_m" => 50,
+    "key_macro" => 112,
+    "key_macro1" => 656,
+    "key_macro10" => 665,
+    "key_macro11" => 666,
+    "key_macro12" => 667,
+    "key_macro13" => 668,
+    "key_macro14" => 669,
+    "key_macro15" => 670,
+    "key_macro16" => 671,
+    "key_macro17" => 672,
+    "key_macro18" => 673,
+    "key_macro19" => 674,
+    "key_macro2" => 657,
+    "key_macro20" => 675,
+    "key_macro21" => 676,
+    "key_macro22" => 677,
+    "key_macro23" => 678,
+    "key_macro24" => 679,
+    "key_macro25" => 680,
+    "key_macro26" => 681,
+    "key_macro27" => 682,
+    "key_macro28" => 683,
+    "key_macro29" => 684,
+    "key_macro3" => 658,
+    "key_macro30" => 685,
+    "key_macro4" => 659,
+    "key_macro5" => 660,
+    "key_macro6" => 661,
+    "key_macro7" => 662,
+    "key_macro8" => 663,
+    "key_macro9" => 664,
+    "key_macro_preset1" => 691,
+    "key_macro_preset2" => 692,
+    "key_macro_preset3" => 693,
+    "key_macro_preset_cycle" => 690,
+    "key_macro_record_start" => 688,
+    "key_macro_record_stop" => 689,
+    "key_mail" => 155,
+    "key_mark_waypoint" => 638,
+    "key_media" => 226,
+    "key_media_repeat" => 439,
+    "key_media_top_menu" => 619,
+    "key_memo" => 396,
+    "key_menu" => 139,
+    "key_messenger" => 430,
+    "key_mhp" => 367,
+    "key_micmute" => 248,
+    "key_minus" => 12,
+    "key_mode" => 373,
+    "key_move" => 175,
+    "key_mp3" => 391,
+    "key_msdos" => 151,
+    "key_muhenkan" => 94,
+    "key_mute" => 113,
+    "key_n" => 49,
+    "key_nav_chart" => 640,
+    "key_nav_info" => 648,
+    "key_new" => 181,
+    "key_news" => 427,
+    "key_next" => 407,
+    "key_nextsong" => 163,
+    "key_next_element" => 635,
+    "key_next_favorite" => 624,
+    "key_notification_center" => 444,
+    "key_numeric_0" => 512,
+    "key_numeric_1" => 513,
+    "key_numeric_11" => 620,
+    "key_numeric_12" => 621,
+    "key_numeric_2" => 514,
+    "key_numeric_3" => 515,
+    "key_numeric_4" => 516,
+    "key_numeric_5" => 517,
+    "key_numeric_6" => 518,
+    "key_numeric_7" => 519,
+    "key_numeric_8" => 520,
+    "key_numeric_9" => 521,
+    "key_numeric_a" => 524,
+    "key_numeric_b" => 525,
+    "key_numeric_c" => 526,
+    "key_numeric_d" => 527,
+    "key_numeric_pound" => 523,
+    "key_numeric_star" => 522,
+    "key_numlock" => 69,
+    "key_o" => 24,
+    "key_ok" => 352,
+    "key_onscreen_keyboard" => 632,
+    "key_open" => 134,
+    "key_option" => 357,
+    "key_p" => 25,
+    "key_pagedown" => 109,
+    "key_pageup" => 104,
+    "key_paste" => 135,
+    "key_pause" => 119,
+    "key_pausecd" => 201,
+    "key_pause_record" => 626,
+    "key_pc" => 376,
+    "key_phone" => 169,
+    "key_pickup_phone" => 445,
+    "key_play" => 207,
+    "key_playcd" => 200,
+    "key_player" => 387,
+    "key_playpause" => 164,
+    "key_power" => 116,
+    "key_power2" => 356,
+    "key_presentation" => 425,
+    "key_previous" => 412,
+    "key_previoussong" => 165,
+    "key_previous_element" => 636,
+    "key_print" => 210,
+    "key_privacy_screen_toggle" => 633,
+    "key_prog1" => 148,
+    "key_prog2" => 149,
+    "key_prog3" => 202,
+    "key_prog4" => 203,
+    "key_program" => 362,
+    "key_props" => 130,
+    "key_pvr" => 366,
+    "key_q" => 16,
+    "key_question" => 214,
+    "key_r" => 19,
+    "key_radar_overlay" => 644,
+    "key_radio" => 385,
+    "key_record" => 167,
+    "key_red" => 398,
+    "key_redo" => 182,
+    "key_refresh" => 173,
+    "key_refresh_rate_toggle" => 562,
+    "key_reply" => 232,
+    "key_restart" => 408,
+    "key_rewind" => 168,
+    "key_rfkill" => 247,
+    "key_right" => 106,
+    "key_rightalt" => 100,
+    "key_rightbrace" => 27,
+    "key_rightctrl" => 97,
+    "key_rightmeta" => 126,
+    "key_rightshift" => 54,
+    "key_right_down" => 615,
+    "key_right_up" => 614,
+    "key_ro" => 89,
+    "key_root_menu" => 618,
+    "key_rotate_display" => 153,
+    "key_rotate_lock_toggle" => 561,
+    "key_s" => 31,
+    "key_sat" => 381,
+    "key_sat2" => 382,
+    "key_save" => 234,
+    "key_scale" => 120,
+    "key_screen" => 375,
+    "key_screenlock" => 152,
+    "key_screensaver" => 581,
+    "key_scrolldown" => 178,
+    "key_scrolllock" => 70,
+    "key_scrollup" => 177,
+    "key_search" => 217,
+    "key_select" => 353,
+    "key_selective_screenshot" => 634,
+    "key_semicolon" => 39,
+    "key_send" => 231,
+    "key_sendfile" => 145,
+    "key_setup" => 141,
+    "key_shop" => 221,
+    "key_shuffle" => 410,
+    "key_sidevu_sonar" => 647,
+    "key_single_range_radar" => 642,
+    "key_slash" => 53,
+    "key_sleep" => 142,
+    "key_slow" => 409,
+    "key_slowreverse" => 630,
+    "key_sos" => 639,
+    "key_sound" => 213,
+    "key_space" => 57,
+    "key_spellcheck" => 432,
+    "key_sport" => 220,
+    "key_spreadsheet" => 423,
+    "key_stop" => 128,
+    "key_stopcd" => 166,
+    "key_stop_record" => 625,
+    "key_subtitle" => 370,
+    "key_suspend" => 205,
+    "key_switchvideomode" => 227,
+    "key_sysrq" => 99,
+    "key_t" => 20,
+    "key_tab" => 15,
+    "key_tape" => 384,
+    "key_taskmanager" => 577,
+    "key_teen" => 414,
+    "key_text" => 388,
+    "key_time" => 359,
+    "key_title" => 369,
+    "key_touchpad_off" => 532,
+    "key_touchpad_on" => 531,
+    "key_touchpad_toggle" => 530,
+    "key_traditional_sonar" => 645,
+    "key_tuner" => 386,
+    "key_tv" => 377,
+    "key_tv2" => 378,
+    "key_twen" => 415,
+    "key_u" => 22,
+    "key_undo" => 131,
+    "key_unknown" => 240,
+    "key_unmute" => 628,
+    "key_up" => 103,
+    "key_uwb" => 239,
+    "key_v" => 47,
+    "key_vcr" => 379,
+    "key_vcr2" => 380,
+    "key_vendor" => 360,
+    "key_video" => 393,
+    "key_videophone" => 416,
+    "key_video_next" => 241,
+    "key_video_prev" => 242,
+    "key_vod" => 627,
+    "key_voicecommand" => 582,
+    "key_voicemail" => 428,
+    "key_volumedown" => 114,
+    "key_volumeup" => 115,
+    "key_w" => 17,
+    "key_wakeup" => 143,
+    "key_wimax" => 246,
+    "key_wlan" => 238,
+    "key_wordprocessor" => 421,
+    "key_wps_button" => 529,
+    "key_wwan" => 246,
+    "key_www" => 150,
+    "key_x" => 45,
+    "key_xfer" => 147,
+    "key_y" => 21,
+    "key_yellow" => 400,
+    "key_yen" => 124,
+    "key_z" => 44,
+    "key_zenkakuhankaku" => 85,
+    "key_zoom" => 372,
+    "key_zoomin" => 418,
+    "key_zoomout" => 419,
+    "key_zoomreset" => 420,
+};
@@ -0,0 +1,227 @@
+//! Loading whatever part of a config is valid, see
+//! [`SwhkdParser::from_lenient`].
+
+use std::ops::Range;
+
+use pest::{error::LineColLocation, Span};
+
+use crate::{
+    parse::parse_main, resolve::ImportContext, ParseError, ParseOptions, ParserInput, SourceSpan,
+    SwhkdParser,
+};
+
+/// A config parsed by [`SwhkdParser::from_lenient`].
+#[derive(Debug)]
+pub struct LenientResult {
+    /// The config without the skipped declarations.
+    pub parsed: SwhkdParser,
+    /// Every declaration left out, in the order they were skipped.
+    pub skipped: Vec<SkippedDeclaration>,
+    /// Whether any declaration was skipped, i.e. whether `parsed` misses
+    /// part of the config.
+    pub degraded: bool,
+}
+
+/// A declaration [`SwhkdParser::from_lenient`] left out.
+#[derive(Debug)]
+pub struct SkippedDeclaration {
+    /// The declaration as written: a binding with its command, a whole
+    /// `mode` block or an `include` statement. Only the chord line for
+    /// bindings entering an undeclared mode, and empty when the whole
+    /// config was skipped.
+    pub text: String,
+    /// Where the declaration is written, `None` when the whole config was
+    /// skipped, e.g. because it cannot be read.
+    pub span: Option<SourceSpan>,
+    /// Why it was skipped. Skipped includes fail with
+    /// [`ParseError::ImportFailed`].
+    pub error: ParseError,
+}
+
+impl SkippedDeclaration {
+    /// The declaration of the file `source` at `span` failing with `error`.
+    pub(crate) fn new(source: &str, span: Span<'_>, error: ParseError) -> Self {
+        Self {
+            text: span.as_str().to_string(),
+            span: Some(SourceSpan::new(source, span)),
+            error,
+        }
+    }
+
+    /// A binding removed after merging, found by its chord.
+    pub(crate) fn binding(error: ParseError) -> Self {
+        let span = match &error {
+            ParseError::UnknownMode { span, .. } => span.clone(),
+            _ => None,
+        };
+        Self {
+            text: span
+                .as_ref()
+                .map_or_else(String::new, |span| span.line_text.to_string()),
+            span,
+            error,
+        }
+    }
+}
+
+impl SwhkdParser {
+    /// Parses as much of a config as possible, leaving out the
+    /// declarations that fail instead of failing as a whole, e.g. so that
+    /// a daemon keeps most hotkeys when a single line of its config is
+    /// broken. Never fails: at worst, every declaration is skipped.
+    ///
+    /// Declarations are skipped whole, one top-level declaration at a
+    /// time: a binding along with its command, a `mode` block if any of
+    /// its statements fails, or an `include` statement along with
+    /// everything the file brings in when it cannot be read. Declarations
+    /// of an included file that fail are skipped on their own, the rest of
+    /// the file still loads. Grammar errors skip the declaration they are
+    /// in, and the file is parsed again without it.
+    ///
+    /// A config without errors parses like with [`SwhkdParser::from`].
+    ///
+    /// ```
+    /// use sweet::{ParserInput, SwhkdParser};
+    ///
+    /// let config = "
+    /// super + a
+    ///     firefox
+    /// super + {b,c
+    ///     foot
+    /// super + d
+    ///     alacritty";
+    /// let result = SwhkdParser::from_lenient(ParserInput::Raw(config));
+    /// assert!(result.degraded);
+    /// assert_eq!(result.parsed.bindings.len(), 2);
+    /// assert_eq!(result.skipped[0].text, "super + {b,c\n    foot");
+    /// assert_eq!(result.skipped[0].span.as_ref().unwrap().line, 4);
+    /// ```
+    pub fn from_lenient(input: ParserInput) -> LenientResult {
+        Self::from_lenient_with_options(input, &ParseOptions::default())
+    }
+
+    /// Like [`SwhkdParser::from_lenient`], with non-default
+    /// [`ParseOptions`].
+    pub fn from_lenient_with_options(input: ParserInput, options: &ParseOptions) -> LenientResult {
+        let mut ctx = ImportContext {
+            options: options.clone(),
+            skipped: Some(vec![]),
+            ..Default::default()
+        };
+        let parsed = Self::from_context(input, &mut ctx);
+        let mut skipped = ctx.skipped.unwrap_or_default();
+        let parsed = parsed.unwrap_or_else(|error| {
+            skipped.push(SkippedDeclaration {
+                text: String::new(),
+                span: None,
+                error,
+            });
+            Self::default()
+        });
+        LenientResult {
+            parsed,
+            degraded: !skipped.is_empty(),
+            skipped,
+        }
+    }
+}
+
+/// Blanks the declarations of the file `source` the grammar fails on, one
+/// at a time until it parses or nothing is left, recording them in
+/// `skipped`. Spaces replace them so that the lines and columns of the
+/// rest of the file do not change.
+pub(crate) fn skip_grammar_errors(
+    raw: &str,
+    source: &str,
+    skipped: &mut Vec<SkippedDeclaration>,
+) -> String {
+    let mut text = raw.to_string();
+    while let Err(error) = parse_main(&text, source) {
+        let ParseError::Grammar(grammar) = &error else {
+            break;
+        };
+        let (LineColLocation::Pos((line, _)) | LineColLocation::Span((line, _), _)) =
+            grammar.line_col;
+        let Some(range) = failing_declaration(&text, line - 1) else {
+            break;
+        };
+        // Safety: declarations start and end at line boundaries.
+        let span = Span::new(&text, range.start, range.end).unwrap();
+        skipped.push(SkippedDeclaration::new(source, span, error));
+        // Byte for byte, so that the text stays valid UTF-8 with the same
+        // length.
+        let blank: String = text[range.clone()]
+            .bytes()
+            .map(|byte| if byte == b'\n' { '\n' } else { ' ' })
+            .collect();
+        text.replace_range(range, &blank);
+    }
+    text
+}
+
+/// The byte range of the top-level declaration of `text` to skip for a
+/// grammar error on the 0-based `line`: the one the line belongs to, or
+/// else the last one starting before it, or else the first one. `None`
+/// when `text` holds no declaration.
+fn failing_declaration(text: &str, line: usize) -> Option<Range<usize>> {
+    let mut offsets = vec![];
+    let mut offset = 0;
+    for text_line in text.split('\n') {
+        let content = text_line.trim_end_matches('\r');
+        offsets.push(offset..offset + content.len());
+        offset += text_line.len() + 1;
+    }
+    let declarations = declaration_lines(text);
+    let lines = declarations
+        .iter()
+        .find(|lines| lines.contains(&line))
+        .or_else(|| declarations.iter().rev().find(|lines| lines.start <= line))
+        .or_else(|| declarations.first())?;
+    Some(offsets[lines.start].start..offsets[lines.end - 1].end)
+}
+
+/// The 0-based lines of every top-level declaration of `text`, without the
+/// blank lines and comments around them. A declaration starts on a line
+/// that is not indented, and goes on over indented lines and lines
+/// continued with `\`, or up to `endmode` or `endblock` for `mode` and
+/// `block` statements.
+fn declaration_lines(text: &str) -> Vec<Range<usize>> {
+    let mut declarations: Vec<Range<usize>> = vec![];
+    let mut block_end = None;
+    let mut continued = false;
+    for (index, line) in text.split('\n').enumerate() {
+        let trimmed = line.trim();
+        let extends = if trimmed.is_empty() {
+            continue;
+        } else if let Some(end) = block_end {
+            if trimmed.split_whitespace().next() == Some(end) {
+                block_end = None;
+            }
+            true
+        } else if continued {
+            true
+        } else if trimmed.starts_with('#') {
+            continue;
+        } else {
+            line.starts_with([' ', '\t'])
+        };
+        continued = trimmed.ends_with('\\');
+        match declarations.last_mut() {
+            Some(lines) if extends => lines.end = index + 1,
+            _ => {
+                declarations.push(index..index + 1);
+                // A profile tag may come first, e.g. `[laptop] mode resize`.
+                let statement = match trimmed.strip_prefix('[') {
+                    Some(tagged) => tagged.split_once(']').map_or("", |(_, rest)| rest),
+                    None => trimmed,
+                };
+                block_end = match statement.split_whitespace().next() {
+                    Some("mode") => Some("endmode"),
+                    Some("block") => Some("endblock"),
+                    _ => None,
+                };
+            }
+        }
+    }
+    declarations
+}
@@ -1,25 +1,136 @@
-use itertools::Itertools;
-use pest::{iterators::Pair, Parser};
-use pest_derive::Parser;
-use range::Bounds;
+use error_log::ErrorLog;
+use reload::LoadedFiles;
+use resolve::ImportContext;
 use std::{
-    collections::BTreeSet,
-    fs,
-    os::unix::fs::MetadataExt,
+    collections::{BTreeMap, BTreeSet},
     path::{Path, PathBuf},
 };
 use thiserror::Error;
 
 mod bindings;
+mod blocks;
+mod builder;
+#[cfg(feature = "cache")]
+mod cache;
+mod compat;
+mod conflicts;
+#[cfg(feature = "grammar-coverage")]
+mod coverage;
+mod default_config;
 mod definition;
-mod evdev_mappings;
-mod range;
+mod diagnostics;
+mod dump;
+mod error_log;
+mod estimate;
+pub mod evdev_mappings;
+mod expand;
+mod export;
+mod format;
+#[cfg(feature = "generate")]
+pub mod generate;
+mod homoglyphs;
+mod indent;
+mod ineffective;
+mod instructions;
+mod interop;
+mod items;
+mod keys_generated;
+mod lenient;
+mod modes;
+mod options;
+mod parse;
+mod preview;
+mod profiles;
+mod reachability;
+mod reload;
+mod render;
+mod report;
+mod resolve;
+mod resolver;
+mod schedule;
+pub mod semantics;
+#[cfg(feature = "serde")]
+mod serialize;
+mod settings;
+mod sha256;
+mod shell;
+mod span;
+mod style;
+mod syntax;
+#[cfg(feature = "test-helpers")]
+pub mod test_helpers;
 mod token;
+mod unused;
+mod variables;
+mod warnings;
+mod watch;
 
-pub use crate::bindings::Binding;
+pub use crate::bindings::{Binding, BindingBuilder};
+pub use crate::builder::{ConfigBuilder, ModeBuilder};
+#[cfg(feature = "cache")]
+pub use crate::cache::{CacheError, CachedConfig};
+pub use crate::compat::from_sxhkd;
+pub use crate::conflicts::{BindingConflict, ConflictKind};
+#[cfg(feature = "grammar-coverage")]
+pub use crate::coverage::{record_rules, RuleCoverage};
+pub use crate::default_config::{default_config_paths, FallbackPolicy, DEFAULT_CONFIG};
 pub use crate::definition::{Definition, DefinitionUncompiled};
-pub use crate::token::{Key, KeyAttribute, KeyRepr, Modifier, ModifierRepr};
+pub use crate::dump::{DumpFormat, DumpOptions};
+pub use crate::estimate::{estimate, DeclarationEstimate, Estimate};
+pub use crate::evdev_mappings::{key_name, lookup_key, supported_key_names};
+pub use crate::expand::{unescape, COMMAND_ESCAPES, KEY_ESCAPES};
+pub use crate::export::{
+    to_hyprland, to_hyprland_with_options, to_sway, to_sway_with_options, ExportOptions, Exported,
+    SkippedBinding,
+};
+pub use crate::format::{normalize_commands, reindent, CommandForm};
+pub use crate::homoglyphs::{fix_homoglyphs, Homoglyph, HOMOGLYPHS};
+pub use crate::indent::IndentStyle;
+pub use crate::interop::{Conflict, ExternalBinding, ExternalBindingError};
+pub use crate::items::{
+    render_items, CommandPart, CommandSegment, ConfigItem, RawBinding, RawChord, RawMode,
+};
+pub use crate::lenient::{LenientResult, SkippedDeclaration};
+pub use crate::options::{ImportPolicy, ParseOptions};
+pub use crate::preview::{diff_previews, PreviewLine};
+pub use crate::reachability::Unreachable;
+pub use crate::reload::ReloadReport;
+pub use crate::render::{Diagnostic, Label, RenderStyle, Severity};
+pub use crate::report::{Report, ReportEnvironment, ReportOptions, ReportOutcome, ReportedFile};
+pub use crate::resolve::read_config;
+pub use crate::resolver::{FsResolver, ImportResolver, MapResolver, ResolvedImport};
+pub use crate::schedule::{Schedule, TimeOfDay, TimeRange, Weekday};
+pub use crate::settings::Settings;
+pub use crate::shell::{lint_command, CommandLint};
+pub use crate::span::SourceSpan;
+pub use crate::style::{ChordStyle, CompactStyle, DefaultStyle, MacStyle};
+pub use crate::syntax::{syntax_tree, SyntaxKind, SyntaxNode};
+pub use crate::token::{
+    AttributeInfo, Key, KeyAttribute, KeyRepr, Modifier, ModifierInfo, ModifierRepr,
+    ScrollDirection, SwitchType, Trigger, KEY_ATTRIBUTES, MODIFIERS,
+};
+pub use crate::unused::DefinitionKind;
+pub use crate::warnings::Warning;
+pub use crate::watch::{show_watch_events, ConfigWatcher, WatchEvent};
 
+/// Grammar errors carry the position of the problem:
+///
+/// ```
+/// use pest::error::LineColLocation;
+/// use sweet::{ParseError, ParserInput, SwhkdParser};
+///
+/// let err = SwhkdParser::from(ParserInput::Raw("super + a")).unwrap_err();
+/// let ParseError::Grammar(err) = err else {
+///     panic!("expected a grammar error");
+/// };
+/// let (LineColLocation::Pos((line, column)) | LineColLocation::Span((line, column), _)) =
+///     err.line_col;
+/// assert_eq!((line, column), (1, 1));
+/// assert_eq!(
+///     err.variant.message(),
+///     "key definition `super + a` at end of file has no command"
+/// );
+/// ```
 #[derive(Debug, Error)]
 pub enum ParseError {
     #[error("unable to parse config file")]
@@ -30,33 +141,213 @@ pub enum ParseError {
     MainSection,
     #[error(transparent)]
     ConfigRead(#[from] ConfigReadError),
-    #[error("`{0}` is not recongnized as a valid evdev key")]
+    /// A key name unknown to [`evdev_mappings::convert`]. Keys in a config
+    /// are reported as [`ParseError::Grammar`] instead, pointing at the key.
+    #[error("`{0}` is not recognized as a valid evdev key")]
     InvalidKey(String),
+    /// A modifier name missing from [`MODIFIERS`]. Modifiers in a config
+    /// are reported as [`ParseError::Grammar`] instead, pointing at the
+    /// modifier.
+    #[error("`{0}` is not a modifier")]
+    InvalidModifier(String),
+    /// An included file could not be read.
+    /// A failure to read the root config is reported as [`ParseError::ConfigRead`] instead.
+    #[error("{}", requested_at.render(&import_failure_note(path, cause)))]
+    ImportFailed {
+        path: PathBuf,
+        cause: ConfigReadError,
+        /// The `include` statement that requested the file, boxed to keep
+        /// errors small.
+        requested_at: Box<SourceSpan>,
+    },
+    /// An item given to [`SwhkdParser::from_items`] that a config could not
+    /// hold, or that fails the checks a parsed declaration would.
+    #[error("{0}")]
+    InvalidItem(String),
+    /// A binding enters a mode that no `mode` block declares.
+    #[error("{}", unknown_mode_note(name, declared))]
+    UnknownMode {
+        name: String,
+        declared: Vec<String>,
+        /// The chord of the binding, `None` for bindings built from
+        /// [`ConfigItem`]s.
+        span: Option<SourceSpan>,
+    },
+    /// A declaration expands to more than
+    /// [`ParseOptions::max_binding_variants`] bindings, or brings the
+    /// config over [`ParseOptions::max_total_variants`]. Checked before
+    /// any of the bindings is built.
+    #[error("{}", span.render(&too_many_variants_note(*got, *limit, *whole_config)))]
+    TooManyVariants {
+        /// The number of bindings of the declaration, or of the config so
+        /// far including it.
+        got: usize,
+        limit: usize,
+        /// Whether `limit` is the one of the whole config.
+        whole_config: bool,
+        /// The declaration.
+        span: SourceSpan,
+    },
+    /// Errors of [`SwhkdParser::from_all_errors`] saying the same on
+    /// consecutive lines of the same file, e.g. on every line of a
+    /// generated config, collapsed into the first of them.
+    #[error("{}", error_log::repeated_note(error, *count, *first_line, *last_line))]
+    Repeated {
+        error: Box<ParseError>,
+        count: usize,
+        /// The lines of the first and the last of the errors.
+        first_line: usize,
+        last_line: usize,
+    },
+    /// The number of errors [`SwhkdParser::from_all_errors`] left out past
+    /// [`ParseOptions::max_errors`].
+    #[error("... and {0} more errors; rerun with --max-errors=0 for all")]
+    MoreErrors(usize),
+}
+
+/// A config parsed by [`SwhkdParser::from_all_errors`], along with the
+/// errors of the declarations left out.
+#[derive(Debug)]
+pub struct ParseOutcome {
+    /// The config without the declarations that failed, `None` if a whole
+    /// file failed, e.g. on a grammar error.
+    pub parsed: Option<SwhkdParser>,
+    /// Every error in the order it was found, the one of the file that
+    /// failed last, bounded by [`ParseOptions::max_errors`].
+    pub errors: Vec<ParseError>,
+    /// See [`ParseOutcome::render`].
+    max_report_bytes: usize,
+}
+
+impl ParseOutcome {
+    /// The config if nothing failed, like [`SwhkdParser::from`], and every
+    /// error otherwise.
+    pub fn into_result(self) -> Result<SwhkdParser, Vec<ParseError>> {
+        match self.parsed {
+            Some(parsed) if self.errors.is_empty() => Ok(parsed),
+            _ => Err(self.errors),
+        }
+    }
+}
+
+pub(crate) fn unknown_mode_note(name: &str, declared: &[String]) -> String {
+    if declared.is_empty() {
+        format!("`@enter {name}` refers to an undeclared mode, the config declares no modes")
+    } else {
+        format!(
+            "`@enter {name}` refers to an undeclared mode, declared modes are: {}",
+            declared.join(", ")
+        )
+    }
+}
+
+pub(crate) fn too_many_variants_note(got: usize, limit: usize, whole_config: bool) -> String {
+    if whole_config {
+        format!(
+            "with this declaration, the config expands to {got} bindings, over the limit of {limit}; \
+             raise it with `ParseOptions::with_max_total_variants`"
+        )
+    } else {
+        format!(
+            "this declaration expands to {got} bindings, over the limit of {limit}; \
+             raise it with `ParseOptions::with_max_binding_variants`"
+        )
+    }
 }
 
-#[derive(Parser)]
-#[grammar = "template.pest"]
+pub(crate) fn import_failure_note(path: &Path, cause: &ConfigReadError) -> String {
+    match std::error::Error::source(cause) {
+        Some(io) => format!("unable to import `{}`: {}: {}", path.display(), cause, io),
+        None => format!("unable to import `{}`: {}", path.display(), cause),
+    }
+}
+
+#[cfg_attr(feature = "derive", derive(pest_derive::Parser))]
+#[cfg_attr(feature = "derive", grammar = "template.pest")]
 pub struct SwhkdGrammar;
 
-#[derive(Default, Debug)]
+// Included rather than declared as a module file so that rustfmt leaves
+// the generated code alone.
+#[cfg(not(feature = "derive"))]
+#[allow(clippy::all)]
+mod grammar_generated {
+    include!("grammar_generated.rs");
+}
+#[cfg(not(feature = "derive"))]
+pub use grammar_generated::Rule;
+
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mode {
     pub name: String,
+    /// The comment lines right above the `mode` line, see
+    /// [`Binding::description`].
+    pub description: Option<String>,
     pub oneoff: bool,
     pub swallow: bool,
     pub bindings: Vec<Binding>,
     pub unbinds: Vec<Definition>,
+    /// Unbound sequences of several chords, e.g. `ignore super + a ; b`.
+    pub sequence_unbinds: Vec<Vec<Definition>>,
+    /// Keys delivered to the focused client even though the mode swallows
+    /// the keyboard, declared with `replay`.
+    pub replay: Vec<Definition>,
+    pub settings: Settings,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SwhkdParser {
     pub bindings: Vec<Binding>,
+    /// Unbound chords, along with every sequence starting with them.
     pub unbinds: Vec<Definition>,
+    /// Unbound sequences of several chords, e.g. `ignore super + a ; b`,
+    /// along with every longer sequence starting with them.
+    pub sequence_unbinds: Vec<Vec<Definition>>,
+    /// The [`ResolvedImport::id`] of every included file, its canonical
+    /// path for files on disk.
     pub imports: BTreeSet<String>,
     pub modes: Vec<Mode>,
+    /// Modes removed with `ignore mode name`, along with the bindings
+    /// entering them.
+    pub ignored_modes: BTreeSet<String>,
+    pub settings: Settings,
+    /// Settings of the bindings of one input device, declared with
+    /// `[device=name] set NAME VALUE`, by device name.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub device_settings: BTreeMap<String, Settings>,
+    /// Bindings tagged only with profiles that are not active. They are
+    /// validated but not part of [`SwhkdParser::bindings`] or of any mode.
+    pub inactive: Vec<Binding>,
+    /// Every profile a declaration is tagged with, active or not.
+    pub profiles: BTreeSet<String>,
+    pub warnings: Vec<Warning>,
+    /// Top-level bindings replaced by a later binding on the same
+    /// definition, see [`SwhkdParser::conflicts`].
+    pub overridden: Vec<Binding>,
+    /// Variables defined with `define` in the config and its includes.
+    pub variables: BTreeMap<String, String>,
+    /// Command lines of the blocks defined with `block` in the config and
+    /// its includes, joined with newlines.
+    pub blocks: BTreeMap<String, String>,
+    /// The files the config was read from, see [`SwhkdParser::reload`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) loaded: LoadedFiles,
 }
 
 /// Input to the grammar parser.
 /// Can be either a string or a path.
+///
+/// ```
+/// use std::io::Write;
+/// use sweet::{ParserInput, SwhkdParser};
+///
+/// let mut config = tempfile::NamedTempFile::new()?;
+/// writeln!(config, "super + return\n    alacritty")?;
+/// let parsed = SwhkdParser::from(ParserInput::Path(config.path()))?;
+/// assert_eq!(parsed.bindings[0].command(), "alacritty");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
 pub enum ParserInput<'a> {
     Raw(&'a str),
     Path(&'a Path),
@@ -68,292 +359,260 @@ pub enum ConfigReadError {
     ReadingConfig(#[from] std::io::Error),
     #[error("path `{0}` supplied as config is not a regular file")]
     NotRegularFile(PathBuf),
-    #[error("the supplied config file {0} size exceeds the {1}MiB limit")]
-    TooLarge(PathBuf, u64),
-}
-
-pub fn read_config<P: AsRef<Path>>(path: P) -> Result<String, ConfigReadError> {
-    let path = path.as_ref();
-    let stat = fs::metadata(path)?;
-    if !stat.is_file() {
-        return Err(ConfigReadError::NotRegularFile(path.to_path_buf()));
-    }
-    let size = stat.size();
-    let mib_cap = std::option_env!("FILESIZE_CAP_MIB")
-        .and_then(|cap| cap.parse().ok())
-        .unwrap_or(50);
-    if size > (mib_cap << 20) {
-        return Err(ConfigReadError::TooLarge(path.to_path_buf(), mib_cap));
-    }
-    // TODO: Use mmap instead of fs::read_to_string
-    Ok(fs::read_to_string(path)?)
+    /// An `include` path refers to an environment variable that is not set.
+    #[error("environment variable `{0}` is not set")]
+    UnsetVariable(String),
+    /// An `include` path starts with `~user` for a user that does not exist.
+    #[error("user `{0}` does not exist")]
+    UnknownUser(String),
+    /// The file at `path` is larger than the limit in bytes,
+    /// [`ParseOptions::max_config_bytes`] or else the limit sweet was
+    /// built with. `path` is `<anonymous>` for a config given as a string.
+    #[error("config `{}` exceeds the size limit of {limit} bytes", path.display())]
+    ExceedsLimit { path: PathBuf, limit: u64 },
+    /// Including the file at `path` nests includes deeper than
+    /// [`ParseOptions::max_import_depth`], the limit.
+    #[error("includes are nested deeper than the limit of {limit}")]
+    ImportTooDeep {
+        path: PathBuf,
+        /// The `include` statement that requested the file.
+        requested_at: Box<SourceSpan>,
+        limit: usize,
+    },
+    /// Including the file at `path` makes the config include more files
+    /// than [`ParseOptions::max_imports`], the limit.
+    #[error("the config includes more than the limit of {limit} files")]
+    TooManyImports {
+        path: PathBuf,
+        /// The `include` statement that requested the file.
+        requested_at: Box<SourceSpan>,
+        limit: usize,
+    },
+    /// A config path given as [`ParserInput::Path`] is not valid UTF-8,
+    /// which the `include` paths it is resolved with must be.
+    #[error("path `{}` is not valid UTF-8", .0.display())]
+    NonUtf8Path(PathBuf),
+    #[error("no config file found in any of {0:?}")]
+    NotFound(Vec<PathBuf>),
+    /// An error of a custom [`ImportResolver`].
+    #[error(transparent)]
+    Resolver(Box<dyn std::error::Error + Send + Sync>),
 }
 
 impl SwhkdParser {
+    /// Parses a config along with everything it includes.
+    ///
+    /// Included files are expanded depth-first in the order their `include`
+    /// statements appear, and each file is only read the first time it is
+    /// included. Relative paths are resolved against the directory of the
+    /// including file, or the working directory for [`ParserInput::Raw`].
+    /// Bindings, unbinds and modes are merged in that order:
+    ///
+    /// - bindings of an included file come after the ones of the file
+    ///   including it, so an include overrides the including file;
+    /// - a later include overrides an earlier one;
+    /// - settings are the exception, the including file takes precedence
+    ///   over the files it includes.
+    ///
+    /// An `include` in a mode block adds the top-level bindings, `ignore`
+    /// and `set` statements of the included file, and of the files it
+    /// includes in turn, to the mode, after the statements of the block.
+    /// Such files cannot declare modes of their own.
+    ///
+    /// Variables defined with `define name = value` are referenced as
+    /// `$name` or `${name}` in chords and commands, anywhere in the file
+    /// defining them and in the files including it. Like settings, the
+    /// including file takes precedence, and within a file a later
+    /// definition replaces an earlier one. References in commands to names
+    /// that are not defined are left to the shell, e.g. `$HOME`, and `\$`
+    /// keeps a literal dollar sign.
+    ///
+    /// Blocks of command lines defined with `block name` ... `endblock` are
+    /// referenced as `&name` in commands and are visible like variables.
+    /// References are replaced by the lines of the block before shorthands
+    /// are expanded, a single level deep, and `\&` keeps an ampersand for
+    /// the shell.
+    ///
+    /// A top-level binding overrides an earlier one on the same key when
+    /// their modifiers and key attributes match. Otherwise, on the same key:
+    ///
+    /// | modifiers | key attributes | outcome |
+    /// | --- | --- | --- |
+    /// | equal, e.g. `super + a` twice | equal | the later binding overrides the earlier one |
+    /// | equal | `@` differs, e.g. `super + a` and `super + @a` | both are kept and fire, one on press and the other on release, with a [`Warning::AttributeVariants`] |
+    /// | equal | `~` or `!` differs, e.g. `super + a` and `super + ~a` | both are kept and fire on the same key event, with a [`Warning::AttributeVariants`] |
+    /// | `any` on one side only, e.g. `any + a` and `super + a` | any | independent, an exact modifier match beats `any` |
+    /// | different, e.g. `super + a` and `alt + a` | any | independent |
+    ///
+    /// The warnings cover mode bindings as well. Within a mode, bindings
+    /// are not merged; duplicates are reported by
+    /// [`SwhkdParser::unreachable_bindings`] instead.
+    ///
+    /// Overriding a top-level binding declared earlier in the same file
+    /// comes with a [`Warning::OverriddenBinding`], and an `ignore`
+    /// matching no binding with a [`Warning::UnmatchedIgnore`].
+    ///
+    /// An `ignore` removes the top-level bindings it matches wherever
+    /// both are written: it wins over a binding on the same chord declared
+    /// before or after it, in the root file as well as in an include.
+    /// Shorthands and ranges remove every chord they expand to, e.g.
+    /// `ignore super + {1-9}`, a chord removes every sequence starting
+    /// with it, and `any` and `any_key` match several chords, see
+    /// [`Definition::unbinds`]. Likewise, a top-level `ignore mode name`
+    /// removes the mode wherever it is declared, e.g. in a shared include,
+    /// along with every binding entering it, top-level or in another mode.
+    ///
+    /// A mode declared more than once, e.g. in the config and in an
+    /// include, is merged into its first declaration: bindings of a later
+    /// declaration override the ones of an earlier declaration on the same
+    /// chord, and the other statements and flags of both apply.
+    ///
+    /// Entering a mode that is not declared fails with
+    /// [`ParseError::UnknownMode`], including a mode declared in an include
+    /// left out through [`ImportPolicy::Ignore`]. Every `@enter` of the
+    /// result resolves to one of [`SwhkdParser::modes`] through
+    /// [`SwhkdParser::entered_mode`], wherever the mode is declared.
+    ///
+    /// ```
+    /// use sweet::{Modifier, ParserInput, SwhkdParser};
+    ///
+    /// let config = "
+    /// super + {h,l}
+    ///     bspc node -f {west,east}
+    /// ";
+    /// let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    /// assert_eq!(parsed.bindings.len(), 2);
+    /// let binding = &parsed.bindings[1];
+    /// assert!(binding.definition().modifiers().contains(&Modifier::Super));
+    /// assert_eq!(binding.command(), "bspc node -f east");
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
     pub fn from(input: ParserInput) -> Result<Self, ParseError> {
-        let mut imports = BTreeSet::new();
-        let root = Self::as_import(input, &mut imports)?;
-        let mut bindings: Vec<Binding> = vec![];
-        for binding in root.bindings {
-            if let Some(b) = bindings
-                .iter_mut()
-                .find(|b| b.definition == binding.definition)
-            {
-                b.command = binding.command;
-                b.mode_instructions = binding.mode_instructions;
-                continue;
-            }
-
-            if root
-                .unbinds
-                .iter()
-                .find(|b| binding.definition.eq(b))
-                .is_some()
-            {
-                continue;
-            }
-            bindings.push(binding);
-        }
-        Ok(SwhkdParser {
-            bindings,
-            imports,
-            unbinds: root.unbinds,
-            modes: root.modes,
-        })
+        Self::from_with_options(input, &ParseOptions::default())
     }
-    fn as_import(input: ParserInput, seen: &mut BTreeSet<String>) -> Result<Self, ParseError> {
-        let (raw, source) = match input {
-            // If a config is loaded from a string instead of a path, name it `<anonymous>`
-            ParserInput::Raw(s) => (s.to_string(), "<anonymous>"),
-            ParserInput::Path(p) => (read_config(p)?, p.to_str().unwrap_or_default()),
-        };
-        let parse_result = SwhkdGrammar::parse(Rule::main, &raw)
-            .map_err(|err| ParseError::Grammar(Box::new(err.with_path(source))))?;
 
-        let Some(contents) = parse_result.into_iter().next() else {
-            return Err(ParseError::MainSection);
+    /// Like [`SwhkdParser::from`], with non-default [`ParseOptions`].
+    pub fn from_with_options(
+        input: ParserInput,
+        options: &ParseOptions,
+    ) -> Result<Self, ParseError> {
+        let mut ctx = ImportContext {
+            options: options.clone(),
+            includes: Some(Default::default()),
+            ..Default::default()
         };
-
-        let mut bindings: Vec<Binding> = vec![];
-        let mut unbinds = vec![];
-        let mut imports = BTreeSet::new();
-        let mut modes = vec![];
-        for decl in contents.into_inner() {
-            match decl.as_rule() {
-                Rule::binding => bindings.extend(binding_parser(decl)?),
-                Rule::unbind => unbinds.extend(unbind_parser(decl)?),
-                Rule::mode => modes.push(mode_parser(decl)?),
-                Rule::import => imports.extend(import_parser(decl)),
-                // End of identifier
-                // Here, it means the end of the file.
-                Rule::EOI => {}
-                _ => unreachable!(),
-            }
-        }
-
-        while let Some(import) = imports.pop_first() {
-            if !seen.insert(import.clone()) {
-                continue;
-            }
-            let child = Self::as_import(ParserInput::Path(Path::new(&import)), seen)?;
-            bindings.extend(child.bindings);
-            imports.extend(child.imports);
-            unbinds.extend(child.unbinds);
-            modes.extend(child.modes);
-        }
-        Ok(SwhkdParser {
-            bindings,
-            unbinds,
-            imports,
-            modes,
-        })
+        Self::from_context(input, &mut ctx)
     }
-}
 
-fn pair_to_string(pair: Pair<'_, Rule>) -> String {
-    pair.as_str().to_string()
-}
-
-/// Unescapes a string that has been escaped using backslashes
-/// but only for the charset of '{}|\-+~@,' that were allowed to
-/// be escaped in the grammar in the first place.
-/// ```ignore
-/// use sweet::unescape;
-/// fn main() {
-/// assert_eq!(unescape(r"hello\\\{\}\|\-\+\~\@\,"), r"hello\{}|-+~@,");
-/// }    
-/// ```
-fn unescape(s: &str) -> String {
-    let mut unescaped = String::with_capacity(s.len());
-    let mut was_a_slash = None;
-    for char in s.chars() {
-        match (char, was_a_slash.take()) {
-            ('\\', None) => was_a_slash = Some(()),
-            ('\\', Some(())) => unescaped.push(char),
-            ('{' | '}' | '|' | '-' | '+' | '~' | '@' | ',', Some(())) => unescaped.push(char),
-            (_, Some(())) => {}
-            _ => unescaped.push(char),
-        }
+    /// Like [`SwhkdParser::from`], carrying on past the errors of single
+    /// declarations to report every error of a config at once.
+    /// Errors saying the same on consecutive lines are collapsed into a
+    /// [`ParseError::Repeated`], and at most [`ParseOptions::max_errors`]
+    /// errors are kept, followed by a [`ParseError::MoreErrors`] counting
+    /// the others.
+    ///
+    /// A binding, unbind, setting or `replay` statement that fails, e.g. on
+    /// an unknown key or on shorthands of different sizes, is left out and
+    /// its error recorded, and so is a binding entering a mode that is not
+    /// declared. Failures of a whole file still end parsing: grammar
+    /// errors, and includes that cannot be read. Errors of included files
+    /// name the file they are in.
+    ///
+    /// ```
+    /// use sweet::{ParseError, ParserInput, SwhkdParser};
+    ///
+    /// let config = "
+    /// super + a
+    ///     firefox
+    /// super + key_nope
+    ///     foot
+    /// super + {b,c}
+    ///     {alacritty,kitty,foot}
+    /// super + r
+    ///     @enter resize";
+    /// let outcome = SwhkdParser::from_all_errors(ParserInput::Raw(config));
+    /// assert_eq!(outcome.errors.len(), 3);
+    /// assert!(matches!(outcome.errors[2], ParseError::UnknownMode { .. }));
+    /// assert_eq!(outcome.parsed.unwrap().bindings.len(), 1);
+    /// ```
+    pub fn from_all_errors(input: ParserInput) -> ParseOutcome {
+        Self::from_all_errors_with_options(input, &ParseOptions::default())
     }
-    unescaped
-}
-fn unbind_parser(pair: Pair<'_, Rule>) -> Result<Vec<Definition>, ParseError> {
-    let mut uncompiled = DefinitionUncompiled::default();
-    for thing in pair.into_inner() {
-        uncompiled.ingest(thing)?;
-    }
-    Ok(uncompiled.compile())
-}
-
-fn import_parser(pair: Pair<'_, Rule>) -> Vec<String> {
-    pair.into_inner()
-        .filter(|component| matches!(component.as_rule(), Rule::import_file))
-        .map(pair_to_string)
-        .collect()
-}
 
-fn parse_key(component: Pair<'_, Rule>) -> KeyRepr {
-    let mut attribute = KeyAttribute::None;
-    let mut key = String::default();
-    for inner in component.into_inner() {
-        match inner.as_rule() {
-            Rule::send => attribute |= KeyAttribute::Send,
-            Rule::on_release => attribute |= KeyAttribute::OnRelease,
-            Rule::shorthand_allow | Rule::key_base => {
-                key = unescape(&inner.as_str().to_lowercase()).to_string()
-            }
-            _ => {}
+    /// Like [`SwhkdParser::from_all_errors`], with non-default
+    /// [`ParseOptions`].
+    pub fn from_all_errors_with_options(
+        input: ParserInput,
+        options: &ParseOptions,
+    ) -> ParseOutcome {
+        let mut ctx = ImportContext {
+            options: options.clone(),
+            errors: Some(ErrorLog::new(options.max_errors)),
+            ..Default::default()
+        };
+        let parsed = Self::from_context(input, &mut ctx);
+        let log = ctx.errors.unwrap_or_default();
+        let (parsed, errors) = match parsed {
+            Ok(parsed) => (Some(parsed), log.finish(None)),
+            Err(err) => (None, log.finish(Some(err))),
+        };
+        ParseOutcome {
+            parsed,
+            errors,
+            max_report_bytes: options.max_report_bytes,
         }
     }
-    KeyRepr { key, attribute }
-}
 
-fn parse_command_shorthand(pair: Pair<'_, Rule>) -> Result<Vec<String>, ParseError> {
-    let mut command_variants = vec![];
-
-    for component in pair.into_inner() {
-        match component.as_rule() {
-            Rule::command_component => {
-                command_variants.push(unescape(component.as_str()).to_string())
-            }
-            Rule::range => {
-                let (lower_bound, upper_bound) = Bounds::new(component).expand_commands()?;
-                command_variants.extend((lower_bound..=upper_bound).map(|key| key.to_string()));
-            }
-            _ => {}
-        }
+    /// The top-level bindings of a config, expanded one at a time instead
+    /// of all at once like [`SwhkdParser::from`], e.g. to stream a generated
+    /// config expanding to a huge number of bindings.
+    ///
+    /// The whole config is read and checked first, and an error is the
+    /// only item if it fails like [`SwhkdParser::from`] would. Only then
+    /// are the bindings built, in the order of [`SwhkdParser::bindings`] but
+    /// before merging: a binding overridden by a later one on the same
+    /// chord is yielded as well, and `ignore` statements do not apply.
+    /// Bindings of modes and of inactive profiles are left out. Since they
+    /// are never all held at once, the top-level bindings do not count
+    /// towards the variant limits of [`ParseOptions`].
+    ///
+    /// ```
+    /// use sweet::{ParserInput, SwhkdParser};
+    ///
+    /// let config = "super + {_, shift +} {a-z}\n    bspc {node,desktop} -f {a-z}";
+    /// let mut bindings = SwhkdParser::bindings_iter(ParserInput::Raw(config));
+    /// let first = bindings.next().unwrap()?;
+    /// assert_eq!(first.command(), "bspc node -f a");
+    /// assert_eq!(bindings.count(), 51);
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn bindings_iter(input: ParserInput) -> impl Iterator<Item = Result<Binding, ParseError>> {
+        let (deferred, error) = match Self::deferred_bindings(input) {
+            Ok(deferred) => (deferred, None),
+            Err(err) => (vec![], Some(err)),
+        };
+        error
+            .map(Err)
+            .into_iter()
+            .chain(deferred.into_iter().flatten().map(Ok))
     }
-    Ok(command_variants)
-}
-fn mode_parser(pair: Pair<'_, Rule>) -> Result<Mode, ParseError> {
-    let mut mode = Mode::default();
-    for component in pair.into_inner() {
-        match component.as_rule() {
-            Rule::modename => mode.name = component.as_str().to_string(),
-            Rule::binding => mode.bindings.extend(binding_parser(component)?),
-            Rule::unbind => mode.unbinds.extend(unbind_parser(component)?),
-            Rule::oneoff => mode.oneoff = true,
-            Rule::swallow => mode.swallow = true,
-            _ => {}
-        }
+
+    /// Bindings triggered by virtual keys, which the daemon dispatches by
+    /// name instead of through evdev.
+    pub fn virtual_bindings(&self) -> impl Iterator<Item = &Binding> {
+        self.bindings
+            .iter()
+            .filter(|binding| binding.definition().is_virtual())
     }
-    Ok(mode)
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ModeInstruction {
+    /// Enters the mode of that name, see [`SwhkdParser::entered_mode`].
     Enter(String),
+    /// Leaves the active mode. In a top-level binding, which also fires
+    /// while a mode is active unless the mode binds the same chord, it leaves
+    /// whichever mode is active and does nothing outside of modes.
     Escape,
 }
-
-fn binding_parser(pair: Pair<'_, Rule>) -> Result<Vec<Binding>, ParseError> {
-    let mut comm = vec![];
-    let mut mode_enters = vec![];
-    let mut mode_escapes = vec![];
-    let mut uncompiled = DefinitionUncompiled::default();
-    for component in pair.clone().into_inner() {
-        match component.as_rule() {
-            Rule::command => {
-                for subcomponent in component.into_inner() {
-                    match subcomponent.as_rule() {
-                        Rule::command_standalone => {
-                            comm.push(vec![pair_to_string(subcomponent)]);
-                        }
-                        Rule::command_shorthand => {
-                            comm.push(parse_command_shorthand(subcomponent)?);
-                        }
-                        Rule::command_double_ampersand => {
-                            if comm
-                                .last()
-                                .is_some_and(|last| last.len() == 1 && last[0] == "&&")
-                            {
-                                continue;
-                            }
-                            comm.push(vec![pair_to_string(subcomponent)]);
-                        }
-                        Rule::enter_mode => {
-                            // Safety: the first element is guaranteed to be a modename
-                            // by the grammar.
-                            let modename = subcomponent.into_inner().next().unwrap();
-                            mode_enters.push(ModeInstruction::Enter(pair_to_string(modename)));
-                        }
-                        Rule::escape_mode => {
-                            if mode_enters.pop().is_none() {
-                                mode_escapes.push(ModeInstruction::Escape);
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-
-                if comm
-                    .last()
-                    .is_some_and(|last| last.len() == 1 && last[0] == "&&")
-                {
-                    comm.pop();
-                }
-            }
-            _ => uncompiled.ingest(component)?,
-        }
-    }
-    let bind_cartesian_product = uncompiled.compile();
-    let command_cartesian_product = comm
-        .into_iter()
-        .multi_cartesian_product()
-        .map(|c| c.join(""))
-        .collect_vec();
-    let bind_len = bind_cartesian_product.len();
-    let command_len = command_cartesian_product.len();
-
-    if bind_len != command_len {
-        let err = pest::error::Error::new_from_span(
-            pest::error::ErrorVariant::<Rule>::CustomError {
-                message: format!(
-                    "the number of possible binding variants {0} does not equal the number of possible command variants {1}.",
-                    bind_len, command_len
-                ),
-            },
-            pair.as_span(),
-        );
-        return Err(Box::new(err).into());
-    }
-
-    let mut bindings: Vec<Binding> = bind_cartesian_product
-        .into_iter()
-        .zip(command_cartesian_product)
-        .map(|(definition, command)| Binding {
-            definition,
-            command,
-            mode_instructions: mode_enters
-                .iter()
-                .chain(mode_escapes.iter())
-                .cloned()
-                .collect(),
-        })
-        .collect();
-
-    for binding in bindings.iter_mut() {
-        binding.definition.modifiers.remove(&Modifier::Omission);
-    }
-    Ok(bindings)
-}
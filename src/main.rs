@@ -1,24 +1,366 @@
 use anyhow::{bail, Result};
-use std::path::Path;
-use sweet::{ParserInput, SwhkdParser};
+use std::{
+    io::IsTerminal,
+    path::Path,
+    process::ExitCode,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use sweet::{
+    diff_previews, fix_homoglyphs, normalize_commands, show_watch_events, CommandForm,
+    ConfigWatcher, Diagnostic, DumpFormat, DumpOptions, ParseOptions, ParserInput, RenderStyle,
+    ReportOptions, SwhkdParser,
+};
 
-fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
     let Some(arg) = std::env::args().nth(1) else {
         bail!("please supply a path to a hotkeys config file");
     };
-    let parser = SwhkdParser::from(ParserInput::Path(Path::new(&arg)))?;
+    if arg == "preview" {
+        return Ok(preview(std::env::args().skip(2)));
+    }
+    if arg == "report" {
+        return report(std::env::args().skip(2)).map(|()| ExitCode::SUCCESS);
+    }
+    if arg == "check" {
+        return check(std::env::args().skip(2));
+    }
+    if arg == "fix" {
+        return fix(std::env::args().skip(2)).map(|()| ExitCode::SUCCESS);
+    }
+    if arg == "dump" {
+        return dump(std::env::args().skip(2));
+    }
+    print_dump(&arg, DumpFormat::Text, DumpOptions::default())
+}
+
+/// `sweet dump [--format text|json] [--effective-settings] <config>`
+/// prints the parsed config, see [`SwhkdParser::dump_with_options`].
+/// `sweet <config>` is short for the text format.
+fn dump(mut args: impl Iterator<Item = String>) -> Result<ExitCode> {
+    const USAGE: &str = "usage: sweet dump [--format text|json] [--effective-settings] <config>";
+    let mut format = DumpFormat::Text;
+    let mut options = DumpOptions::default();
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = match args.next().as_deref() {
+                    Some("text") => DumpFormat::Text,
+                    Some("json") => DumpFormat::Json,
+                    _ => bail!(USAGE),
+                }
+            }
+            "--effective-settings" => options.effective_settings = true,
+            _ if path.is_none() => path = Some(arg),
+            _ => bail!(USAGE),
+        }
+    }
+    let Some(path) = path else {
+        bail!(USAGE);
+    };
+    print_dump(&path, format, options)
+}
+
+/// Prints the config at `path` in `format`, or its error, exiting with 1
+/// when it does not parse. The text format prints warnings to stderr, the
+/// JSON format holds them.
+fn print_dump(path: &str, format: DumpFormat, options: DumpOptions) -> Result<ExitCode> {
+    let parser = match SwhkdParser::from(ParserInput::Path(Path::new(path))) {
+        Ok(parser) => parser,
+        Err(err) => {
+            let style = auto_style(std::io::stderr().is_terminal());
+            eprintln!("{}", Diagnostic::from(&err).render(style));
+            return Ok(ExitCode::from(1));
+        }
+    };
+    if format == DumpFormat::Text {
+        for warning in &parser.warnings {
+            eprintln!("warning: {}", warning);
+        }
+        for unreachable in parser.unreachable_bindings() {
+            eprintln!("warning: {}", unreachable);
+        }
+    }
+    let dump = parser.dump_with_options(format, options);
+    match format {
+        DumpFormat::Text => print!("{}", dump),
+        DumpFormat::Json => println!("{}", dump),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `sweet preview <config>` prints what every key runs, and
+/// `sweet preview --diff <old> <new>` compares two configs that way. Like
+/// diff(1), exits with 1 when they differ and 2 on errors.
+fn preview(args: impl Iterator<Item = String>) -> ExitCode {
+    let mut diff = false;
+    let mut paths = vec![];
+    for arg in args {
+        match arg.as_str() {
+            "--diff" => diff = true,
+            _ => paths.push(arg),
+        }
+    }
+    let expected = if diff { 2 } else { 1 };
+    if paths.len() != expected {
+        eprintln!(
+            "usage: sweet preview <config> or sweet preview --diff <old config> <new config>"
+        );
+        return ExitCode::from(2);
+    }
+    let mut previews = vec![];
+    for path in &paths {
+        match SwhkdParser::preview(ParserInput::Path(Path::new(path))) {
+            Ok(preview) => previews.push(preview),
+            Err(err) => {
+                eprintln!("Error: {:#}", anyhow::Error::new(err).context(path.clone()));
+                return ExitCode::from(2);
+            }
+        }
+    }
+    if !diff {
+        for line in &previews[0] {
+            println!("{}", line);
+        }
+        return ExitCode::SUCCESS;
+    }
+    match diff_previews(&previews[0], &previews[1], &paths[0], &paths[1]) {
+        Some(diff) => {
+            print!("{}", diff);
+            ExitCode::from(1)
+        }
+        None => ExitCode::SUCCESS,
+    }
+}
+
+/// `sweet report [--include-contents] [--json] <config>` prints a report to
+/// paste into bug reports.
+fn report(args: impl Iterator<Item = String>) -> Result<()> {
+    let mut options = ReportOptions::default();
+    let mut json = false;
+    let mut path = None;
+    for arg in args {
+        match arg.as_str() {
+            "--include-contents" => options.include_contents = true,
+            "--json" => json = true,
+            _ if path.is_none() => path = Some(arg),
+            _ => bail!("unexpected argument `{}`", arg),
+        }
+    }
+    let Some(path) = path else {
+        bail!("please supply a path to a hotkeys config file");
+    };
+    let report = SwhkdParser::report(ParserInput::Path(Path::new(&path)), options);
+    if json {
+        println!("{}", report.to_json());
+    } else {
+        print!("{}", report.to_markdown());
+    }
+    Ok(())
+}
+
+/// Colors diagnostics written to a terminal, unless `NO_COLOR` is set to
+/// anything but an empty string, see <https://no-color.org>.
+fn auto_style(terminal: bool) -> RenderStyle {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    if no_color || !terminal {
+        RenderStyle::Plain
+    } else {
+        RenderStyle::Color
+    }
+}
 
-    for binding in parser.bindings {
-        println!("{}", binding);
+/// The warnings of a config that parsed, unreachable bindings included.
+fn diagnostics(parser: &SwhkdParser) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<_> = parser.warnings.iter().map(Diagnostic::from).collect();
+    diagnostics.extend(parser.unreachable_bindings().iter().map(Diagnostic::from));
+    diagnostics
+}
+
+/// The options `sweet check` parses with, checking the shell syntax of
+/// commands as well.
+fn options() -> ParseOptions {
+    ParseOptions::default().with_validate_commands(true)
+}
+
+/// `sweet check <config>` prints every error of a config, or its warnings
+/// when it has no errors, and exits with 1 when it does not parse. At most
+/// 50 errors are printed, `--max-errors=<n>` changes the limit and
+/// `--max-errors=0` lifts it. With `--watch`, see [`watch`].
+fn check(args: impl Iterator<Item = String>) -> Result<ExitCode> {
+    const USAGE: &str = "usage: sweet check [--watch] [--max-errors=<n>] <config> \
+         or sweet check --grammar-coverage <dir>";
+    let mut watching = false;
+    let mut coverage = false;
+    let mut path = None;
+    let mut options = options();
+    for arg in args {
+        match arg.as_str() {
+            "--watch" => watching = true,
+            "--grammar-coverage" => coverage = true,
+            _ if arg.starts_with("--max-errors=") => {
+                let Ok(limit) = arg["--max-errors=".len()..].parse() else {
+                    bail!(USAGE);
+                };
+                options = options.with_max_errors(limit);
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => bail!(USAGE),
+        }
+    }
+    let Some(path) = path else {
+        bail!(USAGE);
+    };
+    match (watching, coverage) {
+        (true, true) => bail!(USAGE),
+        (true, false) => return Ok(watch(&path)),
+        (false, true) => return grammar_coverage(Path::new(&path)),
+        (false, false) => {}
+    }
+    let style = auto_style(std::io::stderr().is_terminal());
+    let outcome =
+        SwhkdParser::from_all_errors_with_options(ParserInput::Path(Path::new(&path)), &options);
+    if !outcome.errors.is_empty() {
+        eprintln!("{}", outcome.render(style));
+        return Ok(ExitCode::from(1));
+    }
+    let Ok(parser) = outcome.into_result() else {
+        return Ok(ExitCode::from(1));
+    };
+    let diagnostics = diagnostics(&parser);
+    for diagnostic in &diagnostics {
+        eprintln!("{}", diagnostic.render(style));
+    }
+    match diagnostics.len() {
+        0 => println!("{}: ok", path),
+        1 => println!("{}: 1 warning", path),
+        count => println!("{}: {} warnings", path, count),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `sweet check --grammar-coverage <dir>` parses every file in the
+/// directory and prints how many times each rule of the grammar was
+/// visited, rules never visited first. A development aid for the grammar,
+/// only available with the `grammar-coverage` feature.
+#[cfg(feature = "grammar-coverage")]
+fn grammar_coverage(dir: &Path) -> Result<ExitCode> {
+    let mut coverage = sweet::RuleCoverage::default();
+    let mut files = 0;
+    let mut entries = std::fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.path());
+    for entry in entries {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        // Rules are recorded as the grammar runs, configs failing later on
+        // count as well.
+        let (_, file) = sweet::record_rules(|| SwhkdParser::from(ParserInput::Path(&path)));
+        coverage.merge(&file);
+        files += 1;
+    }
+    let mut rules: Vec<_> = coverage.rules().collect();
+    rules.sort_by_key(|(_, hits)| *hits != 0);
+    for (rule, hits) in &rules {
+        println!("{hits:>8} {rule:?}");
+    }
+    let reached = rules.iter().filter(|(_, hits)| *hits != 0).count();
+    println!(
+        "{reached} of {} rules visited over {files} files",
+        rules.len()
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(not(feature = "grammar-coverage"))]
+fn grammar_coverage(_dir: &Path) -> Result<ExitCode> {
+    bail!("`--grammar-coverage` needs sweet built with the `grammar-coverage` feature")
+}
+
+/// How often `sweet check --watch` looks at the files of the config.
+const WATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Set once `sweet check --watch` receives SIGINT or SIGTERM.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+/// `sweet check --watch <config>` checks the config again whenever it or
+/// one of its includes changes, redrawing the screen with the diagnostics
+/// or a summary. Once interrupted, exits like `sweet check` would have on
+/// the last state, with 1 if the config was missing.
+fn watch(path: &str) -> ExitCode {
+    let handler = interrupt as extern "C" fn(libc::c_int);
+    // Safety: the handler only stores to an atomic, which is
+    // async-signal-safe.
+    unsafe {
+        libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+        libc::signal(libc::SIGTERM, handler as libc::sighandler_t);
+    }
+    let terminal = std::io::stdout().is_terminal();
+    let style = auto_style(terminal);
+    let mut watcher = ConfigWatcher::with_options(path, options());
+    let mut first = true;
+    let polls = std::iter::from_fn(|| {
+        if !std::mem::take(&mut first) {
+            std::thread::sleep(WATCH_INTERVAL);
+        }
+        (!INTERRUPTED.load(Ordering::Relaxed)).then(|| watcher.poll())
+    });
+    match show_watch_events(path, polls, &mut std::io::stdout(), style, terminal) {
+        Ok(true) => ExitCode::SUCCESS,
+        _ => ExitCode::from(1),
+    }
+}
+
+/// `sweet fix --unicode <config>` replaces Unicode lookalikes of ASCII
+/// characters outside of commands, and `--inline` or `--indented` puts the
+/// commands of bindings in that form, printing the changed lines. The
+/// config is only rewritten with `--write`.
+fn fix(args: impl Iterator<Item = String>) -> Result<()> {
+    let mut unicode = false;
+    let mut form = None;
+    let mut write = false;
+    let mut path = None;
+    for arg in args {
+        match arg.as_str() {
+            "--unicode" => unicode = true,
+            "--write" => write = true,
+            "--inline" => form = Some(CommandForm::Inline),
+            "--indented" => form = Some(CommandForm::Indented),
+            _ if path.is_none() => path = Some(arg),
+            _ => bail!("unexpected argument `{}`", arg),
+        }
+    }
+    if !unicode && form.is_none() {
+        bail!("please choose what to fix: `--unicode`, `--inline` or `--indented`");
+    }
+    let Some(path) = path else {
+        bail!("please supply a path to a hotkeys config file");
+    };
+    let original = std::fs::read_to_string(&path)?;
+    let mut fixed = original.clone();
+    if unicode {
+        fixed = fix_homoglyphs(&fixed);
+    }
+    if let Some(form) = form {
+        fixed = normalize_commands(&fixed, form)?;
     }
-    for unbind in parser.unbinds {
-        println!("unbind: {}", unbind);
+    if fixed == original {
+        println!("{}: nothing to fix", path);
+        return Ok(());
     }
-    for import in parser.imports {
-        println!("import: {:?}", import);
+    let before: Vec<_> = original.lines().collect();
+    let after: Vec<_> = fixed.lines().collect();
+    if let Some(diff) = diff_previews(&before, &after, &path, &path) {
+        print!("{}", diff);
     }
-    for mode in parser.modes {
-        println!("mode: {:?}", mode);
+    if write {
+        std::fs::write(&path, fixed)?;
     }
     Ok(())
 }
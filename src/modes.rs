@@ -0,0 +1,159 @@
+use std::collections::{BTreeSet, HashSet};
+
+use crate::{Binding, Mode, ModeInstruction, ParseError, SourceSpan, SwhkdParser};
+
+/// Merges modes declared more than once, e.g. once in the config and once
+/// in an include, into the first declaration of their name. Bindings of a
+/// later declaration override the ones of an earlier declaration on the
+/// same chord, everything else is combined.
+pub(crate) fn merge_modes(modes: Vec<Mode>) -> Vec<Mode> {
+    let mut merged: Vec<Mode> = vec![];
+    for mode in modes {
+        let Some(earlier) = merged.iter_mut().find(|earlier| earlier.name == mode.name) else {
+            merged.push(mode);
+            continue;
+        };
+        let triggers: HashSet<_> = mode
+            .bindings
+            .iter()
+            .map(|binding| (binding.sequence(), binding.device()))
+            .collect();
+        earlier
+            .bindings
+            .retain(|binding| !triggers.contains(&(binding.sequence(), binding.device())));
+        earlier.bindings.extend(mode.bindings);
+        earlier.unbinds.extend(mode.unbinds);
+        earlier.sequence_unbinds.extend(mode.sequence_unbinds);
+        earlier.replay.extend(mode.replay);
+        earlier.oneoff |= mode.oneoff;
+        earlier.swallow |= mode.swallow;
+        earlier.settings = mode.settings.or(&earlier.settings);
+        earlier.description = mode.description.or(earlier.description.take());
+    }
+    for mode in &mut merged {
+        for binding in &mut mode.bindings {
+            binding.record_mode(Some(&mode.name));
+        }
+    }
+    merged
+}
+
+/// Removes the modes named by `ignore mode` statements, along with the
+/// bindings entering them, top-level ones and ones of the other modes
+/// alike.
+pub(crate) fn ignore_modes(
+    ignored: &BTreeSet<String>,
+    bindings: &mut Vec<Binding>,
+    modes: &mut Vec<Mode>,
+) {
+    if ignored.is_empty() {
+        return;
+    }
+    let keep = |binding: &Binding| {
+        !binding.mode_instructions().iter().any(|instruction| {
+            matches!(instruction, ModeInstruction::Enter(name) if ignored.contains(name))
+        })
+    };
+    bindings.retain(keep);
+    modes.retain(|mode| !ignored.contains(&mode.name));
+    for mode in modes {
+        mode.bindings.retain(keep);
+    }
+}
+
+impl SwhkdParser {
+    /// The mode an `@enter` instruction enters, along with its index in
+    /// [`SwhkdParser::modes`], e.g. to tell whether it is `oneoff` or
+    /// `swallow` when the instruction runs. `None` for `@escape`.
+    ///
+    /// Modes are looked up in the whole config, so a mode declared in an
+    /// include or after the binding entering it resolves as well. Every
+    /// `@enter` of a parsed config resolves, see [`SwhkdParser::from`].
+    ///
+    /// ```
+    /// use sweet::{ParserInput, SwhkdParser};
+    ///
+    /// let config = "
+    /// super + r
+    ///     @enter resize
+    /// mode resize oneoff
+    ///     h
+    ///         bspc node -z left -20 0
+    /// endmode";
+    /// let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    /// let enter = &parsed.bindings[0].mode_instructions()[0];
+    /// let (index, mode) = parsed.entered_mode(enter).unwrap();
+    /// assert_eq!((index, mode.oneoff, mode.swallow), (0, true, false));
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn entered_mode(&self, instruction: &ModeInstruction) -> Option<(usize, &Mode)> {
+        let ModeInstruction::Enter(name) = instruction else {
+            return None;
+        };
+        self.modes
+            .iter()
+            .enumerate()
+            .find(|(_, mode)| &mode.name == name)
+    }
+
+    /// Fails on the first binding entering a mode that is not declared.
+    pub(crate) fn check_mode_references(&self) -> Result<(), ParseError> {
+        let declared = self.mode_names();
+        let scopes =
+            std::iter::once(&self.bindings).chain(self.modes.iter().map(|mode| &mode.bindings));
+        match scopes.flatten().find_map(|binding| {
+            unknown_mode(&declared, binding.mode_instructions(), binding.span())
+        }) {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Removes the bindings entering a mode that is not declared, recording
+    /// an error for every declaration they were expanded from.
+    pub(crate) fn remove_unknown_mode_bindings(&mut self, errors: &mut Vec<ParseError>) {
+        let declared = self.mode_names();
+        let mut reported = vec![];
+        let mut keep = |binding: &Binding| {
+            let Some(err) = unknown_mode(&declared, binding.mode_instructions(), binding.span())
+            else {
+                return true;
+            };
+            let declaration = binding.span().cloned();
+            if !reported.contains(&declaration) {
+                reported.push(declaration);
+                errors.push(err);
+            }
+            false
+        };
+        self.bindings.retain(&mut keep);
+        for mode in &mut self.modes {
+            mode.bindings.retain(&mut keep);
+        }
+    }
+
+    pub(crate) fn mode_names(&self) -> Vec<String> {
+        self.modes.iter().map(|mode| mode.name.clone()).collect()
+    }
+}
+
+/// The error of the first of `instructions` of the binding at `span`
+/// entering a mode missing from `declared`.
+pub(crate) fn unknown_mode<'a>(
+    declared: &[String],
+    instructions: impl IntoIterator<Item = &'a ModeInstruction>,
+    span: Option<&SourceSpan>,
+) -> Option<ParseError> {
+    instructions
+        .into_iter()
+        .find_map(|instruction| match instruction {
+            ModeInstruction::Enter(name) if !declared.contains(name) => {
+                Some(ParseError::UnknownMode {
+                    name: name.clone(),
+                    declared: declared.to_vec(),
+                    span: span.cloned(),
+                })
+            }
+            _ => None,
+        })
+}
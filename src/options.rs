@@ -0,0 +1,203 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use crate::{
+    resolver::{FsResolver, ResolverSlot},
+    ImportResolver, IndentStyle,
+};
+
+/// Options controlling how a config is parsed, see
+/// [`SwhkdParser::from_with_options`](crate::SwhkdParser::from_with_options).
+///
+/// New options may be added in any release, build them with the setters:
+///
+/// ```
+/// use sweet::{ImportPolicy, ParseOptions};
+///
+/// let options = ParseOptions::default()
+///     .with_profile("laptop")
+///     .with_max_config_bytes(64 * 1024)
+///     .with_follow_imports(ImportPolicy::Deny);
+/// assert!(options.active_profiles.contains("laptop"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ParseOptions {
+    /// Profiles whose tagged declarations are part of the parsed config.
+    /// Untagged declarations are always included.
+    pub active_profiles: BTreeSet<String>,
+    /// Size limit for the config and each included file. `None` uses the
+    /// limit sweet was built with, which a `Some` limit replaces, whether
+    /// smaller or larger.
+    pub max_config_bytes: Option<u64>,
+    /// What to do with `include` statements.
+    pub follow_imports: ImportPolicy,
+    /// How deeply includes may nest, 1 allowing the config to include
+    /// files that include nothing. 64 by default.
+    pub max_import_depth: usize,
+    /// How many files a config may include, each file counting once
+    /// however many times it is included. 4,096 by default.
+    pub max_imports: usize,
+    /// Whether to fill [`SwhkdParser::warnings`](crate::SwhkdParser::warnings).
+    /// On by default.
+    pub collect_warnings: bool,
+    /// The most errors [`SwhkdParser::from_all_errors`](crate::SwhkdParser::from_all_errors)
+    /// keeps, 0 for no limit. 50 by default.
+    pub max_errors: usize,
+    /// The size in bytes past which [`ParseOutcome::render`](crate::ParseOutcome::render)
+    /// leaves errors out, 0 for no limit. 64 KiB by default.
+    pub max_report_bytes: usize,
+    /// Whether chords may hold several keys, e.g. `ctrl + x & e`. Off by
+    /// default, since swhkd only acts on the key that fires.
+    pub multi_key_chords: bool,
+    /// The most bindings a single declaration may expand to, 65,536 by
+    /// default. Shorthands multiply, so a short line can otherwise expand
+    /// to enough bindings to exhaust memory.
+    pub max_binding_variants: usize,
+    /// The most bindings and unbinds a whole config may expand to,
+    /// 4,194,304 by default, which leaves room for 64 declarations at the
+    /// limit of a single one.
+    pub max_total_variants: usize,
+    /// How commands and mode bodies must be indented. Lines indented
+    /// otherwise come with a [`Warning::Indentation`](crate::Warning::Indentation)
+    /// but parse the same. [`IndentStyle::Any`] by default.
+    pub indent_style: IndentStyle,
+    /// Whether to check the shell syntax of commands, see
+    /// [`lint_command`](crate::lint_command). Findings come as
+    /// [`Warning::CommandSyntax`](crate::Warning::CommandSyntax). Off by
+    /// default, since valid but unusual shell may be reported.
+    pub validate_commands: bool,
+    /// See [`ParseOptions::with_resolver`].
+    pub(crate) resolver: ResolverSlot,
+}
+
+/// The default of [`ParseOptions::max_binding_variants`].
+const DEFAULT_MAX_VARIANTS: usize = 1 << 16;
+
+/// The default of [`ParseOptions::max_total_variants`].
+const DEFAULT_MAX_TOTAL_VARIANTS: usize = 1 << 22;
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            active_profiles: BTreeSet::new(),
+            max_config_bytes: None,
+            follow_imports: ImportPolicy::default(),
+            max_import_depth: 64,
+            max_imports: 4096,
+            collect_warnings: true,
+            max_errors: 50,
+            max_report_bytes: 64 << 10,
+            multi_key_chords: false,
+            max_binding_variants: DEFAULT_MAX_VARIANTS,
+            max_total_variants: DEFAULT_MAX_TOTAL_VARIANTS,
+            indent_style: IndentStyle::default(),
+            validate_commands: false,
+            resolver: ResolverSlot::default(),
+        }
+    }
+}
+
+/// What the parser does with `include` statements.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImportPolicy {
+    /// Read included files and merge them into the config.
+    #[default]
+    Follow,
+    /// Leave included files out without reading them.
+    Ignore,
+    /// Fail on the first `include` statement, e.g. for configs from
+    /// untrusted sources.
+    Deny,
+}
+
+impl ParseOptions {
+    /// Activates the profile `name`.
+    pub fn with_profile<S: Into<String>>(mut self, name: S) -> Self {
+        self.active_profiles.insert(name.into());
+        self
+    }
+
+    pub fn with_max_config_bytes(mut self, bytes: u64) -> Self {
+        self.max_config_bytes = Some(bytes);
+        self
+    }
+
+    pub fn with_follow_imports(mut self, policy: ImportPolicy) -> Self {
+        self.follow_imports = policy;
+        self
+    }
+
+    pub fn with_max_import_depth(mut self, depth: usize) -> Self {
+        self.max_import_depth = depth;
+        self
+    }
+
+    pub fn with_max_imports(mut self, count: usize) -> Self {
+        self.max_imports = count;
+        self
+    }
+
+    pub fn with_collect_warnings(mut self, collect: bool) -> Self {
+        self.collect_warnings = collect;
+        self
+    }
+
+    /// See [`ParseOptions::max_errors`], 0 lifts the limit.
+    pub fn with_max_errors(mut self, limit: usize) -> Self {
+        self.max_errors = limit;
+        self
+    }
+
+    /// See [`ParseOptions::max_report_bytes`], 0 lifts the limit.
+    pub fn with_max_report_bytes(mut self, limit: usize) -> Self {
+        self.max_report_bytes = limit;
+        self
+    }
+
+    /// Accepts chords of several keys, e.g. `ctrl + x & e`, see
+    /// [`Definition::held_keys`](crate::Definition::held_keys).
+    pub fn with_multi_key_chords(mut self, enabled: bool) -> Self {
+        self.multi_key_chords = enabled;
+        self
+    }
+
+    /// See [`ParseOptions::max_binding_variants`], `usize::MAX` lifts the
+    /// limit.
+    pub fn with_max_binding_variants(mut self, limit: usize) -> Self {
+        self.max_binding_variants = limit;
+        self
+    }
+
+    /// See [`ParseOptions::max_total_variants`], `usize::MAX` lifts the
+    /// limit.
+    pub fn with_max_total_variants(mut self, limit: usize) -> Self {
+        self.max_total_variants = limit;
+        self
+    }
+
+    pub fn with_indent_style(mut self, style: IndentStyle) -> Self {
+        self.indent_style = style;
+        self
+    }
+
+    pub fn with_validate_commands(mut self, enabled: bool) -> Self {
+        self.validate_commands = enabled;
+        self
+    }
+
+    /// Reads the config and its includes through `resolver` instead of
+    /// from disk. [`ParseOptions::max_config_bytes`] still applies.
+    pub fn with_resolver(mut self, resolver: Arc<dyn ImportResolver>) -> Self {
+        self.resolver = ResolverSlot(Some(resolver));
+        self
+    }
+
+    /// The resolver set with [`ParseOptions::with_resolver`], or a
+    /// [`FsResolver`] by default.
+    pub(crate) fn resolver(&self) -> Arc<dyn ImportResolver> {
+        match &self.resolver.0 {
+            Some(resolver) => resolver.clone(),
+            None => Arc::new(FsResolver::limited(self.max_config_bytes)),
+        }
+    }
+}
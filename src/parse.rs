@@ -0,0 +1,853 @@
+//! Walking the grammar of one file into its bindings, unbinds, modes and
+//! settings. Includes are read in [`crate::resolve`], and shorthands are
+//! expanded in [`crate::expand`].
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use itertools::Itertools;
+use pest::{
+    iterators::{Pair, Pairs},
+    Parser, Span,
+};
+
+use crate::{
+    bindings::{BindingVariants, CommandUncompiled},
+    blocks::{self, Blocks},
+    definition::modifier,
+    diagnostics, evdev_mappings,
+    expand::{self, unescape, Product, KEY_ESCAPES},
+    indent,
+    ineffective::{IgnoreSite, ModeSite},
+    profiles::profile_tags,
+    resolve::ImportContext,
+    settings::SettingScope,
+    span::Spanned,
+    unused::{References, VisibleDefinitions},
+    variables::{self, Variables},
+    Binding, Definition, DefinitionKind, DefinitionUncompiled, ImportPolicy, KeyAttribute, KeyRepr,
+    Mode, Modifier, ParseError, Rule, Schedule, Settings, SourceSpan, SwhkdGrammar, SwhkdParser,
+    Warning,
+};
+
+/// An `include` statement of a file.
+pub(crate) struct Import {
+    pub(crate) path: String,
+    pub(crate) requested_at: SourceSpan,
+    /// The mode whose block holds the statement, the top-level
+    /// declarations of the included file joining the mode.
+    pub(crate) mode: Option<String>,
+}
+
+/// The includes to read and the variables and blocks defined in one file.
+#[derive(Default)]
+pub(crate) struct FileDefinitions {
+    pub(crate) imports: Vec<Import>,
+    pub(crate) variables: Variables,
+    pub(crate) blocks: Blocks,
+    /// Where the variables and blocks are defined.
+    pub(crate) sites: VisibleDefinitions,
+}
+
+/// Finds the includes to read and the variables and blocks defined in one
+/// file.
+pub(crate) fn imports_and_definitions(
+    contents: Pair<'_, Rule>,
+    source: &str,
+    ctx: &mut ImportContext,
+) -> Result<FileDefinitions, ParseError> {
+    let mut defined = FileDefinitions::default();
+    for decl in contents.into_inner() {
+        match decl.as_rule() {
+            // A later definition replaces an earlier one.
+            Rule::define => {
+                let span = ctx.span(source, decl.as_span());
+                let (name, value) = variables::define_parser(decl);
+                let kind = DefinitionKind::Variable;
+                ctx.definitions
+                    .define(&mut defined.sites, kind, name.clone(), span);
+                defined.variables.insert(name, value);
+            }
+            Rule::block => {
+                let span = ctx.span(source, decl.as_span());
+                let (name, lines) = blocks::block_parser(decl);
+                let kind = DefinitionKind::Block;
+                ctx.definitions
+                    .define(&mut defined.sites, kind, name.clone(), span);
+                defined.blocks.insert(name, lines);
+            }
+            Rule::import => import_parser(decl, None, source, ctx, &mut defined.imports)?,
+            Rule::mode if ctx.profile_active(profile_tags(&decl)) => {
+                let mut components = decl.into_inner();
+                let name = components
+                    .find(|component| component.as_rule() == Rule::modename)
+                    .map(|name| name.as_str().to_string());
+                for component in components.filter(|c| c.as_rule() == Rule::import) {
+                    import_parser(component, name.clone(), source, ctx, &mut defined.imports)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(defined)
+}
+
+/// Adds the files an `include` statement reads to `imports`, `mode` being
+/// the mode whose block holds it.
+fn import_parser(
+    pair: Pair<'_, Rule>,
+    mode: Option<String>,
+    source: &str,
+    ctx: &mut ImportContext,
+    imports: &mut Vec<Import>,
+) -> Result<(), ParseError> {
+    // Includes of inactive profiles are not even read.
+    if !ctx.profile_active(profile_tags(&pair)) {
+        return Ok(());
+    }
+    match ctx.options.follow_imports {
+        ImportPolicy::Follow => {}
+        ImportPolicy::Ignore => return Ok(()),
+        ImportPolicy::Deny => {
+            return Err(spanned_error(
+                "`include` is not allowed by the parse options".to_string(),
+                pair.as_span(),
+            ))
+        }
+    }
+    let requested_at = ctx.span(source, pair.as_span());
+    for component in pair.into_inner() {
+        if component.as_rule() == Rule::import_file {
+            imports.push(Import {
+                path: pair_to_string(component),
+                requested_at: requested_at.clone(),
+                mode: mode.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Parses the declarations of one file, with the variables and blocks of
+/// the [`ImportContext`], leaving its includes to the caller.
+pub(crate) fn declarations(
+    contents: Pair<'_, Rule>,
+    source: &str,
+    ctx: &mut ImportContext,
+) -> Result<SwhkdParser, ParseError> {
+    let mut bindings: Vec<Binding> = vec![];
+    let mut unbinds = vec![];
+    let mut sequence_unbinds = vec![];
+    let mut modes = vec![];
+    let mut ignored_modes = BTreeSet::new();
+    let mut settings = Settings::default();
+    let mut device_settings = BTreeMap::new();
+    // The top-level declarations of a file included in a mode block are
+    // the mode's, see `resolve`.
+    let scoped = ctx.mode_scope.is_some();
+    let setting_scope = match scoped {
+        true => SettingScope::Mode,
+        false => SettingScope::TopLevel,
+    };
+    // Streamed bindings are built one at a time, never all at once.
+    let streamed = ctx.deferred.is_some() && !scoped;
+    indent::warn_about_indentation(contents.clone(), source, ctx);
+    for decl in contents.into_inner() {
+        match decl.as_rule() {
+            Rule::binding => {
+                let active = ctx.profile_active(profile_tags(&decl));
+                warn_about_any_omission(&decl, source, ctx);
+                warn_about_redundant_shift(&decl, source, ctx);
+                let span = decl.as_span();
+                let parsed = binding_parser(decl, source, ctx).and_then(|parsed| {
+                    if !streamed {
+                        ctx.count_variants(parsed.0.len(), source, span)?;
+                    }
+                    Ok(parsed)
+                });
+                let Some((parsed, references)) = ctx.recover(parsed, source, Some(span))? else {
+                    continue;
+                };
+                ctx.definitions.read(
+                    references,
+                    parsed.span().map(|span| (span.clone(), parsed.len())),
+                );
+                match &mut ctx.deferred {
+                    Some(deferred) if streamed && active => deferred.push(parsed),
+                    // Inactive bindings are not iterated over.
+                    Some(_) if streamed => {}
+                    _ if active => bindings.extend(parsed),
+                    _ => ctx.inactive.extend(parsed),
+                }
+            }
+            Rule::setting => {
+                let span = decl.as_span();
+                let ingested = match device_of_setting(&decl) {
+                    Some(Err(err)) => Err(err),
+                    Some(Ok(_)) if scoped => Err(device_setting_in_mode(span)),
+                    Some(Ok(device)) => device_settings
+                        .entry(device)
+                        .or_insert_with(Settings::default)
+                        .ingest(decl, SettingScope::Device),
+                    None => settings.ingest(decl, setting_scope),
+                };
+                ctx.recover(ingested, source, Some(span))?;
+            }
+            Rule::unbind => {
+                let span = decl.as_span();
+                let parsed = unbind_parser(decl, source, ctx);
+                if let Some((chords, sequences)) = ctx.recover(parsed, source, Some(span))? {
+                    ctx.ignores.push(IgnoreSite {
+                        span: ctx.span(source, span),
+                        mode: ctx.mode_scope.clone(),
+                        chords: chords.clone(),
+                        sequences: sequences.clone(),
+                    });
+                    unbinds.extend(chords);
+                    sequence_unbinds.extend(sequences);
+                }
+            }
+            Rule::mode if scoped => {
+                let span = decl.as_span();
+                let name = decl
+                    .clone()
+                    .into_inner()
+                    .find(|component| component.as_rule() == Rule::modename)
+                    .map_or(span, |name| name.as_span());
+                let err = spanned_error(
+                    format!(
+                        "this file is included in the block of mode `{}`, it cannot declare modes",
+                        ctx.mode_scope.as_deref().unwrap_or_default()
+                    ),
+                    name,
+                );
+                ctx.recover(Err::<(), _>(err), source, Some(span))?;
+            }
+            Rule::mode => {
+                let active = ctx.profile_active(profile_tags(&decl));
+                let span = decl.as_span();
+                let mut components = decl.clone().into_inner();
+                let name = components.find(|component| component.as_rule() == Rule::modename);
+                let declares_bindings =
+                    components.any(|component| component.as_rule() == Rule::binding);
+                let ignores = ctx.ignores.len();
+                let parsed = mode_parser(decl, source, ctx);
+                let Some(mode) = ctx.recover(parsed, source, Some(span))? else {
+                    continue;
+                };
+                // The `ignore` statements of a mode left out have no
+                // bindings to match.
+                if !active {
+                    ctx.ignores.truncate(ignores);
+                }
+                if active {
+                    if let Some(name) = name {
+                        ctx.mode_sites.push(ModeSite {
+                            name: mode.name.clone(),
+                            span: ctx.span(source, name.as_span()),
+                            declares_bindings,
+                        });
+                    }
+                    modes.push(mode);
+                } else {
+                    ctx.inactive.extend(mode.bindings);
+                }
+            }
+            Rule::unbind_mode => {
+                let span = ctx.span(source, decl.as_span());
+                let name = decl
+                    .into_inner()
+                    .find(|component| component.as_rule() == Rule::modename)
+                    .map(pair_to_string)
+                    .unwrap_or_default();
+                ctx.mode_ignores.push((name.clone(), span));
+                ignored_modes.insert(name);
+            }
+            // See `imports_and_definitions`.
+            Rule::import | Rule::define | Rule::block => {}
+            // End of identifier
+            // Here, it means the end of the file.
+            Rule::EOI => {}
+            rule => {
+                let message = format!("unexpected {rule:?} among the declarations");
+                return Err(spanned_error(message, decl.as_span()));
+            }
+        }
+    }
+
+    let parsed = SwhkdParser {
+        bindings,
+        unbinds,
+        sequence_unbinds,
+        modes,
+        ignored_modes,
+        settings,
+        device_settings,
+        ..Default::default()
+    };
+    Ok(parsed)
+}
+
+/// Runs the grammar on the file `source`, explaining common mistakes in
+/// its errors.
+pub(crate) fn parse_main<'a>(raw: &'a str, source: &str) -> Result<Pair<'a, Rule>, ParseError> {
+    let parse_result = run_grammar(Rule::main, raw).map_err(|err| {
+        let err = diagnostics::explain_braces(*err, raw);
+        let err = diagnostics::explain_any_key(err, raw);
+        let err = diagnostics::explain_eof(err, raw);
+        let err = diagnostics::explain_homoglyphs(err, raw);
+        ParseError::Grammar(Box::new(err.with_path(source)))
+    })?;
+    parse_result
+        .into_iter()
+        .next()
+        .ok_or(ParseError::MainSection)
+}
+
+/// Runs the grammar on `input` from `rule`. Every parse goes through here,
+/// so that the `grammar-coverage` feature sees the rules it visits.
+pub(crate) fn run_grammar(
+    rule: Rule,
+    input: &str,
+) -> Result<Pairs<'_, Rule>, Box<pest::error::Error<Rule>>> {
+    let pairs = SwhkdGrammar::parse(rule, input).map_err(Box::new)?;
+    #[cfg(feature = "grammar-coverage")]
+    crate::coverage::record(pairs.clone());
+    Ok(pairs)
+}
+
+/// Names `source` in errors raised on its declarations, their spans do not
+/// know which file they are in.
+pub(crate) fn in_file(err: ParseError, source: &str) -> ParseError {
+    match err {
+        ParseError::Grammar(err) if err.path().is_none() => {
+            ParseError::Grammar(Box::new(err.with_path(source)))
+        }
+        err => err,
+    }
+}
+
+pub(crate) fn spanned_error(message: String, span: Span<'_>) -> ParseError {
+    let err = pest::error::Error::new_from_span(
+        pest::error::ErrorVariant::<Rule>::CustomError { message },
+        span,
+    );
+    Box::new(err).into()
+}
+
+pub(crate) fn pair_to_string(pair: Pair<'_, Rule>) -> String {
+    pair.as_str().to_string()
+}
+
+/// Expands the trigger of a `replay` statement.
+fn trigger_parser(
+    pair: Pair<'_, Rule>,
+    ctx: &mut ImportContext,
+) -> Result<Vec<Definition>, ParseError> {
+    let mut references = References::default();
+    let definitions = chord_parser(pair.into_inner().collect(), ctx, &mut references.variables)?;
+    ctx.definitions.read(references, None);
+    Ok(definitions)
+}
+
+/// Expands the chords of a sequence separated by `;`, each on its own, into
+/// every combination of their definitions, the first chord varying slowest.
+fn sequence_parser(
+    components: Vec<Pair<'_, Rule>>,
+    ctx: &ImportContext,
+    used: &mut BTreeSet<String>,
+) -> Result<Product<Definition>, ParseError> {
+    let steps = components
+        .split(|component| component.as_rule() == Rule::sequence_then)
+        .map(|step| chord_parser(step.to_vec(), ctx, used))
+        .collect::<Result<_, _>>()?;
+    Ok(Product::new(steps))
+}
+
+/// The single chords and the sequences of an `ignore` statement.
+fn unbind_parser(
+    pair: Pair<'_, Rule>,
+    source: &str,
+    ctx: &mut ImportContext,
+) -> Result<(Vec<Definition>, Vec<Vec<Definition>>), ParseError> {
+    let mut chords = vec![];
+    let mut sequences = vec![];
+    let span = pair.as_span();
+    let mut references = References::default();
+    let product = sequence_parser(pair.into_inner().collect(), ctx, &mut references.variables)?;
+    ctx.count_variants(product.len(), source, span)?;
+    ctx.definitions.read(references, None);
+    for variant in 0..product.len() {
+        let sequence = product.get(variant).into_iter().cloned().collect_vec();
+        match <[_; 1]>::try_from(sequence) {
+            Ok([definition]) => chords.push(definition),
+            Err(sequence) => sequences.push(sequence),
+        }
+    }
+    Ok((chords, sequences))
+}
+
+/// Expands the components of a chord, substituting its variables and
+/// adding their names to `used`.
+fn chord_parser(
+    components: Vec<Pair<'_, Rule>>,
+    ctx: &ImportContext,
+    used: &mut BTreeSet<String>,
+) -> Result<Vec<Definition>, ParseError> {
+    variables::with_chord(components, &ctx.variables, used, |components| {
+        let mut uncompiled = DefinitionUncompiled::default();
+        for component in components {
+            if component.as_rule() == Rule::key_and && !ctx.options.multi_key_chords {
+                return Err(spanned_error(
+                    "chords of several keys are opt-in, enable them with `ParseOptions::with_multi_key_chords`"
+                        .to_string(),
+                    component.as_span(),
+                ));
+            }
+            uncompiled.ingest(component)?;
+        }
+        Ok(uncompiled.compile())
+    })
+}
+
+/// A key in key position along with where it is written, attributes
+/// included.
+pub(crate) fn parse_key(component: Pair<'_, Rule>) -> Result<Spanned<KeyRepr>, ParseError> {
+    let span = component.as_span();
+    let mut attribute = KeyAttribute::None;
+    let mut key = String::default();
+    for inner in component.into_inner() {
+        match inner.as_rule() {
+            Rule::send => attribute |= KeyAttribute::Send,
+            Rule::on_release => attribute |= KeyAttribute::OnRelease,
+            Rule::shorthand_allow | Rule::key_base => {
+                key = unescape(&inner.as_str().to_lowercase(), KEY_ESCAPES);
+                if let Some(Err(message)) = evdev_mappings::key_code(&key) {
+                    return Err(spanned_error(message, inner.as_span()));
+                }
+            }
+            Rule::key_escape_invalid => {
+                // Safety: the grammar guarantees a character after the backslash.
+                let escaped = inner.as_str()[1..].chars().next().unwrap();
+                return Err(spanned_error(
+                    format!("unnecessary escape of '{escaped}' in key position"),
+                    inner.as_span(),
+                ));
+            }
+            _ => {}
+        }
+    }
+    Ok(Spanned::new(KeyRepr { key, attribute }, span))
+}
+
+fn mode_parser(
+    pair: Pair<'_, Rule>,
+    source: &str,
+    ctx: &mut ImportContext,
+) -> Result<Mode, ParseError> {
+    let mut mode = Mode {
+        description: description(&pair),
+        ..Default::default()
+    };
+    for component in pair.into_inner() {
+        match component.as_rule() {
+            Rule::modename => mode.name = component.as_str().to_string(),
+            Rule::binding => {
+                let active = ctx.profile_active(profile_tags(&component));
+                warn_about_any_omission(&component, source, ctx);
+                warn_about_redundant_shift(&component, source, ctx);
+                let span = component.as_span();
+                let parsed = binding_parser(component, source, ctx).and_then(|parsed| {
+                    ctx.count_variants(parsed.0.len(), source, span)?;
+                    Ok(parsed)
+                });
+                let Some((parsed, references)) = ctx.recover(parsed, source, None)? else {
+                    continue;
+                };
+                ctx.definitions.read(
+                    references,
+                    parsed.span().map(|span| (span.clone(), parsed.len())),
+                );
+                if active {
+                    mode.bindings.extend(parsed);
+                } else {
+                    ctx.inactive.extend(parsed);
+                }
+            }
+            Rule::unbind => {
+                let span = ctx.span(source, component.as_span());
+                let parsed = unbind_parser(component, source, ctx);
+                if let Some((chords, sequences)) = ctx.recover(parsed, source, None)? {
+                    ctx.ignores.push(IgnoreSite {
+                        span,
+                        mode: Some(mode.name.clone()),
+                        chords: chords.clone(),
+                        sequences: sequences.clone(),
+                    });
+                    mode.unbinds.extend(chords);
+                    mode.sequence_unbinds.extend(sequences);
+                }
+            }
+            // Parsed to explain that modes are only ignored at the top level.
+            Rule::unbind_mode => {
+                let err = spanned_error(
+                    "`ignore mode` only applies at the top level, outside of `mode` blocks"
+                        .to_string(),
+                    component.as_span(),
+                );
+                ctx.recover(Err::<(), _>(err), source, None)?;
+            }
+            Rule::replay => {
+                let parsed = trigger_parser(component, ctx);
+                if let Some(replay) = ctx.recover(parsed, source, None)? {
+                    mode.replay.extend(replay);
+                }
+            }
+            Rule::setting => {
+                let ingested = match device_of_setting(&component) {
+                    Some(_) => Err(device_setting_in_mode(component.as_span())),
+                    None => mode.settings.ingest(component, SettingScope::Mode),
+                };
+                ctx.recover(ingested, source, None)?;
+            }
+            Rule::oneoff => mode.oneoff = true,
+            Rule::swallow => mode.swallow = true,
+            _ => {}
+        }
+    }
+    Ok(mode)
+}
+
+/// Control characters in commands end up in swhkd's logs and IPC messages,
+/// where they garble the output or break the framing.
+fn reject_control_characters(command: &Pair<'_, Rule>) -> Result<(), ParseError> {
+    let span = command.as_span();
+    let Some((offset, c)) = span
+        .as_str()
+        .char_indices()
+        .find(|(_, c)| c.is_control() && !matches!(c, '\t' | '\n' | '\r'))
+    else {
+        return Ok(());
+    };
+    let start = span.start() + offset;
+    // Safety: `start` is the start of a character within the command.
+    let span = Span::new(span.get_input(), start, start + c.len_utf8()).unwrap();
+    Err(spanned_error(
+        format!(
+            "command contains the control character U+{:04X} at byte {start}, write it as an escape sequence such as `\\x{:02x}` instead",
+            c as u32, c as u32
+        ),
+        span,
+    ))
+}
+
+/// Warns about omission shorthands with an `any` variant, e.g. `{_, any +} a`,
+/// and about the modifiers `any` replaces in that variant, e.g. `super` in
+/// `super + {_, any +} a`. Each chord of a sequence is checked on its own.
+fn warn_about_any_omission(binding: &Pair<'_, Rule>, source: &str, ctx: &mut ImportContext) {
+    let modifiers = |pair: &Pair<'_, Rule>| -> Vec<Modifier> {
+        let pairs = match pair.as_rule() {
+            Rule::modifier_group => pair.clone().into_inner().collect_vec(),
+            _ => vec![pair.clone()],
+        };
+        pairs
+            .into_iter()
+            .filter(|pair| pair.as_rule() == Rule::modifier)
+            .filter_map(|pair| modifier(pair).ok())
+            .map(|(modifier, _)| modifier)
+            .collect()
+    };
+    let components = binding.clone().into_inner().collect_vec();
+    for chord in components.split(|component| component.as_rule() == Rule::sequence_then) {
+        let outer = chord
+            .iter()
+            .filter(|component| component.as_rule() == Rule::modifier)
+            .flat_map(modifiers)
+            .collect_vec();
+        for shorthand in chord
+            .iter()
+            .filter(|component| component.as_rule() == Rule::modifier_omit_shorthand)
+        {
+            let variants = shorthand.clone().into_inner().collect_vec();
+            if !variants.iter().any(|v| v.as_rule() == Rule::omission) {
+                continue;
+            }
+            let Some(any) = variants
+                .iter()
+                .map(modifiers)
+                .find(|variant| variant.contains(&Modifier::Any))
+            else {
+                continue;
+            };
+            let span = ctx.span(source, shorthand.as_span());
+            let replaced: BTreeSet<_> = outer
+                .iter()
+                .chain(&any)
+                .copied()
+                .filter(|modifier| *modifier != Modifier::Any)
+                .collect();
+            if !replaced.is_empty() {
+                ctx.warnings.push(Warning::AnyReplacesModifiers {
+                    span: span.clone(),
+                    modifiers: replaced.into_iter().collect(),
+                });
+            }
+            ctx.warnings.push(Warning::AnyInOmission(span));
+        }
+    }
+}
+
+/// Warns about `shift +` in a chord on a shifted symbol, e.g.
+/// `shift + exclam`, which holds Shift anyway. Each chord of a sequence is
+/// checked on its own.
+fn warn_about_redundant_shift(binding: &Pair<'_, Rule>, source: &str, ctx: &mut ImportContext) {
+    let components = binding.clone().into_inner().collect_vec();
+    for chord in components.split(|component| component.as_rule() == Rule::sequence_then) {
+        let components = chord
+            .iter()
+            .flat_map(|component| {
+                std::iter::once(component.clone()).chain(component.clone().into_inner().flatten())
+            })
+            .collect_vec();
+        let shifted_symbol = components.iter().any(|component| {
+            matches!(
+                component.as_rule(),
+                Rule::key_normal | Rule::key_in_shorthand
+            ) && evdev_mappings::convert_shifted(
+                &component
+                    .as_str()
+                    .trim_start_matches(['~', '@'])
+                    .to_lowercase(),
+            )
+            .is_some()
+        });
+        if !shifted_symbol {
+            continue;
+        }
+        for shift in components.iter().filter(|component| {
+            component.as_rule() == Rule::modifier
+                && component.as_str().eq_ignore_ascii_case("shift")
+        }) {
+            ctx.warnings
+                .push(Warning::RedundantShift(ctx.span(source, shift.as_span())));
+        }
+    }
+}
+
+/// Rejects a single-line command missing a blank around its `=`, or
+/// missing altogether.
+fn check_inline_command(command: &Pair<'_, Rule>) -> Result<(), ParseError> {
+    let span = command.as_span();
+    let input = span.get_input();
+    // Safety: `=` is a single byte starting the command.
+    let equals = Span::new(input, span.start(), span.start() + 1).unwrap();
+    let after = &span.as_str()[1..];
+    if after.trim().is_empty() {
+        return Err(spanned_error(
+            "`=` must be followed by the command on the same line".to_string(),
+            equals,
+        ));
+    }
+    if !input[..span.start()].ends_with([' ', '\t']) || !after.starts_with([' ', '\t']) {
+        return Err(spanned_error(
+            "`=` needs a blank on both sides to start a command, e.g. `super + a = firefox`; \
+             escape it as `\\=` for the key"
+                .to_string(),
+            equals,
+        ));
+    }
+    Ok(())
+}
+
+/// The bindings of a declaration, along with the variables and blocks it
+/// references.
+fn binding_parser(
+    pair: Pair<'_, Rule>,
+    source: &str,
+    ctx: &ImportContext,
+) -> Result<(BindingVariants, References), ParseError> {
+    let variables = &ctx.variables;
+    let mut references = References::default();
+    let mut command = CommandUncompiled::default();
+    let mut trigger = vec![];
+    let mut definitions = None;
+    let mut schedule = None;
+    let mut device = None;
+    let mut settings = Settings::default();
+    // Byte range of the key definition in the source.
+    let mut trigger_start = None;
+    let mut trigger_end = 0;
+    for component in pair.clone().into_inner() {
+        match component.as_rule() {
+            Rule::command | Rule::command_inline => {
+                // The chord comes first, and is reported on first.
+                definitions = Some(sequence_parser(
+                    std::mem::take(&mut trigger),
+                    ctx,
+                    &mut references.variables,
+                )?);
+                if component.as_rule() == Rule::command_inline {
+                    check_inline_command(&component)?;
+                }
+                let used = &mut references.variables;
+                let input = pair.get_input();
+                blocks::with_command(component, &ctx.blocks, &mut references.blocks, |text| {
+                    reject_control_characters(&text)?;
+                    // Commands expanding blocks are not written as such in
+                    // the config, their shorthands cannot be pointed at.
+                    let as_written = std::ptr::eq(text.get_input(), input);
+                    for subcomponent in text.into_inner() {
+                        match subcomponent.as_rule() {
+                            Rule::command_standalone => {
+                                command.text(variables::substitute_command(
+                                    subcomponent.as_str(),
+                                    variables,
+                                    used,
+                                ))
+                            }
+                            Rule::command_shorthand => {
+                                let span = subcomponent.as_span();
+                                let variants = expand::command_variants(subcomponent)?
+                                    .iter()
+                                    .map(|variant| {
+                                        variables::substitute_command(
+                                            &variant.value,
+                                            variables,
+                                            used,
+                                        )
+                                    })
+                                    .collect();
+                                let written = as_written.then_some(span.start()..span.end());
+                                command.variants(variants, written);
+                            }
+                            Rule::command_double_ampersand => command.and(),
+                            Rule::enter_mode => {
+                                let span = subcomponent.as_span();
+                                let Some(modename) = subcomponent.into_inner().next() else {
+                                    return Err(spanned_error(
+                                        "`@enter` needs the name of a mode".to_string(),
+                                        span,
+                                    ));
+                                };
+                                command.enter(pair_to_string(modename));
+                            }
+                            // A reference to no block is left to the shell.
+                            Rule::block_reference => {
+                                command.text(subcomponent.as_str().to_string())
+                            }
+                            Rule::escape_mode => command.escape(),
+                            Rule::dangling_continuation => {
+                                return Err(spanned_error(
+                                    "line continuation at end of file, there is no line to continue"
+                                        .to_string(),
+                                    subcomponent.as_span(),
+                                ));
+                            }
+                            Rule::command_separator => command.end_line(),
+                            _ => {}
+                        }
+                    }
+                    Ok(())
+                })?;
+                command.end_line();
+            }
+            Rule::schedule_tag => {
+                // Safety: the grammar guarantees an expression in the tag.
+                let expression = component.into_inner().next().unwrap();
+                let offset = expression.as_span().start();
+                schedule = Some(Schedule::parse_expression(expression.as_str()).map_err(
+                    |(message, range)| {
+                        // Safety: the range lies within the expression.
+                        let span =
+                            Span::new(pair.get_input(), offset + range.start, offset + range.end)
+                                .unwrap();
+                        spanned_error(message, span)
+                    },
+                )?);
+            }
+            Rule::device_tag => device = Some(device_parser(component)?),
+            Rule::setting_tag => settings.ingest(component, SettingScope::Binding)?,
+            Rule::profile_tag | Rule::maybe_some_lines_of_comments => {}
+            _ => {
+                let span = component.as_span();
+                trigger_start.get_or_insert(span.start());
+                trigger_end = span.end();
+                trigger.push(component);
+            }
+        }
+    }
+    // Safety: the grammar guarantees a command in every binding.
+    let definitions = definitions.unwrap();
+    // Safety: both ends are boundaries of components of `pair`.
+    let trigger_span =
+        trigger_start.map(|start| Span::new(pair.get_input(), start, trigger_end).unwrap());
+    let source_text: Arc<str> = trigger_span.map_or("".into(), |span| span.as_str().into());
+    let span = trigger_span.map(|span| ctx.span(source, span));
+    // A mismatch is reported on the shorthands of the command line at
+    // fault, or on the whole binding for a line without shorthands.
+    let parsed = command
+        .compile(definitions, schedule, device, source_text, span)
+        .map_err(|(message, written)| match written {
+            // Safety: the range is the one of shorthands of `pair`.
+            Some(range) => spanned_error(
+                message,
+                Span::new(pair.get_input(), range.start, range.end).unwrap(),
+            ),
+            None => spanned_error(message, pair.as_span()),
+        })?
+        .described(description(&pair))
+        .with_settings(settings);
+    Ok((parsed, references))
+}
+
+/// The comment lines right above `pair`, without their `#` and blanks,
+/// see [`Binding::description`]. A blank line or any other line in
+/// between detaches them.
+fn description(pair: &Pair<'_, Rule>) -> Option<String> {
+    let input = pair.get_input();
+    let start = pair.as_span().start();
+    let line_start = input[..start].rfind('\n').map_or(0, |newline| newline + 1);
+    let lines = input[..line_start]
+        .lines()
+        .rev()
+        .map(str::trim)
+        .take_while(|line| line.starts_with('#'))
+        .map(|line| line.trim_start_matches('#').trim())
+        .collect_vec();
+    (!lines.is_empty()).then(|| lines.into_iter().rev().join("\n"))
+}
+
+/// The device of a `[device=...] set` statement, `None` for a `set`
+/// statement applying to every device.
+fn device_of_setting(setting: &Pair<'_, Rule>) -> Option<Result<String, ParseError>> {
+    let tag = setting.clone().into_inner().next()?;
+    (tag.as_rule() == Rule::device_tag).then(|| device_parser(tag))
+}
+
+fn device_setting_in_mode(span: Span<'_>) -> ParseError {
+    spanned_error(
+        "device settings are only allowed at the top level, outside of `mode` blocks".to_string(),
+        span,
+    )
+}
+
+/// The device name of a `[device=...]` tag, without quotes.
+fn device_parser(tag: Pair<'_, Rule>) -> Result<String, ParseError> {
+    // Safety: the grammar guarantees a name in the tag, if an empty one.
+    let name = tag.into_inner().next().unwrap();
+    let trimmed = name.as_str().trim();
+    let unquoted = trimmed
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .unwrap_or(trimmed);
+    if unquoted.trim().is_empty() {
+        return Err(spanned_error(
+            "empty device name, expected the name of an input device like `[device=Keychron K6]`"
+                .to_string(),
+            name.as_span(),
+        ));
+    }
+    Ok(unquoted.to_string())
+}
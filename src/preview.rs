@@ -0,0 +1,233 @@
+use std::fmt::{Display, Write};
+
+use itertools::Itertools;
+
+use crate::{Binding, DefaultStyle, ModeInstruction, ParseError, ParserInput, SwhkdParser};
+
+/// Lines of context around each change in [`diff_previews`].
+const CONTEXT: usize = 3;
+
+/// What one expanded binding runs, see [`SwhkdParser::preview`].
+///
+/// Displayed as `chord<TAB>command`, prefixed with `[mode] ` for mode
+/// bindings and followed by a tab and the mode instructions, if any. The
+/// device and schedule of a binding follow its chord, e.g.
+/// `super+f [device=Keychron K6] (weekday 09:00-17:00)`.
+/// Newlines between command lines are written as `\n`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PreviewLine {
+    /// The mode the binding is declared in, `None` for the top level.
+    pub mode: Option<String>,
+    /// The chord in [`DefaultStyle`], e.g. `super+shift+a`.
+    pub chord: String,
+    /// The input device the binding is scoped to, see [`Binding::device`].
+    pub device: Option<String>,
+    /// When the binding is active, see [`Binding::schedule`].
+    pub schedule: Option<String>,
+    pub command: String,
+    /// The mode instructions in config syntax, e.g. `@enter resize`.
+    pub instructions: Vec<String>,
+}
+
+impl PreviewLine {
+    fn new(mode: Option<&str>, binding: &Binding) -> Self {
+        Self {
+            mode: mode.map(str::to_string),
+            chord: binding
+                .sequence()
+                .iter()
+                .map(|definition| definition.display_with(&DefaultStyle))
+                .join(" ; "),
+            device: binding.device().map(str::to_string),
+            schedule: binding.schedule().map(ToString::to_string),
+            command: binding.command().to_string(),
+            instructions: binding
+                .mode_instructions()
+                .iter()
+                .map(|instruction| match instruction {
+                    ModeInstruction::Enter(mode) => format!("@enter {mode}"),
+                    ModeInstruction::Escape => "@escape".to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Display for PreviewLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(mode) = &self.mode {
+            write!(f, "[{mode}] ")?;
+        }
+        write!(f, "{}", self.chord)?;
+        if let Some(device) = &self.device {
+            write!(f, " [device={device}]")?;
+        }
+        if let Some(schedule) = &self.schedule {
+            write!(f, " ({schedule})")?;
+        }
+        write!(f, "\t{}", self.command.replace('\n', "\\n"))?;
+        if !self.instructions.is_empty() {
+            write!(f, "\t{}", self.instructions.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl SwhkdParser {
+    /// Lists what every key runs, one line per expanded binding, top-level
+    /// bindings first and then each mode, sorted so that two previews can
+    /// be compared with [`diff_previews`] or `diff -u`.
+    ///
+    /// ```
+    /// use sweet::{ParserInput, SwhkdParser};
+    ///
+    /// let preview = SwhkdParser::preview(ParserInput::Raw("super + {b, a}\n    echo {b, a}"))?;
+    /// let lines: Vec<_> = preview.iter().map(ToString::to_string).collect();
+    /// assert_eq!(lines, ["super+a\techo a", "super+b\techo b"]);
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn preview(input: ParserInput) -> Result<Vec<PreviewLine>, ParseError> {
+        let parsed = Self::from(input)?;
+        let mut lines: Vec<_> = parsed
+            .bindings
+            .iter()
+            .map(|binding| PreviewLine::new(None, binding))
+            .chain(parsed.modes.iter().flat_map(|mode| {
+                mode.bindings
+                    .iter()
+                    .map(|binding| PreviewLine::new(Some(&mode.name), binding))
+            }))
+            .collect();
+        lines.sort();
+        Ok(lines)
+    }
+}
+
+enum Edit {
+    Keep,
+    Remove,
+    Add,
+}
+
+/// A shortest edit script turning `old` into `new`, from their longest
+/// common subsequence.
+fn edit_script(old: &[String], new: &[String]) -> Vec<Edit> {
+    // `common[i][j]` is the length of the longest common subsequence of
+    // `old[i..]` and `new[j..]`.
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut edits = vec![];
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            edits.push(Edit::Keep);
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+            edits.push(Edit::Remove);
+            i += 1;
+        } else {
+            edits.push(Edit::Add);
+            j += 1;
+        }
+    }
+    edits
+}
+
+/// A hunk range in unified diff syntax, `start,count` with the count left
+/// out when it is one.
+fn hunk_range(before: usize, count: usize) -> String {
+    match count {
+        0 => format!("{before},0"),
+        1 => format!("{}", before + 1),
+        _ => format!("{},{count}", before + 1),
+    }
+}
+
+/// Compares two previews in unified diff format, as `diff -u` would.
+/// Returns `None` when they are the same. Any lines can be compared, e.g.
+/// the lines of a config before and after rewriting it.
+pub fn diff_previews<T: Display>(
+    old: &[T],
+    new: &[T],
+    old_name: &str,
+    new_name: &str,
+) -> Option<String> {
+    let old: Vec<String> = old.iter().map(ToString::to_string).collect();
+    let new: Vec<String> = new.iter().map(ToString::to_string).collect();
+    let edits = edit_script(&old, &new);
+    let changes: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, edit)| !matches!(edit, Edit::Keep))
+        .map(|(index, _)| index)
+        .collect();
+    if changes.is_empty() {
+        return None;
+    }
+
+    // Changes closer than twice the context share a hunk.
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for &change in &changes {
+        match hunks.last_mut() {
+            Some((_, last)) if change - *last <= 2 * CONTEXT => *last = change,
+            _ => hunks.push((change, change)),
+        }
+    }
+
+    let mut out = format!("--- {old_name}\n+++ {new_name}\n");
+    for (first, last) in hunks {
+        let start = first.saturating_sub(CONTEXT);
+        let end = (last + CONTEXT + 1).min(edits.len());
+        let old_before = edits[..start]
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Add))
+            .count();
+        let new_before = edits[..start]
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Remove))
+            .count();
+        let hunk = &edits[start..end];
+        let old_count = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Add))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Remove))
+            .count();
+        let _ = writeln!(
+            out,
+            "@@ -{} +{} @@",
+            hunk_range(old_before, old_count),
+            hunk_range(new_before, new_count)
+        );
+        let (mut i, mut j) = (old_before, new_before);
+        for edit in hunk {
+            match edit {
+                Edit::Keep => {
+                    let _ = writeln!(out, " {}", old[i]);
+                    i += 1;
+                    j += 1;
+                }
+                Edit::Remove => {
+                    let _ = writeln!(out, "-{}", old[i]);
+                    i += 1;
+                }
+                Edit::Add => {
+                    let _ = writeln!(out, "+{}", new[j]);
+                    j += 1;
+                }
+            }
+        }
+    }
+    Some(out)
+}
@@ -0,0 +1,39 @@
+use pest::iterators::Pair;
+
+use crate::{parse::pair_to_string, resolve::ImportContext, Rule, SwhkdParser, Warning};
+
+/// Profiles a declaration is tagged with, empty if it is not tagged.
+pub(crate) fn profile_tags(decl: &Pair<'_, Rule>) -> Vec<String> {
+    decl.clone()
+        .into_inner()
+        .filter(|component| component.as_rule() == Rule::profile_tag)
+        .flat_map(|tag| tag.into_inner().map(pair_to_string))
+        .collect()
+}
+
+impl ImportContext {
+    /// Whether a declaration tagged with `tags` is part of the parsed config,
+    /// which is the case if it is untagged or one of its profiles is active.
+    pub(crate) fn profile_active(&mut self, tags: Vec<String>) -> bool {
+        let active = tags.is_empty()
+            || tags
+                .iter()
+                .any(|tag| self.options.active_profiles.contains(tag));
+        self.profiles.extend(tags);
+        active
+    }
+}
+
+impl SwhkdParser {
+    pub(crate) fn warn_about_profiles(&mut self, ctx: &ImportContext) {
+        let active = &ctx.options.active_profiles;
+        for profile in self.profiles.difference(active) {
+            self.warnings
+                .push(Warning::InactiveProfile(profile.to_string()));
+        }
+        for profile in active.difference(&self.profiles) {
+            self.warnings
+                .push(Warning::UnknownProfile(profile.to_string()));
+        }
+    }
+}
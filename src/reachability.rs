@@ -0,0 +1,145 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{conflicts::location, Binding, KeyAttribute, SwhkdParser, Warning};
+
+/// A binding that can never be selected, because a binding declared later
+/// in the same scope has the same chord and replaces it.
+///
+/// Bindings are looked up per scope, the top level being one scope and each
+/// mode being another. When several bindings of a scope share the same
+/// chord the last one declared wins. Chords are compared once parsed, so
+/// `shift + super + a` duplicates `super + shift + a`, and a shorthand
+/// variant duplicates the same chord written out.
+///
+/// Only such duplicates and overrides are reported. Other bindings on the
+/// same key never shadow each other: modifiers are matched exactly, see
+/// [`should_fire`](crate::semantics::should_fire), so `super + shift + a`
+/// does not shadow `super + a`, and an exact modifier match beating `any`
+/// leaves `any + a` firing with every other set of modifiers held. While a
+/// mode is active its bindings take precedence over the top-level ones, so
+/// a mode binding never makes a top-level binding unreachable either.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Unreachable<'a> {
+    /// Name of the mode the bindings are in, or `None` for the top level.
+    pub mode: Option<&'a str>,
+    pub binding: &'a Binding,
+    /// The binding on the same chord replacing it, declared later in the
+    /// same scope.
+    pub shadowed_by: &'a Binding,
+}
+
+impl Display for Unreachable<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.mode {
+            Some(mode) => write!(f, "in mode `{}`, ", mode)?,
+            None => write!(f, "at the top level, ")?,
+        }
+        write!(
+            f,
+            "`{}` at {} can never fire, it is replaced by `{}` at {}, declared later on the same chord",
+            self.binding.chords_string(),
+            location(self.binding),
+            self.shadowed_by.chords_string(),
+            location(self.shadowed_by)
+        )
+    }
+}
+
+impl SwhkdParser {
+    /// Finds bindings replaced by a later binding on the same chord in
+    /// their scope, see [`Unreachable`].
+    pub fn unreachable_bindings(&self) -> Vec<Unreachable<'_>> {
+        let mut unreachable: Vec<_> = self
+            .overridden
+            .iter()
+            .filter_map(|binding| {
+                let shadowed_by = self
+                    .bindings
+                    .iter()
+                    .find(|later| later.same_trigger(binding))?;
+                Some(Unreachable {
+                    mode: None,
+                    binding,
+                    shadowed_by,
+                })
+            })
+            .collect();
+        unreachable.extend(unreachable_in_scope(None, &self.bindings));
+        for mode in &self.modes {
+            unreachable.extend(unreachable_in_scope(Some(&mode.name), &mode.bindings));
+        }
+        unreachable
+    }
+
+    /// Warns about bindings on the same modifiers and key that only differ
+    /// in their key attributes. They do not override one another, so both
+    /// fire, see [`SwhkdParser::from`].
+    pub(crate) fn warn_about_attribute_variants(&mut self) {
+        let scopes =
+            std::iter::once(&self.bindings).chain(self.modes.iter().map(|mode| &mode.bindings));
+        let mut warnings = vec![];
+        for bindings in scopes {
+            // Only bindings firing in the same situation are compared, as
+            // pairs of indices in the scope to warn in declaration order.
+            let mut groups: HashMap<_, Vec<usize>> = HashMap::new();
+            for (index, binding) in bindings.iter().enumerate() {
+                let (prefix, _) = binding.sequence().split_at(binding.sequence().len() - 1);
+                let definition = binding.last_definition();
+                let situation = (
+                    prefix,
+                    binding.device(),
+                    definition.modifiers(),
+                    &definition.key().trigger,
+                    definition.held_keys(),
+                );
+                groups.entry(situation).or_default().push(index);
+            }
+            let mut pairs = vec![];
+            for group in groups.values() {
+                for (position, &first) in group.iter().enumerate() {
+                    pairs.extend(group[position + 1..].iter().map(|&second| (first, second)));
+                }
+            }
+            pairs.sort_unstable();
+            for (first, second) in pairs {
+                let (first, second) = (&bindings[first], &bindings[second]);
+                let (a, b) = (first.last_definition(), second.last_definition());
+                if a.key().attribute == b.key().attribute {
+                    continue;
+                }
+                let (Some(first), Some(second)) = (first.span(), second.span()) else {
+                    continue;
+                };
+                let timing = KeyAttribute::OnRelease;
+                warnings.push(Warning::AttributeVariants {
+                    first: first.clone(),
+                    second: second.clone(),
+                    press_and_release: (a.key().attribute & timing) != (b.key().attribute & timing),
+                });
+            }
+        }
+        self.warnings.extend(warnings);
+    }
+}
+
+fn unreachable_in_scope<'a>(
+    mode: Option<&'a str>,
+    bindings: &'a [Binding],
+) -> Vec<Unreachable<'a>> {
+    bindings
+        .iter()
+        .enumerate()
+        .filter_map(|(index, binding)| {
+            // The last declaration of a definition is the one that is selected.
+            let shadowed_by = bindings[index + 1..]
+                .iter()
+                .rev()
+                .find(|later| later.same_trigger(binding))?;
+            Some(Unreachable {
+                mode,
+                binding,
+                shadowed_by,
+            })
+        })
+        .collect()
+}
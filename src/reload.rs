@@ -0,0 +1,463 @@
+//! Parsing a config again only when one of its files changed, see
+//! [`SwhkdParser::reload`].
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use crate::{
+    ineffective::{IgnoreSite, ModeSite},
+    resolve::ImportContext,
+    resolver::ResolvedImport,
+    unused::DefinitionUses,
+    watch::{stamp, Stamp},
+    Binding, Definition, ParseError, ParseOptions, ParserInput, SourceSpan, SwhkdParser, Warning,
+};
+
+/// The files a [`SwhkdParser`] was parsed from, to tell whether it has to
+/// be parsed again.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LoadedFiles {
+    pub(crate) options: ParseOptions,
+    /// The path of the root config, `None` for a config given as a string
+    /// or not parsed at all.
+    pub(crate) root: Option<PathBuf>,
+    /// The metadata of every file read, by the path it was read from.
+    pub(crate) stamps: BTreeMap<PathBuf, Stamp>,
+    /// Every included file along with its own includes, by identity, see
+    /// [`SwhkdParser::parse_include`].
+    pub(crate) includes: BTreeMap<String, Arc<ParsedInclude>>,
+}
+
+impl LoadedFiles {
+    /// The files whose size or modification time changed since they were
+    /// read, or that cannot be read anymore.
+    fn changed(&self) -> Vec<PathBuf> {
+        self.stamps
+            .iter()
+            .filter(|(path, &before)| stamp(path) != before)
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+}
+
+/// The included files parsed in the last parse and in the current one,
+/// see [`SwhkdParser::parse_include`].
+#[derive(Debug, Default)]
+pub(crate) struct IncludeCache {
+    previous: BTreeMap<String, Arc<ParsedInclude>>,
+    parsed: BTreeMap<String, Arc<ParsedInclude>>,
+    /// The identities of the files skipped as included already so far.
+    pub(crate) duplicates: Vec<String>,
+}
+
+impl IncludeCache {
+    pub(crate) fn new(previous: BTreeMap<String, Arc<ParsedInclude>>) -> Self {
+        Self {
+            previous,
+            ..Default::default()
+        }
+    }
+}
+
+/// What parsing an included file along with its own includes recorded in
+/// the [`ImportContext`] for merging the config and for reloading it.
+#[derive(Debug, Clone, Default)]
+struct Recorded {
+    profiles: BTreeSet<String>,
+    inactive: Vec<Binding>,
+    warnings: Vec<Warning>,
+    ignores: Vec<IgnoreSite>,
+    mode_sites: Vec<ModeSite>,
+    mode_ignores: Vec<(String, SourceSpan)>,
+    stamps: BTreeMap<PathBuf, Stamp>,
+}
+
+impl Recorded {
+    /// Takes what `ctx` recorded so far, leaving it empty.
+    fn take(ctx: &mut ImportContext) -> Self {
+        Self {
+            profiles: std::mem::take(&mut ctx.profiles),
+            inactive: std::mem::take(&mut ctx.inactive),
+            warnings: std::mem::take(&mut ctx.warnings),
+            ignores: std::mem::take(&mut ctx.ignores),
+            mode_sites: std::mem::take(&mut ctx.mode_sites),
+            mode_ignores: std::mem::take(&mut ctx.mode_ignores),
+            stamps: std::mem::take(&mut ctx.stamps),
+        }
+    }
+
+    /// Records these in `ctx` after what it recorded so far.
+    fn extend(self, ctx: &mut ImportContext) {
+        ctx.profiles.extend(self.profiles);
+        ctx.inactive.extend(self.inactive);
+        ctx.warnings.extend(self.warnings);
+        ctx.ignores.extend(self.ignores);
+        ctx.mode_sites.extend(self.mode_sites);
+        ctx.mode_ignores.extend(self.mode_ignores);
+        ctx.stamps.extend(self.stamps);
+    }
+}
+
+/// An included file parsed along with its own includes, reused by the
+/// next parse as long as none of these files changed and the file is
+/// included the same way.
+#[derive(Debug)]
+pub(crate) struct ParsedInclude {
+    parsed: SwhkdParser,
+    /// The path the file was read from and its metadata.
+    stamp: (PathBuf, Stamp),
+    mode_scope: Option<String>,
+    depth: usize,
+    /// The includes parsed along with the file, by identity, and where
+    /// they were included.
+    included: BTreeMap<String, SourceSpan>,
+    /// The files included before that the file and its includes include
+    /// again, and where they were first included, see
+    /// [`Warning::DuplicateInclude`].
+    duplicates: Vec<(String, Option<SourceSpan>)>,
+    variants: usize,
+    recorded: Recorded,
+    definitions: DefinitionUses,
+}
+
+impl ParsedInclude {
+    /// Whether parsing the file again in `ctx` would give the same.
+    fn reusable(&self, ctx: &ImportContext) -> bool {
+        let (path, before) = &self.stamp;
+        let mut stamps = std::iter::once((path, before)).chain(&self.recorded.stamps);
+        self.mode_scope == ctx.mode_scope
+            && self.depth == ctx.depth
+            && ctx.variants.saturating_add(self.variants) <= ctx.options.max_total_variants
+            && ctx.seen.len() + self.included.len() <= ctx.options.max_imports
+            && self.included.keys().all(|id| !ctx.seen.contains(id))
+            && self
+                .duplicates
+                .iter()
+                .all(|(id, first)| ctx.included_at.get(id) == first.as_ref())
+            && stamps.all(|(path, before)| stamp(path) == *before)
+    }
+
+    /// Records the file and its includes in `ctx` as if they were parsed
+    /// again.
+    fn replay(&self, ctx: &mut ImportContext) -> SwhkdParser {
+        for (id, requested_at) in &self.included {
+            ctx.seen.insert(id.clone());
+            ctx.included_at.insert(id.clone(), requested_at.clone());
+        }
+        ctx.variants += self.variants;
+        self.recorded.clone().extend(ctx);
+        ctx.definitions.append(&self.definitions);
+        self.parsed.clone_declarations()
+    }
+}
+
+/// The state of an [`ImportContext`] before parsing an included file, to
+/// tell what parsing it recorded.
+struct Capture {
+    seen: BTreeSet<String>,
+    duplicates: usize,
+    mode_scope: Option<String>,
+    depth: usize,
+    variants: usize,
+    definitions: (usize, usize),
+    /// What was recorded before, taken out of the context meanwhile.
+    outer: Recorded,
+}
+
+impl Capture {
+    fn start(ctx: &mut ImportContext) -> Self {
+        Self {
+            seen: ctx.seen.clone(),
+            duplicates: ctx
+                .includes
+                .as_ref()
+                .map_or(0, |includes| includes.duplicates.len()),
+            mode_scope: ctx.mode_scope.clone(),
+            depth: ctx.depth,
+            variants: ctx.variants,
+            definitions: ctx.definitions.mark(),
+            outer: Recorded::take(ctx),
+        }
+    }
+
+    /// Puts back what was recorded before the included file `resolved`,
+    /// then what parsing it recorded, and keeps it for the next parse if
+    /// it parsed.
+    fn finish(
+        self,
+        resolved: &ResolvedImport,
+        parsed: Result<SwhkdParser, ParseError>,
+        ctx: &mut ImportContext,
+    ) -> Result<SwhkdParser, ParseError> {
+        let recorded = Recorded::take(ctx);
+        self.outer.extend(ctx);
+        let parsed = match parsed {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                recorded.extend(ctx);
+                return Err(err);
+            }
+        };
+        let included: BTreeMap<_, _> = ctx
+            .included_at
+            .iter()
+            .filter(|(id, _)| !self.seen.contains(*id))
+            .map(|(id, span)| (id.clone(), span.clone()))
+            .collect();
+        let duplicates = ctx
+            .includes
+            .iter()
+            .flat_map(|includes| &includes.duplicates[self.duplicates..])
+            .filter(|id| !included.contains_key(*id))
+            .map(|id| (id.clone(), ctx.included_at.get(id).cloned()))
+            .collect();
+        let include = ParsedInclude {
+            parsed: parsed.clone_declarations(),
+            stamp: (
+                resolved.path.clone(),
+                ctx.stamps.get(&resolved.path).copied().flatten(),
+            ),
+            mode_scope: self.mode_scope,
+            depth: self.depth,
+            included,
+            duplicates,
+            variants: ctx.variants - self.variants,
+            recorded: recorded.clone(),
+            definitions: ctx.definitions.since(self.definitions),
+        };
+        recorded.extend(ctx);
+        if let Some(includes) = &mut ctx.includes {
+            includes
+                .parsed
+                .insert(resolved.id.clone(), Arc::new(include));
+        }
+        Ok(parsed)
+    }
+}
+
+/// What [`SwhkdParser::reload`] changed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReloadReport {
+    /// The files that changed since the last parse, empty if the config
+    /// was not parsed again.
+    pub changed_files: Vec<PathBuf>,
+    /// Bindings on a chord or sequence that was not bound in their mode,
+    /// or at the top level, see [`Binding::mode`].
+    pub added: Vec<Binding>,
+    /// Bindings on a chord or sequence that is not bound anymore in their
+    /// mode, or at the top level.
+    pub removed: Vec<Binding>,
+    /// Bindings that changed, before and after, e.g. running another
+    /// command on the same chord of the same mode.
+    pub modified: Vec<(Binding, Binding)>,
+}
+
+impl ReloadReport {
+    /// Whether the bindings did not change.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+type Trigger<'a> = (Option<&'a str>, &'a [Definition], Option<&'a str>);
+
+fn trigger(binding: &Binding) -> Trigger<'_> {
+    (binding.mode(), binding.sequence(), binding.device())
+}
+
+/// The top-level bindings, then the bindings of each mode.
+fn all_bindings(parsed: &SwhkdParser) -> Vec<&Binding> {
+    let modes = parsed.modes.iter().flat_map(|mode| &mode.bindings);
+    parsed.bindings.iter().chain(modes).collect()
+}
+
+/// The bindings added, removed and modified from `before` to `after`, in
+/// the order of `after` and then `before`. A mode may hold several
+/// bindings on the same chord, the last one of them is compared.
+fn diff(before: &SwhkdParser, after: &SwhkdParser) -> ReloadReport {
+    let (before, after) = (all_bindings(before), all_bindings(after));
+    let old: HashMap<Trigger, &Binding> = before.iter().map(|&b| (trigger(b), b)).collect();
+    let new: HashMap<Trigger, &Binding> = after.iter().map(|&b| (trigger(b), b)).collect();
+    let mut report = ReloadReport::default();
+    for &binding in &after {
+        if !std::ptr::eq(new[&trigger(binding)], binding) {
+            continue;
+        }
+        match old.get(&trigger(binding)) {
+            None => report.added.push(binding.clone()),
+            Some(&previous) if previous != binding => {
+                report.modified.push((previous.clone(), binding.clone()))
+            }
+            Some(_) => {}
+        }
+    }
+    report.removed = before
+        .iter()
+        .filter(|&&binding| {
+            std::ptr::eq(old[&trigger(binding)], binding) && !new.contains_key(&trigger(binding))
+        })
+        .map(|&binding| binding.clone())
+        .collect();
+    report
+}
+
+impl SwhkdParser {
+    /// Parses the included file `resolved`, or reuses it from the last
+    /// parse when neither it nor its includes changed, see
+    /// [`SwhkdParser::reload`].
+    pub(crate) fn parse_include(
+        resolved: &ResolvedImport,
+        source: &str,
+        ctx: &mut ImportContext,
+    ) -> Result<Self, ParseError> {
+        if ctx.includes.is_none() {
+            return Self::parse_source(&resolved.contents, source, Some(&resolved.path), ctx);
+        }
+        if let Some(parsed) = Self::reuse_include(resolved, ctx) {
+            return Ok(parsed);
+        }
+        // Boxed, since includes nest as deep as the options allow.
+        let capture = Box::new(Capture::start(ctx));
+        let parsed = Self::parse_source(&resolved.contents, source, Some(&resolved.path), ctx);
+        capture.finish(resolved, parsed, ctx)
+    }
+
+    /// The included file `resolved` from the last parse, recorded in `ctx`,
+    /// if it can be reused.
+    fn reuse_include(resolved: &ResolvedImport, ctx: &mut ImportContext) -> Option<Self> {
+        let includes = ctx.includes.as_ref()?;
+        let include = includes.previous.get(&resolved.id)?.clone();
+        if !include.reusable(ctx) {
+            return None;
+        }
+        let parsed = include.replay(ctx);
+        let includes = ctx.includes.as_mut()?;
+        // So that the next parse reuses them too.
+        for id in include.included.keys() {
+            if let Some(nested) = includes.previous.get(id) {
+                includes.parsed.insert(id.clone(), nested.clone());
+            }
+        }
+        includes.parsed.insert(resolved.id.clone(), include);
+        Some(parsed)
+    }
+
+    /// A copy of what [`SwhkdParser::parse_include`] returns for a file.
+    fn clone_declarations(&self) -> Self {
+        let SwhkdParser {
+            bindings,
+            unbinds,
+            sequence_unbinds,
+            imports,
+            modes,
+            ignored_modes,
+            settings,
+            device_settings,
+            inactive,
+            profiles,
+            warnings,
+            overridden,
+            variables,
+            blocks,
+            loaded: _,
+        } = self;
+        SwhkdParser {
+            bindings: bindings.clone(),
+            unbinds: unbinds.clone(),
+            sequence_unbinds: sequence_unbinds.clone(),
+            imports: imports.clone(),
+            modes: modes.clone(),
+            ignored_modes: ignored_modes.clone(),
+            settings: settings.clone(),
+            device_settings: device_settings.clone(),
+            inactive: inactive.clone(),
+            profiles: profiles.clone(),
+            warnings: warnings.clone(),
+            overridden: overridden.clone(),
+            variables: variables.clone(),
+            blocks: blocks.clone(),
+            loaded: Default::default(),
+        }
+    }
+
+    /// Parses the config again if the root config or one of its includes
+    /// changed since the last parse, e.g. on `SIGUSR1`, and reports how the
+    /// bindings changed, at the top level and in every mode.
+    ///
+    /// A file whose size and modification time did not change is taken as
+    /// unchanged without reading it, so reloading an unchanged config only
+    /// costs a `stat` per file and gives an empty report. Otherwise the
+    /// changed files are parsed again, with the [`ParseOptions`] of the
+    /// last parse, along with the files including them, whose declarations
+    /// may use their variables and blocks. An included file is reused from
+    /// the last parse, with its own includes, when none of them changed,
+    /// and the declarations of every file are then merged again like with
+    /// [`SwhkdParser::from`]. Parsers built otherwise, e.g. by
+    /// [`SwhkdParser::from_all_errors`], do not keep their included files,
+    /// and their first reload parses every file again. A config given as a
+    /// string, or from another path than the last parse, is always parsed
+    /// again.
+    ///
+    /// On error, the parser is left as it was, and the next reload tries
+    /// again.
+    ///
+    /// ```
+    /// use sweet::{ParserInput, SwhkdParser};
+    ///
+    /// let dir = tempfile::tempdir()?;
+    /// let path = dir.path().join("swhkdrc");
+    /// std::fs::write(&path, "super + a\n    firefox")?;
+    /// let mut parsed = SwhkdParser::from(ParserInput::Path(&path))?;
+    /// assert!(parsed.reload(ParserInput::Path(&path))?.changed_files.is_empty());
+    /// std::fs::write(&path, "super + a\n    firefox\nsuper + b\n    foot")?;
+    /// let report = parsed.reload(ParserInput::Path(&path))?;
+    /// assert_eq!(report.changed_files, [path]);
+    /// assert_eq!(report.added[0].command(), "foot");
+    /// assert_eq!(parsed.bindings.len(), 2);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn reload(&mut self, input: ParserInput) -> Result<ReloadReport, ParseError> {
+        let changed_files = match input {
+            ParserInput::Path(path) if self.loaded.root.as_deref() == Some(path) => {
+                let changed = self.loaded.changed();
+                if changed.is_empty() {
+                    return Ok(ReloadReport::default());
+                }
+                changed
+            }
+            ParserInput::Path(path) => vec![path.to_path_buf()],
+            ParserInput::Raw(_) => vec![],
+        };
+        let mut ctx = ImportContext {
+            options: self.loaded.options.clone(),
+            includes: Some(IncludeCache::new(self.loaded.includes.clone())),
+            ..Default::default()
+        };
+        let parsed = Self::from_context(input, &mut ctx)?;
+        let report = ReloadReport {
+            changed_files,
+            ..diff(self, &parsed)
+        };
+        *self = parsed;
+        Ok(report)
+    }
+
+    /// Records what [`SwhkdParser::reload`] needs to tell whether the
+    /// config read from `root` changed, `None` for a config given as a
+    /// string.
+    pub(crate) fn record_loaded(&mut self, root: Option<PathBuf>, ctx: &mut ImportContext) {
+        self.loaded = LoadedFiles {
+            options: ctx.options.clone(),
+            root,
+            stamps: std::mem::take(&mut ctx.stamps),
+            includes: ctx
+                .includes
+                .take()
+                .map(|includes| includes.parsed)
+                .unwrap_or_default(),
+        };
+    }
+}
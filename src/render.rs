@@ -0,0 +1,219 @@
+//! Errors and warnings as diagnostics, rendered as plain text or with ANSI
+//! colors for terminals.
+
+use std::{fmt::Display, sync::Arc};
+
+use pest::error::LineColLocation;
+
+use crate::{
+    import_failure_note, too_many_variants_note, unknown_mode_note, ParseError, SourceSpan,
+    Unreachable,
+};
+
+pub(crate) const BOLD: &str = "1";
+pub(crate) const DIM: &str = "2";
+pub(crate) const CYAN: &str = "36";
+const BOLD_RED: &str = "1;31";
+const BOLD_YELLOW: &str = "1;33";
+
+/// How bad a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The config could not be parsed.
+    Error,
+    /// The config parsed, but something about it is suspicious.
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        })
+    }
+}
+
+/// How [`Diagnostic::render`] decorates its text.
+///
+/// The library never looks at the terminal or the environment, callers pick
+/// the style, e.g. [`RenderStyle::Plain`] when `NO_COLOR` is set or when
+/// writing to a file or a journal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenderStyle {
+    /// Text only.
+    #[default]
+    Plain,
+    /// The same text as [`RenderStyle::Plain`] with ANSI colors: the
+    /// severity in red or yellow, underlines in bold, paths dimmed and notes
+    /// in cyan.
+    Color,
+}
+
+impl RenderStyle {
+    pub(crate) fn paint(self, code: &str, text: &str) -> String {
+        match self {
+            RenderStyle::Plain => text.to_string(),
+            RenderStyle::Color => format!("\x1b[{code}m{text}\x1b[0m"),
+        }
+    }
+}
+
+/// A note attached to a span of a config, rendered as a snippet of the
+/// line with the span underlined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: SourceSpan,
+    pub note: String,
+}
+
+/// An error or warning along with the spans it points at, built from a
+/// [`ParseError`], a [`Warning`](crate::Warning) or an [`Unreachable`]
+/// binding.
+///
+/// ```
+/// use sweet::{Diagnostic, RenderStyle, SwhkdParser, ParserInput};
+///
+/// let err = SwhkdParser::from(ParserInput::Raw("super + + b")).unwrap_err();
+/// let rendered = Diagnostic::from(&err).render(RenderStyle::Plain);
+/// assert!(rendered.starts_with("error: unable to parse config file\n --> <anonymous>:1:9"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// What went wrong, empty when the notes of the labels tell it all.
+    pub message: String,
+    /// The spans the diagnostic points at, in the order they are rendered.
+    pub labels: Vec<Label>,
+}
+
+impl Diagnostic {
+    /// Renders the severity, the message and a snippet for every label,
+    /// e.g. to print on a terminal. Both styles lay out the same text, the
+    /// colored one only adds ANSI escape codes around parts of it.
+    pub fn render(&self, style: RenderStyle) -> String {
+        let color = match self.severity {
+            Severity::Error => BOLD_RED,
+            Severity::Warning => BOLD_YELLOW,
+        };
+        format!(
+            "{} {}",
+            style.paint(color, &format!("{}:", self.severity)),
+            self.body(style)
+        )
+    }
+
+    /// The message and the labels, without the severity.
+    pub(crate) fn body(&self, style: RenderStyle) -> String {
+        let message = (!self.message.is_empty()).then(|| style.paint(BOLD, &self.message));
+        let labels = self
+            .labels
+            .iter()
+            .map(|label| label.span.render_styled(&label.note, style));
+        message
+            .into_iter()
+            .chain(labels)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(err: &ParseError) -> Self {
+        let (message, labels) = match err {
+            ParseError::Grammar(grammar) => {
+                let ((line, column), (end_line, end_column)) = match grammar.line_col {
+                    LineColLocation::Pos(pos) => (pos, pos),
+                    LineColLocation::Span(start, end) => (start, end),
+                };
+                let span = SourceSpan {
+                    file: Arc::from(grammar.path().unwrap_or("<anonymous>")),
+                    line,
+                    column,
+                    end_line,
+                    end_column,
+                    line_text: Arc::from(grammar.line().trim_end_matches(['\r', '\n'])),
+                };
+                let note = grammar.variant.message().into_owned();
+                (err.to_string(), vec![Label { span, note }])
+            }
+            ParseError::ImportFailed {
+                path,
+                cause,
+                requested_at,
+            } => (
+                String::new(),
+                vec![Label {
+                    span: (**requested_at).clone(),
+                    note: import_failure_note(path, cause),
+                }],
+            ),
+            ParseError::TooManyVariants {
+                got,
+                limit,
+                whole_config,
+                span,
+            } => (
+                String::new(),
+                vec![Label {
+                    span: span.clone(),
+                    note: too_many_variants_note(*got, *limit, *whole_config),
+                }],
+            ),
+            // The first of the errors, under a message counting them.
+            ParseError::Repeated { error, .. } => {
+                (err.to_string(), Diagnostic::from(&**error).labels)
+            }
+            ParseError::UnknownMode {
+                name,
+                declared,
+                span: Some(span),
+            } => (
+                String::new(),
+                vec![Label {
+                    span: span.clone(),
+                    note: unknown_mode_note(name, declared),
+                }],
+            ),
+            err => {
+                let mut message = err.to_string();
+                let mut source = std::error::Error::source(err);
+                while let Some(cause) = source {
+                    message.push_str(&format!(": {cause}"));
+                    source = cause.source();
+                }
+                (message, vec![])
+            }
+        };
+        Self {
+            severity: Severity::Error,
+            message,
+            labels,
+        }
+    }
+}
+
+impl From<&Unreachable<'_>> for Diagnostic {
+    fn from(unreachable: &Unreachable<'_>) -> Self {
+        let labels = [
+            (unreachable.binding, "this binding can never fire"),
+            (
+                unreachable.shadowed_by,
+                "because this later binding on the same chord replaces it",
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(binding, note)| {
+            Some(Label {
+                span: binding.span()?.clone(),
+                note: note.to_string(),
+            })
+        })
+        .collect();
+        Self {
+            severity: Severity::Warning,
+            message: unreachable.to_string(),
+            labels,
+        }
+    }
+}
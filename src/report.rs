@@ -0,0 +1,274 @@
+use std::fmt::Write;
+
+use crate::{
+    resolve::{filesize_cap_mib, ImportContext},
+    sha256::Sha256,
+    ParseError, ParserInput, SwhkdParser,
+};
+
+/// What to put in a [`Report`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReportOptions {
+    /// Inline the contents of every config file. Off by default, since
+    /// configs may contain private commands.
+    pub include_contents: bool,
+}
+
+/// Everything needed to reproduce a parse, meant to be pasted into a bug
+/// report. Built by [`SwhkdParser::report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub sweet_version: &'static str,
+    /// Every config file that was read, in the order they were read.
+    /// The first one is the root config.
+    pub files: Vec<ReportedFile>,
+    pub outcome: ReportOutcome,
+    pub environment: ReportEnvironment,
+}
+
+/// A config file read while building a [`Report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportedFile {
+    /// The path as written on the command line or in the `include`
+    /// statement, `<anonymous>` for raw input.
+    pub path: String,
+    /// The file whose `include` statement pulled this one in.
+    pub included_from: Option<String>,
+    /// Size in bytes.
+    pub size: usize,
+    /// Lowercase hex SHA-256 of the contents.
+    pub sha256: String,
+    /// Only set with [`ReportOptions::include_contents`].
+    pub contents: Option<String>,
+}
+
+impl ReportedFile {
+    pub(crate) fn new(
+        path: &str,
+        included_from: Option<&str>,
+        contents: &str,
+        keep_contents: bool,
+    ) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(contents.as_bytes());
+        Self {
+            path: path.to_string(),
+            included_from: included_from.map(str::to_string),
+            size: contents.len(),
+            sha256: hasher.finish_hex(),
+            contents: keep_contents.then(|| contents.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReportOutcome {
+    Parsed {
+        /// Top-level bindings after overrides and unbinds.
+        bindings: usize,
+        modes: usize,
+        /// Bindings declared inside modes.
+        mode_bindings: usize,
+        unbinds: usize,
+        /// See [`SwhkdParser::unreachable_bindings`].
+        unreachable: usize,
+    },
+    Failed {
+        /// The error as it would be printed to the user.
+        diagnostic: String,
+    },
+}
+
+/// Environment bits that influence parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportEnvironment {
+    /// The first of `LC_ALL`, `LC_CTYPE` and `LANG` that is set.
+    pub locale: Option<String>,
+    pub filesize_cap_mib: u64,
+    pub options: ReportOptions,
+}
+
+impl SwhkdParser {
+    /// Parses `input` and describes the outcome along with every file that
+    /// was read. Files are hashed as they are read, never read twice.
+    pub fn report(input: ParserInput, options: ReportOptions) -> Report {
+        let mut ctx = ImportContext {
+            files: Some(vec![]),
+            keep_contents: options.include_contents,
+            ..Default::default()
+        };
+        let outcome = match Self::from_context(input, &mut ctx) {
+            Ok(parsed) => ReportOutcome::Parsed {
+                bindings: parsed.bindings.len(),
+                modes: parsed.modes.len(),
+                mode_bindings: parsed.modes.iter().map(|mode| mode.bindings.len()).sum(),
+                unbinds: parsed.unbinds.len(),
+                unreachable: parsed.unreachable_bindings().len(),
+            },
+            Err(err) => ReportOutcome::Failed {
+                diagnostic: diagnostic(&err),
+            },
+        };
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .into_iter()
+            .filter_map(std::env::var_os)
+            .find(|value| !value.is_empty())
+            .map(|value| value.to_string_lossy().into_owned());
+        Report {
+            sweet_version: env!("CARGO_PKG_VERSION"),
+            files: ctx.files.unwrap_or_default(),
+            outcome,
+            environment: ReportEnvironment {
+                locale,
+                filesize_cap_mib: filesize_cap_mib(),
+                options,
+            },
+        }
+    }
+}
+
+/// Renders grammar errors with their source snippet instead of the
+/// generic message of [`ParseError::Grammar`].
+fn diagnostic(err: &ParseError) -> String {
+    match err {
+        ParseError::Grammar(err) => err.to_string(),
+        err => match std::error::Error::source(err) {
+            Some(source) => format!("{err}: {source}"),
+            None => err.to_string(),
+        },
+    }
+}
+
+impl Report {
+    /// Renders the report as markdown, ready to paste into an issue.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "## sweet report\n");
+        let _ = writeln!(out, "- sweet version: {}", self.sweet_version);
+        let _ = writeln!(
+            out,
+            "- locale: {}",
+            self.environment.locale.as_deref().unwrap_or("unset")
+        );
+        let _ = writeln!(
+            out,
+            "- file size cap: {} MiB",
+            self.environment.filesize_cap_mib
+        );
+        let _ = writeln!(
+            out,
+            "- contents included: {}",
+            self.environment.options.include_contents
+        );
+
+        let _ = writeln!(out, "\n### Files\n");
+        let _ = writeln!(out, "| path | included from | size | sha256 |");
+        let _ = writeln!(out, "| --- | --- | --- | --- |");
+        for file in &self.files {
+            let _ = writeln!(
+                out,
+                "| `{}` | {} | {} | `{}` |",
+                file.path,
+                file.included_from
+                    .as_deref()
+                    .map_or("-".to_string(), |from| format!("`{from}`")),
+                file.size,
+                file.sha256
+            );
+        }
+
+        let _ = writeln!(out, "\n### Outcome\n");
+        match &self.outcome {
+            ReportOutcome::Parsed {
+                bindings,
+                modes,
+                mode_bindings,
+                unbinds,
+                unreachable,
+            } => {
+                let _ = writeln!(out, "Parsed successfully.\n");
+                let _ = writeln!(out, "- bindings: {bindings}");
+                let _ = writeln!(out, "- modes: {modes} ({mode_bindings} bindings)");
+                let _ = writeln!(out, "- unbinds: {unbinds}");
+                let _ = writeln!(out, "- unreachable bindings: {unreachable}");
+            }
+            ReportOutcome::Failed { diagnostic } => {
+                let _ = writeln!(out, "Failed to parse:\n\n```\n{diagnostic}\n```");
+            }
+        }
+
+        for file in &self.files {
+            if let Some(contents) = &file.contents {
+                let _ = writeln!(out, "\n### `{}`\n\n```\n{}\n```", file.path, contents);
+            }
+        }
+        out
+    }
+
+    /// Renders the report as a JSON object.
+    pub fn to_json(&self) -> String {
+        let files = self
+            .files
+            .iter()
+            .map(|file| {
+                format!(
+                    r#"{{"path":{},"included_from":{},"size":{},"sha256":{},"contents":{}}}"#,
+                    json_string(&file.path),
+                    json_optional(file.included_from.as_deref()),
+                    file.size,
+                    json_string(&file.sha256),
+                    json_optional(file.contents.as_deref()),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let outcome = match &self.outcome {
+            ReportOutcome::Parsed {
+                bindings,
+                modes,
+                mode_bindings,
+                unbinds,
+                unreachable,
+            } => format!(
+                r#"{{"status":"parsed","bindings":{bindings},"modes":{modes},"mode_bindings":{mode_bindings},"unbinds":{unbinds},"unreachable":{unreachable}}}"#
+            ),
+            ReportOutcome::Failed { diagnostic } => format!(
+                r#"{{"status":"failed","diagnostic":{}}}"#,
+                json_string(diagnostic)
+            ),
+        };
+        format!(
+            r#"{{"sweet_version":{},"files":[{}],"outcome":{},"environment":{{"locale":{},"filesize_cap_mib":{},"include_contents":{}}}}}"#,
+            json_string(self.sweet_version),
+            files,
+            outcome,
+            json_optional(self.environment.locale.as_deref()),
+            self.environment.filesize_cap_mib,
+            self.environment.options.include_contents,
+        )
+    }
+}
+
+pub(crate) fn json_optional(s: Option<&str>) -> String {
+    s.map_or("null".to_string(), json_string)
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,684 @@
+//! Reading a config and its includes, and merging their declarations into
+//! one [`SwhkdParser`], see [`SwhkdParser::from`].
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use pest::Span;
+
+#[cfg(feature = "cache")]
+use crate::cache::SourceFile;
+use crate::{
+    bindings::BindingVariants,
+    blocks::Blocks,
+    definition::unbinds_sequence,
+    error_log::ErrorLog,
+    ineffective::{self, IgnoreSite, ModeSite},
+    lenient::{self, SkippedDeclaration},
+    modes::{self, unknown_mode},
+    parse::{self, in_file, parse_main, Import},
+    reload::IncludeCache,
+    span::LineIndex,
+    unused::DefinitionUses,
+    variables::Variables,
+    watch::{stamp, Stamp},
+    Binding, ConfigReadError, Definition, ParseError, ParseOptions, ParserInput, ReportedFile,
+    SourceSpan, SwhkdParser, Warning,
+};
+
+/// The size limit for a single config file, set in `build.rs`.
+pub(crate) fn filesize_cap_mib() -> u64 {
+    std::option_env!("FILESIZE_CAP_MIB")
+        .and_then(|cap| cap.parse().ok())
+        .unwrap_or(50)
+}
+
+/// The config path given as [`ParserInput::Path`] as a string, the form
+/// an [`ImportResolver`](crate::ImportResolver) resolves.
+pub(crate) fn path_str(path: &Path) -> Result<&str, ConfigReadError> {
+    path.to_str()
+        .ok_or_else(|| ConfigReadError::NonUtf8Path(path.to_path_buf()))
+}
+
+pub fn read_config<P: AsRef<Path>>(path: P) -> Result<String, ConfigReadError> {
+    read_config_limited(path.as_ref(), None)
+}
+
+/// Like [`read_config`], enforcing [`ParseOptions::max_config_bytes`] in
+/// place of the size limit sweet was built with when it is set.
+pub(crate) fn read_config_limited(
+    path: &Path,
+    max_bytes: Option<u64>,
+) -> Result<String, ConfigReadError> {
+    let stat = fs::metadata(path)?;
+    if !stat.is_file() {
+        return Err(ConfigReadError::NotRegularFile(path.to_path_buf()));
+    }
+    let limit = max_bytes.unwrap_or(filesize_cap_mib() << 20);
+    if stat.size() > limit {
+        return Err(ConfigReadError::ExceedsLimit {
+            path: path.to_path_buf(),
+            limit,
+        });
+    }
+    // TODO: Use mmap instead of fs::read_to_string
+    Ok(fs::read_to_string(path)?)
+}
+
+/// State shared by every file of a config while it is being parsed.
+#[derive(Default)]
+pub(crate) struct ImportContext {
+    pub(crate) options: ParseOptions,
+    /// Identities of included files, each one is only parsed once.
+    pub(crate) seen: BTreeSet<String>,
+    /// Where each included file was first included, by identity, see
+    /// [`Warning::DuplicateInclude`].
+    pub(crate) included_at: BTreeMap<String, SourceSpan>,
+    /// Profiles seen so far, see [`SwhkdParser::profiles`].
+    pub(crate) profiles: BTreeSet<String>,
+    /// See [`SwhkdParser::inactive`].
+    pub(crate) inactive: Vec<Binding>,
+    /// Every file read so far, only recorded when building a [`Report`].
+    pub(crate) files: Option<Vec<ReportedFile>>,
+    /// The metadata of every file on disk read so far, see
+    /// [`SwhkdParser::reload`].
+    pub(crate) stamps: BTreeMap<PathBuf, Stamp>,
+    /// Whether recorded files keep their contents.
+    pub(crate) keep_contents: bool,
+    /// Top-level bindings in the order [`SwhkdParser::from`] declares them,
+    /// left unexpanded for [`SwhkdParser::bindings_iter`] instead of being
+    /// parsed into [`SwhkdParser::bindings`].
+    pub(crate) deferred: Option<Vec<BindingVariants>>,
+    /// Every file read so far, only recorded when caching the config.
+    #[cfg(feature = "cache")]
+    pub(crate) sources: Option<Vec<SourceFile>>,
+    /// See [`SwhkdParser::warnings`].
+    pub(crate) warnings: Vec<Warning>,
+    /// Variables visible in the file whose declarations are being parsed.
+    pub(crate) variables: Variables,
+    /// Blocks visible in the file whose declarations are being parsed.
+    pub(crate) blocks: Blocks,
+    /// See [`DefinitionUses`].
+    pub(crate) definitions: DefinitionUses,
+    /// Every `ignore` statement so far, see [`Warning::UnmatchedIgnore`].
+    pub(crate) ignores: Vec<IgnoreSite>,
+    /// Every active `mode` declaration so far.
+    pub(crate) mode_sites: Vec<ModeSite>,
+    /// Every `ignore mode` statement so far, see
+    /// [`Warning::UnmatchedModeIgnore`].
+    pub(crate) mode_ignores: Vec<(String, SourceSpan)>,
+    /// How deeply the file being parsed is included, 0 for the root.
+    pub(crate) depth: usize,
+    /// The mode the file being parsed is included into, from an `include`
+    /// in its block, directly or through other includes.
+    pub(crate) mode_scope: Option<String>,
+    /// The bindings and unbinds every declaration so far expands to, see
+    /// [`ImportContext::count_variants`].
+    pub(crate) variants: usize,
+    /// The errors of the declarations skipped so far, only collected by
+    /// [`SwhkdParser::from_all_errors`], see [`ImportContext::recover`].
+    pub(crate) errors: Option<ErrorLog>,
+    /// The top-level declarations skipped so far, only collected by
+    /// [`SwhkdParser::from_lenient`], see [`ImportContext::recover`].
+    pub(crate) skipped: Option<Vec<SkippedDeclaration>>,
+    /// The lines of the file being parsed, see [`ImportContext::span`].
+    pub(crate) lines: LineIndex,
+    /// The included files of the last parse to reuse and of this one,
+    /// only kept by parses that can be reloaded, see
+    /// [`SwhkdParser::reload`].
+    pub(crate) includes: Option<IncludeCache>,
+}
+
+impl ImportContext {
+    /// The location of `span` in the file `source`, found through the
+    /// lines of the file being parsed rather than by scanning it again for
+    /// each declaration.
+    pub(crate) fn span(&self, source: &str, span: Span<'_>) -> SourceSpan {
+        self.lines.span(source, span)
+    }
+
+    /// Enforces [`ParseOptions::max_config_bytes`] on the file at `path`
+    /// however it was read.
+    pub(crate) fn check_size(&self, contents: &str, path: &Path) -> Result<(), ConfigReadError> {
+        match self.options.max_config_bytes {
+            Some(limit) if contents.len() as u64 > limit => Err(ConfigReadError::ExceedsLimit {
+                path: path.to_path_buf(),
+                limit,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Enforces the variant limits of the [`ParseOptions`] on the
+    /// declaration at `span`, expanding to `variants` bindings or unbinds,
+    /// before they are built.
+    pub(crate) fn count_variants(
+        &mut self,
+        variants: usize,
+        source: &str,
+        span: Span<'_>,
+    ) -> Result<(), ParseError> {
+        let total = self.variants.saturating_add(variants);
+        let (got, limit, whole_config) = if variants > self.options.max_binding_variants {
+            (variants, self.options.max_binding_variants, false)
+        } else if total > self.options.max_total_variants {
+            (total, self.options.max_total_variants, true)
+        } else {
+            self.variants = total;
+            return Ok(());
+        };
+        Err(ParseError::TooManyVariants {
+            got,
+            limit,
+            whole_config,
+            span: self.span(source, span),
+        })
+    }
+
+    /// Records the error of a declaration of the file `source` and skips
+    /// the declaration when collecting every error, fails on it otherwise.
+    /// When skipping declarations, `declaration` is the top-level one to
+    /// skip, `None` for a statement of a mode, which fails the whole mode.
+    pub(crate) fn recover<T>(
+        &mut self,
+        result: Result<T, ParseError>,
+        source: &str,
+        declaration: Option<Span<'_>>,
+    ) -> Result<Option<T>, ParseError> {
+        let err = match result {
+            Ok(value) => return Ok(Some(value)),
+            Err(err) => in_file(err, source),
+        };
+        match (&mut self.errors, &mut self.skipped, declaration) {
+            (Some(errors), _, _) => errors.push(err),
+            (None, Some(skipped), Some(span)) => {
+                skipped.push(SkippedDeclaration::new(source, span, err))
+            }
+            _ => return Err(err),
+        }
+        Ok(None)
+    }
+
+    /// Skips an include that failed when skipping declarations, fails on
+    /// it otherwise.
+    fn recover_import(
+        &mut self,
+        err: ParseError,
+        requested_at: &SourceSpan,
+    ) -> Result<(), ParseError> {
+        let Some(skipped) = &mut self.skipped else {
+            return Err(err);
+        };
+        skipped.push(SkippedDeclaration {
+            text: requested_at.line_text.trim().to_string(),
+            span: Some(requested_at.clone()),
+            error: err,
+        });
+        Ok(())
+    }
+
+    /// Enforces the import limits of the [`ParseOptions`] on the file at
+    /// `path`, about to be included by the one being parsed at
+    /// `requested_at`.
+    pub(crate) fn check_import(
+        &self,
+        path: &Path,
+        requested_at: &SourceSpan,
+    ) -> Result<(), ConfigReadError> {
+        if self.depth >= self.options.max_import_depth {
+            return Err(ConfigReadError::ImportTooDeep {
+                path: path.to_path_buf(),
+                requested_at: Box::new(requested_at.clone()),
+                limit: self.options.max_import_depth,
+            });
+        }
+        if self.seen.len() >= self.options.max_imports {
+            return Err(ConfigReadError::TooManyImports {
+                path: path.to_path_buf(),
+                requested_at: Box::new(requested_at.clone()),
+                limit: self.options.max_imports,
+            });
+        }
+        Ok(())
+    }
+
+    /// Records a file that was read, `requested` as written in the
+    /// `include` statement of `base` or on the command line.
+    pub(crate) fn record(
+        &mut self,
+        path: &str,
+        included_from: Option<&str>,
+        requested: &str,
+        base: Option<&Path>,
+        contents: &str,
+    ) {
+        #[cfg(feature = "cache")]
+        if let Some(sources) = &mut self.sources {
+            sources.push(SourceFile::new(requested, base, path, contents));
+        }
+        #[cfg(not(feature = "cache"))]
+        let _ = (requested, base);
+        if let Some(files) = &mut self.files {
+            files.push(ReportedFile::new(
+                path,
+                included_from,
+                contents,
+                self.keep_contents,
+            ));
+        }
+    }
+}
+
+/// The `ignore` statements of a config, looked up for every binding.
+struct Unbound<'a> {
+    chords: HashSet<&'a Definition>,
+    sequences: HashSet<&'a [Definition]>,
+    /// Statements with `any` or `any_key`, which match more than one
+    /// chord and are checked one by one.
+    wildcards: Vec<&'a [Definition]>,
+}
+
+impl<'a> Unbound<'a> {
+    fn new(chords: &'a [Definition], sequences: &'a [Vec<Definition>]) -> Self {
+        let mut unbound = Self {
+            chords: HashSet::new(),
+            sequences: HashSet::new(),
+            wildcards: vec![],
+        };
+        for chord in chords {
+            if chord.is_wildcard() {
+                unbound.wildcards.push(std::slice::from_ref(chord));
+            } else {
+                unbound.chords.insert(chord);
+            }
+        }
+        for sequence in sequences {
+            if sequence.iter().any(Definition::is_wildcard) {
+                unbound.wildcards.push(sequence);
+            } else {
+                unbound.sequences.insert(sequence);
+            }
+        }
+        unbound
+    }
+
+    /// Whether an `ignore` removes a binding on `sequence`.
+    fn contains(&self, sequence: &[Definition]) -> bool {
+        self.chords.contains(&sequence[0])
+            || (1..=sequence.len()).any(|len| self.sequences.contains(&sequence[..len]))
+            || self
+                .wildcards
+                .iter()
+                .any(|unbind| unbinds_sequence(unbind, sequence))
+    }
+}
+
+impl SwhkdParser {
+    pub(crate) fn from_context(
+        input: ParserInput,
+        ctx: &mut ImportContext,
+    ) -> Result<Self, ParseError> {
+        let path = match input {
+            ParserInput::Path(path) => Some(path.to_path_buf()),
+            ParserInput::Raw(_) => None,
+        };
+        let root = Self::as_import(input, ctx)?;
+        let mut parsed = Self::merge(root, ctx)?;
+        parsed.record_loaded(path, ctx);
+        Ok(parsed)
+    }
+
+    /// The top-level bindings of a config in the order of
+    /// [`SwhkdParser::bindings`] before merging, checked but not expanded,
+    /// see [`SwhkdParser::bindings_iter`].
+    pub(crate) fn deferred_bindings(
+        input: ParserInput,
+    ) -> Result<Vec<BindingVariants>, ParseError> {
+        let mut ctx = ImportContext {
+            deferred: Some(vec![]),
+            ..Default::default()
+        };
+        let root = Self::as_import(input, &mut ctx)?;
+        let deferred = ctx.deferred.take().unwrap_or_default();
+        // Every variant of a declaration enters the same modes.
+        let declared = root.mode_names();
+        if let Some(err) = deferred.iter().find_map(|variants| {
+            unknown_mode(&declared, variants.mode_instructions(), variants.span())
+        }) {
+            return Err(err);
+        }
+        root.check_mode_references()?;
+        Ok(deferred)
+    }
+
+    /// Merges the declarations of a whole config, see [`SwhkdParser::from`],
+    /// and checks them for errors and warnings.
+    pub(crate) fn merge(root: Self, ctx: &mut ImportContext) -> Result<Self, ParseError> {
+        let collect_warnings = ctx.options.collect_warnings;
+        let mut warnings = vec![];
+        // Bindings of inactive profiles are only left out for now, an
+        // `ignore` matching them is not warned about.
+        if collect_warnings {
+            warnings = ineffective::unmatched_ignores(
+                &ctx.ignores,
+                &root.bindings,
+                &root.modes,
+                &ctx.inactive,
+            );
+            warnings.extend(ineffective::unmatched_mode_ignores(
+                &ctx.mode_ignores,
+                &root.modes,
+            ));
+        }
+        let (mut root_bindings, mut root_modes) = (root.bindings, root.modes);
+        modes::ignore_modes(&root.ignored_modes, &mut root_bindings, &mut root_modes);
+        let mut bindings: Vec<Binding> = vec![];
+        let mut overridden = vec![];
+        // Where the binding on each chord and device is, a later binding
+        // on the same ones overriding it in place.
+        let mut index = HashMap::new();
+        // The spans of the overrides already warned about, variants of a
+        // shorthand overriding each other share them.
+        let mut warned = HashSet::new();
+        // Every `ignore` of the config applies, wherever it is written.
+        let unbound = Unbound::new(&root.unbinds, &root.sequence_unbinds);
+        for mut binding in root_bindings {
+            binding.record_mode(None);
+            let trigger = (
+                binding.sequence().to_vec(),
+                binding.device().map(str::to_string),
+            );
+            if let Some(&i) = index.get(&trigger) {
+                let b: &mut Binding = &mut bindings[i];
+                if collect_warnings {
+                    if let Some(Warning::OverriddenBinding { first, second }) =
+                        ineffective::overridden_binding(b, &binding)
+                    {
+                        if warned.insert((first.clone(), second.clone())) {
+                            warnings.push(Warning::OverriddenBinding { first, second });
+                        }
+                    }
+                }
+                // `bindings` never holds two top-level bindings on the same
+                // chord. The replaced one is kept aside, so that conflicts,
+                // unreachable bindings and `any` collisions can still pair
+                // it with the binding replacing it.
+                overridden.push(std::mem::replace(b, binding));
+                continue;
+            }
+
+            if unbound.contains(binding.sequence()) {
+                continue;
+            }
+            index.insert(trigger, bindings.len());
+            bindings.push(binding);
+        }
+        let mut parsed = SwhkdParser {
+            bindings,
+            imports: ctx.seen.clone(),
+            unbinds: root.unbinds,
+            sequence_unbinds: root.sequence_unbinds,
+            modes: modes::merge_modes(root_modes),
+            ignored_modes: root.ignored_modes,
+            settings: root.settings,
+            device_settings: root.device_settings,
+            inactive: std::mem::take(&mut ctx.inactive),
+            profiles: ctx.profiles.clone(),
+            warnings: std::mem::take(&mut ctx.warnings),
+            overridden,
+            variables: root.variables,
+            blocks: root.blocks,
+            loaded: Default::default(),
+        };
+        // Modes declared in files that were not read, e.g. under
+        // `ImportPolicy::Ignore`, are unknown as well.
+        match (&mut ctx.errors, &mut ctx.skipped) {
+            (Some(log), _) => {
+                let mut errors = vec![];
+                parsed.remove_unknown_mode_bindings(&mut errors);
+                errors.into_iter().for_each(|err| log.push(err));
+            }
+            (None, Some(skipped)) => {
+                let mut errors = vec![];
+                parsed.remove_unknown_mode_bindings(&mut errors);
+                skipped.extend(errors.into_iter().map(SkippedDeclaration::binding));
+            }
+            (None, None) => parsed.check_mode_references()?,
+        }
+        if !collect_warnings {
+            // The warnings found while reading the files.
+            parsed.warnings.clear();
+            return Ok(parsed);
+        }
+        parsed.warn_about_profiles(ctx);
+        parsed.warn_about_attribute_variants();
+        parsed.warn_about_any_collisions();
+        // Chords written differently with `any` are reported as collisions.
+        let collisions: HashSet<_> = parsed
+            .warnings
+            .iter()
+            .filter_map(|warning| match warning {
+                Warning::AnyCollision { first, second } => Some((first, second)),
+                _ => None,
+            })
+            .collect();
+        warnings.retain(|warning| match warning {
+            Warning::OverriddenBinding { first, second } => !collisions.contains(&(first, second)),
+            _ => true,
+        });
+        parsed.warnings.extend(warnings);
+        parsed.warn_about_mode_instructions();
+        if ctx.options.validate_commands {
+            parsed.warn_about_commands();
+        }
+        parsed.warn_about_unused_definitions(&ctx.definitions);
+        parsed.warn_about_empty_modes(&ctx.mode_sites);
+        for mode in &parsed.modes {
+            if !mode.replay.is_empty() && !mode.swallow {
+                parsed
+                    .warnings
+                    .push(Warning::ReplayWithoutSwallow(mode.name.clone()));
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn as_import(input: ParserInput, ctx: &mut ImportContext) -> Result<Self, ParseError> {
+        let (raw, path) = match input {
+            // If a config is loaded from a string instead of a path, name it `<anonymous>`
+            // and resolve its includes as if it had no path.
+            ParserInput::Raw(s) => (s.to_string(), None),
+            ParserInput::Path(p) => {
+                let resolved = ctx.options.resolver().resolve(path_str(p)?, None)?;
+                (resolved.contents, Some(resolved.path))
+            }
+        };
+        let requested = match input {
+            ParserInput::Raw(_) => "<anonymous>",
+            ParserInput::Path(p) => path_str(p)?,
+        };
+        Self::as_root_contents(&raw, path.as_deref(), requested, ctx)
+    }
+
+    /// Parses `raw` as the root config read from `path`, `None` for a
+    /// config given as a string. Also used for configs translated from
+    /// other formats, see [`crate::from_sxhkd`].
+    pub(crate) fn as_root_contents(
+        raw: &str,
+        path: Option<&Path>,
+        requested: &str,
+        ctx: &mut ImportContext,
+    ) -> Result<Self, ParseError> {
+        ctx.check_size(raw, path.unwrap_or(Path::new("<anonymous>")))?;
+        let source = path.map_or("<anonymous>".into(), Path::to_string_lossy);
+        ctx.record(&source, None, requested, None, raw);
+        if let Some(path) = path {
+            ctx.stamps.insert(path.to_path_buf(), stamp(path));
+        }
+        Self::parse_source(raw, &source, path, ctx)
+    }
+
+    /// Parses the file `source`, whose includes are resolved from `path`.
+    pub(crate) fn parse_source(
+        raw: &str,
+        source: &str,
+        path: Option<&Path>,
+        ctx: &mut ImportContext,
+    ) -> Result<Self, ParseError> {
+        let repaired;
+        let raw = match &mut ctx.skipped {
+            Some(skipped) => {
+                repaired = lenient::skip_grammar_errors(raw, source, skipped);
+                &repaired
+            }
+            None => raw,
+        };
+        let contents = parse_main(raw, source)?;
+        ctx.lines = LineIndex::new(raw);
+        let defined = parse::imports_and_definitions(contents.clone(), source, ctx)
+            .map_err(|err| in_file(err, source))?;
+
+        // Includes are expanded depth-first in the order they are declared,
+        // so the resulting bindings and modes do not depend on path names.
+        // They are parsed before the declarations of this file, which may
+        // use their variables and blocks.
+        let deferred_before = ctx.deferred.as_ref().map_or(0, Vec::len);
+        let mut children = vec![];
+        let mut child_sites = vec![];
+        for Import {
+            path: import,
+            requested_at,
+            mode,
+        } in defined.imports
+        {
+            let resolved = ctx
+                .options
+                .resolver()
+                .resolve(&import, path)
+                .and_then(|resolved| {
+                    ctx.check_size(&resolved.contents, &resolved.path)
+                        .map(|_| resolved)
+                });
+            let failed = |cause| ParseError::ImportFailed {
+                path: PathBuf::from(&import),
+                cause,
+                requested_at: Box::new(requested_at.clone()),
+            };
+            let resolved = match resolved {
+                Ok(resolved) => resolved,
+                Err(cause) => {
+                    ctx.recover_import(failed(cause), &requested_at)?;
+                    continue;
+                }
+            };
+            // However its path is spelled, a file is only parsed once.
+            if ctx.seen.contains(&resolved.id) {
+                if let Some(includes) = &mut ctx.includes {
+                    includes.duplicates.push(resolved.id.clone());
+                }
+                if let Some(first) = ctx.included_at.get(&resolved.id) {
+                    ctx.warnings.push(Warning::DuplicateInclude {
+                        first: first.clone(),
+                        second: requested_at,
+                    });
+                }
+                continue;
+            }
+            if let Err(cause) = ctx.check_import(&resolved.path, &requested_at) {
+                ctx.recover_import(failed(cause), &requested_at)?;
+                continue;
+            }
+            ctx.included_at
+                .insert(resolved.id.clone(), requested_at.clone());
+            ctx.seen.insert(resolved.id.clone());
+            let requested = import;
+            let import = resolved.path.to_string_lossy();
+            ctx.record(&import, Some(source), &requested, path, &resolved.contents);
+            ctx.stamps
+                .insert(resolved.path.clone(), stamp(&resolved.path));
+            ctx.depth += 1;
+            // An include in a mode block scopes the files included in turn.
+            let outer = match &mode {
+                Some(mode) => ctx.mode_scope.replace(mode.clone()),
+                None => ctx.mode_scope.clone(),
+            };
+            let lines = std::mem::take(&mut ctx.lines);
+            let child = Self::parse_include(&resolved, &import, ctx);
+            ctx.lines = lines;
+            ctx.mode_scope = outer;
+            ctx.depth -= 1;
+            match child {
+                Ok(child) => children.push((child, mode)),
+                Err(err) => {
+                    ctx.recover_import(err, &requested_at)?;
+                    continue;
+                }
+            }
+            child_sites.push(std::mem::take(&mut ctx.definitions.visible));
+        }
+
+        // Like settings, variables and blocks of the including file take
+        // precedence over the ones of its includes, and an earlier include
+        // over a later one.
+        let mut variables = defined.variables;
+        let mut blocks = defined.blocks;
+        for (child, _) in &children {
+            for (name, value) in &child.variables {
+                variables
+                    .entry(name.clone())
+                    .or_insert_with(|| value.clone());
+            }
+            for (name, lines) in &child.blocks {
+                blocks.entry(name.clone()).or_insert_with(|| lines.clone());
+            }
+        }
+        let mut sites = defined.sites;
+        for child in child_sites {
+            for (key, site) in child {
+                sites.entry(key).or_insert(site);
+            }
+        }
+        ctx.variables = variables;
+        ctx.blocks = blocks;
+        ctx.definitions.visible = sites;
+        let deferred_includes = ctx.deferred.as_ref().map_or(0, Vec::len);
+        let mut parsed =
+            parse::declarations(contents, source, ctx).map_err(|err| in_file(err, source))?;
+        parsed.variables = std::mem::take(&mut ctx.variables);
+        parsed.blocks = std::mem::take(&mut ctx.blocks);
+        // The bindings of this file come before the ones of its includes.
+        if let Some(deferred) = &mut ctx.deferred {
+            let own = deferred.len() - deferred_includes;
+            deferred[deferred_before..].rotate_right(own);
+        }
+        for (child, mode) in children {
+            // A file included in a mode block joins the first declaration
+            // of the mode in this file, it holds no modes of its own.
+            if let Some(mode) = mode {
+                if let Some(mode) = parsed.modes.iter_mut().find(|m| m.name == mode) {
+                    mode.bindings.extend(child.bindings);
+                    mode.unbinds.extend(child.unbinds);
+                    mode.sequence_unbinds.extend(child.sequence_unbinds);
+                    mode.settings = std::mem::take(&mut mode.settings).or(&child.settings);
+                }
+                parsed.ignored_modes.extend(child.ignored_modes);
+                continue;
+            }
+            parsed.bindings.extend(child.bindings);
+            parsed.unbinds.extend(child.unbinds);
+            parsed.sequence_unbinds.extend(child.sequence_unbinds);
+            parsed.modes.extend(child.modes);
+            parsed.ignored_modes.extend(child.ignored_modes);
+            parsed.settings = parsed.settings.or(&child.settings);
+            for (device, settings) in child.device_settings {
+                let merged = parsed.device_settings.entry(device).or_default();
+                *merged = std::mem::take(merged).or(&settings);
+            }
+        }
+        Ok(parsed)
+    }
+}
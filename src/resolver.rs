@@ -0,0 +1,216 @@
+use std::{
+    collections::HashMap,
+    env,
+    fmt::Debug,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{resolve::read_config_limited, ConfigReadError};
+
+/// Where the parser reads configs and their includes from, see
+/// [`ParseOptions::with_resolver`](crate::ParseOptions::with_resolver).
+/// [`FsResolver`] reads them from disk, [`MapResolver`] from memory.
+///
+/// ```
+/// use std::{path::Path, sync::Arc};
+/// use sweet::{ConfigReadError, ImportResolver, ParseOptions, ParserInput, ResolvedImport, SwhkdParser};
+///
+/// /// Every include is the same file.
+/// #[derive(Debug)]
+/// struct Constant;
+///
+/// impl ImportResolver for Constant {
+///     fn resolve(&self, _: &str, _: Option<&Path>) -> Result<ResolvedImport, ConfigReadError> {
+///         Ok(ResolvedImport::new("constant", "super + c\n    hello"))
+///     }
+/// }
+///
+/// let options = ParseOptions::default().with_resolver(Arc::new(Constant));
+/// let parsed = SwhkdParser::from_with_options(ParserInput::Raw("include anything"), &options)?;
+/// assert_eq!(parsed.bindings[0].command(), "hello");
+/// # Ok::<(), sweet::ParseError>(())
+/// ```
+pub trait ImportResolver: Debug + Send + Sync {
+    /// Reads the file `requested` by an `include` statement, or the config
+    /// given as a [`ParserInput::Path`](crate::ParserInput::Path). `from`
+    /// is the path of the including file, `None` for the config itself and
+    /// for includes of a [`ParserInput::Raw`](crate::ParserInput::Raw)
+    /// config.
+    ///
+    /// Errors are reported along with the `include` statement, see
+    /// [`ParseError::ImportFailed`](crate::ParseError::ImportFailed). Use
+    /// [`ConfigReadError::Resolver`] for errors of your own.
+    fn resolve(
+        &self,
+        requested: &str,
+        from: Option<&Path>,
+    ) -> Result<ResolvedImport, ConfigReadError>;
+}
+
+/// A file read by an [`ImportResolver`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedImport {
+    /// Tells files apart however their path is spelled, e.g. the canonical
+    /// path of a file on disk. A file is only parsed the first time it is
+    /// included, which also ends include cycles.
+    pub id: String,
+    /// Names the file in errors and spans, and is passed as `from` when
+    /// resolving its includes.
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+impl ResolvedImport {
+    /// A file identified and named by `path`.
+    pub fn new(path: impl Into<String>, contents: impl Into<String>) -> Self {
+        let id = path.into();
+        Self {
+            path: PathBuf::from(&id),
+            id,
+            contents: contents.into(),
+        }
+    }
+}
+
+/// Reads files from disk, the default. `~`, `~user` and environment
+/// variables in paths are expanded and relative paths are resolved against
+/// the directory of the including file, or the working directory. Only
+/// regular files within the size limit sweet was built with are read.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsResolver {
+    max_bytes: Option<u64>,
+}
+
+impl FsResolver {
+    /// Also limits files to `max_bytes`, see
+    /// [`ParseOptions::max_config_bytes`](crate::ParseOptions::max_config_bytes).
+    pub(crate) fn limited(max_bytes: Option<u64>) -> Self {
+        Self { max_bytes }
+    }
+}
+
+impl ImportResolver for FsResolver {
+    fn resolve(
+        &self,
+        requested: &str,
+        from: Option<&Path>,
+    ) -> Result<ResolvedImport, ConfigReadError> {
+        let dir = from.and_then(Path::parent).unwrap_or(Path::new(""));
+        let path = dir.join(expand_path(requested)?);
+        let contents = read_config_limited(&path, self.max_bytes)?;
+        // Missing files fail to be read above.
+        let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        Ok(ResolvedImport {
+            id: canonical.to_string_lossy().into_owned(),
+            path,
+            contents,
+        })
+    }
+}
+
+/// Files held in memory by their path as written after `include`, e.g.
+/// for tests or where there is no filesystem. Paths are neither expanded
+/// nor resolved against the including file.
+///
+/// ```
+/// use std::{collections::HashMap, sync::Arc};
+/// use sweet::{MapResolver, ParseOptions, ParserInput, SwhkdParser};
+///
+/// let files = HashMap::from([("extra.swhkd".to_string(), "super + c\n    hello".to_string())]);
+/// let options = ParseOptions::default().with_resolver(Arc::new(MapResolver(files)));
+/// let parsed = SwhkdParser::from_with_options(ParserInput::Raw("include extra.swhkd"), &options)?;
+/// assert_eq!(parsed.bindings[0].command(), "hello");
+/// # Ok::<(), sweet::ParseError>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MapResolver(pub HashMap<String, String>);
+
+impl ImportResolver for MapResolver {
+    fn resolve(
+        &self,
+        requested: &str,
+        _: Option<&Path>,
+    ) -> Result<ResolvedImport, ConfigReadError> {
+        match self.0.get(requested) {
+            Some(contents) => Ok(ResolvedImport::new(requested, contents.as_str())),
+            None => Err(std::io::Error::from(std::io::ErrorKind::NotFound).into()),
+        }
+    }
+}
+
+/// The resolver of [`ParseOptions`](crate::ParseOptions), compared by
+/// identity since resolvers cannot be compared otherwise.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ResolverSlot(pub(crate) Option<Arc<dyn ImportResolver>>);
+
+impl PartialEq for ResolverSlot {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+}
+
+impl Eq for ResolverSlot {}
+
+/// Expands a leading `~` or `~user` and every `$VAR` or `${VAR}` in an
+/// `include` path, the way a shell would.
+fn expand_path(path: &str) -> Result<String, ConfigReadError> {
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path;
+    if let Some(after_tilde) = path.strip_prefix('~') {
+        let end = after_tilde.find('/').unwrap_or(after_tilde.len());
+        let user = &after_tilde[..end];
+        if user.is_empty() {
+            expanded.push_str(&variable("HOME")?);
+        } else {
+            expanded.push_str(&home_of(user)?);
+        }
+        rest = &after_tilde[end..];
+    }
+
+    while let Some(dollar) = rest.find('$') {
+        expanded.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+        let (name, remainder) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(close) => (&braced[..close], &braced[close + 1..]),
+                None => ("", after),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+        if name.is_empty() {
+            // Not a variable, keep the `$` as is.
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+        expanded.push_str(&variable(name)?);
+        rest = remainder;
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn variable(name: &str) -> Result<String, ConfigReadError> {
+    env::var(name).map_err(|_| ConfigReadError::UnsetVariable(name.to_string()))
+}
+
+/// The home directory of `user`, as listed in `/etc/passwd`.
+fn home_of(user: &str) -> Result<String, ConfigReadError> {
+    let passwd = fs::read_to_string("/etc/passwd").unwrap_or_default();
+    passwd
+        .lines()
+        .map(|entry| entry.split(':').collect::<Vec<_>>())
+        .find(|fields| fields.len() >= 6 && fields[0] == user)
+        .map(|fields| fields[5].to_string())
+        .ok_or_else(|| ConfigReadError::UnknownUser(user.to_string()))
+}
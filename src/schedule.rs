@@ -0,0 +1,367 @@
+//! Schedules of bindings that are only active at certain times, written
+//! as `[when=weekday 09:00-17:00]` before the chord. Sweet only checks
+//! them; when a binding is active is up to the daemon, in local time.
+
+use std::{collections::BTreeSet, fmt::Display, ops::Range, str::FromStr};
+
+use itertools::Itertools;
+use pest::Span;
+
+use crate::{parse::spanned_error, ParseError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    /// Every day of the week, starting on Monday.
+    pub const ALL: [Weekday; 7] = [
+        Self::Monday,
+        Self::Tuesday,
+        Self::Wednesday,
+        Self::Thursday,
+        Self::Friday,
+        Self::Saturday,
+        Self::Sunday,
+    ];
+
+    /// The name of the day in a schedule, e.g. `mon`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Monday => "mon",
+            Self::Tuesday => "tue",
+            Self::Wednesday => "wed",
+            Self::Thursday => "thu",
+            Self::Friday => "fri",
+            Self::Saturday => "sat",
+            Self::Sunday => "sun",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|day| day.name() == name)
+    }
+}
+
+/// A time of day, from `00:00` to `23:59`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeOfDay {
+    pub hour: u8,
+    pub minute: u8,
+}
+
+impl Display for TimeOfDay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+/// The time from `start` up to, but not including, `end`. A range ending
+/// before it starts crosses midnight, e.g. `22:00-02:00`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeRange {
+    pub start: TimeOfDay,
+    pub end: TimeOfDay,
+}
+
+impl TimeRange {
+    pub fn crosses_midnight(&self) -> bool {
+        self.end < self.start
+    }
+}
+
+impl Display for TimeRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// When a binding is active: on some days of the week, during some times
+/// of those days.
+///
+/// Days are names like `mon`, ranges like `mon-fri`, lists like `sat,sun`,
+/// or one of `weekday`, `weekend` and `daily`. Times are ranges like
+/// `09:00-17:00`, several of them separated by commas. Either can be left
+/// out, meaning every day or all day.
+///
+/// ```
+/// use sweet::{Schedule, Weekday};
+///
+/// let schedule: Schedule = "mon-fri 09:00-12:00,13:00-17:00".parse()?;
+/// assert!(schedule.days().contains(&Weekday::Wednesday));
+/// assert!(!schedule.days().contains(&Weekday::Saturday));
+/// assert_eq!(schedule.times().len(), 2);
+/// assert_eq!(schedule.to_string(), "weekday 09:00-12:00,13:00-17:00");
+/// # Ok::<(), sweet::ParseError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "String", try_from = "String")
+)]
+pub struct Schedule {
+    days: BTreeSet<Weekday>,
+    times: Vec<TimeRange>,
+}
+
+const WEEKDAYS: [Weekday; 5] = [
+    Weekday::Monday,
+    Weekday::Tuesday,
+    Weekday::Wednesday,
+    Weekday::Thursday,
+    Weekday::Friday,
+];
+const WEEKEND: [Weekday; 2] = [Weekday::Saturday, Weekday::Sunday];
+
+impl Schedule {
+    /// A schedule active on `days` during `times`. No days means every
+    /// day and no times all day.
+    pub fn new(days: impl IntoIterator<Item = Weekday>, times: Vec<TimeRange>) -> Self {
+        let mut days: BTreeSet<_> = days.into_iter().collect();
+        if days.is_empty() {
+            days.extend(Weekday::ALL);
+        }
+        Self { days, times }
+    }
+
+    pub fn days(&self) -> &BTreeSet<Weekday> {
+        &self.days
+    }
+
+    /// The times of day the schedule is active, empty if it is active all
+    /// day.
+    pub fn times(&self) -> &[TimeRange] {
+        &self.times
+    }
+
+    /// Parses the expression of a `[when=...]` tag, failing with a message
+    /// and the byte range of `expression` it is about.
+    pub(crate) fn parse_expression(expression: &str) -> Result<Self, (String, Range<usize>)> {
+        let words = words(expression);
+        if words.is_empty() {
+            return Err((
+                "empty schedule, expected days like `mon-fri` or times like `09:00-17:00`"
+                    .to_string(),
+                0..expression.len(),
+            ));
+        }
+        let mut days = None;
+        let mut times = None;
+        for (start, word) in words {
+            let range = start..start + word.len();
+            if times.is_some() {
+                return Err((
+                    "unexpected text after the times of the schedule".to_string(),
+                    range,
+                ));
+            }
+            if word.starts_with(|c: char| c.is_ascii_digit()) {
+                times = Some(parse_times(word, start)?);
+            } else if days.is_some() {
+                return Err((
+                    "the days of the schedule are already given, separate days with commas"
+                        .to_string(),
+                    range,
+                ));
+            } else {
+                days = Some(parse_days(word, start)?);
+            }
+        }
+        Ok(Self::new(
+            days.unwrap_or_default(),
+            times.unwrap_or_default(),
+        ))
+    }
+}
+
+/// The words of `text` separated by whitespace, along with their offsets.
+fn words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = vec![];
+    let mut start = None;
+    for (index, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(index),
+            (Some(from), true) => {
+                words.push((from, &text[from..index]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+/// The comma separated items of `word`, along with their offsets, given
+/// that `word` starts at `offset`.
+fn items(word: &str, offset: usize) -> impl Iterator<Item = (Range<usize>, &str)> {
+    let mut start = offset;
+    word.split(',').map(move |item| {
+        let range = start..start + item.len();
+        start = range.end + 1;
+        (range, item)
+    })
+}
+
+fn parse_days(word: &str, offset: usize) -> Result<BTreeSet<Weekday>, (String, Range<usize>)> {
+    let mut days = BTreeSet::new();
+    for (range, item) in items(word, offset) {
+        let day = |name: &str, range: Range<usize>| {
+            Weekday::from_name(&name.to_lowercase()).ok_or_else(|| {
+                (
+                    format!(
+                        "unknown day `{name}`, expected one of {}, weekday, weekend or daily",
+                        Weekday::ALL.iter().map(|day| day.name()).join(", ")
+                    ),
+                    range,
+                )
+            })
+        };
+        match item.to_lowercase().as_str() {
+            "weekday" | "weekdays" => days.extend(WEEKDAYS),
+            "weekend" | "weekends" => days.extend(WEEKEND),
+            "daily" => days.extend(Weekday::ALL),
+            _ => match item.split_once('-') {
+                // A range may wrap around the end of the week, e.g. `fri-mon`.
+                Some((first, last)) => {
+                    let first_range = range.start..range.start + first.len();
+                    let first = day(first, first_range)?;
+                    let last = day(last, range.end - last.len()..range.end)?;
+                    let mut current = first as usize;
+                    loop {
+                        days.insert(Weekday::ALL[current]);
+                        if current == last as usize {
+                            break;
+                        }
+                        current = (current + 1) % Weekday::ALL.len();
+                    }
+                }
+                None => {
+                    days.insert(day(item, range)?);
+                }
+            },
+        }
+    }
+    Ok(days)
+}
+
+fn parse_times(word: &str, offset: usize) -> Result<Vec<TimeRange>, (String, Range<usize>)> {
+    items(word, offset)
+        .map(|(range, item)| {
+            let Some((start, end)) = item.split_once('-') else {
+                return Err((
+                    format!("expected a time range like `09:00-17:00`, found `{item}`"),
+                    range,
+                ));
+            };
+            let time_range = TimeRange {
+                start: parse_time(start, range.start)?,
+                end: parse_time(end, range.end - end.len())?,
+            };
+            if time_range.start == time_range.end {
+                return Err((
+                    format!("the time range `{item}` is empty, it starts when it ends"),
+                    range,
+                ));
+            }
+            Ok(time_range)
+        })
+        .collect()
+}
+
+fn parse_time(time: &str, offset: usize) -> Result<TimeOfDay, (String, Range<usize>)> {
+    let range = offset..offset + time.len();
+    let number = |digits: &str| {
+        (digits.len() == 2 && digits.bytes().all(|b| b.is_ascii_digit()))
+            .then(|| digits.parse::<u8>().ok())
+            .flatten()
+    };
+    let parsed = time
+        .split_once(':')
+        .and_then(|(hour, minute)| Some((number(hour)?, number(minute)?)));
+    match parsed {
+        Some((hour, minute)) if hour < 24 && minute < 60 => Ok(TimeOfDay { hour, minute }),
+        Some(_) => Err((
+            format!("`{time}` is not a time of day, they range from 00:00 to 23:59"),
+            range,
+        )),
+        None => Err((
+            format!("expected a time like `09:00`, found `{time}`"),
+            range,
+        )),
+    }
+}
+
+impl Display for Schedule {
+    /// Writes the schedule as it is written in a `[when=...]` tag, naming
+    /// days as briefly as possible, e.g. `weekday` or `mon-wed,sat`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let days = if self.days.len() == Weekday::ALL.len() {
+            None
+        } else if self.days.iter().eq(WEEKDAYS.iter()) {
+            Some("weekday".to_string())
+        } else if self.days.iter().eq(WEEKEND.iter()) {
+            Some("weekend".to_string())
+        } else {
+            // Runs of at least three consecutive days are written as ranges.
+            let runs = self
+                .days
+                .iter()
+                .map(|day| *day as usize)
+                .enumerate()
+                .group_by(|(index, day)| day - index);
+            let runs = runs.into_iter().map(|(_, run)| {
+                let run = run.map(|(_, day)| Weekday::ALL[day]).collect_vec();
+                match run.as_slice() {
+                    [first, .., last] if run.len() >= 3 => {
+                        format!("{}-{}", first.name(), last.name())
+                    }
+                    _ => run.iter().map(|day| day.name()).join(","),
+                }
+            });
+            Some(runs.collect_vec().join(","))
+        };
+        match (days, self.times.is_empty()) {
+            (None, true) => write!(f, "daily"),
+            (None, false) => write!(f, "{}", self.times.iter().format(",")),
+            (Some(days), true) => write!(f, "{days}"),
+            (Some(days), false) => write!(f, "{days} {}", self.times.iter().format(",")),
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = ParseError;
+
+    /// Parses the expression of a `[when=...]` tag, e.g. `weekend 10:00-18:00`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_expression(s).map_err(|(message, range)| {
+            // Safety: the range lies on character boundaries of `s`.
+            spanned_error(message, Span::new(s, range.start, range.end).unwrap())
+        })
+    }
+}
+
+impl From<Schedule> for String {
+    fn from(schedule: Schedule) -> Self {
+        schedule.to_string()
+    }
+}
+
+impl TryFrom<String> for Schedule {
+    type Error = ParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
@@ -0,0 +1,340 @@
+//! What bindings mean at runtime, as pure functions a daemon can call
+//! instead of reimplementing them.
+//!
+//! # Firing
+//!
+//! [`should_fire`] decides what a single input event does to a single
+//! [`Definition`]. Key events come in two [`Phase`]s. A definition matches
+//! a key event when its key is the key of the event, its modifiers are
+//! exactly the modifiers held, or it uses `any`, and its held keys, as in
+//! `ctrl + x & e`, are all held. Other held keys do not prevent a match.
+//! On a match, the key attributes decide what happens:
+//!
+//! | attribute | press | release |
+//! | --- | --- | --- |
+//! | none, e.g. `a` | fire | consume |
+//! | `~`, e.g. `~a` | fire, forward | consume, forward |
+//! | `@`, e.g. `@a` | consume | fire |
+//! | `~@`, e.g. `~@a` | consume, forward | fire, forward |
+//!
+//! Consuming means the event belongs to the chord, so the daemon does not
+//! look for another binding, but the command does not run. Forwarding
+//! means the focused window receives the event as well, otherwise the
+//! daemon swallows it. A key marked with `passthrough`, e.g.
+//! `a passthrough`, is always forwarded: the daemon should not grab it in
+//! the first place, which is left to the daemon as well.
+//!
+//! Attributes of modifiers, e.g. `@super + tab`, are left to the daemon:
+//! the definition matches as if its modifiers had none.
+//!
+//! Modifier-only chords, e.g. `shift + @super`, match the press or the
+//! release of one of their modifiers while the other ones and no other key
+//! are held. Their events are always forwarded, since other chords and
+//! windows rely on the modifiers. With `!`, e.g. `@super!`, the release
+//! only fires if no other key was pressed since the modifiers were, and is
+//! consumed otherwise.
+//!
+//! Scroll events, switches and virtual keys have no release: they match on
+//! [`Phase::Press`] only, and never on [`Phase::Release`].
+//!
+//! Selecting among several matching bindings is left to the daemon, see
+//! [`Unreachable`](crate::Unreachable) for the precedence.
+//!
+//! # Modes
+//!
+//! A [`ModeStack`] holds the modes entered, the last one being active.
+//! [`mode_transition`] applies a single instruction: `@enter` pushes the
+//! mode, even if it is already on the stack, and `@escape` leaves the
+//! active mode, doing nothing outside of modes. [`after_binding`] applies
+//! every instruction of a binding that fired, then leaves a `oneoff` mode
+//! if the binding belongs to it and did not change the stack itself.
+//! [`forwards_unbound`] tells whether an event no binding matched reaches
+//! the focused window, which only a `swallow` mode prevents, except for
+//! its `replay` keys.
+
+use std::collections::BTreeSet;
+
+use crate::{
+    Binding, Definition, KeyAttribute, Mode, ModeInstruction, Modifier, ScrollDirection,
+    SwitchType, Trigger,
+};
+
+/// An input event, see [`should_fire`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimulatedEvent {
+    /// A key pressed or released, modifiers included.
+    Key(evdev::Key),
+    Scroll(ScrollDirection),
+    Switch {
+        switch: SwitchType,
+        state: bool,
+    },
+    /// A virtual key fired by name.
+    Virtual(String),
+}
+
+/// Whether a key went down or up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Press,
+    Release,
+}
+
+/// What is held when an event happens, not counting the key of the event
+/// itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModifierState {
+    pub modifiers: BTreeSet<Modifier>,
+    /// Keys held other than modifiers.
+    pub keys: BTreeSet<evdev::Key>,
+    /// Whether a key other than a modifier was pressed since the modifiers
+    /// were, for modifier-only chords with `!`.
+    pub interrupted: bool,
+}
+
+impl ModifierState {
+    /// Holding `modifiers` and nothing else.
+    pub fn holding(modifiers: &[Modifier]) -> Self {
+        Self {
+            modifiers: modifiers.iter().copied().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Also holding `keys`.
+    pub fn with_keys(mut self, keys: impl IntoIterator<Item = evdev::Key>) -> Self {
+        self.keys.extend(keys);
+        self
+    }
+
+    /// Marks another key as pressed since the modifiers were.
+    pub fn interrupted(mut self) -> Self {
+        self.interrupted = true;
+        self
+    }
+}
+
+/// What an event does to a definition, see [`should_fire`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FireDecision {
+    /// The event is not part of the chord, other bindings may match it.
+    NoMatch,
+    /// The event is part of the chord, but the command does not run, e.g.
+    /// the press of `@a`.
+    Consume { forward: bool },
+    /// The command runs.
+    Fire { forward: bool },
+}
+
+impl FireDecision {
+    pub fn fires(self) -> bool {
+        matches!(self, Self::Fire { .. })
+    }
+}
+
+/// The modifier a key is, if any. Both keys of a modifier map to it, except
+/// for Alt: the right one is AltGr. A definition with modifiers of one side,
+/// e.g. `lalt + x`, only matches a [`ModifierState`] holding them as such.
+pub fn modifier_of(key: evdev::Key) -> Option<Modifier> {
+    use evdev::Key;
+    match key {
+        Key::KEY_LEFTMETA | Key::KEY_RIGHTMETA => Some(Modifier::Super),
+        Key::KEY_LEFTALT => Some(Modifier::Alt),
+        Key::KEY_RIGHTALT => Some(Modifier::Altgr),
+        Key::KEY_LEFTCTRL | Key::KEY_RIGHTCTRL => Some(Modifier::Control),
+        Key::KEY_LEFTSHIFT | Key::KEY_RIGHTSHIFT => Some(Modifier::Shift),
+        _ => None,
+    }
+}
+
+/// Decides whether `event` in `phase` fires `def` while `held` is held,
+/// following the rules of the [module documentation](self).
+///
+/// ```
+/// use sweet::semantics::{should_fire, FireDecision, ModifierState, Phase, SimulatedEvent};
+/// use sweet::{Definition, Modifier};
+///
+/// let definition: Definition = "super + @a".parse()?;
+/// let event = SimulatedEvent::Key(evdev::Key::KEY_A);
+/// let held = ModifierState::holding(&[Modifier::Super]);
+/// assert_eq!(
+///     should_fire(&definition, &event, &held, Phase::Press),
+///     FireDecision::Consume { forward: false }
+/// );
+/// assert!(should_fire(&definition, &event, &held, Phase::Release).fires());
+/// # Ok::<(), sweet::ParseError>(())
+/// ```
+pub fn should_fire(
+    def: &Definition,
+    event: &SimulatedEvent,
+    held: &ModifierState,
+    phase: Phase,
+) -> FireDecision {
+    let attribute = def.key().attribute;
+    let forward = attribute.intersects(KeyAttribute::Send | KeyAttribute::Passthrough);
+    let modifiers_match = |modifiers: &BTreeSet<Modifier>| {
+        def.modifiers().contains(&Modifier::Any) || def.modifiers() == modifiers
+    };
+    match (&def.key().trigger, event) {
+        (Trigger::Key(key), SimulatedEvent::Key(event_key)) if key == event_key => {
+            if !modifiers_match(&held.modifiers) || !def.held_keys().is_subset(&held.keys) {
+                return FireDecision::NoMatch;
+            }
+            timed(attribute, phase, forward)
+        }
+        (Trigger::ModifiersOnly, SimulatedEvent::Key(event_key)) => {
+            let Some(modifier) = modifier_of(*event_key) else {
+                return FireDecision::NoMatch;
+            };
+            let mut modifiers = held.modifiers.clone();
+            modifiers.insert(modifier);
+            if !modifiers_match(&modifiers) || !held.keys.is_empty() {
+                return FireDecision::NoMatch;
+            }
+            match timed(attribute, phase, true) {
+                FireDecision::Fire { forward }
+                    if attribute.contains(KeyAttribute::Solo) && held.interrupted =>
+                {
+                    FireDecision::Consume { forward }
+                }
+                decision => decision,
+            }
+        }
+        (Trigger::Scroll(direction), SimulatedEvent::Scroll(event_direction))
+            if direction == event_direction && modifiers_match(&held.modifiers) =>
+        {
+            instant(phase, forward)
+        }
+        (
+            Trigger::Switch { switch, state },
+            SimulatedEvent::Switch {
+                switch: s,
+                state: on,
+            },
+        ) if switch == s && state == on => instant(phase, forward),
+        (Trigger::Virtual(name), SimulatedEvent::Virtual(fired)) if name == fired => {
+            instant(phase, forward)
+        }
+        _ => FireDecision::NoMatch,
+    }
+}
+
+/// The decision on a matching key event, from the `@` attribute.
+fn timed(attribute: KeyAttribute, phase: Phase, forward: bool) -> FireDecision {
+    let fire_phase = if attribute.contains(KeyAttribute::OnRelease) {
+        Phase::Release
+    } else {
+        Phase::Press
+    };
+    if phase == fire_phase {
+        FireDecision::Fire { forward }
+    } else {
+        FireDecision::Consume { forward }
+    }
+}
+
+/// The decision on a matching event without a release.
+fn instant(phase: Phase, forward: bool) -> FireDecision {
+    match phase {
+        Phase::Press => FireDecision::Fire { forward },
+        Phase::Release => FireDecision::NoMatch,
+    }
+}
+
+/// The modes entered, as indices in [`SwhkdParser::modes`](crate::SwhkdParser::modes)
+/// like the ones of [`SwhkdParser::entered_mode`](crate::SwhkdParser::entered_mode).
+/// The last one is active, and an empty stack is the top level.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModeStack(pub Vec<usize>);
+
+impl ModeStack {
+    /// The index of the active mode, `None` at the top level.
+    pub fn active(&self) -> Option<usize> {
+        self.0.last().copied()
+    }
+
+    /// The active mode among `modes`, `None` at the top level.
+    pub fn active_mode<'a>(&self, modes: &'a [Mode]) -> Option<&'a Mode> {
+        modes.get(self.active()?)
+    }
+}
+
+/// The stack after running `instr`. Entering a mode missing from
+/// `mode_defs` changes nothing, parsed configs never do so.
+///
+/// ```
+/// use sweet::semantics::{mode_transition, ModeStack};
+/// use sweet::{ModeInstruction, ParserInput, SwhkdParser};
+///
+/// let config = "mode resize\n    h\n        grow\nendmode";
+/// let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+/// let enter = ModeInstruction::Enter("resize".to_string());
+/// let stack = mode_transition(&ModeStack::default(), &enter, &parsed.modes);
+/// assert_eq!(stack, ModeStack(vec![0]));
+/// let stack = mode_transition(&stack, &ModeInstruction::Escape, &parsed.modes);
+/// assert_eq!(stack, ModeStack::default());
+/// # Ok::<(), sweet::ParseError>(())
+/// ```
+pub fn mode_transition(
+    current: &ModeStack,
+    instr: &ModeInstruction,
+    mode_defs: &[Mode],
+) -> ModeStack {
+    let mut next = current.clone();
+    match instr {
+        ModeInstruction::Enter(name) => {
+            if let Some(index) = mode_defs.iter().position(|mode| mode.name == *name) {
+                next.0.push(index);
+            }
+        }
+        ModeInstruction::Escape => {
+            next.0.pop();
+        }
+    }
+    next
+}
+
+/// The stack after `binding` fired, `in_active_mode` telling whether it is
+/// a binding of the active mode rather than a top-level one. Its
+/// instructions run in order, then a `oneoff` mode is left if the binding
+/// belongs to it and the instructions did not change the stack.
+pub fn after_binding(
+    current: &ModeStack,
+    binding: &Binding,
+    in_active_mode: bool,
+    mode_defs: &[Mode],
+) -> ModeStack {
+    let mut next = binding
+        .mode_instructions()
+        .iter()
+        .fold(current.clone(), |stack, instr| {
+            mode_transition(&stack, instr, mode_defs)
+        });
+    let oneoff = current
+        .active_mode(mode_defs)
+        .is_some_and(|mode| mode.oneoff);
+    if in_active_mode && oneoff && next == *current {
+        next.0.pop();
+    }
+    next
+}
+
+/// Whether an event no binding matched reaches the focused window. Only
+/// the active mode counts: a `swallow` mode keeps every event but the ones
+/// of its `replay` keys.
+pub fn forwards_unbound(
+    current: &ModeStack,
+    mode_defs: &[Mode],
+    event: &SimulatedEvent,
+    held: &ModifierState,
+    phase: Phase,
+) -> bool {
+    let Some(mode) = current.active_mode(mode_defs) else {
+        return true;
+    };
+    !mode.swallow
+        || mode
+            .replay
+            .iter()
+            .any(|replay| should_fire(replay, event, held, phase) != FireDecision::NoMatch)
+}
@@ -0,0 +1,253 @@
+//! Serde support for parsed configs, enabled by the `serde` feature.
+//!
+//! Keys are written by their canonical config name, e.g. `"return"`, and
+//! key attributes as a list of flag names, e.g. `["Send", "OnRelease"]`.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    Binding, Definition, Key, KeyAttribute, ModeInstruction, Modifier, Schedule, Settings,
+    SourceSpan,
+};
+
+/// What a [`Definition`] is deserialized from, so that it goes through
+/// the same constructor as parsed ones.
+#[derive(Deserialize)]
+pub(crate) struct DefinitionParts {
+    modifiers: Vec<Modifier>,
+    key: Key,
+    #[serde(default, with = "held_keys")]
+    held: BTreeSet<evdev::Key>,
+    #[serde(default, with = "modifier_attributes")]
+    modifier_attributes: BTreeMap<Modifier, KeyAttribute>,
+}
+
+impl From<DefinitionParts> for Definition {
+    fn from(parts: DefinitionParts) -> Self {
+        let definition =
+            Definition::from_parts(parts.modifiers, parts.key).with_held_keys(parts.held);
+        parts.modifier_attributes.into_iter().fold(
+            definition,
+            |definition, (modifier, attribute)| {
+                definition.with_modifier_attribute(modifier, attribute)
+            },
+        )
+    }
+}
+
+#[derive(Serialize)]
+struct BindingRef<'a> {
+    definition: &'a Definition,
+    /// The chords after the first one of a sequence.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    then: &'a [Definition],
+    command: &'a str,
+    instructions_before_command: &'a [ModeInstruction],
+    instructions_after_command: &'a [ModeInstruction],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schedule: Option<&'a Schedule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device: Option<&'a str>,
+    #[serde(skip_serializing_if = "is_default")]
+    settings: &'a Settings,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mode: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    source_text: &'a str,
+    span: Option<&'a SourceSpan>,
+}
+
+#[derive(Deserialize)]
+struct BindingParts {
+    definition: Definition,
+    #[serde(default)]
+    then: Vec<Definition>,
+    command: String,
+    #[serde(default)]
+    instructions_before_command: Vec<ModeInstruction>,
+    #[serde(default)]
+    instructions_after_command: Vec<ModeInstruction>,
+    #[serde(default)]
+    schedule: Option<Schedule>,
+    #[serde(default)]
+    device: Option<String>,
+    #[serde(default)]
+    settings: Settings,
+    #[serde(default)]
+    mode: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    source_text: Arc<str>,
+    #[serde(default)]
+    span: Option<SourceSpan>,
+}
+
+impl Serialize for Binding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        BindingRef {
+            definition: self.definition(),
+            then: &self.sequence()[1..],
+            command: self.command(),
+            instructions_before_command: self.instructions_before_command(),
+            instructions_after_command: self.instructions_after_command(),
+            schedule: self.schedule(),
+            device: self.device(),
+            settings: self.settings(),
+            mode: self.mode(),
+            description: self.description(),
+            source_text: self.source_text(),
+            span: self.span(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Binding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let parts = BindingParts::deserialize(deserializer)?;
+        let mut binding = Binding::running(parts.command).on(parts.definition);
+        binding = parts
+            .then
+            .into_iter()
+            .fold(binding, Binding::then)
+            .with_mode_instructions_before_command(parts.instructions_before_command)
+            .with_mode_instructions(parts.instructions_after_command)
+            .written_at(parts.source_text, parts.span)
+            .with_settings(parts.settings);
+        binding.record_mode(parts.mode.as_deref());
+        if let Some(schedule) = parts.schedule {
+            binding = binding.with_schedule(schedule);
+        }
+        if let Some(device) = parts.device {
+            binding = binding.with_device(device);
+        }
+        if let Some(description) = parts.description {
+            binding = binding.with_description(description);
+        }
+        Ok(binding)
+    }
+}
+
+fn is_default(settings: &&Settings) -> bool {
+    **settings == Settings::default()
+}
+
+/// An evdev key as its canonical config name, see [`crate::key_name`].
+pub(crate) mod evdev_key {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    use crate::evdev_mappings;
+
+    pub(crate) fn serialize<S: Serializer>(
+        key: &evdev::Key,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match evdev_mappings::key_name(*key) {
+            Some(name) => serializer.serialize_str(name),
+            None => serializer.serialize_str(&evdev_mappings::key_code_name(*key)),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<evdev::Key, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        evdev_mappings::lookup_key(&name)
+            .ok_or_else(|| D::Error::custom(format!("unknown key `{name}`")))
+    }
+}
+
+/// Held keys of a [`Definition`] as a list of key names.
+pub(crate) mod held_keys {
+    use std::collections::BTreeSet;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(transparent)]
+    struct Name(#[serde(with = "super::evdev_key")] evdev::Key);
+
+    pub(crate) fn serialize<S: Serializer>(
+        keys: &BTreeSet<evdev::Key>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(keys.iter().map(|key| Name(*key)))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeSet<evdev::Key>, D::Error> {
+        Ok(Vec::<Name>::deserialize(deserializer)?
+            .into_iter()
+            .map(|name| name.0)
+            .collect())
+    }
+}
+
+/// Key attributes as the names of their flags.
+pub(crate) mod key_attribute {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    use crate::KeyAttribute;
+
+    pub(crate) fn serialize<S: Serializer>(
+        attribute: &KeyAttribute,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(attribute.iter_names().map(|(name, _)| name))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<KeyAttribute, D::Error> {
+        Vec::<String>::deserialize(deserializer)?.iter().try_fold(
+            KeyAttribute::None,
+            |attribute, name| {
+                KeyAttribute::from_name(name)
+                    .map(|flag| attribute | flag)
+                    .ok_or_else(|| D::Error::custom(format!("unknown key attribute `{name}`")))
+            },
+        )
+    }
+}
+
+/// Attributes of modifiers as a map from each modifier to the names of its
+/// attribute flags, see [`key_attribute`].
+pub(crate) mod modifier_attributes {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::{KeyAttribute, Modifier};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(transparent)]
+    struct Flags(#[serde(with = "super::key_attribute")] KeyAttribute);
+
+    pub(crate) fn serialize<S: Serializer>(
+        attributes: &BTreeMap<Modifier, KeyAttribute>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            attributes
+                .iter()
+                .map(|(modifier, attribute)| (modifier, Flags(*attribute))),
+        )
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<Modifier, KeyAttribute>, D::Error> {
+        Ok(BTreeMap::<Modifier, Flags>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(modifier, flags)| (modifier, flags.0))
+            .collect())
+    }
+}
@@ -0,0 +1,217 @@
+use std::time::Duration;
+
+use pest::iterators::Pair;
+
+use crate::{parse::spanned_error, Binding, ParseError, Rule, SwhkdParser};
+
+/// Daemon settings declared with `set NAME VALUE` statements.
+///
+/// Every field is optional so that a scope only overrides what it sets.
+/// Settings are resolved per binding by [`SwhkdParser::effective_settings`]
+/// in the following order, the first scope setting a field wins:
+///
+/// 1. the binding's own settings, from `[set NAME=VALUE]` tags
+/// 2. the settings of the binding's device, from `[device=...] set`
+///    statements
+/// 3. the enclosing mode's settings
+/// 4. the top-level settings
+/// 5. the built-in defaults ([`Settings::builtin`])
+///
+/// Top-level and device settings of the root config take precedence over
+/// the ones declared in included files.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Settings {
+    /// Shell used to run commands.
+    pub shell: Option<String>,
+    /// Default delay between repeated triggers of a binding.
+    pub throttle_default: Option<Duration>,
+    /// Mode entered when the daemon starts. Only valid at the top level.
+    pub start_mode: Option<String>,
+}
+
+/// The scope a `set` statement appears in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SettingScope {
+    TopLevel,
+    Mode,
+    /// A top-level `set` statement tagged with a device.
+    Device,
+    /// A `[set NAME=VALUE]` tag of a binding.
+    Binding,
+}
+
+impl Settings {
+    /// The built-in defaults every scope falls back to.
+    pub fn builtin() -> Self {
+        Self {
+            shell: Some("/bin/sh".to_string()),
+            throttle_default: Some(Duration::ZERO),
+            start_mode: None,
+        }
+    }
+
+    /// Fills every field not set in `self` from `outer`.
+    pub fn or(self, outer: &Settings) -> Self {
+        Self {
+            shell: self.shell.or_else(|| outer.shell.clone()),
+            throttle_default: self.throttle_default.or(outer.throttle_default),
+            start_mode: self.start_mode.or_else(|| outer.start_mode.clone()),
+        }
+    }
+
+    pub(crate) fn ingest(
+        &mut self,
+        pair: Pair<'_, Rule>,
+        scope: SettingScope,
+    ) -> Result<(), ParseError> {
+        let span = pair.as_span();
+        let mut inner = pair
+            .into_inner()
+            .skip_while(|pair| pair.as_rule() == Rule::device_tag);
+        // Safety: the grammar guarantees a setting name followed by a value.
+        let name = inner.next().unwrap().as_str();
+        let value = inner.next().unwrap().as_str().trim();
+        match name {
+            "shell" => self.shell = Some(value.to_string()),
+            "throttle_default" => {
+                let Some(duration) = parse_duration(value) else {
+                    return Err(spanned_error(
+                        format!("`{value}` is not a valid duration, expected e.g. `250ms` or `1s`"),
+                        span,
+                    ));
+                };
+                self.throttle_default = Some(duration);
+            }
+            "start_mode" if scope != SettingScope::TopLevel => {
+                return Err(spanned_error(start_mode_outside_top_level(), span));
+            }
+            "start_mode" => self.start_mode = Some(value.to_string()),
+            _ => {
+                return Err(spanned_error(
+                    format!("`{name}` is not a recognized setting"),
+                    span,
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks settings built in code: they have to be allowed in `scope`
+    /// and writable as `set` statements.
+    pub(crate) fn check(&self, scope: SettingScope) -> Result<(), String> {
+        if scope != SettingScope::TopLevel && self.start_mode.is_some() {
+            return Err(start_mode_outside_top_level());
+        }
+        for (name, value) in [("shell", &self.shell), ("start_mode", &self.start_mode)] {
+            if let Some(value) = value.as_deref().filter(|value| {
+                value.is_empty() || value.trim() != *value || value.contains(['\n', '\r'])
+            }) {
+                return Err(format!(
+                    "setting `{name}` cannot be set to {value:?}, values are single lines without surrounding whitespace"
+                ));
+            }
+            if let Some(value) = value
+                .as_deref()
+                .filter(|value| scope == SettingScope::Binding && value.contains(']'))
+            {
+                return Err(format!(
+                    "setting `{name}` cannot be set to {value:?} on a binding, `[set ...]` tags cannot hold `]`"
+                ));
+            }
+        }
+        if let Some(duration) = self
+            .throttle_default
+            .filter(|duration| duration.subsec_nanos() % 1_000_000 != 0)
+        {
+            return Err(format!(
+                "setting `throttle_default` cannot be set to {duration:?}, durations are whole milliseconds"
+            ));
+        }
+        Ok(())
+    }
+
+    /// The `set` statements declaring these settings.
+    pub(crate) fn to_statements(&self) -> Vec<String> {
+        self.entries()
+            .into_iter()
+            .map(|(name, value)| format!("set {name} {value}"))
+            .collect()
+    }
+
+    /// The `[set NAME=VALUE]` tags declaring these settings on a binding.
+    pub(crate) fn to_tags(&self) -> Vec<String> {
+        self.entries()
+            .into_iter()
+            .map(|(name, value)| format!("[set {name}={value}]"))
+            .collect()
+    }
+
+    /// The name and value of every field set, as written in a config.
+    fn entries(&self) -> Vec<(&'static str, String)> {
+        let mut entries = vec![];
+        if let Some(shell) = &self.shell {
+            entries.push(("shell", shell.clone()));
+        }
+        if let Some(duration) = self.throttle_default {
+            entries.push(("throttle_default", format!("{}ms", duration.as_millis())));
+        }
+        if let Some(mode) = &self.start_mode {
+            entries.push(("start_mode", mode.clone()));
+        }
+        entries
+    }
+}
+
+fn start_mode_outside_top_level() -> String {
+    "setting `start_mode` is only allowed at the top level".to_string()
+}
+
+fn parse_duration(value: &str) -> Option<Duration> {
+    if let Some(millis) = value.strip_suffix("ms") {
+        return millis.parse().ok().map(Duration::from_millis);
+    }
+    value
+        .strip_suffix('s')
+        .and_then(|secs| secs.parse().ok())
+        .map(Duration::from_secs)
+}
+
+impl SwhkdParser {
+    /// Resolves the settings that apply to `binding`, from its own
+    /// settings and the ones of the mode and device it is declared for,
+    /// see [`Binding::mode`] and [`Binding::device`]. `binding` may be a
+    /// clone or a deserialized copy of a binding of this parser. See
+    /// [`Settings`] for the merge order.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use sweet::{ParserInput, SwhkdParser};
+    ///
+    /// let config = "
+    /// set throttle_default 50ms
+    /// [device=macro-pad] set throttle_default 100ms
+    /// [device=macro-pad] f1
+    ///     true
+    /// [device=macro-pad] [set throttle_default=0ms] f2
+    ///     true";
+    /// let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    /// let throttle = |i: usize| parsed.effective_settings(&parsed.bindings[i]).throttle_default;
+    /// assert_eq!(throttle(0), Some(Duration::from_millis(100)));
+    /// assert_eq!(throttle(1), Some(Duration::ZERO));
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn effective_settings(&self, binding: &Binding) -> Settings {
+        let mut settings = self.settings.clone().or(&Settings::builtin());
+        if let Some(mode) = binding
+            .mode()
+            .and_then(|name| self.modes.iter().find(|mode| mode.name == name))
+        {
+            settings = mode.settings.clone().or(&settings);
+        }
+        if let Some(device) = binding.device().and_then(|d| self.device_settings.get(d)) {
+            settings = device.clone().or(&settings);
+        }
+        binding.settings().clone().or(&settings)
+    }
+}
@@ -0,0 +1,134 @@
+//! Checking commands for shell syntax that `sh -c` fails on, see
+//! [`lint_command`].
+
+use std::fmt::Display;
+
+use crate::{SwhkdParser, Warning};
+
+/// A likely mistake in the shell syntax of a command, found by
+/// [`lint_command`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CommandLint {
+    /// A `'`, `"` or `` ` `` is never closed.
+    UnclosedQuote(char),
+    /// A `(` or `{` is never closed.
+    UnclosedBracket(char),
+    /// A `)` or `}` closes nothing, or closes the other kind of bracket.
+    UnmatchedBracket(char),
+    /// The command ends with a backslash, which escapes nothing.
+    TrailingBackslash,
+    /// The command is empty or only whitespace, e.g. a shorthand variant
+    /// left empty.
+    Empty,
+}
+
+impl Display for CommandLint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnclosedQuote(quote) => write!(f, "`{quote}` is never closed"),
+            Self::UnclosedBracket(bracket) => write!(f, "`{bracket}` is never closed"),
+            Self::UnmatchedBracket(bracket) => write!(f, "`{bracket}` closes nothing"),
+            Self::TrailingBackslash => f.write_str("the command ends with a backslash"),
+            Self::Empty => f.write_str("the command is empty"),
+        }
+    }
+}
+
+/// Checks the shell syntax of `command` for unclosed quotes and brackets,
+/// a trailing backslash and empty commands, which `sh -c` fails on when
+/// the binding fires rather than when the config is parsed.
+///
+/// The check only follows quotes, backslashes, brackets and comments, so
+/// valid but unusual shell, e.g. a `case` pattern closed with a lone `)`,
+/// is reported as well. Each lint is reported once.
+///
+/// ```
+/// use sweet::{lint_command, CommandLint};
+///
+/// assert_eq!(lint_command("notify-send 'hello"), [CommandLint::UnclosedQuote('\'')]);
+/// assert!(lint_command("echo \"it's $(date)\" | wc -c # (comment").is_empty());
+/// ```
+pub fn lint_command(command: &str) -> Vec<CommandLint> {
+    let mut lints = vec![];
+    let mut report = |lint| {
+        if !lints.contains(&lint) {
+            lints.push(lint);
+        }
+    };
+    if command.trim().is_empty() {
+        report(CommandLint::Empty);
+        return lints;
+    }
+    let mut brackets = vec![];
+    let mut quote = None;
+    // Whether the previous character ends a word, so a `#` starts a
+    // comment.
+    let mut word_start = true;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        let starts_word = c.is_whitespace() || matches!(c, ';' | '&' | '|' | '(' | ')');
+        match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') | (Some('`'), '`') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => {
+                if chars.next().is_none() {
+                    report(CommandLint::TrailingBackslash);
+                }
+            }
+            (Some(_), _) => {}
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, '#') if word_start => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            (None, '(' | '{') => brackets.push(c),
+            (None, ')' | '}') => {
+                let open = if c == ')' { '(' } else { '{' };
+                if brackets.last() == Some(&open) {
+                    brackets.pop();
+                } else {
+                    report(CommandLint::UnmatchedBracket(c));
+                }
+            }
+            (None, _) => {}
+        }
+        word_start = starts_word;
+    }
+    if let Some(quote) = quote {
+        report(CommandLint::UnclosedQuote(quote));
+    }
+    for bracket in brackets {
+        report(CommandLint::UnclosedBracket(bracket));
+    }
+    lints
+}
+
+impl SwhkdParser {
+    /// Warns about commands that likely fail in the shell, see
+    /// [`ParseOptions::validate_commands`](crate::ParseOptions::validate_commands).
+    pub(crate) fn warn_about_commands(&mut self) {
+        let scopes =
+            std::iter::once(&self.bindings).chain(self.modes.iter().map(|mode| &mode.bindings));
+        let mut warnings = vec![];
+        for binding in scopes.flatten() {
+            let Some(span) = binding.span() else {
+                continue;
+            };
+            for lint in lint_command(binding.command()) {
+                // Bindings only entering or leaving modes run no command.
+                if lint == CommandLint::Empty && !binding.mode_instructions().is_empty() {
+                    continue;
+                }
+                let warning = Warning::CommandSyntax {
+                    span: span.clone(),
+                    lint,
+                };
+                // Variants of a shorthand share their span.
+                if !warnings.contains(&warning) {
+                    warnings.push(warning);
+                }
+            }
+        }
+        self.warnings.extend(warnings);
+    }
+}
@@ -0,0 +1,171 @@
+use std::{fmt::Write, ops::Range, sync::Arc};
+
+use pest::Span;
+
+use crate::{
+    parse::spanned_error,
+    render::{RenderStyle, BOLD, CYAN, DIM},
+    ParseError,
+};
+
+/// A location in a config file, kept around after parsing for diagnostics.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceSpan {
+    /// Path of the config file, or `<anonymous>` for raw input.
+    pub file: Arc<str>,
+    /// 1-based line of the start of the span.
+    pub line: usize,
+    /// 1-based column of the start of the span.
+    pub column: usize,
+    /// 1-based line of the end of the span.
+    pub end_line: usize,
+    /// 1-based column of the end of the span.
+    pub end_column: usize,
+    /// The full text of the line the span starts on.
+    pub line_text: Arc<str>,
+}
+
+impl SourceSpan {
+    pub(crate) fn new(file: &str, span: Span<'_>) -> Self {
+        let (line, column) = span.start_pos().line_col();
+        let (end_line, end_column) = span.end_pos().line_col();
+        let line_text = span.start_pos().line_of().trim_end_matches(['\r', '\n']);
+        Self {
+            file: file.into(),
+            line,
+            column,
+            end_line,
+            end_column,
+            line_text: line_text.into(),
+        }
+    }
+
+    /// Renders `note` below a snippet of the spanned line, in the same
+    /// layout as pest's grammar errors.
+    pub fn render(&self, note: &str) -> String {
+        self.render_styled(note, RenderStyle::Plain)
+    }
+
+    /// [`SourceSpan::render`] in `style`. Only the colors differ between
+    /// styles, never the text or its layout.
+    pub(crate) fn render_styled(&self, note: &str, style: RenderStyle) -> String {
+        let gutter = " ".repeat(self.line.to_string().len());
+        // Underline up to the end of the first line only.
+        let line_len = self.line_text.chars().count() + 1;
+        let end = if self.end_line == self.line {
+            self.end_column.min(line_len)
+        } else {
+            line_len
+        };
+        let width = end.saturating_sub(self.column).max(1);
+        let underline = if width == 1 {
+            "^".to_string()
+        } else {
+            format!("^{}^", "-".repeat(width - 2))
+        };
+
+        let mut out = String::new();
+        let location = format!("{}:{}:{}", self.file, self.line, self.column);
+        let _ = writeln!(out, "{gutter}--> {}", style.paint(DIM, &location));
+        let _ = writeln!(out, "{gutter} |");
+        let _ = writeln!(out, "{} | {}", self.line, self.line_text);
+        let _ = writeln!(
+            out,
+            "{gutter} | {}{}",
+            " ".repeat(self.column - 1),
+            style.paint(BOLD, &underline)
+        );
+        let _ = writeln!(out, "{gutter} |");
+        let _ = write!(out, "{gutter} = {}", style.paint(CYAN, note));
+        out
+    }
+}
+
+/// The offsets at which the lines of a file start, so that the spans of
+/// its declarations are located by a binary search instead of by scanning
+/// the file from its start, as [`SourceSpan::new`] does.
+#[derive(Debug, Default)]
+pub(crate) struct LineIndex {
+    /// Address and length of the indexed text. Spans of any other text,
+    /// e.g. of a block expanded in a command, are located by
+    /// [`SourceSpan::new`].
+    input: (usize, usize),
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub(crate) fn new(input: &str) -> Self {
+        let lines = input.match_indices('\n').map(|(offset, _)| offset + 1);
+        Self {
+            input: (input.as_ptr() as usize, input.len()),
+            starts: std::iter::once(0).chain(lines).collect(),
+        }
+    }
+
+    /// Like [`SourceSpan::new`], in time logarithmic in the size of the
+    /// file when `span` is in the indexed text.
+    pub(crate) fn span(&self, file: &str, span: Span<'_>) -> SourceSpan {
+        let input = span.get_input();
+        if (input.as_ptr() as usize, input.len()) != self.input {
+            return SourceSpan::new(file, span);
+        }
+        let (line, column) = self.line_col(input, span.start());
+        let (end_line, end_column) = self.line_col(input, span.end());
+        let end = self.starts.get(line).copied().unwrap_or(input.len());
+        let line_text = input[self.starts[line - 1]..end].trim_end_matches(['\r', '\n']);
+        SourceSpan {
+            file: file.into(),
+            line,
+            column,
+            end_line,
+            end_column,
+            line_text: line_text.into(),
+        }
+    }
+
+    /// The 1-based line and column of the byte offset `pos`, counted like
+    /// [`pest::Position::line_col`].
+    fn line_col(&self, input: &str, pos: usize) -> (usize, usize) {
+        let line = self.starts.partition_point(|&start| start <= pos);
+        let column = input[self.starts[line - 1]..pos].chars().count() + 1;
+        (line, column)
+    }
+}
+
+/// A fragment of a declaration, e.g. a key or a command variant of a
+/// shorthand, along with the byte range it is written at in its file. Kept
+/// through the expansion of shorthands so that an error about one variant
+/// points at the element it comes from rather than at the whole group.
+///
+/// The file is the one whose text the fragment was read from, named in
+/// errors by [`crate::parse::in_file`] like any other error of the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Spanned<T> {
+    pub(crate) value: T,
+    pub(crate) range: Range<usize>,
+}
+
+impl<T> Spanned<T> {
+    pub(crate) fn new(value: T, span: Span<'_>) -> Self {
+        Self {
+            value,
+            range: span.start()..span.end(),
+        }
+    }
+
+    pub(crate) fn map<U>(self, f: impl FnOnce(T) -> U) -> Spanned<U> {
+        Spanned {
+            value: f(self.value),
+            range: self.range,
+        }
+    }
+
+    /// A grammar error on the fragment, `input` being the text of the file
+    /// it was read from.
+    pub(crate) fn error(&self, message: String, input: &str) -> ParseError {
+        // Safety: the range was taken from a span of `input`.
+        let span = Span::new(input, self.range.start, self.range.end).unwrap();
+        spanned_error(message, span)
+    }
+}
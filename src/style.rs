@@ -0,0 +1,281 @@
+use std::borrow::Cow;
+
+use itertools::Itertools;
+
+use crate::{evdev_mappings, Definition, Key, KeyAttribute, Modifier, ModifierInfo, Trigger};
+
+/// How [`Definition::display_with`] spells out a chord, e.g. to show
+/// `Strg` instead of `control` in a cheat sheet.
+pub trait ChordStyle {
+    fn modifier(&self, modifier: Modifier) -> Cow<'_, str>;
+
+    /// Renders the key of a chord along with its attributes. Never called
+    /// for modifier-only chords, see [`ChordStyle::attributed`].
+    fn key(&self, key: &Key) -> Cow<'_, str>;
+
+    fn separator(&self) -> &str;
+
+    /// Separates held keys from each other and from the key that fires.
+    /// Defaults to the config syntax, `&`.
+    fn held_separator(&self) -> &str {
+        "&"
+    }
+
+    /// Marks `name` with `attribute`. Defaults to the config syntax, e.g.
+    /// `@super!`. Modifier-only chords mark their last modifier with it,
+    /// and modifiers with attributes of their own are marked with those,
+    /// e.g. `@super + tab`.
+    fn attributed(&self, attribute: KeyAttribute, name: &str) -> String {
+        let mut out = String::new();
+        if attribute.contains(KeyAttribute::Send) {
+            out.push('~');
+        }
+        if attribute.contains(KeyAttribute::OnRelease) {
+            out.push('@');
+        }
+        out.push_str(name);
+        if attribute.contains(KeyAttribute::Solo) {
+            out.push('!');
+        }
+        if attribute.contains(KeyAttribute::Passthrough) {
+            out.push_str(" passthrough");
+        }
+        out
+    }
+}
+
+/// The names used in configs, e.g. `super+shift+enter`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultStyle;
+
+/// Symbols as printed on Apple keyboards, e.g. `⌘⇧↩`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MacStyle;
+
+/// Single letter modifiers in the emacs tradition, e.g. `s-S-enter`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactStyle;
+
+/// Config syntax as a user would write it, e.g. `super + shift + return`,
+/// see [`Definition::to_config_string`].
+struct ConfigStyle;
+
+/// The config name of the trigger of `key`, without attributes.
+pub(crate) fn key_name(key: &Key) -> Cow<'_, str> {
+    match &key.trigger {
+        Trigger::Key(key) => evdev_mappings::key_name(*key)
+            .map(Cow::Borrowed)
+            .unwrap_or_else(|| evdev_mappings::key_code_name(*key).into()),
+        Trigger::Virtual(name) => format!("virtual:{name}").into(),
+        Trigger::ModifiersOnly => "".into(),
+        Trigger::AnyKey => "any_key".into(),
+        Trigger::Scroll(direction) => direction.name().into(),
+        Trigger::Switch { switch, state } => format!("switch:{}", switch.event_name(*state)).into(),
+    }
+}
+
+impl ChordStyle for DefaultStyle {
+    fn modifier(&self, modifier: Modifier) -> Cow<'_, str> {
+        ModifierInfo::of(modifier).canonical.into()
+    }
+
+    fn key(&self, key: &Key) -> Cow<'_, str> {
+        self.attributed(key.attribute, &key_name(key)).into()
+    }
+
+    fn separator(&self) -> &str {
+        "+"
+    }
+}
+
+impl ChordStyle for ConfigStyle {
+    fn modifier(&self, modifier: Modifier) -> Cow<'_, str> {
+        DefaultStyle.modifier(modifier).into_owned().into()
+    }
+
+    fn key(&self, key: &Key) -> Cow<'_, str> {
+        DefaultStyle.key(key).into_owned().into()
+    }
+
+    fn separator(&self) -> &str {
+        " + "
+    }
+
+    fn held_separator(&self) -> &str {
+        " & "
+    }
+}
+
+impl ChordStyle for MacStyle {
+    fn modifier(&self, modifier: Modifier) -> Cow<'_, str> {
+        match modifier {
+            Modifier::Super => "⌘",
+            Modifier::Alt => "⌥",
+            Modifier::Altgr => "AltGr",
+            Modifier::Control => "⌃",
+            Modifier::Shift => "⇧",
+            Modifier::LeftAlt => "L⌥",
+            Modifier::RightAlt => "R⌥",
+            Modifier::LeftSuper => "L⌘",
+            Modifier::RightSuper => "R⌘",
+            Modifier::LeftControl => "L⌃",
+            Modifier::RightControl => "R⌃",
+            Modifier::LeftShift => "L⇧",
+            Modifier::RightShift => "R⇧",
+            Modifier::Any => "*",
+            Modifier::Omission => "",
+        }
+        .into()
+    }
+
+    fn key(&self, key: &Key) -> Cow<'_, str> {
+        let name = key_name(key);
+        let symbol = match name.as_ref() {
+            "return" | "enter" => "↩",
+            "escape" => "⎋",
+            "backspace" => "⌫",
+            "delete" => "⌦",
+            "tab" => "⇥",
+            "capslock" => "⇪",
+            "space" => "Space",
+            "left" => "←",
+            "right" => "→",
+            "up" => "↑",
+            "down" => "↓",
+            "home" => "↖",
+            "end" => "↘",
+            "pageup" => "⇞",
+            "pagedown" => "⇟",
+            "comma" => ",",
+            "dot" => ".",
+            "minus" => "-",
+            "equal" => "=",
+            "slash" => "/",
+            "backslash" => "\\",
+            "semicolon" => ";",
+            "apostrophe" => "'",
+            "grave" => "`",
+            "leftbrace" => "[",
+            "rightbrace" => "]",
+            name if matches!(key.trigger, Trigger::Key(_)) => {
+                return self.attributed(key.attribute, &name.to_uppercase()).into()
+            }
+            name => name,
+        };
+        self.attributed(key.attribute, symbol).into()
+    }
+
+    fn separator(&self) -> &str {
+        ""
+    }
+}
+
+impl ChordStyle for CompactStyle {
+    fn modifier(&self, modifier: Modifier) -> Cow<'_, str> {
+        match modifier {
+            Modifier::Super => "s",
+            Modifier::Alt => "M",
+            Modifier::Altgr => "G",
+            Modifier::Control => "C",
+            Modifier::Shift => "S",
+            Modifier::LeftAlt => "lM",
+            Modifier::RightAlt => "rM",
+            Modifier::LeftSuper => "ls",
+            Modifier::RightSuper => "rs",
+            Modifier::LeftControl => "lC",
+            Modifier::RightControl => "rC",
+            Modifier::LeftShift => "lS",
+            Modifier::RightShift => "rS",
+            Modifier::Any => "*",
+            Modifier::Omission => "",
+        }
+        .into()
+    }
+
+    fn key(&self, key: &Key) -> Cow<'_, str> {
+        self.attributed(key.attribute, &key_name(key)).into()
+    }
+
+    fn separator(&self) -> &str {
+        "-"
+    }
+}
+
+impl Definition {
+    /// Renders the chord in `style`, e.g. `super+shift+a` with
+    /// [`DefaultStyle`] or `⌘⇧A` with [`MacStyle`].
+    pub fn display_with(&self, style: &dyn ChordStyle) -> String {
+        let mut parts = self
+            .modifiers()
+            .iter()
+            .map(|modifier| self.modifier_with(style, *modifier))
+            .collect::<Vec<_>>();
+        if self.key().trigger == Trigger::ModifiersOnly {
+            if let Some(last) = parts.last_mut() {
+                *last = style.attributed(self.key().attribute, last);
+            }
+        } else {
+            parts.push(self.keys_with(style, style.key(self.key()).into_owned()));
+        }
+        parts.join(style.separator())
+    }
+
+    /// `modifier` in `style`, along with its attributes.
+    fn modifier_with(&self, style: &dyn ChordStyle, modifier: Modifier) -> String {
+        let name = style.modifier(modifier);
+        match self.modifier_attribute(modifier) {
+            KeyAttribute::None => name.into_owned(),
+            attribute => style.attributed(attribute, &name),
+        }
+    }
+
+    /// The held keys in `style`, if any, followed by the key that fires.
+    fn keys_with(&self, style: &dyn ChordStyle, key: String) -> String {
+        self.held_keys()
+            .iter()
+            .map(|held| style.key(&Key::from(*held)).into_owned())
+            .chain([key])
+            .join(style.held_separator())
+    }
+
+    /// Renders the chord the way it is written in a config, e.g.
+    /// `super + shift + @return`. Parsing the result gives back an equal
+    /// definition. Shift along with a key that has a shifted symbol is
+    /// written as the symbol, e.g. `super + exclam` for Super, Shift and 1,
+    /// unless Shift has attributes of its own.
+    /// This is also how definitions are displayed.
+    ///
+    /// ```
+    /// use sweet::{Definition, KeyAttribute, Modifier};
+    ///
+    /// let definition = Definition::new(evdev::Key::KEY_DOT)
+    ///     .with_attribute(KeyAttribute::OnRelease)
+    ///     .with_modifiers(&[Modifier::Super]);
+    /// assert_eq!(definition.to_config_string(), "super + @period");
+    /// assert_eq!(definition.to_config_string().parse::<Definition>()?, definition);
+    /// # Ok::<(), sweet::ParseError>(())
+    /// ```
+    pub fn to_config_string(&self) -> String {
+        let symbol = match self.key().trigger {
+            Trigger::Key(key)
+                if self.modifiers().contains(&Modifier::Shift)
+                    && self.modifier_attribute(Modifier::Shift) == KeyAttribute::None =>
+            {
+                evdev_mappings::shifted_name_of(key)
+            }
+            _ => None,
+        };
+        let Some(symbol) = symbol else {
+            return self.display_with(&ConfigStyle);
+        };
+        let mut parts = self
+            .modifiers()
+            .iter()
+            .filter(|modifier| **modifier != Modifier::Shift)
+            .map(|modifier| self.modifier_with(&ConfigStyle, *modifier))
+            .collect::<Vec<_>>();
+        let key = ConfigStyle.attributed(self.key().attribute, symbol);
+        parts.push(self.keys_with(&ConfigStyle, key));
+        parts.join(ConfigStyle.separator())
+    }
+}
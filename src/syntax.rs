@@ -0,0 +1,464 @@
+//! A lossless syntax tree of a config, built from the same parse as the
+//! bindings, for editors and tools rewriting configs. Every byte of the
+//! input belongs to exactly one leaf, including comments, whitespace and
+//! punctuation, so concatenating the leaves gives back the input.
+
+use std::{fmt, ops::Range, sync::Arc};
+
+use pest::iterators::Pair;
+
+use crate::{parse::parse_main, ParseError, Rule};
+
+/// What a [`SyntaxNode`] is. Nodes mirror the meaningful rules of the
+/// grammar, and the text between them is split into the token kinds at
+/// the end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    /// The whole config.
+    Root,
+    Binding,
+    /// `ignore` followed by a chord, or by `mode` and the name of a mode.
+    Unbind,
+    /// `replay` followed by a chord, in a mode.
+    Replay,
+    /// `mode name` ... `endmode`.
+    Mode,
+    ModeName,
+    /// The `oneoff` flag of a mode.
+    Oneoff,
+    /// The `swallow` flag of a mode.
+    Swallow,
+    /// `set name value`.
+    Setting,
+    SettingName,
+    SettingValue,
+    /// `define name = value`.
+    Define,
+    VariableName,
+    VariableValue,
+    /// `$name` or `${name}` in a chord.
+    Variable,
+    /// `block name` ... `endblock`.
+    Block,
+    BlockName,
+    /// A line of a block.
+    BlockLine,
+    /// `&name` in a command.
+    BlockReference,
+    /// `include path`.
+    Include,
+    IncludePath,
+    /// `[laptop, desktop]` before a declaration.
+    ProfileTag,
+    ProfileName,
+    /// `[when=...]` before a binding.
+    ScheduleTag,
+    /// The expression of a schedule tag.
+    Schedule,
+    /// `[device=...]` before a binding or a `set` statement.
+    DeviceTag,
+    DeviceName,
+    /// `[set name=value]` before a binding.
+    SettingTag,
+    Modifier,
+    /// `{super, alt}` or `{_, shift +}` in a chord.
+    ModifierShorthand,
+    /// The `_` of a modifier shorthand.
+    Omission,
+    /// A key along with its attributes, alone or in a shorthand.
+    Key,
+    /// The name of a key, e.g. `a`, `Return` or `keycode(148)`.
+    KeyName,
+    /// The `~` attribute.
+    Send,
+    /// The `@` attribute.
+    OnRelease,
+    /// The `!` marker of a modifier-only chord.
+    Solo,
+    /// The `passthrough` keyword after a key.
+    Passthrough,
+    /// `{a, b}` in a chord.
+    KeyShorthand,
+    /// `a-z` in a key shorthand.
+    KeyRange,
+    /// The `:2` of a key or command range keeping every other value.
+    RangeStep,
+    /// A chord of modifiers alone, e.g. `@super!`.
+    ModifierOnly,
+    /// `virtual:name` or `virtual:{a, b}`.
+    VirtualKey,
+    VirtualShorthand,
+    VirtualName,
+    /// `switch:name` or `switch:{a, b}`.
+    SwitchEvent,
+    SwitchShorthand,
+    SwitchName,
+    /// Every line run by a binding, or the `=` and the line after it in
+    /// a single-line binding.
+    Command,
+    /// Command text outside of shorthands.
+    CommandText,
+    /// `{a, b}` in a command.
+    CommandShorthand,
+    /// A variant of a command shorthand.
+    CommandVariant,
+    /// `1-9` in a command shorthand.
+    CommandRange,
+    /// A bound of a command range.
+    CommandRangeBound,
+    /// `&&` between the parts of a command.
+    CommandAnd,
+    /// The newline and indent starting another command line.
+    CommandSeparator,
+    /// `@enter name`.
+    EnterMode,
+    /// `@escape`.
+    EscapeMode,
+    /// A trailing `\` with nothing to continue.
+    DanglingContinuation,
+    /// Spaces, tabs and line continuations.
+    Whitespace,
+    Newline,
+    /// A comment from `#` to the end of the line.
+    Comment,
+    /// A word of the syntax, e.g. `mode`, `include` or `endblock`.
+    Keyword,
+    /// A character of the syntax, e.g. `+`, `{` or `,`.
+    Punctuation,
+    /// Text of a command variant between block references.
+    Text,
+}
+
+impl SyntaxKind {
+    /// Whether nodes of this kind carry no meaning, i.e. whitespace,
+    /// newlines and comments.
+    pub fn is_trivia(self) -> bool {
+        matches!(self, Self::Whitespace | Self::Newline | Self::Comment)
+    }
+
+    /// The kind of the nodes of `rule` under a node of kind `parent`, or
+    /// `None` for rules whose children belong to the parent.
+    fn of(rule: Rule, parent: SyntaxKind) -> Option<Self> {
+        Some(match rule {
+            Rule::main => Self::Root,
+            Rule::binding => Self::Binding,
+            Rule::unbind | Rule::unbind_mode => Self::Unbind,
+            Rule::replay => Self::Replay,
+            Rule::mode => Self::Mode,
+            Rule::modename => Self::ModeName,
+            Rule::oneoff => Self::Oneoff,
+            Rule::swallow => Self::Swallow,
+            Rule::setting => Self::Setting,
+            Rule::setting_name => Self::SettingName,
+            Rule::setting_value | Rule::setting_tag_value => Self::SettingValue,
+            Rule::define => Self::Define,
+            Rule::variable_name => Self::VariableName,
+            Rule::variable_value => Self::VariableValue,
+            Rule::variable => Self::Variable,
+            Rule::block => Self::Block,
+            Rule::block_name => Self::BlockName,
+            Rule::block_line => Self::BlockLine,
+            Rule::block_reference => Self::BlockReference,
+            Rule::import => Self::Include,
+            Rule::import_file => Self::IncludePath,
+            Rule::profile_tag => Self::ProfileTag,
+            Rule::profile_name => Self::ProfileName,
+            Rule::schedule_tag => Self::ScheduleTag,
+            Rule::schedule_expression => Self::Schedule,
+            Rule::device_tag => Self::DeviceTag,
+            Rule::device_name => Self::DeviceName,
+            Rule::setting_tag => Self::SettingTag,
+            Rule::modifier => Self::Modifier,
+            Rule::modifier_shorthand | Rule::modifier_omit_shorthand => Self::ModifierShorthand,
+            Rule::omission => Self::Omission,
+            Rule::key_normal | Rule::key_in_shorthand | Rule::any_key => Self::Key,
+            Rule::key_base | Rule::key_escape_invalid => Self::KeyName,
+            // Escaped shorthand characters are text in commands.
+            Rule::shorthand_allow if parent == Self::Key => Self::KeyName,
+            Rule::send => Self::Send,
+            Rule::on_release => Self::OnRelease,
+            Rule::solo => Self::Solo,
+            Rule::passthrough => Self::Passthrough,
+            Rule::shorthand => Self::KeyShorthand,
+            Rule::key_range => Self::KeyRange,
+            Rule::range_step => Self::RangeStep,
+            Rule::modifier_only => Self::ModifierOnly,
+            Rule::virtual_key => Self::VirtualKey,
+            Rule::virtual_shorthand => Self::VirtualShorthand,
+            Rule::virtual_name => Self::VirtualName,
+            Rule::switch_event => Self::SwitchEvent,
+            Rule::switch_shorthand => Self::SwitchShorthand,
+            Rule::switch_name => Self::SwitchName,
+            Rule::command | Rule::command_inline => Self::Command,
+            Rule::command_standalone => Self::CommandText,
+            Rule::command_shorthand | Rule::nested_shorthand => Self::CommandShorthand,
+            Rule::command_component | Rule::nested_component => Self::CommandVariant,
+            Rule::range => Self::CommandRange,
+            Rule::visible_composite => Self::CommandRangeBound,
+            Rule::command_double_ampersand => Self::CommandAnd,
+            Rule::command_separator => Self::CommandSeparator,
+            Rule::enter_mode => Self::EnterMode,
+            Rule::escape_mode => Self::EscapeMode,
+            Rule::dangling_continuation => Self::DanglingContinuation,
+            _ => return None,
+        })
+    }
+
+    /// Whether nodes of this kind are leaves even though their rule has
+    /// inner rules, e.g. `keycode(148)`.
+    fn is_opaque(self) -> bool {
+        matches!(self, Self::KeyName | Self::CommandRangeBound)
+    }
+}
+
+struct NodeData {
+    kind: SyntaxKind,
+    range: Range<usize>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// The end of the descendants of the node, nodes being stored in
+    /// preorder.
+    end: usize,
+}
+
+struct Tree {
+    input: String,
+    nodes: Vec<NodeData>,
+}
+
+/// A node of the tree returned by [`syntax_tree`], cheap to clone. Ranges
+/// are byte offsets into the input.
+#[derive(Clone)]
+pub struct SyntaxNode {
+    tree: Arc<Tree>,
+    index: usize,
+}
+
+impl SyntaxNode {
+    fn data(&self) -> &NodeData {
+        &self.tree.nodes[self.index]
+    }
+
+    fn at(&self, index: usize) -> Self {
+        Self {
+            tree: self.tree.clone(),
+            index,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.data().kind
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.data().range.clone()
+    }
+
+    /// The text of the input the node covers.
+    pub fn text(&self) -> &str {
+        &self.tree.input[self.range()]
+    }
+
+    pub fn parent(&self) -> Option<Self> {
+        self.data().parent.map(|index| self.at(index))
+    }
+
+    /// The children of the node in source order. Their ranges follow each
+    /// other and cover the node exactly.
+    pub fn children(&self) -> impl Iterator<Item = Self> + '_ {
+        self.data().children.iter().map(|&index| self.at(index))
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.data().children.is_empty()
+    }
+
+    /// The parent of the node, its parent and so on up to the root.
+    pub fn ancestors(&self) -> impl Iterator<Item = Self> {
+        std::iter::successors(self.parent(), Self::parent)
+    }
+
+    /// The node followed by its descendants, each before its children.
+    pub fn preorder(&self) -> impl Iterator<Item = Self> + '_ {
+        (self.index..self.data().end).map(|index| self.at(index))
+    }
+
+    /// The leaves under the node in source order, their texts concatenate
+    /// to the text of the node.
+    pub fn leaves(&self) -> impl Iterator<Item = Self> + '_ {
+        self.preorder().filter(Self::is_leaf)
+    }
+
+    /// The deepest node under this one covering the byte at `offset`, e.g.
+    /// for hovers. `None` if the node does not cover it.
+    pub fn node_at_offset(&self, offset: usize) -> Option<Self> {
+        if !self.range().contains(&offset) {
+            return None;
+        }
+        let mut node = self.clone();
+        loop {
+            let child = node
+                .children()
+                .find(|child| child.range().contains(&offset));
+            match child {
+                Some(child) => node = child,
+                None => return Some(node),
+            }
+        }
+    }
+}
+
+impl PartialEq for SyntaxNode {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.tree, &other.tree) && self.index == other.index
+    }
+}
+
+impl Eq for SyntaxNode {}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let range = self.range();
+        write!(f, "{:?}@{}..{}", self.kind(), range.start, range.end)
+    }
+}
+
+/// Parses `input` into a lossless syntax tree and returns its root. The
+/// tree only reflects the grammar: unknown keys, undefined variables and
+/// the like are not reported, and includes are not read.
+///
+/// ```
+/// use sweet::SyntaxKind;
+///
+/// let input = "super + a # browser\n    firefox";
+/// let root = sweet::syntax_tree(input)?;
+/// let leaves: String = root.leaves().map(|leaf| leaf.text().to_string()).collect();
+/// assert_eq!(leaves, input);
+/// let hovered = root.node_at_offset(8).unwrap();
+/// assert_eq!(hovered.kind(), SyntaxKind::KeyName);
+/// assert_eq!(hovered.parent().unwrap().kind(), SyntaxKind::Key);
+/// # Ok::<(), sweet::ParseError>(())
+/// ```
+pub fn syntax_tree(input: &str) -> Result<SyntaxNode, ParseError> {
+    let main = parse_main(input, "<anonymous>")?;
+    let mut nodes = vec![];
+    build(&mut nodes, input, main, SyntaxKind::Root, None);
+    Ok(SyntaxNode {
+        tree: Arc::new(Tree {
+            input: input.to_string(),
+            nodes,
+        }),
+        index: 0,
+    })
+}
+
+fn push(nodes: &mut Vec<NodeData>, kind: SyntaxKind, range: Range<usize>, parent: Option<usize>) {
+    let index = nodes.len();
+    if let Some(parent) = parent {
+        nodes[parent].children.push(index);
+    }
+    nodes.push(NodeData {
+        kind,
+        range,
+        parent,
+        children: vec![],
+        end: index + 1,
+    });
+}
+
+/// Adds the node of `pair` and its descendants, the text between the
+/// children becoming tokens.
+fn build(
+    nodes: &mut Vec<NodeData>,
+    input: &str,
+    pair: Pair<'_, Rule>,
+    kind: SyntaxKind,
+    parent: Option<usize>,
+) {
+    let span = pair.as_span();
+    let index = nodes.len();
+    push(nodes, kind, span.start()..span.end(), parent);
+    let children = if kind.is_opaque() {
+        vec![]
+    } else {
+        meaningful(pair, kind)
+    };
+    if !children.is_empty() {
+        let mut at = span.start();
+        for (child, child_kind) in children {
+            let start = child.as_span().start();
+            let end = child.as_span().end();
+            tokens(nodes, input, at..start, kind, index);
+            build(nodes, input, child, child_kind, Some(index));
+            at = end;
+        }
+        tokens(nodes, input, at..span.end(), kind, index);
+    }
+    nodes[index].end = nodes.len();
+}
+
+/// The inner pairs of `pair` making nodes, looking through the ones that
+/// do not.
+fn meaningful(pair: Pair<'_, Rule>, kind: SyntaxKind) -> Vec<(Pair<'_, Rule>, SyntaxKind)> {
+    let mut children = vec![];
+    for inner in pair.into_inner() {
+        match SyntaxKind::of(inner.as_rule(), kind) {
+            Some(inner_kind) => children.push((inner, inner_kind)),
+            None => children.extend(meaningful(inner, kind)),
+        }
+    }
+    children
+}
+
+/// Splits the text between two children of a node into tokens.
+fn tokens(
+    nodes: &mut Vec<NodeData>,
+    input: &str,
+    range: Range<usize>,
+    parent_kind: SyntaxKind,
+    parent: usize,
+) {
+    if range.is_empty() {
+        return;
+    }
+    if parent_kind == SyntaxKind::CommandVariant {
+        push(nodes, SyntaxKind::Text, range, Some(parent));
+        return;
+    }
+    let text = &input[range.clone()];
+    let mut at = 0;
+    while at < text.len() {
+        let rest = &text[at..];
+        let (kind, len) = if rest.starts_with('#') {
+            (
+                SyntaxKind::Comment,
+                rest.find(['\r', '\n']).unwrap_or(rest.len()),
+            )
+        } else if rest.starts_with("\r\n") {
+            (SyntaxKind::Newline, 2)
+        } else if rest.starts_with('\n') {
+            (SyntaxKind::Newline, 1)
+        } else if rest.starts_with([' ', '\t']) || rest.starts_with("\\\n") {
+            let mut len = 0;
+            while let Some(space) = ["\\\n", " ", "\t"]
+                .into_iter()
+                .find(|space| rest[len..].starts_with(space))
+            {
+                len += space.len();
+            }
+            (SyntaxKind::Whitespace, len)
+        } else if rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '@') {
+            let len = rest[1..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .map_or(rest.len(), |len| len + 1);
+            (SyntaxKind::Keyword, len)
+        } else {
+            // Safety: `rest` is not empty.
+            let c = rest.chars().next().unwrap();
+            (SyntaxKind::Punctuation, c.len_utf8())
+        };
+        let start = range.start + at;
+        push(nodes, kind, start..start + len, Some(parent));
+        at += len;
+    }
+}
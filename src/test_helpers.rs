@@ -0,0 +1,130 @@
+//! Constructors and assertions for tests written against sweet, enabled by
+//! the `test-helpers` feature.
+//!
+//! ```
+//! use sweet::{assert_parse_error, assert_parses_to, binding};
+//!
+//! assert_parses_to!("super + {a, b}\n    {firefox, kitty}", [
+//!     binding!("super + a" => "firefox"),
+//!     binding!("super + b" => "kitty"),
+//! ]);
+//! assert_parse_error!("super + \\a\n    firefox", span: (1, 9)..(1, 11), contains: "unnecessary escape");
+//! ```
+
+use pest::error::LineColLocation;
+
+use crate::{Binding, ParseError, ParserInput, SwhkdParser};
+
+/// Builds a [`Binding`] from a chord in config syntax and a command.
+#[macro_export]
+macro_rules! binding {
+    ($chord:expr => $command:expr) => {
+        $crate::Binding::running($command).on($chord
+            .parse::<$crate::Definition>()
+            .unwrap_or_else(|err| panic!("invalid chord {:?}: {}", $chord, err)))
+    };
+}
+
+/// Asserts that a config parses to exactly the given top-level bindings, in order.
+#[macro_export]
+macro_rules! assert_parses_to {
+    ($config:expr, [$($binding:expr),* $(,)?]) => {
+        $crate::test_helpers::assert_parses_to($config, vec![$($binding),*])
+    };
+}
+
+/// Asserts that a config fails to parse with a grammar error at a position
+/// (`at: (line, col)`) or span (`span: (line, col)..(line, col)`), optionally
+/// with a message containing the given text.
+#[macro_export]
+macro_rules! assert_parse_error {
+    ($config:expr, at: ($line:expr, $col:expr) $(,)?) => {
+        $crate::test_helpers::assert_parse_error_at($config, ($line, $col), None)
+    };
+    ($config:expr, at: ($line:expr, $col:expr), contains: $needle:expr $(,)?) => {
+        $crate::test_helpers::assert_parse_error_at($config, ($line, $col), Some($needle))
+    };
+    ($config:expr, span: ($l1:expr, $c1:expr)..($l2:expr, $c2:expr) $(,)?) => {
+        $crate::test_helpers::assert_parse_error_spanning($config, ($l1, $c1), ($l2, $c2), None)
+    };
+    ($config:expr, span: ($l1:expr, $c1:expr)..($l2:expr, $c2:expr), contains: $needle:expr $(,)?) => {
+        $crate::test_helpers::assert_parse_error_spanning(
+            $config,
+            ($l1, $c1),
+            ($l2, $c2),
+            Some($needle),
+        )
+    };
+}
+
+/// See [`assert_parses_to!`].
+#[track_caller]
+pub fn assert_parses_to(config: &str, expected: Vec<Binding>) {
+    let parsed = match SwhkdParser::from(ParserInput::Raw(config)) {
+        Ok(parsed) => parsed.bindings,
+        Err(err) => panic!("expected config to parse, got:\n{err}"),
+    };
+
+    let missing: Vec<_> = expected.iter().filter(|b| !parsed.contains(b)).collect();
+    let unexpected: Vec<_> = parsed.iter().filter(|b| !expected.contains(b)).collect();
+    if !missing.is_empty() || !unexpected.is_empty() {
+        let mut report = String::from("parsed bindings differ from the expected ones");
+        for (title, bindings) in [("missing", missing), ("unexpected", unexpected)] {
+            if !bindings.is_empty() {
+                report.push_str(&format!("\n{title}:"));
+                for binding in bindings {
+                    report.push_str(&format!("\n  {binding}"));
+                }
+            }
+        }
+        panic!("{report}");
+    }
+    if parsed != expected {
+        let mut report = String::from("parsed bindings are in a different order\nexpected:");
+        for binding in &expected {
+            report.push_str(&format!("\n  {binding}"));
+        }
+        report.push_str("\nparsed:");
+        for binding in &parsed {
+            report.push_str(&format!("\n  {binding}"));
+        }
+        panic!("{report}");
+    }
+}
+
+/// See [`assert_parse_error!`].
+#[track_caller]
+pub fn assert_parse_error_at(config: &str, pos: (usize, usize), contains: Option<&str>) {
+    assert_grammar_error(config, LineColLocation::Pos(pos), contains)
+}
+
+/// See [`assert_parse_error!`].
+#[track_caller]
+pub fn assert_parse_error_spanning(
+    config: &str,
+    start: (usize, usize),
+    end: (usize, usize),
+    contains: Option<&str>,
+) {
+    assert_grammar_error(config, LineColLocation::Span(start, end), contains)
+}
+
+#[track_caller]
+fn assert_grammar_error(config: &str, location: LineColLocation, contains: Option<&str>) {
+    let err = match SwhkdParser::from(ParserInput::Raw(config)) {
+        Err(ParseError::Grammar(err)) => err,
+        Err(err) => panic!("expected a grammar error, got:\n{err}"),
+        Ok(_) => panic!("expected a grammar error, but the config parsed"),
+    };
+    assert_eq!(
+        err.line_col, location,
+        "error at the wrong location:\n{err}"
+    );
+    if let Some(needle) = contains {
+        let rendered = err.to_string();
+        assert!(
+            rendered.contains(needle),
+            "expected the error to contain {needle:?}, got:\n{rendered}"
+        );
+    }
+}
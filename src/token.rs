@@ -9,50 +9,415 @@ bitflags::bitflags! {
         const Send = 0b00000001;
         const OnRelease = 0b00000010;
         const Both = Self::Send.bits() | Self::OnRelease.bits();
+        /// Only fire if no other key was pressed while the modifiers of a
+        /// modifier-only chord were held, written as a trailing `!`.
+        const Solo = 0b00000100;
+        /// Leave the key to the focused window, written as a trailing
+        /// `passthrough` keyword: the daemon does not grab it, and runs the
+        /// command as well.
+        const Passthrough = 0b00001000;
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ModifierRepr(pub String);
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Modifier {
     Super,
+    /// Modifiers of one side, e.g. `lsuper`, follow the one matching
+    /// either side.
+    LeftSuper,
+    RightSuper,
     Alt,
+    LeftAlt,
+    RightAlt,
     Altgr,
     Control,
+    LeftControl,
+    RightControl,
     Shift,
+    LeftShift,
+    RightShift,
     Any,
     Omission,
 }
 
-impl From<ModifierRepr> for Modifier {
-    fn from(value: ModifierRepr) -> Self {
-        match value.0.to_lowercase().as_str() {
-            "ctrl" => Modifier::Control,
-            "control" => Modifier::Control,
-            "super" | "mod4" | "meta" => Modifier::Super,
-            "alt" => Modifier::Alt,
-            "mod1" => Modifier::Alt,
-            "altgr" => Modifier::Altgr,
-            "mod5" => Modifier::Altgr,
-            "shift" => Modifier::Shift,
-            "any" => Modifier::Any,
-            "_" => Modifier::Omission,
-            _ => panic!("{:?} is not a modifier", value),
+impl Modifier {
+    /// The modifier matching either side of `self`, e.g. `Alt` for
+    /// `RightAlt`, `self` for the others.
+    pub fn either_side(self) -> Self {
+        match self {
+            Self::LeftAlt | Self::RightAlt => Self::Alt,
+            Self::LeftSuper | Self::RightSuper => Self::Super,
+            Self::LeftControl | Self::RightControl => Self::Control,
+            Self::LeftShift | Self::RightShift => Self::Shift,
+            modifier => modifier,
         }
     }
+
+    /// Whether the modifier only matches the key of one side, e.g. `lalt`.
+    pub fn is_sided(self) -> bool {
+        self.either_side() != self
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl TryFrom<ModifierRepr> for Modifier {
+    type Error = ParseError;
+
+    fn try_from(value: ModifierRepr) -> Result<Self, Self::Error> {
+        ModifierInfo::lookup(&value.0)
+            .map(|info| info.modifier)
+            .ok_or(ParseError::InvalidModifier(value.0))
+    }
+}
+
+/// Describes a modifier for user interfaces, see [`MODIFIERS`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModifierInfo {
+    pub modifier: Modifier,
+    /// The name the modifier is documented under.
+    pub canonical: &'static str,
+    /// Other accepted spellings. Names are case insensitive.
+    pub aliases: &'static [&'static str],
+    pub description: &'static str,
+    /// Not an actual key, but a placeholder with special meaning in a chord.
+    pub pseudo: bool,
+}
+
+impl ModifierInfo {
+    /// Finds the modifier spelled `name`, either canonically or by an alias.
+    pub fn lookup(name: &str) -> Option<&'static ModifierInfo> {
+        let name = name.to_lowercase();
+        MODIFIERS
+            .iter()
+            .find(|info| info.canonical == name || info.aliases.contains(&name.as_str()))
+    }
+
+    /// The entry of `modifier` in [`MODIFIERS`].
+    pub fn of(modifier: Modifier) -> &'static ModifierInfo {
+        MODIFIERS
+            .iter()
+            .find(|info| info.modifier == modifier)
+            .expect("every modifier is listed in MODIFIERS")
+    }
+}
+
+/// Every modifier the parser accepts. Converting a [`ModifierRepr`] looks
+/// names up in this table.
+pub const MODIFIERS: &[ModifierInfo] = &[
+    ModifierInfo {
+        modifier: Modifier::Super,
+        canonical: "super",
+        aliases: &["mod4", "meta"],
+        description: "The logo key, also known as the Windows key",
+        pseudo: false,
+    },
+    ModifierInfo {
+        modifier: Modifier::LeftSuper,
+        canonical: "lsuper",
+        aliases: &[],
+        description: "Only the left logo key",
+        pseudo: false,
+    },
+    ModifierInfo {
+        modifier: Modifier::RightSuper,
+        canonical: "rsuper",
+        aliases: &[],
+        description: "Only the right logo key",
+        pseudo: false,
+    },
+    ModifierInfo {
+        modifier: Modifier::Alt,
+        canonical: "alt",
+        aliases: &["mod1"],
+        description: "The left Alt key",
+        pseudo: false,
+    },
+    ModifierInfo {
+        modifier: Modifier::LeftAlt,
+        canonical: "lalt",
+        aliases: &[],
+        description: "Only the left Alt key",
+        pseudo: false,
+    },
+    ModifierInfo {
+        modifier: Modifier::RightAlt,
+        canonical: "ralt",
+        aliases: &[],
+        description: "Only the right Alt key",
+        pseudo: false,
+    },
+    ModifierInfo {
+        modifier: Modifier::Altgr,
+        canonical: "altgr",
+        aliases: &["mod5"],
+        description: "The right Alt key, used to type alternative characters",
+        pseudo: false,
+    },
+    ModifierInfo {
+        modifier: Modifier::Control,
+        canonical: "control",
+        aliases: &["ctrl"],
+        description: "Either Control key",
+        pseudo: false,
+    },
+    ModifierInfo {
+        modifier: Modifier::LeftControl,
+        canonical: "lcontrol",
+        aliases: &["lctrl"],
+        description: "Only the left Control key",
+        pseudo: false,
+    },
+    ModifierInfo {
+        modifier: Modifier::RightControl,
+        canonical: "rcontrol",
+        aliases: &["rctrl"],
+        description: "Only the right Control key",
+        pseudo: false,
+    },
+    ModifierInfo {
+        modifier: Modifier::Shift,
+        canonical: "shift",
+        aliases: &[],
+        description: "Either Shift key",
+        pseudo: false,
+    },
+    ModifierInfo {
+        modifier: Modifier::LeftShift,
+        canonical: "lshift",
+        aliases: &[],
+        description: "Only the left Shift key",
+        pseudo: false,
+    },
+    ModifierInfo {
+        modifier: Modifier::RightShift,
+        canonical: "rshift",
+        aliases: &[],
+        description: "Only the right Shift key",
+        pseudo: false,
+    },
+    ModifierInfo {
+        modifier: Modifier::Any,
+        canonical: "any",
+        aliases: &[],
+        description: "Matches the key regardless of the modifiers held",
+        pseudo: true,
+    },
+    ModifierInfo {
+        modifier: Modifier::Omission,
+        canonical: "_",
+        aliases: &[],
+        description: "Leaves out the modifier in a shorthand, e.g. `{_, shift +} a`",
+        pseudo: true,
+    },
+];
+
+/// Describes a key attribute for user interfaces, see [`KEY_ATTRIBUTES`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttributeInfo {
+    pub attribute: KeyAttribute,
+    /// How the attribute is written in a chord.
+    pub written: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// Every attribute a key can be marked with in a chord.
+pub const KEY_ATTRIBUTES: &[AttributeInfo] = &[
+    AttributeInfo {
+        attribute: KeyAttribute::Send,
+        written: "~",
+        name: "send",
+        description: "Also send the key to the focused window instead of swallowing it",
+    },
+    AttributeInfo {
+        attribute: KeyAttribute::OnRelease,
+        written: "@",
+        name: "on release",
+        description: "Run the command when the key is released instead of pressed",
+    },
+    AttributeInfo {
+        attribute: KeyAttribute::Solo,
+        written: "!",
+        name: "solo",
+        description: "Written after a modifier-only chord released with `@`, only run the command if no other key was pressed meanwhile",
+    },
+    AttributeInfo {
+        attribute: KeyAttribute::Passthrough,
+        written: "passthrough",
+        name: "passthrough",
+        description: "Written after the key, run the command without grabbing the key, so that the focused window still receives it",
+    },
+];
+
+/// Which way a scroll wheel turned, see [`Trigger::Scroll`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScrollDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl ScrollDirection {
+    /// The direction named `name` in a config, e.g. `scroll_up`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "scroll_up" => Some(Self::Up),
+            "scroll_down" => Some(Self::Down),
+            "scroll_left" => Some(Self::Left),
+            "scroll_right" => Some(Self::Right),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Up => "scroll_up",
+            Self::Down => "scroll_down",
+            Self::Left => "scroll_left",
+            Self::Right => "scroll_right",
+        }
+    }
+}
+
+/// A switch reported by evdev, see [`Trigger::Switch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SwitchType {
+    /// The laptop lid, on when closed.
+    Lid,
+    TabletMode,
+    /// On while headphones are plugged in.
+    HeadphoneInsert,
+    /// On while docked.
+    Dock,
+}
+
+/// The names of switch events in configs, along with the switch and the
+/// state it changes to.
+const SWITCH_EVENTS: &[(&str, SwitchType, bool)] = &[
+    ("lid_close", SwitchType::Lid, true),
+    ("lid_open", SwitchType::Lid, false),
+    ("tablet_mode_on", SwitchType::TabletMode, true),
+    ("tablet_mode_off", SwitchType::TabletMode, false),
+    ("headphone_insert", SwitchType::HeadphoneInsert, true),
+    ("headphone_remove", SwitchType::HeadphoneInsert, false),
+    ("dock", SwitchType::Dock, true),
+    ("undock", SwitchType::Dock, false),
+];
+
+impl SwitchType {
+    /// The switch and state named `name` in a config, e.g. `lid_close`.
+    pub fn from_event_name(name: &str) -> Option<(Self, bool)> {
+        SWITCH_EVENTS
+            .iter()
+            .find(|(event, ..)| *event == name)
+            .map(|&(_, switch, state)| (switch, state))
+    }
+
+    /// The config name of this switch changing to `state`.
+    pub fn event_name(self, state: bool) -> &'static str {
+        // Safety: the table has both states of every switch.
+        SWITCH_EVENTS
+            .iter()
+            .find(|&&(_, switch, on)| switch == self && on == state)
+            .map(|(event, ..)| *event)
+            .unwrap()
+    }
+
+    /// Every switch event name accepted in configs.
+    pub fn event_names() -> impl Iterator<Item = &'static str> {
+        SWITCH_EVENTS.iter().map(|(event, ..)| *event)
+    }
+
+    pub fn evdev(self) -> evdev::SwitchType {
+        match self {
+            Self::Lid => evdev::SwitchType::SW_LID,
+            Self::TabletMode => evdev::SwitchType::SW_TABLET_MODE,
+            Self::HeadphoneInsert => evdev::SwitchType::SW_HEADPHONE_INSERT,
+            Self::Dock => evdev::SwitchType::SW_DOCK,
+        }
+    }
+}
+
+/// What has to happen for a binding to fire.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Trigger {
+    /// A physical key reported by evdev.
+    Key(#[cfg_attr(feature = "serde", serde(with = "crate::serialize::evdev_key"))] evdev::Key),
+    /// A consumer-defined key fired by name, e.g. over IPC, written as
+    /// `virtual:name` in the config.
+    Virtual(String),
+    /// The modifiers of the definition alone, without any other key,
+    /// e.g. `@super`.
+    ModifiersOnly,
+    /// A scroll wheel turning, reported by evdev as a `REL_WHEEL` or
+    /// `REL_HWHEEL` event rather than a key, e.g. `scroll_up`.
+    Scroll(ScrollDirection),
+    /// A switch changing state, reported by evdev as an `EV_SW` event,
+    /// e.g. `switch:lid_close`.
+    Switch { switch: SwitchType, state: bool },
+    /// Every key, written `any_key`. Only `ignore` statements hold it,
+    /// e.g. `ignore super + any_key`, see [`Definition::unbinds`].
+    ///
+    /// [`Definition::unbinds`]: crate::Definition::unbinds
+    AnyKey,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Key {
-    pub key: evdev::Key,
+    pub trigger: Trigger,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::key_attribute"))]
     pub attribute: KeyAttribute,
 }
 
 impl Key {
+    pub fn modifiers_only(attribute: KeyAttribute) -> Self {
+        Self {
+            trigger: Trigger::ModifiersOnly,
+            attribute,
+        }
+    }
+
     pub fn new(key: evdev::Key, attribute: KeyAttribute) -> Self {
-        Self { key, attribute }
+        Self {
+            trigger: Trigger::Key(key),
+            attribute,
+        }
+    }
+
+    pub fn scroll(direction: ScrollDirection) -> Self {
+        Self {
+            trigger: Trigger::Scroll(direction),
+            attribute: KeyAttribute::None,
+        }
+    }
+
+    pub fn virtual_key<S: AsRef<str>>(name: S) -> Self {
+        Self {
+            trigger: Trigger::Virtual(name.as_ref().to_string()),
+            attribute: KeyAttribute::None,
+        }
+    }
+
+    pub fn switch(switch: SwitchType, state: bool) -> Self {
+        Self {
+            trigger: Trigger::Switch { switch, state },
+            attribute: KeyAttribute::None,
+        }
+    }
+}
+
+/// A key without attributes.
+impl From<evdev::Key> for Key {
+    fn from(key: evdev::Key) -> Self {
+        Self::new(key, KeyAttribute::None)
     }
 }
 
@@ -66,9 +431,13 @@ impl TryFrom<KeyRepr> for Key {
     type Error = ParseError;
 
     fn try_from(value: KeyRepr) -> Result<Self, Self::Error> {
+        if let Some(direction) = ScrollDirection::from_name(&value.key) {
+            return Ok(Self {
+                trigger: Trigger::Scroll(direction),
+                attribute: value.attribute,
+            });
+        }
         let key = evdev_mappings::convert(&value.key)?;
-        let attribute = value.attribute;
-
-        Ok(Self { key, attribute })
+        Ok(Self::new(key, value.attribute))
     }
 }
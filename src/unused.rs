@@ -0,0 +1,237 @@
+//! Finding the variables and blocks that no binding of a config ends up
+//! reading, see [`Warning::UnusedDefinition`].
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Arc,
+};
+
+use crate::{Binding, SourceSpan, SwhkdParser, Warning};
+
+/// What a `define` or `block` statement defines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DefinitionKind {
+    /// `define name = value`, referenced as `$name`.
+    Variable,
+    /// `block name` ... `endblock`, referenced as `&name`.
+    Block,
+}
+
+impl DefinitionKind {
+    /// How a definition of this kind named `name` is referenced, e.g.
+    /// `variable `$mod``.
+    pub(crate) fn describe(self, name: &str) -> String {
+        match self {
+            Self::Variable => format!("variable `${name}`"),
+            Self::Block => format!("block `&{name}`"),
+        }
+    }
+}
+
+/// The names of the variables and blocks a declaration references,
+/// recorded as they are substituted.
+#[derive(Debug, Default)]
+pub(crate) struct References {
+    pub(crate) variables: BTreeSet<String>,
+    pub(crate) blocks: BTreeSet<String>,
+}
+
+/// The definitions visible in a file by kind and name, as indices of
+/// [`DefinitionUses`].
+pub(crate) type VisibleDefinitions = BTreeMap<(DefinitionKind, String), usize>;
+
+#[derive(Debug, Clone)]
+struct Site {
+    kind: DefinitionKind,
+    name: String,
+    span: SourceSpan,
+    /// The later definition of the same file replacing this one.
+    replaced_by: Option<usize>,
+    /// Whether an `ignore` or `replay` statement reads it, which always
+    /// takes effect.
+    read: bool,
+    /// The binding declarations reading it, as indices of
+    /// [`DefinitionUses::declarations`].
+    readers: Vec<usize>,
+}
+
+/// Every `define` and `block` statement of a config along with the
+/// declarations reading them.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DefinitionUses {
+    sites: Vec<Site>,
+    /// The chord and the number of bindings of every binding declaration
+    /// reading a definition.
+    declarations: Vec<(SourceSpan, usize)>,
+    /// See [`VisibleDefinitions`], for the file being parsed.
+    pub(crate) visible: VisibleDefinitions,
+}
+
+impl DefinitionUses {
+    /// Records a definition in a file, where it replaces any earlier one
+    /// of the same kind and name in `defined`.
+    pub(crate) fn define(
+        &mut self,
+        defined: &mut VisibleDefinitions,
+        kind: DefinitionKind,
+        name: String,
+        span: SourceSpan,
+    ) {
+        let index = self.sites.len();
+        if let Some(replaced) = defined.insert((kind, name.clone()), index) {
+            self.sites[replaced].replaced_by = Some(index);
+        }
+        self.sites.push(Site {
+            kind,
+            name,
+            span,
+            replaced_by: None,
+            read: false,
+            readers: vec![],
+        });
+    }
+
+    /// How many definitions and reading declarations are recorded, to take
+    /// the ones recorded afterwards with [`DefinitionUses::since`].
+    pub(crate) fn mark(&self) -> (usize, usize) {
+        (self.sites.len(), self.declarations.len())
+    }
+
+    /// The definitions and declarations recorded since `mark`, along with
+    /// the visible definitions, which must all be recorded since then too.
+    /// Their indices start from 0, see [`DefinitionUses::append`].
+    pub(crate) fn since(&self, (sites, declarations): (usize, usize)) -> Self {
+        Self {
+            sites: self.sites[sites..]
+                .iter()
+                .map(|site| Site {
+                    replaced_by: site.replaced_by.map(|index| index - sites),
+                    readers: site
+                        .readers
+                        .iter()
+                        .map(|reader| reader - declarations)
+                        .collect(),
+                    ..site.clone()
+                })
+                .collect(),
+            declarations: self.declarations[declarations..].to_vec(),
+            visible: self
+                .visible
+                .iter()
+                .map(|(key, index)| (key.clone(), index - sites))
+                .collect(),
+        }
+    }
+
+    /// Records again the definitions and declarations of `uses`, taken
+    /// with [`DefinitionUses::since`], whose visible definitions become
+    /// the visible ones.
+    pub(crate) fn append(&mut self, uses: &DefinitionUses) {
+        let (sites, declarations) = self.mark();
+        self.sites.extend(uses.sites.iter().map(|site| {
+            Site {
+                replaced_by: site.replaced_by.map(|index| index + sites),
+                readers: site
+                    .readers
+                    .iter()
+                    .map(|reader| reader + declarations)
+                    .collect(),
+                ..site.clone()
+            }
+        }));
+        self.declarations.extend_from_slice(&uses.declarations);
+        self.visible = uses
+            .visible
+            .iter()
+            .map(|(key, index)| (key.clone(), index + sites))
+            .collect();
+    }
+
+    /// Records the definitions a declaration read, resolved in the file
+    /// being parsed. `binding` is the chord and the number of bindings of
+    /// a binding declaration, `None` for statements that always take
+    /// effect.
+    pub(crate) fn read(&mut self, references: References, binding: Option<(SourceSpan, usize)>) {
+        let names = references
+            .variables
+            .into_iter()
+            .map(|name| (DefinitionKind::Variable, name))
+            .chain(
+                references
+                    .blocks
+                    .into_iter()
+                    .map(|name| (DefinitionKind::Block, name)),
+            );
+        let sites: Vec<usize> = names
+            .filter_map(|key| self.visible.get(&key).copied())
+            .collect();
+        if sites.is_empty() {
+            return;
+        }
+        let reader = binding.map(|binding| {
+            self.declarations.push(binding);
+            self.declarations.len() - 1
+        });
+        for site in sites {
+            match reader {
+                Some(reader) => self.sites[site].readers.push(reader),
+                None => self.sites[site].read = true,
+            }
+        }
+    }
+}
+
+/// Where a binding was declared, the same for every binding expanded from
+/// one declaration.
+fn declared_at(span: &SourceSpan) -> (Arc<str>, usize, usize) {
+    (span.file.clone(), span.line, span.column)
+}
+
+impl SwhkdParser {
+    /// Warns about definitions replaced by a later one of the same file,
+    /// and about the ones no binding left after merging reads. Bindings of
+    /// inactive profiles count as read, they are only left out for now.
+    pub(crate) fn warn_about_unused_definitions(&mut self, uses: &DefinitionUses) {
+        let mut kept: HashMap<_, usize> = HashMap::new();
+        let mode_bindings = self.modes.iter().flat_map(|mode| &mode.bindings);
+        let bindings = self
+            .bindings
+            .iter()
+            .chain(mode_bindings)
+            .chain(&self.inactive);
+        for span in bindings.filter_map(Binding::span) {
+            *kept.entry(declared_at(span)).or_default() += 1;
+        }
+        for site in &uses.sites {
+            if let Some(replaced_by) = site.replaced_by {
+                self.warnings.push(Warning::ShadowedDefinition {
+                    kind: site.kind,
+                    name: site.name.clone(),
+                    first: site.span.clone(),
+                    second: uses.sites[replaced_by].span.clone(),
+                });
+                continue;
+            }
+            if site.read {
+                continue;
+            }
+            let mut removed = 0;
+            let mut used = false;
+            for &reader in &site.readers {
+                let (span, variants) = &uses.declarations[reader];
+                let kept = kept.get(&declared_at(span)).copied().unwrap_or_default();
+                used |= kept > 0;
+                removed += variants.saturating_sub(kept);
+            }
+            if !used {
+                self.warnings.push(Warning::UnusedDefinition {
+                    kind: site.kind,
+                    name: site.name.clone(),
+                    span: site.span.clone(),
+                    removed,
+                });
+            }
+        }
+    }
+}
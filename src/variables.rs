@@ -0,0 +1,156 @@
+//! Variables declared with `define name = value` and referenced as `$name`
+//! or `${name}` in chords and commands.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use pest::{error::ErrorVariant, iterators::Pair, Span};
+
+use crate::{
+    parse::{run_grammar, spanned_error},
+    ParseError, Rule,
+};
+
+/// Values of variables by name.
+pub(crate) type Variables = BTreeMap<String, String>;
+
+/// The name and value of a `define` statement.
+pub(crate) fn define_parser(pair: Pair<'_, Rule>) -> (String, String) {
+    let mut name = String::new();
+    let mut value = String::new();
+    for component in pair.into_inner() {
+        match component.as_rule() {
+            Rule::variable_name => name = component.as_str().to_string(),
+            Rule::variable_value => value = component.as_str().trim_end().to_string(),
+            _ => {}
+        }
+    }
+    (name, value)
+}
+
+/// The error for a reference to a variable that is not defined.
+pub(crate) fn undefined(variable: &Pair<'_, Rule>) -> ParseError {
+    // Safety: the grammar guarantees a name in every reference.
+    let name = variable.clone().into_inner().next().unwrap();
+    spanned_error(
+        format!(
+            "undefined variable `{}`, define it with `define {} = ...`",
+            variable.as_str(),
+            name.as_str()
+        ),
+        variable.as_span(),
+    )
+}
+
+/// Substitutes the variables referenced in a piece of command text, adding
+/// their names to `used`. References to names that are not defined are
+/// left to the shell, e.g. `$HOME`, and `\$` is a literal dollar sign.
+pub(crate) fn substitute_command(
+    text: &str,
+    variables: &Variables,
+    used: &mut BTreeSet<String>,
+) -> String {
+    if !text.contains('$') {
+        return text.to_string();
+    }
+    let mut substituted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(dollar) = rest.find('$') {
+        let after = &rest[dollar + 1..];
+        if rest[..dollar].ends_with('\\') {
+            substituted.push_str(&rest[..dollar - 1]);
+            substituted.push('$');
+            rest = after;
+            continue;
+        }
+        substituted.push_str(&rest[..dollar]);
+        let (name, remainder) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(close) => (&braced[..close], &braced[close + 1..]),
+                None => ("", after),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+        match variables.get(name) {
+            Some(value) => {
+                used.insert(name.to_string());
+                substituted.push_str(value);
+                rest = remainder;
+            }
+            None => {
+                substituted.push('$');
+                rest = after;
+            }
+        }
+    }
+    substituted.push_str(rest);
+    substituted
+}
+
+/// Runs `f` on the components of a chord, after substituting its
+/// variables and adding their names to `used`. The substituted chord is
+/// not in the config, so errors about it are reported on the chord as
+/// written.
+pub(crate) fn with_chord<R>(
+    components: Vec<Pair<'_, Rule>>,
+    variables: &Variables,
+    used: &mut BTreeSet<String>,
+    f: impl for<'i> FnOnce(Vec<Pair<'i, Rule>>) -> Result<R, ParseError>,
+) -> Result<R, ParseError> {
+    if !components
+        .iter()
+        .any(|component| component.as_rule() == Rule::variable)
+    {
+        return f(components);
+    }
+    // Safety: chords holding a variable have components, and both ends are
+    // boundaries of them.
+    let start = components.first().unwrap().as_span().start();
+    let end = components.last().unwrap().as_span().end();
+    let span = Span::new(components[0].get_input(), start, end).unwrap();
+
+    let mut expanded = String::new();
+    let mut copied = start;
+    for component in components
+        .iter()
+        .filter(|component| component.as_rule() == Rule::variable)
+    {
+        let name = component.clone().into_inner().next().unwrap();
+        let value = variables
+            .get(name.as_str())
+            .ok_or_else(|| undefined(component))?;
+        used.insert(name.as_str().to_string());
+        let reference = component.as_span();
+        expanded.push_str(&span.get_input()[copied..reference.start()]);
+        expanded.push_str(value);
+        copied = reference.end();
+    }
+    expanded.push_str(&span.get_input()[copied..end]);
+
+    let in_chord = |message: String| {
+        spanned_error(
+            format!("`{}` expands to `{expanded}`, {message}", span.as_str()),
+            span,
+        )
+    };
+    let chord = run_grammar(Rule::chord, &expanded)
+        .map_err(|_| in_chord("which is not a chord".to_string()))?
+        .next()
+        .ok_or(ParseError::MainSection)?;
+    f(chord.into_inner().collect()).map_err(|err| in_chord(message(err)))
+}
+
+/// The message of an error, without the snippet of pest errors.
+pub(crate) fn message(err: ParseError) -> String {
+    match err {
+        ParseError::Grammar(err) => match err.variant {
+            ErrorVariant::CustomError { message } => message,
+            variant => variant.message().into_owned(),
+        },
+        err => err.to_string(),
+    }
+}
@@ -0,0 +1,293 @@
+use std::fmt::Display;
+
+use thiserror::Error;
+
+use crate::{
+    CommandLint, DefinitionKind, Diagnostic, Label, Modifier, ModifierInfo, RenderStyle, Severity,
+    SourceSpan,
+};
+
+/// Something suspicious about a config that still parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Warning {
+    /// Declarations are tagged with a profile that is not active, so they
+    /// were left out.
+    InactiveProfile(String),
+    /// A profile was activated but nothing in the config is tagged with it.
+    UnknownProfile(String),
+    /// A mode declares `replay` keys without being a swallow mode, so
+    /// every key reaches the client anyway.
+    ReplayWithoutSwallow(String),
+    /// An omission shorthand has an `any` variant, e.g. `{_, any +} a`.
+    /// An exact modifier match beats `any`, so the plain `a` variant fires
+    /// when no modifier is held and `any + a` only with modifiers held.
+    AnyInOmission(SourceSpan),
+    /// The `any` variant of an omission shorthand leaves out modifiers of
+    /// its chord, e.g. `super` in `super + {_, any +} a`, since `any`
+    /// replaces the other modifiers.
+    AnyReplacesModifiers {
+        span: SourceSpan,
+        modifiers: Vec<Modifier>,
+    },
+    /// Two bindings on the same chord only differ in their key attributes,
+    /// e.g. `super + a` and `super + @a`. Neither overrides the other, so
+    /// both fire.
+    AttributeVariants {
+        first: SourceSpan,
+        second: SourceSpan,
+        /// One fires on press and the other on release.
+        press_and_release: bool,
+    },
+    /// A chord holds `shift` along with a shifted symbol such as `exclam`,
+    /// which implies Shift already.
+    RedundantShift(SourceSpan),
+    /// A binding that enters a mode fires on release, e.g. `super + @r`.
+    /// Its command waits for the release as well.
+    CommandRunsOnRelease(SourceSpan),
+    /// A binding uses `@escape` but the config declares no modes, so there
+    /// is never a mode to leave.
+    EscapeWithoutModes(SourceSpan),
+    /// Two chords written differently with the `any` modifier on the same
+    /// key in one scope, e.g. `any + a` and `any + shift + a`. `any`
+    /// replaces the other modifiers, so both fire on the same events and
+    /// the `second` one, declared later, overrides the `first` one.
+    AnyCollision {
+        first: SourceSpan,
+        second: SourceSpan,
+    },
+    /// A variable or block that no binding left after merging reads.
+    /// `removed` counts the bindings reading it that were overridden or
+    /// unbound, 0 when nothing ever read it.
+    UnusedDefinition {
+        kind: DefinitionKind,
+        name: String,
+        span: SourceSpan,
+        removed: usize,
+    },
+    /// A variable or block is defined again later in the same file. The
+    /// later definition replaces it in the whole file, so it is never
+    /// read.
+    ShadowedDefinition {
+        kind: DefinitionKind,
+        name: String,
+        first: SourceSpan,
+        second: SourceSpan,
+    },
+    /// A top-level binding is declared again later in the same file on the
+    /// same chord, so the `second` declaration overrides the `first` one.
+    /// Overriding a binding of another file is how configs are layered and
+    /// is not warned about.
+    OverriddenBinding {
+        first: SourceSpan,
+        second: SourceSpan,
+    },
+    /// An `ignore` statement matches no binding of its scope, so it has no
+    /// effect. One in a mode is matched by the bindings of the mode and by
+    /// the top-level ones.
+    UnmatchedIgnore(SourceSpan),
+    /// An `ignore mode` statement names no mode of the config, so it has
+    /// no effect.
+    UnmatchedModeIgnore { name: String, span: SourceSpan },
+    /// A file is included again, e.g. by two files of the config. Files are
+    /// only parsed the `first` time they are included, so the `second`
+    /// include has no effect.
+    DuplicateInclude {
+        first: SourceSpan,
+        second: SourceSpan,
+    },
+    /// A mode has no bindings, so nothing fires while it is active.
+    EmptyMode { name: String, span: SourceSpan },
+    /// A binding uses `@escape` but there is never a mode to leave. For a
+    /// top-level binding, no binding of the config enters a mode. For a
+    /// binding of `mode`, no binding enters that mode and the config does
+    /// not start in it, so the binding never fires.
+    EscapeWithoutEnter {
+        span: SourceSpan,
+        mode: Option<String>,
+    },
+    /// A command line or a statement of a mode is not indented in the
+    /// [`ParseOptions::indent_style`](crate::ParseOptions::indent_style).
+    /// `found` and `expected` describe the indentation, e.g. `4 spaces`.
+    Indentation {
+        span: SourceSpan,
+        found: String,
+        expected: String,
+    },
+    /// A command likely fails in the shell, e.g. on an unclosed quote.
+    /// Only reported with
+    /// [`ParseOptions::validate_commands`](crate::ParseOptions::validate_commands).
+    CommandSyntax { span: SourceSpan, lint: CommandLint },
+}
+
+/// The text of the diagnostic, without the `warning:` prefix.
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&Diagnostic::from(self).body(RenderStyle::Plain))
+    }
+}
+
+impl From<&Warning> for Diagnostic {
+    fn from(warning: &Warning) -> Self {
+        let label = |span: &SourceSpan, note: &str| Label {
+            span: span.clone(),
+            note: note.to_string(),
+        };
+        let (message, labels) = match warning {
+            Warning::InactiveProfile(profile) => (
+                format!("profile `{profile}` is not active, declarations tagged with it were left out"),
+                vec![],
+            ),
+            Warning::UnknownProfile(profile) => (
+                format!("profile `{profile}` is active but no declaration is tagged with it"),
+                vec![],
+            ),
+            Warning::ReplayWithoutSwallow(mode) => (
+                format!("mode `{mode}` replays keys but does not swallow, so the `replay` statements have no effect"),
+                vec![],
+            ),
+            Warning::AnyInOmission(span) => (
+                String::new(),
+                vec![label(span, "an exact match beats `any`, so the `_` variant fires when no modifier is held and the `any` variant only when some are; declare two separate bindings if both should fire")],
+            ),
+            Warning::AnyReplacesModifiers { span, modifiers } => (
+                String::new(),
+                vec![label(
+                    span,
+                    &format!(
+                        "`any` replaces the other modifiers, so the `any` variant leaves out {}",
+                        modifiers
+                            .iter()
+                            .map(|modifier| format!("`{}`", ModifierInfo::of(*modifier).canonical))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                )],
+            ),
+            Warning::AttributeVariants {
+                first,
+                second,
+                press_and_release,
+            } => (
+                String::new(),
+                vec![
+                    label(first, "bound here"),
+                    label(second, &attribute_variants_note(*press_and_release)),
+                ],
+            ),
+            Warning::RedundantShift(span) => (
+                String::new(),
+                vec![label(span, "`shift` is redundant, the shifted symbol in this chord already implies it")],
+            ),
+            Warning::CommandRunsOnRelease(span) => (
+                String::new(),
+                vec![label(span, "this binding enters a mode on release, so its command also runs on release rather than on press")],
+            ),
+            Warning::EscapeWithoutModes(span) => (
+                String::new(),
+                vec![label(span, "`@escape` has no effect, the config declares no modes")],
+            ),
+            Warning::AnyCollision { first, second } => (
+                String::new(),
+                vec![
+                    label(first, "bound here with `any`"),
+                    label(second, "and here with `any` on the same key, `any` replaces the other modifiers so both fire on the same events, this later declaration wins"),
+                ],
+            ),
+            Warning::UnusedDefinition {
+                kind,
+                name,
+                span,
+                removed,
+            } => (
+                String::new(),
+                vec![label(span, &unused_definition_note(kind.describe(name), *removed))],
+            ),
+            Warning::ShadowedDefinition {
+                kind,
+                name,
+                first,
+                second,
+            } => (
+                String::new(),
+                vec![
+                    label(first, &format!("{} is never used", kind.describe(name))),
+                    label(second, "because this later definition replaces it in the whole file"),
+                ],
+            ),
+            Warning::OverriddenBinding { first, second } => (
+                String::new(),
+                vec![
+                    label(first, "this binding never fires"),
+                    label(second, "because this later binding on the same chord overrides it"),
+                ],
+            ),
+            Warning::UnmatchedIgnore(span) => (
+                String::new(),
+                vec![label(span, "this `ignore` has no effect, no binding of the config matches it")],
+            ),
+            Warning::UnmatchedModeIgnore { name, span } => (
+                String::new(),
+                vec![label(span, &format!("this `ignore` has no effect, no mode `{name}` is declared"))],
+            ),
+            Warning::DuplicateInclude { first, second } => (
+                String::new(),
+                vec![
+                    label(first, "the file is included here"),
+                    label(second, "and again here, files are only parsed once so this include has no effect"),
+                ],
+            ),
+            Warning::EmptyMode { name, span } => (
+                String::new(),
+                vec![label(span, &format!("mode `{name}` has no bindings, nothing fires while it is active"))],
+            ),
+            Warning::EscapeWithoutEnter { span, mode: None } => (
+                String::new(),
+                vec![label(span, "`@escape` has no effect, no binding of the config enters a mode")],
+            ),
+            Warning::EscapeWithoutEnter {
+                span,
+                mode: Some(mode),
+            } => (
+                String::new(),
+                vec![label(span, &format!("`@escape` has no effect, no binding enters mode `{mode}`"))],
+            ),
+            Warning::Indentation {
+                span,
+                found,
+                expected,
+            } => (
+                String::new(),
+                vec![label(span, &format!("indented with {found}, expected {expected}"))],
+            ),
+            Warning::CommandSyntax { span, lint } => (
+                String::new(),
+                vec![label(span, &format!("{lint}, the shell will likely fail to run this command"))],
+            ),
+        };
+        Self {
+            severity: Severity::Warning,
+            message,
+            labels,
+        }
+    }
+}
+
+fn unused_definition_note(definition: String, removed: usize) -> String {
+    match removed {
+        0 => format!("{definition} is never used"),
+        1 => format!("{definition} is unused, the only binding using it was overridden or unbound"),
+        removed => format!(
+            "{definition} is unused, the {removed} bindings using it were overridden or unbound"
+        ),
+    }
+}
+
+fn attribute_variants_note(press_and_release: bool) -> String {
+    let when = if press_and_release {
+        ", one on press and the other on release"
+    } else {
+        " on the same key event"
+    };
+    format!("and here with different key attributes, it does not override the first binding, both fire{when}")
+}
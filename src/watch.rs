@@ -0,0 +1,296 @@
+//! Parsing a config again whenever one of its files changes, e.g. to check
+//! it while editing, see [`ConfigWatcher`].
+//!
+//! Files are polled rather than watched through the kernel, which keeps
+//! working across editors replacing files on save and across filesystems
+//! without change notifications.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    resolve::ImportContext, sha256::Sha256, ConfigReadError, Diagnostic, ParseError, ParseOptions,
+    ParserInput, RenderStyle, SwhkdParser,
+};
+
+/// What [`ConfigWatcher::poll`] found.
+#[derive(Debug)]
+pub enum WatchEvent {
+    /// The config does not exist, e.g. while an editor replaces it on save.
+    /// Reported once until it exists again.
+    Missing,
+    /// A file of the config changed, and the config was parsed again.
+    Parsed {
+        result: Result<Box<SwhkdParser>, ParseError>,
+        /// How long parsing took.
+        elapsed: Duration,
+    },
+}
+
+/// The metadata of a file telling whether it may have changed, `None` for
+/// a file that could not be read.
+pub(crate) type Stamp = Option<(u64, SystemTime)>;
+
+pub(crate) fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+fn digest(contents: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(contents);
+    hasher.finish_hex()
+}
+
+/// A file read by the last parse.
+struct WatchedFile {
+    /// `None` until the first poll after parsing, which reads the file
+    /// again in case it changed while being parsed.
+    stamp: Option<Stamp>,
+    /// The digest of the contents that were parsed.
+    sha256: String,
+}
+
+/// Parses a config from a path and parses it again when the config or one
+/// of its includes changes.
+///
+/// Only the files read by the last parse are watched, so includes added
+/// to the config are watched from the parse reading them on. An include
+/// that failed to resolve is tried again on every poll.
+///
+/// ```
+/// use sweet::{ConfigWatcher, WatchEvent};
+///
+/// let dir = tempfile::tempdir()?;
+/// let path = dir.path().join("swhkdrc");
+/// std::fs::write(&path, "super + a\n    firefox")?;
+/// let mut watcher = ConfigWatcher::new(&path);
+/// let Some(WatchEvent::Parsed { result, .. }) = watcher.poll() else {
+///     panic!("the first poll parses the config");
+/// };
+/// assert_eq!(result?.bindings.len(), 1);
+/// assert!(watcher.poll().is_none());
+/// std::fs::write(&path, "super + {a,b}\n    {firefox,foot}")?;
+/// let Some(WatchEvent::Parsed { result, .. }) = watcher.poll() else {
+///     panic!("the config changed");
+/// };
+/// assert_eq!(result?.bindings.len(), 2);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct ConfigWatcher {
+    path: PathBuf,
+    options: ParseOptions,
+    /// Every file read by the last parse, by the path it was read from.
+    files: BTreeMap<PathBuf, WatchedFile>,
+    /// The include that failed to resolve in the last parse, as requested
+    /// and the file requesting it.
+    unresolved: Option<(String, Option<PathBuf>)>,
+    /// Whether the config was parsed at least once.
+    parsed: bool,
+    /// Whether [`WatchEvent::Missing`] was the last event.
+    missing: bool,
+}
+
+impl ConfigWatcher {
+    /// Watches the config at `path`, which is parsed on the first poll.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self::with_options(path, ParseOptions::default())
+    }
+
+    /// Like [`ConfigWatcher::new`], parsing with non-default
+    /// [`ParseOptions`].
+    pub fn with_options(path: impl Into<PathBuf>, options: ParseOptions) -> Self {
+        Self {
+            path: path.into(),
+            options,
+            files: BTreeMap::new(),
+            unresolved: None,
+            parsed: false,
+            missing: false,
+        }
+    }
+
+    /// The files read by the last parse.
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    /// Parses the config if it was never parsed or if one of its files
+    /// changed since the last parse, `None` otherwise.
+    ///
+    /// A file whose size and modification time did not change is taken as
+    /// unchanged without reading it. A file rewritten with the same
+    /// contents is not a change.
+    pub fn poll(&mut self) -> Option<WatchEvent> {
+        if stamp(&self.path).is_none() {
+            return self.report_missing();
+        }
+        if self.parsed && !self.changed() {
+            return None;
+        }
+        let start = Instant::now();
+        let mut ctx = ImportContext {
+            options: self.options.clone(),
+            files: Some(vec![]),
+            ..Default::default()
+        };
+        let result =
+            SwhkdParser::from_context(ParserInput::Path(&self.path), &mut ctx).map(Box::new);
+        let elapsed = start.elapsed();
+        if matches!(&result, Err(ParseError::ConfigRead(_))) && stamp(&self.path).is_none() {
+            return self.report_missing();
+        }
+        self.parsed = true;
+        self.missing = false;
+        self.files = ctx
+            .files
+            .unwrap_or_default()
+            .into_iter()
+            .map(|file| {
+                let watched = WatchedFile {
+                    stamp: None,
+                    sha256: file.sha256,
+                };
+                (PathBuf::from(file.path), watched)
+            })
+            .collect();
+        self.unresolved = match &result {
+            Err(ParseError::ImportFailed {
+                path,
+                cause: ConfigReadError::ReadingConfig(_) | ConfigReadError::NotFound(_),
+                requested_at,
+            }) => Some((
+                path.to_string_lossy().into_owned(),
+                Some(PathBuf::from(&*requested_at.file)),
+            )),
+            _ => None,
+        };
+        Some(WatchEvent::Parsed { result, elapsed })
+    }
+
+    fn report_missing(&mut self) -> Option<WatchEvent> {
+        // Parsed again once it is back, whatever its contents.
+        self.parsed = false;
+        if std::mem::replace(&mut self.missing, true) {
+            return None;
+        }
+        Some(WatchEvent::Missing)
+    }
+
+    /// Whether a file of the last parse changed, or the include that
+    /// failed to resolve now does.
+    fn changed(&mut self) -> bool {
+        let mut changed = false;
+        for (path, file) in &mut self.files {
+            let now = stamp(path);
+            if file.stamp == Some(now) {
+                continue;
+            }
+            file.stamp = Some(now);
+            let same = fs::read(path).is_ok_and(|contents| digest(&contents) == file.sha256);
+            changed |= !same;
+        }
+        if let Some((requested, from)) = &self.unresolved {
+            changed |= self
+                .options
+                .resolver()
+                .resolve(requested, from.as_deref())
+                .is_ok();
+        }
+        changed
+    }
+}
+
+/// Writes what `sweet check --watch` shows to `out`: for every event of
+/// `events`, the diagnostics of the config, or a summary when it parsed
+/// without any. `events` yields the result of each
+/// [`ConfigWatcher::poll`], `None` writing nothing, and ends once watching
+/// stops. With `clear_screen`, the screen of a terminal is cleared before
+/// each event.
+///
+/// Returns whether the config parsed on the last event, `false` when there
+/// was none.
+///
+/// ```
+/// use sweet::{show_watch_events, ConfigWatcher, RenderStyle};
+///
+/// let dir = tempfile::tempdir()?;
+/// let path = dir.path().join("swhkdrc");
+/// std::fs::write(&path, "super + a\n    firefox")?;
+/// let mut watcher = ConfigWatcher::new(&path);
+/// let mut out = vec![];
+/// let events = [watcher.poll()];
+/// assert!(show_watch_events("swhkdrc", events, &mut out, RenderStyle::Plain, false)?);
+/// assert!(String::from_utf8(out)?.starts_with("OK: 1 bindings, 0 modes"));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn show_watch_events(
+    path: &str,
+    events: impl IntoIterator<Item = Option<WatchEvent>>,
+    out: &mut impl io::Write,
+    style: RenderStyle,
+    clear_screen: bool,
+) -> io::Result<bool> {
+    let mut ok = false;
+    for event in events.into_iter().flatten() {
+        if clear_screen {
+            // Clears the screen and moves the cursor to the top left.
+            write!(out, "\x1b[2J\x1b[H")?;
+        }
+        ok = show_watch_event(path, event, out, style)?;
+        out.flush()?;
+    }
+    Ok(ok)
+}
+
+/// Writes what one event found, telling whether the config parsed.
+fn show_watch_event(
+    path: &str,
+    event: WatchEvent,
+    out: &mut impl io::Write,
+    style: RenderStyle,
+) -> io::Result<bool> {
+    let (parser, elapsed) = match event {
+        WatchEvent::Missing => {
+            writeln!(out, "{}: waiting for file", path)?;
+            return Ok(false);
+        }
+        WatchEvent::Parsed {
+            result: Err(err), ..
+        } => {
+            writeln!(out, "{}", Diagnostic::from(&err).render(style))?;
+            return Ok(false);
+        }
+        WatchEvent::Parsed {
+            result: Ok(parser),
+            elapsed,
+        } => (parser, elapsed),
+    };
+    for warning in &parser.warnings {
+        writeln!(out, "{}", Diagnostic::from(warning).render(style))?;
+    }
+    for unreachable in &parser.unreachable_bindings() {
+        writeln!(out, "{}", Diagnostic::from(unreachable).render(style))?;
+    }
+    let bindings = parser.bindings.len()
+        + parser
+            .modes
+            .iter()
+            .map(|mode| mode.bindings.len())
+            .sum::<usize>();
+    let summary = format!(
+        "OK: {} bindings, {} modes (parsed in {} ms)",
+        bindings,
+        parser.modes.len(),
+        elapsed.as_millis()
+    );
+    match style {
+        RenderStyle::Color => writeln!(out, "\x1b[32m{}\x1b[0m", summary)?,
+        _ => writeln!(out, "{}", summary)?,
+    }
+    Ok(true)
+}
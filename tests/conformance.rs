@@ -0,0 +1,316 @@
+//! Bindings behave the same wherever they are declared: at the top level,
+//! inside a mode and inside a mode of an included file. Every feature of
+//! the matrix below is parsed in each of these contexts and compared with
+//! the top level.
+
+use std::path::PathBuf;
+
+use pest::error::LineColLocation;
+use sweet::{binding, Binding, Definition, ModeInstruction, ParseError, ParserInput, SwhkdParser};
+
+#[derive(Debug, Clone, Copy)]
+enum Context {
+    TopLevel,
+    Mode,
+    IncludedMode,
+}
+
+const CONTEXTS: [Context; 3] = [Context::TopLevel, Context::Mode, Context::IncludedMode];
+
+/// What a config parsed to, wherever its bindings ended up.
+#[derive(Debug, PartialEq)]
+struct Parsed {
+    bindings: Vec<Binding>,
+    unbinds: Vec<Definition>,
+}
+
+/// A config placed in a context, along with the file it was written to.
+struct Placed {
+    result: Result<Parsed, ParseError>,
+    /// The file holding the config, `None` for raw input.
+    file: Option<PathBuf>,
+    /// Lines and columns the config was shifted by.
+    offset: (usize, usize),
+    _dir: tempfile::TempDir,
+}
+
+fn indent(config: &str) -> String {
+    config
+        .lines()
+        .map(|line| format!("    {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Declares the mode the configs enter, so that they can.
+const OTHER_MODE: &str = "\nmode other\n    escape\n        @escape\nendmode";
+
+fn place(config: &str, context: Context) -> Placed {
+    let dir = tempfile::tempdir().unwrap();
+    let in_mode = |config: &str| format!("\nmode conformance\n{}\nendmode", indent(config));
+    let (result, file, offset) = match context {
+        Context::TopLevel => (
+            SwhkdParser::from(ParserInput::Raw(&format!("\n{config}{OTHER_MODE}"))),
+            None,
+            (1, 0),
+        ),
+        Context::Mode => (
+            SwhkdParser::from(ParserInput::Raw(&format!(
+                "{}{OTHER_MODE}",
+                in_mode(config)
+            ))),
+            None,
+            (2, 4),
+        ),
+        Context::IncludedMode => {
+            let included = dir.path().join("included.swhkd");
+            std::fs::write(&included, in_mode(config)).unwrap();
+            let root = format!(
+                "\ninclude {}\nsuper + z\n    root{OTHER_MODE}",
+                included.display()
+            );
+            (
+                SwhkdParser::from(ParserInput::Raw(&root)),
+                Some(included),
+                (2, 4),
+            )
+        }
+    };
+    let result = result.map(|parsed| match context {
+        Context::TopLevel => Parsed {
+            bindings: parsed.bindings,
+            unbinds: parsed.unbinds,
+        },
+        Context::Mode | Context::IncludedMode => {
+            let mode = parsed
+                .modes
+                .into_iter()
+                .find(|mode| mode.name == "conformance")
+                .unwrap();
+            Parsed {
+                bindings: mode.bindings,
+                unbinds: mode.unbinds,
+            }
+        }
+    });
+    Placed {
+        result,
+        file,
+        offset,
+        _dir: dir,
+    }
+}
+
+/// Features that parse, along with what they parse to at the top level.
+fn features() -> Vec<(&'static str, &'static str, Parsed)> {
+    let parsed = |bindings: Vec<Binding>| Parsed {
+        bindings,
+        unbinds: vec![],
+    };
+    vec![
+        (
+            "plain",
+            "super + a\n    firefox",
+            parsed(vec![binding!("super + a" => "firefox")]),
+        ),
+        (
+            "shorthand",
+            "super + {a, b}\n    {firefox, kitty}",
+            parsed(vec![
+                binding!("super + a" => "firefox"),
+                binding!("super + b" => "kitty"),
+            ]),
+        ),
+        (
+            "range",
+            "{super, alt} + {1-3}\n    {focus, move} {1-3}",
+            parsed(vec![
+                binding!("super + 1" => "focus 1"),
+                binding!("super + 2" => "focus 2"),
+                binding!("super + 3" => "focus 3"),
+                binding!("alt + 1" => "move 1"),
+                binding!("alt + 2" => "move 2"),
+                binding!("alt + 3" => "move 3"),
+            ]),
+        ),
+        (
+            "omission",
+            "{_, shift +} super + a\n    {focus, move}",
+            parsed(vec![
+                binding!("super + a" => "focus"),
+                binding!("shift + super + a" => "move"),
+            ]),
+        ),
+        (
+            "attributes",
+            "super + {@a, ~b}\n    {released, sent}",
+            parsed(vec![
+                binding!("super + @a" => "released"),
+                binding!("super + ~b" => "sent"),
+            ]),
+        ),
+        (
+            "modifier only",
+            "@super!\n    launcher",
+            parsed(vec![binding!("@super!" => "launcher")]),
+        ),
+        (
+            "multiple commands",
+            "super + {a, b}\n    first {a, b}\n    then {b, a}",
+            parsed(vec![
+                binding!("super + a" => "first a\nthen b"),
+                binding!("super + b" => "first b\nthen a"),
+            ]),
+        ),
+        (
+            "instructions",
+            "super + e\n    notify-send entering && @enter other\nsuper + q\n    @escape",
+            parsed(vec![
+                binding!("super + e" => "notify-send entering ")
+                    .with_mode_instructions(vec![ModeInstruction::Enter("other".to_string())]),
+                binding!("super + q" => "").with_mode_instructions(vec![ModeInstruction::Escape]),
+            ]),
+        ),
+        (
+            "comments",
+            "super + a # focus\n# between the chord and the command\n    firefox",
+            parsed(vec![binding!("super + a" => "firefox")]),
+        ),
+        (
+            "unbind",
+            "ignore super + {1-3}",
+            Parsed {
+                bindings: vec![],
+                unbinds: ["super + 1", "super + 2", "super + 3"]
+                    .map(|chord| chord.parse().unwrap())
+                    .into(),
+            },
+        ),
+    ]
+}
+
+#[test]
+fn test_features_in_every_context() {
+    for (name, config, expected) in features() {
+        for context in CONTEXTS {
+            let placed = place(config, context);
+            match placed.result {
+                Ok(parsed) => assert_eq!(parsed, expected, "{name} in {context:?}"),
+                Err(err) => panic!("{name} in {context:?} failed to parse:\n{err}"),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_spans_in_every_context() {
+    let config = "super + {a, b}\n    {firefox, kitty}";
+    for context in CONTEXTS {
+        let placed = place(config, context);
+        let (lines, columns) = placed.offset;
+        let bindings = placed.result.unwrap().bindings;
+        for binding in bindings {
+            let span = binding.span().unwrap();
+            assert_eq!(
+                (span.line, span.column),
+                (1 + lines, 1 + columns),
+                "{context:?}"
+            );
+            let file = placed.file.as_ref().map(|file| file.display().to_string());
+            assert_eq!(
+                &*span.file,
+                file.as_deref().unwrap_or("<anonymous>"),
+                "{context:?}"
+            );
+            assert_eq!(binding.source_text(), "super + {a, b}", "{context:?}");
+        }
+    }
+}
+
+/// The start and end of a span, as lines and columns.
+type LineColSpan = ((usize, usize), (usize, usize));
+
+/// Configs that fail, along with the span of the error at the top level and
+/// part of its message.
+const ERRORS: &[(&str, LineColSpan, &str)] = &[
+    (
+        "super + {a, b}\n    firefox",
+        ((1, 1), (2, 12)),
+        "the number of possible binding variants 2 does not equal the number of possible command variants 1",
+    ),
+    (
+        "super + {a, b}\n    {firefox, kitty}\n    notify-send",
+        ((1, 1), (3, 16)),
+        "the number of possible binding variants 2 does not equal the number of possible command variants 1",
+    ),
+    (
+        "super + \\a\n    firefox",
+        ((1, 9), (1, 11)),
+        "unnecessary escape of 'a' in key position",
+    ),
+    (
+        "super + a\n    echo \u{7}",
+        ((2, 10), (2, 11)),
+        "command contains the control character U+0007",
+    ),
+    (
+        "super + {3-a}\n    workspace {3-a}",
+        ((1, 10), (1, 13)),
+        "shorthand range `3-a` goes from a digit to a lowercase letter",
+    ),
+];
+
+#[test]
+fn test_error_spans_in_every_context() {
+    for (config, ((l1, c1), (l2, c2)), message) in ERRORS {
+        for context in CONTEXTS {
+            let placed = place(config, context);
+            let (lines, columns) = placed.offset;
+            let err = match placed.result {
+                Err(ParseError::Grammar(err)) => err,
+                Err(err) => {
+                    panic!("{config:?} in {context:?}: expected a grammar error, got {err}")
+                }
+                Ok(_) => panic!("{config:?} in {context:?}: expected an error"),
+            };
+            assert_eq!(
+                err.line_col,
+                LineColLocation::Span((l1 + lines, c1 + columns), (l2 + lines, c2 + columns)),
+                "{config:?} in {context:?}:\n{err}"
+            );
+            let file = placed.file.as_ref().map(|file| file.display().to_string());
+            assert_eq!(
+                err.path(),
+                Some(file.as_deref().unwrap_or("<anonymous>")),
+                "{config:?} in {context:?}:\n{err}"
+            );
+            assert!(err.to_string().contains(message), "{err}");
+        }
+    }
+}
+
+/// The top level merges bindings on the same chord and drops ignored ones.
+/// Modes keep them all and leave their `ignore` statements to the daemon,
+/// see [`SwhkdParser::from`].
+#[test]
+fn test_only_top_level_merges_bindings() {
+    let config =
+        "super + a\n    first\nsuper + a\n    second\nsuper + b\n    third\nignore super + b";
+    let top = place(config, Context::TopLevel).result.unwrap();
+    assert_eq!(top.bindings, [binding!("super + a" => "second")]);
+    for context in [Context::Mode, Context::IncludedMode] {
+        let nested = place(config, context).result.unwrap();
+        assert_eq!(
+            nested,
+            Parsed {
+                bindings: vec![
+                    binding!("super + a" => "first"),
+                    binding!("super + a" => "second"),
+                    binding!("super + b" => "third"),
+                ],
+                unbinds: vec!["super + b".parse().unwrap()],
+            },
+            "{context:?}"
+        );
+    }
+}
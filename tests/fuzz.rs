@@ -0,0 +1,212 @@
+//! Raw config text is untrusted input of a daemon running as root, so
+//! parsing it must fail with an error rather than panic, whatever the
+//! text. The properties below throw arbitrary strings, lossily decoded
+//! bytes, soups of grammar tokens and mutations of the fixture corpus at
+//! every way of parsing raw input.
+//!
+//! They run with `cargo test` like any other test. For a longer run, e.g.
+//! after a grammar change, raise the number of cases:
+//!
+//! ```sh
+//! PROPTEST_CASES=100000 cargo test --release --test fuzz
+//! ```
+//!
+//! Inputs that once panicked are kept in [`REGRESSIONS`].
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use proptest::prelude::*;
+use sweet::{syntax_tree, ParseOptions, ParserInput, SwhkdParser};
+
+/// Parses `config` every way raw input can be parsed, failing on the first
+/// panic with the config that caused it.
+fn assert_no_panic(config: &str) {
+    let options = ParseOptions::default()
+        .with_multi_key_chords(true)
+        .with_validate_commands(true);
+    let parsers: [(&str, &dyn Fn()); 5] = [
+        ("from", &|| {
+            drop(SwhkdParser::from(ParserInput::Raw(config)))
+        }),
+        ("from_with_options", &|| {
+            drop(SwhkdParser::from_with_options(
+                ParserInput::Raw(config),
+                &options,
+            ))
+        }),
+        ("from_all_errors", &|| {
+            drop(SwhkdParser::from_all_errors(ParserInput::Raw(config)))
+        }),
+        ("from_lenient", &|| {
+            drop(SwhkdParser::from_lenient(ParserInput::Raw(config)))
+        }),
+        ("syntax_tree", &|| drop(syntax_tree(config))),
+    ];
+    for (name, parse) in parsers {
+        let result = catch_unwind(AssertUnwindSafe(parse));
+        assert!(result.is_ok(), "`{name}` panicked on {config:?}");
+    }
+}
+
+/// Inputs that panicked once, each checked on every run. Each one ends
+/// with a chord missing its command that is reported on an empty line,
+/// where the end of file error used to get a span ending before it
+/// starts.
+const REGRESSIONS: &[&str] = &[
+    // Only when skipping the declarations in error.
+    "super\n#\\\nsuper",
+    "de report_include.swhkd\n\nsuper +switch:firefox\\\n\n",
+    // Every way of parsing.
+    "super + a\\\n\n",
+    "a\\\n\n\n",
+    "super\\\n    ",
+    "super + a\n    b\nc\\\n\n",
+    "mode m\nsuper + a\\\n\n",
+];
+
+#[test]
+fn regressions_do_not_panic() {
+    for config in REGRESSIONS {
+        assert_no_panic(config);
+    }
+}
+
+/// The fixture configs, covering every rule of the grammar.
+fn corpus() -> Vec<String> {
+    let mut configs = vec![];
+    for entry in std::fs::read_dir("tests/fixtures").unwrap() {
+        let path = entry.unwrap().path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "swhkd")
+        {
+            configs.push(std::fs::read_to_string(path).unwrap());
+        }
+    }
+    configs.sort();
+    configs
+}
+
+/// Pieces of the config syntax, glued together in any order.
+fn token() -> impl Strategy<Value = String> {
+    let tokens = prop::sample::select(vec![
+        "super",
+        "shift",
+        "ctrl",
+        "lalt",
+        "any",
+        "any_key",
+        "_",
+        "a",
+        "Return",
+        "key_a",
+        "keycode(",
+        "keycode(0x94)",
+        "btn_left",
+        "scroll_up",
+        "switch:",
+        "virtual:",
+        "{",
+        "}",
+        ",",
+        "-",
+        ":",
+        "+",
+        ";",
+        "&",
+        "~",
+        "@",
+        "!",
+        "=",
+        "$",
+        "${",
+        "\\",
+        "\\\n",
+        "\n",
+        "\n    ",
+        "\n        ",
+        "\t",
+        " ",
+        "#",
+        "[",
+        "]",
+        "[laptop]",
+        "[device=",
+        "\"",
+        "'",
+        "@enter",
+        "@escape",
+        "&&",
+        "mode",
+        "endmode",
+        "oneoff",
+        "swallow",
+        "replay",
+        "include",
+        "ignore",
+        "define",
+        "block",
+        "endblock",
+        "set",
+        "throttle",
+        "10ms",
+        "1-9",
+        "9-1:2",
+        "a-z",
+        "\u{00e9}",
+        "\u{200b}",
+        "\u{feff}",
+        "\u{0}",
+    ]);
+    prop_oneof![
+        8 => tokens.prop_map(str::to_string),
+        1 => any::<char>().prop_map(String::from),
+    ]
+}
+
+/// A fixture config with a few bytes replaced, inserted or removed.
+fn mutated(corpus: Vec<String>) -> impl Strategy<Value = String> {
+    let edits = prop::collection::vec((any::<prop::sample::Index>(), 0..3u8, token()), 1..6);
+    (prop::sample::select(corpus), edits).prop_map(|(config, edits)| {
+        let mut config = config.into_bytes();
+        for (index, kind, token) in edits {
+            let at = index.index(config.len() + 1);
+            match kind {
+                0 => {
+                    let end = (at + token.len()).min(config.len());
+                    config.splice(at..end, token.bytes());
+                }
+                1 => {
+                    config.splice(at..at, token.bytes());
+                }
+                _ => {
+                    let end = (at + token.len()).min(config.len());
+                    config.drain(at..end);
+                }
+            }
+        }
+        String::from_utf8_lossy(&config).into_owned()
+    })
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_strings_do_not_panic(config in any::<String>()) {
+        assert_no_panic(&config);
+    }
+
+    #[test]
+    fn arbitrary_bytes_do_not_panic(bytes in prop::collection::vec(any::<u8>(), 0..512)) {
+        assert_no_panic(&String::from_utf8_lossy(&bytes));
+    }
+
+    #[test]
+    fn token_soups_do_not_panic(tokens in prop::collection::vec(token(), 0..48)) {
+        assert_no_panic(&tokens.concat());
+    }
+
+    #[test]
+    fn mutated_fixtures_do_not_panic(config in mutated(corpus())) {
+        assert_no_panic(&config);
+    }
+}
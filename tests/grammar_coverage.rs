@@ -0,0 +1,109 @@
+//! Every rule of the grammar is exercised by some config of the fixture
+//! corpus, so that a rule nothing tests anymore, or a rule whose fixtures
+//! were deleted, fails here instead of going unnoticed. Rules that can't
+//! produce pairs are listed in [`UNREACHABLE`].
+
+use std::path::PathBuf;
+
+use sweet::{record_rules, ParseOptions, ParserInput, Rule, SwhkdParser};
+
+/// Rules the corpus never visits on purpose: silent rules produce no pairs,
+/// and `shorthand_bounds` and `shorthand_deny` only appear in lookaheads.
+/// Keep in sync with template.pest.
+const UNREACHABLE: &[Rule] = &[
+    Rule::keys_always_escaped,
+    Rule::key_attributes,
+    Rule::concat,
+    Rule::escape_lf,
+    Rule::WHITESPACE,
+    Rule::not_newline,
+    Rule::shorthand_bounds,
+    Rule::shorthand_deny,
+    Rule::key_or_range,
+    Rule::modifier_variant,
+    Rule::modifier_omit,
+    Rule::modifier_or_shorthand,
+    Rule::comment,
+    Rule::escaped_ampersand,
+    Rule::command_composite,
+    Rule::command_component_and,
+    Rule::nested_component_and,
+    Rule::continued,
+    Rule::inside_braces,
+    Rule::braced_variable,
+    Rule::command_chunk,
+    Rule::mode_instruction,
+    Rule::command_chunk_or_mode,
+    Rule::command_line,
+    Rule::command_line_continued,
+    Rule::command_indent,
+    Rule::key_slot,
+    Rule::key_slots,
+    Rule::trigger,
+    Rule::sequence,
+    Rule::unbind_wildcard,
+    Rule::binding_tags,
+    Rule::modename_characters,
+    Rule::primitives,
+    Rule::content,
+];
+
+/// The sample config and every config of the fixture corpus.
+fn corpus() -> std::io::Result<Vec<PathBuf>> {
+    let mut corpus = vec![PathBuf::from("hotkeys.swhkd")];
+    for dir in ["tests/fixtures", "tests/fixtures/lenient"] {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "swhkd") {
+                corpus.push(path);
+            }
+        }
+    }
+    corpus.sort();
+    Ok(corpus)
+}
+
+#[test]
+fn test_every_rule_is_visited() -> std::io::Result<()> {
+    let corpus = corpus()?;
+    // tests/fixtures/grammar.swhkd needs multi-key chords.
+    let options = ParseOptions::default().with_multi_key_chords(true);
+    let ((), coverage) = record_rules(|| {
+        for path in &corpus {
+            // Broken configs count as well, the grammar ran on them.
+            let _ = SwhkdParser::from_with_options(ParserInput::Path(path), &options);
+        }
+    });
+
+    let unvisited: Vec<_> = coverage
+        .unreached()
+        .filter(|rule| !UNREACHABLE.contains(rule))
+        .collect();
+    assert!(
+        unvisited.is_empty(),
+        "no config of the corpus visits {unvisited:?}, add one to tests/fixtures"
+    );
+    let visited: Vec<_> = UNREACHABLE
+        .iter()
+        .filter(|rule| coverage.hits(**rule) != 0)
+        .collect();
+    assert!(
+        visited.is_empty(),
+        "{visited:?} are visited after all, remove them from `UNREACHABLE`"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_recordings_nest() {
+    let input = "super + a\n    firefox";
+    let ((inner, _), outer) =
+        record_rules(|| record_rules(|| SwhkdParser::from(ParserInput::Raw(input))));
+    assert!(inner.is_ok());
+    assert_eq!(outer.hits(Rule::binding), 1);
+
+    // Nothing is recorded outside of a recording.
+    SwhkdParser::from(ParserInput::Raw(input)).unwrap();
+    let ((), coverage) = record_rules(|| {});
+    assert_eq!(coverage.unreached().count(), Rule::all_rules().len());
+}
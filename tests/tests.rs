@@ -1,27 +1,22 @@
-use std::io::Write;
+use std::{borrow::Cow, io::Write, path::Path, sync::Arc, time::Duration};
 
-use pest::error::LineColLocation::{Pos, Span};
+use sweet::evdev_mappings::{convert, name_of};
 use sweet::{
-    Binding, Definition, ParseError, ParserInput, SwhkdParser,
+    assert_parse_error, assert_parses_to, binding, diff_previews, estimate, fix_homoglyphs,
+    key_name, lint_command, lookup_key, normalize_commands, reindent, render_items,
+    show_watch_events, supported_key_names, Binding, CacheError, CachedConfig, ChordStyle,
+    CommandForm, CommandLint, CommandPart, CommandSegment, CompactStyle, ConfigBuilder, ConfigItem,
+    ConfigReadError, ConfigWatcher, ConflictKind, DefaultStyle, Definition, DefinitionKind,
+    Diagnostic, DumpFormat, DumpOptions, ExportOptions, ExternalBinding, ExternalBindingError,
+    FallbackPolicy, ImportPolicy, ImportResolver, IndentStyle, MacStyle, MapResolver,
+    ModeInstruction, Modifier, ModifierInfo, ParseError, ParseOptions, ParserInput, RawBinding,
+    RawChord, RawMode, RenderStyle, ReportOptions, ReportOutcome, ReportedFile, ResolvedImport,
+    Schedule, ScrollDirection, Settings, Severity, SwhkdParser, SwitchType, SyntaxKind, SyntaxNode,
+    TimeOfDay, TimeRange, Trigger, Warning, WatchEvent, Weekday, KEY_ATTRIBUTES, MODIFIERS,
     {Key, KeyAttribute, Modifier::*},
 };
 use thiserror::Error;
 
-fn assert_grammar_error_at(contents: &str, pos: (usize, usize)) {
-    let parse_result = SwhkdParser::from(ParserInput::Raw(contents));
-    let Err(ParseError::Grammar(e)) = parse_result else {
-        panic!("expected grammar parse error")
-    };
-    assert_eq!(e.line_col, Pos(pos));
-}
-fn assert_grammar_error_at_span(contents: &str, start: (usize, usize), end: (usize, usize)) {
-    let parse_result = SwhkdParser::from(ParserInput::Raw(contents));
-    let Err(ParseError::Grammar(e)) = parse_result else {
-        panic!("expected grammar parse error")
-    };
-    assert_eq!(e.line_col, Span(start, end));
-}
-
 fn assert_equal_binding_set(a: Vec<Binding>, mut b: Vec<Binding>) {
     for binding in a {
         if let Some(pos) = b.iter().position(|bin| bin.eq(&binding)) {
@@ -84,6 +79,45 @@ super + b
     Ok(())
 }
 
+#[test]
+fn test_spans_through_imports() -> Result<(), IoOrParseError> {
+    let mut import = tempfile::NamedTempFile::new()?;
+    import.write_all(b"\nsuper + {c, d}\n    {hello, world}\nsuper + b\n    chromium")?;
+
+    let mut setup = tempfile::NamedTempFile::new()?;
+    write!(
+        setup,
+        "include {}\n\nsuper + b\n    firefox",
+        import.path().display()
+    )?;
+
+    let parsed = SwhkdParser::from(ParserInput::Path(setup.path()))?;
+    let location = |binding: &Binding| {
+        let span = binding.span().unwrap();
+        (span.file.to_string(), span.line, span.column)
+    };
+    let import_path = import.path().display().to_string();
+    let setup_path = setup.path().display().to_string();
+    // Every variant of a shorthand points at the line it was written on.
+    let locations = parsed.bindings.iter().map(location).collect::<Vec<_>>();
+    assert_eq!(
+        locations,
+        [
+            (import_path.clone(), 4, 1),
+            (import_path.clone(), 2, 1),
+            (import_path, 2, 1),
+        ]
+    );
+    assert_eq!(location(&parsed.overridden[0]), (setup_path, 3, 1));
+    // The overriding binding is equal to one built in code, spans aside.
+    assert_eq!(
+        parsed.bindings[0],
+        Binding::running("chromium")
+            .on(Definition::new(evdev::Key::KEY_B).with_modifiers(&[Super]))
+    );
+    Ok(())
+}
+
 #[test]
 fn test_relative_import() -> Result<(), IoOrParseError> {
     // create a temporary file in the working directory
@@ -114,6 +148,50 @@ super + b
     Ok(())
 }
 
+#[test]
+fn test_include_relative_to_including_file() -> Result<(), IoOrParseError> {
+    let dir = tempfile::tempdir()?;
+    let root = dir.path().join("root");
+    let first = dir.path().join("first");
+    let second = first.join("second");
+    std::fs::create_dir_all(&root)?;
+    std::fs::create_dir_all(&second)?;
+    std::fs::write(
+        root.join("swhkdrc"),
+        "include ../first/extra.swhkd\ninclude ../first/./second/../second/more.swhkd\nsuper + a\n    root",
+    )?;
+    std::fs::write(
+        first.join("extra.swhkd"),
+        "include second/more.swhkd\nsuper + b\n    first",
+    )?;
+    std::fs::write(
+        second.join("more.swhkd"),
+        "include ../extra.swhkd\nsuper + c\n    second",
+    )?;
+
+    let parsed = SwhkdParser::from(ParserInput::Path(&root.join("swhkdrc")))?;
+    let known = [
+        Binding::running("root").on(Definition::new(evdev::Key::KEY_A).with_modifiers(&[Super])),
+        Binding::running("first").on(Definition::new(evdev::Key::KEY_B).with_modifiers(&[Super])),
+        Binding::running("second").on(Definition::new(evdev::Key::KEY_C).with_modifiers(&[Super])),
+    ];
+    assert_eq!(parsed.bindings, known);
+    assert_eq!(
+        parsed.imports,
+        [first.join("extra.swhkd"), second.join("more.swhkd")]
+            .map(|path| path.canonicalize().unwrap().display().to_string())
+            .into()
+    );
+
+    // Raw configs have no file, their includes are relative to the working
+    // directory.
+    let parsed = SwhkdParser::from(ParserInput::Raw(
+        "include tests/fixtures/report_include.swhkd",
+    ))?;
+    assert_eq!(parsed.bindings.len(), 1);
+    Ok(())
+}
+
 #[test]
 fn test_circular_import() -> Result<(), IoOrParseError> {
     let mut setup = tempfile::NamedTempFile::new()?;
@@ -200,20 +278,16 @@ ignore super + d",
 }
 
 #[test]
-fn test_basic_keybind() -> Result<(), ParseError> {
+fn test_basic_keybind() {
     let contents = "
 r
     alacritty
             ";
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
-    let known = [Binding::running("alacritty").on(Definition::new(evdev::Key::KEY_R))];
-
-    assert_eq!(parsed.bindings, known);
-    Ok(())
+    assert_parses_to!(contents, [binding!("r" => "alacritty")]);
 }
 
 #[test]
-fn test_multiple_keybinds() -> Result<(), ParseError> {
+fn test_multiple_keybinds() {
     let contents = "
 r
     alacritty
@@ -224,21 +298,18 @@ w
 t
     /bin/firefox
         ";
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
-
-    let known = [
-        Binding::running("alacritty").on(Definition::new(evdev::Key::KEY_R)),
-        Binding::running("kitty").on(Definition::new(evdev::Key::KEY_W)),
-        Binding::running("/bin/firefox").on(Definition::new(evdev::Key::KEY_T)),
-    ];
-
-    assert_eq!(parsed.bindings, known);
-
-    Ok(())
+    assert_parses_to!(
+        contents,
+        [
+            binding!("r" => "alacritty"),
+            binding!("w" => "kitty"),
+            binding!("t" => "/bin/firefox"),
+        ]
+    );
 }
 
 #[test]
-fn test_comments() -> Result<(), ParseError> {
+fn test_comments() {
     let contents = "
 r
     alacritty
@@ -249,20 +320,65 @@ w
 #t
     #/bin/firefox
         ";
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    assert_parses_to!(
+        contents,
+        [binding!("r" => "alacritty"), binding!("w" => "kitty")]
+    );
+}
 
-    let known = vec![
-        Binding::running("alacritty").on(Definition::new(evdev::Key::KEY_R)),
-        Binding::running("kitty").on(Definition::new(evdev::Key::KEY_W)),
-    ];
+#[test]
+fn test_binding_descriptions() -> Result<(), ParseError> {
+    let contents = "
+# Open a terminal
+super + return
+    alacritty
+super + w
+    firefox
+# Close the window
 
-    assert_eq!(parsed.bindings, known);
+super + q
+    close
+
+#   Focus a workspace
+## by number
+super + {1-2}
+    workspace {1-2}
 
+# Resize windows
+mode resize
+    # Grow
+    h
+        grow
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let descriptions: Vec<_> = parsed.bindings.iter().map(Binding::description).collect();
+    assert_eq!(
+        descriptions,
+        [
+            Some("Open a terminal"),
+            None,
+            None,
+            Some("Focus a workspace\nby number"),
+            Some("Focus a workspace\nby number"),
+        ]
+    );
+    assert_eq!(
+        parsed.modes[0].description.as_deref(),
+        Some("Resize windows")
+    );
+    assert_eq!(parsed.modes[0].bindings[0].description(), Some("Grow"));
+
+    // Descriptions are written back as comments.
+    let reparsed = SwhkdParser::from(ParserInput::Raw(&parsed.to_config_string()))?;
+    let redescribed: Vec<_> = reparsed.bindings.iter().map(Binding::description).collect();
+    assert_eq!(redescribed, descriptions);
+    assert_eq!(reparsed.modes[0].description, parsed.modes[0].description);
+    assert_eq!(reparsed.modes[0].bindings[0].description(), Some("Grow"));
     Ok(())
 }
 
 #[test]
-fn test_greedy_ordering() -> Result<(), ParseError> {
+fn test_greedy_ordering() {
     let contents = "
 f9
     librewolf
@@ -270,16 +386,10 @@ f9
 f12
     kitty
         ";
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
-
-    let known = vec![
-        Binding::running("librewolf").on(Definition::new(evdev::Key::KEY_F9)),
-        Binding::running("kitty").on(Definition::new(evdev::Key::KEY_F12)),
-    ];
-
-    assert_eq!(parsed.bindings, known);
-
-    Ok(())
+    assert_parses_to!(
+        contents,
+        [binding!("f9" => "librewolf"), binding!("f12" => "kitty")]
+    );
 }
 
 #[test]
@@ -289,7 +399,7 @@ fn test_commented_out_keybind() {
     let contents = "
 #w
     gimp";
-    assert_grammar_error_at(&contents, (3, 6));
+    assert_parse_error!(contents, at: (3, 6));
 }
 
 #[test]
@@ -300,7 +410,7 @@ super + a #comment and comment super
 super + shift + b
     ts #this comment should be handled by shell
 ";
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
     let known = [
         Binding::running("st").on(Definition::new(evdev::Key::KEY_A).with_modifiers(&[Super])),
         Binding::running("ts #this comment should be handled by shell")
@@ -315,7 +425,7 @@ super + shift + b
 #[test]
 fn test_blank_config() -> Result<(), ParseError> {
     let contents = "";
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
     assert_eq!(parsed.bindings, vec![]);
     Ok(())
 }
@@ -326,25 +436,18 @@ fn test_blank_config_with_whitespace() -> Result<(), ParseError> {
 
 
             ";
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
     assert_eq!(parsed.bindings, []);
     Ok(())
 }
 
 #[test]
-fn test_multiple_keypress() -> Result<(), ParseError> {
+fn test_multiple_keypress() {
     let contents = "
 super + 5
     alacritty
         ";
-
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
-    let known = [Binding::running("alacritty")
-        .on(Definition::new(evdev::Key::KEY_5).with_modifiers(&[Super]))];
-
-    assert_eq!(parsed.bindings, known);
-
-    Ok(())
+    assert_parses_to!(contents, [binding!("super + 5" => "alacritty")]);
 }
 
 #[test]
@@ -354,11 +457,7 @@ shift + k + m
     notify-send 'Hello world!'
             ";
 
-    let parse_result = SwhkdParser::from(ParserInput::Raw(&contents));
-    let Err(ParseError::Grammar(e)) = parse_result else {
-        panic!("expected grammar parse error")
-    };
-    assert_eq!(e.line_col, Pos((2, 11)));
+    assert_parse_error!(contents, at: (2, 11));
 }
 
 #[test]
@@ -367,7 +466,7 @@ fn test_modifier_instead_of_keysym() {
 shift + k + alt
     notify-send 'Hello world!'
             ";
-    assert_grammar_error_at(&contents, (2, 11));
+    assert_parse_error!(contents, at: (2, 11));
 }
 
 #[test]
@@ -378,7 +477,7 @@ fn test_unfinished_plus_sign() {
 shift + alt +
     notify-send 'Hello world!'
             ";
-    assert_grammar_error_at(&contents, (4, 14));
+    assert_parse_error!(contents, at: (4, 14));
 }
 
 #[test]
@@ -387,11 +486,11 @@ fn test_plus_sign_at_start() {
 + shift + k
     notify-send 'Hello world!'
             ";
-    assert_grammar_error_at(&contents, (2, 1));
+    assert_parse_error!(contents, at: (2, 1));
 }
 
 #[test]
-fn test_common_modifiers() -> Result<(), ParseError> {
+fn test_common_modifiers() {
     let contents = "
 shift + k
     notify-send 'Hello world!'
@@ -408,20 +507,17 @@ altgr + i
 super + z
     notify-send 'Hello world!'
             ";
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
-
     let command = "notify-send 'Hello world!'";
-    let known = [
-        Binding::running(command).on(Definition::new(evdev::Key::KEY_K).with_modifiers(&[Shift])),
-        Binding::running(command).on(Definition::new(evdev::Key::KEY_5).with_modifiers(&[Control])),
-        Binding::running(command).on(Definition::new(evdev::Key::KEY_2).with_modifiers(&[Alt])),
-        Binding::running(command).on(Definition::new(evdev::Key::KEY_I).with_modifiers(&[Altgr])),
-        Binding::running(command).on(Definition::new(evdev::Key::KEY_Z).with_modifiers(&[Super])),
-    ];
-
-    assert_eq!(parsed.bindings, known);
-
-    Ok(())
+    assert_parses_to!(
+        contents,
+        [
+            binding!("shift + k" => command),
+            binding!("control + 5" => command),
+            binding!("alt + 2" => command),
+            binding!("altgr + i" => command),
+            binding!("super + z" => command),
+        ]
+    );
 }
 
 #[test]
@@ -430,7 +526,7 @@ fn test_command_with_many_spaces() -> Result<(), ParseError> {
 p
     xbacklight -inc 10 -fps 30 -time 200
         ";
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
     let known = [Binding::running("xbacklight -inc 10 -fps 30 -time 200")
         .on(Definition::new(evdev::Key::KEY_P))];
 
@@ -448,7 +544,7 @@ p
 pesto
     xterm
                     ";
-    assert_grammar_error_at(&contents, (5, 2));
+    assert_parse_error!(contents, at: (5, 2));
 }
 
 #[test]
@@ -463,7 +559,7 @@ w
 
                     ";
 
-    assert!(SwhkdParser::from(ParserInput::Raw(&contents)).is_err());
+    assert!(SwhkdParser::from(ParserInput::Raw(contents)).is_err());
 }
 
 #[test]
@@ -491,7 +587,7 @@ ctrl + 0
 super + minus
     play-song.sh album
                     ";
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
 
     let known = vec![
         Binding::running("pkill -USR1 -x sxhkd ; sxhkd &")
@@ -514,6 +610,127 @@ super + minus
     Ok(())
 }
 
+#[test]
+fn test_config_builder() -> Result<(), ParseError> {
+    // The config of `test_real_config_snippet`, along with a mode.
+    let contents = "
+super + Escape
+    pkill -USR1 -x sxhkd ; sxhkd &
+super + Return
+    alacritty -t \"Terminal\" -e \"$HOME/.config/sxhkd/new_tmux_terminal.sh\"
+super + shift + Return
+    alacritty -t \"Terminal\"
+alt + Return
+    alacritty -t \"Terminal\" -e \"tmux\"
+ctrl + 0
+    play-song.sh
+super + minus
+    play-song.sh album
+super + minus
+    play-song.sh artist
+ignore ctrl + 0
+super + r
+    @enter resize
+mode resize oneoff swallow
+    h
+        bspc node -z left -20 0
+    ignore super + minus
+    replay alt + tab
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let key = Definition::new;
+    let built = ConfigBuilder::new()
+        .binding(
+            key(evdev::Key::KEY_ESC).with_modifiers(&[Super]),
+            "pkill -USR1 -x sxhkd ; sxhkd &",
+        )
+        .binding(
+            key(evdev::Key::KEY_ENTER).with_modifiers(&[Super]),
+            "alacritty -t \"Terminal\" -e \"$HOME/.config/sxhkd/new_tmux_terminal.sh\"",
+        )
+        .binding(
+            key(evdev::Key::KEY_ENTER).with_modifiers(&[Super, Shift]),
+            "alacritty -t \"Terminal\"",
+        )
+        .binding(
+            key(evdev::Key::KEY_ENTER).with_modifiers(&[Alt]),
+            "alacritty -t \"Terminal\" -e \"tmux\"",
+        )
+        .binding(
+            key(evdev::Key::KEY_0).with_modifiers(&[Control]),
+            "play-song.sh",
+        )
+        .binding(
+            key(evdev::Key::KEY_MINUS).with_modifiers(&[Super]),
+            "play-song.sh album",
+        )
+        .binding(
+            key(evdev::Key::KEY_MINUS).with_modifiers(&[Super]),
+            "play-song.sh artist",
+        )
+        .unbind(key(evdev::Key::KEY_0).with_modifiers(&[Control]))
+        .with_binding(
+            Binding::running("")
+                .with_mode_instructions(vec![ModeInstruction::Enter("resize".to_string())])
+                .on(key(evdev::Key::KEY_R).with_modifiers(&[Super])),
+        )
+        .mode("resize", |mode| {
+            mode.oneoff()
+                .swallow()
+                .binding(key(evdev::Key::KEY_H), "bspc node -z left -20 0")
+                .unbind(key(evdev::Key::KEY_MINUS).with_modifiers(&[Super]))
+                .replay(key(evdev::Key::KEY_TAB).with_modifiers(&[Alt]))
+        })
+        .build()?;
+    assert_eq!(built.bindings, parsed.bindings);
+    assert_eq!(built.overridden, parsed.overridden);
+    assert_eq!(built.unbinds, parsed.unbinds);
+    let [built_mode] = &built.modes[..] else {
+        panic!("expected one mode, got {:?}", built.modes);
+    };
+    let mode = &parsed.modes[0];
+    assert_eq!(
+        (&built_mode.name, built_mode.oneoff, built_mode.swallow),
+        (&mode.name, mode.oneoff, mode.swallow)
+    );
+    assert_eq!(built_mode.bindings, mode.bindings);
+    assert_eq!(built_mode.unbinds, mode.unbinds);
+    assert_eq!(built_mode.replay, mode.replay);
+    assert_eq!(built.to_config_string(), parsed.to_config_string());
+    Ok(())
+}
+
+#[test]
+fn test_config_builder_validation() {
+    let invalid = |builder: ConfigBuilder| match builder.build() {
+        Err(ParseError::InvalidItem(message)) => message,
+        other => panic!("expected an invalid item, got {other:?}"),
+    };
+    let message = invalid(ConfigBuilder::new().mode("two words", |mode| mode));
+    assert!(
+        message.contains("`two words` is not a valid mode name"),
+        "{message}"
+    );
+    let virtual_key = Definition::virtual_key("lid").with_modifiers(&[Super]);
+    let message = invalid(ConfigBuilder::new().binding(virtual_key, "true"));
+    assert!(
+        message.contains("cannot be combined with virtual keys"),
+        "{message}"
+    );
+    let settings = Settings {
+        start_mode: Some("resize".to_string()),
+        ..Default::default()
+    };
+    let message = invalid(ConfigBuilder::new().mode("resize", |mode| mode.with_settings(settings)));
+    assert!(message.starts_with("mode `resize`"), "{message}");
+
+    let enter = Binding::running("")
+        .with_mode_instructions(vec![ModeInstruction::Enter("resize".to_string())])
+        .on(Definition::new(evdev::Key::KEY_R));
+    let result = ConfigBuilder::new().with_binding(enter).build();
+    assert!(matches!(result, Err(ParseError::UnknownMode { name, .. }) if name == "resize"));
+}
+
 #[test]
 fn test_multiline_command() -> Result<(), ParseError> {
     let contents = "
@@ -521,7 +738,7 @@ k
     mpc ls | dmenu | \\
     sed -i 's/foo/bar/g'
                     ";
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
 
     let known = [Binding::running("mpc ls | dmenu | sed -i 's/foo/bar/g'")
         .on(Definition::new(evdev::Key::KEY_K))];
@@ -531,47 +748,156 @@ k
 }
 
 #[test]
-fn test_case_insensitive() -> Result<(), ParseError> {
+fn test_continuation_in_shorthands() -> Result<(), ParseError> {
+    let single = "
+super + {a, b, c}
+    {echo 'a', echo 'b', echo 'c'}";
+    let continued = "
+super + {a, \\
+         b, \\
+         c}
+    {echo 'a', \\
+     echo 'b' \\
+     , echo 'c'\\
+    }";
+    let parsed = SwhkdParser::from(ParserInput::Raw(single))?;
+    assert_eq!(parsed.bindings.len(), 3);
+    assert_eq!(
+        SwhkdParser::from(ParserInput::Raw(continued))?.bindings,
+        parsed.bindings
+    );
+    Ok(())
+}
+
+#[test]
+fn test_braces_across_continuations() {
+    let valid = "super + {a,b}\n    {echo a, \\\n     echo b}";
+    assert!(SwhkdParser::from(ParserInput::Raw(valid)).is_ok());
+    // The brace is closed on the continued line, `a-b` is what fails.
+    let closed = "super + {a,b}\n    {echo a-b, \\\n     echo b}";
+    let err = SwhkdParser::from(ParserInput::Raw(closed)).unwrap_err();
+    assert!(!Diagnostic::from(&err)
+        .render(RenderStyle::Plain)
+        .contains("unclosed"));
+    assert_parse_error!(closed, at: (2, 12), contains: "shorthand_deny");
+    let unclosed = "super + {a,b}\n    echo a \\\n     {b, c\nsuper + d\n    true";
+    assert_parse_error!(
+        unclosed,
+        span: (3, 6)..(3, 7),
+        contains: "unclosed '{' opened at column 6"
+    );
+}
+
+#[test]
+fn test_lint_command() {
+    use CommandLint::*;
+    assert_eq!(lint_command("notify-send 'hello"), [UnclosedQuote('\'')]);
+    assert_eq!(lint_command("notify-send \"hello"), [UnclosedQuote('"')]);
+    assert_eq!(lint_command("echo `date"), [UnclosedQuote('`')]);
+    assert_eq!(lint_command("echo $(date"), [UnclosedBracket('(')]);
+    assert_eq!(lint_command("{ foo; bar;"), [UnclosedBracket('{')]);
+    assert_eq!(
+        lint_command("echo ${HOME)"),
+        [UnmatchedBracket(')'), UnclosedBracket('{')]
+    );
+    assert_eq!(lint_command("echo done }"), [UnmatchedBracket('}')]);
+    assert_eq!(lint_command("echo hi \\"), [TrailingBackslash]);
+    assert_eq!(lint_command("  "), [Empty]);
+    assert_eq!(lint_command("echo '(' \"it's\" \\' \\( # don't"), []);
+    assert_eq!(lint_command("echo \"$(date +%s)\" \\\\"), []);
+    assert_eq!(lint_command("echo $# 'a#b'"), []);
+}
+
+#[test]
+fn test_validate_commands() -> Result<(), ParseError> {
+    let options = ParseOptions::default().with_validate_commands(true);
+    let parse = |config| SwhkdParser::from_with_options(ParserInput::Raw(config), &options);
+    // The commands of other tests, with pipes, quotes and continuations.
+    let valid = "
+k
+    mpc ls | dmenu | \\
+    sed -i 's/foo/bar/g'
+super + Escape
+    pkill -USR1 -x sxhkd ; sxhkd &
+super + Return
+    alacritty -t \"Terminal\" -e \"$HOME/.config/sxhkd/new_tmux_terminal.sh\"
+super + {a, b, c}
+    {echo 'a', echo 'b', echo 'c'}
+super + r
+    @enter resize
+mode resize
+    h
+        bspc node -z left -20 0
+    escape
+        @escape
+endmode";
+    assert_eq!(parse(valid)?.warnings, []);
+
+    let config = "
+super + a
+    notify-send 'hello
+super + {b,c}
+    {firefox,_}
+super + d
+    echo $(date";
+    let parsed = parse(config)?;
+    let lints: Vec<_> = parsed
+        .warnings
+        .iter()
+        .map(|warning| match warning {
+            Warning::CommandSyntax { span, lint } => (span.line, *lint),
+            warning => panic!("unexpected warning {warning:?}"),
+        })
+        .collect();
+    assert_eq!(
+        lints,
+        [
+            (2, CommandLint::UnclosedQuote('\'')),
+            (4, CommandLint::Empty),
+            (6, CommandLint::UnclosedBracket('(')),
+        ]
+    );
+    assert!(parsed.warnings[0]
+        .to_string()
+        .contains("`'` is never closed, the shell will likely fail to run this command"));
+    // Off by default.
+    assert_eq!(SwhkdParser::from(ParserInput::Raw(config))?.warnings, []);
+    Ok(())
+}
+
+#[test]
+fn test_case_insensitive() {
     let contents = "
 Super + SHIFT + alt + a
     st
 ReTurn
     ts
             ";
-    let known = vec![
-        Binding::running("st")
-            .on(Definition::new(evdev::Key::KEY_A).with_modifiers(&[Super, Shift, Alt])),
-        Binding::running("ts").on(Definition::new(evdev::Key::KEY_ENTER)),
-    ];
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
-    assert_eq!(parsed.bindings, known);
-    Ok(())
+    assert_parses_to!(
+        contents,
+        [
+            binding!("super + shift + alt + a" => "st"),
+            binding!("return" => "ts"),
+        ]
+    );
 }
 
 #[test]
-fn test_override() -> Result<(), ParseError> {
+fn test_override() {
     let contents = "
 super + a
     1
 super + a
     2";
-    let known =
-        vec![Binding::running("2").on(Definition::new(evdev::Key::KEY_A).with_modifiers(&[Super]))];
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
-    assert_eq!(parsed.bindings, known);
-    Ok(())
+    assert_parses_to!(contents, [binding!("super + a" => "2")]);
 }
 
 #[test]
-fn test_any_modifier() -> Result<(), ParseError> {
+fn test_any_modifier() {
     let contents = "
 any + a
     1";
-    let known =
-        vec![Binding::running("1").on(Definition::new(evdev::Key::KEY_A).with_modifiers(&[Any]))];
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
-    assert_eq!(parsed.bindings, known);
-    Ok(())
+    assert_parses_to!(contents, [binding!("any + a" => "1")]);
 }
 
 #[test]
@@ -591,7 +917,7 @@ B
             .on(Definition::new(evdev::Key::KEY_A).with_modifiers(&[Super, Shift])),
         Binding::running("ts").on(Definition::new(evdev::Key::KEY_B)),
     ];
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
     assert_eq!(parsed.bindings, known);
     Ok(())
 }
@@ -608,7 +934,7 @@ super + {a-c}
         Binding::running("librewolf")
             .on(Definition::new(evdev::Key::KEY_C).with_modifiers(&[Super])),
     ];
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
     assert_eq!(parsed.bindings, known);
     Ok(())
 }
@@ -619,7 +945,7 @@ fn test_range_syntax_not_ascii() {
 super + {a-是}
     {firefox, brave}
     ";
-    assert_grammar_error_at(contents, (2, 12));
+    assert_parse_error!(contents, at: (2, 12));
 }
 
 #[test]
@@ -641,7 +967,7 @@ super + {shift,alt} + {c,d}
         Binding::running("firefox --help")
             .on(Definition::new(evdev::Key::KEY_D).with_modifiers(&[Super, Alt])),
     ];
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
     assert_eq!(parsed.bindings, known);
     Ok(())
 }
@@ -670,7 +996,7 @@ fn test_multiple_ranges() -> Result<(), ParseError> {
         Binding::running("echo are")
             .on(Definition::new(evdev::Key::KEY_3).with_modifiers(&[Super])),
     ];
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
     assert_eq!(parsed.bindings, known);
     Ok(())
 }
@@ -687,7 +1013,7 @@ super + {a,b,c}
         Binding::running("librewolf")
             .on(Definition::new(evdev::Key::KEY_C).with_modifiers(&[Super])),
     ];
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
     assert_eq!(parsed.bindings, known);
     Ok(())
 }
@@ -708,7 +1034,7 @@ super + {h,j,k,l}
         Binding::running("bspc node -p east")
             .on(Definition::new(evdev::Key::KEY_L).with_modifiers(&[Super])),
     ];
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
     assert_eq!(parsed.bindings, known);
     Ok(())
 }
@@ -719,7 +1045,7 @@ fn test_invalid_curly_brace_config() -> Result<(), ParseError> {
 super + }a,b,c{
     {firefox, brave, librewolf}
     ";
-    assert_grammar_error_at(contents, (2, 9));
+    assert_parse_error!(contents, at: (2, 9));
     Ok(())
 }
 
@@ -730,7 +1056,7 @@ fn test_curly_brace_less_commands() -> Result<(), ParseError> {
 super + {a,b,c}
     {firefox, brave}
     ";
-    assert_grammar_error_at_span(contents, (2, 1), (3, 21));
+    assert_parse_error!(contents, span: (3, 5)..(3, 21));
     Ok(())
 }
 
@@ -741,7 +1067,7 @@ fn test_curly_brace_less_keysyms() -> Result<(), ParseError> {
 super + {a,b}
     {firefox, brave, librewolf}
     ";
-    assert_grammar_error_at_span(contents, (2, 1), (3, 32));
+    assert_parse_error!(contents, span: (3, 5)..(3, 32));
     Ok(())
 }
 
@@ -753,23 +1079,128 @@ fn test_curly_brace_single_variant() -> Result<(), ParseError> {
 super + {a}
     {firefox}
     ";
-    assert_grammar_error_at(contents, (2, 10));
+    assert_parse_error!(contents, at: (2, 10));
     Ok(())
 }
 
 #[test]
-fn test_omission() -> Result<(), ParseError> {
+fn test_omission() {
     let contents = "
 super + {_, shift +} b
     {firefox, brave}";
-    let known = [
-        Binding::running("firefox").on(Definition::new(evdev::Key::KEY_B).with_modifiers(&[Super])),
-        Binding::running("brave")
-            .on(Definition::new(evdev::Key::KEY_B).with_modifiers(&[Super, Shift])),
-    ];
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
-    assert_eq!(parsed.bindings, known);
-    Ok(())
+    assert_parses_to!(
+        contents,
+        [
+            binding!("super + b" => "firefox"),
+            binding!("super + shift + b" => "brave"),
+        ]
+    );
+}
+
+#[test]
+fn test_modifier_groups_in_shorthand() {
+    let contents = "
+super + {_, ctrl, ctrl + shift} + a
+    {one, two, three}";
+    assert_parses_to!(
+        contents,
+        [
+            binding!("super + a" => "one"),
+            binding!("super + ctrl + a" => "two"),
+            binding!("super + ctrl + shift + a" => "three"),
+        ]
+    );
+    let contents = "
+{_, alt + shift +} {_, ctrl +} b
+    {one, two, three, four}";
+    assert_parses_to!(
+        contents,
+        [
+            binding!("b" => "one"),
+            binding!("ctrl + b" => "two"),
+            binding!("alt + shift + b" => "three"),
+            binding!("alt + shift + ctrl + b" => "four"),
+        ]
+    );
+}
+
+#[test]
+fn test_omission_in_commands() {
+    // Dashes are escaped in shorthands, which take them for ranges.
+    assert_parses_to!(
+        "super + {_, shift +} q\n    bspc node -c {_, \\-\\-force}",
+        [
+            binding!("super + q" => "bspc node -c "),
+            binding!("super + shift + q" => "bspc node -c --force"),
+        ]
+    );
+    assert_parses_to!(
+        "super + {a-c}\n    echo {one,_,three} done",
+        [
+            binding!("super + a" => "echo one done"),
+            binding!("super + b" => "echo  done"),
+            binding!("super + c" => "echo three done"),
+        ]
+    );
+    assert_parses_to!(
+        "super + {a-c}\n    echo {one,two,_}",
+        [
+            binding!("super + a" => "echo one"),
+            binding!("super + b" => "echo two"),
+            binding!("super + c" => "echo "),
+        ]
+    );
+    // An escaped underscore runs as is, and underscores within a variant
+    // are left alone.
+    assert_parses_to!(
+        "super + {a-c}\n    echo {\\_, a_b, _}",
+        [
+            binding!("super + a" => "echo _"),
+            binding!("super + b" => "echo a_b"),
+            binding!("super + c" => "echo "),
+        ]
+    );
+    assert_parse_error!(
+        "super + {a, b}\n    echo {_, _, _}",
+        span: (2, 10)..(2, 19),
+        contains: "the number of possible binding variants 2 does not equal the number of possible command variants 3"
+    );
+}
+
+#[test]
+fn test_nested_command_shorthands() {
+    assert_parses_to!(
+        "super + {a-d}\n    echo {foo, bar{1-3}}",
+        [
+            binding!("super + a" => "echo foo"),
+            binding!("super + b" => "echo bar1"),
+            binding!("super + c" => "echo bar2"),
+            binding!("super + d" => "echo bar3"),
+        ]
+    );
+    assert_parses_to!(
+        "super + {a-c}\n    notify-send {a,{b,c}x}",
+        [
+            binding!("super + a" => "notify-send a"),
+            binding!("super + b" => "notify-send bx"),
+            binding!("super + c" => "notify-send cx"),
+        ]
+    );
+    // Escaped braces in a nested variant are text.
+    assert_parses_to!(
+        "super + {a-c}\n    echo {\\{, x{\\}, y}}",
+        [
+            binding!("super + a" => "echo {"),
+            binding!("super + b" => "echo x}"),
+            binding!("super + c" => "echo xy"),
+        ]
+    );
+    // Nested variants count towards the total.
+    assert_parse_error!(
+        "super + {a-c}\n    echo {foo, bar{1-3}}",
+        span: (2, 10)..(2, 25),
+        contains: "the number of possible binding variants 3 does not equal the number of possible command variants 4"
+    );
 }
 
 #[test]
@@ -800,7 +1231,7 @@ super + {1-9,0}
         Binding::running("bspc desktop -f '0'")
             .on(Definition::new(evdev::Key::KEY_0).with_modifiers(&[Super])),
     ];
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
     assert_eq!(parsed.bindings, known);
     Ok(())
 }
@@ -811,7 +1242,7 @@ fn test_range_syntax_invalid_range() {
 super + {bc-ad}
     {firefox, brave}
     ";
-    assert_grammar_error_at(contents, (2, 10));
+    assert_parse_error!(contents, at: (2, 10));
 }
 
 #[test]
@@ -819,39 +1250,214 @@ fn test_ranger_syntax_not_full_range() {
     let contents = "
 super + {a-}
     {firefox, brave}";
-    assert_grammar_error_at(contents, (2, 12));
+    assert_parse_error!(contents, at: (2, 12));
+}
+
+#[test]
+fn test_reverse_and_stepped_ranges() {
+    assert_parses_to!(
+        "super + {3-1}\n    workspace {1-3}",
+        [
+            binding!("super + 3" => "workspace 1"),
+            binding!("super + 2" => "workspace 2"),
+            binding!("super + 1" => "workspace 3"),
+        ]
+    );
+    assert_parses_to!(
+        "super + {1-9:3}\n    echo {z-a:12}",
+        [
+            binding!("super + 1" => "echo z"),
+            binding!("super + 4" => "echo n"),
+            binding!("super + 7" => "echo b"),
+        ]
+    );
+    assert_parse_error!(
+        "super + {1-9:4}\n    echo {1-4}",
+        span: (2, 10)..(2, 15),
+        contains: "the number of possible binding variants 3 does not equal the number of possible command variants 4"
+    );
+}
+
+#[test]
+fn test_invalid_reverse_and_stepped_ranges() {
+    assert_parse_error!(
+        "super + {a-5}\n    echo {a, b}",
+        span: (1, 10)..(1, 13),
+        contains: "shorthand range `a-5` goes from a lowercase letter to a digit"
+    );
+    assert_parse_error!(
+        "super + {a, b}\n    echo {B-a}",
+        span: (2, 11)..(2, 14),
+        contains: "shorthand range `B-a` goes from an uppercase letter to a lowercase letter"
+    );
+    assert_parse_error!(
+        "super + {1-3:0}\n    echo {a-c}",
+        span: (1, 13)..(1, 15),
+        contains: "the step of a range must be at least 1"
+    );
+}
+
+#[test]
+fn test_range_bounds_validation() {
+    assert_parse_error!(
+        "super + {5-a}\n    echo",
+        span: (1, 10)..(1, 13),
+        contains: "shorthand range `5-a` goes from a digit to a lowercase letter"
+    );
+    // Named keys have no order to range over.
+    assert_parse_error!(
+        "super + {comma-period}\n    echo",
+        span: (1, 10)..(1, 15),
+        contains: "shorthand lower bound `comma` is not a single character, list the keys instead, e.g. `{comma, period}`"
+    );
+    // Letters are keys whatever their case.
+    assert_parses_to!(
+        "super + {A-C}\n    echo {A-C}",
+        [
+            binding!("super + a" => "echo A"),
+            binding!("super + b" => "echo B"),
+            binding!("super + c" => "echo C"),
+        ]
+    );
+    assert_parses_to!(
+        "super + {Z-x}\n    echo {z-x}",
+        [
+            binding!("super + z" => "echo z"),
+            binding!("super + y" => "echo y"),
+            binding!("super + x" => "echo x"),
+        ]
+    );
+}
+
+#[test]
+fn test_ranges_expand_alike() -> Result<(), ParseError> {
+    // Keys of a range keep the attributes of its bounds, in bindings and
+    // in `ignore` statements alike.
+    let parsed = SwhkdParser::from(ParserInput::Raw(
+        "super + {@1-@2}\n    echo {1-2}\nignore alt + {~a-~b}",
+    ))?;
+    let released = |key| {
+        Definition::new(key)
+            .with_modifiers(&[Super])
+            .with_attribute(KeyAttribute::OnRelease)
+    };
+    assert_eq!(
+        parsed.bindings,
+        [
+            Binding::running("echo 1").on(released(evdev::Key::KEY_1)),
+            Binding::running("echo 2").on(released(evdev::Key::KEY_2)),
+        ]
+    );
+    for (unbind, key) in parsed
+        .unbinds
+        .iter()
+        .zip([evdev::Key::KEY_A, evdev::Key::KEY_B])
+    {
+        assert_eq!(
+            unbind,
+            &Definition::new(key)
+                .with_modifiers(&[Alt])
+                .with_attribute(KeyAttribute::Send)
+        );
+    }
+
+    // Bounds are unescaped like the other variants of a command shorthand,
+    // and must be single characters in chords and commands alike.
+    assert_parses_to!(
+        "super + {a-c}\n    echo {\\{-\\}}",
+        [
+            binding!("super + a" => "echo {"),
+            binding!("super + b" => "echo |"),
+            binding!("super + c" => "echo }"),
+        ]
+    );
+    assert_parse_error!(
+        "super + {a-return}\n    echo",
+        span: (1, 12)..(1, 18),
+        contains: "shorthand upper bound `return` is not a single character",
+    );
+    Ok(())
+}
+
+#[test]
+fn test_errors_after_expansion_point_at_the_element() {
+    // A key of a shorthand at fault with the attributes of the shorthand.
+    assert_parse_error!(
+        "super + @{a, scroll_down}\n    {a, b}",
+        span: (1, 14)..(1, 25),
+        contains: "scroll events have no press or release"
+    );
+    // A bound of a range in a chord or in a command, rather than the range.
+    assert_parse_error!(
+        "super + {a, f1-f3}\n    echo",
+        span: (1, 13)..(1, 15),
+        contains: "shorthand lower bound `f1` is not a single character"
+    );
+    assert_parse_error!(
+        "super + {a, b}\n    echo {x-ÿ}",
+        span: (2, 13)..(2, 14),
+        contains: "shorthand upper bound `ÿ` is not an ASCII character"
+    );
+    // A range across classes of characters, on both bounds.
+    assert_parse_error!(
+        "super + {a, 0-a}\n    echo",
+        span: (1, 13)..(1, 16),
+        contains: "shorthand range `0-a` goes from a digit to a lowercase letter"
+    );
+    // The shorthands of the command line whose variants do not match.
+    assert_parse_error!(
+        "super + {a, b}\n    echo {1, 2}\n    notify-send {x, y} {z, w}",
+        span: (3, 17)..(3, 30),
+        contains: "binding variants 2 does not equal the number of possible command variants 4"
+    );
+    // A held key of a shorthand.
+    let options = ParseOptions::default().with_multi_key_chords(true);
+    let config = "{a, @b} & e\n    {true, false}";
+    let Err(ParseError::Grammar(err)) =
+        SwhkdParser::from_with_options(ParserInput::Raw(config), &options)
+    else {
+        panic!("expected a grammar error");
+    };
+    assert_eq!(
+        err.line_col,
+        pest::error::LineColLocation::Span((1, 5), (1, 7))
+    );
+
+    // Commands expanding blocks are not written as such, a mismatch in one
+    // is reported on the whole binding.
+    assert_parse_error!(
+        "block echo\n    echo {1, 2, 3}\nendblock\nsuper + {a, b}\n    &echo",
+        span: (4, 1)..(5, 10),
+        contains: "binding variants 2 does not equal the number of possible command variants 3"
+    );
 }
 
 #[test]
-fn test_period_escape_binding() -> Result<(), ParseError> {
+fn test_period_escape_binding() {
     let contents = "
 super + {\\,, .}
 	riverctl focus-output {previous, next}";
-    let known = vec![
-        Binding::running("riverctl focus-output previous")
-            .on(Definition::new(evdev::Key::KEY_COMMA).with_modifiers(&[Super])),
-        Binding::running("riverctl focus-output next")
-            .on(Definition::new(evdev::Key::KEY_DOT).with_modifiers(&[Super])),
-    ];
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
-    assert_eq!(parsed.bindings, known);
-    Ok(())
+    assert_parses_to!(
+        contents,
+        [
+            binding!("super + comma" => "riverctl focus-output previous"),
+            binding!("super + period" => "riverctl focus-output next"),
+        ]
+    );
 }
 
 #[test]
-fn test_period_binding() -> Result<(), ParseError> {
+fn test_period_binding() {
     let contents = "
 super + {comma, period}
 	riverctl focus-output {previous, next}";
-    let known = vec![
-        Binding::running("riverctl focus-output previous")
-            .on(Definition::new(evdev::Key::KEY_COMMA).with_modifiers(&[Super])),
-        Binding::running("riverctl focus-output next")
-            .on(Definition::new(evdev::Key::KEY_DOT).with_modifiers(&[Super])),
-    ];
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
-    assert_eq!(parsed.bindings, known);
-    Ok(())
+    assert_parses_to!(
+        contents,
+        [
+            binding!("super + ," => "riverctl focus-output previous"),
+            binding!("super + ." => "riverctl focus-output next"),
+        ]
+    );
 }
 
 #[test]
@@ -877,7 +1483,7 @@ super + {_,shift + }{h,j,k,l}
         Binding::running("bspc node -s east")
             .on(Definition::new(evdev::Key::KEY_L).with_modifiers(&[Super, Shift])),
     ];
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
     assert_eq!(parsed.bindings, known);
     Ok(())
 }
@@ -905,7 +1511,7 @@ super + {_, ctrl +} {_, shift +} {1-2}
         Binding::running("riverctl toggle-view-tags 2")
             .on(Definition::new(evdev::Key::KEY_2).with_modifiers(&[Super, Shift, Control])),
     ];
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
     assert_equal_binding_set(parsed.bindings, known);
     Ok(())
 }
@@ -922,24 +1528,20 @@ super + ~@3
 super + @~4
     4";
     let known = vec![
-        Binding::running("1").on(Definition {
-            modifiers: [Super].into_iter().collect(),
-            key: Key::new(evdev::Key::KEY_1, KeyAttribute::OnRelease),
-        }),
-        Binding::running("2").on(Definition {
-            modifiers: [Super].into_iter().collect(),
-            key: Key::new(evdev::Key::KEY_2, KeyAttribute::Send),
-        }),
-        Binding::running("3").on(Definition {
-            modifiers: [Super].into_iter().collect(),
-            key: Key::new(evdev::Key::KEY_3, KeyAttribute::Both),
-        }),
-        Binding::running("4").on(Definition {
-            modifiers: [Super].into_iter().collect(),
-            key: Key::new(evdev::Key::KEY_4, KeyAttribute::Both),
-        }),
+        Binding::running("1").on(Definition::new(evdev::Key::KEY_1)
+            .with_attribute(KeyAttribute::OnRelease)
+            .with_modifiers(&[Super])),
+        Binding::running("2").on(Definition::new(evdev::Key::KEY_2)
+            .with_attribute(KeyAttribute::Send)
+            .with_modifiers(&[Super])),
+        Binding::running("3").on(Definition::new(evdev::Key::KEY_3)
+            .with_attribute(KeyAttribute::Both)
+            .with_modifiers(&[Super])),
+        Binding::running("4").on(Definition::new(evdev::Key::KEY_4)
+            .with_attribute(KeyAttribute::Both)
+            .with_modifiers(&[Super])),
     ];
-    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
     assert_equal_binding_set(parsed.bindings, known);
     Ok(())
 }
@@ -979,6 +1581,100 @@ fn test_homerow_special_keys_top() -> Result<(), ParseError> {
     Ok(())
 }
 
+#[test]
+fn test_mouse_buttons() -> Result<(), ParseError> {
+    let symbols: [&str; 7] = [
+        "btn_left",
+        "BTN_RIGHT",
+        "btn_middle",
+        "btn_side",
+        "btn_extra",
+        "btn_forward",
+        "btn_back",
+    ];
+
+    let keysyms: [evdev::Key; 7] = [
+        evdev::Key::BTN_LEFT,
+        evdev::Key::BTN_RIGHT,
+        evdev::Key::BTN_MIDDLE,
+        evdev::Key::BTN_SIDE,
+        evdev::Key::BTN_EXTRA,
+        evdev::Key::BTN_FORWARD,
+        evdev::Key::BTN_BACK,
+    ];
+
+    let mut contents = String::new();
+    for symbol in &symbols {
+        contents.push_str(&format!("super + {}\n    st\n", symbol));
+    }
+    let known = keysyms
+        .iter()
+        .map(|k| Binding::running("st").on(Definition::new(*k).with_modifiers(&[Super])))
+        .collect();
+    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    assert_equal_binding_set(parsed.bindings, known);
+
+    let contents = "
+super + {btn_side, btn_extra}
+    playerctl {previous, next}";
+    assert_parses_to!(
+        contents,
+        [
+            Binding::running("playerctl previous")
+                .on(Definition::new(evdev::Key::BTN_SIDE).with_modifiers(&[Super])),
+            Binding::running("playerctl next")
+                .on(Definition::new(evdev::Key::BTN_EXTRA).with_modifiers(&[Super])),
+        ]
+    );
+    assert_eq!(name_of(evdev::Key::BTN_SIDE), Some("btn_side"));
+    Ok(())
+}
+
+#[test]
+fn test_keypad_and_high_function_keys() -> Result<(), ParseError> {
+    let symbols: [&str; 10] = [
+        "kp5",
+        "f24",
+        "kp_enter",
+        "KP_PLUS",
+        "kp_minus",
+        "kp_asterisk",
+        "kp_slash",
+        "kp_dot",
+        "numlock",
+        "kpslash",
+    ];
+
+    let keysyms: [evdev::Key; 10] = [
+        evdev::Key::KEY_KP5,
+        evdev::Key::KEY_F24,
+        evdev::Key::KEY_KPENTER,
+        evdev::Key::KEY_KPPLUS,
+        evdev::Key::KEY_KPMINUS,
+        evdev::Key::KEY_KPASTERISK,
+        evdev::Key::KEY_KPSLASH,
+        evdev::Key::KEY_KPDOT,
+        evdev::Key::KEY_NUMLOCK,
+        evdev::Key::KEY_KPSLASH,
+    ];
+
+    for (symbol, keysym) in symbols.iter().zip(keysyms) {
+        let contents = format!("super + {}\n    st\n", symbol);
+        let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+        let known =
+            vec![Binding::running("st").on(Definition::new(keysym).with_modifiers(&[Super]))];
+        assert_equal_binding_set(parsed.bindings, known);
+    }
+
+    // Longer names sharing a prefix still parse as themselves.
+    let parsed = SwhkdParser::from(ParserInput::Raw("kpplusminus\n    st"))?;
+    assert_equal_binding_set(
+        parsed.bindings,
+        vec![Binding::running("st").on(Definition::new(evdev::Key::KEY_KPPLUSMINUS))],
+    );
+    Ok(())
+}
+
 #[test]
 fn test_all_alphanumeric() -> Result<(), ParseError> {
     let symbols: [&str; 36] = [
@@ -1036,3 +1732,7188 @@ fn test_all_alphanumeric() -> Result<(), ParseError> {
     assert_equal_binding_set(parsed.bindings, known);
     Ok(())
 }
+
+#[test]
+fn test_default_config_parses() {
+    // `default_bindings` panics if the built-in default ever stops parsing.
+    let parsed = SwhkdParser::default_bindings();
+    let known = vec![
+        Binding::running("$TERMINAL")
+            .on(Definition::new(evdev::Key::KEY_ENTER).with_modifiers(&[Super])),
+        Binding::running("pamixer -i 5").on(Definition::new(evdev::Key::KEY_VOLUMEUP)),
+        Binding::running("pamixer -d 5").on(Definition::new(evdev::Key::KEY_VOLUMEDOWN)),
+        Binding::running("pamixer -t").on(Definition::new(evdev::Key::KEY_MUTE)),
+        Binding::running("brightnessctl set +5%").on(Definition::new(evdev::Key::KEY_BRIGHTNESSUP)),
+        Binding::running("brightnessctl set 5%-")
+            .on(Definition::new(evdev::Key::KEY_BRIGHTNESSDOWN)),
+    ];
+    assert_eq!(parsed.bindings, known);
+    assert!(parsed.imports.is_empty());
+    assert!(parsed.modes.is_empty());
+}
+
+#[test]
+fn test_default_config_cheatsheet() {
+    // Every binding is described, so a cheat sheet of the default reads well.
+    let parsed = SwhkdParser::default_bindings();
+    let cheatsheet: String = parsed
+        .bindings
+        .iter()
+        .map(|binding| {
+            let chord = binding.definition().display_with(&DefaultStyle);
+            let description = binding
+                .description()
+                .unwrap_or_else(|| panic!("`{chord}` of the default config has no description"));
+            format!("{chord:<16}{description}\n")
+        })
+        .collect();
+    assert_eq!(cheatsheet, include_str!("fixtures/default_cheatsheet.txt"));
+}
+
+#[test]
+fn test_default_config_keys_on_generic_keyboard() {
+    // The keys of a generic laptop keyboard: the main block from Escape to
+    // F12, the navigation and multimedia keys, and the brightness keys.
+    let generic: Vec<evdev::Key> = (1..=88)
+        .chain(96..=127)
+        .chain([224, 225])
+        .map(evdev::Key::new)
+        .collect();
+    for binding in SwhkdParser::default_bindings().bindings {
+        let definition = binding.definition();
+        let Trigger::Key(key) = definition.key().trigger else {
+            panic!("`{definition}` of the default config is not a key");
+        };
+        assert!(
+            generic.contains(&key),
+            "{key:?} is not on a generic keyboard"
+        );
+        // Keys without a name would have to be written as `keycode(N)`.
+        assert!(key_name(key).is_some(), "{key:?} has no name");
+        for modifier in definition.modifiers() {
+            assert!(
+                !ModifierInfo::of(*modifier).pseudo,
+                "{modifier:?} is not a key"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_default_config_fallback() -> Result<(), IoOrParseError> {
+    let dir = tempfile::tempdir()?;
+    let missing = [dir.path().join("swhkdrc"), dir.path().join("other")];
+
+    let Err(ParseError::ConfigRead(ConfigReadError::NotFound(searched))) =
+        SwhkdParser::from_first_existing(&missing, FallbackPolicy::Error)
+    else {
+        panic!("expected a not found error")
+    };
+    assert_eq!(searched, missing);
+
+    let parsed = SwhkdParser::from_first_existing(&missing, FallbackPolicy::BuiltinDefault)?;
+    assert_eq!(parsed.bindings, SwhkdParser::default_bindings().bindings);
+    Ok(())
+}
+
+#[test]
+fn test_default_config_prefers_existing_file() -> Result<(), IoOrParseError> {
+    let mut setup = tempfile::NamedTempFile::new()?;
+    setup.write_all(
+        b"
+super + b
+    firefox",
+    )?;
+    let paths = [Path::new("/nonexistent/swhkdrc"), setup.path()];
+    let parsed = SwhkdParser::from_first_existing(&paths, FallbackPolicy::BuiltinDefault)?;
+    let known =
+        [Binding::running("firefox")
+            .on(Definition::new(evdev::Key::KEY_B).with_modifiers(&[Super]))];
+    assert_eq!(parsed.bindings, known);
+    Ok(())
+}
+
+#[test]
+fn test_scoped_settings() -> Result<(), ParseError> {
+    let contents = "
+set throttle_default 250ms
+set start_mode gaming
+
+super + a
+    firefox
+
+mode gaming
+set throttle_default 0ms
+super + b
+    steam
+endmode
+
+mode media
+set shell /bin/dash
+super + c
+    mpv
+endmode
+";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_eq!(
+        parsed.settings,
+        Settings {
+            shell: None,
+            throttle_default: Some(Duration::from_millis(250)),
+            start_mode: Some("gaming".to_string()),
+        }
+    );
+
+    // Built-in shell, top-level throttle.
+    let top_level = parsed.effective_settings(&parsed.bindings[0]);
+    assert_eq!(top_level.shell.as_deref(), Some("/bin/sh"));
+    assert_eq!(top_level.throttle_default, Some(Duration::from_millis(250)));
+    assert_eq!(top_level.start_mode.as_deref(), Some("gaming"));
+
+    // Mode overrides the top-level throttle.
+    let gaming = parsed.effective_settings(&parsed.modes[0].bindings[0]);
+    assert_eq!(gaming.shell.as_deref(), Some("/bin/sh"));
+    assert_eq!(gaming.throttle_default, Some(Duration::ZERO));
+
+    // Mode overrides the built-in shell and inherits the top-level throttle.
+    let media = parsed.effective_settings(&parsed.modes[1].bindings[0]);
+    assert_eq!(media.shell.as_deref(), Some("/bin/dash"));
+    assert_eq!(media.throttle_default, Some(Duration::from_millis(250)));
+    Ok(())
+}
+
+#[test]
+fn test_top_level_only_setting_in_mode() {
+    let contents = "
+mode gaming
+set start_mode gaming
+super + b
+    steam
+endmode";
+    assert_parse_error!(contents, span: (3, 1)..(3, 22), contains: "only allowed at the top level");
+}
+
+#[test]
+fn test_invalid_setting() {
+    assert_parse_error!("\nset throttle_default fast", span: (2, 1)..(2, 26));
+    assert_parse_error!("\nset colour blue", span: (2, 1)..(2, 16));
+}
+
+#[test]
+fn test_device_and_binding_settings() -> Result<(), Box<dyn std::error::Error>> {
+    let contents = "
+set throttle_default 250ms
+[device=macro-pad] set throttle_default 100ms
+[device=macro-pad] set shell /bin/dash
+
+[device=macro-pad] super + a
+    first
+[device=macro-pad] [set shell=/bin/bash] super + b
+    second
+
+mode gaming
+set throttle_default 0ms
+[device=macro-pad] super + c
+    steam
+[set throttle_default=50ms] super + d
+    mpv
+endmode
+";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let resolved = |parsed: &SwhkdParser, binding: &Binding| {
+        let settings = parsed.effective_settings(binding);
+        (settings.shell, settings.throttle_default)
+    };
+    let dash = Some("/bin/dash".to_string());
+    let expected = [
+        // The device overrides the top level.
+        (dash.clone(), Some(Duration::from_millis(100))),
+        // The binding overrides its device.
+        (
+            Some("/bin/bash".to_string()),
+            Some(Duration::from_millis(100)),
+        ),
+        // The device overrides the mode.
+        (dash, Some(Duration::from_millis(100))),
+        // The binding overrides its mode.
+        (Some("/bin/sh".to_string()), Some(Duration::from_millis(50))),
+    ];
+    let bindings = || parsed.bindings.iter().chain(&parsed.modes[0].bindings);
+    assert_eq!(
+        bindings().map(|b| resolved(&parsed, b)).collect::<Vec<_>>(),
+        expected
+    );
+    // Clones resolve like the bindings they were cloned from.
+    let clones: Vec<Binding> = bindings().cloned().collect();
+    assert_eq!(
+        clones
+            .iter()
+            .map(|b| resolved(&parsed, b))
+            .collect::<Vec<_>>(),
+        expected
+    );
+
+    // So do deserialized bindings, of the parsed or of a deserialized config.
+    let reparsed: SwhkdParser = serde_json::from_str(&serde_json::to_string(&parsed)?)?;
+    assert_eq!(reparsed.device_settings, parsed.device_settings);
+    for (binding, expected) in bindings().zip(&expected) {
+        let binding: Binding = serde_json::from_str(&serde_json::to_string(binding)?)?;
+        assert_eq!(&resolved(&parsed, &binding), expected);
+        assert_eq!(&resolved(&reparsed, &binding), expected);
+    }
+
+    // And the bindings of the config rendered back.
+    let emitted = SwhkdParser::from(ParserInput::Raw(&parsed.to_config_string()))?;
+    let emitted_bindings = emitted.bindings.iter().chain(&emitted.modes[0].bindings);
+    assert_eq!(
+        emitted_bindings
+            .map(|b| resolved(&emitted, b))
+            .collect::<Vec<_>>(),
+        expected
+    );
+    Ok(())
+}
+
+#[test]
+fn test_misplaced_scoped_settings() {
+    let contents = "
+mode gaming
+[device=macro-pad] set throttle_default 0ms
+super + b
+    steam
+endmode";
+    assert_parse_error!(
+        contents,
+        span: (3, 1)..(3, 44),
+        contains: "device settings are only allowed at the top level"
+    );
+    assert_parse_error!(
+        "\n[set start_mode=gaming] super + a\n    steam",
+        span: (2, 1)..(2, 24),
+        contains: "only allowed at the top level"
+    );
+    assert_parse_error!(
+        "\n[device=macro-pad] set start_mode gaming",
+        span: (2, 1)..(2, 41),
+        contains: "only allowed at the top level"
+    );
+}
+
+/// The mode and the lines of the unreachable bindings and of the ones
+/// replacing them.
+fn unreachable_pairs(parsed: &SwhkdParser) -> Vec<(Option<&str>, usize, usize)> {
+    parsed
+        .unreachable_bindings()
+        .iter()
+        .map(|u| {
+            let line = |binding: &Binding| binding.span().unwrap().line;
+            (u.mode, line(u.binding), line(u.shadowed_by))
+        })
+        .collect()
+}
+
+#[test]
+fn test_unreachable_duplicate_in_mode() -> Result<(), ParseError> {
+    let contents = "
+mode resize
+super + a
+    first
+super + b
+    other
+super + a
+    second
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_eq!(unreachable_pairs(&parsed), [(Some("resize"), 3, 7)]);
+    let unreachable = &parsed.unreachable_bindings()[0];
+    assert_eq!(unreachable.binding.command(), "first");
+    assert_eq!(unreachable.shadowed_by.command(), "second");
+    Ok(())
+}
+
+#[test]
+fn test_unreachable_alias_spelling() -> Result<(), ParseError> {
+    let contents = "
+mode launch
+mod4 + return
+    first
+super + enter
+    second
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_eq!(unreachable_pairs(&parsed), [(Some("launch"), 3, 5)]);
+    Ok(())
+}
+
+#[test]
+fn test_unreachable_any_duplicate() -> Result<(), ParseError> {
+    let contents = "
+mode media
+any + a
+    first
+a
+    plain
+any + a
+    second
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_eq!(unreachable_pairs(&parsed), [(Some("media"), 3, 7)]);
+    Ok(())
+}
+
+#[test]
+fn test_unreachable_shorthand_overlap() -> Result<(), ParseError> {
+    let contents = "
+mode workspaces
+super + {1-3}
+    focus {1-3}
+super + 2
+    special
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_eq!(unreachable_pairs(&parsed), [(Some("workspaces"), 3, 5)]);
+    Ok(())
+}
+
+#[test]
+fn test_unreachable_at_top_level() -> Result<(), ParseError> {
+    let contents = "
+super + shift + a
+    first
+shift + super + a
+    second
+mod4 + {1-3}
+    focus {1-3}
+super + 2
+    special
+ctrl + x
+    last";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_eq!(unreachable_pairs(&parsed), [(None, 2, 4), (None, 6, 8)]);
+    let unreachable = parsed.unreachable_bindings();
+    assert_eq!(
+        (
+            unreachable[1].binding.command(),
+            unreachable[1].shadowed_by.command()
+        ),
+        ("focus 2", "special")
+    );
+    assert_eq!(
+        unreachable[0].to_string(),
+        "at the top level, `super + shift + a` at <anonymous>:2:1 can never fire, it is replaced by `super + shift + a` at <anonymous>:4:1, declared later on the same chord"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_unreachable_across_includes() -> Result<(), ParseError> {
+    // The include is merged after the root config, so it overrides it.
+    let options = map_options(&[
+        ("root", "super + return\n    root\ninclude extra"),
+        ("extra", "\nmod4 + enter\n    extra"),
+    ]);
+    let parsed = SwhkdParser::from_with_options(ParserInput::Path(Path::new("root")), &options)?;
+    let unreachable = parsed.unreachable_bindings();
+    let spans: Vec<_> = unreachable
+        .iter()
+        .map(|u| {
+            let span = |binding: &Binding| {
+                let span = binding.span().unwrap();
+                (span.file.to_string(), span.line)
+            };
+            (span(u.binding), span(u.shadowed_by))
+        })
+        .collect();
+    assert_eq!(spans, [(("root".to_string(), 1), ("extra".to_string(), 2))]);
+    Ok(())
+}
+
+#[test]
+fn test_reachable_near_misses() -> Result<(), ParseError> {
+    let contents = "
+any + a
+    any
+super + a
+    super
+super + shift + a
+    super shift
+super + @a
+    on release
+mode gaming
+a
+    mode local
+super + a
+    mode super
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_eq!(unreachable_pairs(&parsed), []);
+    Ok(())
+}
+
+#[test]
+fn test_conflicts() -> Result<(), ParseError> {
+    let contents = "
+super + a
+    first
+any + b
+    any
+super + b
+    specific
+super + a
+    second
+mode resize
+super + b
+    mode
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let conflicts: Vec<_> = parsed
+        .conflicts()
+        .iter()
+        .map(|c| (c.kind, c.mode, c.first.command(), c.second.command()))
+        .collect();
+    assert_eq!(
+        conflicts,
+        [
+            (ConflictKind::Overridden, None, "first", "second"),
+            (ConflictKind::AnyOverlap, None, "any", "specific"),
+            (
+                ConflictKind::ModeOverridesTopLevel,
+                Some("resize"),
+                "specific",
+                "mode"
+            ),
+        ]
+    );
+    assert_eq!(
+        parsed.conflicts()[0].to_string(),
+        "at the top level, `super + a` at <anonymous>:8:1 overrides the same chord at <anonymous>:2:1"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_missing_include_names_include_line() -> Result<(), IoOrParseError> {
+    let dir = tempfile::tempdir()?;
+    let missing = dir.path().join("missing.swhkd");
+    let mut setup = tempfile::NamedTempFile::new()?;
+    write!(
+        setup,
+        "
+super + b
+    firefox
+include {}",
+        missing.display()
+    )?;
+    let Err(err) = SwhkdParser::from(ParserInput::Path(setup.path())) else {
+        panic!("expected an import failure")
+    };
+    let ParseError::ImportFailed {
+        path,
+        cause: ConfigReadError::ReadingConfig(_),
+        requested_at,
+    } = &err
+    else {
+        panic!("expected an import failure, got {err:?}")
+    };
+    assert_eq!(path, &missing);
+    assert_eq!((requested_at.line, requested_at.column), (4, 1));
+    assert_eq!(&*requested_at.file, setup.path().to_str().unwrap());
+
+    let rendered = err.to_string();
+    assert!(rendered.contains(&format!("4 | include {}", missing.display())));
+    assert!(rendered.contains(&format!("unable to import `{}`", missing.display())));
+    assert!(rendered.contains("No such file or directory"));
+    Ok(())
+}
+
+#[test]
+fn test_directory_include() -> Result<(), IoOrParseError> {
+    let dir = tempfile::tempdir()?;
+    let mut setup = tempfile::NamedTempFile::new()?;
+    write!(setup, "\ninclude {}", dir.path().display())?;
+    let Err(ParseError::ImportFailed {
+        cause: ConfigReadError::NotRegularFile(path),
+        requested_at,
+        ..
+    }) = SwhkdParser::from(ParserInput::Path(setup.path()))
+    else {
+        panic!("expected an import failure")
+    };
+    assert_eq!(path, dir.path());
+    assert_eq!(requested_at.line, 2);
+    Ok(())
+}
+
+#[test]
+fn test_include_expands_variables() -> Result<(), IoOrParseError> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("extra.swhkd"), "\nsuper + c\n    hello")?;
+    std::env::set_var("SWEET_TEST_INCLUDE_DIR", dir.path());
+    let mut setup = tempfile::NamedTempFile::new()?;
+    write!(
+        setup,
+        "
+include ${{SWEET_TEST_INCLUDE_DIR}}/extra.swhkd
+include $SWEET_TEST_INCLUDE_DIR/extra.swhkd
+include {}/extra.swhkd
+super + b
+   firefox",
+        dir.path().display()
+    )?;
+
+    let parsed = SwhkdParser::from(ParserInput::Path(setup.path()))?;
+    let known = [
+        Binding::running("firefox").on(Definition::new(evdev::Key::KEY_B).with_modifiers(&[Super])),
+        Binding::running("hello").on(Definition::new(evdev::Key::KEY_C).with_modifiers(&[Super])),
+    ];
+    assert_eq!(parsed.bindings, known);
+
+    // However it is spelled, the file is only read once.
+    let report = SwhkdParser::report(ParserInput::Path(setup.path()), ReportOptions::default());
+    assert_eq!(report.files.len(), 2);
+    assert_eq!(
+        report.files[1].path,
+        dir.path().join("extra.swhkd").display().to_string()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_include_unset_variable() -> Result<(), IoOrParseError> {
+    let mut setup = tempfile::NamedTempFile::new()?;
+    write!(setup, "\ninclude $SWEET_TEST_UNSET_VARIABLE/extra.swhkd")?;
+    let Err(ParseError::ImportFailed {
+        cause: ConfigReadError::UnsetVariable(name),
+        requested_at,
+        ..
+    }) = SwhkdParser::from(ParserInput::Path(setup.path()))
+    else {
+        panic!("expected an import failure")
+    };
+    assert_eq!(name, "SWEET_TEST_UNSET_VARIABLE");
+    assert_eq!(requested_at.line, 2);
+    Ok(())
+}
+
+#[test]
+fn test_missing_root_config() -> Result<(), IoOrParseError> {
+    let dir = tempfile::tempdir()?;
+    let missing = dir.path().join("swhkdrc");
+    let Err(ParseError::ConfigRead(ConfigReadError::ReadingConfig(_))) =
+        SwhkdParser::from(ParserInput::Path(&missing))
+    else {
+        panic!("expected a config read error")
+    };
+    Ok(())
+}
+
+#[test]
+fn test_virtual_key() -> Result<(), ParseError> {
+    let contents = "
+virtual:lid_close
+    systemctl suspend
+super + a
+    firefox";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let known = [
+        Binding::running("systemctl suspend").on(Definition::virtual_key("lid_close")),
+        Binding::running("firefox").on(Definition::new(evdev::Key::KEY_A).with_modifiers(&[Super])),
+    ];
+    assert_eq!(parsed.bindings, known);
+    assert_eq!(parsed.virtual_bindings().collect::<Vec<_>>(), [&known[0]]);
+    Ok(())
+}
+
+#[test]
+fn test_virtual_key_shorthand() -> Result<(), ParseError> {
+    let contents = "
+virtual:{monitor_1, monitor_2}
+    wlr-randr --output {DP1, DP2} --on";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let known = [
+        Binding::running("wlr-randr --output DP1 --on").on(Definition::virtual_key("monitor_1")),
+        Binding::running("wlr-randr --output DP2 --on").on(Definition::virtual_key("monitor_2")),
+    ];
+    assert_eq!(parsed.bindings, known);
+    Ok(())
+}
+
+#[test]
+fn test_virtual_key_with_modifiers() {
+    let contents = "
+super + virtual:lid_close
+    systemctl suspend";
+    assert_parse_error!(contents, span: (2, 9)..(2, 26), contains: "modifiers cannot be combined with virtual keys");
+}
+
+#[test]
+fn test_virtual_key_invalid_name() {
+    assert_parse_error!(
+        "\nvirtual:Lid_Close\n    systemctl suspend", span: (2, 9)..(2, 18));
+}
+
+#[test]
+fn test_unbind_virtual_key() -> Result<(), ParseError> {
+    let contents = "
+virtual:{lid_open, lid_close}
+    {true, systemctl suspend}
+ignore virtual:lid_close";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let known = [Binding::running("true").on(Definition::virtual_key("lid_open"))];
+    assert_eq!(parsed.bindings, known);
+    Ok(())
+}
+
+fn parse_with_includes(first: &Path, second: &Path) -> Result<SwhkdParser, IoOrParseError> {
+    let mut setup = tempfile::NamedTempFile::new()?;
+    write!(
+        setup,
+        "
+super + a
+    root
+include {}
+include {}",
+        first.display(),
+        second.display()
+    )?;
+    Ok(SwhkdParser::from(ParserInput::Path(setup.path()))?)
+}
+
+#[test]
+fn test_later_include_wins() -> Result<(), IoOrParseError> {
+    let mut one = tempfile::NamedTempFile::new()?;
+    one.write_all(
+        b"
+super + b
+    one
+mode one
+a
+    one
+endmode",
+    )?;
+    let mut two = tempfile::NamedTempFile::new()?;
+    two.write_all(
+        b"
+super + b
+    two
+mode two
+a
+    two
+endmode",
+    )?;
+
+    let parsed = parse_with_includes(one.path(), two.path())?;
+    let known = [
+        Binding::running("root").on(Definition::new(evdev::Key::KEY_A).with_modifiers(&[Super])),
+        Binding::running("two").on(Definition::new(evdev::Key::KEY_B).with_modifiers(&[Super])),
+    ];
+    assert_eq!(parsed.bindings, known);
+    let modes: Vec<_> = parsed.modes.iter().map(|mode| mode.name.as_str()).collect();
+    assert_eq!(modes, ["one", "two"]);
+
+    let parsed = parse_with_includes(two.path(), one.path())?;
+    let known = [
+        Binding::running("root").on(Definition::new(evdev::Key::KEY_A).with_modifiers(&[Super])),
+        Binding::running("one").on(Definition::new(evdev::Key::KEY_B).with_modifiers(&[Super])),
+    ];
+    assert_eq!(parsed.bindings, known);
+    let modes: Vec<_> = parsed.modes.iter().map(|mode| mode.name.as_str()).collect();
+    assert_eq!(modes, ["two", "one"]);
+    Ok(())
+}
+
+#[test]
+fn test_include_overrides_including_file() -> Result<(), IoOrParseError> {
+    let mut one = tempfile::NamedTempFile::new()?;
+    one.write_all(
+        b"
+super + a
+    one",
+    )?;
+    let two = tempfile::NamedTempFile::new()?;
+    let parsed = parse_with_includes(one.path(), two.path())?;
+    let known =
+        [Binding::running("one").on(Definition::new(evdev::Key::KEY_A).with_modifiers(&[Super]))];
+    assert_eq!(parsed.bindings, known);
+    Ok(())
+}
+
+#[test]
+fn test_escaped_key_outside_shorthand() -> Result<(), ParseError> {
+    let contents = "
+super + \\,
+    riverctl focus-output previous";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let known = [Binding::running("riverctl focus-output previous")
+        .on(Definition::new(evdev::Key::KEY_COMMA).with_modifiers(&[Super]))];
+    assert_eq!(parsed.bindings, known);
+    Ok(())
+}
+
+#[test]
+fn test_escaped_dash_in_shorthand() -> Result<(), ParseError> {
+    let contents = "
+super + {\\-, a}
+    {zoom out, launch}";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let known = [
+        Binding::running("zoom out")
+            .on(Definition::new(evdev::Key::KEY_MINUS).with_modifiers(&[Super])),
+        Binding::running("launch").on(Definition::new(evdev::Key::KEY_A).with_modifiers(&[Super])),
+    ];
+    assert_eq!(parsed.bindings, known);
+    Ok(())
+}
+
+#[test]
+fn test_unnecessary_key_escape() {
+    let contents = "
+super + \\a
+    firefox";
+    assert_parse_error!(contents, span: (2, 9)..(2, 11), contains: "unnecessary escape of 'a' in key position");
+
+    let contents = "
+super + {b, \\minus}
+    {firefox, zoom}";
+    assert_parse_error!(contents, span: (2, 13)..(2, 19), contains: "unnecessary escape of 'm' in key position");
+}
+
+#[test]
+fn test_unknown_command_escape_is_kept() -> Result<(), ParseError> {
+    let contents = r"
+super + {a, b}
+    printf '{one\n, two\, three}'";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let known = [
+        Binding::running(r"printf 'one\n'")
+            .on(Definition::new(evdev::Key::KEY_A).with_modifiers(&[Super])),
+        Binding::running("printf 'two, three'")
+            .on(Definition::new(evdev::Key::KEY_B).with_modifiers(&[Super])),
+    ];
+    assert_eq!(parsed.bindings, known);
+    Ok(())
+}
+
+fn conflict_pairs(
+    parsed: &SwhkdParser,
+    external: &[ExternalBinding],
+) -> Vec<(Option<String>, usize, String)> {
+    parsed
+        .conflicts_with(external)
+        .iter()
+        .map(|c| {
+            (
+                c.mode.map(str::to_string),
+                c.index,
+                c.external.owner.clone(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_conflicts_with_sway_bindings() -> Result<(), Box<dyn std::error::Error>> {
+    let external = ExternalBinding::parse_sway_json(include_str!("fixtures/sway_bindings.json"))?;
+    assert_eq!(external.len(), 3);
+    assert_eq!(
+        external[1],
+        ExternalBinding {
+            modifiers: [Super, Shift].into(),
+            key: evdev::Key::KEY_ENTER,
+            owner: "sway: exec foot".to_string(),
+        }
+    );
+
+    let contents = "
+super + 3
+    notify-send three
+super + return
+    foot
+any + print
+    grim
+mode launcher
+super + shift + return
+    kitty
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_eq!(
+        conflict_pairs(&parsed, &external),
+        [
+            (None, 0, "sway: workspace number 3".to_string()),
+            (None, 2, "sway: exec grim".to_string()),
+            (
+                Some("launcher".to_string()),
+                0,
+                "sway: exec foot".to_string()
+            ),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_conflicts_with_line_bindings() -> Result<(), Box<dyn std::error::Error>> {
+    let external =
+        ExternalBinding::parse_lines(include_str!("fixtures/compositor_binds.txt"), "river")?;
+    let owners: Vec<_> = external.iter().map(|b| b.owner.as_str()).collect();
+    assert_eq!(
+        owners,
+        ["river: set-view-tags 4", "river: spawn foot", "river"]
+    );
+
+    let contents = "
+super + {_, shift +} 3
+    {echo three, echo tags}
+any + delete
+    loginctl lock-session
+virtual:delete
+    true";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_eq!(
+        conflict_pairs(&parsed, &external),
+        [
+            (None, 1, "river: set-view-tags 4".to_string()),
+            (None, 2, "river".to_string()),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_invalid_external_bindings() {
+    assert_eq!(
+        ExternalBinding::parse_lines("super+a\nhyper+b", "river"),
+        Err(ExternalBindingError::InvalidModifier {
+            line: 2,
+            name: "hyper".to_string()
+        })
+    );
+    assert_eq!(
+        ExternalBinding::parse_lines("super+", "river"),
+        Err(ExternalBindingError::MissingKey { line: 1 })
+    );
+    assert!(matches!(
+        ExternalBinding::parse_sway_json(r#"[{"input_type": "keyboard"}]"#),
+        Err(ExternalBindingError::MissingField {
+            index: 0,
+            field: "symbol"
+        })
+    ));
+}
+
+#[test]
+fn test_modifier_only_bindings() -> Result<(), ParseError> {
+    let contents = "
+@super
+    wofi
+@super!
+    wofi --show drun
+super
+    notify-send super
+control + @alt!
+    rofi";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let known = [
+        Binding::running("wofi").on(Definition::modifiers_only(
+            &[Super],
+            KeyAttribute::OnRelease,
+        )),
+        Binding::running("wofi --show drun").on(Definition::modifiers_only(
+            &[Super],
+            KeyAttribute::OnRelease | KeyAttribute::Solo,
+        )),
+        Binding::running("notify-send super")
+            .on(Definition::modifiers_only(&[Super], KeyAttribute::None)),
+        Binding::running("rofi").on(Definition::modifiers_only(
+            &[Control, Alt],
+            KeyAttribute::OnRelease | KeyAttribute::Solo,
+        )),
+    ];
+    assert_eq!(parsed.bindings, known);
+    assert!(parsed.unreachable_bindings().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_modifier_only_override() {
+    let contents = "
+@super!
+    wofi
+super + a
+    firefox
+@super!
+    fuzzel";
+    assert_parses_to!(
+        contents,
+        [
+            binding!("@super!" => "fuzzel"),
+            binding!("super + a" => "firefox"),
+        ]
+    );
+}
+
+#[test]
+fn test_unbind_modifier_only() -> Result<(), ParseError> {
+    let contents = "
+super
+    notify-send super
+@super
+    wofi
+@super!
+    fuzzel
+ignore super
+ignore @super!";
+    // Only the definition with the same attributes is unbound.
+    assert_parses_to!(contents, [binding!("@super" => "wofi")]);
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_eq!(
+        parsed.unbinds,
+        [
+            Definition::modifiers_only(&[Super], KeyAttribute::None),
+            Definition::modifiers_only(&[Super], KeyAttribute::OnRelease | KeyAttribute::Solo),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_solo_requires_release() {
+    let contents = "
+super!
+    wofi";
+    assert_parse_error!(contents, span: (2, 1)..(2, 7), contains: "`!` can only be used on release");
+}
+
+#[test]
+fn test_report_lists_files_with_hashes() {
+    let report = SwhkdParser::report(
+        ParserInput::Path(Path::new("tests/fixtures/report_root.swhkd")),
+        ReportOptions::default(),
+    );
+    assert_eq!(
+        report.files,
+        [
+            ReportedFile {
+                path: "tests/fixtures/report_root.swhkd".to_string(),
+                included_from: None,
+                size: 52,
+                sha256: "1731c4a354c2e223108678e2f5c345de06488967e2e4445074beaa1ba606a0ba"
+                    .to_string(),
+                contents: None,
+            },
+            ReportedFile {
+                path: "tests/fixtures/report_include.swhkd".to_string(),
+                included_from: Some("tests/fixtures/report_root.swhkd".to_string()),
+                size: 20,
+                sha256: "8ebd9ad190ebc973e416738a9cacf57e67fb9de9289b42c760b8bbdd8340eea7"
+                    .to_string(),
+                contents: None,
+            },
+        ]
+    );
+    assert_eq!(
+        report.outcome,
+        ReportOutcome::Parsed {
+            bindings: 2,
+            modes: 0,
+            mode_bindings: 0,
+            unbinds: 0,
+            unreachable: 0,
+        }
+    );
+    let markdown = report.to_markdown();
+    assert!(markdown.contains(
+        "| `tests/fixtures/report_include.swhkd` | `tests/fixtures/report_root.swhkd` | 20 | `8ebd9ad190ebc973e416738a9cacf57e67fb9de9289b42c760b8bbdd8340eea7` |"
+    ));
+    assert!(!markdown.contains("firefox"));
+
+    let report = SwhkdParser::report(
+        ParserInput::Path(Path::new("tests/fixtures/report_root.swhkd")),
+        ReportOptions {
+            include_contents: true,
+        },
+    );
+    assert_eq!(
+        report.files[1].contents.as_deref(),
+        Some("super + c\n    hello\n")
+    );
+    assert!(report
+        .to_json()
+        .contains(r#""contents":"super + c\n    hello\n""#));
+}
+
+#[test]
+fn test_report_of_failing_config() {
+    let report = SwhkdParser::report(
+        ParserInput::Raw("\nshift + k + alt\n    notify-send 'Hello world!'"),
+        ReportOptions::default(),
+    );
+    assert_eq!(report.files.len(), 1);
+    assert_eq!(
+        report.files[0].sha256,
+        "2824eb4d564fa5b2c8dc26c785df191b394ba1fa14b2152b5aae3acee18a64f6"
+    );
+    let ReportOutcome::Failed { diagnostic } = &report.outcome else {
+        panic!("expected the report of a failed parse")
+    };
+    assert!(diagnostic.contains("<anonymous>:2:11"));
+    assert!(report.to_json().contains(r#""status":"failed""#));
+}
+
+#[test]
+fn test_multiple_command_lines() -> Result<(), ParseError> {
+    let contents = "
+super + w
+    pkill waybar
+    waybar &
+super + q
+    notify-send bye
+";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_parses_to!(
+        contents,
+        [
+            binding!("super + w" => "pkill waybar\nwaybar &"),
+            binding!("super + q" => "notify-send bye"),
+        ]
+    );
+    let commands: Vec<_> = parsed.bindings[0].commands().collect();
+    assert_eq!(commands, ["pkill waybar", "waybar &"]);
+    Ok(())
+}
+
+#[test]
+fn test_continuation_then_command_line() {
+    let contents = "
+k
+    mpc ls | dmenu | \\
+        sed -i 's/foo/bar/g'
+    notify-send done";
+    assert_parses_to!(
+        contents,
+        [binding!("k" => "mpc ls | dmenu | sed -i 's/foo/bar/g'\nnotify-send done")]
+    );
+}
+
+#[test]
+fn test_blank_line_ends_command_lines() {
+    let contents = "
+k
+    notify-send one
+
+    notify-send two";
+    assert!(SwhkdParser::from(ParserInput::Raw(contents)).is_err());
+}
+
+#[test]
+fn test_shorthands_on_every_command_line() {
+    let contents = "
+super + {1, 2}
+    swaymsg workspace {one, two}
+    notify-send {first, second}";
+    assert_parses_to!(
+        contents,
+        [
+            binding!("super + 1" => "swaymsg workspace one\nnotify-send first"),
+            binding!("super + 2" => "swaymsg workspace two\nnotify-send second"),
+        ]
+    );
+
+    let contents = "
+super + {1, 2}
+    swaymsg workspace {one, two}
+    notify-send switched";
+    assert_parse_error!(
+        contents,
+        span: (2, 1)..(4, 25),
+        contains: "the number of possible binding variants 2 does not equal the number of possible command variants 1"
+    );
+}
+
+#[test]
+fn test_modifier_table_matches_grammar() -> Result<(), ParseError> {
+    // Every spelling in the table is accepted in a config...
+    for info in MODIFIERS.iter().filter(|info| info.modifier != Omission) {
+        for name in std::iter::once(&info.canonical).chain(info.aliases) {
+            let contents = format!("{} + a\n    true", name.to_uppercase());
+            let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+            assert_eq!(
+                *parsed.bindings[0].definition().modifiers(),
+                [info.modifier].into(),
+                "`{name}`"
+            );
+        }
+    }
+    // ...and every modifier in the grammar is in the table.
+    let grammar = include_str!("../template.pest");
+    let start = grammar.find("\nmodifier = ${").unwrap();
+    let rule = &grammar[start..start + grammar[start..].find('}').unwrap()];
+    for name in rule.split("^\"").skip(1) {
+        let name = &name[..name.find('"').unwrap()];
+        assert!(
+            ModifierInfo::lookup(name).is_some(),
+            "`{name}` is missing from MODIFIERS"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_sided_modifiers() -> Result<(), ParseError> {
+    let config = "
+ralt + x
+    echo right
+alt + x
+    echo either
+lctrl + RShift + y
+    echo sided
+lalt + z
+    echo left
+ignore lalt + z
+ignore super + z";
+    let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    assert_eq!(
+        parsed.bindings,
+        [
+            Binding::running("echo right")
+                .on(Definition::new(evdev::Key::KEY_X).with_modifiers(&[RightAlt])),
+            Binding::running("echo either")
+                .on(Definition::new(evdev::Key::KEY_X).with_modifiers(&[Alt])),
+            Binding::running("echo sided")
+                .on(Definition::new(evdev::Key::KEY_Y).with_modifiers(&[LeftControl, RightShift])),
+        ]
+    );
+    assert_eq!(
+        parsed.bindings[2].definition().to_string(),
+        "lcontrol + rshift + y"
+    );
+    assert_eq!(
+        SwhkdParser::from(ParserInput::Raw(&parsed.to_config_string()))?.bindings,
+        parsed.bindings
+    );
+    assert_eq!(RightAlt.either_side(), Alt);
+    assert!(!Altgr.is_sided());
+    let exported = sweet::to_sway(&parsed);
+    assert_eq!(exported.trim_end(), "bindsym Mod1+x exec echo either");
+    Ok(())
+}
+
+#[test]
+fn test_modifier_attributes() -> Result<(), Box<dyn std::error::Error>> {
+    let config = "
+@super + tab
+    echo released
+super + tab
+    echo plain
+{_, @shift +} ~ctrl + a
+    echo {bare, shifted}
+{@lalt, ralt} + b
+    echo {left, right}
+@shift + exclam
+    echo shifted
+";
+    let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    let tab = Definition::new(evdev::Key::KEY_TAB).with_modifiers(&[Super]);
+    let a = Definition::new(evdev::Key::KEY_A)
+        .with_modifiers(&[Control])
+        .with_modifier_attribute(Control, KeyAttribute::Send);
+    assert_eq!(
+        parsed.bindings,
+        [
+            Binding::running("echo released").on(tab
+                .clone()
+                .with_modifier_attribute(Super, KeyAttribute::OnRelease)),
+            Binding::running("echo plain").on(tab),
+            Binding::running("echo bare").on(a.clone()),
+            Binding::running("echo shifted").on(a
+                .with_modifiers(&[Control, Shift])
+                .with_modifier_attribute(Shift, KeyAttribute::OnRelease)),
+            Binding::running("echo left").on(Definition::new(evdev::Key::KEY_B)
+                .with_modifiers(&[LeftAlt])
+                .with_modifier_attribute(LeftAlt, KeyAttribute::OnRelease)),
+            Binding::running("echo right")
+                .on(Definition::new(evdev::Key::KEY_B).with_modifiers(&[RightAlt])),
+            Binding::running("echo shifted").on(Definition::new(evdev::Key::KEY_1)
+                .with_modifiers(&[Shift])
+                .with_modifier_attribute(Shift, KeyAttribute::OnRelease)),
+        ]
+    );
+    let rendered: Vec<_> = parsed
+        .bindings
+        .iter()
+        .map(|binding| binding.definition().to_string())
+        .collect();
+    assert_eq!(
+        rendered,
+        [
+            "@super + tab",
+            "super + tab",
+            "~control + a",
+            "~control + @shift + a",
+            "@lalt + b",
+            "ralt + b",
+            "@shift + 1",
+        ]
+    );
+    assert_eq!(
+        SwhkdParser::from(ParserInput::Raw(&parsed.to_config_string()))?.bindings,
+        parsed.bindings
+    );
+    let reparsed: SwhkdParser = serde_json::from_str(&serde_json::to_string(&parsed)?)?;
+    assert_eq!(reparsed.bindings, parsed.bindings);
+
+    // An `ignore` only matches the same attributes.
+    let parsed = SwhkdParser::from(ParserInput::Raw(&format!("{config}ignore super + tab")))?;
+    assert_eq!(parsed.bindings[0].definition().to_string(), "@super + tab");
+    assert_eq!(parsed.bindings.len(), 6);
+
+    // Modifiers that stay keep their attributes, and `!` is no modifier
+    // attribute.
+    let definition: Definition = "@super + ~shift + tab".parse()?;
+    let definition = definition
+        .with_modifiers(&[Super, Control])
+        .with_modifier_attribute(Control, KeyAttribute::Solo);
+    assert_eq!(definition.to_string(), "@super + control + tab");
+    let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    let exported = sweet::to_sway_with_options(&parsed, &ExportOptions::default());
+    assert_eq!(
+        exported.skipped[0].to_string(),
+        "`@super + tab` skipped: `~` and `@` on modifiers"
+    );
+
+    assert_parse_error!(
+        "@any + a\n    true",
+        span: (1, 1)..(1, 5),
+        contains: "`~` and `@` cannot be used on it"
+    );
+    assert_parse_error!(
+        "@shift + @super\n    true",
+        span: (1, 10)..(1, 16),
+        contains: "on their last modifier alone"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_modifier_and_attribute_tables() {
+    let modifiers: Vec<_> = MODIFIERS
+        .iter()
+        .map(|info| {
+            format!(
+                "{:?} {} {:?} pseudo={}",
+                info.modifier, info.canonical, info.aliases, info.pseudo
+            )
+        })
+        .collect();
+    assert_eq!(
+        modifiers,
+        [
+            r#"Super super ["mod4", "meta"] pseudo=false"#,
+            r#"LeftSuper lsuper [] pseudo=false"#,
+            r#"RightSuper rsuper [] pseudo=false"#,
+            r#"Alt alt ["mod1"] pseudo=false"#,
+            r#"LeftAlt lalt [] pseudo=false"#,
+            r#"RightAlt ralt [] pseudo=false"#,
+            r#"Altgr altgr ["mod5"] pseudo=false"#,
+            r#"Control control ["ctrl"] pseudo=false"#,
+            r#"LeftControl lcontrol ["lctrl"] pseudo=false"#,
+            r#"RightControl rcontrol ["rctrl"] pseudo=false"#,
+            r#"Shift shift [] pseudo=false"#,
+            r#"LeftShift lshift [] pseudo=false"#,
+            r#"RightShift rshift [] pseudo=false"#,
+            r#"Any any [] pseudo=true"#,
+            r#"Omission _ [] pseudo=true"#,
+        ]
+    );
+    let attributes: Vec<_> = KEY_ATTRIBUTES
+        .iter()
+        .map(|info| format!("{} {} {:?}", info.written, info.name, info.attribute))
+        .collect();
+    assert_eq!(
+        attributes,
+        [
+            "~ send KeyAttribute(Send)",
+            "@ on release KeyAttribute(OnRelease)",
+            "! solo KeyAttribute(Solo)",
+            "passthrough passthrough KeyAttribute(Passthrough)",
+        ]
+    );
+}
+
+#[test]
+fn test_continuation_at_end_of_file() {
+    let message = "line continuation at end of file";
+    assert_parse_error!("\nsuper + a\n    echo hi \\", span: (3, 13)..(3, 14), contains: message);
+    assert_parse_error!("\nsuper + a\n    echo hi \\\n", span: (3, 13)..(3, 14), contains: message);
+}
+
+#[test]
+fn test_key_definition_at_end_of_file() {
+    let contents = "
+super + b
+    firefox
+super + a
+";
+    assert_parse_error!(
+        contents,
+        span: (4, 1)..(4, 10),
+        contains: "key definition `super + a` at end of file has no command"
+    );
+}
+
+#[test]
+fn test_key_definition_continued_at_end_of_file() {
+    // Used to panic building the span of the last, empty line.
+    for contents in ["super\n#\\\nsuper", "super + a\\\n\n"] {
+        let result = std::panic::catch_unwind(|| SwhkdParser::from(ParserInput::Raw(contents)));
+        assert!(
+            matches!(result, Ok(Err(ParseError::Grammar(_)))),
+            "{contents:?}"
+        );
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_utf8_config_path() {
+    use std::os::unix::ffi::OsStrExt;
+    let path = Path::new(std::ffi::OsStr::from_bytes(b"/tmp/swhkd\xff.rc"));
+    let Err(ParseError::ConfigRead(ConfigReadError::NonUtf8Path(found))) =
+        SwhkdParser::from(ParserInput::Path(path))
+    else {
+        panic!("a non-UTF-8 path must not be read as an empty one");
+    };
+    assert_eq!(found, path);
+    assert!(matches!(
+        sweet::from_sxhkd(ParserInput::Path(path)),
+        Err(ParseError::ConfigRead(ConfigReadError::NonUtf8Path(_)))
+    ));
+}
+
+#[test]
+fn test_unterminated_mode_at_end_of_file() {
+    let contents = "
+mode resize oneoff
+super + h
+    swaymsg resize shrink width
+";
+    assert_parse_error!(
+        contents,
+        span: (2, 1)..(2, 19),
+        contains: "unterminated mode block, `mode resize oneoff` is never closed with `endmode`"
+    );
+    // Without a newline, pest gives up in the middle of the last command.
+    assert_parse_error!(
+        "mode foo\nsuper + a\n    echo",
+        span: (1, 1)..(1, 9),
+        contains: "unterminated mode block, `mode foo` is never closed with `endmode`"
+    );
+    assert_parse_error!(
+        "[desktop] mode foo\nsuper + a\n    echo",
+        span: (1, 11)..(1, 19),
+        contains: "unterminated mode block, `mode foo` is never closed with `endmode`"
+    );
+    // A closed mode is not blamed for what follows.
+    assert!(SwhkdParser::from(ParserInput::Raw("mode foo\nsuper + a\n    echo\nendmode")).is_ok());
+}
+
+const PROFILES_CONFIG: &str = "
+[laptop] brightnessup
+    brightnessctl s +5%
+[desktop] f2
+    ddcutil setvcp 10 + 10
+super + return
+    foot
+[desktop] mode gaming
+super + g
+    steam
+endmode
+mode media
+[laptop] f3
+    playerctl play-pause
+endmode";
+
+#[test]
+fn test_active_profile() -> Result<(), ParseError> {
+    let options = ParseOptions::default().with_profile("laptop");
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(PROFILES_CONFIG), &options)?;
+    assert_eq!(
+        parsed.bindings,
+        [
+            binding!("brightnessup" => "brightnessctl s +5%"),
+            binding!("super + return" => "foot"),
+        ]
+    );
+    assert_eq!(parsed.modes.len(), 1);
+    assert_eq!(
+        parsed.modes[0].bindings,
+        [binding!("f3" => "playerctl play-pause")]
+    );
+    assert_eq!(
+        parsed.inactive,
+        [
+            binding!("f2" => "ddcutil setvcp 10 + 10"),
+            binding!("super + g" => "steam"),
+        ]
+    );
+    assert_eq!(
+        parsed.profiles,
+        ["desktop".to_string(), "laptop".to_string()].into()
+    );
+    assert_eq!(
+        parsed.warnings,
+        [Warning::InactiveProfile("desktop".to_string())]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_no_active_profiles() -> Result<(), ParseError> {
+    let parsed = SwhkdParser::from(ParserInput::Raw(PROFILES_CONFIG))?;
+    assert_eq!(parsed.bindings, [binding!("super + return" => "foot")]);
+    assert_eq!(parsed.modes.len(), 1);
+    assert!(parsed.modes[0].bindings.is_empty());
+    assert_eq!(parsed.inactive.len(), 4);
+    assert_eq!(
+        parsed.warnings,
+        [
+            Warning::InactiveProfile("desktop".to_string()),
+            Warning::InactiveProfile("laptop".to_string()),
+        ]
+    );
+
+    let options = ParseOptions::default().with_profile("tablet");
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw("a\n    true"), &options)?;
+    assert_eq!(
+        parsed.warnings,
+        [Warning::UnknownProfile("tablet".to_string())]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_inactive_profile_include_is_not_read() -> Result<(), IoOrParseError> {
+    let dir = tempfile::tempdir()?;
+    let missing = dir.path().join("missing.swhkd");
+    let contents = format!(
+        "[desktop] include {}\nsuper + a\n    true",
+        missing.display()
+    );
+    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    assert_eq!(parsed.bindings, [binding!("super + a" => "true")]);
+    assert!(parsed.imports.is_empty());
+
+    let options = ParseOptions::default().with_profile("desktop");
+    let Err(ParseError::ImportFailed { path, .. }) =
+        SwhkdParser::from_with_options(ParserInput::Raw(&contents), &options)
+    else {
+        panic!("expected the include of the active profile to be read")
+    };
+    assert_eq!(path, missing);
+    Ok(())
+}
+
+#[test]
+fn test_generated_parser_is_up_to_date() {
+    let committed = std::fs::read_to_string(sweet::generate::GENERATED_PATH).unwrap();
+    assert!(
+        committed == sweet::generate::generated_parser(),
+        "{} is out of date, run `cargo regenerate-grammar`",
+        sweet::generate::GENERATED_PATH
+    );
+}
+
+#[test]
+fn test_display_with_styles() {
+    let chords = [
+        "super + shift + return",
+        "ctrl + alt + comma",
+        "super + ~@a",
+        "any + f5",
+        "super + @alt!",
+        "virtual:lid_close",
+    ];
+    let render = |style: &dyn ChordStyle| {
+        chords
+            .iter()
+            .map(|chord| chord.parse::<Definition>().unwrap().display_with(style))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        render(&DefaultStyle),
+        [
+            "super+shift+return",
+            "alt+control+comma",
+            "super+~@a",
+            "any+f5",
+            "super+@alt!",
+            "virtual:lid_close",
+        ]
+    );
+    assert_eq!(
+        render(&MacStyle),
+        ["⌘⇧↩", "⌥⌃,", "⌘~@A", "*F5", "⌘@⌥!", "virtual:lid_close"]
+    );
+    assert_eq!(
+        render(&CompactStyle),
+        [
+            "s-S-return",
+            "M-C-comma",
+            "s-~@a",
+            "*-f5",
+            "s-@M!",
+            "virtual:lid_close",
+        ]
+    );
+}
+
+#[test]
+fn test_display_with_custom_style() {
+    struct German;
+    impl ChordStyle for German {
+        fn modifier(&self, modifier: sweet::Modifier) -> Cow<'_, str> {
+            match modifier {
+                Control => "Strg".into(),
+                modifier => DefaultStyle.modifier(modifier).into_owned().into(),
+            }
+        }
+        fn key(&self, key: &Key) -> Cow<'_, str> {
+            DefaultStyle.key(key).to_uppercase().into()
+        }
+        fn separator(&self) -> &str {
+            " + "
+        }
+    }
+
+    let definition = "ctrl + shift + t".parse::<Definition>().unwrap();
+    assert_eq!(definition.display_with(&German), "Strg + shift + T");
+}
+
+#[test]
+fn test_homoglyph_in_key_position() {
+    assert_parse_error!(
+        "super\u{a0}+ a\n    true",
+        span: (1, 6)..(1, 7),
+        contains: "found Unicode character U+00A0 'NO-BREAK SPACE'; did you mean ' '?"
+    );
+    assert_parse_error!(
+        "super + {1\u{2013}3}\n    true",
+        span: (1, 11)..(1, 12),
+        contains: "found Unicode character U+2013 'EN DASH'; did you mean '-'?"
+    );
+    assert_parse_error!(
+        "super + \u{2019}\n    true",
+        span: (1, 9)..(1, 10),
+        contains: "U+2019 'RIGHT SINGLE QUOTATION MARK'; did you mean '''?"
+    );
+    assert_parse_error!(
+        "super +\u{200b} a\n    true",
+        span: (1, 8)..(1, 9),
+        contains: "U+200B 'ZERO WIDTH SPACE', which is invisible; did you mean to remove it?"
+    );
+}
+
+#[test]
+fn test_homoglyph_in_command_position() -> Result<(), ParseError> {
+    let contents =
+        "super + a\n    notify-send \u{201c}it\u{2019}s\u{a0}done\u{201d} \u{2013} \u{200b}ok";
+    assert_parses_to!(
+        contents,
+        [
+            binding!("super + a" => "notify-send \u{201c}it\u{2019}s\u{a0}done\u{201d} \u{2013} \u{200b}ok")
+        ]
+    );
+    assert_eq!(fix_homoglyphs(contents), contents);
+    Ok(())
+}
+
+#[test]
+fn test_fix_homoglyphs() -> Result<(), ParseError> {
+    let contents = "super\u{a0}+ {1\u{2014}3}\u{200b}\n    echo \u{2018}{1-3}\u{2019}";
+    let fixed = fix_homoglyphs(contents);
+    assert_eq!(fixed, "super + {1-3}\n    echo \u{2018}{1-3}\u{2019}");
+    SwhkdParser::from(ParserInput::Raw(&fixed))?;
+    Ok(())
+}
+
+#[test]
+fn test_fix_command() -> Result<(), std::io::Error> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("swhkdrc");
+    let contents = "super\u{a0}+ a\n    firefox\n";
+    std::fs::write(&path, contents)?;
+    let fix = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_sweet"))
+            .arg("fix")
+            .args(args)
+            .arg(&path)
+            .output()
+            .unwrap()
+    };
+
+    // Without `--write`, the changes are only printed.
+    let output = fix(&["--unicode"]);
+    assert_eq!(output.status.code(), Some(0));
+    let diff = String::from_utf8_lossy(&output.stdout);
+    assert!(diff.contains("-super\u{a0}+ a\n"));
+    assert!(diff.contains("+super + a\n"));
+    assert_eq!(std::fs::read_to_string(&path)?, contents);
+
+    let output = fix(&["--unicode", "--write"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), diff);
+    assert_eq!(std::fs::read_to_string(&path)?, "super + a\n    firefox\n");
+    let output = fix(&["--unicode"]);
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with(": nothing to fix\n"));
+    Ok(())
+}
+
+#[test]
+fn test_replay_in_swallow_mode() -> Result<(), ParseError> {
+    let contents = "
+mode vim swallow
+replay {a-c}
+replay super + ~return
+super + h
+    move left
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let mode = &parsed.modes[0];
+    assert_eq!(
+        mode.replay,
+        [
+            Definition::new(evdev::Key::KEY_A),
+            Definition::new(evdev::Key::KEY_B),
+            Definition::new(evdev::Key::KEY_C),
+            "super + ~return".parse()?,
+        ]
+    );
+    assert!(mode.unbinds.is_empty());
+    assert_eq!(mode.bindings.len(), 1);
+    assert!(parsed.warnings.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_replay_without_swallow_warns() -> Result<(), ParseError> {
+    let contents = "
+mode vim
+replay a
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_eq!(parsed.modes[0].replay, [Definition::new(evdev::Key::KEY_A)]);
+    let [Warning::EmptyMode { name, .. }, Warning::ReplayWithoutSwallow(mode)] =
+        parsed.warnings.as_slice()
+    else {
+        panic!("unexpected warnings {:?}", parsed.warnings);
+    };
+    assert_eq!((name.as_str(), mode.as_str()), ("vim", "vim"));
+
+    let options = ParseOptions::default().with_collect_warnings(false);
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(contents), &options)?;
+    assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+    Ok(())
+}
+
+#[test]
+fn test_definitions_never_hold_omission() -> Result<(), ParseError> {
+    let built = Definition::new(evdev::Key::KEY_A).with_modifiers(&[Omission, Super]);
+    assert_eq!(*built.modifiers(), [Super].into());
+    let modifiers_only = Definition::modifiers_only(&[Omission, Alt], KeyAttribute::None);
+    assert_eq!(*modifiers_only.modifiers(), [Alt].into());
+
+    let parsed = SwhkdParser::from(ParserInput::Raw("{_, shift +} a\n    {plain, shifted}"))?;
+    for binding in &parsed.bindings {
+        assert!(!binding.definition().modifiers().contains(&Omission));
+    }
+
+    // `any` already matches every set of modifiers.
+    let any = Definition::new(evdev::Key::KEY_A).with_modifiers(&[Super, Any]);
+    assert_eq!(*any.modifiers(), [Any].into());
+    assert_eq!("any + super + a".parse::<Definition>()?, any);
+    Ok(())
+}
+
+#[test]
+fn test_source_text_shared_across_variants() -> Result<(), ParseError> {
+    let contents = "
+Super +   {1-9}  # workspaces
+    echo {1-9}";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_eq!(parsed.bindings.len(), 9);
+    let first = parsed.bindings[0].source_text();
+    assert_eq!(first, "Super +   {1-9}");
+    assert!(parsed
+        .bindings
+        .iter()
+        .all(|binding| std::ptr::eq(binding.source_text(), first)));
+    Ok(())
+}
+
+#[test]
+fn test_source_text_of_overriding_binding() -> Result<(), ParseError> {
+    let contents = "
+super + a
+    first
+[desktop] mod4+A
+    second";
+    let options = ParseOptions::default().with_profile("desktop");
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(contents), &options)?;
+    assert_eq!(parsed.bindings.len(), 1);
+    assert_eq!(parsed.bindings[0].command(), "second");
+    assert_eq!(parsed.bindings[0].source_text(), "mod4+A");
+    Ok(())
+}
+
+#[test]
+fn test_control_character_in_command() {
+    assert_parse_error!(
+        "super + a\n    printf '\u{1b}[1m'",
+        span: (2, 13)..(2, 14),
+        contains: "control character U+001B at byte 22"
+    );
+}
+
+#[test]
+fn test_tab_in_command() -> Result<(), ParseError> {
+    assert_parses_to!(
+        "super + a\n    printf 'a\tb'",
+        [binding!("super + a" => "printf 'a\tb'")]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_any_in_omission_shorthand_warns() -> Result<(), ParseError> {
+    let parsed = SwhkdParser::from(ParserInput::Raw("\n{_, any +} a\n    echo {plain, any}"))?;
+    let [Warning::AnyInOmission(span)] = parsed.warnings.as_slice() else {
+        panic!("unexpected warnings {:?}", parsed.warnings);
+    };
+    assert_eq!((span.line, span.column), (2, 1));
+    assert_eq!((span.end_line, span.end_column), (2, 11));
+    assert!(parsed.warnings[0]
+        .to_string()
+        .contains("the `_` variant fires when no modifier is held"));
+    // Both variants are reachable, an exact match beating `any`.
+    assert!(parsed.unreachable_bindings().is_empty());
+
+    let parsed = SwhkdParser::from(ParserInput::Raw("{_, shift +} a\n    echo {plain, shift}"))?;
+    assert!(parsed.warnings.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_any_in_omission_shorthand_replaces_modifiers() -> Result<(), ParseError> {
+    let contents = "super + {_, any + shift +} a\n    echo {super, any}";
+    // `any` replaces `super` as well as the `shift` of its own variant.
+    assert_parses_to!(
+        contents,
+        [
+            binding!("super + a" => "echo super"),
+            binding!("any + a" => "echo any"),
+        ]
+    );
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let [Warning::AnyReplacesModifiers { span, modifiers }, Warning::AnyInOmission(_)] =
+        parsed.warnings.as_slice()
+    else {
+        panic!("unexpected warnings {:?}", parsed.warnings);
+    };
+    assert_eq!((span.line, span.column), (1, 9));
+    assert_eq!(modifiers, &[Super, Shift]);
+    assert!(parsed.warnings[0]
+        .to_string()
+        .contains("the `any` variant leaves out `super`, `shift`"));
+
+    // Without other modifiers, nothing is left out.
+    let parsed = SwhkdParser::from(ParserInput::Raw("{_, any +} a\n    echo {plain, any}"))?;
+    assert!(!parsed
+        .warnings
+        .iter()
+        .any(|warning| matches!(warning, Warning::AnyReplacesModifiers { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_mode_instruction_placement() -> Result<(), ParseError> {
+    let contents = "
+super + r
+    @enter resize && notify-send resizing
+super + e
+    notify-send editing && @enter edit
+super + x
+    @enter first && notify-send switching && @enter second
+mode resize
+    escape
+        @escape
+endmode
+mode edit
+    escape
+        @escape
+endmode
+mode first
+    escape
+        @escape
+endmode
+mode second
+    escape
+        @escape
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let enter = |mode: &str| ModeInstruction::Enter(mode.to_string());
+    assert_eq!(
+        parsed.bindings,
+        [
+            binding!("super + r" => " notify-send resizing")
+                .with_mode_instructions_before_command(vec![enter("resize")]),
+            binding!("super + e" => "notify-send editing ")
+                .with_mode_instructions(vec![enter("edit")]),
+            binding!("super + x" => " notify-send switching ")
+                .with_mode_instructions_before_command(vec![enter("first")])
+                .with_mode_instructions(vec![enter("second")]),
+        ]
+    );
+    assert_eq!(
+        parsed.bindings[0].instructions_before_command(),
+        [enter("resize")]
+    );
+    assert!(parsed.bindings[0].instructions_after_command().is_empty());
+    assert_eq!(
+        parsed.bindings[2].mode_instructions(),
+        [enter("first"), enter("second")]
+    );
+
+    // Without a command the instructions come after it.
+    let escape = &parsed.modes[0].bindings[0];
+    assert!(escape.instructions_before_command().is_empty());
+    assert_eq!(
+        escape.instructions_after_command(),
+        [ModeInstruction::Escape]
+    );
+    assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+    Ok(())
+}
+
+#[test]
+fn test_mode_instruction_warnings() -> Result<(), ParseError> {
+    let contents = "
+super + {@r, @t}
+    notify-send {resizing, tiling} && @enter resize
+super + @m
+    @enter resize
+mode resize
+    escape
+        @escape
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let [Warning::CommandRunsOnRelease(span)] = parsed.warnings.as_slice() else {
+        panic!("unexpected warnings {:?}", parsed.warnings);
+    };
+    assert_eq!((span.line, span.column), (2, 1));
+    assert!(parsed.warnings[0]
+        .to_string()
+        .contains("also runs on release"));
+
+    let parsed = SwhkdParser::from(ParserInput::Raw("super + q\n    @escape"))?;
+    let [Warning::EscapeWithoutModes(span)] = parsed.warnings.as_slice() else {
+        panic!("unexpected warnings {:?}", parsed.warnings);
+    };
+    assert_eq!((span.line, span.column), (1, 1));
+
+    // Declared modes are never entered without an `@enter`.
+    let parsed = SwhkdParser::from(ParserInput::Raw(
+        "super + q\n    @escape\nmode resize\n    escape\n        @escape\nendmode",
+    ))?;
+    let lines: Vec<_> = parsed
+        .warnings
+        .iter()
+        .map(|warning| match warning {
+            Warning::EscapeWithoutEnter { span, mode } => (span.line, mode.as_deref()),
+            warning => panic!("unexpected warning {warning:?}"),
+        })
+        .collect();
+    assert_eq!(lines, [(1, None), (4, Some("resize"))]);
+    assert!(parsed.warnings[0]
+        .to_string()
+        .contains("no binding of the config enters a mode"));
+
+    let parsed = SwhkdParser::from(ParserInput::Raw(
+        "super + q\n    @escape\nsuper + r\n    @enter resize\nmode resize\n    escape\n        @escape\nendmode",
+    ))?;
+    assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+
+    // Entering one mode does not make the `@escape` of another one fire,
+    // starting in a mode does.
+    let contents = "
+set start_mode launcher
+super + r
+    @enter resize
+mode resize
+    escape
+        @escape
+endmode
+mode gaming
+    escape
+        @escape
+endmode
+mode launcher
+    escape
+        @escape
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let [Warning::EscapeWithoutEnter {
+        span,
+        mode: Some(mode),
+    }] = parsed.warnings.as_slice()
+    else {
+        panic!("unexpected warnings {:?}", parsed.warnings);
+    };
+    assert_eq!((span.line, mode.as_str()), (10, "gaming"));
+    assert!(parsed.warnings[0]
+        .to_string()
+        .contains("no binding enters mode `gaming`"));
+    Ok(())
+}
+
+#[test]
+fn test_duplicate_modes_merge() -> Result<(), IoOrParseError> {
+    let mut import = tempfile::NamedTempFile::new()?;
+    import
+        .write_all(b"mode resize swallow\n    h\n        grow\n    l\n        shrink\nendmode")?;
+    let mut setup = tempfile::NamedTempFile::new()?;
+    write!(
+        setup,
+        "
+super + r
+    @enter resize
+mode resize oneoff
+    h
+        shrink
+    escape
+        @escape
+endmode
+include {}",
+        import.path().display()
+    )?;
+
+    let parsed = SwhkdParser::from(ParserInput::Path(setup.path()))?;
+    let [resize] = parsed.modes.as_slice() else {
+        panic!("expected one mode, got {:?}", parsed.modes);
+    };
+    assert!(resize.oneoff && resize.swallow);
+    let commands: Vec<_> = resize.bindings.iter().map(Binding::command).collect();
+    // `h` of the include overrides `h` of the including file.
+    assert_eq!(commands, ["", "grow", "shrink"]);
+    Ok(())
+}
+
+#[test]
+fn test_unknown_modes() -> Result<(), IoOrParseError> {
+    // A mode declared in an include can be entered from the including file.
+    let mut import = tempfile::NamedTempFile::new()?;
+    import.write_all(b"mode resize\n    escape\n        @escape\nendmode")?;
+    let mut setup = tempfile::NamedTempFile::new()?;
+    write!(
+        setup,
+        "include {}\nsuper + r\n    @enter resize",
+        import.path().display()
+    )?;
+    let parsed = SwhkdParser::from(ParserInput::Path(setup.path()))?;
+    assert_eq!(parsed.modes[0].name, "resize");
+
+    // Unless the include is not read, which leaves the mode undeclared.
+    let options = ParseOptions::default().with_follow_imports(ImportPolicy::Ignore);
+    let err =
+        SwhkdParser::from_with_options(ParserInput::Path(setup.path()), &options).unwrap_err();
+    let ParseError::UnknownMode { name, declared, .. } = &err else {
+        panic!("expected an unknown mode, got {err:?}");
+    };
+    assert_eq!((name.as_str(), declared.len()), ("resize", 0));
+
+    let err = SwhkdParser::from(ParserInput::Raw(
+        "super + r\n    @enter rezise\nmode resize\n    escape\n        @enter normal\nendmode\nmode normal\n    a\n        true\nendmode",
+    ))
+    .unwrap_err();
+    let ParseError::UnknownMode { name, declared, .. } = &err else {
+        panic!("expected an unknown mode, got {err:?}");
+    };
+    assert_eq!(name, "rezise");
+    assert_eq!(declared, &["resize", "normal"]);
+    assert_eq!(
+        err.to_string(),
+        "`@enter rezise` refers to an undeclared mode, declared modes are: resize, normal"
+    );
+
+    let err = SwhkdParser::from(ParserInput::Raw("super + r\n    @enter resize")).unwrap_err();
+    assert!(
+        err.to_string().contains("the config declares no modes"),
+        "{err}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_entered_modes() -> Result<(), IoOrParseError> {
+    // The mode is declared in an include read after the binding entering it.
+    let mut import = tempfile::NamedTempFile::new()?;
+    import.write_all(
+        b"mode launcher oneoff swallow\n    f\n        firefox\nendmode\nmode resize\n    q\n        @escape\nendmode",
+    )?;
+    let mut setup = tempfile::NamedTempFile::new()?;
+    write!(
+        setup,
+        "super + space\n    @enter launcher\nsuper + r\n    @enter resize\nsuper + Escape\n    @escape\ninclude {}",
+        import.path().display()
+    )?;
+    let parsed = SwhkdParser::from(ParserInput::Path(setup.path()))?;
+    let entered: Vec<_> = parsed
+        .bindings
+        .iter()
+        .flat_map(Binding::mode_instructions)
+        .map(|instruction| {
+            parsed
+                .entered_mode(instruction)
+                .map(|(index, mode)| (index, mode.name.as_str(), mode.oneoff, mode.swallow))
+        })
+        .collect();
+    assert_eq!(
+        entered,
+        [
+            Some((0, "launcher", true, true)),
+            Some((1, "resize", false, false)),
+            None
+        ]
+    );
+    // A top-level `@escape` leaves whichever mode is active, so it does not
+    // warn once the config declares modes.
+    assert_eq!(
+        parsed.modes[1].bindings[0].mode_instructions(),
+        [ModeInstruction::Escape]
+    );
+    assert_eq!(parsed.warnings, []);
+    Ok(())
+}
+
+/// Parses two bindings, returning the surviving commands and whether a
+/// warning about attribute variants was emitted.
+fn merge_pair(first: &str, second: &str) -> (Vec<String>, Option<bool>) {
+    let contents = format!("{first}\n    first\n{second}\n    second");
+    let parsed = SwhkdParser::from(ParserInput::Raw(&contents)).unwrap();
+    let commands = parsed
+        .bindings
+        .iter()
+        .map(|b| b.command().to_string())
+        .collect();
+    // Overrides within one file are warned about on their own.
+    let warnings: Vec<_> = parsed
+        .warnings
+        .iter()
+        .filter(|warning| !matches!(warning, Warning::OverriddenBinding { .. }))
+        .collect();
+    let warning = match warnings.as_slice() {
+        [] => None,
+        [Warning::AttributeVariants {
+            first,
+            second,
+            press_and_release,
+        }] => {
+            assert_eq!((first.line, second.line), (1, 3));
+            Some(*press_and_release)
+        }
+        warnings => panic!("unexpected warnings {warnings:?}"),
+    };
+    (commands, warning)
+}
+
+#[test]
+fn test_override_matrix() {
+    // Equal modifiers and attributes override.
+    assert_eq!(
+        merge_pair("super + a", "mod4 + a"),
+        (vec!["second".into()], None)
+    );
+    assert_eq!(
+        merge_pair("super + @a", "super + @a"),
+        (vec!["second".into()], None)
+    );
+    // Press and release coexist with a warning.
+    assert_eq!(
+        merge_pair("super + a", "super + @a"),
+        (vec!["first".into(), "second".into()], Some(true))
+    );
+    assert_eq!(
+        merge_pair("super + ~@a", "super + ~a"),
+        (vec!["first".into(), "second".into()], Some(true))
+    );
+    // Other attributes fire on the same event, with a warning.
+    assert_eq!(
+        merge_pair("super + a", "super + ~a"),
+        (vec!["first".into(), "second".into()], Some(false))
+    );
+    assert_eq!(
+        merge_pair("@super", "@super!"),
+        (vec!["first".into(), "second".into()], Some(false))
+    );
+    // `any` and exact modifiers are independent.
+    assert_eq!(
+        merge_pair("any + a", "super + a"),
+        (vec!["first".into(), "second".into()], None)
+    );
+    assert_eq!(
+        merge_pair("any + a", "super + @a"),
+        (vec!["first".into(), "second".into()], None)
+    );
+    // Different modifiers are independent.
+    assert_eq!(
+        merge_pair("super + a", "alt + @a"),
+        (vec!["first".into(), "second".into()], None)
+    );
+}
+
+#[test]
+fn test_override_and_unbind_order() -> Result<(), IoOrParseError> {
+    let mut included = tempfile::NamedTempFile::new()?;
+    included.write_all(
+        b"
+super + a
+    included
+ignore super + c
+ignore super + e ; f",
+    )?;
+    let contents = format!(
+        "
+super + a
+    first
+super + b
+    b
+super + c
+    c
+super + e ; f ; g
+    efg
+super + e ; g
+    eg
+super + a
+    second
+ignore super + b
+include {}
+super + b
+    b again",
+        included.path().display()
+    );
+    let mut root = tempfile::NamedTempFile::new()?;
+    root.write_all(contents.as_bytes())?;
+    let parsed = SwhkdParser::from(ParserInput::Path(root.path()))?;
+    let commands: Vec<_> = parsed.bindings.iter().map(Binding::command).collect();
+    // Overrides take the place of the binding they override, an `ignore`
+    // drops its chord wherever it is written, even before the binding.
+    assert_eq!(commands, ["included", "eg"]);
+    Ok(())
+}
+
+#[test]
+fn test_unbind_shorthands_and_ranges() -> Result<(), ParseError> {
+    let contents = "
+super + {1-9}
+    workspace {1-9}
+super + e ; {a, b}
+    sequence {a, b}
+ignore super + {1-3}
+ignore super + {5, 7}
+ignore super + e ; {a, c}
+mode resize
+    ignore alt + {a-c:2}
+    alt + {a, b}
+        {shrink, grow}
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let commands: Vec<_> = parsed.bindings.iter().map(Binding::command).collect();
+    assert_eq!(
+        commands,
+        [
+            "workspace 4",
+            "workspace 6",
+            "workspace 8",
+            "workspace 9",
+            "sequence b"
+        ]
+    );
+    let unbinds: Vec<_> = parsed.modes[0]
+        .unbinds
+        .iter()
+        .map(Definition::to_string)
+        .collect();
+    assert_eq!(unbinds, ["alt + a", "alt + c"]);
+    assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+    Ok(())
+}
+
+#[test]
+fn test_unbind_wildcards() -> Result<(), ParseError> {
+    let contents = "
+a
+    a
+super + a
+    super a
+ctrl + shift + a
+    ctrl shift a
+super + @a
+    super on release
+any + a
+    any a
+super + b
+    super b
+super + shift + c
+    super shift c
+super + d ; e
+    super d e
+alt + {f, g}
+    alt {f, g}
+ctrl + x ; y
+    ctrl x y
+ignore any + a
+ignore super + any_key
+ignore any + g
+ignore any + x ; y";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let commands: Vec<_> = parsed.bindings.iter().map(Binding::command).collect();
+    // `any` leaves the attributes alone, `any_key` takes them all.
+    assert_eq!(commands, ["super shift c", "alt f"]);
+    assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+
+    let unbind = &parsed.unbinds[1];
+    assert_eq!(unbind.to_string(), "super + any_key");
+    assert!(unbind.unbinds(&"super + @x".parse()?));
+    assert!(!unbind.unbinds(&"super + alt + x".parse()?));
+    assert!(parsed
+        .to_config_string()
+        .contains("\nignore super + any_key\n"));
+
+    let contents = "
+super + a
+    a
+ignore ctrl + any_key";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert!(matches!(parsed.warnings[..], [Warning::UnmatchedIgnore(_)]));
+    Ok(())
+}
+
+#[test]
+fn test_misplaced_any_key() {
+    assert_parse_error!("super + any_key\n    echo", span: (1, 9)..(1, 16), contains: "only applies to `ignore`");
+    assert_parse_error!(
+        "mode m\n    super + any_key\n        echo\nendmode",
+        span: (2, 13)..(2, 20),
+        contains: "only applies to `ignore`"
+    );
+    assert_parse_error!("ignore super + {any_key, b}", span: (1, 17)..(1, 24), contains: "every key");
+    assert_parse_error!("ignore super + any_key ; b", span: (1, 16)..(1, 23), contains: "every key");
+    assert_parse_error!("ignore @any_key", span: (1, 9)..(1, 16), contains: "every key");
+}
+
+#[test]
+fn test_merge_many_bindings() -> Result<(), ParseError> {
+    let modifiers = [
+        "",
+        "super + ",
+        "alt + ",
+        "ctrl + ",
+        "shift + ",
+        "super + alt + ",
+    ];
+    let chords: Vec<_> = modifiers
+        .iter()
+        .flat_map(|modifier| (1..=200).map(move |code| format!("{modifier}keycode({code})")))
+        .collect();
+    let mut contents = String::new();
+    for (i, chord) in chords.iter().enumerate() {
+        contents.push_str(&format!("{chord}\n    first {i}\n"));
+    }
+    for (i, chord) in chords.iter().enumerate().step_by(3) {
+        contents.push_str(&format!("{chord}\n    second {i}\n"));
+    }
+    for chord in chords.iter().step_by(5) {
+        contents.push_str(&format!("ignore {chord}\n"));
+    }
+    let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+    let commands: Vec<_> = parsed.bindings.iter().map(Binding::command).collect();
+    let known: Vec<_> = (0..chords.len())
+        .filter(|i| i % 5 != 0)
+        .map(|i| match i % 3 {
+            0 => format!("second {i}"),
+            _ => format!("first {i}"),
+        })
+        .collect();
+    assert_eq!(commands, known);
+    Ok(())
+}
+
+#[test]
+fn test_attribute_variants_in_mode() -> Result<(), ParseError> {
+    let contents = "
+mode media
+super + a
+    first
+super + @a
+    second
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let [Warning::AttributeVariants { first, second, .. }] = parsed.warnings.as_slice() else {
+        panic!("unexpected warnings {:?}", parsed.warnings);
+    };
+    assert_eq!((first.line, second.line), (3, 5));
+    assert!(parsed.warnings[0]
+        .to_string()
+        .contains("both fire, one on press and the other on release"));
+    Ok(())
+}
+
+#[test]
+fn test_scroll_triggers() -> Result<(), ParseError> {
+    let contents = "
+super + scroll_up
+    pamixer -i 2
+super + scroll_down
+    pamixer -d 2
+scroll_left
+    left
+shift + scroll_right
+    right";
+    assert_parses_to!(
+        contents,
+        [
+            Binding::running("pamixer -i 2")
+                .on(Definition::scroll(ScrollDirection::Up).with_modifiers(&[Super])),
+            Binding::running("pamixer -d 2")
+                .on(Definition::scroll(ScrollDirection::Down).with_modifiers(&[Super])),
+            Binding::running("left").on(Definition::scroll(ScrollDirection::Left)),
+            Binding::running("right")
+                .on(Definition::scroll(ScrollDirection::Right).with_modifiers(&[Shift])),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_scroll_rejects_attributes() {
+    assert_parse_error!(
+        "super + @scroll_up\n    true",
+        span: (1, 9)..(1, 19),
+        contains: "scroll events have no press or release"
+    );
+    assert_parse_error!(
+        "super + {~scroll_up, scroll_down}\n    {a, b}",
+        span: (1, 10)..(1, 20),
+        contains: "scroll events have no press or release"
+    );
+}
+
+#[test]
+fn test_scroll_shorthand() -> Result<(), ParseError> {
+    let contents = "
+super + {scroll_up,scroll_down}
+    volume {up,down}";
+    assert_parses_to!(
+        contents,
+        [
+            binding!("super + scroll_up" => "volume up"),
+            binding!("super + scroll_down" => "volume down"),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_unbind_scroll() -> Result<(), ParseError> {
+    let contents = "
+super + {scroll_up,scroll_down}
+    volume {up,down}
+ignore super + scroll_down";
+    assert_parses_to!(contents, [binding!("super + scroll_up" => "volume up")]);
+    Ok(())
+}
+
+#[test]
+fn test_max_config_bytes() -> Result<(), IoOrParseError> {
+    let contents = "super + a\n    true";
+    let options = ParseOptions::default().with_max_config_bytes(8);
+    let err = SwhkdParser::from_with_options(ParserInput::Raw(contents), &options).unwrap_err();
+    let ParseError::ConfigRead(ConfigReadError::ExceedsLimit { path, limit: 8 }) = &err else {
+        panic!("expected a config over the limit, got {err:?}")
+    };
+    assert_eq!(path, Path::new("<anonymous>"));
+    assert!(err
+        .to_string()
+        .contains("`<anonymous>` exceeds the size limit of 8 bytes"));
+
+    let mut included = tempfile::NamedTempFile::new()?;
+    included.write_all(format!("# {}\n{contents}", "-".repeat(64)).as_bytes())?;
+    let root = format!("include {}", included.path().display());
+    let options = ParseOptions::default().with_max_config_bytes(root.len() as u64);
+    let err = SwhkdParser::from_with_options(ParserInput::Raw(&root), &options).unwrap_err();
+    let ParseError::ImportFailed {
+        cause: ConfigReadError::ExceedsLimit { path, .. },
+        ..
+    } = &err
+    else {
+        panic!("expected an include over the limit, got {err:?}")
+    };
+    assert_eq!(path, included.path());
+
+    let options = ParseOptions::default().with_max_config_bytes(256);
+    SwhkdParser::from_with_options(ParserInput::Path(included.path()), &options)?;
+    Ok(())
+}
+
+#[test]
+fn test_max_config_bytes_above_built_in_cap() -> Result<(), IoOrParseError> {
+    // Past the 50 MiB that `build.rs` sets, in characters of 4 bytes to
+    // keep the grammar's work down.
+    let mut large = tempfile::NamedTempFile::new()?;
+    write!(large, "super + a\n    true\n# {}", "😀".repeat(51 << 18))?;
+    let err = SwhkdParser::from(ParserInput::Path(large.path())).unwrap_err();
+    let ParseError::ConfigRead(ConfigReadError::ExceedsLimit { path, limit }) = &err else {
+        panic!("expected a config over the built-in limit, got {err:?}")
+    };
+    assert_eq!((path.as_path(), *limit), (large.path(), 50 << 20));
+
+    let options = ParseOptions::default().with_max_config_bytes(200 << 20);
+    let parsed = SwhkdParser::from_with_options(ParserInput::Path(large.path()), &options)?;
+    assert_eq!(parsed.bindings[0].command(), "true");
+    Ok(())
+}
+
+#[test]
+fn test_import_policies() -> Result<(), IoOrParseError> {
+    let mut included = tempfile::NamedTempFile::new()?;
+    included.write_all(b"super + b\n    included")?;
+    let root = format!("super + a\n    root\ninclude {}", included.path().display());
+    let parse = |policy| {
+        let options = ParseOptions::default().with_follow_imports(policy);
+        SwhkdParser::from_with_options(ParserInput::Raw(&root), &options)
+    };
+
+    let parsed = parse(ImportPolicy::Follow)?;
+    assert_eq!(parsed.bindings.len(), 2);
+    assert_eq!(parsed.imports.len(), 1);
+
+    let parsed = parse(ImportPolicy::Ignore)?;
+    assert_eq!(parsed.bindings.len(), 1);
+    assert!(parsed.imports.is_empty());
+
+    let Err(ParseError::Grammar(err)) = parse(ImportPolicy::Deny) else {
+        panic!("expected includes to be denied");
+    };
+    assert_eq!(err.line(), root.lines().nth(2).unwrap());
+    assert!(err.to_string().contains("`include` is not allowed"));
+    Ok(())
+}
+
+/// The configs of `files` resolved from memory.
+fn map_options(files: &[(&str, &str)]) -> ParseOptions {
+    let files = files
+        .iter()
+        .map(|(path, contents)| (path.to_string(), contents.to_string()))
+        .collect();
+    ParseOptions::default().with_resolver(Arc::new(MapResolver(files)))
+}
+
+#[test]
+fn test_map_resolver_includes() -> Result<(), ParseError> {
+    // The same cycle as `test_circular_import`, in memory.
+    let options = map_options(&[
+        ("a", "a\n    a"),
+        ("b", "include a\nb\n    b"),
+        ("c", "include a\ninclude b\ninclude c\ninclude d\nc\n    c"),
+        ("d", "include c\nd\n    d"),
+    ]);
+    let parsed = SwhkdParser::from_with_options(ParserInput::Path(Path::new("d")), &options)?;
+    let known = vec![
+        Binding::running("d").on(Definition::new(evdev::Key::KEY_D)),
+        Binding::running("c").on(Definition::new(evdev::Key::KEY_C)),
+        Binding::running("b").on(Definition::new(evdev::Key::KEY_B)),
+        Binding::running("a").on(Definition::new(evdev::Key::KEY_A)),
+    ];
+    assert_equal_binding_set(parsed.bindings, known);
+    // The root config is included back by `c`.
+    assert_eq!(
+        parsed.imports.iter().collect::<Vec<_>>(),
+        vec!["a", "b", "c", "d"]
+    );
+
+    // Later includes override the including file, as on disk.
+    let options = map_options(&[("extra", "super + a\n    extra")]);
+    let parsed = SwhkdParser::from_with_options(
+        ParserInput::Raw("super + a\n    root\ninclude extra"),
+        &options,
+    )?;
+    assert_eq!(parsed.bindings.len(), 1);
+    assert_eq!(parsed.bindings[0].command(), "extra");
+    assert_eq!(&*parsed.bindings[0].span().unwrap().file, "extra");
+
+    // Modes entered in one file may be declared in another.
+    let options = map_options(&[("modes", "mode resize\nh\n    shrink\nendmode")]);
+    let config = "include modes\nsuper + r\n    @enter resize";
+    SwhkdParser::from_with_options(ParserInput::Raw(config), &options)?;
+    let Err(ParseError::UnknownMode { name, .. }) =
+        SwhkdParser::from_with_options(ParserInput::Raw(config), &map_options(&[("modes", "")]))
+    else {
+        panic!("expected an unknown mode");
+    };
+    assert_eq!(name, "resize");
+    Ok(())
+}
+
+#[test]
+fn test_include_in_mode() -> Result<(), ParseError> {
+    let options = map_options(&[
+        (
+            "resize",
+            "include arrows\nh\n    shrink\nignore left\nset shell /bin/bash",
+        ),
+        ("arrows", "left\n    shrink more"),
+    ]);
+    let config = "
+super + r
+    @enter resize
+mode resize oneoff
+    include resize
+    r
+        reset
+endmode";
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(config), &options)?;
+    assert_eq!(parsed.bindings.len(), 1);
+    let [mode] = &parsed.modes[..] else {
+        panic!("expected one mode, got {:?}", parsed.modes);
+    };
+    // The mode's own bindings come first, then the ones of its includes.
+    let commands: Vec<_> = mode.bindings.iter().map(Binding::command).collect();
+    assert_eq!(commands, ["reset", "shrink", "shrink more"]);
+    assert_eq!(mode.unbinds, ["left".parse::<Definition>()?]);
+    assert_eq!(mode.settings.shell.as_deref(), Some("/bin/bash"));
+    assert!(parsed.unbinds.is_empty());
+    assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+
+    // A mode may get all of its bindings from an include.
+    let config = "mode resize\n    include arrows\nendmode";
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(config), &options)?;
+    assert_eq!(parsed.modes[0].bindings[0].command(), "shrink more");
+    assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+
+    // Settings only valid at the top level are rejected.
+    let options = map_options(&[("resize", "set start_mode resize")]);
+    let config = "mode resize\n    include resize\nendmode";
+    assert!(SwhkdParser::from_with_options(ParserInput::Raw(config), &options).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_include_in_mode_rejects_modes() {
+    let options = map_options(&[("nested", "mode inner\nh\n    shrink\nendmode")]);
+    let config = "mode resize\n    include nested\nendmode";
+    let Err(ParseError::Grammar(err)) =
+        SwhkdParser::from_with_options(ParserInput::Raw(config), &options)
+    else {
+        panic!("expected the include to fail");
+    };
+    assert_eq!(err.path(), Some("nested"));
+    assert_eq!(
+        err.line_col,
+        pest::error::LineColLocation::Span((1, 6), (1, 11))
+    );
+    let message = err.to_string();
+    assert!(
+        message.contains("included in the block of mode `resize`, it cannot declare modes"),
+        "{message}"
+    );
+}
+
+#[test]
+fn test_ignore_mode() -> Result<(), ParseError> {
+    let shared = "
+super + r
+    @enter resize
+super + l
+    @enter launch
+mode resize
+    h
+        shrink
+endmode
+mode launch
+    f
+        firefox
+    r
+        @escape && @enter resize
+endmode";
+    let options = map_options(&[("shared", shared)]);
+    let config = "include shared\nignore mode resize";
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(config), &options)?;
+    // The bindings entering the mode go with it, wherever they are.
+    let names: Vec<_> = parsed.modes.iter().map(|mode| mode.name.as_str()).collect();
+    assert_eq!(names, ["launch"]);
+    assert_eq!(parsed.bindings.len(), 1);
+    assert_eq!(
+        parsed.bindings[0].mode_instructions(),
+        [ModeInstruction::Enter("launch".to_string())]
+    );
+    let commands: Vec<_> = parsed.modes[0]
+        .bindings
+        .iter()
+        .map(Binding::command)
+        .collect();
+    assert_eq!(commands, ["firefox"]);
+    assert_eq!(parsed.ignored_modes, ["resize".to_string()].into());
+    assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+
+    let config = "include shared\nignore mode retired";
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(config), &options)?;
+    assert_eq!(parsed.modes.len(), 2);
+    let [Warning::UnmatchedModeIgnore { name, span }] = &parsed.warnings[..] else {
+        panic!("expected an unmatched ignore, got {:?}", parsed.warnings);
+    };
+    assert_eq!((name.as_str(), span.line), ("retired", 2));
+
+    // Only the top level ignores modes.
+    assert_parse_error!(
+        "mode resize\n    ignore mode launch\n    h\n        shrink\nendmode",
+        span: (2, 5)..(2, 23),
+        contains: "only applies at the top level"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_include_in_mode_cycle() -> Result<(), ParseError> {
+    // The files included in the mode include one another, each is only
+    // read once.
+    let options = map_options(&[
+        ("resize", "include arrows\nh\n    shrink"),
+        ("arrows", "include resize\nleft\n    shrink more"),
+    ]);
+    let config = "a\n    a\nmode resize\n    include resize\nendmode";
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(config), &options)?;
+    assert_eq!(parsed.bindings.len(), 1);
+    let commands: Vec<_> = parsed.modes[0]
+        .bindings
+        .iter()
+        .map(Binding::command)
+        .collect();
+    assert_eq!(commands, ["shrink", "shrink more"]);
+    assert!(matches!(
+        parsed.warnings[..],
+        [Warning::DuplicateInclude { .. }]
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_ineffective_statement_warnings() -> Result<(), ParseError> {
+    let options = map_options(&[
+        ("colors", "super + c\n    pick-color"),
+        ("extra", "include colors\nsuper + a\n    extra"),
+    ]);
+    let config = "
+include colors
+include extra
+super + {a,b}
+    {first,other}
+super + b
+    second
+ignore super + z
+ignore super + c
+[laptop] super + x
+    battery
+ignore super + x
+mode empty
+    ignore super + q
+endmode";
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(config), &options)?;
+    let commands: Vec<_> = parsed.bindings.iter().map(Binding::command).collect();
+    assert_eq!(commands, ["extra", "second"]);
+    let sites: Vec<_> = parsed
+        .warnings
+        .iter()
+        .map(|warning| match warning {
+            Warning::DuplicateInclude { first, second } => {
+                vec![(&*first.file, first.line), (&*second.file, second.line)]
+            }
+            Warning::InactiveProfile(_) => vec![],
+            Warning::UnmatchedIgnore(span) => vec![(&*span.file, span.line)],
+            // `extra` overriding `super + a` of the config is how configs
+            // layer, it is not warned about.
+            Warning::OverriddenBinding { first, second } => {
+                vec![(&*first.file, first.line), (&*second.file, second.line)]
+            }
+            Warning::EmptyMode { name, span } => {
+                assert_eq!((name.as_str(), span.column), ("empty", 6));
+                vec![(&*span.file, span.line)]
+            }
+            warning => panic!("unexpected warning {warning:?}"),
+        })
+        .collect();
+    assert_eq!(
+        sites,
+        [
+            vec![("<anonymous>", 2), ("extra", 1)],
+            vec![],
+            vec![("<anonymous>", 8)],
+            vec![("<anonymous>", 14)],
+            vec![("<anonymous>", 4), ("<anonymous>", 6)],
+            vec![("<anonymous>", 13)],
+        ]
+    );
+    let rendered = parsed.warnings[2].to_string();
+    assert!(
+        rendered.contains("no binding of the config matches it"),
+        "{rendered}"
+    );
+    let rendered = parsed.warnings[4].to_string();
+    assert!(rendered.contains("this binding never fires"), "{rendered}");
+    Ok(())
+}
+
+#[test]
+fn test_unmatched_ignores_in_modes() -> Result<(), ParseError> {
+    let options = map_options(&[("keys", "ignore h\nignore k")]);
+    let config = "
+super + q
+    quit
+[laptop] super + b
+    battery
+mode resize
+    h
+        shrink
+    ignore h
+    ignore j
+    ignore super + q
+    ignore super + b
+    include keys
+endmode
+mode resize
+    k
+        grow
+endmode
+[laptop] mode hidden
+    ignore x
+endmode";
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(config), &options)?;
+    let unmatched: Vec<_> = parsed
+        .warnings
+        .iter()
+        .filter_map(|warning| match warning {
+            Warning::UnmatchedIgnore(span) => Some((&*span.file, span.line)),
+            _ => None,
+        })
+        .collect();
+    // Top-level bindings, bindings of another declaration of the mode and
+    // bindings of inactive profiles match, and the statements of a mode
+    // left out are not checked.
+    assert_eq!(unmatched, [("<anonymous>", 10)]);
+    Ok(())
+}
+
+#[test]
+fn test_map_resolver_errors() -> Result<(), ParseError> {
+    let config = "super + b\n    firefox\ninclude missing.swhkd";
+    let err =
+        SwhkdParser::from_with_options(ParserInput::Raw(config), &map_options(&[])).unwrap_err();
+    let ParseError::ImportFailed {
+        path,
+        cause: ConfigReadError::ReadingConfig(_),
+        requested_at,
+    } = &err
+    else {
+        panic!("expected an import failure, got {err:?}")
+    };
+    assert_eq!(path, Path::new("missing.swhkd"));
+    assert_eq!((requested_at.line, requested_at.column), (3, 1));
+    assert!(err.to_string().contains("3 | include missing.swhkd"));
+
+    let options =
+        map_options(&[("big", "super + a\n    a very long command")]).with_max_config_bytes(24);
+    let err =
+        SwhkdParser::from_with_options(ParserInput::Raw("include big"), &options).unwrap_err();
+    let ParseError::ImportFailed {
+        cause: ConfigReadError::ExceedsLimit { path, limit: 24 },
+        ..
+    } = &err
+    else {
+        panic!("expected an include over the limit, got {err:?}")
+    };
+    assert_eq!(path, Path::new("big"));
+    Ok(())
+}
+
+#[test]
+fn test_custom_resolver_error() {
+    #[derive(Debug)]
+    struct Offline;
+
+    impl ImportResolver for Offline {
+        fn resolve(
+            &self,
+            requested: &str,
+            _: Option<&Path>,
+        ) -> Result<ResolvedImport, ConfigReadError> {
+            Err(ConfigReadError::Resolver(
+                format!("`{requested}` is not cached while offline").into(),
+            ))
+        }
+    }
+
+    let options = ParseOptions::default().with_resolver(Arc::new(Offline));
+    let config = "super + a\n    a\n\ninclude remote.swhkd";
+    let err = SwhkdParser::from_with_options(ParserInput::Raw(config), &options).unwrap_err();
+    let ParseError::ImportFailed {
+        cause: ConfigReadError::Resolver(cause),
+        requested_at,
+        ..
+    } = &err
+    else {
+        panic!("expected an import failure, got {err:?}")
+    };
+    assert_eq!(
+        cause.to_string(),
+        "`remote.swhkd` is not cached while offline"
+    );
+    assert_eq!((requested_at.line, requested_at.column), (4, 1));
+    assert_eq!(&*requested_at.file, "<anonymous>");
+    assert!(err.to_string().contains("is not cached while offline"));
+
+    // The root config is read through the resolver too.
+    let err =
+        SwhkdParser::from_with_options(ParserInput::Path(Path::new("root")), &options).unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::ConfigRead(ConfigReadError::Resolver(_))
+    ));
+}
+
+#[test]
+fn test_collect_warnings() -> Result<(), ParseError> {
+    let contents = "super + a\n    press\nsuper + @a\n    release";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_eq!(parsed.warnings.len(), 1);
+
+    let options = ParseOptions::default().with_collect_warnings(false);
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(contents), &options)?;
+    assert!(parsed.warnings.is_empty());
+    assert_eq!(parsed.bindings.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_parse_options_builder() {
+    // `ParseOptions` is non-exhaustive, so callers outside the crate can
+    // only build it from the default and the setters.
+    let options = ParseOptions::default()
+        .with_profile("laptop")
+        .with_max_config_bytes(1024)
+        .with_follow_imports(ImportPolicy::Ignore)
+        .with_collect_warnings(false);
+    assert!(options.active_profiles.contains("laptop"));
+    let resolver: Arc<dyn ImportResolver> = Arc::new(MapResolver::default());
+    let with_resolver = options.clone().with_resolver(resolver.clone());
+    assert_ne!(with_resolver, options);
+    assert_eq!(with_resolver, options.clone().with_resolver(resolver));
+    assert_eq!(options.max_config_bytes, Some(1024));
+    assert_eq!(options.follow_imports, ImportPolicy::Ignore);
+    assert!(!options.collect_warnings);
+
+    let defaults = ParseOptions::default();
+    assert_eq!(defaults.max_config_bytes, None);
+    assert_eq!(defaults.follow_imports, ImportPolicy::Follow);
+    assert!(defaults.collect_warnings);
+}
+
+#[test]
+fn test_error_summaries() {
+    let defaults = ParseOptions::default();
+    assert_eq!(
+        (defaults.max_errors, defaults.max_report_bytes),
+        (50, 64 << 10)
+    );
+    let options = ParseOptions::default()
+        .with_max_errors(0)
+        .with_max_report_bytes(1024);
+    assert_eq!((options.max_errors, options.max_report_bytes), (0, 1024));
+
+    let repeated = ParseError::Repeated {
+        error: Box::new(ParseError::InvalidKey("nope".to_string())),
+        count: 151,
+        first_line: 100,
+        last_line: 250,
+    };
+    assert_eq!(
+        repeated.to_string(),
+        "lines 100-250: `nope` is not recognized as a valid evdev key (×151)"
+    );
+    assert_eq!(
+        ParseError::MoreErrors(950).to_string(),
+        "... and 950 more errors; rerun with --max-errors=0 for all"
+    );
+}
+
+#[test]
+fn test_generated_key_table_is_up_to_date() {
+    let committed = std::fs::read_to_string(sweet::generate::GENERATED_KEYS_PATH).unwrap();
+    assert!(
+        committed == sweet::generate::generated_key_table(),
+        "{} is out of date, run `cargo regenerate-keys`",
+        sweet::generate::GENERATED_KEYS_PATH
+    );
+}
+
+#[test]
+fn test_curated_key_names_unchanged() {
+    let fixture = std::fs::read_to_string("tests/fixtures/key_aliases.txt").unwrap();
+    for line in fixture.lines().filter(|line| !line.starts_with('#')) {
+        let (name, code) = line.rsplit_once(' ').unwrap();
+        let code: u16 = code.parse().unwrap();
+        assert_eq!(
+            convert(name).map(|key| key.code()).ok(),
+            Some(code),
+            "`{name}`"
+        );
+    }
+}
+
+#[test]
+fn test_media_key_aliases() -> Result<(), ParseError> {
+    let parsed = SwhkdParser::from(ParserInput::Raw(
+        "XF86AudioPlay\n    mpc toggle\nsuper + playpause\n    mpc toggle",
+    ))?;
+    let play = Definition::new(evdev::Key::KEY_PLAYPAUSE);
+    assert_eq!(*parsed.bindings[0].definition(), play);
+    assert_eq!(
+        *parsed.bindings[1].definition(),
+        play.with_modifiers(&[Super])
+    );
+    for (name, key) in [
+        ("XF86AudioRaiseVolume", evdev::Key::KEY_VOLUMEUP),
+        ("XF86AudioLowerVolume", evdev::Key::KEY_VOLUMEDOWN),
+        ("XF86AudioMute", evdev::Key::KEY_MUTE),
+        ("XF86AudioNext", evdev::Key::KEY_NEXTSONG),
+        ("XF86AudioPrev", evdev::Key::KEY_PREVIOUSSONG),
+        ("XF86MonBrightnessUp", evdev::Key::KEY_BRIGHTNESSUP),
+        ("XF86MonBrightnessDown", evdev::Key::KEY_BRIGHTNESSDOWN),
+        ("XF86Search", evdev::Key::KEY_SEARCH),
+        ("XF86Explorer", evdev::Key::KEY_FILE),
+        ("XF86Calculator", evdev::Key::KEY_CALC),
+        ("nextsong", evdev::Key::KEY_NEXTSONG),
+        ("previoussong", evdev::Key::KEY_PREVIOUSSONG),
+        ("stopcd", evdev::Key::KEY_STOPCD),
+        ("micmute", evdev::Key::KEY_MICMUTE),
+        ("sleep", evdev::Key::KEY_SLEEP),
+        ("wakeup", evdev::Key::KEY_WAKEUP),
+        ("power", evdev::Key::KEY_POWER),
+    ] {
+        let config = format!("super + {name}\n    true");
+        let parsed = SwhkdParser::from(ParserInput::Raw(&config))?;
+        let expected = Definition::new(key).with_modifiers(&[Super]);
+        assert_eq!(*parsed.bindings[0].definition(), expected, "{name}");
+    }
+    // Plain names are preferred when rendering.
+    assert_eq!(key_name(evdev::Key::KEY_PLAYPAUSE), Some("playpause"));
+    assert_eq!(key_name(evdev::Key::KEY_CALC), Some("xf86calculator"));
+    Ok(())
+}
+
+#[test]
+fn test_kernel_key_names() -> Result<(), ParseError> {
+    let codes = sweet::generate::kernel_key_codes();
+    for key in [
+        evdev::Key::KEY_ESC,
+        evdev::Key::KEY_F24,
+        evdev::Key::KEY_MICMUTE,
+        evdev::Key::KEY_FN_F1,
+        evdev::Key::KEY_PROG1,
+    ] {
+        let name = format!("{key:?}");
+        assert_eq!(codes[&name], key.code(), "{name}");
+        assert_eq!(convert(&name.to_lowercase())?, key);
+    }
+    // Newer than the evdev crate.
+    let macro1 = evdev::Key::new(0x290);
+    assert_eq!(codes["KEY_MACRO1"], macro1.code());
+    // Defined in terms of another key.
+    assert_eq!(codes["KEY_HANGUEL"], evdev::Key::KEY_HANGEUL.code());
+
+    assert_parses_to!(
+        "super + KEY_MACRO1\n    macro",
+        [Binding::running("macro").on(Definition::new(macro1).with_modifiers(&[Super]))]
+    );
+    assert_eq!(name_of(macro1), Some("key_macro1"));
+    assert_eq!(name_of(evdev::Key::KEY_ESC), Some("escape"));
+    Ok(())
+}
+
+#[test]
+fn test_raw_key_codes() -> Result<(), ParseError> {
+    let prog1 = |attribute| {
+        Definition::new(evdev::Key::KEY_PROG1)
+            .with_attribute(attribute)
+            .with_modifiers(&[Super])
+    };
+    assert_parses_to!(
+        "super + keycode(148)\n    decimal",
+        [Binding::running("decimal").on(prog1(KeyAttribute::None))]
+    );
+    assert_parses_to!(
+        "super + ~@KeyCode(0X94)\n    hex",
+        [Binding::running("hex").on(prog1(KeyAttribute::Both))]
+    );
+    assert_parses_to!(
+        "super + {keycode(148), keycode(0x2fe)}\n    {prog1, unnamed}",
+        [
+            Binding::running("prog1").on(prog1(KeyAttribute::None)),
+            Binding::running("unnamed")
+                .on(Definition::new(evdev::Key::new(0x2fe)).with_modifiers(&[Super])),
+        ]
+    );
+    assert_parse_error!(
+        "super + keycode(768)\n    too high",
+        span: (1, 9)..(1, 21),
+        contains: "`768` is not a key code, they range from 0 to 767",
+    );
+    assert_parse_error!(
+        "super + {a, keycode(0x10000)}\n    too high",
+        span: (1, 13)..(1, 29),
+        contains: "`0x10000` is not a key code",
+    );
+
+    // Keys without a name are written by their code.
+    let unnamed = Definition::new(evdev::Key::new(0x2fe));
+    assert_eq!(unnamed.to_config_string(), "keycode(766)");
+    assert_eq!("keycode(766)".parse::<Definition>()?, unnamed);
+    assert_eq!(lookup_key("keycode(0x94)"), Some(evdev::Key::KEY_PROG1));
+    Ok(())
+}
+
+#[test]
+fn test_config_string_round_trips_every_key() -> Result<(), ParseError> {
+    let attributes = [
+        KeyAttribute::None,
+        KeyAttribute::OnRelease,
+        KeyAttribute::Send,
+        KeyAttribute::Both,
+    ];
+    let modifier_sets: [&[Modifier]; 3] = [&[], &[Super, Shift], &[Any]];
+    let mut definitions = vec![
+        Definition::virtual_key("lid_close"),
+        Definition::scroll(ScrollDirection::Up),
+        Definition::switch(SwitchType::Lid, true),
+        Definition::modifiers_only(&[Super, Alt], KeyAttribute::OnRelease | KeyAttribute::Solo),
+    ];
+    for name in supported_key_names() {
+        let key = lookup_key(name).unwrap();
+        for attribute in attributes {
+            for modifiers in modifier_sets {
+                definitions.push(
+                    Definition::new(key)
+                        .with_attribute(attribute)
+                        .with_modifiers(modifiers),
+                );
+            }
+        }
+    }
+    for definition in definitions {
+        let written = definition.to_config_string();
+        assert_eq!(written.parse::<Definition>()?, definition, "{written}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_binding_config_string_round_trips() -> Result<(), ParseError> {
+    let contents = "
+super + {a, b}
+    first {a, b}
+    then {b, a}
+super + @r
+    @enter resize && notify-send resizing
+ctrl + ~@q
+    notify-send quitting && @escape
+mode resize
+    escape
+        @escape
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_eq!(
+        parsed.bindings[2].to_config_string(),
+        "super + @r\n    @enter resize && notify-send resizing"
+    );
+    let bindings = parsed.bindings.iter().chain(&parsed.modes[0].bindings);
+    for binding in bindings {
+        let written = binding.to_config_string();
+        let with_mode = format!("{written}\nmode resize\n    escape\n        @escape\nendmode");
+        let reparsed = SwhkdParser::from(ParserInput::Raw(&with_mode))?;
+        assert_eq!(
+            reparsed.bindings,
+            std::slice::from_ref(binding),
+            "{written}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_shifted_symbols_imply_shift() -> Result<(), ParseError> {
+    for (name, key) in [
+        ("exclam", evdev::Key::KEY_1),
+        ("at", evdev::Key::KEY_2),
+        ("question", evdev::Key::KEY_SLASH),
+        ("underscore", evdev::Key::KEY_MINUS),
+        ("braceleft", evdev::Key::KEY_LEFTBRACE),
+        ("greater", evdev::Key::KEY_DOT),
+    ] {
+        let shifted = Definition::new(key).with_modifiers(&[Super, Shift]);
+        let contents = format!("super + {name}\n    true");
+        let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+        assert_eq!(*parsed.bindings[0].definition(), shifted, "{name}");
+        assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+
+        // Displayed as the symbol, which parses back to the same chord.
+        assert_eq!(shifted.to_string(), format!("super + {name}"));
+        assert_eq!(shifted.to_string().parse::<Definition>()?, shifted);
+    }
+
+    assert_parses_to!(
+        "super + {@exclam, 1}\n    {bang, one}",
+        [
+            Binding::running("bang").on(Definition::new(evdev::Key::KEY_1)
+                .with_attribute(KeyAttribute::OnRelease)
+                .with_modifiers(&[Super, Shift])),
+            Binding::running("one").on(Definition::new(evdev::Key::KEY_1).with_modifiers(&[Super])),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_explicit_shift_on_shifted_symbol_warns() -> Result<(), ParseError> {
+    let parsed = SwhkdParser::from(ParserInput::Raw("super + Shift + exclam\n    true"))?;
+    let [Warning::RedundantShift(span)] = parsed.warnings.as_slice() else {
+        panic!("unexpected warnings {:?}", parsed.warnings);
+    };
+    assert_eq!((span.line, span.column), (1, 9));
+    assert_eq!((span.end_line, span.end_column), (1, 14));
+    assert_eq!(
+        *parsed.bindings[0].definition(),
+        Definition::new(evdev::Key::KEY_1).with_modifiers(&[Super, Shift])
+    );
+
+    let parsed = SwhkdParser::from(ParserInput::Raw("super + shift + 1\n    true"))?;
+    assert!(parsed.warnings.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_serde_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let contents = "
+set shell /bin/bash
+super + {@a, ~b, c}
+    {first, second, third}
+super + r
+    @enter resize && notify-send resizing
+virtual:lid_close
+    lock
+scroll_up
+    louder
+ignore super + d
+mode resize oneoff
+    escape
+        @escape
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let json = serde_json::to_string(&parsed)?;
+    let value: serde_json::Value = serde_json::from_str(&json)?;
+    let first = &value["bindings"][0];
+    assert_eq!(
+        first["definition"]["key"]["trigger"],
+        serde_json::json!({ "Key": "a" })
+    );
+    assert_eq!(
+        first["definition"]["key"]["attribute"],
+        serde_json::json!(["OnRelease"])
+    );
+    assert_eq!(
+        first["definition"]["modifiers"],
+        serde_json::json!(["Super"])
+    );
+
+    let reparsed: SwhkdParser = serde_json::from_str(&json)?;
+    assert_eq!(reparsed.bindings, parsed.bindings);
+    assert_eq!(reparsed.unbinds, parsed.unbinds);
+    assert_eq!(reparsed.modes[0].bindings, parsed.modes[0].bindings);
+    assert_eq!(reparsed.settings, parsed.settings);
+    assert_eq!(
+        reparsed.bindings[1].instructions_before_command(),
+        parsed.bindings[1].instructions_before_command()
+    );
+    // Everything else, spans included, survives as well.
+    assert_eq!(serde_json::to_string(&reparsed)?, json);
+
+    let omitted: Definition = serde_json::from_str(
+        r#"{"modifiers": ["Omission", "Shift"], "key": {"trigger": {"Key": "RETURN"}, "attribute": ["Send", "OnRelease"]}}"#,
+    )?;
+    assert_eq!(
+        omitted,
+        Definition::new(evdev::Key::KEY_ENTER)
+            .with_attribute(KeyAttribute::Both)
+            .with_modifiers(&[Shift])
+    );
+    Ok(())
+}
+
+#[test]
+fn test_schedules() -> Result<(), Box<dyn std::error::Error>> {
+    use sweet::Weekday::*;
+    let time = |hour, minute| TimeOfDay { hour, minute };
+    let nine_to_five = TimeRange {
+        start: time(9, 0),
+        end: time(17, 0),
+    };
+    for (expression, days, times) in [
+        (
+            "weekday 09:00-17:00",
+            vec![Monday, Tuesday, Wednesday, Thursday, Friday],
+            vec![nine_to_five],
+        ),
+        (
+            "mon-fri 09:00-17:00",
+            vec![Monday, Tuesday, Wednesday, Thursday, Friday],
+            vec![nine_to_five],
+        ),
+        ("sat,sun", vec![Saturday, Sunday], vec![]),
+        ("Weekend", vec![Saturday, Sunday], vec![]),
+        ("fri-mon", vec![Friday, Saturday, Sunday, Monday], vec![]),
+        ("09:00-17:00", Weekday::ALL.to_vec(), vec![nine_to_five]),
+        (
+            "wed 08:00-12:00,13:30-17:00",
+            vec![Wednesday],
+            vec![
+                TimeRange {
+                    start: time(8, 0),
+                    end: time(12, 0),
+                },
+                TimeRange {
+                    start: time(13, 30),
+                    end: time(17, 0),
+                },
+            ],
+        ),
+    ] {
+        assert_eq!(
+            expression.parse::<Schedule>()?,
+            Schedule::new(days, times),
+            "{expression}"
+        );
+    }
+
+    let night: Schedule = "daily 22:00-02:00".parse()?;
+    assert!(night.times()[0].crosses_midnight());
+    assert!(!nine_to_five.crosses_midnight());
+    assert_eq!(night.to_string(), "22:00-02:00");
+
+    // Every variant of a shorthand is scheduled alike.
+    let config = "[when=weekday 09:00-17:00] super + {a, b}\n    focus {a, b}";
+    let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    let weekdays: Schedule = "weekday 09:00-17:00".parse()?;
+    assert_eq!(parsed.bindings.len(), 2);
+    for binding in &parsed.bindings {
+        assert_eq!(binding.schedule(), Some(&weekdays));
+    }
+    assert_parses_to!(
+        "[laptop] [when=sat,sun] super + a\n    fun\nsuper + b\n    always",
+        [Binding::running("always")
+            .on(Definition::new(evdev::Key::KEY_B).with_modifiers(&[Super])),]
+    );
+
+    // Round trips through config syntax, display and serde.
+    let scheduled = &parsed.bindings[0];
+    assert_eq!(
+        scheduled.to_config_string(),
+        "[when=weekday 09:00-17:00] super + a\n    focus a"
+    );
+    let reparsed = SwhkdParser::from(ParserInput::Raw(&scheduled.to_config_string()))?;
+    assert_eq!(&reparsed.bindings[0], scheduled);
+    for expression in [
+        "mon-wed,fri 23:00-01:00",
+        "sat 10:00-11:00,12:00-13:00",
+        "daily",
+    ] {
+        let schedule: Schedule = expression.parse()?;
+        assert_eq!(schedule.to_string(), expression);
+    }
+    let json = serde_json::to_value(scheduled)?;
+    assert_eq!(json["schedule"], serde_json::json!("weekday 09:00-17:00"));
+    assert_eq!(&serde_json::from_value::<Binding>(json)?, scheduled);
+    let preview = SwhkdParser::preview(ParserInput::Raw(config))?;
+    assert_eq!(
+        preview[0].to_string(),
+        "super+a (weekday 09:00-17:00)\tfocus a"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_invalid_schedules() {
+    assert_parse_error!(
+        "[when=weekday 9:00-17:00] super + a\n    focus",
+        span: (1, 15)..(1, 19),
+        contains: "expected a time like `09:00`, found `9:00`",
+    );
+    assert_parse_error!(
+        "[when=mon-fri 09:00-24:00] super + a\n    focus",
+        span: (1, 21)..(1, 26),
+        contains: "`24:00` is not a time of day",
+    );
+    assert_parse_error!(
+        "[when=mon-fry] super + a\n    focus",
+        span: (1, 11)..(1, 14),
+        contains: "unknown day `fry`",
+    );
+    assert_parse_error!(
+        "[when=sat 10:00-10:00] super + a\n    focus",
+        span: (1, 11)..(1, 22),
+        contains: "is empty",
+    );
+    assert_parse_error!(
+        "[when=10:00-12:00 sat] super + a\n    focus",
+        span: (1, 19)..(1, 22),
+        contains: "unexpected text after the times",
+    );
+    assert_parse_error!(
+        "[when=] super + a\n    focus",
+        span: (1, 7)..(1, 7),
+        contains: "empty schedule",
+    );
+}
+
+#[test]
+fn test_devices() -> Result<(), Box<dyn std::error::Error>> {
+    // The same chord on two devices and globally are three bindings.
+    let config = "[device=Keychron K6] super + a\n    left\n[device=\"Logitech MX Keys\"] super + a\n    right\nsuper + a\n    anywhere";
+    let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    let devices: Vec<_> = parsed
+        .bindings
+        .iter()
+        .map(|binding| (binding.device(), binding.command()))
+        .collect();
+    assert_eq!(
+        devices,
+        [
+            (Some("Keychron K6"), "left"),
+            (Some("Logitech MX Keys"), "right"),
+            (None, "anywhere"),
+        ]
+    );
+    assert!(parsed.conflicts().is_empty());
+    assert!(parsed.unreachable_bindings().is_empty());
+
+    // Only a binding on the same device overrides, in place.
+    let parsed = SwhkdParser::from(ParserInput::Raw(
+        "[device=K6] super + a\n    old\n[device=MX] super + a\n    other\n[device=K6] super + a\n    new",
+    ))?;
+    let commands: Vec<_> = parsed.bindings.iter().map(Binding::command).collect();
+    assert_eq!(commands, ["new", "other"]);
+
+    // Unbinding a chord unbinds it on every device.
+    let parsed = SwhkdParser::from(ParserInput::Raw(
+        "[device=K6] super + a\n    left\nsuper + a\n    anywhere\nignore super + a",
+    ))?;
+    assert!(parsed.bindings.is_empty());
+
+    // Either order of tags, each variant scoped alike.
+    let parsed = SwhkdParser::from(ParserInput::Raw(
+        "[when=weekend] [device=K6] super + {a, b}\n    focus {a, b}",
+    ))?;
+    assert_eq!(parsed.bindings.len(), 2);
+    for binding in &parsed.bindings {
+        assert_eq!(binding.device(), Some("K6"));
+        assert!(binding.schedule().is_some());
+    }
+
+    // Round trips through config syntax, display, serde and previews.
+    let scoped = &parsed.bindings[0];
+    assert_eq!(
+        scoped.to_config_string(),
+        "[device=K6] [when=weekend] super + a\n    focus a"
+    );
+    let reparsed = SwhkdParser::from(ParserInput::Raw(&scoped.to_config_string()))?;
+    assert_eq!(&reparsed.bindings[0], scoped);
+    let json = serde_json::to_value(scoped)?;
+    assert_eq!(json["device"], serde_json::json!("K6"));
+    assert_eq!(&serde_json::from_value::<Binding>(json)?, scoped);
+    let preview = SwhkdParser::preview(ParserInput::Raw(config))?;
+    assert_eq!(preview[1].to_string(), "super+a [device=Keychron K6]\tleft");
+    assert_eq!(
+        RawBinding::new(RawChord::new(evdev::Key::KEY_A).with_modifier(Super))
+            .with_device("K6")
+            .with_command("left")
+            .to_config_string(),
+        "[device=K6] super + a\n    left"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_invalid_devices() {
+    assert_parse_error!(
+        "[device=] super + a\n    focus",
+        span: (1, 9)..(1, 9),
+        contains: "empty device name",
+    );
+    assert_parse_error!(
+        "[device=\"  \"] super + a\n    focus",
+        span: (1, 9)..(1, 13),
+        contains: "empty device name",
+    );
+}
+
+#[test]
+fn test_key_lookup_api() {
+    assert_eq!(lookup_key("RETURN"), Some(evdev::Key::KEY_ENTER));
+    assert_eq!(lookup_key("key_macro1"), Some(evdev::Key::new(0x290)));
+    assert_eq!(lookup_key("hyper"), None);
+
+    assert_eq!(key_name(evdev::Key::KEY_ENTER), Some("return"));
+    assert_eq!(key_name(evdev::Key::KEY_DOT), Some("period"));
+    assert_eq!(key_name(evdev::Key::KEY_COMMA), Some("comma"));
+    assert_eq!(key_name(evdev::Key::KEY_PAGEUP), Some("pageup"));
+    assert_eq!(key_name(evdev::Key::KEY_MUTE), Some("mute"));
+
+    let names: Vec<_> = supported_key_names().collect();
+    let unique: std::collections::HashSet<_> = names.iter().collect();
+    assert_eq!(unique.len(), names.len());
+    for name in ["kp_enter", "period", "btn_left", "key_macro1"] {
+        assert!(names.contains(&name), "{name}");
+    }
+    for name in names {
+        let key = lookup_key(name).unwrap();
+        assert_eq!(lookup_key(key_name(key).unwrap()), Some(key), "{name}");
+    }
+}
+
+#[test]
+fn test_preview_snapshot() -> Result<(), ParseError> {
+    let preview =
+        SwhkdParser::preview(ParserInput::Path(Path::new("tests/fixtures/preview.swhkd")))?;
+    let rendered: String = preview.iter().map(|line| format!("{line}\n")).collect();
+    assert_eq!(rendered, include_str!("fixtures/preview.txt"));
+    Ok(())
+}
+
+#[test]
+fn test_dump_formats() -> Result<(), ParseError> {
+    let parsed = SwhkdParser::from(ParserInput::Path(Path::new("tests/fixtures/preview.swhkd")))?;
+    assert_eq!(
+        parsed.dump(DumpFormat::Text),
+        include_str!("fixtures/dump.txt")
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&parsed.dump(DumpFormat::Json)).unwrap();
+    assert_eq!(json["bindings"].as_array().unwrap().len(), 12);
+    let shifted = &json["bindings"][7];
+    // The physical key along with Shift, where the text has the shifted
+    // symbol `exclam`.
+    assert_eq!(
+        shifted["chords"][0]["modifiers"],
+        serde_json::json!(["super", "shift"])
+    );
+    assert_eq!(shifted["chords"][0]["key"], "1");
+    assert_eq!(shifted["command"], "bspc node -d '^1'");
+    assert_eq!(
+        json["bindings"][10]["instructions_after_command"],
+        serde_json::json!([{ "enter": "resize" }])
+    );
+    assert_eq!(json["bindings"][11]["span"]["line"], 10);
+    let mode = &json["modes"][0];
+    assert_eq!(
+        (&mode["name"], &mode["swallow"]),
+        (&"resize".into(), &false.into())
+    );
+    assert_eq!(
+        mode["bindings"][2]["instructions_after_command"],
+        serde_json::json!([{ "escape": null }])
+    );
+
+    let contents = "
+set shell /bin/zsh
+ignore super + q ; w
+@super
+    rofi
+mode launch oneoff swallow
+    ignore alt + @a
+    replay ctrl + c
+    set throttle_default 250ms
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_eq!(
+        parsed.dump(DumpFormat::Text),
+        "Binding @super (written `@super`) \u{2192} rofi (mode instructions: [])
+unbind: super + q ; w
+set shell /bin/zsh
+mode: launch oneoff swallow
+mode launch: unbind: alt + @a
+mode launch: replay: control + c
+mode launch: set throttle_default 250ms
+"
+    );
+    let json: serde_json::Value = serde_json::from_str(&parsed.dump(DumpFormat::Json)).unwrap();
+    assert_eq!(
+        json["bindings"][0]["chords"][0],
+        serde_json::json!({ "modifiers": ["super"], "key": null, "attributes": ["OnRelease"], "held": [], "modifier_attributes": {} })
+    );
+    assert_eq!(json["sequence_unbinds"][0][1]["key"], "w");
+    assert_eq!(json["settings"]["shell"], "/bin/zsh");
+    assert_eq!(json["modes"][0]["settings"]["throttle_default_ms"], 250);
+    assert_eq!(
+        json["modes"][0]["replay"][0]["modifiers"],
+        serde_json::json!(["control"])
+    );
+    Ok(())
+}
+
+#[test]
+fn test_dump_effective_settings() -> Result<(), ParseError> {
+    let contents = "
+set throttle_default 250ms
+[device=macro-pad] set shell /bin/dash
+[device=macro-pad] super + a
+    first
+mode gaming
+set throttle_default 0ms
+[set shell=/bin/bash] super + b
+    steam
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let options = DumpOptions {
+        effective_settings: true,
+    };
+    assert_eq!(
+        parsed.dump_with_options(DumpFormat::Text, options),
+        "Binding super + a [device=macro-pad] (written `super + a`) \u{2192} first (mode instructions: [])
+effective: set shell /bin/dash
+effective: set throttle_default 250ms
+set throttle_default 250ms
+[device=macro-pad] set shell /bin/dash
+mode: gaming
+mode gaming: Binding super + b (written `super + b`) \u{2192} steam (mode instructions: [])
+mode gaming: effective: set shell /bin/bash
+mode gaming: effective: set throttle_default 0ms
+mode gaming: set throttle_default 0ms
+"
+    );
+    let json: serde_json::Value =
+        serde_json::from_str(&parsed.dump_with_options(DumpFormat::Json, options)).unwrap();
+    assert_eq!(
+        json["bindings"][0]["effective_settings"],
+        serde_json::json!({ "shell": "/bin/dash", "throttle_default_ms": 250, "start_mode": null })
+    );
+    assert_eq!(
+        json["modes"][0]["bindings"][0]["effective_settings"],
+        serde_json::json!({ "shell": "/bin/bash", "throttle_default_ms": 0, "start_mode": null })
+    );
+
+    // Only on request.
+    let json: serde_json::Value = serde_json::from_str(&parsed.dump(DumpFormat::Json)).unwrap();
+    assert!(json["bindings"][0].get("effective_settings").is_none());
+    assert!(!parsed.dump(DumpFormat::Text).contains("effective:"));
+    Ok(())
+}
+
+#[test]
+fn test_dump_command() -> Result<(), std::io::Error> {
+    let dump = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_sweet"))
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    let fixture = "tests/fixtures/preview.swhkd";
+    let output = dump(&["dump", fixture]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, include_bytes!("fixtures/dump.txt"));
+    assert_eq!(dump(&[fixture]).stdout, output.stdout);
+
+    let output = dump(&["dump", "--format", "json", fixture]);
+    assert_eq!(output.status.code(), Some(0));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["modes"][0]["name"], "resize");
+
+    let output = dump(&["dump", "--effective-settings", fixture]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("effective: set shell /bin/sh"));
+
+    let mut broken = tempfile::NamedTempFile::new()?;
+    broken.write_all(b"super + \n")?;
+    let output = dump(&["dump", "--format", "json", broken.path().to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unable to parse config file"));
+    assert_eq!(
+        dump(&["dump", "--format", "yaml", fixture]).status.code(),
+        Some(1)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_preview_diff() -> Result<(), IoOrParseError> {
+    let old = include_str!("fixtures/preview.swhkd");
+    let new = old.replace("north", "up");
+    let old = SwhkdParser::preview(ParserInput::Raw(old))?;
+    let new = SwhkdParser::preview(ParserInput::Raw(&new))?;
+    assert_eq!(diff_previews(&old, &old, "old", "new"), None);
+    assert_eq!(
+        diff_previews(&old, &new, "old", "new").as_deref(),
+        Some(
+            "--- old
++++ new
+@@ -4,7 +4,7 @@
+ super+3\tbspc desktop -f '^3'
+ super+h\tbspc node -f west
+ super+j\tbspc node -f south
+-super+k\tbspc node -f north
++super+k\tbspc node -f up
+ super+l\tbspc node -f east
+ super+r\tnotify-send resize \t@enter resize
+ super+shift+1\tbspc node -d '^1'
+"
+        )
+    );
+    Ok(())
+}
+
+#[test]
+fn test_preview_exit_codes() -> Result<(), IoOrParseError> {
+    let fixture = "tests/fixtures/preview.swhkd";
+    let mut changed = tempfile::NamedTempFile::new()?;
+    changed.write_all(
+        std::fs::read_to_string(fixture)?
+            .replace("alacritty", "foot")
+            .as_bytes(),
+    )?;
+    let changed = changed.path().to_str().unwrap();
+    let preview = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_sweet"))
+            .arg("preview")
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let output = preview(&[fixture]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, include_bytes!("fixtures/preview.txt"));
+    assert_eq!(
+        preview(&["--diff", fixture, fixture]).status.code(),
+        Some(0)
+    );
+    let output = preview(&["--diff", fixture, changed]);
+    assert_eq!(output.status.code(), Some(1));
+    let diff = String::from_utf8_lossy(&output.stdout);
+    assert!(diff.contains("-alt+return\talacritty\\nnotify-send launched\n"));
+    assert!(diff.contains("+alt+return\tfoot\\nnotify-send launched\n"));
+    assert_eq!(
+        preview(&["--diff", fixture, "missing.swhkd"]).status.code(),
+        Some(2)
+    );
+    assert_eq!(preview(&[]).status.code(), Some(2));
+    Ok(())
+}
+
+#[test]
+fn test_unknown_key_points_at_key() -> Result<(), IoOrParseError> {
+    let contents = "
+super + a
+    true
+
+super + key_nope
+    false";
+    assert_parse_error!(
+        contents,
+        span: (5, 9)..(5, 17),
+        contains: "`key_nope` is not recognized as a valid evdev key"
+    );
+    assert_parse_error!(
+        "super + {0-a}\n    echo {0-a}",
+        span: (1, 10)..(1, 13),
+        contains: "shorthand range `0-a` goes from a digit to a lowercase letter"
+    );
+
+    let mut config = tempfile::NamedTempFile::new()?;
+    config.write_all(contents.as_bytes())?;
+    let Err(ParseError::Grammar(err)) = SwhkdParser::from(ParserInput::Path(config.path())) else {
+        panic!("expected a grammar error")
+    };
+    assert!(err
+        .to_string()
+        .contains(&format!("--> {}:5:9", config.path().display())));
+    Ok(())
+}
+
+#[test]
+fn test_nested_shorthand_groups() -> Result<(), ParseError> {
+    assert_parse_error!(
+        "super + {a, {b, c}}\n    echo {a, b, c}",
+        span: (1, 13)..(1, 14),
+        contains: "nested shorthand groups are not supported; write the variants in a single group"
+    );
+    assert_parse_error!(
+        "mode resize\n    super + {h, {j, k}}\n        true\nendmode",
+        span: (2, 17)..(2, 18),
+        contains: "nested shorthand groups are not supported"
+    );
+    assert_parse_error!(
+        "super + {a, b}\n    echo {1, {2, {3, 4}}}",
+        span: (2, 18)..(2, 19),
+        contains: "shorthand groups in commands nest one level deep at most"
+    );
+    let deep = format!(
+        "super + {}{}\n    true",
+        "{a, ".repeat(10_000),
+        "}".repeat(10_000)
+    );
+    assert_parse_error!(&deep, span: (1, 13)..(1, 14), contains: "nested shorthand groups");
+    // Escaped braces are neither nested groups nor left open.
+    let contents = "super + {a, b}\n    echo {\\{, \\}}";
+    assert_parses_to!(
+        contents,
+        [
+            binding!("super + a" => "echo {"),
+            binding!("super + b" => "echo }")
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_unclosed_shorthand_group() {
+    assert_parse_error!(
+        "super + {a, b\n    echo {a, b}",
+        span: (1, 9)..(1, 10),
+        contains: "unclosed '{' opened at column 9"
+    );
+    assert_parse_error!(
+        "super + {a, b}\n    echo {a, b \\}",
+        span: (2, 10)..(2, 11),
+        contains: "unclosed '{' opened at column 10"
+    );
+}
+
+#[test]
+fn test_switch_events() -> Result<(), ParseError> {
+    let events = [
+        ("lid_close", SwitchType::Lid, true),
+        ("lid_open", SwitchType::Lid, false),
+        ("tablet_mode_on", SwitchType::TabletMode, true),
+        ("tablet_mode_off", SwitchType::TabletMode, false),
+        ("headphone_insert", SwitchType::HeadphoneInsert, true),
+        ("headphone_remove", SwitchType::HeadphoneInsert, false),
+        ("dock", SwitchType::Dock, true),
+        ("undock", SwitchType::Dock, false),
+    ];
+    for (name, switch, state) in events {
+        let contents = format!("switch:{name}\n    true");
+        let parsed = SwhkdParser::from(ParserInput::Raw(&contents))?;
+        assert_eq!(
+            parsed.bindings,
+            [Binding::running("true").on(Definition::switch(switch, state))],
+            "{name}"
+        );
+        assert!(parsed.bindings[0].definition().is_switch());
+        assert_eq!(
+            parsed.bindings[0].definition().display_with(&DefaultStyle),
+            format!("switch:{name}")
+        );
+    }
+    assert_eq!(SwitchType::Lid.evdev(), evdev::SwitchType::SW_LID);
+
+    let contents = "
+switch:{lid_close, lid_open}
+    systemctl {suspend, resume}";
+    assert_parses_to!(
+        contents,
+        [
+            Binding::running("systemctl suspend").on(Definition::switch(SwitchType::Lid, true)),
+            Binding::running("systemctl resume").on(Definition::switch(SwitchType::Lid, false)),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_switch_errors() {
+    assert_parse_error!(
+        "super + switch:lid_close\n    systemctl suspend",
+        span: (1, 9)..(1, 25),
+        contains: "modifiers cannot be combined with switches"
+    );
+    assert_parse_error!(
+        "switch:lid_shut\n    systemctl suspend",
+        span: (1, 8)..(1, 16),
+        contains: "unknown switch event `lid_shut`, expected one of lid_close, lid_open"
+    );
+}
+
+#[test]
+fn test_unbind_switch() -> Result<(), ParseError> {
+    let contents = "
+switch:{dock, undock}
+    autorandr {docked, mobile}
+ignore switch:undock
+
+mode presenting
+switch:lid_close
+    true
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    assert_eq!(
+        parsed.bindings,
+        [Binding::running("autorandr docked").on(Definition::switch(SwitchType::Dock, true))]
+    );
+    assert_eq!(
+        parsed.unbinds,
+        [Definition::switch(SwitchType::Dock, false)]
+    );
+    assert_eq!(
+        parsed.modes[0].bindings,
+        [Binding::running("true").on(Definition::switch(SwitchType::Lid, true))]
+    );
+    Ok(())
+}
+
+/// A generated config exercising every kind of item, see
+/// [`test_items_match_rendered_config`].
+fn generated_items() -> Vec<ConfigItem> {
+    use evdev::Key as K;
+    let run = |segments: Vec<CommandSegment>| CommandPart::Run(segments);
+    let text = CommandSegment::text;
+    let variants = |variants: [&str; 2]| CommandSegment::variants(variants);
+    vec![
+        Settings {
+            shell: Some("/bin/zsh".to_string()),
+            throttle_default: Some(Duration::from_millis(250)),
+            start_mode: None,
+        }
+        .into(),
+        RawBinding::new(
+            RawChord::new(K::KEY_H)
+                .with_modifier(Super)
+                .with_modifier_shorthand([Omission, Shift]),
+        )
+        .with_line([run(vec![
+            text("bspc node -"),
+            variants(["f", "s"]),
+            text(" west"),
+        ])])
+        .with_line([run(vec![text("echo "), variants(["a,b", "c-d"])])])
+        .with_line([run(vec![
+            text("notify-send "),
+            variants(["{x}", r"back\slash"]),
+        ])])
+        .with_line([run(vec![text("a "), variants(["1", "2"]), text(" && b")])])
+        .with_line([run(vec![text("bspc node -c"), variants(["", "_"])])])
+        .with_schedule("mon,wed 22:00-02:00".parse().unwrap())
+        .into(),
+        RawBinding::new(
+            RawChord::new(Key::new(K::KEY_R, KeyAttribute::OnRelease)).with_modifier(Control),
+        )
+        .with_line([
+            CommandPart::enter("resize"),
+            CommandPart::text(" notify-send resizing "),
+            CommandPart::enter("other"),
+        ])
+        .into(),
+        RawBinding::new(RawChord::new(K::KEY_A).with_modifier(Super))
+            .with_command("first")
+            .into(),
+        RawBinding::new(RawChord::new(K::KEY_A).with_modifier(Super))
+            .with_command("second &&")
+            .into(),
+        RawBinding::new(
+            RawChord::new(Key::modifiers_only(
+                KeyAttribute::OnRelease | KeyAttribute::Solo,
+            ))
+            .with_modifier_shorthand([Super, Alt])
+            .with_modifier(Shift),
+        )
+        .with_line([run(vec![variants(["rofi", "wofi"])])])
+        .into(),
+        RawBinding::new(RawChord::shorthand([
+            Key::virtual_key("lid"),
+            Key::virtual_key("dock"),
+        ]))
+        .with_line([run(vec![text("on "), variants(["lid", "dock"])])])
+        .into(),
+        RawBinding::new(
+            RawChord::shorthand([
+                Key::scroll(ScrollDirection::Up),
+                Key::scroll(ScrollDirection::Down),
+            ])
+            .with_modifier(Alt),
+        )
+        .with_line([run(vec![variants(["volume up", "volume down"])])])
+        .into(),
+        RawBinding::new(RawChord::new(Key::switch(SwitchType::Lid, true)))
+            .with_command("systemctl suspend")
+            .into(),
+        ConfigItem::Unbind(RawChord::new(K::KEY_Q).with_modifier(Super)),
+        RawMode {
+            oneoff: true,
+            swallow: true,
+            replay: vec![RawChord::new(K::KEY_ESC)],
+            unbinds: vec![RawChord::new(K::KEY_B)],
+            settings: Settings {
+                shell: Some("/bin/bash".to_string()),
+                ..Default::default()
+            },
+            ..RawMode::new("resize")
+        }
+        .with_binding(RawBinding::new(RawChord::new(K::KEY_H)).with_command("shrink"))
+        .with_binding(RawBinding::new(RawChord::new(K::KEY_H)).with_command("shrink more"))
+        .with_binding(RawBinding::new(RawChord::new(K::KEY_ESC)).with_line([CommandPart::Escape]))
+        .into(),
+        ConfigItem::Unbind(RawChord::new(K::KEY_Q).with_modifier(Super)),
+        RawMode::new("other")
+            .with_binding(
+                RawBinding::new(RawChord::new(K::KEY_ESC)).with_line([CommandPart::Escape]),
+            )
+            .into(),
+    ]
+}
+
+#[test]
+fn test_items_match_rendered_config() -> Result<(), ParseError> {
+    let items = generated_items();
+    let config = render_items(&items);
+    let parsed = SwhkdParser::from(ParserInput::Raw(&config))?;
+    let built = SwhkdParser::from_items(items)?;
+    assert_eq!(built.bindings, parsed.bindings, "{config}");
+    assert_eq!(built.overridden, parsed.overridden, "{config}");
+    assert_eq!(built.unbinds, parsed.unbinds, "{config}");
+    assert_eq!(built.settings, parsed.settings, "{config}");
+    assert_eq!(built.modes.len(), parsed.modes.len(), "{config}");
+    for (built, parsed) in built.modes.iter().zip(&parsed.modes) {
+        assert_eq!(built.name, parsed.name, "{config}");
+        assert_eq!(
+            (built.oneoff, built.swallow),
+            (parsed.oneoff, parsed.swallow),
+            "{config}"
+        );
+        assert_eq!(built.bindings, parsed.bindings, "{config}");
+        assert_eq!(built.unbinds, parsed.unbinds, "{config}");
+        assert_eq!(built.replay, parsed.replay, "{config}");
+        assert_eq!(built.settings, parsed.settings, "{config}");
+    }
+    // The expansion itself, on top of agreeing with the parser.
+    assert_eq!(built.bindings.len(), 11);
+    assert_eq!(
+        built.bindings[0].command(),
+        "bspc node -f west\necho a,b\nnotify-send {x}\na 1 && b\nbspc node -c"
+    );
+    assert!(built.bindings[1].command().ends_with("\nbspc node -c_"));
+    assert_eq!(built.bindings[2].command(), " notify-send resizing ");
+    assert_eq!(
+        built.bindings[2].instructions_before_command(),
+        [ModeInstruction::Enter("resize".to_string())]
+    );
+    assert_eq!(
+        built.bindings[2].instructions_after_command(),
+        [ModeInstruction::Enter("other".to_string())]
+    );
+    assert_eq!(built.bindings[3].command(), "second ");
+    Ok(())
+}
+
+#[test]
+fn test_invalid_items() {
+    use evdev::Key as K;
+    let invalid = |item: ConfigItem| match SwhkdParser::from_items(vec![item]) {
+        Err(ParseError::InvalidItem(message)) => message,
+        other => panic!("expected an invalid item, got {other:?}"),
+    };
+    let binding = |chord: RawChord| RawBinding::new(chord);
+    assert_eq!(
+        invalid(
+            binding(RawChord::shorthand([K::KEY_A, K::KEY_B]))
+                .with_command("one")
+                .into()
+        ),
+        "binding `{a, b}`: the number of possible binding variants 2 does not equal the number of possible command variants 1."
+    );
+    assert!(invalid(
+        binding(RawChord::new(Key::virtual_key("lid")).with_modifier(Super))
+            .with_command("true")
+            .into()
+    )
+    .contains("modifiers cannot be combined with virtual keys"));
+    assert!(invalid(
+        binding(RawChord::new(K::KEY_A))
+            .with_command("awk '{print}'")
+            .into()
+    )
+    .contains("cannot hold braces outside of a shorthand"));
+    assert!(invalid(
+        binding(RawChord::new(K::KEY_A))
+            .with_command("true && @escape")
+            .into()
+    )
+    .contains("holds a mode instruction"));
+    assert!(invalid(
+        RawMode {
+            settings: Settings {
+                start_mode: Some("normal".to_string()),
+                ..Default::default()
+            },
+            ..RawMode::new("resize")
+        }
+        .into()
+    )
+    .contains("setting `start_mode` is only allowed at the top level"));
+    assert!(invalid(RawMode::new("resize").into()).contains("the mode declares nothing"));
+}
+
+/// Every binding of `parsed`, including the ones in modes.
+fn all_bindings(parsed: &SwhkdParser) -> Vec<&Binding> {
+    parsed
+        .bindings
+        .iter()
+        .chain(parsed.modes.iter().flat_map(|mode| &mode.bindings))
+        .collect()
+}
+
+#[test]
+fn test_estimate_matches_expansion() -> Result<(), IoOrParseError> {
+    let options = ParseOptions::default().with_follow_imports(ImportPolicy::Ignore);
+    for fixture in [
+        "tests/fixtures/pathological.swhkd",
+        "tests/fixtures/preview.swhkd",
+        "tests/fixtures/report_root.swhkd",
+        "tests/fixtures/report_include.swhkd",
+    ] {
+        let contents = std::fs::read_to_string(fixture)?;
+        let estimate = estimate(&contents)?;
+        let parsed = SwhkdParser::from_with_options(ParserInput::Raw(&contents), &options)?;
+        let bindings = all_bindings(&parsed);
+        assert_eq!(estimate.total, bindings.len(), "{fixture}");
+        for declaration in &estimate.declarations {
+            let expanded = bindings
+                .iter()
+                .filter(|binding| binding.span() == Some(&declaration.span))
+                .count();
+            assert_eq!(declaration.variants, expanded, "{fixture}: {declaration:?}");
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_estimate() -> Result<(), ParseError> {
+    let config = "
+super + {a-c}
+    {a-c}
+mode resize
+{_, ctrl +} {h,l}
+    resize {1-4}
+virtual:{up,down}
+    {up,down}
+endmode";
+    let estimate = estimate(config)?;
+    let summary = estimate
+        .declarations
+        .iter()
+        .map(|declaration| {
+            let span = &declaration.span;
+            (span.line, declaration.mode.as_deref(), declaration.variants)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        [(2, None, 3), (5, Some("resize"), 4), (7, Some("resize"), 2)]
+    );
+    assert_eq!(estimate.total, 9);
+    assert_eq!(estimate.exceeding(3).count(), 1);
+    assert_eq!(estimate.exceeding(1).count(), 3);
+
+    // Variants are counted even when commands do not match them.
+    assert_eq!(sweet::estimate("super + {a-z}\n    b")?.total, 26);
+    assert_eq!(sweet::estimate("super + {z-a:5}\n    b")?.total, 6);
+    let Err(ParseError::Grammar(err)) = sweet::estimate("super + {z-1}\n    b") else {
+        panic!("expected the range to be rejected");
+    };
+    assert_eq!(
+        err.line_col,
+        pest::error::LineColLocation::Span((1, 10), (1, 13))
+    );
+    assert!(err
+        .to_string()
+        .contains("goes from a lowercase letter to a digit"));
+    Ok(())
+}
+
+/// Run with `cargo test --release -- --ignored bench_estimate`.
+#[test]
+#[ignore = "timing, run in release mode"]
+fn bench_estimate_against_parsing() -> Result<(), ParseError> {
+    let contents = include_str!("fixtures/pathological.swhkd");
+    let options = ParseOptions::default().with_follow_imports(ImportPolicy::Ignore);
+    let time = |run: &dyn Fn() -> Result<(), ParseError>| {
+        let start = std::time::Instant::now();
+        for _ in 0..20 {
+            run()?;
+        }
+        Ok::<_, ParseError>(start.elapsed())
+    };
+    let estimating = time(&|| estimate(contents).map(drop))?;
+    let parsing =
+        time(&|| SwhkdParser::from_with_options(ParserInput::Raw(contents), &options).map(drop))?;
+    println!("estimating: {estimating:?}, parsing: {parsing:?}");
+    assert!(estimating * 5 <= parsing);
+    Ok(())
+}
+
+#[test]
+#[ignore = "timing, run in release mode"]
+fn bench_parsing_scales_with_bindings() -> Result<(), ParseError> {
+    // Every binding is located in its file, which must not scan the file
+    // again for each binding.
+    let config = |bindings: usize| -> String {
+        (0..bindings)
+            .map(|i| format!("super + keycode({})\n    echo {i}\n", i % 500 + 2))
+            .collect()
+    };
+    let time = |config: &str| {
+        let start = std::time::Instant::now();
+        SwhkdParser::from(ParserInput::Raw(config))?;
+        Ok::<_, ParseError>(start.elapsed())
+    };
+    let small = time(&config(2_500))?;
+    let large = time(&config(10_000))?;
+    println!("2500 bindings: {small:?}, 10000 bindings: {large:?}");
+    assert!(large <= small * 8);
+    Ok(())
+}
+
+#[test]
+fn test_variables() -> Result<(), ParseError> {
+    let config = r#"
+define mod = super
+define hyper = super + ctrl + alt
+define term = alacritty -t scratch
+define keys = {h,l}
+
+$mod + t
+    $term
+
+${mod} + shift + {a,b}
+    ${term} -e {htop,btop}
+
+$hyper + $keys
+    focus {west,east} && echo \$term $HOME ${missing}
+
+ignore $mod + x
+"#;
+    let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    assert_eq!(
+        parsed.bindings,
+        [
+            binding!("super + t" => "alacritty -t scratch"),
+            binding!("super + shift + a" => "alacritty -t scratch -e htop"),
+            binding!("super + shift + b" => "alacritty -t scratch -e btop"),
+            binding!("super + ctrl + alt + h" => "focus west && echo $term $HOME ${missing}"),
+            binding!("super + ctrl + alt + l" => "focus east && echo $term $HOME ${missing}"),
+        ]
+    );
+    assert_eq!(parsed.unbinds, ["super + x".parse::<Definition>()?]);
+    assert_eq!(parsed.variables["term"], "alacritty -t scratch");
+    assert_eq!(parsed.bindings[0].source_text(), "$mod + t");
+
+    // Definitions apply to the whole file, modes included.
+    let config = "mode resize\n$mod + h\n    shrink\nendmode\ndefine mod = alt";
+    let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    assert_eq!(parsed.modes[0].bindings, [binding!("alt + h" => "shrink")]);
+
+    // A later definition replaces an earlier one.
+    let config = "define term = xterm\ndefine term = foot\nsuper + t\n    $term";
+    let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    assert_eq!(parsed.bindings[0].command(), "foot");
+
+    let estimate = sweet::estimate("define keys = {a-c}\nsuper + $keys\n    {a-c}")?;
+    assert_eq!(estimate.total, 3);
+    Ok(())
+}
+
+#[test]
+fn test_invalid_variables() {
+    assert_parse_error!(
+        "$mod + t\n    true",
+        span: (1, 1)..(1, 5),
+        contains: "undefined variable `$mod`, define it with `define mod = ...`"
+    );
+    assert_parse_error!(
+        "define mod = super\nsuper + ${key}\n    true",
+        span: (2, 9)..(2, 15),
+        contains: "undefined variable `${key}`"
+    );
+    assert_parse_error!(
+        "define mod = nope\n$mod + t\n    true",
+        span: (2, 1)..(2, 9),
+        contains: "`$mod + t` expands to `nope + t`, which is not a chord"
+    );
+    assert_parse_error!(
+        "define key = hyper\nsuper + $key\n    true",
+        span: (2, 1)..(2, 13),
+        contains: "`super + $key` expands to `super + hyper`, "
+    );
+    assert!("$mod + a".parse::<Definition>().is_err());
+}
+
+#[test]
+fn test_variables_from_includes() -> Result<(), ParseError> {
+    let options = map_options(&[
+        ("vars", "define mod = super\ndefine term = foot"),
+        ("extra", "include vars\nsuper + e\n    $term"),
+    ]);
+    let config = "define term = alacritty\ninclude extra\n$mod + t\n    $term";
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(config), &options)?;
+    // The including file takes precedence, and an include only sees its
+    // own variables and the ones of its includes.
+    assert_eq!(
+        parsed.bindings,
+        [
+            binding!("super + t" => "alacritty"),
+            binding!("super + e" => "foot"),
+        ]
+    );
+    assert_eq!(parsed.variables["term"], "alacritty");
+    assert_eq!(parsed.variables["mod"], "super");
+
+    let options = map_options(&[("extra", "$mod + e\n    true")]);
+    let config = "define mod = super\ninclude extra";
+    let Err(ParseError::Grammar(err)) =
+        SwhkdParser::from_with_options(ParserInput::Raw(config), &options)
+    else {
+        panic!("expected `$mod` to be undefined in the include");
+    };
+    assert_eq!(err.path(), Some("extra"));
+    assert!(err.to_string().contains("undefined variable `$mod`"));
+    Ok(())
+}
+
+#[test]
+fn test_blocks() -> Result<(), ParseError> {
+    let config = r#"
+block power
+    notify-send {suspending,hibernating}
+    systemctl {suspend,hibernate}
+endblock
+
+block lock
+    swaylock -f -c 000000
+endblock
+
+super + {s, h}
+    &power
+
+super + {Escape, x}
+    {&lock, systemctl suspend} && echo 2>&1 \&done
+
+mode power
+    p
+        &lock
+endmode
+"#;
+    let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    // Shorthands in a block count among the variants of the command, and a
+    // block in a shorthand variant is taken literally.
+    assert_eq!(
+        parsed.bindings,
+        [
+            binding!("super + s" => "notify-send suspending\nsystemctl suspend"),
+            binding!("super + h" => "notify-send hibernating\nsystemctl hibernate"),
+            binding!("super + Escape" => r"swaylock -f -c 000000 && echo 2>&1 \&done"),
+            binding!("super + x" => r"systemctl suspend && echo 2>&1 \&done"),
+        ]
+    );
+    assert_eq!(
+        parsed.modes[0].bindings,
+        [binding!("p" => "swaylock -f -c 000000")]
+    );
+    assert_eq!(parsed.blocks["lock"], "swaylock -f -c 000000");
+    assert_eq!(parsed.bindings[0].source_text(), "super + {s, h}");
+    Ok(())
+}
+
+#[test]
+fn test_ampersands_for_the_shell() -> Result<(), ParseError> {
+    // `&name` naming no block is shell syntax, and so is a lone `&`.
+    let config = "block lock\n    swaylock\nendblock
+super + a
+    sleep 1 &disown
+super + b
+    cmd &
+super + c
+    foot &disown && &lock
+super + {d, e}
+    {mpv &disown, &lock}";
+    let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    assert_eq!(
+        parsed.bindings,
+        [
+            binding!("super + a" => "sleep 1 &disown"),
+            binding!("super + b" => "cmd &"),
+            binding!("super + c" => "foot &disown && swaylock"),
+            binding!("super + d" => "mpv &disown"),
+            binding!("super + e" => "swaylock"),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_invalid_blocks() {
+    assert_parse_error!(
+        "block a\n    echo a\n    &b\nendblock\nblock b\n    &a\nendblock\nsuper + l\n    &a",
+        span: (9, 5)..(9, 7),
+        contains: "recursive block reference `&a` \u{2192} `&b`, blocks cannot reference other blocks",
+    );
+    assert_parse_error!(
+        "block menu\n    wlogout\n    true\nendblock\nsuper + {a, b}\n    {&menu, true}",
+        span: (6, 6)..(6, 11),
+        contains: "block `&menu` has several lines, it cannot be used in a shorthand",
+    );
+    // Errors in the expanded command are reported on the command.
+    assert_parse_error!(
+        "block bell\n    printf \u{7}\nendblock\nsuper + a\n    &bell",
+        span: (5, 5)..(5, 10),
+        contains: "with `&bell` expanded, command contains the control character U+0007",
+    );
+}
+
+#[test]
+fn test_blocks_from_includes() -> Result<(), ParseError> {
+    let options = map_options(&[(
+        "blocks",
+        "block lock\n    swaylock\nendblock\nblock menu\n    wlogout\nendblock",
+    )]);
+    let config = "include blocks\nblock lock\n    loginctl lock-session\nendblock\nsuper + l\n    &lock\nsuper + m\n    &menu";
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(config), &options)?;
+    // Like variables, the blocks of the including file take precedence.
+    assert_eq!(
+        parsed.bindings,
+        [
+            binding!("super + l" => "loginctl lock-session"),
+            binding!("super + m" => "wlogout"),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_shorthand_attributes() -> Result<(), ParseError> {
+    let contents = "
+super + ~{1-3}
+    workspace {1-3}
+alt + @~{a, @b}
+    echo {a,b}
+ctrl + @{~x, y-z}
+    echo {x,y,z}";
+    assert_parses_to!(
+        contents,
+        [
+            binding!("super + ~1" => "workspace 1"),
+            binding!("super + ~2" => "workspace 2"),
+            binding!("super + ~3" => "workspace 3"),
+            binding!("alt + ~@a" => "echo a"),
+            binding!("alt + ~@b" => "echo b"),
+            binding!("ctrl + ~@x" => "echo x"),
+            binding!("ctrl + @y" => "echo y"),
+            binding!("ctrl + @z" => "echo z"),
+        ]
+    );
+    assert_eq!(sweet::estimate(contents)?.total, 8);
+    assert_parse_error!(
+        "super + ~{scroll_up, scroll_down}\n    {a, b}",
+        span: (1, 11)..(1, 20),
+        contains: "scroll events have no press or release"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_passthrough() -> Result<(), ParseError> {
+    let parsed = SwhkdParser::from(ParserInput::Raw(
+        "
+super + a passthrough
+    notify-send a
+super + ~@b passthrough = notify-send b
+super + {1-3} passthrough
+    workspace {1-3}
+{_, shift +} @{c, d} passthrough
+    echo {c,d,C,D}
+super + x ; y passthrough
+    echo y",
+    ))?;
+    let definitions = parsed
+        .bindings
+        .iter()
+        .map(|binding| binding.definition().clone())
+        .collect::<Vec<_>>();
+    let passthrough = |modifiers: &[Modifier], key, attribute| {
+        Definition::new(key)
+            .with_modifiers(modifiers)
+            .with_attribute(attribute | KeyAttribute::Passthrough)
+    };
+    let none = KeyAttribute::None;
+    assert_eq!(
+        definitions[..9],
+        [
+            passthrough(&[Super], evdev::Key::KEY_A, none),
+            passthrough(&[Super], evdev::Key::KEY_B, KeyAttribute::Both),
+            passthrough(&[Super], evdev::Key::KEY_1, none),
+            passthrough(&[Super], evdev::Key::KEY_2, none),
+            passthrough(&[Super], evdev::Key::KEY_3, none),
+            passthrough(&[], evdev::Key::KEY_C, KeyAttribute::OnRelease),
+            passthrough(&[], evdev::Key::KEY_D, KeyAttribute::OnRelease),
+            passthrough(&[Shift], evdev::Key::KEY_C, KeyAttribute::OnRelease),
+            passthrough(&[Shift], evdev::Key::KEY_D, KeyAttribute::OnRelease),
+        ]
+    );
+    // Only the chord the keyword follows is left to the window.
+    let sequence = parsed.bindings[9].sequence();
+    assert_eq!(sequence[0].key().attribute, KeyAttribute::None);
+    assert_eq!(sequence[1].key().attribute, KeyAttribute::Passthrough);
+
+    // The keyword is part of the config syntax of the chord.
+    for chord in ["super + a passthrough", "super + ~@b passthrough"] {
+        let definition: Definition = chord.parse()?;
+        assert_eq!(definition.to_string(), chord);
+        assert_eq!(definition.to_string().parse::<Definition>()?, definition);
+    }
+    assert_eq!(
+        sweet::to_hyprland(&parsed).lines().next(),
+        Some("bindn = SUPER, a, exec, notify-send a")
+    );
+
+    assert_parse_error!(
+        "virtual:lid passthrough\n    echo lid",
+        span: (1, 13)..(1, 24),
+        contains: "`passthrough` can only be used on keys"
+    );
+    // A key named like the keyword is not one.
+    assert_parse_error!("super + passthrough\n    echo", at: (1, 10));
+    Ok(())
+}
+
+#[test]
+fn test_syntax_tree_node_at_offset() -> Result<(), ParseError> {
+    let input = "# launchers\nsuper + {a, b-d}\n    firefox {x,y,z,w}";
+    let root = sweet::syntax_tree(input)?;
+    let at = |text: &str| {
+        let node = root.node_at_offset(input.find(text).unwrap()).unwrap();
+        let kinds: Vec<_> = std::iter::once(node.kind())
+            .chain(node.ancestors().map(|ancestor| ancestor.kind()))
+            .collect();
+        (node.text().to_string(), kinds)
+    };
+    assert_eq!(
+        at("# launchers"),
+        (
+            "# launchers".to_string(),
+            vec![SyntaxKind::Comment, SyntaxKind::Root]
+        )
+    );
+    assert_eq!(
+        at("super"),
+        (
+            "super".to_string(),
+            vec![SyntaxKind::Modifier, SyntaxKind::Binding, SyntaxKind::Root]
+        )
+    );
+    // A key, and a key in a range of a shorthand.
+    assert_eq!(
+        at("a,"),
+        (
+            "a".to_string(),
+            vec![
+                SyntaxKind::KeyName,
+                SyntaxKind::Key,
+                SyntaxKind::KeyShorthand,
+                SyntaxKind::Binding,
+                SyntaxKind::Root
+            ]
+        )
+    );
+    assert_eq!(
+        at("d}"),
+        (
+            "d".to_string(),
+            vec![
+                SyntaxKind::KeyName,
+                SyntaxKind::Key,
+                SyntaxKind::KeyRange,
+                SyntaxKind::KeyShorthand,
+                SyntaxKind::Binding,
+                SyntaxKind::Root
+            ]
+        )
+    );
+    assert_eq!(
+        at("firefox"),
+        (
+            "firefox ".to_string(),
+            vec![
+                SyntaxKind::CommandText,
+                SyntaxKind::Command,
+                SyntaxKind::Binding,
+                SyntaxKind::Root
+            ]
+        )
+    );
+    assert_eq!(
+        at("z"),
+        (
+            "z".to_string(),
+            vec![
+                SyntaxKind::CommandVariant,
+                SyntaxKind::CommandShorthand,
+                SyntaxKind::Command,
+                SyntaxKind::Binding,
+                SyntaxKind::Root
+            ]
+        )
+    );
+    assert_eq!(root.node_at_offset(input.len()), None);
+    Ok(())
+}
+
+/// Checks the leaves of a syntax tree give back its input, and that the
+/// children of every node cover it exactly.
+fn assert_lossless(input: &str, root: &SyntaxNode) {
+    let leaves: String = root.leaves().map(|leaf| leaf.text().to_string()).collect();
+    assert_eq!(leaves, input);
+    for node in root.preorder() {
+        if node.is_leaf() {
+            continue;
+        }
+        let mut at = node.range().start;
+        for child in node.children() {
+            assert_eq!(child.parent().as_ref(), Some(&node));
+            assert_eq!(child.range().start, at, "{child:?} in {node:?}");
+            at = child.range().end;
+        }
+        assert_eq!(at, node.range().end, "{node:?}");
+    }
+    for offset in 0..input.len() {
+        if let Some(node) = root.node_at_offset(offset) {
+            assert!(node.is_leaf() && node.range().contains(&offset));
+        }
+    }
+}
+
+#[test]
+fn test_syntax_tree_lossless_on_fixtures() -> Result<(), ParseError> {
+    for input in [
+        sweet::DEFAULT_CONFIG,
+        include_str!("fixtures/preview.swhkd"),
+        include_str!("fixtures/report_root.swhkd"),
+        "mode resize oneoff swallow # resizing\n    ignore super + a\n    replay ~b\n    virtual:{a,b}\n        @escape\nendmode\n",
+        "block lock # locks\n    swaylock\n\nendblock\n[laptop] [device=\"K6\"] @super!\n    {&lock, \\,foo} \\\n        && echo \\&\n    switch:lid_close\n",
+        "define m = super\r\n$m + {_, shift +} keycode(148)\r\n    echo ${m} {a - c}\r\n    @enter x\r\n",
+    ] {
+        assert_lossless(input, &sweet::syntax_tree(input)?);
+    }
+    Ok(())
+}
+
+mod syntax_tree_properties {
+    use proptest::prelude::*;
+
+    use super::assert_lossless;
+
+    fn declaration() -> impl Strategy<Value = String> {
+        let modifiers =
+            prop::sample::select(vec!["", "super + ", "{alt,ctrl} + ", "{_, shift +} "]);
+        let key = prop::sample::select(vec![
+            "a",
+            "Return",
+            "~@b",
+            "{a, b}",
+            "@{1-3}",
+            "keycode(148)",
+            "$key",
+            "\\,",
+        ]);
+        let comment = prop::sample::select(vec!["", " # note", "\n# between"]);
+        let command = prop::sample::select(vec![
+            "echo hi",
+            "echo {a,b} && true",
+            "&lock {1-3}",
+            "{&lock, echo \\,}",
+            "a \\\n        b",
+            "@enter m",
+            "echo \\& $HOME ${key}",
+        ]);
+        let binding = (modifiers, key, comment, command, any::<bool>()).prop_map(
+            |(modifiers, key, comment, command, tagged)| {
+                let tag = if tagged { "[laptop] " } else { "" };
+                format!("{tag}{modifiers}{key}{comment}\n    {command}\n")
+            },
+        );
+        prop_oneof![
+            binding.clone(),
+            Just("# a comment\n".to_string()),
+            Just("\n".to_string()),
+            Just("define key = x\n".to_string()),
+            Just("block lock\n    swaylock # locks\nendblock\n".to_string()),
+            Just("ignore super + q\n".to_string()),
+            Just("set throttle 10ms\n".to_string()),
+            Just("include ~/.config/extra\n".to_string()),
+            binding.prop_map(|binding| {
+                let indented = binding.replace('\n', "\n    ");
+                format!(
+                    "mode m oneoff\n    {}endmode\n",
+                    indented.trim_end_matches(' ')
+                )
+            }),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn syntax_tree_is_lossless(declarations in prop::collection::vec(declaration(), 0..8)) {
+            let input = declarations.concat();
+            let root = sweet::syntax_tree(&input);
+            prop_assert!(root.is_ok(), "{input:?} does not parse");
+            assert_lossless(&input, &root.unwrap());
+        }
+    }
+}
+
+#[test]
+fn test_multi_key_chords() -> Result<(), Box<dyn std::error::Error>> {
+    let contents = "
+ctrl + x & e
+    emacs-eval
+ctrl + x & {e, f}
+    run {e,f}
+ctrl + {x, y} & z & @w
+    run {x,y}
+ignore ctrl + z & x & @w";
+    let options = ParseOptions::default().with_multi_key_chords(true);
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(contents), &options)?;
+    let held = |keys: &[evdev::Key], key: evdev::Key| {
+        Definition::new(key)
+            .with_modifiers(&[Control])
+            .with_held_keys(keys.iter().copied())
+    };
+    use evdev::Key as K;
+    // The later `ctrl + x & e` overrides the first one.
+    assert_eq!(
+        parsed.bindings,
+        [
+            Binding::running("run e").on(held(&[K::KEY_X], K::KEY_E)),
+            Binding::running("run f").on(held(&[K::KEY_X], K::KEY_F)),
+            Binding::running("run y")
+                .on(held(&[K::KEY_Y, K::KEY_Z], K::KEY_W).with_attribute(KeyAttribute::OnRelease)),
+        ]
+    );
+    // Held keys are a set, the key that fires is not one of them.
+    assert_eq!(
+        parsed.unbinds,
+        [held(&[K::KEY_X, K::KEY_Z], K::KEY_W).with_attribute(KeyAttribute::OnRelease)]
+    );
+    assert_ne!(held(&[K::KEY_X], K::KEY_E), held(&[K::KEY_E], K::KEY_X));
+    assert_eq!(
+        parsed.bindings[2].definition().to_string(),
+        "control + y & z & @w"
+    );
+    assert_eq!(
+        parsed.bindings[0].definition().display_with(&MacStyle),
+        "⌃X&E"
+    );
+    let definition: Definition = "ctrl + x & exclam".parse()?;
+    assert_eq!(
+        definition,
+        held(&[K::KEY_X], K::KEY_1).with_modifiers(&[Control, Shift])
+    );
+    assert_eq!(definition.to_string().parse::<Definition>()?, definition);
+    assert_eq!(estimate(contents)?.total, 5);
+
+    let json = serde_json::to_value(&parsed.bindings[2])?;
+    assert_eq!(json["definition"]["held"], serde_json::json!(["y", "z"]));
+    let reparsed: Binding = serde_json::from_value(json)?;
+    assert_eq!(reparsed, parsed.bindings[2]);
+    Ok(())
+}
+
+#[test]
+fn test_key_sequences() -> Result<(), Box<dyn std::error::Error>> {
+    let contents = "
+super + w ; {1-3}
+    bspc desktop -f ^{1-3}
+super + {a, b} ; {x, y}
+    run {ax,ay,bx,by} && @enter resize
+ignore super + b ; y
+mode resize
+    @escape ; q
+        @escape
+    ignore super + w
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    let chord = |key: evdev::Key| Definition::new(key).with_modifiers(&[Super]);
+    use evdev::Key as K;
+    let resize = vec![ModeInstruction::Enter("resize".to_string())];
+    // Each chord expands on its own, the first one varying slowest.
+    assert_eq!(
+        parsed.bindings,
+        [
+            Binding::running("bspc desktop -f ^1")
+                .on(chord(K::KEY_W))
+                .then(Definition::new(K::KEY_1)),
+            Binding::running("bspc desktop -f ^2")
+                .on(chord(K::KEY_W))
+                .then(Definition::new(K::KEY_2)),
+            Binding::running("bspc desktop -f ^3")
+                .on(chord(K::KEY_W))
+                .then(Definition::new(K::KEY_3)),
+            Binding::running("run ax ")
+                .on(chord(K::KEY_A))
+                .then(Definition::new(K::KEY_X))
+                .with_mode_instructions(resize.clone()),
+            Binding::running("run ay ")
+                .on(chord(K::KEY_A))
+                .then(Definition::new(K::KEY_Y))
+                .with_mode_instructions(resize.clone()),
+            Binding::running("run bx ")
+                .on(chord(K::KEY_B))
+                .then(Definition::new(K::KEY_X))
+                .with_mode_instructions(resize),
+        ]
+    );
+    assert_eq!(
+        parsed.sequence_unbinds,
+        [vec![chord(K::KEY_B), Definition::new(K::KEY_Y)]]
+    );
+    assert_eq!(parsed.modes[0].unbinds, [chord(K::KEY_W)]);
+    assert_eq!(
+        parsed.modes[0].bindings[0].sequence(),
+        [
+            Definition::new(K::KEY_ESC).with_attribute(KeyAttribute::OnRelease),
+            Definition::new(K::KEY_Q)
+        ]
+    );
+    assert_eq!(parsed.bindings[0].definition(), &chord(K::KEY_W));
+    assert_eq!(
+        parsed.bindings[0].last_definition(),
+        &Definition::new(K::KEY_1)
+    );
+    assert_eq!(
+        parsed.bindings[0].to_config_string(),
+        "super + w ; 1\n    bspc desktop -f ^1"
+    );
+    assert_eq!(estimate(contents)?.total, 8);
+
+    // A chord alone unbinds the sequences starting with it.
+    let parsed = SwhkdParser::from(ParserInput::Raw(
+        "super + w ; 1\n    one\nsuper + w\n    two\nignore super + w",
+    ))?;
+    assert!(parsed.bindings.is_empty());
+
+    // Sequences only override sequences of the same chords.
+    let parsed = SwhkdParser::from(ParserInput::Raw(
+        "super + w ; 1\n    one\nsuper + 1\n    two\nsuper + w ; 1\n    three",
+    ))?;
+    let commands: Vec<_> = parsed.bindings.iter().map(Binding::command).collect();
+    assert_eq!(commands, ["three", "two"]);
+    assert_eq!(parsed.conflicts().len(), 1);
+
+    let json = serde_json::to_value(&parsed.bindings[0])?;
+    assert_eq!(
+        json["then"][0]["key"]["trigger"],
+        serde_json::json!({"Key": "1"})
+    );
+    let reparsed: Binding = serde_json::from_value(json)?;
+    assert_eq!(reparsed, parsed.bindings[0]);
+
+    // Variants multiply across the chords.
+    assert_parse_error!(
+        "super + {a, b} ; {1-3}\n    run {1-3}",
+        span: (2, 9)..(2, 14),
+        contains: "binding variants 6 does not equal the number of possible command variants 3"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_invalid_multi_key_chords() {
+    assert_parse_error!(
+        "ctrl + x & e\n    true",
+        span: (1, 10)..(1, 11),
+        contains: "chords of several keys are opt-in, enable them with `ParseOptions::with_multi_key_chords`"
+    );
+    let options = ParseOptions::default().with_multi_key_chords(true);
+    let error = |contents: &str| {
+        let err = SwhkdParser::from_with_options(ParserInput::Raw(contents), &options).unwrap_err();
+        let ParseError::Grammar(err) = err else {
+            panic!("expected a grammar error, got {err:?}");
+        };
+        (err.line_col.clone(), err.variant.message().into_owned())
+    };
+    let (at, message) = error("ctrl + @x & e\n    true");
+    assert!(matches!(
+        at,
+        pest::error::LineColLocation::Span((1, 8), (1, 10))
+    ));
+    assert_eq!(
+        message,
+        "held keys cannot have attributes, `~` and `@` apply to the key after the last `&`"
+    );
+    let (at, message) = error("{a, scroll_up} & e\n    {true,false}");
+    assert!(matches!(
+        at,
+        pest::error::LineColLocation::Span((1, 5), (1, 14))
+    ));
+    assert_eq!(
+        message,
+        "scroll events cannot be held, only keys can come before `&`"
+    );
+    assert!(matches!(
+        error("ctrl + x & super\n    true").0,
+        pest::error::LineColLocation::Pos(_)
+    ));
+}
+
+#[test]
+fn test_any_collisions() -> Result<(), ParseError> {
+    let contents = "
+any + a
+    first
+any + shift + a
+    second
+any + b
+    kept
+any + b
+    replaced
+mode media
+    any + {ctrl +, alt +} c
+        {one,two}
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+    // The last declaration wins, like any override.
+    let commands: Vec<_> = parsed.bindings.iter().map(Binding::command).collect();
+    assert_eq!(commands, ["second", "replaced"]);
+    let collisions: Vec<_> = parsed
+        .warnings
+        .iter()
+        .filter_map(|warning| match warning {
+            Warning::AnyCollision { first, second } => Some((first.line, second.line)),
+            _ => None,
+        })
+        .collect();
+    // Declarations written the same way are plain overrides.
+    assert_eq!(collisions, [(2, 4), (11, 11)]);
+    let rendered = parsed.warnings[0].to_string();
+    assert!(rendered.contains("bound here with `any`"), "{rendered}");
+    assert!(
+        rendered.contains("this later declaration wins"),
+        "{rendered}"
+    );
+
+    // Resolution does not depend on anything but the config.
+    for _ in 0..8 {
+        let reparsed = SwhkdParser::from(ParserInput::Raw(contents))?;
+        assert_eq!(reparsed.bindings, parsed.bindings);
+        assert_eq!(reparsed.modes[0].bindings, parsed.modes[0].bindings);
+        assert_eq!(reparsed.warnings, parsed.warnings);
+    }
+    Ok(())
+}
+
+/// Removes the ANSI escape codes of a colored rendering.
+fn strip_ansi(colored: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = colored.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|c| *c == 'm');
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+#[test]
+fn test_render_diagnostics() -> Result<(), ParseError> {
+    let Err(err) = SwhkdParser::from(ParserInput::Raw("super + a\n    true\nsuper + + b")) else {
+        panic!("expected a grammar error");
+    };
+    let grammar = Diagnostic::from(&err);
+    let parsed = SwhkdParser::from(ParserInput::Raw(
+        "super + Shift + exclam\n    true\nsuper + a\n    one\nsuper + @a\n    two",
+    ))?;
+    let [redundant, variants] = &parsed.warnings[..] else {
+        panic!("unexpected warnings {:?}", parsed.warnings);
+    };
+    let redundant = Diagnostic::from(redundant);
+    let variants = Diagnostic::from(variants);
+    let Err(err) = SwhkdParser::from(ParserInput::Raw("super + a\n    @enter nowhere")) else {
+        panic!("expected an unknown mode");
+    };
+    let unknown = Diagnostic::from(&err);
+
+    assert_eq!(
+        grammar.render(RenderStyle::Plain),
+        "error: unable to parse config file
+ --> <anonymous>:3:9
+  |
+3 | super + + b
+  |         ^
+  |
+  = expected modifier, key_normal, shorthand, modifier_shorthand, modifier_omit_shorthand, variable, virtual_key, switch_event, or modifier_only"
+    );
+    assert_eq!(
+        grammar.render(RenderStyle::Color),
+        "\x1b[1;31merror:\x1b[0m \x1b[1munable to parse config file\x1b[0m
+ --> \x1b[2m<anonymous>:3:9\x1b[0m
+  |
+3 | super + + b
+  |         \x1b[1m^\x1b[0m
+  |
+  = \x1b[36mexpected modifier, key_normal, shorthand, modifier_shorthand, modifier_omit_shorthand, variable, virtual_key, switch_event, or modifier_only\x1b[0m"
+    );
+    assert_eq!(
+        redundant.render(RenderStyle::Plain),
+        "warning:  --> <anonymous>:1:9
+  |
+1 | super + Shift + exclam
+  |         ^---^
+  |
+  = `shift` is redundant, the shifted symbol in this chord already implies it"
+    );
+    assert_eq!(
+        redundant.render(RenderStyle::Color),
+        "\x1b[1;33mwarning:\x1b[0m  --> \x1b[2m<anonymous>:1:9\x1b[0m
+  |
+1 | super + Shift + exclam
+  |         \x1b[1m^---^\x1b[0m
+  |
+  = \x1b[36m`shift` is redundant, the shifted symbol in this chord already implies it\x1b[0m"
+    );
+    assert_eq!(
+        variants.render(RenderStyle::Plain),
+        "warning:  --> <anonymous>:3:1
+  |
+3 | super + a
+  | ^-------^
+  |
+  = bound here
+ --> <anonymous>:5:1
+  |
+5 | super + @a
+  | ^--------^
+  |
+  = and here with different key attributes, it does not override the first binding, both fire, one on press and the other on release"
+    );
+    assert_eq!(
+        variants.render(RenderStyle::Color),
+        "\x1b[1;33mwarning:\x1b[0m  --> \x1b[2m<anonymous>:3:1\x1b[0m
+  |
+3 | super + a
+  | \x1b[1m^-------^\x1b[0m
+  |
+  = \x1b[36mbound here\x1b[0m
+ --> \x1b[2m<anonymous>:5:1\x1b[0m
+  |
+5 | super + @a
+  | \x1b[1m^--------^\x1b[0m
+  |
+  = \x1b[36mand here with different key attributes, it does not override the first binding, both fire, one on press and the other on release\x1b[0m"
+    );
+    assert_eq!(unknown.severity, Severity::Error);
+    assert_eq!(
+        unknown.render(RenderStyle::Plain),
+        "error:  --> <anonymous>:1:1
+  |
+1 | super + a
+  | ^-------^
+  |
+  = `@enter nowhere` refers to an undeclared mode, the config declares no modes"
+    );
+
+    // Colors never change the text, and the plain rendering of warnings is
+    // what they display as.
+    for diagnostic in [&grammar, &redundant, &variants, &unknown] {
+        assert_eq!(
+            strip_ansi(&diagnostic.render(RenderStyle::Color)),
+            diagnostic.render(RenderStyle::Plain)
+        );
+    }
+    assert_eq!(
+        variants.render(RenderStyle::Plain),
+        format!("warning: {}", parsed.warnings[1])
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cache_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let config = dir.path().join("swhkdrc");
+    std::fs::write(dir.path().join("extra.swhkd"), "super + b\n    extra")?;
+    std::fs::write(
+        &config,
+        "include extra.swhkd
+ignore super + c
+super + {1-9}
+    bspc desktop -f ^{1-9}
+super + x ; shift + {a,b}
+    run {a,b}
+mode resize
+    escape
+        @escape
+endmode",
+    )?;
+    let input = || ParserInput::Path(&config);
+    let cached = SwhkdParser::cacheable(input())?;
+    let bytes = cached.serialize();
+    let reloaded = CachedConfig::deserialize(&bytes)?;
+    reloaded.check_fresh(&input())?;
+
+    let parsed = SwhkdParser::from(input())?;
+    assert_eq!(reloaded.parsed.bindings, parsed.bindings);
+    assert_eq!(reloaded.parsed.unbinds, parsed.unbinds);
+    assert_eq!(reloaded.parsed.modes[0].bindings, parsed.modes[0].bindings);
+    assert_eq!(reloaded.parsed.imports, parsed.imports);
+    assert_eq!(
+        serde_json::to_string(&reloaded.parsed)?,
+        serde_json::to_string(&parsed)?
+    );
+    assert_eq!(reloaded.serialize(), bytes);
+
+    // Raw input is compared as is.
+    let raw = "super + a\n    a";
+    let cached = SwhkdParser::cacheable(ParserInput::Raw(raw))?;
+    cached.check_fresh(&ParserInput::Raw(raw))?;
+    assert!(matches!(
+        cached.check_fresh(&ParserInput::Raw("super + a\n    b")),
+        Err(CacheError::Stale(_))
+    ));
+    assert!(matches!(
+        cached.check_fresh(&input()),
+        Err(CacheError::Stale(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_stale_cache() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let config = dir.path().join("swhkdrc");
+    let extra = dir.path().join("extra.swhkd");
+    let cache = dir.path().join("swhkdrc.cache");
+    std::fs::write(&extra, "super + b\n    before")?;
+    std::fs::write(&config, "include extra.swhkd\nsuper + a\n    root")?;
+    let input = || ParserInput::Path(&config);
+
+    let parsed = SwhkdParser::from_with_cache_file(input(), &cache)?;
+    assert_eq!(parsed.bindings[1].command(), "before");
+    let cached = CachedConfig::deserialize(&std::fs::read(&cache)?)?;
+    cached.check_fresh(&input())?;
+
+    // Editing an include invalidates the cache, which is written again.
+    std::fs::write(&extra, "super + b\n    after")?;
+    match cached.check_fresh(&input()) {
+        Err(CacheError::Stale(path)) => assert!(path.ends_with("extra.swhkd")),
+        other => panic!("expected a stale cache, got {other:?}"),
+    }
+    let parsed = SwhkdParser::from_with_cache_file(input(), &cache)?;
+    assert_eq!(parsed.bindings[1].command(), "after");
+    CachedConfig::deserialize(&std::fs::read(&cache)?)?.check_fresh(&input())?;
+
+    // So does removing it.
+    std::fs::remove_file(&extra)?;
+    assert!(matches!(
+        CachedConfig::deserialize(&std::fs::read(&cache)?)?.check_fresh(&input()),
+        Err(CacheError::Stale(_))
+    ));
+    assert!(SwhkdParser::from_with_cache_file(input(), &cache).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_corrupted_cache() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let cache = dir.path().join("swhkdrc.cache");
+    let input = || ParserInput::Raw("super + a\n    a");
+    let bytes = SwhkdParser::cacheable(input())?.serialize();
+
+    assert!(matches!(
+        CachedConfig::deserialize(b"not a cache"),
+        Err(CacheError::NotACache)
+    ));
+    assert!(matches!(
+        CachedConfig::deserialize(&bytes[..bytes.len() / 2]),
+        Err(CacheError::Corrupted(_))
+    ));
+    assert!(matches!(
+        CachedConfig::deserialize(&[bytes.as_slice(), b"\0"].concat()),
+        Err(CacheError::Corrupted(_))
+    ));
+    let mut other_format = bytes.clone();
+    other_format[6] += 1;
+    assert!(matches!(
+        CachedConfig::deserialize(&other_format),
+        Err(CacheError::UnsupportedFormat(2))
+    ));
+
+    // None of them is an error when loading the config.
+    for corrupted in [&b"not a cache"[..], &bytes[..bytes.len() / 2]] {
+        std::fs::write(&cache, corrupted)?;
+        let parsed = SwhkdParser::from_with_cache_file(input(), &cache)?;
+        assert_eq!(parsed.bindings[0].command(), "a");
+        assert_eq!(std::fs::read(&cache)?, bytes);
+    }
+    Ok(())
+}
+
+/// Run with `cargo test --release -- --ignored bench_cache`.
+#[test]
+#[ignore = "timing, run in release mode"]
+fn bench_cache_against_parsing() -> Result<(), ParseError> {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = dir.path().join("pathological.cache");
+    let input = || ParserInput::Path(Path::new("tests/fixtures/pathological.swhkd"));
+    SwhkdParser::from_with_cache_file(input(), &cache)?;
+    let time = |run: &dyn Fn() -> Result<(), ParseError>| {
+        let start = std::time::Instant::now();
+        for _ in 0..20 {
+            run()?;
+        }
+        Ok::<_, ParseError>(start.elapsed())
+    };
+    let cached = time(&|| SwhkdParser::from_with_cache_file(input(), &cache).map(drop))?;
+    let parsing = time(&|| SwhkdParser::from(input()).map(drop))?;
+    println!("cached: {cached:?}, parsing: {parsing:?}");
+    assert!(cached * 2 <= parsing);
+    Ok(())
+}
+
+#[test]
+fn test_bindings_iter() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let config = dir.path().join("swhkdrc");
+    std::fs::write(
+        dir.path().join("extra.swhkd"),
+        "super + {b,c}\n    extra {b,c}\n[work] super + w\n    work",
+    )?;
+    std::fs::write(
+        &config,
+        "include extra.swhkd
+super + {1-9}
+    bspc desktop -f ^{1-9} && @enter resize
+super + x ; shift + {a,b}
+    run {a,b}
+mode resize
+    escape
+        @escape
+endmode",
+    )?;
+    let streamed =
+        SwhkdParser::bindings_iter(ParserInput::Path(&config)).collect::<Result<Vec<_>, _>>()?;
+    let parsed = SwhkdParser::from(ParserInput::Path(&config))?;
+    assert_eq!(streamed, parsed.bindings);
+
+    // Before merging, overridden bindings are yielded as well.
+    let commands: Vec<_> = SwhkdParser::bindings_iter(ParserInput::Raw(
+        "super + a\n    first\nsuper + a\n    second\nignore super + a",
+    ))
+    .map(|binding| binding.map(|binding| binding.command().to_string()))
+    .collect::<Result<_, _>>()?;
+    assert_eq!(commands, ["first", "second"]);
+
+    // Errors are the only item.
+    for config in ["super + a\n    @enter nowhere", "super + \n    a"] {
+        let mut streamed = SwhkdParser::bindings_iter(ParserInput::Raw(config));
+        assert!(streamed.next().is_some_and(|item| item.is_err()));
+        assert!(streamed.next().is_none());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_bindings_iter_large_config() -> Result<(), ParseError> {
+    // 8 * 26^3 bindings, built one at a time.
+    let config = "{_, ctrl +} {_, alt +} {_, shift +} {a-z} ; {a-z} ; {a-z}
+    run {1-8} {a-z}{a-z}{a-z}";
+    let mut count = 0;
+    let mut last = None;
+    for binding in SwhkdParser::bindings_iter(ParserInput::Raw(config)) {
+        count += 1;
+        last = Some(binding?);
+    }
+    assert_eq!(count, 140_608);
+    let last = last.unwrap();
+    assert_eq!(last.command(), "run 8 zzz");
+    assert_eq!(
+        last.sequence(),
+        [
+            Definition::new(evdev::Key::KEY_Z).with_modifiers(&[Control, Alt, Shift]),
+            Definition::new(evdev::Key::KEY_Z),
+            Definition::new(evdev::Key::KEY_Z),
+        ]
+    );
+
+    // The numbers of variants are counted, not built.
+    let mismatched = "{_, ctrl +} {_, alt +} {_, shift +} {a-z} ; {a-z} ; {a-z}
+    run {1-8} {a-z}{a-z}";
+    assert_parse_error!(
+        mismatched,
+        span: (2, 9)..(2, 25),
+        contains: "the number of possible binding variants 140608 does not equal the number of possible command variants 5408"
+    );
+    let errors: Vec<_> = SwhkdParser::bindings_iter(ParserInput::Raw(mismatched)).collect();
+    assert!(matches!(errors.as_slice(), [Err(ParseError::Grammar(_))]));
+    Ok(())
+}
+
+/// Every cell of key attribute, phase and held modifiers, see
+/// `sweet::semantics`.
+#[test]
+fn test_semantics_key_matrix() -> Result<(), ParseError> {
+    use sweet::semantics::{should_fire, FireDecision::*, ModifierState, Phase, SimulatedEvent};
+
+    let timing = [
+        ("a", Fire { forward: false }, Consume { forward: false }),
+        ("~a", Fire { forward: true }, Consume { forward: true }),
+        ("@a", Consume { forward: false }, Fire { forward: false }),
+        ("~@a", Consume { forward: true }, Fire { forward: true }),
+        (
+            "a passthrough",
+            Fire { forward: true },
+            Consume { forward: true },
+        ),
+        (
+            "@a passthrough",
+            Consume { forward: true },
+            Fire { forward: true },
+        ),
+    ];
+    let states = [&[][..], &[Super], &[Super, Shift], &[Shift]];
+    // The states each prefix of modifiers matches.
+    let prefixes = [
+        ("", &[true, false, false, false]),
+        ("super + ", &[false, true, false, false]),
+        ("super + shift + ", &[false, false, true, false]),
+        ("any + ", &[true, true, true, true]),
+    ];
+    let pressed = SimulatedEvent::Key(evdev::Key::KEY_A);
+    let other = SimulatedEvent::Key(evdev::Key::KEY_B);
+    for (key, on_press, on_release) in timing {
+        for (prefix, matching) in prefixes {
+            let definition: Definition = format!("{prefix}{key}").parse()?;
+            for (state, matches) in states.iter().zip(matching) {
+                let held = ModifierState::holding(state);
+                for (phase, expected) in [(Phase::Press, on_press), (Phase::Release, on_release)] {
+                    let expected = if *matches { expected } else { NoMatch };
+                    assert_eq!(
+                        should_fire(&definition, &pressed, &held, phase),
+                        expected,
+                        "`{prefix}{key}` holding {state:?} on {phase:?}"
+                    );
+                    assert_eq!(should_fire(&definition, &other, &held, phase), NoMatch);
+                }
+            }
+        }
+    }
+
+    // Held keys have to be held, other ones do not matter.
+    let definition = Definition::new(evdev::Key::KEY_E)
+        .with_modifiers(&[Control])
+        .with_held_keys([evdev::Key::KEY_X]);
+    let event = SimulatedEvent::Key(evdev::Key::KEY_E);
+    for (keys, expected) in [
+        (&[][..], NoMatch),
+        (&[evdev::Key::KEY_X], Fire { forward: false }),
+        (
+            &[evdev::Key::KEY_X, evdev::Key::KEY_Y],
+            Fire { forward: false },
+        ),
+        (&[evdev::Key::KEY_Y], NoMatch),
+    ] {
+        let held = ModifierState::holding(&[Control]).with_keys(keys.iter().copied());
+        assert_eq!(
+            should_fire(&definition, &event, &held, Phase::Press),
+            expected
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_semantics_modifiers_only() {
+    use sweet::semantics::{should_fire, FireDecision::*, ModifierState, Phase, SimulatedEvent};
+
+    let on_release = Definition::modifiers_only(&[Super, Shift], KeyAttribute::OnRelease);
+    let solo = Definition::modifiers_only(&[Super], KeyAttribute::OnRelease | KeyAttribute::Solo);
+    let meta = SimulatedEvent::Key(evdev::Key::KEY_LEFTMETA);
+    let shift = SimulatedEvent::Key(evdev::Key::KEY_RIGHTSHIFT);
+    let altgr = SimulatedEvent::Key(evdev::Key::KEY_RIGHTALT);
+    let letter = SimulatedEvent::Key(evdev::Key::KEY_A);
+    let super_held = ModifierState::holding(&[Super]);
+    let shift_held = ModifierState::holding(&[Shift]);
+    for (definition, event, held, phase, expected) in [
+        // Either modifier completes the chord, and is always forwarded.
+        (
+            &on_release,
+            &meta,
+            &shift_held,
+            Phase::Press,
+            Consume { forward: true },
+        ),
+        (
+            &on_release,
+            &meta,
+            &shift_held,
+            Phase::Release,
+            Fire { forward: true },
+        ),
+        (
+            &on_release,
+            &shift,
+            &super_held,
+            Phase::Release,
+            Fire { forward: true },
+        ),
+        (
+            &on_release,
+            &meta,
+            &ModifierState::default(),
+            Phase::Release,
+            NoMatch,
+        ),
+        (&on_release, &altgr, &super_held, Phase::Release, NoMatch),
+        (&on_release, &letter, &super_held, Phase::Release, NoMatch),
+        (
+            &on_release,
+            &meta,
+            &shift_held.clone().with_keys([evdev::Key::KEY_A]),
+            Phase::Release,
+            NoMatch,
+        ),
+        // `!` only fires when nothing interrupted the modifiers.
+        (
+            &solo,
+            &meta,
+            &ModifierState::default(),
+            Phase::Press,
+            Consume { forward: true },
+        ),
+        (
+            &solo,
+            &meta,
+            &ModifierState::default(),
+            Phase::Release,
+            Fire { forward: true },
+        ),
+        (
+            &solo,
+            &meta,
+            &ModifierState::default().interrupted(),
+            Phase::Release,
+            Consume { forward: true },
+        ),
+        (&solo, &meta, &shift_held, Phase::Release, NoMatch),
+    ] {
+        assert_eq!(
+            should_fire(definition, event, held, phase),
+            expected,
+            "{definition} on {event:?} holding {held:?} on {phase:?}"
+        );
+    }
+}
+
+#[test]
+fn test_semantics_instant_triggers() -> Result<(), ParseError> {
+    use sweet::semantics::{should_fire, FireDecision::*, ModifierState, Phase, SimulatedEvent};
+
+    let none = ModifierState::default();
+    let super_held = ModifierState::holding(&[Super]);
+    let scroll: Definition = "super + scroll_up".parse()?;
+    let lid = Definition::switch(SwitchType::Lid, true);
+    let lock = Definition::virtual_key("lock");
+    for (definition, event, held, on_press) in [
+        (
+            &scroll,
+            SimulatedEvent::Scroll(ScrollDirection::Up),
+            &super_held,
+            Fire { forward: false },
+        ),
+        (
+            &scroll,
+            SimulatedEvent::Scroll(ScrollDirection::Up),
+            &none,
+            NoMatch,
+        ),
+        (
+            &scroll,
+            SimulatedEvent::Scroll(ScrollDirection::Down),
+            &super_held,
+            NoMatch,
+        ),
+        (
+            &lid,
+            SimulatedEvent::Switch {
+                switch: SwitchType::Lid,
+                state: true,
+            },
+            &none,
+            Fire { forward: false },
+        ),
+        (
+            &lid,
+            SimulatedEvent::Switch {
+                switch: SwitchType::Lid,
+                state: false,
+            },
+            &none,
+            NoMatch,
+        ),
+        (
+            &lock,
+            SimulatedEvent::Virtual("lock".to_string()),
+            &none,
+            Fire { forward: false },
+        ),
+        (
+            &lock,
+            SimulatedEvent::Virtual("unlock".to_string()),
+            &none,
+            NoMatch,
+        ),
+    ] {
+        assert_eq!(
+            should_fire(definition, &event, held, Phase::Press),
+            on_press
+        );
+        // Nothing is ever released.
+        assert_eq!(
+            should_fire(definition, &event, held, Phase::Release),
+            NoMatch
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_semantics_modes() -> Result<(), ParseError> {
+    use sweet::semantics::{
+        after_binding, forwards_unbound, mode_transition, ModeStack, ModifierState, Phase,
+        SimulatedEvent,
+    };
+
+    let config = "
+super + r
+    @enter resize
+mode resize oneoff
+    h
+        grow && @enter fine
+    l
+        shrink
+endmode
+mode fine swallow
+    replay ~b
+    escape
+        @escape
+endmode";
+    let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    let modes = &parsed.modes;
+    let top = ModeStack::default();
+    let enter = |name: &str| ModeInstruction::Enter(name.to_string());
+
+    // Instructions on their own.
+    let resize = mode_transition(&top, &enter("resize"), modes);
+    assert_eq!(resize, ModeStack(vec![0]));
+    assert_eq!(resize.active_mode(modes).unwrap().name, "resize");
+    assert_eq!(
+        mode_transition(&resize, &enter("resize"), modes),
+        ModeStack(vec![0, 0])
+    );
+    assert_eq!(mode_transition(&resize, &enter("nowhere"), modes), resize);
+    assert_eq!(
+        mode_transition(&resize, &ModeInstruction::Escape, modes),
+        top
+    );
+    assert_eq!(mode_transition(&top, &ModeInstruction::Escape, modes), top);
+
+    // A binding of a oneoff mode leaves it, unless it changes modes itself.
+    let (enter_resize, grow, shrink) = (
+        &parsed.bindings[0],
+        &modes[0].bindings[0],
+        &modes[0].bindings[1],
+    );
+    assert_eq!(after_binding(&top, enter_resize, false, modes), resize);
+    assert_eq!(after_binding(&resize, shrink, true, modes), top);
+    assert_eq!(
+        after_binding(&resize, enter_resize, false, modes),
+        ModeStack(vec![0, 0])
+    );
+    let fine = after_binding(&resize, grow, true, modes);
+    assert_eq!(fine, ModeStack(vec![0, 1]));
+    assert_eq!(
+        after_binding(&fine, &modes[1].bindings[0], true, modes),
+        resize
+    );
+
+    // Swallow modes keep unbound events but their replay keys.
+    let held = ModifierState::default();
+    let key = |key| SimulatedEvent::Key(key);
+    for (stack, event, forwarded) in [
+        (&top, key(evdev::Key::KEY_A), true),
+        (&resize, key(evdev::Key::KEY_A), true),
+        (&fine, key(evdev::Key::KEY_A), false),
+        (&fine, key(evdev::Key::KEY_B), true),
+    ] {
+        for phase in [Phase::Press, Phase::Release] {
+            assert_eq!(
+                forwards_unbound(stack, modes, &event, &held, phase),
+                forwarded,
+                "{event:?} in {stack:?}"
+            );
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_inline_command() {
+    assert_parses_to!(
+        "super + a = firefox --new-window",
+        [binding!("super + a" => "firefox --new-window")]
+    );
+    // Only the first ` = ` separates the command.
+    assert_parses_to!(
+        "super + e = env A=1 sh -c 'test a = a'",
+        [binding!("super + e" => "env A=1 sh -c 'test a = a'")]
+    );
+    assert_parse_error!(
+        "super + a=firefox",
+        span: (1, 10)..(1, 11),
+        contains: "`=` needs a blank on both sides"
+    );
+    assert_parse_error!(
+        "super + a =firefox",
+        span: (1, 11)..(1, 12),
+        contains: "`=` needs a blank on both sides"
+    );
+    assert_parse_error!(
+        "super + a =",
+        span: (1, 11)..(1, 12),
+        contains: "followed by the command on the same line"
+    );
+}
+
+#[test]
+fn test_inline_command_shorthand() {
+    assert_parses_to!(
+        "super + {_, shift +} {1,2} = bspc {desktop,node} -f '^{1,2}'",
+        [
+            binding!("super + 1" => "bspc desktop -f '^1'"),
+            binding!("super + 2" => "bspc desktop -f '^2'"),
+            binding!("super + shift + 1" => "bspc node -f '^1'"),
+            binding!("super + shift + 2" => "bspc node -f '^2'"),
+        ]
+    );
+}
+
+#[test]
+fn test_inline_command_on_equal_key() {
+    assert_parses_to!(
+        "super + \\= = pamixer -i 5",
+        [binding!("super + equal" => "pamixer -i 5")]
+    );
+    assert_parses_to!(
+        "super + =\n    pamixer -i 5",
+        [binding!("super + equal" => "pamixer -i 5")]
+    );
+    // The first `=` is a key, so the second one starts the command.
+    assert_parses_to!(
+        "super + = = pamixer -i 5",
+        [binding!("super + equal" => "pamixer -i 5")]
+    );
+}
+
+#[test]
+fn test_inline_and_indented_commands() -> Result<(), ParseError> {
+    let config = "
+super + a = firefox
+super + b
+    alacritty
+# a comment
+super + c = kitty \\
+    --single-instance
+";
+    assert_parses_to!(
+        config,
+        [
+            binding!("super + a" => "firefox"),
+            binding!("super + b" => "alacritty"),
+            binding!("super + c" => "kitty --single-instance"),
+        ]
+    );
+    let root = sweet::syntax_tree(config)?;
+    let leaves: String = root.leaves().map(|leaf| leaf.text().to_string()).collect();
+    assert_eq!(leaves, config);
+    Ok(())
+}
+
+#[test]
+fn test_inline_command_in_mode() -> Result<(), ParseError> {
+    let config = "
+super + r = @enter resize
+mode resize
+    h = bspc node -z left -20 0
+    l
+        bspc node -z right 20 0
+    Escape = @escape
+endmode
+";
+    let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    assert_eq!(
+        parsed.bindings[0].mode_instructions(),
+        [ModeInstruction::Enter("resize".to_string())]
+    );
+    let mode = &parsed.modes[0];
+    assert_eq!(mode.bindings[0].command(), "bspc node -z left -20 0");
+    assert_eq!(mode.bindings[1].command(), "bspc node -z right 20 0");
+    assert_eq!(
+        mode.bindings[2].mode_instructions(),
+        [ModeInstruction::Escape]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_normalize_commands() -> Result<(), ParseError> {
+    let indented = "
+super + a
+    firefox
+mode resize
+    h # shrink
+        bspc node -z left -20 0
+    l
+        bspc node -z right 20 0
+endmode
+super + b
+    echo one
+    echo two
+";
+    let inline = "
+super + a = firefox
+mode resize
+    h # shrink
+        bspc node -z left -20 0
+    l = bspc node -z right 20 0
+endmode
+super + b
+    echo one
+    echo two
+";
+    assert_eq!(normalize_commands(indented, CommandForm::Inline)?, inline);
+    // Commented and multi-line commands stay indented, the others come back
+    // the way they were.
+    assert_eq!(normalize_commands(inline, CommandForm::Indented)?, indented);
+    for form in [CommandForm::Inline, CommandForm::Indented] {
+        let normalized = normalize_commands(indented, form)?;
+        let normalized = SwhkdParser::from(ParserInput::Raw(&normalized))?;
+        let original = SwhkdParser::from(ParserInput::Raw(indented))?;
+        assert_eq!(normalized.bindings, original.bindings);
+        assert_eq!(normalized.modes[0].bindings, original.modes[0].bindings);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_indent_style() -> Result<(), ParseError> {
+    let config = "
+super + a
+\tfirefox \\
+\t\t--private-window
+super + b
+    alacritty
+mode resize
+\th
+\t\tbspc node -z left -20 0
+\tignore h
+endmode
+";
+    let warnings = |style| -> Result<Vec<_>, ParseError> {
+        let options = ParseOptions::default().with_indent_style(style);
+        let parsed = SwhkdParser::from_with_options(ParserInput::Raw(config), &options)?;
+        assert_eq!(
+            parsed.bindings,
+            SwhkdParser::from(ParserInput::Raw(config))?.bindings
+        );
+        Ok(parsed
+            .warnings
+            .iter()
+            .map(|warning| match warning {
+                Warning::Indentation {
+                    span,
+                    found,
+                    expected,
+                } => (
+                    span.line,
+                    span.column,
+                    span.end_column,
+                    found.clone(),
+                    expected.clone(),
+                ),
+                warning => panic!("unexpected warning {warning:?}"),
+            })
+            .collect())
+    };
+    let entry = |line, end_column, found: &str, expected: &str| {
+        (line, 1, end_column, found.to_string(), expected.to_string())
+    };
+
+    assert_eq!(warnings(IndentStyle::Any)?, []);
+    assert_eq!(
+        warnings(IndentStyle::Tabs)?,
+        [entry(6, 5, "4 spaces", "1 tab")]
+    );
+    assert_eq!(
+        warnings(IndentStyle::Spaces(2))?,
+        [
+            entry(3, 2, "1 tab", "2 spaces"),
+            entry(4, 3, "2 tabs", "4 spaces"),
+            entry(6, 5, "4 spaces", "2 spaces"),
+            entry(8, 2, "1 tab", "2 spaces"),
+            entry(9, 3, "2 tabs", "4 spaces"),
+            entry(10, 2, "1 tab", "2 spaces"),
+        ]
+    );
+
+    let options = ParseOptions::default().with_indent_style(IndentStyle::Spaces(4));
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(config), &options)?;
+    assert_eq!(
+        parsed.warnings[0].to_string(),
+        " --> <anonymous>:3:1
+  |
+3 | \tfirefox \\
+  | ^
+  |
+  = indented with 1 tab, expected 4 spaces"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_reindent() -> Result<(), ParseError> {
+    let config = "
+super + a
+  firefox \\
+    --private-window
+mode resize
+    h
+\tbspc node -z left -20 0
+endmode
+";
+    assert_eq!(reindent(config, IndentStyle::Any)?, config);
+    assert_eq!(
+        reindent(config, IndentStyle::Tabs)?,
+        "
+super + a
+\tfirefox \\
+\t\t--private-window
+mode resize
+\th
+\t\tbspc node -z left -20 0
+endmode
+"
+    );
+    let spaces = reindent(config, IndentStyle::Spaces(2))?;
+    assert_eq!(
+        spaces,
+        "
+super + a
+  firefox \\
+    --private-window
+mode resize
+  h
+    bspc node -z left -20 0
+endmode
+"
+    );
+    // The result conforms to the style and means the same.
+    let options = ParseOptions::default().with_indent_style(IndentStyle::Spaces(2));
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(&spaces), &options)?;
+    assert_eq!(parsed.warnings, []);
+    let original = SwhkdParser::from(ParserInput::Raw(config))?;
+    assert_eq!(parsed.bindings, original.bindings);
+    assert_eq!(parsed.modes[0].bindings, original.modes[0].bindings);
+    Ok(())
+}
+
+#[test]
+fn test_to_config_string_round_trips() -> Result<(), Box<dyn std::error::Error>> {
+    let mut paths = vec![Path::new("hotkeys.swhkd").to_path_buf()];
+    for dir in ["tests/fixtures", "tests/fixtures/lenient"] {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "swhkd") {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    let mut round_tripped = 0;
+    for path in &paths {
+        // Fixtures of broken configs are only parsed leniently.
+        let Ok(parsed) = SwhkdParser::from(ParserInput::Path(path)) else {
+            continue;
+        };
+        let emitted = parsed.to_config_string();
+        let reparsed = SwhkdParser::from(ParserInput::Raw(&emitted)).unwrap_or_else(|err| {
+            panic!("{} emitted\n{emitted}\nfailing with {err}", path.display())
+        });
+        let context = format!("{} emitted\n{emitted}", path.display());
+        assert_eq!(reparsed.bindings, parsed.bindings, "{context}");
+        assert_eq!(reparsed.unbinds, parsed.unbinds, "{context}");
+        assert_eq!(
+            reparsed.sequence_unbinds, parsed.sequence_unbinds,
+            "{context}"
+        );
+        assert_eq!(reparsed.settings, parsed.settings, "{context}");
+        assert_eq!(reparsed.modes.len(), parsed.modes.len(), "{context}");
+        for (reparsed, parsed) in reparsed.modes.iter().zip(&parsed.modes) {
+            assert_eq!(reparsed.name, parsed.name, "{context}");
+            assert_eq!(
+                (reparsed.oneoff, reparsed.swallow),
+                (parsed.oneoff, parsed.swallow),
+                "{context}"
+            );
+            assert_eq!(reparsed.bindings, parsed.bindings, "{context}");
+            assert_eq!(reparsed.unbinds, parsed.unbinds, "{context}");
+            assert_eq!(
+                reparsed.sequence_unbinds, parsed.sequence_unbinds,
+                "{context}"
+            );
+            assert_eq!(reparsed.replay, parsed.replay, "{context}");
+            assert_eq!(reparsed.settings, parsed.settings, "{context}");
+        }
+        // Emitting is canonical: the result renders to itself, but for the
+        // comments naming the included files.
+        assert!(emitted.ends_with(&reparsed.to_config_string()), "{context}");
+        round_tripped += 1;
+    }
+    assert!(round_tripped >= 5, "only {round_tripped} fixtures parsed");
+    Ok(())
+}
+
+#[test]
+fn test_too_many_variants() -> Result<(), ParseError> {
+    let options = ParseOptions::default().with_max_binding_variants(676);
+    let parse = |config| SwhkdParser::from_with_options(ParserInput::Raw(config), &options);
+
+    // 26 * 26 bindings, just at the limit.
+    let parsed = parse("super + {a-z} ; {a-z}\n    echo {a-z}{a-z}")?;
+    assert_eq!(parsed.bindings.len(), 676);
+
+    let err =
+        parse("a\n    true\n{_, shift +} {a-z} ; {a-z}\n    echo {x,y} {a-z}{a-z}").unwrap_err();
+    let ParseError::TooManyVariants {
+        got,
+        limit,
+        whole_config,
+        span,
+    } = &err
+    else {
+        panic!("expected too many variants, got {err:?}");
+    };
+    assert_eq!((*got, *limit, *whole_config), (1352, 676, false));
+    assert_eq!((span.line, span.column), (3, 1));
+    assert!(err
+        .to_string()
+        .contains("this declaration expands to 1352 bindings, over the limit of 676"));
+
+    // Unbinds count too.
+    let err = parse("ignore {_, shift +} {a-z} ; {a-z}").unwrap_err();
+    assert!(matches!(err, ParseError::TooManyVariants { got: 1352, .. }));
+
+    // The defaults apply to `SwhkdParser::from`, before anything is built.
+    let err = SwhkdParser::from(ParserInput::Raw(
+        "{a-z} ; {a-z} ; {a-z} ; {a-d}\n    echo {a-z}{a-z}{a-z}{a-d}",
+    ))
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::TooManyVariants {
+            got: 70_304,
+            limit: 65_536,
+            whole_config: false,
+            ..
+        }
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_too_many_variants_in_config() -> Result<(), ParseError> {
+    let options = ParseOptions::default().with_max_total_variants(1000);
+    let parse = |config| SwhkdParser::from_with_options(ParserInput::Raw(config), &options);
+
+    let parsed =
+        parse("super + {a-z} ; {a-z}\n    echo {a-z}{a-z}\nsuper + {0-9}\n    echo {0-9}")?;
+    assert_eq!(parsed.bindings.len(), 686);
+
+    let config = "
+super + {a-z} ; {a-z}
+    echo {a-z}{a-z}
+mode m
+    alt + {a-z} ; {a-z}
+        echo {a-z}{a-z}
+endmode";
+    let err = parse(config).unwrap_err();
+    assert!(matches!(
+        &err,
+        ParseError::TooManyVariants {
+            got: 1352,
+            limit: 1000,
+            whole_config: true,
+            span,
+        } if span.line == 5
+    ));
+    let rendered = Diagnostic::from(&err).render(RenderStyle::Plain);
+    assert!(rendered.contains("with this declaration, the config expands to 1352 bindings"));
+    assert!(rendered.contains("ParseOptions::with_max_total_variants"));
+
+    // By default, a config may expand to many more bindings than a single
+    // declaration.
+    let config = ["super", "alt", "ctrl", "shift"]
+        .map(|modifier| {
+            format!("{modifier} + {{a-z}} ; {{a-z}} ; {{a-z}}\n    echo {{a-z}}{{a-z}}{{a-z}}")
+        })
+        .join("\n");
+    let parsed = SwhkdParser::from(ParserInput::Raw(&config))?;
+    assert_eq!(parsed.bindings.len(), 4 * 26 * 26 * 26);
+    assert!(parsed.bindings.len() > ParseOptions::default().max_binding_variants);
+    Ok(())
+}
+
+#[test]
+fn test_max_import_depth() -> Result<(), ParseError> {
+    // `f1` includes `f2` and so on up to `f100`.
+    let chain: Vec<_> = (1..=100)
+        .map(|i| (format!("f{i}"), format!("include f{}\n", i + 1)))
+        .collect();
+    let chain: Vec<_> = chain
+        .iter()
+        .map(|(path, contents)| (path.as_str(), contents.as_str()))
+        .chain([("f101", "super + a\n    deepest")])
+        .collect();
+
+    let options = map_options(&chain).with_max_import_depth(101);
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw("include f1"), &options)?;
+    assert_eq!(parsed.bindings[0].command(), "deepest");
+
+    let err = SwhkdParser::from_with_options(ParserInput::Raw("include f1"), &map_options(&chain))
+        .unwrap_err();
+    let ParseError::ImportFailed {
+        path,
+        cause:
+            ConfigReadError::ImportTooDeep {
+                path: too_deep,
+                requested_at: too_deep_at,
+                limit: 64,
+            },
+        requested_at,
+    } = &err
+    else {
+        panic!("expected a too deep import, got {err:?}")
+    };
+    assert_eq!(path, Path::new("f65"));
+    assert_eq!(&*requested_at.file, "f64");
+    assert_eq!(too_deep, Path::new("f65"));
+    assert_eq!(too_deep_at, requested_at);
+    assert!(err
+        .to_string()
+        .contains("unable to import `f65`: includes are nested deeper than the limit of 64"));
+    Ok(())
+}
+
+#[test]
+fn test_max_imports() -> Result<(), ParseError> {
+    let files = [
+        ("a", "include c\nsuper + a\n    a"),
+        ("b", "super + b\n    b"),
+        ("c", "super + c\n    c"),
+        ("d", "super + d\n    d"),
+    ];
+    // `c` is included twice but counts once.
+    let config = "include a\ninclude b\ninclude c";
+    let options = map_options(&files).with_max_imports(3);
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(config), &options)?;
+    assert_eq!(parsed.bindings.len(), 3);
+
+    let err =
+        SwhkdParser::from_with_options(ParserInput::Raw(&format!("{config}\ninclude d")), &options)
+            .unwrap_err();
+    let ParseError::ImportFailed {
+        path,
+        cause:
+            ConfigReadError::TooManyImports {
+                path: one_too_many,
+                requested_at: one_too_many_at,
+                limit: 3,
+            },
+        requested_at,
+    } = &err
+    else {
+        panic!("expected too many imports, got {err:?}")
+    };
+    assert_eq!(path, Path::new("d"));
+    assert_eq!(requested_at.line, 4);
+    assert_eq!(one_too_many, Path::new("d"));
+    assert_eq!((one_too_many_at.line, one_too_many_at.column), (4, 1));
+    Ok(())
+}
+
+fn definition_warnings(parsed: &SwhkdParser) -> Vec<&Warning> {
+    parsed
+        .warnings
+        .iter()
+        .filter(|warning| {
+            matches!(
+                warning,
+                Warning::UnusedDefinition { .. } | Warning::ShadowedDefinition { .. }
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_unused_variable() -> Result<(), ParseError> {
+    let config = "
+define mod = super
+define term = alacritty
+block menu
+    rofi -show drun
+endblock
+$mod + a
+    firefox";
+    let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    let [Warning::UnusedDefinition {
+        kind: DefinitionKind::Variable,
+        name,
+        span,
+        removed: 0,
+    }, Warning::UnusedDefinition {
+        kind: DefinitionKind::Block,
+        name: block,
+        removed: 0,
+        ..
+    }] = definition_warnings(&parsed)[..]
+    else {
+        panic!(
+            "expected an unused variable and block, got {:?}",
+            parsed.warnings
+        )
+    };
+    assert_eq!((name.as_str(), block.as_str()), ("term", "menu"));
+    assert_eq!((span.line, span.column), (3, 1));
+    assert!(parsed.warnings[0]
+        .to_string()
+        .contains("variable `$term` is never used"));
+    Ok(())
+}
+
+#[test]
+fn test_variable_used_by_overridden_binding() -> Result<(), ParseError> {
+    let config = "
+define browser = firefox
+define term = alacritty
+super + {a,b}
+    $browser {1,2}
+super + {a,b}
+    chromium {1,2}
+ignore super + c
+super + c
+    $term";
+    let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    let warnings = definition_warnings(&parsed);
+    assert!(matches!(
+        warnings[..],
+        [
+            Warning::UnusedDefinition { name: browser, removed: 2, .. },
+            Warning::UnusedDefinition { name: term, removed: 1, .. },
+        ] if browser == "browser" && term == "term"
+    ));
+    assert!(warnings[0]
+        .to_string()
+        .contains("the 2 bindings using it were overridden or unbound"));
+
+    // A binding of an inactive profile still uses its variables.
+    let config = "define term = alacritty\n[laptop] super + t\n    $term";
+    let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    assert_eq!(definition_warnings(&parsed), [] as [&Warning; 0]);
+    Ok(())
+}
+
+#[test]
+fn test_shadowed_definition() -> Result<(), ParseError> {
+    let config = "
+define term = alacritty
+super + t
+    $term
+define term = kitty";
+    let parsed = SwhkdParser::from(ParserInput::Raw(config))?;
+    assert_eq!(parsed.bindings[0].command(), "kitty");
+    let [Warning::ShadowedDefinition {
+        kind: DefinitionKind::Variable,
+        name,
+        first,
+        second,
+    }] = definition_warnings(&parsed)[..]
+    else {
+        panic!("expected a shadowed variable, got {:?}", parsed.warnings)
+    };
+    assert_eq!(name, "term");
+    assert_eq!((first.line, second.line), (2, 5));
+    Ok(())
+}
+
+#[test]
+fn test_used_definitions() -> Result<(), ParseError> {
+    let files = [(
+        "common",
+        "define mod = super\nblock lock\n    swaylock\n    systemctl suspend\nendblock",
+    )];
+    let config = "
+include common
+define resizing = notify-send resizing
+define ignored = alt
+$mod + l
+    &lock
+$mod + r
+    $resizing && @enter resize
+ignore $ignored + x
+mode resize
+    $mod + h
+        bspc node -z left -20 0
+endmode";
+    let parsed = SwhkdParser::from_with_options(ParserInput::Raw(config), &map_options(&files))?;
+    assert_eq!(definition_warnings(&parsed), [] as [&Warning; 0]);
+    Ok(())
+}
+
+/// The result of a poll that parsed the config.
+fn watch_parsed(watcher: &mut ConfigWatcher) -> Result<Box<SwhkdParser>, ParseError> {
+    match watcher.poll() {
+        Some(WatchEvent::Parsed { result, .. }) => result,
+        event => panic!("expected the config to be parsed, got {:?}", event),
+    }
+}
+
+#[test]
+fn test_config_watcher() -> Result<(), IoOrParseError> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("swhkdrc");
+    std::fs::write(&path, "super + a\n    firefox")?;
+    let mut watcher = ConfigWatcher::new(&path);
+    assert_eq!(watch_parsed(&mut watcher)?.bindings.len(), 1);
+    assert!(watcher.poll().is_none());
+    // Saving without changes does not parse again.
+    std::fs::write(&path, "super + a\n    firefox")?;
+    assert!(watcher.poll().is_none());
+
+    // Editors saving atomically replace the file with another one.
+    let saved = dir.path().join("swhkdrc.tmp");
+    std::fs::write(&saved, "super + {a,b}\n    {firefox,foot}")?;
+    std::fs::remove_file(&path)?;
+    assert!(matches!(watcher.poll(), Some(WatchEvent::Missing)));
+    assert!(watcher.poll().is_none());
+    std::fs::rename(&saved, &path)?;
+    assert_eq!(watch_parsed(&mut watcher)?.bindings.len(), 2);
+
+    // An include is watched once it exists.
+    std::fs::write(&path, "include extra\nsuper + {a,b}\n    {firefox,foot}")?;
+    assert!(matches!(
+        watch_parsed(&mut watcher),
+        Err(ParseError::ImportFailed { .. })
+    ));
+    assert!(watcher.poll().is_none());
+    let extra = dir.path().join("extra");
+    std::fs::write(&extra, "super + c\n    foot")?;
+    assert_eq!(watch_parsed(&mut watcher)?.bindings.len(), 3);
+    assert!(watcher.files().any(|file| file == extra));
+    std::fs::write(&extra, "super + c\n    foot\nsuper + d\n    foot")?;
+    assert_eq!(watch_parsed(&mut watcher)?.bindings.len(), 4);
+    assert!(watcher.poll().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_reload() -> Result<(), IoOrParseError> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("swhkdrc");
+    let extra = dir.path().join("extra");
+    std::fs::write(&path, "include extra\nsuper + a\n    firefox")?;
+    std::fs::write(&extra, "super + b\n    foot\nsuper + c\n    thunar")?;
+    let mut parsed = SwhkdParser::from(ParserInput::Path(&path))?;
+    assert_eq!(parsed.reload(ParserInput::Path(&path))?, Default::default());
+
+    std::fs::write(&extra, "super + b\n    alacritty\nsuper + d\n    thunar")?;
+    let report = parsed.reload(ParserInput::Path(&path))?;
+    assert_eq!(report.changed_files, [extra.canonicalize()?]);
+    let commands = |bindings: &[Binding]| -> Vec<String> {
+        bindings.iter().map(|b| b.command().to_string()).collect()
+    };
+    assert_eq!(commands(&report.added), ["thunar"]);
+    assert_eq!(report.added[0].definition().to_string(), "super + d");
+    assert_eq!(report.removed[0].definition().to_string(), "super + c");
+    assert_eq!(report.modified.len(), 1);
+    assert_eq!(report.modified[0].0.command(), "foot");
+    assert_eq!(report.modified[0].1.command(), "alacritty");
+    assert_eq!(parsed.bindings.len(), 3);
+    assert!(parsed
+        .reload(ParserInput::Path(&path))?
+        .changed_files
+        .is_empty());
+
+    // A failed reload keeps the last config, and is tried again.
+    std::fs::write(&path, "include extra\nsuper + key_nope\n    firefox")?;
+    assert!(parsed.reload(ParserInput::Path(&path)).is_err());
+    assert_eq!(parsed.bindings.len(), 3);
+    assert!(parsed.reload(ParserInput::Path(&path)).is_err());
+    std::fs::write(&path, "include extra\nsuper + a\n    firefox")?;
+    let report = parsed.reload(ParserInput::Path(&path))?;
+    assert_eq!(report.changed_files, [path.canonicalize()?]);
+    assert!(report.is_empty());
+
+    // A config given as a string is always parsed again.
+    let report = parsed.reload(ParserInput::Raw("super + a\n    firefox"))?;
+    assert!(report.changed_files.is_empty());
+    assert_eq!(commands(&report.removed), ["alacritty", "thunar"]);
+    Ok(())
+}
+
+#[test]
+fn test_reload_reuses_unchanged_includes() -> Result<(), IoOrParseError> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("swhkdrc");
+    let (vars, keys, nested) = (
+        dir.path().join("vars"),
+        dir.path().join("keys"),
+        dir.path().join("nested"),
+    );
+    let root =
+        "include vars\ninclude keys\nsuper + a\n    $term\nmode resize\ninclude nested\nendmode";
+    std::fs::write(&path, root)?;
+    std::fs::write(&vars, "define term = foot\ndefine unused = x")?;
+    std::fs::write(
+        &keys,
+        "include vars\nsuper + b\n    thunar\nignore super + z",
+    )?;
+    std::fs::write(&nested, "h\n    grow")?;
+    let mut parsed = SwhkdParser::from(ParserInput::Path(&path))?;
+    let assert_fresh = |parsed: &SwhkdParser| -> Result<(), ParseError> {
+        let fresh = SwhkdParser::from(ParserInput::Path(&path))?;
+        assert_eq!(parsed.bindings, fresh.bindings);
+        assert_eq!(parsed.modes[0].bindings, fresh.modes[0].bindings);
+        assert_eq!(parsed.warnings, fresh.warnings);
+        assert_eq!(parsed.imports, fresh.imports);
+        assert_eq!(parsed.variables, fresh.variables);
+        Ok(())
+    };
+
+    // Only the root config changed, the includes are reused.
+    std::fs::write(&path, format!("{root}\nsuper + c\n    mpv"))?;
+    let report = parsed.reload(ParserInput::Path(&path))?;
+    assert_eq!(report.added[0].command(), "mpv");
+    assert_fresh(&parsed)?;
+    assert!(parsed
+        .warnings
+        .iter()
+        .any(|warning| matches!(warning, Warning::DuplicateInclude { .. })));
+    assert!(parsed
+        .warnings
+        .iter()
+        .any(|warning| matches!(warning, Warning::UnusedDefinition { .. })));
+
+    // Changing an include parses the files including it again.
+    std::fs::write(&vars, "define term = kitty")?;
+    let report = parsed.reload(ParserInput::Path(&path))?;
+    assert_eq!(report.changed_files, [vars.canonicalize()?]);
+    assert_eq!(report.modified[0].1.command(), "kitty");
+    assert_fresh(&parsed)?;
+
+    // A file whose size and modification time did not change is not parsed
+    // again, even when the root config changed.
+    let modified = std::fs::metadata(&keys)?.modified()?;
+    std::fs::write(
+        &keys,
+        "include vars\nsuper + b\n    konqui\nignore super + z",
+    )?;
+    std::fs::File::options()
+        .write(true)
+        .open(&keys)?
+        .set_modified(modified)?;
+    std::fs::write(&path, root)?;
+    parsed.reload(ParserInput::Path(&path))?;
+    assert_eq!(parsed.bindings[1].command(), "thunar");
+    Ok(())
+}
+
+#[test]
+fn test_reload_reports_mode_bindings() -> Result<(), IoOrParseError> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("swhkdrc");
+    let resize = dir.path().join("resize");
+    std::fs::write(
+        &path,
+        "super + r\n    @enter resize\nmode resize\ninclude resize\nendmode",
+    )?;
+    std::fs::write(&resize, "h\n    grow\nl\n    shrink")?;
+    let mut parsed = SwhkdParser::from(ParserInput::Path(&path))?;
+
+    std::fs::write(&resize, "h\n    grow more\nj\n    down")?;
+    let report = parsed.reload(ParserInput::Path(&path))?;
+    let commands = |bindings: &[Binding]| -> Vec<String> {
+        bindings.iter().map(|b| b.command().to_string()).collect()
+    };
+    assert_eq!(commands(&report.added), ["down"]);
+    assert_eq!(commands(&report.removed), ["shrink"]);
+    assert_eq!(report.modified.len(), 1);
+    assert_eq!(report.modified[0].1.command(), "grow more");
+    assert_eq!(report.modified[0].1.mode(), Some("resize"));
+
+    // The same chord at the top level is another binding.
+    std::fs::write(
+        &path,
+        "super + r\n    @enter resize\nh\n    grow more\nmode resize\ninclude resize\nendmode",
+    )?;
+    let report = parsed.reload(ParserInput::Path(&path))?;
+    assert_eq!(commands(&report.added), ["grow more"]);
+    assert_eq!(report.added[0].mode(), None);
+    assert!(report.modified.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_check_lints_commands() -> Result<(), IoOrParseError> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("swhkdrc");
+    std::fs::write(&path, "super + a\n    notify-send 'hello")?;
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_sweet"))
+        .arg("check")
+        .arg(&path)
+        .env("NO_COLOR", "1")
+        .output()?;
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("`'` is never closed"), "{}", stderr);
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with(": 1 warning\n"));
+    Ok(())
+}
+
+#[test]
+fn test_check_watch() -> Result<(), IoOrParseError> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("swhkdrc");
+    std::fs::write(&path, "super + a\n    firefox")?;
+    let child = std::process::Command::new(env!("CARGO_BIN_EXE_sweet"))
+        .args(["check", "--watch"])
+        .arg(&path)
+        .env("NO_COLOR", "1")
+        .stdout(std::process::Stdio::piped())
+        .spawn()?;
+    // Longer than the polling interval of the binary.
+    let settle = || std::thread::sleep(Duration::from_millis(800));
+    settle();
+    std::fs::remove_file(&path)?;
+    settle();
+    std::fs::write(
+        &path,
+        "super + b\n    firefox\nmode resize\n    h\n        grow\nendmode",
+    )?;
+    settle();
+    std::fs::write(&path, "super + a\n    @enter nowhere")?;
+    settle();
+    let interrupted = std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()?;
+    assert!(interrupted.success());
+    let output = child.wait_with_output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<_> = stdout.lines().collect();
+    assert!(lines[0].starts_with("OK: 1 bindings, 0 modes (parsed in "));
+    assert_eq!(lines[1], format!("{}: waiting for file", path.display()));
+    assert!(lines[2].starts_with("OK: 2 bindings, 1 modes (parsed in "));
+    assert!(lines[3].starts_with("error: "), "{}", stdout);
+    // Exits on the last state, which does not parse.
+    assert_eq!(output.status.code(), Some(1));
+    Ok(())
+}
+
+#[test]
+fn test_from_all_errors() -> Result<(), ParseError> {
+    let files = [("extra", "super + key_nope\n    foot\nsuper + e\n    thunar")];
+    let config = "
+include extra
+super + a
+    firefox
+super + {b,c}
+    {alacritty,kitty,foot}
+super + r
+    @enter resize
+mode normal
+    x
+        @enter nowhere
+    y
+        true
+    ignore key_nope
+endmode
+super + n
+    @enter normal && notify-send normal";
+    let options = map_options(&files);
+    let outcome = SwhkdParser::from_all_errors_with_options(ParserInput::Raw(config), &options);
+    let [ParseError::Grammar(key), ParseError::Grammar(variants), ParseError::Grammar(unbind), ParseError::UnknownMode {
+        name: resize,
+        span: Some(resize_at),
+        ..
+    }, ParseError::UnknownMode {
+        name: nowhere,
+        span: Some(nowhere_at),
+        ..
+    }] = &outcome.errors[..]
+    else {
+        panic!("expected five errors, got {:?}", outcome.errors);
+    };
+    // Includes are parsed before the declarations of the including file.
+    assert_eq!(key.path(), Some("extra"));
+    assert_eq!(key.line(), "super + key_nope");
+    assert_eq!(variants.path(), Some("<anonymous>"));
+    assert_eq!(variants.line(), "    {alacritty,kitty,foot}");
+    assert_eq!(unbind.line(), "    ignore key_nope");
+    assert_eq!((resize.as_str(), resize_at.line), ("resize", 7));
+    assert_eq!((nowhere.as_str(), nowhere_at.line), ("nowhere", 10));
+    let parsed = outcome.parsed.as_ref().unwrap();
+    let chords: Vec<_> = parsed
+        .bindings
+        .iter()
+        .map(|binding| binding.definition().to_string())
+        .collect();
+    assert_eq!(chords, ["super + a", "super + n", "super + e"]);
+    assert_eq!(parsed.modes[0].bindings.len(), 1);
+    assert!(outcome.into_result().is_err());
+
+    // Grammar errors end parsing before any declaration is looked at.
+    let outcome =
+        SwhkdParser::from_all_errors(ParserInput::Raw("super + key_nope\n    foot\nsuper + a"));
+    assert!(outcome.parsed.is_none());
+    assert!(matches!(outcome.errors[..], [ParseError::Grammar(_)]));
+
+    let parsed = SwhkdParser::from_all_errors(ParserInput::Raw("super + a\n    firefox"))
+        .into_result()
+        .unwrap();
+    assert_eq!(parsed.bindings.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_from_all_errors_is_bounded() {
+    let distinct: String = (0..1000)
+        .map(|i| format!("super + key_nope{i}\n    true\n"))
+        .collect();
+    let outcome = SwhkdParser::from_all_errors(ParserInput::Raw(&distinct));
+    assert_eq!(outcome.errors.len(), 51);
+    assert!(outcome.errors[..50]
+        .iter()
+        .all(|err| matches!(err, ParseError::Grammar(_))));
+    let ParseError::MoreErrors(950) = &outcome.errors[50] else {
+        panic!("expected a summary, got {:?}", outcome.errors[50]);
+    };
+    assert_eq!(
+        outcome.errors[50].to_string(),
+        "... and 950 more errors; rerun with --max-errors=0 for all"
+    );
+    let options = ParseOptions::default().with_max_errors(0);
+    let outcome = SwhkdParser::from_all_errors_with_options(ParserInput::Raw(&distinct), &options);
+    assert_eq!(outcome.errors.len(), 1000);
+
+    // Identical errors on consecutive lines collapse, distinct ones are
+    // kept.
+    let repeated = format!(
+        "super + key_zzfirst = true\n\n{}super + key_zzlast = true\nsuper + key_nope = true",
+        "super + key_nope = true\n".repeat(151)
+    );
+    let outcome = SwhkdParser::from_all_errors(ParserInput::Raw(&repeated));
+    let [ParseError::Grammar(first), ParseError::Repeated {
+        count: 151,
+        first_line: 3,
+        last_line: 153,
+        ..
+    }, ParseError::Grammar(last), ParseError::Grammar(after)] = &outcome.errors[..]
+    else {
+        panic!("expected four errors, got {:?}", outcome.errors);
+    };
+    assert_eq!(first.line(), "super + key_zzfirst = true");
+    assert_eq!(last.line(), "super + key_zzlast = true");
+    assert_eq!(after.line(), "super + key_nope = true");
+    let diagnostic = Diagnostic::from(&outcome.errors[1]);
+    assert_eq!(
+        diagnostic.message,
+        "lines 3-153: `key_nope` is not recognized as a valid evdev key (×151)"
+    );
+    assert_eq!(diagnostic.labels[0].span.line, 3);
+
+    // Identical errors on lines that are not consecutive are kept apart,
+    // a range would claim the lines between are broken too.
+    let gap = "super + key_nope = true\nsuper + a = true\nsuper + key_nope = true\n\
+               super + key_nope\n    true\nsuper + key_nope\n    true";
+    let outcome = SwhkdParser::from_all_errors(ParserInput::Raw(gap));
+    let [ParseError::Grammar(first), ParseError::Repeated {
+        count: 2,
+        first_line: 3,
+        last_line: 4,
+        ..
+    }, ParseError::Grammar(last)] = &outcome.errors[..]
+    else {
+        panic!("expected three errors, got {:?}", outcome.errors);
+    };
+    assert_eq!(Diagnostic::from(&outcome.errors[0]).labels[0].span.line, 1);
+    assert_eq!(first.line(), "super + key_nope = true");
+    assert_eq!(Diagnostic::from(&outcome.errors[2]).labels[0].span.line, 6);
+    assert_eq!(last.line(), "super + key_nope");
+
+    // The rendered report stops at the size limit, the first error aside.
+    let options = ParseOptions::default().with_max_report_bytes(2000);
+    let outcome = SwhkdParser::from_all_errors_with_options(ParserInput::Raw(&distinct), &options);
+    let report = outcome.render(RenderStyle::Plain);
+    let (shown, notice) = report.rsplit_once('\n').unwrap();
+    assert!(shown.len() <= 2000);
+    assert!(shown.starts_with("error: unable to parse config file"));
+    let shown_errors = shown.matches("error: ").count();
+    assert_eq!(
+        notice,
+        format!(
+            "... {} more errors not shown, the report is limited to 2000 bytes",
+            51 - shown_errors
+        )
+    );
+    let options = ParseOptions::default().with_max_report_bytes(10);
+    let outcome = SwhkdParser::from_all_errors_with_options(ParserInput::Raw(&distinct), &options);
+    assert!(outcome
+        .render(RenderStyle::Plain)
+        .contains("super + key_nope0"));
+}
+
+/// What must hold for any input of [`SwhkdParser::from_lenient`]: the
+/// result is degraded exactly when something was skipped, and configs
+/// without errors parse like with [`SwhkdParser::from`].
+fn assert_lenient<'a>(input: impl Fn() -> ParserInput<'a>) {
+    let result = SwhkdParser::from_lenient(input());
+    assert_eq!(result.degraded, !result.skipped.is_empty());
+    if let Ok(parsed) = SwhkdParser::from(input()) {
+        assert!(!result.degraded, "{:?}", result.skipped);
+        assert_eq!(result.parsed.bindings, parsed.bindings);
+    }
+}
+
+#[test]
+fn test_lenient_broken_binding() {
+    let mut config: String = (0..10)
+        .map(|i| format!("super + {}\n    echo {}\n", i, i))
+        .collect();
+    config = config.replace("super + 4\n", "super + {4,\n");
+    let result = SwhkdParser::from_lenient(ParserInput::Raw(&config));
+    assert!(result.degraded);
+    assert_eq!(result.parsed.bindings.len(), 9);
+    let [skipped] = &result.skipped[..] else {
+        panic!("expected one skipped binding, got {:?}", result.skipped);
+    };
+    assert_eq!(skipped.text, "super + {4,\n    echo 4");
+    assert_eq!(skipped.span.as_ref().unwrap().line, 9);
+    assert!(matches!(skipped.error, ParseError::Grammar(_)));
+
+    // Errors found after the grammar skip the binding as well.
+    let config = "super + a\n    a\nsuper + key_nope\n    b\nsuper + {c,d}\n    {c,d,e}";
+    let result = SwhkdParser::from_lenient(ParserInput::Raw(config));
+    assert_eq!(result.parsed.bindings.len(), 1);
+    let texts: Vec<_> = result.skipped.iter().map(|s| s.text.as_str()).collect();
+    assert_eq!(
+        texts,
+        ["super + key_nope\n    b", "super + {c,d}\n    {c,d,e}"]
+    );
+}
+
+#[test]
+fn test_lenient_broken_mode() {
+    let config = "
+super + a
+    firefox
+mode resize
+    h
+        grow
+    super + key_nope
+        shrink
+endmode
+super + r
+    @enter resize
+super + b
+    foot";
+    let result = SwhkdParser::from_lenient(ParserInput::Raw(config));
+    assert!(result.parsed.modes.is_empty());
+    let chords: Vec<_> = result
+        .parsed
+        .bindings
+        .iter()
+        .map(|binding| binding.definition().to_string())
+        .collect();
+    assert_eq!(chords, ["super + a", "super + b"]);
+    let [mode, enter] = &result.skipped[..] else {
+        panic!(
+            "expected two skipped declarations, got {:?}",
+            result.skipped
+        );
+    };
+    assert!(mode.text.starts_with("mode resize\n") && mode.text.ends_with("endmode"));
+    assert_eq!(mode.span.as_ref().unwrap().line, 4);
+    // Entering the skipped mode fails in turn.
+    assert!(matches!(enter.error, ParseError::UnknownMode { .. }));
+    assert_eq!(enter.text, "super + r");
+
+    // So does a mode the grammar fails on.
+    let config = "mode resize\n    h\n        grow\nsuper + a\n    firefox";
+    let result = SwhkdParser::from_lenient(ParserInput::Raw(config));
+    assert!(result.parsed.bindings.is_empty() && result.parsed.modes.is_empty());
+    assert_eq!(result.skipped[0].text, config);
+}
+
+#[test]
+fn test_lenient_missing_include() {
+    let files = [("nested", "include missing\nsuper + n\n    nested")];
+    let config = "include nested\ninclude missing\nsuper + a\n    firefox";
+    let options = map_options(&files);
+    let result = SwhkdParser::from_lenient_with_options(ParserInput::Raw(config), &options);
+    assert_eq!(result.parsed.bindings.len(), 2);
+    let skipped: Vec<_> = result
+        .skipped
+        .iter()
+        .map(|skipped| (&*skipped.span.as_ref().unwrap().file, skipped.text.as_str()))
+        .collect();
+    assert_eq!(
+        skipped,
+        [
+            ("nested", "include missing"),
+            ("<anonymous>", "include missing")
+        ]
+    );
+    assert!(matches!(
+        result.skipped[0].error,
+        ParseError::ImportFailed { .. }
+    ));
+
+    // Never fails, even without a config.
+    let result = SwhkdParser::from_lenient(ParserInput::Path(Path::new("/nonexistent/swhkdrc")));
+    assert!(result.degraded && result.parsed.bindings.is_empty());
+    assert!(result.skipped[0].span.is_none());
+}
+
+#[test]
+fn test_lenient_corpus() -> std::io::Result<()> {
+    let mut corpus: Vec<_> = std::fs::read_dir("tests/fixtures/lenient")?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    corpus.sort();
+    assert!(!corpus.is_empty());
+    for path in corpus.iter().chain([
+        &"tests/fixtures/preview.swhkd".into(),
+        &"tests/fixtures/pathological.swhkd".into(),
+    ]) {
+        assert_lenient(|| ParserInput::Path(path));
+        let contents = std::fs::read_to_string(path)?;
+        assert_lenient(|| ParserInput::Raw(&contents));
+    }
+    Ok(())
+}
+
+mod lenient_properties {
+    use proptest::prelude::*;
+
+    use super::assert_lenient;
+    use sweet::ParserInput;
+
+    /// Lines of valid and broken declarations, put together in any order.
+    fn fragment() -> impl Strategy<Value = String> {
+        let fragments = prop::sample::select(vec![
+            "super + a\n    firefox\n",
+            "super + {b,c}\n    {foot,kitty}\n",
+            "super + {d,e\n    broken\n",
+            "super + key_nope\n    x\n",
+            "super + {f,g}\n    {one,two,three}\n",
+            "mode resize\n",
+            "    h\n        grow\n",
+            "endmode\n",
+            "super + r\n    @enter resize\n",
+            "ignore super + a\n",
+            "include missing.swhkd\n",
+            "define mod = super\n",
+            "$mod + x\n    y\n",
+            "block lock\n    swaylock\nendblock\n",
+            "super + l = &lock\n",
+            "# comment\n",
+            "\n",
+            "    \n",
+            "\\\n",
+            "=\n",
+            "}\n",
+        ]);
+        prop_oneof![
+            4 => fragments.prop_map(str::to_string),
+            1 => any::<String>(),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn lenient_never_fails(fragments in prop::collection::vec(fragment(), 0..12)) {
+            let config = fragments.concat();
+            assert_lenient(|| ParserInput::Raw(&config));
+        }
+    }
+}
+
+#[test]
+fn test_invalid_modifier_is_an_error() {
+    use sweet::ModifierRepr;
+    assert!(matches!(
+        Modifier::try_from(ModifierRepr("ctrl".to_string())),
+        Ok(Control)
+    ));
+    assert!(matches!(
+        Modifier::try_from(ModifierRepr("hyper".to_string())),
+        Err(ParseError::InvalidModifier(name)) if name == "hyper"
+    ));
+    // Adversarial configs fail instead of panicking.
+    for config in [
+        "{super,hyper} + a\n    x",
+        "{_, super +, } a\n    x",
+        "super + {a-}\n    x",
+        "super + {1-9:}\n    x",
+        "super + a\n    echo {1-}",
+        "@super + \n    x",
+        "mode\nendmode",
+        "\u{0}\u{feff}+\n",
+        "super + a\n    {\u{0}",
+    ] {
+        let result = std::panic::catch_unwind(|| SwhkdParser::from(ParserInput::Raw(config)));
+        assert!(matches!(result, Ok(Err(_))), "{config:?}: {result:?}");
+    }
+}
+
+#[test]
+fn test_from_sxhkd() -> Result<(), ParseError> {
+    let sxhkd = "
+# media keys
+XF86Audio{Pause,Forward,Rewind}
+    playerctl {pause,next,previous}
+super + {Prior,Next}
+    bspc desktop -f {prev,next}
+alt + {Page_Up,Page_Down}
+    bspc node -f {prev,next}
+super + {KP_Add,KP_Subtract,KP_0,quoteright}
+    notify-send {plus,minus,zero,apostrophe}
+super + {button1,button3}
+    bspc node -f {pointed,pointed.floating}
+super + {_,shift + }XF86AudioRaiseVolume
+    pamixer -i {5,10}
+super + a ; b
+    notify-send chained
+";
+    let sweet = "
+key_pausecd
+    playerctl pause
+key_fastforward
+    playerctl next
+key_rewind
+    playerctl previous
+super + {pageup,pagedown}
+    bspc desktop -f {prev,next}
+alt + {pageup,pagedown}
+    bspc node -f {prev,next}
+super + {kpplus,kpminus,kp0,apostrophe}
+    notify-send {plus,minus,zero,apostrophe}
+super + {btn_left,btn_right}
+    bspc node -f {pointed,pointed.floating}
+super + {_,shift + }volumeup
+    pamixer -i {5,10}
+super + a ; b
+    notify-send chained
+";
+    let translated = sweet::from_sxhkd(ParserInput::Raw(sxhkd))?;
+    let expected = SwhkdParser::from(ParserInput::Raw(sweet))?;
+    assert_eq!(translated.bindings, expected.bindings);
+    assert_eq!(translated.bindings.len(), 16);
+
+    // Commands are not translated.
+    let parsed = sweet::from_sxhkd(ParserInput::Raw("super + a\n    echo Prior button4"))?;
+    assert_eq!(parsed.bindings[0].command(), "echo Prior button4");
+    Ok(())
+}
+
+#[test]
+fn test_from_sxhkd_unsupported() -> std::io::Result<()> {
+    for (config, span, needle) in [
+        ("hyper + a\n    x", ((1, 1), (1, 6)), "the `hyper` modifier"),
+        (
+            "super + {a,b}\nMode_switch + c\n    x",
+            ((2, 1), (2, 12)),
+            "`mode_switch`",
+        ),
+        (
+            "super + button4\n    x",
+            ((1, 9), (1, 16)),
+            "scrolling cannot be bound",
+        ),
+        (
+            "super + a : b\n    x",
+            ((1, 11), (1, 12)),
+            "chain chords with `;`",
+        ),
+    ] {
+        let Err(ParseError::Grammar(err)) = sweet::from_sxhkd(ParserInput::Raw(config)) else {
+            panic!("{config:?} parsed");
+        };
+        assert_eq!(
+            err.line_col,
+            pest::error::LineColLocation::Span(span.0, span.1)
+        );
+        assert!(err.to_string().contains(needle), "{err}");
+    }
+
+    // Errors name the file, also those raised after translating.
+    let mut file = tempfile::NamedTempFile::new()?;
+    write!(
+        file,
+        "super + a\n    x\nsuper + Prior\n    y\nsuper + XF86Nope\n    z"
+    )?;
+    let Err(ParseError::Grammar(err)) = sweet::from_sxhkd(ParserInput::Path(file.path())) else {
+        panic!("`XF86Nope` is no key");
+    };
+    let rendered = err.to_string();
+    assert!(
+        rendered.contains(&file.path().display().to_string()),
+        "{rendered}"
+    );
+    assert!(rendered.contains(":5:"), "{rendered}");
+    Ok(())
+}
+
+const EXPORTED_CONFIG: &str = "
+super + {_,shift + }Return
+    {alacritty,foot}
+super + @q
+    swaymsg kill
+~XF86AudioPlay
+    playerctl play-pause
+ctrl + alt + Delete
+    notify-send 'bye'; systemctl poweroff
+super + btn_left
+    notify-send clicked
+super + scroll_up
+    pamixer -i 5
+key_prog1
+    echo program
+super + a ; b
+    echo sequence
+super + r
+    notify-send resize && @enter resize
+mode resize oneoff
+    {h,l}
+        resize {shrink,grow}
+    Escape
+        @escape
+endmode
+";
+
+#[test]
+fn test_to_sway() -> Result<(), ParseError> {
+    let parsed = SwhkdParser::from(ParserInput::Raw(EXPORTED_CONFIG))?;
+    let options = ExportOptions::default().with_modifier_alias(Super, "$mod");
+    let exported = sweet::to_sway_with_options(&parsed, &options);
+    assert_eq!(
+        exported.config,
+        r#"bindsym $mod+Return exec alacritty
+bindsym $mod+Shift+Return exec foot
+bindsym --release $mod+q exec swaymsg kill
+bindsym Mod1+Ctrl+Delete exec "notify-send 'bye'; systemctl poweroff"
+bindsym --whole-window $mod+BTN_LEFT exec notify-send clicked
+bindsym --whole-window $mod+button4 exec pamixer -i 5
+bindcode 156 exec echo program
+bindsym $mod+r exec notify-send resize; mode "resize"
+mode "resize" {
+    bindsym h exec resize shrink; mode "default"
+    bindsym l exec resize grow; mode "default"
+    bindsym Escape mode "default"
+}
+"#
+    );
+    let skipped: Vec<_> = exported.skipped.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        skipped,
+        [
+            "`~playpause` skipped: keys passed through with `~`",
+            "`super + a ; b` skipped: sequences of chords",
+        ]
+    );
+    assert!(sweet::to_sway(&parsed).starts_with("bindsym Mod4+Return exec alacritty\n"));
+    Ok(())
+}
+
+#[test]
+fn test_to_hyprland() -> Result<(), ParseError> {
+    let parsed = SwhkdParser::from(ParserInput::Raw(EXPORTED_CONFIG))?;
+    let exported = sweet::to_hyprland_with_options(&parsed, &ExportOptions::default());
+    assert_eq!(
+        exported.config,
+        "bind = SUPER, Return, exec, alacritty
+bind = SUPER SHIFT, Return, exec, foot
+bindr = SUPER, q, exec, swaymsg kill
+bindn = , XF86AudioPlay, exec, playerctl play-pause
+bind = ALT CTRL, Delete, exec, notify-send 'bye'; systemctl poweroff
+bind = SUPER, mouse:272, exec, notify-send clicked
+bind = SUPER, mouse_up, exec, pamixer -i 5
+bind = , code:156, exec, echo program
+bind = SUPER, r, exec, notify-send resize
+bind = SUPER, r, submap, resize
+submap = resize
+bind = , h, exec, resize shrink
+bind = , h, submap, reset
+bind = , l, exec, resize grow
+bind = , l, submap, reset
+bind = , Escape, submap, reset
+submap = reset
+"
+    );
+    assert_eq!(exported.skipped.len(), 1);
+    assert_eq!(exported.skipped[0].reason, "sequences of chords");
+    assert_eq!(sweet::to_hyprland(&parsed), exported.config);
+
+    // Keys beyond the keycodes of XKB cannot be bound.
+    let parsed = SwhkdParser::from(ParserInput::Raw("key_macro1\n    x"))?;
+    let exported = sweet::to_hyprland_with_options(&parsed, &ExportOptions::default());
+    assert_eq!(exported.config, "");
+    assert_eq!(exported.skipped[0].reason, "keys without an XKB keycode");
+    Ok(())
+}
+
+fn hash_of(value: &impl std::hash::Hash) -> u64 {
+    use std::hash::{BuildHasher, RandomState};
+    thread_local!(static STATE: RandomState = RandomState::new());
+    STATE.with(|state| state.hash_one(value))
+}
+
+#[test]
+fn test_definitions_hash_and_order_regardless_of_writing() -> Result<(), ParseError> {
+    let written: Definition = "shift + super + @a".parse()?;
+    let reordered: Definition = "super + shift + @a".parse()?;
+    let built = Definition::new(evdev::Key::KEY_A)
+        .with_modifiers(&[Shift, Super])
+        .with_attribute(KeyAttribute::OnRelease);
+    for other in [&reordered, &built] {
+        assert_eq!(&written, other);
+        assert_eq!(hash_of(&written), hash_of(other));
+        assert_eq!(written.cmp(other), std::cmp::Ordering::Equal);
+    }
+
+    let set: std::collections::HashSet<Definition> = [
+        written.clone(),
+        reordered,
+        built,
+        "super + shift + a".parse()?,
+        "super + a".parse()?,
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(set.len(), 3);
+    assert!(set.contains(&written));
+
+    let lookup: std::collections::HashMap<Definition, usize> =
+        [(written, 0), ("super + a".parse()?, 1)]
+            .into_iter()
+            .collect();
+    assert_eq!(lookup.get(&"shift+super+@a".parse()?), Some(&0));
+
+    // Modifiers order definitions first, then the key.
+    let mut sorted: Vec<Definition> = ["super + b", "a", "super + a", "b"]
+        .into_iter()
+        .map(str::parse)
+        .collect::<Result<_, _>>()?;
+    sorted.sort();
+    let sorted: Vec<_> = sorted.iter().map(ToString::to_string).collect();
+    assert_eq!(sorted, ["a", "b", "super + a", "super + b"]);
+    Ok(())
+}
+
+#[test]
+fn test_bindings_hash_and_order_regardless_of_writing() -> Result<(), ParseError> {
+    let parsed = SwhkdParser::from(ParserInput::Raw(
+        "shift + super + {a,b}\n    echo {a,b}\nsuper + c\n    echo c",
+    ))?;
+    let written_apart = SwhkdParser::from(ParserInput::Raw(
+        "super + shift + b\n    echo b\n\nsuper+shift+a\n    echo a",
+    ))?;
+    assert_ne!(
+        parsed.bindings[0].source_text(),
+        written_apart.bindings[1].source_text()
+    );
+    assert_eq!(parsed.bindings[0], written_apart.bindings[1]);
+    assert_eq!(
+        hash_of(&parsed.bindings[0]),
+        hash_of(&written_apart.bindings[1])
+    );
+
+    let set: std::collections::HashSet<Binding> = parsed
+        .bindings
+        .iter()
+        .chain(&written_apart.bindings)
+        .cloned()
+        .collect();
+    assert_eq!(set.len(), 3);
+
+    let sorted: std::collections::BTreeSet<&Binding> = parsed.bindings.iter().collect();
+    let commands: Vec<_> = sorted.iter().map(|binding| binding.command()).collect();
+    assert_eq!(commands, ["echo c", "echo a", "echo b"]);
+    Ok(())
+}
+
+/// What `sweet check --watch` writes for `events`, with the directory of
+/// the config and the time parsing took left out as they vary, and whether
+/// it exits successfully.
+fn watch_output(
+    dir: &Path,
+    events: impl IntoIterator<Item = Option<WatchEvent>>,
+    clear_screen: bool,
+) -> Result<(String, bool), Box<dyn std::error::Error>> {
+    let mut out = vec![];
+    let ok = show_watch_events(
+        "swhkdrc",
+        events,
+        &mut out,
+        RenderStyle::Plain,
+        clear_screen,
+    )?;
+    let mut output = String::from_utf8(out)?.replace(&*dir.to_string_lossy(), "<dir>");
+    let mut from = 0;
+    while let Some(start) = output[from..].find("(parsed in ").map(|start| from + start) {
+        let end = start + output[start..].find(" ms)").expect("a closed timing") + " ms)".len();
+        output.replace_range(start..end, "(parsed in _ ms)");
+        from = start + "(parsed in _ ms)".len();
+    }
+    Ok((output, ok))
+}
+
+#[test]
+fn test_show_watch_events() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("swhkdrc");
+    std::fs::write(&path, "super + a\n    firefox")?;
+    let mut watcher = ConfigWatcher::new(&path);
+    let (output, ok) = watch_output(dir.path(), [watcher.poll()], false)?;
+    assert_eq!(output, "OK: 1 bindings, 0 modes (parsed in _ ms)\n");
+    assert!(ok);
+
+    // Every change redraws the screen, and the last one decides how
+    // watching exits.
+    let edits = [
+        Some("super + shift + exclam\n    firefox\nmode resize\n    h\n        shrink\nendmode"),
+        Some("super + key_nope\n    firefox"),
+        None,
+    ];
+    let events = edits.iter().map(|edit| {
+        match edit {
+            Some(contents) => std::fs::write(&path, contents).unwrap(),
+            None => std::fs::remove_file(&path).unwrap(),
+        }
+        watcher.poll()
+    });
+    let (output, ok) = watch_output(dir.path(), events, true)?;
+    assert_eq!(
+        output,
+        "\x1b[2J\x1b[H\
+         warning:  --> <dir>/swhkdrc:1:9\n  \
+         |\n\
+         1 | super + shift + exclam\n  \
+         |         ^---^\n  \
+         |\n  \
+         = `shift` is redundant, the shifted symbol in this chord already implies it\n\
+         OK: 2 bindings, 1 modes (parsed in _ ms)\n\
+         \x1b[2J\x1b[H\
+         error: unable to parse config file\n \
+         --> <dir>/swhkdrc:1:9\n  \
+         |\n\
+         1 | super + key_nope\n  \
+         |         ^------^\n  \
+         |\n  \
+         = `key_nope` is not recognized as a valid evdev key\n\
+         \x1b[2J\x1b[H\
+         swhkdrc: waiting for file\n"
+    );
+    assert!(!ok);
+
+    std::fs::write(&path, "super + a\n    firefox")?;
+    let (output, ok) = watch_output(dir.path(), [watcher.poll()], false)?;
+    assert_eq!(output, "OK: 1 bindings, 0 modes (parsed in _ ms)\n");
+    assert!(ok);
+    // Watching stopped before the first poll.
+    assert_eq!(watch_output(dir.path(), [], false)?, (String::new(), false));
+    Ok(())
+}
+
+#[test]
+fn test_show_watch_events_only_redraws_on_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("swhkdrc");
+    std::fs::write(&path, "super + a\n    firefox")?;
+    let mut watcher = ConfigWatcher::new(&path);
+    let saves: [&[&str]; 5] = [
+        &[],
+        // Polls finding nothing changed and saves without changes draw
+        // nothing.
+        &[],
+        &["super + a\n    firefox"],
+        // Saves between two polls are drawn once.
+        &[
+            "super + {a,b}\n    {firefox,foot}",
+            "super + {a,b,c}\n    {firefox,foot,thunar}",
+        ],
+        &[],
+    ];
+    let events = saves.iter().map(|contents| {
+        for contents in *contents {
+            std::fs::write(&path, contents).unwrap();
+        }
+        watcher.poll()
+    });
+    let (output, ok) = watch_output(dir.path(), events, true)?;
+    assert_eq!(
+        output,
+        "\x1b[2J\x1b[HOK: 1 bindings, 0 modes (parsed in _ ms)\n\
+         \x1b[2J\x1b[HOK: 3 bindings, 0 modes (parsed in _ ms)\n"
+    );
+    assert!(ok);
+
+    // A config missing for several polls is reported once.
+    std::fs::remove_file(&path)?;
+    let events = [watcher.poll(), watcher.poll(), watcher.poll()];
+    let (output, ok) = watch_output(dir.path(), events, false)?;
+    assert_eq!(output, "swhkdrc: waiting for file\n");
+    assert!(!ok);
+    Ok(())
+}